@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+/// 电量变化很慢, 不需要频繁读取.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct BatterySettings {
+    /// 放电时低于这个百分比提示一次.
+    pub warn_percent: u8,
+    /// 电量降到 `stop_percent` 时自动停止录制, 保证文件完整封装.
+    pub auto_stop: bool,
+    pub stop_percent: u8,
+}
+
+impl Default for BatterySettings {
+    fn default() -> Self {
+        Self {
+            warn_percent: 15,
+            auto_stop: false,
+            stop_percent: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ChargeState {
+    Charging,
+    Discharging,
+    Full,
+    /// 接着电源但没有在充电, 或者内核不知道.
+    Unknown,
+}
+
+/// 某一时刻的电池状态, 也写入 sidecar.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct BatteryStatus {
+    pub percent: u8,
+    pub state: ChargeState,
+    /// 放电时预计的剩余时间, 驱动不提供时为 None.
+    pub time_to_empty_secs: Option<u64>,
+}
+
+/// 录制开始和结束时的电池状态.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PowerLog {
+    pub start: Option<BatteryStatus>,
+    pub stop: Option<BatteryStatus>,
+}
+
+/// 需要通知用户的电量变化.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BatteryAlert {
+    /// 低于 `warn_percent`.
+    Low,
+    /// 低于 `stop_percent`, 开启了自动停止时应停止录制.
+    Critical,
+}
+
+/// 定期读取电池状态. 没有电池的台式机上所有读数都是 None.
+pub(crate) struct BatteryMonitor {
+    settings: BatterySettings,
+    /// 第一块电池的目录, 例如 `/sys/class/power_supply/BAT0`.
+    dir: Option<PathBuf>,
+    status: Option<BatteryStatus>,
+    read_at: Option<Instant>,
+    /// 本次放电已经提示过的最严重级别, 充电后重新提示.
+    alerted: Option<BatteryAlert>,
+    recording: Option<PowerLog>,
+}
+
+impl BatteryMonitor {
+    pub fn new(settings: BatterySettings) -> Self {
+        let dir = find_battery(Path::new(POWER_SUPPLY_DIR));
+        if let Some(dir) = &dir {
+            tracing::info!(battery = %dir.display(), "monitoring battery");
+        }
+        Self {
+            settings,
+            dir,
+            status: None,
+            read_at: None,
+            alerted: None,
+            recording: None,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: BatterySettings) {
+        self.settings = settings;
+    }
+
+    /// 开启了自动停止, 并且放电中电量已经降到 `stop_percent`.
+    pub fn should_stop_recording(&self) -> bool {
+        self.settings.auto_stop
+            && self.status.is_some_and(|s| {
+                s.state == ChargeState::Discharging && s.percent <= self.settings.stop_percent
+            })
+    }
+
+    pub fn status(&self) -> Option<BatteryStatus> {
+        self.status
+    }
+
+    /// 记录开始时的状态. 读取最新值, 不等轮询.
+    pub fn start_recording(&mut self) {
+        let start = self.read();
+        self.recording = Some(PowerLog { start, stop: None });
+    }
+
+    /// 结束录制, 返回开始和结束时的状态. 没有电池时返回 None.
+    pub fn finish_recording(&mut self) -> Option<PowerLog> {
+        let mut log = self.recording.take()?;
+        log.stop = self.read();
+        (log.start.is_some() || log.stop.is_some()).then_some(log)
+    }
+
+    /// 到时间时读取一次. 放电中电量跌破阈值时返回提示, 每个级别只返回一次.
+    pub fn poll(&mut self, now: Instant) -> Option<BatteryAlert> {
+        self.dir.as_ref()?;
+        if self
+            .read_at
+            .is_some_and(|at| now.duration_since(at) < POLL_INTERVAL)
+        {
+            return None;
+        }
+        self.read_at = Some(now);
+        let status = self.read()?;
+
+        if status.state != ChargeState::Discharging {
+            self.alerted = None;
+            return None;
+        }
+        let alert = if status.percent <= self.settings.stop_percent {
+            BatteryAlert::Critical
+        } else if status.percent < self.settings.warn_percent {
+            BatteryAlert::Low
+        } else {
+            return None;
+        };
+        if self
+            .alerted
+            .is_some_and(|a| a == alert || a == BatteryAlert::Critical)
+        {
+            return None;
+        }
+        self.alerted = Some(alert);
+        Some(alert)
+    }
+
+    fn read(&mut self) -> Option<BatteryStatus> {
+        let dir = self.dir.as_ref()?;
+        self.status = read_status(dir)
+            .inspect_err(
+                |e| tracing::debug!(battery = %dir.display(), "could not read battery: {}", e),
+            )
+            .ok();
+        self.status
+    }
+}
+
+/// 只看系统电池, 蓝牙鼠标等外设的电池 (scope=Device) 不算.
+fn find_battery(dir: &Path) -> Option<PathBuf> {
+    let mut supplies: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            read_attr(path, "type").is_some_and(|t| t == "Battery")
+                && read_attr(path, "scope").is_none_or(|s| s != "Device")
+                && path.join("capacity").exists()
+        })
+        .collect();
+    supplies.sort();
+    supplies.into_iter().next()
+}
+
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    std::fs::read_to_string(dir.join(name))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+fn read_number(dir: &Path, name: &str) -> Option<u64> {
+    read_attr(dir, name)?.parse().ok()
+}
+
+fn read_status(dir: &Path) -> std::io::Result<BatteryStatus> {
+    let capacity = read_number(dir, "capacity").ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "no readable capacity")
+    })?;
+    let state = match read_attr(dir, "status").as_deref() {
+        Some("Charging") => ChargeState::Charging,
+        Some("Discharging") => ChargeState::Discharging,
+        Some("Full") => ChargeState::Full,
+        _ => ChargeState::Unknown,
+    };
+    let time_to_empty_secs = match state {
+        ChargeState::Discharging => time_to_empty(dir),
+        _ => None,
+    };
+    Ok(BatteryStatus {
+        percent: capacity.min(100) as u8,
+        state,
+        time_to_empty_secs,
+    })
+}
+
+/// 优先用驱动给出的值, 否则用剩余能量 (或电荷) 除以当前功率 (或电流) 估算.
+fn time_to_empty(dir: &Path) -> Option<u64> {
+    if let Some(secs) = read_number(dir, "time_to_empty_now") {
+        return Some(secs);
+    }
+    let (remaining, rate) = match (
+        read_number(dir, "energy_now"),
+        read_number(dir, "power_now"),
+    ) {
+        (Some(energy), Some(power)) => (energy, power),
+        _ => (
+            read_number(dir, "charge_now")?,
+            read_number(dir, "current_now")?,
+        ),
+    };
+    (rate > 0).then(|| remaining * 3600 / rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_supply(dir: &Path, name: &str, attrs: &[(&str, &str)]) {
+        let path = dir.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        for (attr, value) in attrs {
+            std::fs::write(path.join(attr), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_system_battery_and_estimates_time_to_empty() {
+        let dir = std::env::temp_dir().join(format!("cam-ui-battery-{}", std::process::id()));
+        write_supply(&dir, "AC", &[("type", "Mains"), ("online", "0")]);
+        write_supply(
+            &dir,
+            "hid-mouse",
+            &[("type", "Battery"), ("scope", "Device"), ("capacity", "90")],
+        );
+        write_supply(
+            &dir,
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("capacity", "42"),
+                ("status", "Discharging"),
+                ("energy_now", "20000000"),
+                ("power_now", "10000000"),
+            ],
+        );
+
+        let battery = find_battery(&dir).unwrap();
+        assert!(battery.ends_with("BAT0"));
+        let status = read_status(&battery).unwrap();
+        assert_eq!(
+            status,
+            BatteryStatus {
+                percent: 42,
+                state: ChargeState::Discharging,
+                time_to_empty_secs: Some(7200),
+            }
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_battery_on_desktops() {
+        let dir = std::env::temp_dir().join(format!("cam-ui-no-battery-{}", std::process::id()));
+        write_supply(&dir, "AC", &[("type", "Mains"), ("online", "1")]);
+        assert!(find_battery(&dir).is_none());
+        assert!(find_battery(&dir.join("missing")).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
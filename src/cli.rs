@@ -0,0 +1,116 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+
+use crate::config::{Overrides, RecordingConfig};
+use crate::video::benchmark::{self, BenchmarkReport};
+use crate::video::devices::{self, Backend};
+
+// 命令行参数. 除 `--config` 外都只对本次运行生效, 不会写入配置文件.
+// 字段的文档注释就是 `--help` 的内容.
+#[derive(Debug, Parser)]
+#[command(version, about = "Camera preview and recorder")]
+pub(crate) struct Args {
+    /// Camera name or device path, e.g. /dev/video2
+    #[arg(long, value_name = "NAME|/dev/videoN")]
+    pub device: Option<String>,
+
+    /// Capture backend; libcamera is needed for Raspberry Pi CSI camera modules
+    #[arg(long, value_enum, default_value_t = Backend::Auto)]
+    pub backend: Backend,
+
+    /// Directory to write recordings to
+    #[arg(long, value_name = "PATH")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Start fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Start fullscreen, hide the cursor when idle and only quit after confirmation
+    #[arg(long)]
+    pub kiosk: bool,
+
+    /// Recording profile to apply at startup
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Config file to use instead of the one in the XDG config directory
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Print available cameras and exit
+    #[arg(long)]
+    pub list_devices: bool,
+
+    /// Start recording as soon as the camera delivers frames
+    #[arg(long)]
+    pub auto_record: bool,
+
+    /// Run capture and recording without a window until SIGINT/SIGTERM
+    #[arg(long, conflicts_with_all = ["fullscreen", "kiosk"])]
+    pub headless: bool,
+
+    /// Start recording immediately (headless mode only)
+    #[arg(long, requires = "headless")]
+    pub record: bool,
+
+    /// Measure how fast each installed encoder runs on this machine, save the report and exit
+    #[arg(long, conflicts_with_all = ["headless", "list_devices"])]
+    pub benchmark: bool,
+}
+
+impl Args {
+    /// 需要先初始化 GStreamer, 按名称查找摄像头时会枚举设备.
+    pub fn overrides(&self) -> Result<Overrides, String> {
+        let video_source = match (&self.device, self.backend) {
+            (None, Backend::Auto) => None,
+            (device, backend) => Some(devices::source_for(device.as_deref(), backend)?),
+        };
+        Ok(Overrides {
+            video_source,
+            output_dir: self.output_dir.clone(),
+            profile: self.profile.clone(),
+            auto_record: self.auto_record,
+        })
+    }
+}
+
+/// `--benchmark`: 进度写到 stderr, 结果写到 stdout. 失败时返回 false.
+pub(crate) fn run_benchmark(rec: &RecordingConfig) -> bool {
+    let cancel = AtomicBool::new(false);
+    let result = benchmark::run(rec.encoder_options(), &cancel, |done, total| {
+        eprint!("\rBenchmarking encoders {}/{}", done, total);
+    });
+    eprintln!();
+    match result {
+        Ok(report) => {
+            for result in &report.results {
+                println!("{}", result.label());
+            }
+            if let Some(path) = BenchmarkReport::path().filter(|p| p.exists()) {
+                println!("Saved to {}", path.display());
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            false
+        }
+    }
+}
+
+/// `--list-devices` 的输出.
+pub(crate) fn print_devices() {
+    let cameras = devices::list_cameras();
+    if cameras.is_empty() {
+        println!("No cameras found");
+    }
+    for camera in cameras {
+        match (camera.path, camera.libcamera_id) {
+            (Some(path), _) => println!("{}\t{}", path, camera.name),
+            (None, Some(id)) => println!("libcamera:{}\t{}", id, camera.name),
+            (None, None) => println!("-\t{}", camera.name),
+        }
+    }
+}
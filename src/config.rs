@@ -0,0 +1,763 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::battery::BatterySettings;
+use crate::file::markers::MarkerSettings;
+use crate::file::naming::Slate;
+use crate::file::remaining::RemainingSettings;
+use crate::i18n::Language;
+use crate::keymap::Keymap;
+use crate::offload::OffloadSettings;
+use crate::osc::OscSettings;
+use crate::status::StatusSettings;
+use crate::thermal::ThermalSettings;
+use crate::video::assist::AssistSettings;
+use crate::video::decklink::DeckLinkConfig;
+use crate::video::denoise::DenoiseSettings;
+use crate::video::gif::GifSettings;
+use crate::video::histogram::ExposureWarningSettings;
+use crate::video::overlay::OverlayConfig;
+use crate::video::pattern::{self, TestSourceConfig};
+use crate::video::pip::PipConfig;
+use crate::video::pipeline::{CaptureSources, SOURCE};
+use crate::video::record::{
+    BitDepth, ClipTags, Container, EncoderOptions, FrameRateMode, Resolution, VideoEncoder,
+};
+use crate::video::rtsp::RtspConfig;
+use crate::video::screen::{PortalOptions, ScreenBackend, ScreenConfig};
+use crate::video::sharpen::SharpenSettings;
+use crate::video::silence::SilenceSettings;
+use crate::video::snapshot::SnapshotSettings;
+use crate::video::switcher::SwitchConfig;
+use crate::video::timecode::TimecodeSource;
+use crate::webhook::WebhookSettings;
+
+/// 当前写入的配置版本. 与 sidecar 相同, 只做向后兼容的追加:
+/// 新字段必须有默认值, 升级后旧配置照常加载.
+pub(crate) const CONFIG_VERSION: u32 = 1;
+
+const APP_DIR: &str = "cam-ui";
+const FILE_NAME: &str = "config.toml";
+
+/// 启动时加载, 修改后自动保存的全部设置.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Config {
+    pub version: u32,
+    pub capture: CaptureConfig,
+    pub recording: RecordingConfig,
+    pub countdown: CountdownSettings,
+    /// 静帧的格式, 分辨率和连拍帧数.
+    pub snapshot: SnapshotSettings,
+    /// 把录像末尾导出为 GIF 时的长度, 宽度和帧率.
+    pub gif: GifSettings,
+    /// 录制中打标记时的快捷标签和停止后的导出格式.
+    pub markers: MarkerSettings,
+    pub tags: ClipTags,
+    pub slate: Slate,
+    pub offload: OffloadSettings,
+    pub webhook: WebhookSettings,
+    /// 给看板推送状态的 WebSocket 服务.
+    pub status: StatusSettings,
+    pub osc: OscSettings,
+    /// 按顺序对应数字键 1–9.
+    pub profiles: Vec<Profile>,
+    /// 最近应用的预设名称.
+    pub active_profile: Option<String>,
+    /// 启动后预览正常时自动开始录制 (kiosk 部署).
+    pub auto_record: bool,
+    pub behavior: BehaviorSettings,
+    /// 一次录制中丢弃的帧达到这个数时提示降低分辨率或码率, 0 表示不提示.
+    pub drop_warning_frames: u64,
+    /// 剩余录制时间少于这些分钟数时底栏变黄和变红.
+    pub remaining: RemainingSettings,
+    /// 输入音量倍数, 1 为原样, 电平表和录音都受影响.
+    pub audio_gain: f64,
+    /// 录制中持续无声时提示.
+    pub silence: SilenceSettings,
+    pub thermal: ThermalSettings,
+    pub battery: BatterySettings,
+    /// 根据预览的直方图提示过曝和死黑.
+    pub exposure_warning: ExposureWarningSettings,
+    /// 对焦峰值和斑马纹.
+    pub assist: AssistSettings,
+    pub reference: ReferenceSettings,
+    pub program: ProgramSettings,
+    pub tally: TallySettings,
+    /// 画进预览画面的叠加层, 与界面上的辅助显示分开.
+    pub overlay: OverlayConfig,
+    /// 界面缩放, 没有设置时按显示器推测.
+    pub ui_scale: Option<f32>,
+    pub chrome: ChromeSettings,
+    pub window: WindowSettings,
+    pub theme: ThemeSettings,
+    /// 界面语言, 缺少翻译的文字显示英文.
+    pub language: Language,
+    /// 操作到快捷键的映射, 例如 `toggle-record = "R"`.
+    pub keymap: Keymap,
+    /// 手柄按键映射和摇杆, 只在启用 `gamepad` 功能编译时存在.
+    #[cfg(feature = "gamepad")]
+    pub gamepad: crate::gamepad::GamepadSettings,
+    /// MIDI 控制台的端口和绑定, 只在启用 `midi` 功能编译时存在.
+    #[cfg(feature = "midi")]
+    pub midi: crate::midi::MidiSettings,
+    /// 指示灯和按钮的引脚, 只在启用 `gpio` 功能编译时存在.
+    #[cfg(feature = "gpio")]
+    pub gpio: crate::gpio::GpioSettings,
+    /// MQTT 服务器和认证, 只在启用 `mqtt` 功能编译时存在.
+    #[cfg(feature = "mqtt")]
+    pub mqtt: crate::mqtt::MqttSettings,
+    /// 锁定屏幕后输入这个 PIN 也可以解锁, 为空时只能按住锁图标.
+    pub unlock_pin: String,
+    /// 调试叠加层显示资源占用时, 录制期间 CPU 超过这个百分比记录警告, 0 表示不警告.
+    /// 与 top 相同, 多核时可以超过 100.
+    pub cpu_warning_percent: f64,
+    /// 本程序的日志级别, 设置了 `RUST_LOG` 时以环境变量为准.
+    pub log_level: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            capture: CaptureConfig::default(),
+            recording: RecordingConfig::default(),
+            snapshot: SnapshotSettings::default(),
+            gif: GifSettings::default(),
+            tags: ClipTags::default(),
+            markers: MarkerSettings::default(),
+            countdown: CountdownSettings::default(),
+            slate: Slate::default(),
+            offload: OffloadSettings::default(),
+            webhook: WebhookSettings::default(),
+            status: StatusSettings::default(),
+            osc: OscSettings::default(),
+            profiles: Vec::new(),
+            active_profile: None,
+            auto_record: false,
+            log_level: "info".to_string(),
+            drop_warning_frames: 10,
+            remaining: RemainingSettings::default(),
+            audio_gain: 1.0,
+            silence: SilenceSettings::default(),
+            cpu_warning_percent: 90.0,
+            thermal: ThermalSettings::default(),
+            battery: BatterySettings::default(),
+            exposure_warning: ExposureWarningSettings::default(),
+            assist: AssistSettings::default(),
+            reference: ReferenceSettings::default(),
+            program: ProgramSettings::default(),
+            tally: TallySettings::default(),
+            overlay: OverlayConfig::default(),
+            behavior: BehaviorSettings::default(),
+            ui_scale: None,
+            chrome: ChromeSettings::default(),
+            window: WindowSettings::default(),
+            unlock_pin: String::new(),
+            theme: ThemeSettings::default(),
+            language: Language::default(),
+            keymap: Keymap::default(),
+            #[cfg(feature = "gamepad")]
+            gamepad: crate::gamepad::GamepadSettings::default(),
+            #[cfg(feature = "midi")]
+            midi: crate::midi::MidiSettings::default(),
+            #[cfg(feature = "gpio")]
+            gpio: crate::gpio::GpioSettings::default(),
+            #[cfg(feature = "mqtt")]
+            mqtt: crate::mqtt::MqttSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ThemeName {
+    #[default]
+    Standard,
+    /// 黑底暗红色, 在暗处使用.
+    Night,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ThemeSettings {
+    pub name: ThemeName,
+    /// 夜间主题下把预览也调暗.
+    pub dim_preview: bool,
+    /// 预览画面的亮度, 0.1 到 1, 只影响显示.
+    pub preview_brightness: f32,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            name: ThemeName::default(),
+            dim_preview: false,
+            preview_brightness: 1.0,
+        }
+    }
+}
+
+/// 按下录制后先倒计时, 方便一个人拍摄时走到镜头前.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct CountdownSettings {
+    /// 0 表示直接开始录制.
+    pub seconds: u32,
+    /// 每秒响一声, 最后一秒音调更高.
+    pub beep: bool,
+}
+
+impl Default for CountdownSettings {
+    fn default() -> Self {
+        Self {
+            seconds: 0,
+            beep: true,
+        }
+    }
+}
+
+/// 一段时间没有操作后隐藏主窗口的顶部栏, 底部参数区和按钮.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ChromeSettings {
+    /// 0 表示不隐藏.
+    pub hide_after_secs: u32,
+    /// 打开了设置, 浏览器等面板时不隐藏.
+    pub keep_with_panels: bool,
+}
+
+impl Default for ChromeSettings {
+    fn default() -> Self {
+        Self {
+            hide_after_secs: 5,
+            keep_with_panels: true,
+        }
+    }
+}
+
+impl ChromeSettings {
+    pub fn hide_after(&self) -> Option<Duration> {
+        (self.hide_after_secs > 0).then(|| Duration::from_secs(self.hide_after_secs.into()))
+    }
+}
+
+/// 主窗口的全屏状态和位置, 重启后恢复.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct WindowSettings {
+    pub fullscreen: bool,
+    /// 窗口左上角的位置, 单位是点. 多显示器时决定在哪个显示器上全屏.
+    pub position: Option<[f32; 2]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum ReferenceMode {
+    /// 半透明地叠加在预览上.
+    #[default]
+    Overlay,
+    /// 显示预览与参考图的差, 对齐的地方接近黑色.
+    Difference,
+}
+
+/// 叠加在预览上的取景参考图, 参考图本身不保存.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ReferenceSettings {
+    /// 0.1 到 0.6.
+    pub opacity: f32,
+    pub mode: ReferenceMode,
+}
+
+impl Default for ReferenceSettings {
+    fn default() -> Self {
+        Self {
+            opacity: 0.3,
+            mode: ReferenceMode::Overlay,
+        }
+    }
+}
+
+/// 操作方式.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct BehaviorSettings {
+    pub stop_guard: StopGuard,
+}
+
+/// 防止误停止录制. 开始录制总是立即生效.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) enum StopGuard {
+    #[default]
+    Off,
+    /// 按住录制键或按钮 [crate::recorder::STOP_HOLD] 才停止.
+    Hold,
+    /// [crate::recorder::STOP_CONFIRM_WINDOW] 内再按一次才停止.
+    Confirm,
+}
+
+/// 录制时在预览四周画红框, 开始和成功结束时整屏闪一下. 只画在主窗口, 节目窗口不受影响.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct TallySettings {
+    pub border: TallyBorder,
+    pub flash: bool,
+    /// 边框和闪光的不透明度, 0–1.
+    pub intensity: f32,
+}
+
+impl Default for TallySettings {
+    fn default() -> Self {
+        Self {
+            border: TallyBorder::Off,
+            flash: false,
+            intensity: 0.8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum TallyBorder {
+    Off,
+    Solid,
+    /// 缓慢地明暗变化.
+    Pulse,
+}
+
+/// 第二个窗口中没有界面元素的节目画面.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ProgramSettings {
+    /// 打开时全屏, 否则是可以拖到其他显示器上的无边框窗口.
+    pub fullscreen: bool,
+    /// 同时显示只在界面上叠加的画中画.
+    pub overlays: bool,
+}
+
+impl Default for ProgramSettings {
+    fn default() -> Self {
+        Self {
+            fullscreen: true,
+            overlays: false,
+        }
+    }
+}
+
+/// 命名的录制参数预设, 例如 "4K master" 或 "1080p proxy".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Profile {
+    pub name: String,
+    pub recording: RecordingConfig,
+    /// 应用预设时一并切换的峰值和斑马纹, 旧版本保存的预设没有.
+    pub assist: Option<AssistSettings>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::from("Default"),
+            recording: RecordingConfig::default(),
+            assist: None,
+        }
+    }
+}
+
+/// 采集源, 在启动 gst 线程前应用, 修改后需要重启.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct CaptureConfig {
+    /// gst-launch 语法的视频源, 例如 "v4l2src device=/dev/video0".
+    pub video_source: String,
+    pub audio_source: String,
+    /// 设置时使用 DeckLink 采集卡, 代替上面两个采集源.
+    pub decklink: Option<DeckLinkConfig>,
+    /// 设置时录制桌面, 代替视频源.
+    pub screen: Option<ScreenConfig>,
+    /// 设置时使用网络摄像头, 代替视频源, 流中有音频时也代替音频源.
+    pub rtsp: Option<RtspConfig>,
+    /// 视频源是 `videotestsrc` 时的图案.
+    pub test_source: TestSourceConfig,
+    /// 可以在运行中切换到的其他采集源.
+    pub switching: SwitchConfig,
+    /// 设置时在画面上叠加第二个采集源.
+    pub pip: Option<PipConfig>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            video_source: "videotestsrc is-live=true".to_string(),
+            audio_source: CaptureSources::default().audio,
+            decklink: None,
+            screen: None,
+            rtsp: None,
+            test_source: TestSourceConfig::default(),
+            switching: SwitchConfig::default(),
+            pip: None,
+        }
+    }
+}
+
+impl CaptureConfig {
+    pub fn sources(&self) -> CaptureSources {
+        let switch = (!self.switching.sources.is_empty()).then(|| self.switching.clone());
+        CaptureSources {
+            switch,
+            pip: self.pip.clone().filter(|p| !p.source.trim().is_empty()),
+            ..self.primary_sources()
+        }
+    }
+
+    /// 切换源列表中的名称, 只有一路时为空.
+    pub fn source_names(&self) -> Vec<String> {
+        if self.switching.sources.is_empty() {
+            return Vec::new();
+        }
+        let mut names = vec!["Main".to_string()];
+        for (i, alternate) in self.switching.sources.iter().enumerate() {
+            names.push(if alternate.name.is_empty() {
+                format!("Source {}", i + 2)
+            } else {
+                alternate.name.clone()
+            });
+        }
+        names
+    }
+
+    /// 第一路采集源.
+    fn primary_sources(&self) -> CaptureSources {
+        if let Some(decklink) = &self.decklink {
+            return CaptureSources {
+                video: format!("{} name={}", decklink.video_source(), SOURCE),
+                audio: decklink.audio_source(),
+                screen_portal: None,
+                switch: None,
+                pip: None,
+            };
+        }
+        if let Some(rtsp) = &self.rtsp {
+            return CaptureSources {
+                video: rtsp.video_source(),
+                audio: if rtsp.audio {
+                    rtsp.audio_source()
+                } else {
+                    self.audio_source.clone()
+                },
+                screen_portal: None,
+                switch: None,
+                pip: None,
+            };
+        }
+        if let Some(screen) = &self.screen {
+            let portal = screen.backend == ScreenBackend::Portal;
+            return CaptureSources {
+                // 门户授权后由 gst 线程替换
+                video: if portal {
+                    crate::video::screen::fallback_source()
+                } else {
+                    screen.x11_source()
+                },
+                audio: self.audio_source.clone(),
+                screen_portal: portal.then_some(PortalOptions {
+                    show_cursor: screen.show_cursor,
+                    fps: screen.fps,
+                }),
+                switch: None,
+                pip: None,
+            };
+        }
+        let mut video = format!("{} name={}", self.video_source, SOURCE);
+        if pattern::is_test_source(&self.video_source) {
+            video = self.test_source.apply_to(&video);
+        }
+        CaptureSources {
+            video,
+            audio: self.audio_source.clone(),
+            screen_portal: None,
+            switch: None,
+            pip: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct RecordingConfig {
+    pub resolution: Resolution,
+    pub encoder: VideoEncoder,
+    pub container: Container,
+    pub bitrate_kbps: u32,
+    /// AV1 的速度预设, 越大越快.
+    pub av1_speed: u8,
+    /// FFV1 的切片数, 越多越能利用多核.
+    pub ffv1_slices: u8,
+    /// Motion JPEG 的质量, 1 到 100.
+    pub mjpeg_quality: u8,
+    pub bit_depth: BitDepth,
+    /// 时间码轨道的起始值.
+    pub timecode: TimecodeSource,
+    pub frame_rate: FrameRateMode,
+    /// 弱光下的降噪, 运行中切换立即生效.
+    pub denoise: DenoiseSettings,
+    pub sharpen: SharpenSettings,
+    pub output_dir: PathBuf,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            resolution: Resolution {
+                width: 1920,
+                height: 1080,
+            },
+            encoder: VideoEncoder::H264,
+            container: Container::MOV,
+            bitrate_kbps: 2048,
+            av1_speed: 8,
+            ffv1_slices: 12,
+            mjpeg_quality: 85,
+            bit_depth: BitDepth::Auto,
+            timecode: TimecodeSource::TimeOfDay,
+            frame_rate: FrameRateMode::Source,
+            denoise: DenoiseSettings::default(),
+            sharpen: SharpenSettings::default(),
+            output_dir: crate::file::output_dir(),
+        }
+    }
+}
+
+impl RecordingConfig {
+    pub fn encoder_options(&self) -> EncoderOptions {
+        EncoderOptions {
+            bitrate_kbps: self.bitrate_kbps,
+            av1_speed: self.av1_speed,
+            ffv1_slices: self.ffv1_slices,
+            mjpeg_quality: self.mjpeg_quality,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/cam-ui/config.toml`, 未设置时使用 `~/.config`.
+pub(crate) fn config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join(APP_DIR).join(FILE_NAME))
+}
+
+/// 启动时加载的配置, 以及保存时需要的信息.
+pub(crate) struct LoadedConfig {
+    /// 已经应用了命令行覆盖.
+    pub config: Config,
+    /// 配置文件中的原始内容.
+    pub persisted: Config,
+    pub path: Option<PathBuf>,
+    pub overrides: Overrides,
+    /// 加载时出现的问题, 启动后以提示显示.
+    pub warning: Option<String>,
+}
+
+/// 只对本次运行生效的设置 (来自命令行), 保存时不会写入配置文件.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Overrides {
+    pub video_source: Option<String>,
+    pub output_dir: Option<PathBuf>,
+    /// 启动时应用的预设名称.
+    pub profile: Option<String>,
+    pub auto_record: bool,
+}
+
+impl Overrides {
+    /// 可能的错误: 找不到指定名称的预设.
+    pub fn apply(&self, config: &mut Config) -> Result<(), String> {
+        if let Some(name) = &self.profile {
+            let profile = config
+                .profiles
+                .iter()
+                .find(|p| &p.name == name)
+                .ok_or_else(|| format!("no profile named '{}'", name))?;
+            config.recording = profile.recording.clone();
+            config.active_profile = Some(name.clone());
+        }
+        if let Some(source) = &self.video_source {
+            config.capture.video_source = source.clone();
+            config.capture.decklink = None;
+            config.capture.screen = None;
+            config.capture.rtsp = None;
+        }
+        if let Some(dir) = &self.output_dir {
+            config.recording.output_dir = dir.clone();
+        }
+        if self.auto_record {
+            config.auto_record = true;
+        }
+        Ok(())
+    }
+
+    /// 把被覆盖的字段换回配置文件中的值, 用于保存.
+    pub fn restore(&self, config: &mut Config, persisted: &Config) {
+        if let Some(name) = &self.profile {
+            if let Some(profile) = persisted.profiles.iter().find(|p| &p.name == name) {
+                config.recording =
+                    restore_unchanged(&config.recording, &profile.recording, &persisted.recording);
+            }
+            if config.active_profile.as_ref() == Some(name) {
+                config.active_profile = persisted.active_profile.clone();
+            }
+        }
+        if self.video_source.is_some() {
+            config.capture.video_source = persisted.capture.video_source.clone();
+            config.capture.decklink = persisted.capture.decklink.clone();
+            config.capture.screen = persisted.capture.screen.clone();
+            config.capture.rtsp = persisted.capture.rtsp.clone();
+        }
+        if self.output_dir.is_some() {
+            config.recording.output_dir = persisted.recording.output_dir.clone();
+        }
+        if self.auto_record {
+            config.auto_record = persisted.auto_record;
+        }
+    }
+}
+
+/// `current` 中仍然等于 `applied` 的字段换回 `persisted` 中的值, 本次运行中修改过的字段保留.
+/// 逐个比较序列化后的顶层字段, 以后新增的字段也不会漏掉.
+fn restore_unchanged<T: Serialize + serde::de::DeserializeOwned + Clone>(
+    current: &T,
+    applied: &T,
+    persisted: &T,
+) -> T {
+    let (
+        Ok(serde_json::Value::Object(mut fields)),
+        Ok(serde_json::Value::Object(applied)),
+        Ok(serde_json::Value::Object(persisted)),
+    ) = (
+        serde_json::to_value(current),
+        serde_json::to_value(applied),
+        serde_json::to_value(persisted),
+    )
+    else {
+        return current.clone();
+    };
+    for (key, value) in fields.iter_mut() {
+        if applied.get(key) == Some(value)
+            && let Some(original) = persisted.get(key)
+        {
+            *value = original.clone();
+        }
+    }
+    serde_json::from_value(serde_json::Value::Object(fields)).unwrap_or_else(|e| {
+        tracing::warn!("could not restore settings replaced by the profile: {}", e);
+        current.clone()
+    })
+}
+
+impl Config {
+    /// 加载配置. 文件不存在时使用默认值; 文件损坏时备份后使用默认值,
+    /// 并返回给用户的提示.
+    pub fn load(path: Option<&Path>) -> (Self, Option<String>) {
+        let Some(path) = path else {
+            return (Self::default(), None);
+        };
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (Self::default(), None),
+            Err(e) => {
+                tracing::warn!("could not read settings: {}", e);
+                return (
+                    Self::default(),
+                    Some(format!("Could not read settings ({}), using defaults", e)),
+                );
+            }
+        };
+        match toml::from_str::<Self>(&text) {
+            Ok(mut config) => {
+                config.version = CONFIG_VERSION;
+                (config, None)
+            }
+            Err(e) => {
+                tracing::warn!("could not parse settings: {}", e);
+                let message = match backup_corrupt(path) {
+                    Ok(backup) => format!(
+                        "Settings file was corrupt and has been reset; old copy saved as {}",
+                        backup.display()
+                    ),
+                    Err(e) => format!(
+                        "Settings file was corrupt and could not be backed up: {}",
+                        e
+                    ),
+                };
+                (Self::default(), Some(message))
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(std::io::Error::other)?;
+        // 与 sidecar 一样先写临时文件再改名
+        let tmp = path.with_extension("toml.tmp");
+        std::fs::write(&tmp, text)?;
+        restrict_permissions(&tmp)?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
+/// 把损坏的配置改名为 `config.toml.bad-<时间戳>`, 以便事后排查.
+fn backup_corrupt(path: &Path) -> std::io::Result<PathBuf> {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let backup = path.with_extension(format!("toml.bad-{}", stamp));
+    std::fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+/// 配置里有密码和密钥, 只允许当前用户读取.
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restoring_a_profile_keeps_changes_made_during_the_session() {
+        let mut persisted = Config::default();
+        persisted.recording.bitrate_kbps = 8000;
+        persisted.profiles.push(Profile {
+            name: "Interview".to_string(),
+            recording: RecordingConfig {
+                bitrate_kbps: 20000,
+                encoder: VideoEncoder::H265,
+                ..persisted.recording.clone()
+            },
+            assist: None,
+        });
+        let overrides = Overrides {
+            profile: Some("Interview".to_string()),
+            ..Default::default()
+        };
+        let mut config = persisted.clone();
+        overrides.apply(&mut config).unwrap();
+        assert_eq!(config.recording.bitrate_kbps, 20000);
+
+        config.recording.encoder = VideoEncoder::Av1;
+        overrides.restore(&mut config, &persisted);
+        assert_eq!(config.recording.bitrate_kbps, 8000);
+        assert_eq!(config.recording.encoder, VideoEncoder::Av1);
+        assert_eq!(config.active_profile, None);
+    }
+}
@@ -0,0 +1,79 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::video::{self, record::RecordCommand};
+
+/// panic 时等待 gst 线程封装录像的最长时间.
+const FINALIZE_WAIT: Duration = Duration::from_secs(5);
+
+/// 防止 panic 处理过程中再次 panic 时重复进入.
+static HANDLING: AtomicBool = AtomicBool::new(false);
+
+/// 安装 panic 处理: 先让 gst 线程封装正在录制的文件, 再把 panic 信息和调用栈
+/// 写到 `output_dir` 下的 `crash-<时间>.log`, 最后交给原来的处理函数.
+/// 只持有指令通道的弱引用, 不妨碍界面退出时关闭通道.
+pub(crate) fn install_panic_hook(
+    cmd_tx: mpsc::WeakUnboundedSender<RecordCommand>,
+    output_dir: PathBuf,
+) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if HANDLING.swap(true, Ordering::SeqCst) {
+            previous(info);
+            return;
+        }
+        let thread = std::thread::current();
+        let thread_name = thread.name().unwrap_or("<unnamed>");
+        let backtrace = std::backtrace::Backtrace::force_capture();
+
+        // gst 线程自己 panic 时没有人处理指令, 等待只会超时;
+        // 后台线程 (上传, 校验等) panic 不影响录制, 进程继续运行
+        let finalized = match thread_name {
+            "main" => finalize(&cmd_tx),
+            video::GST_THREAD => "skipped (panic in gst thread)",
+            _ => "unaffected",
+        };
+
+        let report = format!(
+            "cam-ui {} crashed at {}\nthread: {}\n{}\nrecording: {}\n\nbacktrace:\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            chrono::Local::now().to_rfc3339(),
+            thread_name,
+            info,
+            finalized,
+            backtrace
+        );
+        match write_log(&output_dir, &report) {
+            Ok(path) => tracing::error!("crash log written to {}", path.display()),
+            Err(e) => tracing::error!("could not write crash log: {}", e),
+        }
+        previous(info);
+        HANDLING.store(false, Ordering::SeqCst);
+    }));
+}
+
+fn finalize(cmd_tx: &mpsc::WeakUnboundedSender<RecordCommand>) -> &'static str {
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    let sent = cmd_tx
+        .upgrade()
+        .is_some_and(|tx| tx.send(RecordCommand::Shutdown(done_tx)).is_ok());
+    if !sent {
+        return "not running";
+    }
+    match done_rx.recv_timeout(FINALIZE_WAIT) {
+        Ok(()) => "finalized",
+        Err(_) => "timed out waiting for finalization",
+    }
+}
+
+fn write_log(dir: &Path, report: &str) -> std::io::Result<PathBuf> {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let path = dir.join(format!("crash-{}.log", stamp));
+    let mut file = std::fs::File::create(&path)?;
+    file.write_all(report.as_bytes())?;
+    file.sync_all()?;
+    Ok(path)
+}
@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use xxhash_rust::xxh3::Xxh3;
+
+use super::drop_page_cache;
+use super::volumes::Volume;
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExportState {
+    Copying,
+    Verifying,
+    /// 全部文件已校验并落盘, 可以安全拔出.
+    Done,
+    Failed(String),
+}
+
+/// 导出任务的进度, 由后台线程写入, UI 读取.
+#[derive(Debug, Clone)]
+pub(crate) struct ExportProgress {
+    pub state: ExportState,
+    pub current_file: String,
+    pub file_done: u64,
+    pub file_total: u64,
+    pub overall_done: u64,
+    /// 复制和校验各读一遍, 所以是文件总大小的两倍.
+    pub overall_total: u64,
+    pub files_done: usize,
+    pub files_total: usize,
+}
+
+impl ExportProgress {
+    pub fn file_fraction(&self) -> f32 {
+        fraction(self.file_done, self.file_total)
+    }
+
+    pub fn overall_fraction(&self) -> f32 {
+        fraction(self.overall_done, self.overall_total)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, ExportState::Done | ExportState::Failed(_))
+    }
+}
+
+fn fraction(done: u64, total: u64) -> f32 {
+    if total == 0 {
+        1.0
+    } else {
+        done as f32 / total as f32
+    }
+}
+
+/// 在后台线程中把录像复制到目标卷, 返回共享的进度.
+pub(crate) fn spawn_export(clips: Vec<PathBuf>, dest: Volume) -> Arc<Mutex<ExportProgress>> {
+    let total: u64 = clips
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .sum();
+    let progress = Arc::new(Mutex::new(ExportProgress {
+        state: ExportState::Copying,
+        current_file: String::new(),
+        file_done: 0,
+        file_total: 0,
+        overall_done: 0,
+        overall_total: total * 2,
+        files_done: 0,
+        files_total: clips.len(),
+    }));
+
+    let progress_c = progress.clone();
+    std::thread::spawn(move || {
+        let result = run_export(&clips, &dest, total, &progress_c);
+        progress_c.lock().state = match result {
+            Ok(()) => ExportState::Done,
+            Err(e) => {
+                tracing::error!("export failed: {}", e);
+                ExportState::Failed(e.to_string())
+            }
+        };
+    });
+    progress
+}
+
+fn run_export(
+    clips: &[PathBuf],
+    dest: &Volume,
+    total: u64,
+    progress: &Mutex<ExportProgress>,
+) -> std::io::Result<()> {
+    let free = super::free_space(&dest.mount_point)?;
+    if free < total {
+        return Err(std::io::Error::other(format!(
+            "not enough space on {}: need {}, have {}",
+            dest.display_name(),
+            super::format_size(total),
+            super::format_size(free)
+        )));
+    }
+
+    for src in clips {
+        let Some(name) = src.file_name() else {
+            continue;
+        };
+        let dst = dest.mount_point.join(name);
+        {
+            let mut p = progress.lock();
+            p.current_file = name.to_string_lossy().into_owned();
+            p.file_total = std::fs::metadata(src)?.len();
+        }
+        if let Err(e) = copy_verified(src, &dst, progress) {
+            // 不留下不完整的文件. 如果设备已被拔出, 删除也会失败, 忽略即可
+            let _ = std::fs::remove_file(&dst);
+            if !dest.is_mounted() {
+                return Err(std::io::Error::other(format!(
+                    "{} was removed during export",
+                    dest.display_name()
+                )));
+            }
+            return Err(e);
+        }
+        progress.lock().files_done += 1;
+    }
+
+    // 确保所有数据都已写入设备, 之后才能提示安全拔出
+    // SAFETY: sync 没有参数, 也不会失败
+    unsafe { libc::sync() };
+    Ok(())
+}
+
+/// 复制单个文件, 然后从目标重新读出并比较大小和 xxh3 校验和.
+fn copy_verified(src: &Path, dst: &Path, progress: &Mutex<ExportProgress>) -> std::io::Result<()> {
+    let mut buf = vec![0u8; CHUNK_SIZE];
+
+    progress.lock().state = ExportState::Copying;
+    let mut reader = File::open(src)?;
+    let mut writer = File::create(dst)?;
+    let mut src_hash = Xxh3::new();
+    let src_len = pump(&mut reader, &mut buf, progress, |chunk| {
+        src_hash.update(chunk);
+        writer.write_all(chunk)
+    })?;
+    writer.sync_all()?;
+    drop_page_cache(&writer);
+    drop(writer);
+
+    progress.lock().state = ExportState::Verifying;
+    let mut reader = File::open(dst)?;
+    let mut dst_hash = Xxh3::new();
+    let dst_len = pump(&mut reader, &mut buf, progress, |chunk| {
+        dst_hash.update(chunk);
+        Ok(())
+    })?;
+
+    if src_len != dst_len || src_hash.digest128() != dst_hash.digest128() {
+        return Err(std::io::Error::other(format!(
+            "verification failed for {}",
+            dst.display()
+        )));
+    }
+    Ok(())
+}
+
+/// 分块读取, 每块交给 `sink` 处理并更新进度, 返回读取的总字节数.
+fn pump(
+    reader: &mut File,
+    buf: &mut [u8],
+    progress: &Mutex<ExportProgress>,
+    mut sink: impl FnMut(&[u8]) -> std::io::Result<()>,
+) -> std::io::Result<u64> {
+    progress.lock().file_done = 0;
+    let mut read_total = 0u64;
+    loop {
+        let n = reader.read(buf)?;
+        if n == 0 {
+            return Ok(read_total);
+        }
+        sink(&buf[..n])?;
+        read_total += n as u64;
+
+        let mut p = progress.lock();
+        p.file_done += n as u64;
+        p.overall_done += n as u64;
+    }
+}
@@ -0,0 +1,174 @@
+//! 录制中打的标记. 保存在 sidecar 中, 停止录制时还可以导出为剪辑软件能导入的列表.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::video::timecode::{FrameRate, Timecode};
+
+/// 没有时间码时把录像内的时间换算为帧号使用的帧率.
+const FALLBACK_RATE: FrameRate = FrameRate { num: 30, den: 1 };
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Marker {
+    /// 从录像开始算起的秒数.
+    pub secs: f64,
+    /// 打标记时最近一帧的时间码, 没有时间码轨道时为 None.
+    pub timecode: Option<String>,
+    pub label: Option<String>,
+}
+
+impl Marker {
+    /// 导出时使用的时间码, 没有时从 00:00:00:00 开始按录像内的时间计算.
+    fn timecode(&self) -> String {
+        self.timecode
+            .clone()
+            .unwrap_or_else(|| Timecode::from_time_of_day(self.secs, FALLBACK_RATE).to_string())
+    }
+
+    fn name(&self, index: usize) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| format!("Marker {}", index + 1))
+    }
+}
+
+/// 停止录制时额外写出的标记列表.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum MarkerExport {
+    /// 只写入 sidecar.
+    #[default]
+    None,
+    Csv,
+    /// DaVinci Resolve 可以导入为时间线标记的 EDL.
+    Edl,
+}
+
+impl MarkerExport {
+    pub const ALL: [MarkerExport; 3] = [MarkerExport::None, MarkerExport::Csv, MarkerExport::Edl];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct MarkerSettings {
+    pub export: MarkerExport,
+    /// 右键点击标记按钮时可选的标签.
+    pub labels: Vec<String>,
+}
+
+impl Default for MarkerSettings {
+    fn default() -> Self {
+        Self {
+            export: MarkerExport::None,
+            labels: ["Good", "Retake", "Note"].map(String::from).to_vec(),
+        }
+    }
+}
+
+/// 按设置写出标记列表, 与录像同名. 不需要导出时返回 None.
+pub(crate) fn export(
+    clip: &Path,
+    markers: &[Marker],
+    format: MarkerExport,
+) -> std::io::Result<Option<PathBuf>> {
+    let name = clip.file_name().unwrap_or_default().to_string_lossy();
+    let (path, text) = match format {
+        MarkerExport::None => return Ok(None),
+        MarkerExport::Csv => (clip.with_extension("markers.csv"), to_csv(markers)),
+        MarkerExport::Edl => (clip.with_extension("edl"), to_edl(&name, markers)),
+    };
+    std::fs::write(&path, text)?;
+    Ok(Some(path))
+}
+
+/// 与 Premiere 导出的标记列表相同的列.
+fn to_csv(markers: &[Marker]) -> String {
+    let mut csv = String::from("Marker Name,Description,In,Out,Duration,Marker Type\n");
+    for (i, m) in markers.iter().enumerate() {
+        let tc = m.timecode();
+        csv.push_str(&format!(
+            "{},{:.3},{},{},00:00:00:00,Comment\n",
+            csv_field(&m.name(i)),
+            m.secs,
+            tc,
+            tc
+        ));
+    }
+    csv
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Resolve 的标记 EDL: 每个标记一条事件, 入点和出点相同, 长度由 `|D:` 给出.
+fn to_edl(title: &str, markers: &[Marker]) -> String {
+    let drop_frame = markers
+        .iter()
+        .any(|m| m.timecode.as_ref().is_some_and(|tc| tc.contains(';')));
+    let mut edl = format!(
+        "TITLE: {}\nFCM: {}\n\n",
+        title,
+        if drop_frame {
+            "DROP FRAME"
+        } else {
+            "NON-DROP FRAME"
+        }
+    );
+    for (i, m) in markers.iter().enumerate() {
+        let tc = m.timecode();
+        edl.push_str(&format!(
+            "{:03}  001      V     C        {tc} {tc} {tc} {tc}\n |C:ResolveColorBlue |M:{} |D:1\n\n",
+            i + 1,
+            m.name(i).replace('|', "/"),
+        ));
+    }
+    edl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markers() -> Vec<Marker> {
+        vec![
+            Marker {
+                secs: 754.2,
+                timecode: Some("10:12:34:05".into()),
+                label: Some("good answer, take it".into()),
+            },
+            Marker {
+                secs: 800.0,
+                timecode: None,
+                label: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_quotes_labels_and_falls_back_to_clip_time() {
+        let csv = to_csv(&markers());
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "\"good answer, take it\",754.200,10:12:34:05,10:12:34:05,00:00:00:00,Comment"
+        );
+        assert!(lines[2].starts_with("Marker 2,800.000,00:13:20:00,"));
+    }
+
+    #[test]
+    fn edl_has_one_event_per_marker() {
+        let edl = to_edl("A001_S01_T01.mov", &markers());
+        assert!(edl.starts_with("TITLE: A001_S01_T01.mov\nFCM: NON-DROP FRAME\n"));
+        assert!(edl.contains(
+            "001  001      V     C        10:12:34:05 10:12:34:05 10:12:34:05 10:12:34:05\n \
+             |C:ResolveColorBlue |M:good answer, take it |D:1\n"
+        ));
+        assert!(edl.contains("002  001"));
+        assert!(edl.contains("|M:Marker 2 |D:1"));
+    }
+}
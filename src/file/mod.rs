@@ -1 +1,140 @@
 // TODO: 处理文件命名、保存设置等
+
+use eframe::egui;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub(crate) mod export;
+pub(crate) mod markers;
+pub(crate) mod naming;
+pub(crate) mod preflight;
+pub(crate) mod remaining;
+pub(crate) mod session_log;
+pub(crate) mod sidecar;
+pub(crate) mod volumes;
+pub(crate) mod watch;
+
+/// 录像浏览器识别的文件扩展名.
+const CLIP_EXTENSIONS: &[&str] = &["mov", "mp4", "mkv", "webm", "avi"];
+
+#[derive(Debug, Clone)]
+pub(crate) struct ClipInfo {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: SystemTime,
+    /// 同名的元数据文件, 旧录像或录制中断时可能没有.
+    pub sidecar: Option<sidecar::Sidecar>,
+}
+
+impl ClipInfo {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// 默认的录像保存目录 (工作目录), 可在设置中修改.
+pub(crate) fn output_dir() -> PathBuf {
+    PathBuf::from(".")
+}
+
+/// 列出目录下的录像文件, 按修改时间从新到旧排序.
+pub(crate) fn list_clips(dir: &Path) -> std::io::Result<Vec<ClipInfo>> {
+    let mut clips = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_clip(&path)
+            && let Some(clip) = clip_info(path)
+        {
+            clips.push(clip);
+        }
+    }
+    sort_clips(&mut clips);
+    Ok(clips)
+}
+
+/// 按扩展名判断是不是录像.
+pub(crate) fn is_clip(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| CLIP_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// 读取一个录像的大小, 修改时间和 sidecar. 不是普通文件或者已经不存在时返回 None.
+pub(crate) fn clip_info(path: PathBuf) -> Option<ClipInfo> {
+    let meta = std::fs::symlink_metadata(&path).ok()?;
+    if !meta.is_file() {
+        return None;
+    }
+    Some(ClipInfo {
+        size: meta.len(),
+        modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        sidecar: sidecar::Sidecar::read(&path).ok(),
+        path,
+    })
+}
+
+/// 按修改时间从新到旧排序.
+pub(crate) fn sort_clips(clips: &mut [ClipInfo]) {
+    clips.sort_by_key(|c| std::cmp::Reverse(c.modified));
+}
+
+/// 读取 PNG 图片, 例如取景参考图.
+pub(crate) fn read_png(path: &Path) -> Result<egui::ColorImage, String> {
+    let image = image::ImageReader::open(path)
+        .map_err(|e| e.to_string())?
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?
+        .decode()
+        .map_err(|e| e.to_string())?
+        .into_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok(egui::ColorImage::from_rgba_unmultiplied(
+        size,
+        image.as_raw(),
+    ))
+}
+
+/// 路径所在文件系统的可用空间 (字节).
+pub(crate) fn free_space(path: &Path) -> std::io::Result<u64> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: statvfs 只写入我们提供的结构体
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    #[allow(clippy::unnecessary_cast)] // 各平台上字段类型不同
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// 丢弃文件的页缓存. 校验时真正从设备读取, 读完也不挤占正在录制的文件.
+#[cfg(target_os = "linux")]
+pub(crate) fn drop_page_cache(file: &std::fs::File) {
+    use std::os::fd::AsRawFd;
+    // SAFETY: fd 在 file 存活期间有效
+    unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn drop_page_cache(_file: &std::fs::File) {}
+
+/// 格式化为人类可读的大小, 例如 "1.3 GB".
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
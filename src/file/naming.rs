@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+/// 场记板信息: 卷号, 场次, 镜次.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Slate {
+    /// 字母加数字, 例如 "A001".
+    pub reel: String,
+    pub scene: String,
+    pub take: u32,
+}
+
+impl Default for Slate {
+    fn default() -> Self {
+        Self {
+            reel: String::from("A001"),
+            scene: String::from("1"),
+            take: 1,
+        }
+    }
+}
+
+impl Slate {
+    /// 卷号必须以字母开头, 后面跟数字.
+    pub fn reel_is_valid(&self) -> bool {
+        let mut chars = self.reel.chars();
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.clone().next().is_some()
+            && chars.all(|c| c.is_ascii_digit())
+    }
+
+    /// 例如 "A001_S12_T03".
+    pub fn label(&self) -> String {
+        format!(
+            "{}_S{}_T{:02}",
+            sanitize(&self.reel),
+            sanitize(&self.scene),
+            self.take
+        )
+    }
+}
+
+/// 录像文件名, 例如 "A001_S12_T03_1730000000.mov".
+/// 时间戳保证即使场记信息重复也不会覆盖已有文件.
+pub(crate) fn clip_filename(slate: &Slate, timestamp: u64, extension: &str) -> String {
+    if slate.reel.is_empty() && slate.scene.is_empty() {
+        format!("rec_{}.{}", timestamp, extension)
+    } else {
+        format!("{}_{}.{}", slate.label(), timestamp, extension)
+    }
+}
+
+/// 只保留文件名中安全的字符.
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
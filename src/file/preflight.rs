@@ -0,0 +1,220 @@
+//! 开始录制前检查输出路径. 可以预见的文件系统错误在构建录制分支之前拦下,
+//! 界面据此给出修复办法, 而不是等 filesink 报一个看不出原因的错误.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// 自动改名时最多尝试的序号.
+const MAX_SUFFIX: u32 = 999;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BlockReason {
+    /// 同名的文件已经存在.
+    Exists(PathBuf),
+    /// 目录不存在.
+    MissingDir(PathBuf),
+    /// 不是目录或者没有写权限.
+    NotWritable(PathBuf),
+    /// 目录所在的文件系统是只读挂载的.
+    ReadOnly(PathBuf),
+}
+
+impl std::fmt::Display for BlockReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockReason::Exists(path) => write!(f, "{} already exists", path.display()),
+            BlockReason::MissingDir(dir) => write!(f, "{} does not exist", dir.display()),
+            BlockReason::NotWritable(dir) => write!(f, "{} is not writable", dir.display()),
+            BlockReason::ReadOnly(dir) => write!(f, "{} is on a read-only mount", dir.display()),
+        }
+    }
+}
+
+/// 界面提供的修复办法.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Suggestion {
+    /// 改用这个不冲突的路径.
+    Rename(PathBuf),
+    /// 创建缺少的目录后重试.
+    CreateDir(PathBuf),
+    /// 只能换一个目录.
+    ChooseFolder,
+}
+
+/// 录像会写到 `path`. 只检查, 不创建也不修改任何文件.
+pub(crate) fn check(path: &Path) -> Result<(), (BlockReason, Suggestion)> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !dir.exists() {
+        // 在最近的已有上级目录中能创建目录时才建议创建
+        let ancestor = dir
+            .ancestors()
+            .find(|d| d.exists())
+            .unwrap_or(Path::new("/"));
+        return Err(if is_read_only(ancestor) {
+            (
+                BlockReason::ReadOnly(dir.to_path_buf()),
+                Suggestion::ChooseFolder,
+            )
+        } else if !is_writable(ancestor) {
+            (
+                BlockReason::NotWritable(dir.to_path_buf()),
+                Suggestion::ChooseFolder,
+            )
+        } else {
+            (
+                BlockReason::MissingDir(dir.to_path_buf()),
+                Suggestion::CreateDir(dir.to_path_buf()),
+            )
+        });
+    }
+    if is_read_only(dir) {
+        return Err((
+            BlockReason::ReadOnly(dir.to_path_buf()),
+            Suggestion::ChooseFolder,
+        ));
+    }
+    if !dir.is_dir() || !is_writable(dir) {
+        return Err((
+            BlockReason::NotWritable(dir.to_path_buf()),
+            Suggestion::ChooseFolder,
+        ));
+    }
+    if path.symlink_metadata().is_ok() {
+        let suggestion = match unique_path(path) {
+            Some(renamed) => Suggestion::Rename(renamed),
+            None => Suggestion::ChooseFolder,
+        };
+        return Err((BlockReason::Exists(path.to_path_buf()), suggestion));
+    }
+    Ok(())
+}
+
+/// 在扩展名前加上 `_2`, `_3` 等序号, 直到录像和它的 sidecar 都不冲突.
+pub(crate) fn unique_path(path: &Path) -> Option<PathBuf> {
+    let stem = path.file_stem()?.to_string_lossy();
+    let extension = path.extension().map(|e| e.to_string_lossy());
+    (2..=MAX_SUFFIX)
+        .map(|n| {
+            let name = match &extension {
+                Some(ext) => format!("{}_{}.{}", stem, n, ext),
+                None => format!("{}_{}", stem, n),
+            };
+            path.with_file_name(name)
+        })
+        .find(|p| {
+            p.symlink_metadata().is_err()
+                && super::sidecar::sidecar_path(p).symlink_metadata().is_err()
+        })
+}
+
+fn is_read_only(dir: &Path) -> bool {
+    let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: statvfs 只写入我们提供的结构体
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    stat.f_flag & libc::ST_RDONLY != 0
+}
+
+fn is_writable(dir: &Path) -> bool {
+    let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: 只读取以 NUL 结尾的路径
+    unsafe { libc::access(c_path.as_ptr(), libc::W_OK | libc::X_OK) == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cam-ui-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn existing_clip_gets_a_free_name() {
+        let dir = temp_dir("preflight-exists");
+        let path = dir.join("A001_C001.mp4");
+        assert_eq!(check(&path), Ok(()));
+
+        std::fs::write(&path, b"").unwrap();
+        std::fs::write(dir.join("A001_C001_2.json"), b"").unwrap();
+        assert_eq!(
+            check(&path),
+            Err((
+                BlockReason::Exists(path.clone()),
+                Suggestion::Rename(dir.join("A001_C001_3.mp4"))
+            ))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_directory_can_be_created() {
+        let dir = temp_dir("preflight-missing");
+        let missing = dir.join("day2/cam_a");
+        assert_eq!(
+            check(&missing.join("clip.mp4")),
+            Err((
+                BlockReason::MissingDir(missing.clone()),
+                Suggestion::CreateDir(missing)
+            ))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unwritable_directory_needs_another_folder() {
+        let dir = temp_dir("preflight-unwritable");
+        // 不是目录
+        let file = dir.join("file");
+        std::fs::write(&file, b"").unwrap();
+        let (reason, suggestion) = check(&file.join("clip.mp4")).unwrap_err();
+        assert_eq!(reason, BlockReason::NotWritable(file));
+        assert_eq!(suggestion, Suggestion::ChooseFolder);
+
+        // root 不受权限限制
+        if unsafe { libc::geteuid() } != 0 {
+            let locked = dir.join("locked");
+            std::fs::create_dir(&locked).unwrap();
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o555)).unwrap();
+            assert_eq!(
+                check(&locked.join("clip.mp4")),
+                Err((
+                    BlockReason::NotWritable(locked.clone()),
+                    Suggestion::ChooseFolder
+                ))
+            );
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_only_mount_needs_another_folder() {
+        // 用系统里任意一个只读挂载点, 没有时跳过
+        let mounts = std::fs::read_to_string("/proc/mounts").unwrap_or_default();
+        let Some(mount) = mounts.lines().find_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let ro = fields.get(3)?.split(',').any(|o| o == "ro");
+            (ro && Path::new(fields[1]).is_dir()).then(|| PathBuf::from(fields[1]))
+        }) else {
+            return;
+        };
+        assert_eq!(
+            check(&mount.join("clip.mp4")),
+            Err((BlockReason::ReadOnly(mount), Suggestion::ChooseFolder))
+        );
+    }
+}
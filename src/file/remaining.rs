@@ -0,0 +1,86 @@
+//! 按剩余空间估计还能录多久. 没有录制时按设置的码率估算,
+//! 录制中按实际的写入速度, 可变码率的编码与设置可能相差很多.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 封装的索引和头部在码率之外额外占用的比例.
+const CONTAINER_OVERHEAD: f64 = 1.02;
+/// 录制开始后这么久才改用实际的写入速度, 刚开始时文件大小还不稳定.
+pub(crate) const MEASURE_AFTER: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct RemainingSettings {
+    /// 剩余时间少于这么多分钟时显示为黄色.
+    pub warn_minutes: u32,
+    /// 少于这么多分钟时显示为红色.
+    pub alert_minutes: u32,
+}
+
+impl Default for RemainingSettings {
+    fn default() -> Self {
+        Self {
+            warn_minutes: 30,
+            alert_minutes: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RemainingLevel {
+    Normal,
+    Low,
+    Critical,
+}
+
+impl RemainingSettings {
+    pub fn level(&self, remaining: Duration) -> RemainingLevel {
+        let minutes = remaining.as_secs() / 60;
+        if minutes < self.alert_minutes as u64 {
+            RemainingLevel::Critical
+        } else if minutes < self.warn_minutes as u64 {
+            RemainingLevel::Low
+        } else {
+            RemainingLevel::Normal
+        }
+    }
+}
+
+/// 按设置的总码率 (kbit/s, 含音频) 估算, 加上封装的开销.
+pub(crate) fn at_bitrate(free: u64, kbps: u32) -> Duration {
+    let bytes_per_sec = kbps.max(1) as f64 * 1000.0 / 8.0 * CONTAINER_OVERHEAD;
+    Duration::from_secs_f64(free as f64 / bytes_per_sec)
+}
+
+/// 按录制以来的平均写入速度估算, 文件大小已经包含了封装的开销.
+/// 还没有写入数据时返回 None.
+pub(crate) fn at_write_rate(free: u64, written: u64, elapsed: Duration) -> Option<Duration> {
+    let bytes_per_sec = written as f64 / elapsed.as_secs_f64();
+    (bytes_per_sec.is_finite() && bytes_per_sec > 0.0)
+        .then(|| Duration::from_secs_f64(free as f64 / bytes_per_sec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_from_bitrate_and_measured_rate() {
+        // 10 Mbit/s 加上 2% 开销, 约 1,275,000 字节每秒
+        let free = 1_275_000 * 3600;
+        assert!((at_bitrate(free, 10_000).as_secs_f64() - 3600.0).abs() < 1.0);
+        assert_eq!(
+            at_write_rate(1_000_000 * 60, 10_000_000, Duration::from_secs(10)),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(at_write_rate(1, 0, Duration::from_secs(10)), None);
+        assert_eq!(at_write_rate(1, 10, Duration::ZERO), None);
+
+        let settings = RemainingSettings::default();
+        let min = |m: u64| Duration::from_secs(m * 60);
+        assert_eq!(settings.level(min(45)), RemainingLevel::Normal);
+        assert_eq!(settings.level(min(29)), RemainingLevel::Low);
+        assert_eq!(settings.level(min(4)), RemainingLevel::Critical);
+    }
+}
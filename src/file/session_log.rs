@@ -0,0 +1,145 @@
+//! 给操作员看的会话记录: 开始和停止录制, 标记, 警告和错误. 与开发用的日志分开,
+//! 每条都追加到录像目录中当天的 `session-<日期>.txt`, 换班时可以直接交给下一位.
+
+use chrono::{DateTime, Local, NaiveDate};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 内存中保留的条数, 文件中不受限制.
+const CAPACITY: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn tag(self) -> &'static str {
+        match self {
+            Severity::Info => "INFO ",
+            Severity::Warning => "WARN ",
+            Severity::Error => "ERROR",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SessionEntry {
+    pub time: DateTime<Local>,
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl SessionEntry {
+    /// 不区分大小写的子串匹配, 也匹配时间和级别.
+    pub fn matches(&self, query: &str) -> bool {
+        query.is_empty()
+            || self
+                .to_string()
+                .to_lowercase()
+                .contains(&query.to_lowercase())
+    }
+}
+
+impl std::fmt::Display for SessionEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}  {}  {}",
+            self.time.format("%Y-%m-%d %H:%M:%S"),
+            self.severity.tag(),
+            self.text
+        )
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct SessionLog {
+    entries: Vec<SessionEntry>,
+    /// 上一次写文件失败的目录, 同一个目录只警告一次.
+    failed_dir: Option<PathBuf>,
+}
+
+impl SessionLog {
+    pub fn entries(&self) -> &[SessionEntry] {
+        &self.entries
+    }
+
+    /// 记一条, 同时追加到 `dir` 中当天的文件. 写文件失败只记录开发日志.
+    pub fn push(&mut self, dir: &Path, severity: Severity, text: impl Into<String>) {
+        let entry = SessionEntry {
+            time: Local::now(),
+            severity,
+            text: text.into(),
+        };
+        match append(dir, &entry) {
+            Ok(()) => self.failed_dir = None,
+            Err(e) if self.failed_dir.as_deref() != Some(dir) => {
+                tracing::warn!(dir = %dir.display(), "could not write session log: {}", e);
+                self.failed_dir = Some(dir.to_path_buf());
+            }
+            Err(_) => {}
+        }
+        if self.entries.len() >= CAPACITY {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry);
+    }
+
+    /// 把这次运行中匹配 `query` 的记录写到 `dir` 中单独的文件.
+    pub fn export(&self, dir: &Path, query: &str) -> std::io::Result<PathBuf> {
+        let path = dir.join(format!(
+            "session-{}.txt",
+            Local::now().format("%Y-%m-%d-%H%M%S")
+        ));
+        let mut text = String::new();
+        for entry in self.entries.iter().filter(|e| e.matches(query)) {
+            text.push_str(&entry.to_string());
+            text.push('\n');
+        }
+        std::fs::write(&path, text)?;
+        Ok(path)
+    }
+}
+
+/// 当天的会话记录文件.
+pub(crate) fn day_file(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("session-{}.txt", date.format("%Y-%m-%d")))
+}
+
+fn append(dir: &Path, entry: &SessionEntry) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(day_file(dir, entry.time.date_naive()))?;
+    writeln!(file, "{}", entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn entries_format_as_one_line_and_match_substrings() {
+        let entry = SessionEntry {
+            time: Local.with_ymd_and_hms(2026, 3, 7, 9, 5, 0).unwrap(),
+            severity: Severity::Warning,
+            text: "Storage is too slow".to_string(),
+        };
+        assert_eq!(
+            entry.to_string(),
+            "2026-03-07 09:05:00  WARN   Storage is too slow"
+        );
+        assert!(entry.matches("too SLOW"));
+        assert!(entry.matches("warn"));
+        assert!(entry.matches(""));
+        assert!(!entry.matches("error"));
+        assert_eq!(
+            day_file(Path::new("/rec"), entry.time.date_naive()),
+            Path::new("/rec/session-2026-03-07.txt")
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use chrono::{DateTime, Local};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::markers::Marker;
+use super::naming::Slate;
+use crate::battery::PowerLog;
+use crate::thermal::TemperatureRange;
+use crate::video::balance::WhiteBalance;
+use crate::video::bus::BufferStats;
+use crate::video::controls::ExposureState;
+use crate::video::silence::Silence;
+use crate::video::write_speed::WriteUnderrun;
+
+/// 当前写入的 schema 版本. 只做向后兼容的追加: 新字段必须有默认值,
+/// 旧版本读取时会忽略不认识的字段.
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+/// UI 线程和后台校验线程可能同时更新同一个文件.
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 与每个录像同名的 `.json` 元数据文件.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct Sidecar {
+    pub schema_version: u32,
+    pub app_version: String,
+    pub clip: String,
+    pub started_at: DateTime<Local>,
+    pub stopped_at: DateTime<Local>,
+    pub duration_secs: f64,
+    pub size_bytes: u64,
+    pub device: String,
+    pub settings: SidecarSettings,
+    pub slate: Option<Slate>,
+    pub profile: Option<String>,
+    /// 停止录制后由操作员标记.
+    pub rating: Option<TakeRating>,
+    pub verification: Option<Verification>,
+    /// 录制期间因 QoS 迟到或丢弃的 buffer.
+    pub buffers: Option<BufferStats>,
+    /// 录制期间的 SoC 温度, 没有 thermal zone 时为 None.
+    pub temperature: Option<TemperatureRange>,
+    /// 录制开始和结束时的电池状态, 没有电池时为 None.
+    pub power: Option<PowerLog>,
+    /// 录制开始时的自动曝光补偿和锁定状态, 不是 V4L2 摄像头时为 None.
+    pub exposure: Option<ExposureState>,
+    pub white_balance: Option<WhiteBalance>,
+    /// 停止录制时的输入音量, 旧版本写的文件没有.
+    pub audio: Option<SidecarAudio>,
+    /// 录制中按下标记键的时刻.
+    pub markers: Vec<Marker>,
+    /// 录制中持续无声的片段.
+    pub silence: Vec<Silence>,
+    /// 录制中存储的写入速度跟不上的片段.
+    pub write_underruns: Vec<WriteUnderrun>,
+}
+
+/// 录制结束后的后台校验结果.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct Verification {
+    /// 整个文件的 xxh3-128, 十六进制.
+    pub xxh3: String,
+    /// 校验失败的原因, 为空表示通过.
+    pub error: Option<String>,
+    pub verified_at: DateTime<Local>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TakeRating {
+    Good,
+    #[serde(rename = "ng")]
+    NoGood,
+}
+
+/// 录制时使用的参数. 编码器和封装格式存为字符串, 以免旧版本遇到新的枚举值时解析失败.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct SidecarSettings {
+    pub encoder: String,
+    pub container: String,
+    pub width: u32,
+    pub height: u32,
+    pub bitrate_kbps: u32,
+    /// 降噪滤镜和强度, 关闭时为 None.
+    pub denoise: Option<String>,
+    /// 锐化滤镜, 强度, 半径和作用的分支, 关闭时为 None.
+    pub sharpen: Option<String>,
+}
+
+/// 录制时的输入音量.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct SidecarAudio {
+    /// 线性倍数, 1.0 为原样.
+    pub gain: f64,
+    /// 静音或者音量调成了 0, 录到的音轨没有声音.
+    pub muted: bool,
+}
+
+/// 元数据文件路径: 把录像的扩展名换成 `.json`.
+pub(crate) fn sidecar_path(clip: &Path) -> PathBuf {
+    clip.with_extension("json")
+}
+
+impl Sidecar {
+    pub fn write(&self, clip: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        // 先写临时文件再改名, 避免中途断电留下半个 JSON
+        let path = sidecar_path(clip);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    pub fn read(clip: &Path) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(sidecar_path(clip))?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+
+    /// 读取-修改-写回, 用于录制结束后补充的信息.
+    pub fn update(clip: &Path, f: impl FnOnce(&mut Self)) -> std::io::Result<()> {
+        let _guard = UPDATE_LOCK.lock();
+        let mut sidecar = Self::read(clip)?;
+        f(&mut sidecar);
+        sidecar.write(clip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battery::{BatteryStatus, ChargeState};
+    use crate::video::bus::{ElementQos, FrameStats};
+    use crate::video::controls::ExposureMode;
+
+    fn full() -> Sidecar {
+        let at = Local::now();
+        Sidecar {
+            schema_version: SCHEMA_VERSION,
+            app_version: "1.2.3".to_string(),
+            clip: "A001_S1_T3.mov".to_string(),
+            started_at: at,
+            stopped_at: at + chrono::Duration::seconds(90),
+            duration_secs: 90.25,
+            size_bytes: 123_456_789,
+            device: "/dev/video0".to_string(),
+            settings: SidecarSettings {
+                encoder: "H265".to_string(),
+                container: "MOV".to_string(),
+                width: 3840,
+                height: 2160,
+                bitrate_kbps: 40_000,
+                denoise: Some("hqdn3d light".to_string()),
+                sharpen: Some("unsharp 0.5".to_string()),
+            },
+            slate: Some(Slate {
+                reel: "B002".to_string(),
+                scene: "12A".to_string(),
+                take: 3,
+            }),
+            profile: Some("Interview".to_string()),
+            rating: Some(TakeRating::NoGood),
+            verification: Some(Verification {
+                xxh3: "0123456789abcdef0123456789abcdef".to_string(),
+                error: Some("size changed".to_string()),
+                verified_at: at,
+            }),
+            buffers: Some(BufferStats {
+                late: 4,
+                dropped: 2,
+                elements: [(
+                    "enc".to_string(),
+                    ElementQos {
+                        late: 4,
+                        dropped: 2,
+                    },
+                )]
+                .into(),
+                frames: FrameStats {
+                    entered: 2700,
+                    encoded: 2698,
+                    dropped: 2,
+                    repeated: 1,
+                    skipped: 1,
+                },
+            }),
+            temperature: Some(TemperatureRange {
+                min_celsius: 41.5,
+                max_celsius: 63.0,
+            }),
+            power: Some(PowerLog {
+                start: Some(BatteryStatus {
+                    percent: 80,
+                    state: ChargeState::Discharging,
+                    time_to_empty_secs: Some(7200),
+                }),
+                stop: None,
+            }),
+            exposure: Some(ExposureState {
+                mode: ExposureMode::Locked,
+                compensation_ev: -0.7,
+                shutter: Some("1/50".to_string()),
+                iso: Some(800),
+            }),
+            white_balance: Some(WhiteBalance::Kelvin(5600)),
+            audio: Some(SidecarAudio {
+                gain: 1.5,
+                muted: true,
+            }),
+            markers: vec![Marker {
+                secs: 12.5,
+                timecode: Some("10:00:12:12".to_string()),
+                label: Some("laugh".to_string()),
+            }],
+            silence: vec![Silence {
+                at,
+                start_secs: 30.0,
+                end_secs: Some(34.5),
+            }],
+            write_underruns: vec![WriteUnderrun {
+                at,
+                start_secs: 60.0,
+                end_secs: None,
+                written_bytes_per_sec: 2_000_000,
+                produced_bytes_per_sec: 5_000_000,
+            }],
+        }
+    }
+
+    #[test]
+    fn round_trips_every_field() {
+        let sidecar = full();
+        let json = serde_json::to_string_pretty(&sidecar).unwrap();
+        assert_eq!(serde_json::from_str::<Sidecar>(&json).unwrap(), sidecar);
+    }
+
+    #[test]
+    fn reads_files_from_older_versions() {
+        let json = r#"{
+            "schema_version": 1,
+            "app_version": "0.1.0",
+            "clip": "clip.mp4",
+            "started_at": "2026-01-02T10:00:00+01:00",
+            "stopped_at": "2026-01-02T10:01:00+01:00",
+            "duration_secs": 60.0,
+            "size_bytes": 1000,
+            "device": "/dev/video0",
+            "settings": { "encoder": "H264", "container": "MP4", "width": 1920, "height": 1080 }
+        }"#;
+        let sidecar: Sidecar = serde_json::from_str(json).unwrap();
+        assert_eq!(sidecar.clip, "clip.mp4");
+        assert_eq!(sidecar.settings.bitrate_kbps, 0);
+        assert_eq!(sidecar.settings.denoise, None);
+        assert_eq!(sidecar.audio, None);
+        assert_eq!(sidecar.rating, None);
+        assert!(sidecar.markers.is_empty());
+        assert!(sidecar.silence.is_empty());
+        assert!(sidecar.write_underruns.is_empty());
+    }
+}
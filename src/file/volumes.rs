@@ -0,0 +1,130 @@
+#[cfg(target_os = "linux")]
+use std::path::Path;
+use std::path::PathBuf;
+
+/// 已挂载的可移动存储卷 (U 盘, SD 卡等).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Volume {
+    pub device: String,
+    pub mount_point: PathBuf,
+    pub label: Option<String>,
+}
+
+impl Volume {
+    pub fn display_name(&self) -> String {
+        match &self.label {
+            Some(label) => format!("{} ({})", label, self.mount_point.display()),
+            None => self.mount_point.display().to_string(),
+        }
+    }
+
+    /// 卷是否仍然挂载在原来的位置. 拔出后挂载点会消失或被卸载.
+    pub fn is_mounted(&self) -> bool {
+        removable_volumes()
+            .iter()
+            .any(|v| v.mount_point == self.mount_point)
+    }
+}
+
+/// 枚举当前挂载的可移动存储卷.
+#[cfg(target_os = "linux")]
+pub(crate) fn removable_volumes() -> Vec<Volume> {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+    parse_mounts(&mounts)
+        .into_iter()
+        .filter(|(device, _)| is_removable(device))
+        .map(|(device, mount_point)| Volume {
+            label: label_for(&device),
+            device,
+            mount_point,
+        })
+        .collect()
+}
+
+/// macOS 上外接卷统一挂载在 /Volumes 下.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn removable_volumes() -> Vec<Volume> {
+    let Ok(entries) = std::fs::read_dir("/Volumes") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| !e.path().is_symlink()) // 跳过指向系统盘的链接
+        .map(|e| Volume {
+            device: String::new(),
+            label: Some(e.file_name().to_string_lossy().into_owned()),
+            mount_point: e.path(),
+        })
+        .collect()
+}
+
+/// 解析 /proc/mounts, 返回 (设备, 挂载点), 只保留块设备.
+#[cfg(target_os = "linux")]
+fn parse_mounts(content: &str) -> Vec<(String, PathBuf)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let mount_point = fields.next()?;
+            device
+                .starts_with("/dev/")
+                .then(|| (unescape(device), PathBuf::from(unescape(mount_point))))
+        })
+        .collect()
+}
+
+/// /proc/mounts 中空格等字符被转义为 `\040` 形式的八进制.
+#[cfg(target_os = "linux")]
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let digits = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(code) = u8::from_str_radix(digits, 8) {
+                out.push(code);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// 通过 sysfs 判断块设备是否可移动. 分区本身没有 `removable`, 需要查看所属磁盘.
+#[cfg(target_os = "linux")]
+fn is_removable(device: &str) -> bool {
+    let Some(name) = Path::new(device).file_name() else {
+        return false;
+    };
+    let Ok(sys_path) = std::fs::canonicalize(Path::new("/sys/class/block").join(name)) else {
+        return false;
+    };
+    let disk_path = if sys_path.join("partition").exists() {
+        sys_path.parent().map(Path::to_path_buf).unwrap_or(sys_path)
+    } else {
+        sys_path
+    };
+    let removable = std::fs::read_to_string(disk_path.join("removable"))
+        .map(|s| s.trim() == "1")
+        .unwrap_or(false);
+    // 部分 USB 移动硬盘不报告 removable, 按总线路径兜底
+    removable || disk_path.to_string_lossy().contains("/usb")
+}
+
+#[cfg(target_os = "linux")]
+fn label_for(device: &str) -> Option<String> {
+    let device = std::fs::canonicalize(device).ok()?;
+    std::fs::read_dir("/dev/disk/by-label")
+        .ok()?
+        .flatten()
+        .find(|e| std::fs::canonicalize(e.path()).ok().as_ref() == Some(&device))
+        // udev 用 `\x20` 转义卷标中的空格
+        .map(|e| e.file_name().to_string_lossy().replace("\\x20", " "))
+}
@@ -0,0 +1,383 @@
+//! 监视输出目录. rsync 拷走录像, 有人清理 SD 卡时, 录像列表和剩余空间要及时更新.
+//! Linux 上用 inotify, 短时间内的大量事件合并成一次报告.
+//! 目录被删除, 卸载或者无法监视时, 改为定时让界面重新扫描整个目录, 目录恢复后重新监视.
+
+use std::collections::HashSet;
+#[cfg(target_os = "linux")]
+use std::ffi::{CString, OsStr};
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// 最后一个事件之后这么久没有新事件才报告.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// 事件一直不断时最多攒这么久.
+const MAX_DELAY: Duration = Duration::from_secs(2);
+/// 无法监视时重新扫描的间隔, 也是检查目录是否被重新挂载的间隔.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+/// 等待事件的超时, 决定退出的响应速度.
+#[cfg(target_os = "linux")]
+const WAIT: Duration = Duration::from_millis(200);
+
+/// 一次报告的变化. 改名算作删除旧文件和添加新文件.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct DirChanges {
+    /// 新建或者写完的文件.
+    pub added: HashSet<PathBuf>,
+    pub removed: HashSet<PathBuf>,
+    /// 事件可能不完整, 应当重新扫描整个目录.
+    pub rescan: bool,
+}
+
+impl DirChanges {
+    fn is_empty(&self) -> bool {
+        !self.rescan && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    fn add(&mut self, path: PathBuf) {
+        self.removed.remove(&path);
+        self.added.insert(path);
+    }
+
+    fn remove(&mut self, path: PathBuf) {
+        self.added.remove(&path);
+        self.removed.insert(path);
+    }
+}
+
+/// 后台线程监视一个目录, 丢弃时线程退出.
+pub(crate) struct DirWatcher {
+    dir: PathBuf,
+    rx: mpsc::Receiver<DirChanges>,
+    stop: Arc<AtomicBool>,
+}
+
+impl DirWatcher {
+    pub fn spawn(dir: &Path) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_dir = dir.to_path_buf();
+        let thread_stop = stop.clone();
+        let spawned = std::thread::Builder::new()
+            .name("dir-watch".to_string())
+            .spawn(move || watch(&thread_dir, &tx, &thread_stop));
+        if let Err(e) = spawned {
+            tracing::warn!(dir = %dir.display(), "could not start directory watcher: {}", e);
+        }
+        Self {
+            dir: dir.to_path_buf(),
+            rx,
+            stop,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 合并上次以来的所有报告, 没有变化时返回 None.
+    pub fn poll(&self) -> Option<DirChanges> {
+        let mut changes = DirChanges::default();
+        for next in self.rx.try_iter() {
+            changes.rescan |= next.rescan;
+            for path in next.removed {
+                changes.remove(path);
+            }
+            for path in next.added {
+                changes.add(path);
+            }
+        }
+        (!changes.is_empty()).then_some(changes)
+    }
+}
+
+impl Drop for DirWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 攒着还没报告的变化.
+struct Pending {
+    changes: DirChanges,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl Pending {
+    fn new() -> Self {
+        Self {
+            changes: DirChanges::default(),
+            first: None,
+            last: None,
+        }
+    }
+
+    fn touch(&mut self, now: Instant) {
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+
+    fn due(&self, now: Instant) -> bool {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => {
+                now.duration_since(last) >= DEBOUNCE || now.duration_since(first) >= MAX_DELAY
+            }
+            _ => false,
+        }
+    }
+
+    fn take(&mut self) -> DirChanges {
+        self.first = None;
+        self.last = None;
+        std::mem::take(&mut self.changes)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn watch(dir: &Path, tx: &mpsc::Sender<DirChanges>, stop: &AtomicBool) {
+    let mut pending = Pending::new();
+    let mut inotify: Option<Inotify> = None;
+    let mut checked_at: Option<Instant> = None;
+    while !stop.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        let check = checked_at.is_none_or(|at| now.duration_since(at) >= RESCAN_INTERVAL);
+        match &inotify {
+            Some(watch) => {
+                let lost = match watch.read(WAIT, &mut pending.changes) {
+                    Ok(read) => {
+                        if read {
+                            pending.touch(Instant::now());
+                        }
+                        // 卸载后挂载点变回原来的空目录, inotify 仍然看着里面的 inode,
+                        // 重新挂载时不会有事件
+                        if check {
+                            checked_at = Some(now);
+                            !watch.still_watching(dir)
+                        } else {
+                            false
+                        }
+                    }
+                    Err(e) => {
+                        tracing::debug!(dir = %dir.display(), "stopped watching: {}", e);
+                        true
+                    }
+                };
+                if lost {
+                    tracing::info!(dir = %dir.display(), "output directory went away, rescanning periodically");
+                    inotify = None;
+                    pending.changes.rescan = true;
+                    pending.touch(Instant::now());
+                }
+            }
+            None => {
+                if check {
+                    checked_at = Some(now);
+                    match Inotify::watch(dir) {
+                        Ok(watch) => {
+                            tracing::debug!(dir = %dir.display(), "watching output directory");
+                            inotify = Some(watch);
+                        }
+                        Err(e) => tracing::debug!(dir = %dir.display(), "could not watch: {}", e),
+                    }
+                    // 刚开始监视时也可能漏掉了变化
+                    pending.changes.rescan = true;
+                    pending.touch(now);
+                }
+                std::thread::sleep(WAIT);
+            }
+        }
+        if pending.due(Instant::now()) && tx.send(pending.take()).is_err() {
+            return;
+        }
+    }
+}
+
+/// 不是 Linux 时没有 inotify, 定时让界面重新扫描.
+#[cfg(not(target_os = "linux"))]
+fn watch(_dir: &Path, tx: &mpsc::Sender<DirChanges>, stop: &AtomicBool) {
+    let mut pending = Pending::new();
+    let mut checked_at: Option<Instant> = None;
+    while !stop.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        if checked_at.is_none_or(|at| now.duration_since(at) >= RESCAN_INTERVAL) {
+            checked_at = Some(now);
+            pending.changes.rescan = true;
+            pending.touch(now);
+        }
+        if pending.due(now) && tx.send(pending.take()).is_err() {
+            return;
+        }
+        std::thread::sleep(DEBOUNCE);
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct Inotify {
+    fd: OwnedFd,
+    dir: PathBuf,
+    /// 开始监视时目录的 (设备, inode), 用来发现重新挂载.
+    identity: (u64, u64),
+}
+
+#[cfg(target_os = "linux")]
+impl Inotify {
+    const MASK: u32 = libc::IN_CREATE
+        | libc::IN_CLOSE_WRITE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_DELETE_SELF
+        | libc::IN_MOVE_SELF
+        | libc::IN_UNMOUNT
+        | libc::IN_ONLYDIR;
+
+    fn watch(dir: &Path) -> std::io::Result<Self> {
+        let identity = identity(dir)?;
+        let c_path = CString::new(dir.as_os_str().as_bytes())?;
+        // SAFETY: 只是创建一个文件描述符
+        let raw = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if raw < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: raw 是刚创建的, 只由 OwnedFd 关闭
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+        // SAFETY: 路径以 NUL 结尾
+        if unsafe { libc::inotify_add_watch(raw, c_path.as_ptr(), Self::MASK) } < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self {
+            fd,
+            dir: dir.to_path_buf(),
+            identity,
+        })
+    }
+
+    fn still_watching(&self, dir: &Path) -> bool {
+        identity(dir).is_ok_and(|id| id == self.identity)
+    }
+
+    /// 最多等待 `timeout`, 把读到的事件合并进 `changes`. 返回是否读到了事件,
+    /// 目录本身没了或者事件队列溢出时返回错误.
+    fn read(&self, timeout: Duration, changes: &mut DirChanges) -> std::io::Result<bool> {
+        let raw = self.fd.as_raw_fd();
+        let mut poll = libc::pollfd {
+            fd: raw,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: 只有一个 pollfd
+        let ready = unsafe { libc::poll(&mut poll, 1, timeout.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            return Ok(false);
+        }
+        // 按 inotify_event 对齐, 至少能放下一个带最长文件名的事件
+        let mut buf = [0u64; 1024];
+        // SAFETY: 写入不超过 buf 的大小
+        let len = unsafe { libc::read(raw, buf.as_mut_ptr().cast(), std::mem::size_of_val(&buf)) };
+        if len < 0 {
+            let e = std::io::Error::last_os_error();
+            return match e.kind() {
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted => Ok(false),
+                _ => Err(e),
+            };
+        }
+        // SAFETY: 读到的前 len 个字节
+        let bytes = unsafe { std::slice::from_raw_parts(buf.as_ptr().cast::<u8>(), len as usize) };
+        let header = std::mem::size_of::<libc::inotify_event>();
+        let mut offset = 0;
+        while offset + header <= bytes.len() {
+            // SAFETY: 内核保证事件完整, 按字节读取不要求对齐
+            let event: libc::inotify_event =
+                unsafe { std::ptr::read_unaligned(bytes[offset..].as_ptr().cast()) };
+            let name_end = (offset + header + event.len as usize).min(bytes.len());
+            let name = &bytes[offset + header..name_end];
+            offset = name_end;
+            let name = name.split(|&b| b == 0).next().unwrap_or_default();
+
+            if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                changes.rescan = true;
+                continue;
+            }
+            if event.mask
+                & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF | libc::IN_UNMOUNT | libc::IN_IGNORED)
+                != 0
+            {
+                return Err(std::io::Error::other("directory was removed or unmounted"));
+            }
+            if name.is_empty() || event.mask & libc::IN_ISDIR != 0 {
+                continue;
+            }
+            let path = self.dir.join(OsStr::from_bytes(name));
+            if event.mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0 {
+                changes.remove(path);
+            } else {
+                changes.add(path);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn identity(dir: &Path) -> std::io::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = std::fs::metadata(dir)?;
+    Ok((meta.dev(), meta.ino()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 等到报告里出现想要的变化, 最多几秒.
+    fn wait_for(watcher: &DirWatcher, mut done: impl FnMut(&DirChanges) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if let Some(changes) = watcher.poll()
+                && done(&changes)
+            {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        panic!("timed out waiting for directory changes");
+    }
+
+    #[test]
+    fn reports_added_and_removed_files_and_a_lost_directory() {
+        let dir = std::env::temp_dir().join(format!("cam-ui-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let watcher = DirWatcher::spawn(&dir);
+        // 开始时总是要求扫描一次
+        wait_for(&watcher, |c| c.rescan);
+
+        let clip = dir.join("A001_C001.mp4");
+        std::fs::write(&clip, b"clip").unwrap();
+        wait_for(&watcher, |c| c.added.contains(&clip));
+        std::fs::remove_file(&clip).unwrap();
+        wait_for(&watcher, |c| c.removed.contains(&clip));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        wait_for(&watcher, |c| c.rescan);
+    }
+
+    #[test]
+    fn merges_changes_to_the_same_file() {
+        let mut changes = DirChanges::default();
+        let path = PathBuf::from("/rec/a.mp4");
+        changes.add(path.clone());
+        changes.remove(path.clone());
+        assert!(changes.added.is_empty());
+        assert!(changes.removed.contains(&path));
+        changes.add(path.clone());
+        assert!(changes.removed.is_empty());
+        assert!(!changes.is_empty());
+    }
+}
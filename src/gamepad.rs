@@ -0,0 +1,275 @@
+//! 游戏手柄输入 (`gamepad` 功能). 按键映射到与快捷键相同的 [Action], 摇杆控制变焦和对焦.
+//! gilrs 自己处理热插拔, 界面每帧取出积压的事件, 不会阻塞.
+
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::i18n::tr;
+use crate::keymap::Action;
+
+/// 可以映射的按键, 按 Xbox 手柄的位置命名.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum PadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl PadButton {
+    pub const ALL: [PadButton; 17] = [
+        PadButton::South,
+        PadButton::East,
+        PadButton::West,
+        PadButton::North,
+        PadButton::LeftBumper,
+        PadButton::RightBumper,
+        PadButton::LeftTrigger,
+        PadButton::RightTrigger,
+        PadButton::Select,
+        PadButton::Start,
+        PadButton::Mode,
+        PadButton::LeftThumb,
+        PadButton::RightThumb,
+        PadButton::DPadUp,
+        PadButton::DPadDown,
+        PadButton::DPadLeft,
+        PadButton::DPadRight,
+    ];
+
+    /// 手柄上印的名称, 与键名一样不翻译.
+    pub fn label(self) -> &'static str {
+        match self {
+            PadButton::South => "A",
+            PadButton::East => "B",
+            PadButton::West => "X",
+            PadButton::North => "Y",
+            PadButton::LeftBumper => "LB",
+            PadButton::RightBumper => "RB",
+            PadButton::LeftTrigger => "LT",
+            PadButton::RightTrigger => "RT",
+            PadButton::Select => "Back",
+            PadButton::Start => "Start",
+            PadButton::Mode => "Guide",
+            PadButton::LeftThumb => "LS",
+            PadButton::RightThumb => "RS",
+            PadButton::DPadUp => "D-pad ↑",
+            PadButton::DPadDown => "D-pad ↓",
+            PadButton::DPadLeft => "D-pad ←",
+            PadButton::DPadRight => "D-pad →",
+        }
+    }
+
+    fn button(self) -> Button {
+        match self {
+            PadButton::South => Button::South,
+            PadButton::East => Button::East,
+            PadButton::West => Button::West,
+            PadButton::North => Button::North,
+            PadButton::LeftBumper => Button::LeftTrigger,
+            PadButton::RightBumper => Button::RightTrigger,
+            PadButton::LeftTrigger => Button::LeftTrigger2,
+            PadButton::RightTrigger => Button::RightTrigger2,
+            PadButton::Select => Button::Select,
+            PadButton::Start => Button::Start,
+            PadButton::Mode => Button::Mode,
+            PadButton::LeftThumb => Button::LeftThumb,
+            PadButton::RightThumb => Button::RightThumb,
+            PadButton::DPadUp => Button::DPadUp,
+            PadButton::DPadDown => Button::DPadDown,
+            PadButton::DPadLeft => Button::DPadLeft,
+            PadButton::DPadRight => Button::DPadRight,
+        }
+    }
+
+    fn from_button(button: Button) -> Option<PadButton> {
+        PadButton::ALL.into_iter().find(|b| b.button() == button)
+    }
+}
+
+/// 摇杆的一个方向. 向右和向上为正.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum PadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+}
+
+impl PadAxis {
+    pub const ALL: [PadAxis; 4] = [
+        PadAxis::LeftX,
+        PadAxis::LeftY,
+        PadAxis::RightX,
+        PadAxis::RightY,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PadAxis::LeftX => tr("settings.gamepad.left_x"),
+            PadAxis::LeftY => tr("settings.gamepad.left_y"),
+            PadAxis::RightX => tr("settings.gamepad.right_x"),
+            PadAxis::RightY => tr("settings.gamepad.right_y"),
+        }
+    }
+
+    fn axis(self) -> Axis {
+        match self {
+            PadAxis::LeftX => Axis::LeftStickX,
+            PadAxis::LeftY => Axis::LeftStickY,
+            PadAxis::RightX => Axis::RightStickX,
+            PadAxis::RightY => Axis::RightStickY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GamepadSettings {
+    pub enabled: bool,
+    /// 按键到操作的映射, 例如 `south = "toggle-record"`.
+    pub buttons: BTreeMap<PadButton, Action>,
+    /// 向上推放大, 为 None 时摇杆不控制变焦.
+    pub zoom_axis: Option<PadAxis>,
+    /// 向上推对远处, 为 None 时摇杆不控制对焦.
+    pub focus_axis: Option<PadAxis>,
+    /// 摇杆偏离中心不到这个比例时忽略, 0 到 1.
+    pub deadzone: f32,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            buttons: BTreeMap::from([
+                (PadButton::South, Action::ToggleRecord),
+                (PadButton::East, Action::Snapshot),
+                (PadButton::North, Action::PunchIn),
+            ]),
+            zoom_axis: Some(PadAxis::LeftY),
+            focus_axis: Some(PadAxis::RightY),
+            deadzone: 0.15,
+        }
+    }
+}
+
+/// 忽略死区内的值, 死区外重新缩放到 0 到 1, 这样推过死区时不会突然跳变.
+pub(crate) fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    let deadzone = deadzone.clamp(0.0, 0.95);
+    if value.abs() <= deadzone {
+        return 0.0;
+    }
+    ((value.abs() - deadzone) / (1.0 - deadzone)).min(1.0) * value.signum()
+}
+
+pub(crate) enum PadEvent {
+    Connected(String),
+    Disconnected(String),
+    Pressed(PadButton),
+}
+
+/// 所有连接的手柄. 最近一次按键或连接的手柄是当前手柄, 摇杆只读它的.
+pub(crate) struct Gamepads {
+    gilrs: Gilrs,
+    active: Option<GamepadId>,
+}
+
+impl Gamepads {
+    /// 系统不支持时返回 None, 只记录日志.
+    pub fn new() -> Option<Self> {
+        let gilrs = Gilrs::new()
+            .inspect_err(|e| tracing::warn!("gamepad input unavailable: {}", e))
+            .ok()?;
+        let active = gilrs.gamepads().next().map(|(id, pad)| {
+            tracing::info!(name = pad.name(), "gamepad connected");
+            id
+        });
+        Some(Self { gilrs, active })
+    }
+
+    /// 取出上一帧以来的事件.
+    pub fn poll(&mut self) -> Vec<PadEvent> {
+        let mut events = Vec::new();
+        while let Some(event) = self.gilrs.next_event() {
+            let name = || self.gilrs.gamepad(event.id).name().to_string();
+            match event.event {
+                EventType::Connected => {
+                    tracing::info!(name = %name(), "gamepad connected");
+                    events.push(PadEvent::Connected(name()));
+                    self.active = Some(event.id);
+                }
+                EventType::Disconnected => {
+                    tracing::info!(name = %name(), "gamepad disconnected");
+                    events.push(PadEvent::Disconnected(name()));
+                    if self.active == Some(event.id) {
+                        self.active = self.gilrs.gamepads().next().map(|(id, _)| id);
+                    }
+                }
+                EventType::ButtonPressed(button, _) => {
+                    self.active = Some(event.id);
+                    if let Some(button) = PadButton::from_button(button) {
+                        events.push(PadEvent::Pressed(button));
+                    }
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    /// 当前手柄的名称, 没有连接手柄时为 None.
+    pub fn name(&self) -> Option<String> {
+        self.active
+            .and_then(|id| self.gilrs.connected_gamepad(id))
+            .map(|pad| pad.name().to_string())
+    }
+
+    pub fn axis(&self, axis: PadAxis) -> f32 {
+        self.active
+            .and_then(|id| self.gilrs.connected_gamepad(id))
+            .map_or(0.0, |pad| pad.value(axis.axis()))
+    }
+
+    pub fn is_pressed(&self, button: PadButton) -> bool {
+        self.active
+            .and_then(|id| self.gilrs.connected_gamepad(id))
+            .is_some_and(|pad| pad.is_pressed(button.button()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_is_rescaled() {
+        assert_eq!(apply_deadzone(0.1, 0.2), 0.0);
+        assert_eq!(apply_deadzone(-0.2, 0.2), 0.0);
+        assert!((apply_deadzone(0.6, 0.2) - 0.5).abs() < 1e-6);
+        assert_eq!(apply_deadzone(-1.0, 0.2), -1.0);
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = GamepadSettings::default();
+        let text = toml::to_string(&settings).unwrap();
+        assert!(text.contains("south = \"toggle-record\""), "{}", text);
+        assert_eq!(toml::from_str::<GamepadSettings>(&text).unwrap(), settings);
+    }
+}
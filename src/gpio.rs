@@ -0,0 +1,277 @@
+//! GPIO 录制指示灯和按钮 (`gpio` 功能), 用于树莓派等 Linux 板子.
+//! 通过 `/dev/gpiochipN` 访问, 在单独的线程中采样按钮和刷新指示灯.
+//! 打不开芯片或引脚时只记录警告, 其他系统上什么也不做.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::recorder::RecordPhase;
+
+/// 开始和停止过程中指示灯闪烁的半周期.
+const BLINK_INTERVAL: Duration = Duration::from_millis(250);
+/// 按钮电平保持这么久不变才算数.
+const DEBOUNCE: Duration = Duration::from_millis(30);
+#[cfg(target_os = "linux")]
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GpioSettings {
+    pub chip: PathBuf,
+    /// 指示灯的引脚 (芯片上的编号, 树莓派上即 BCM 编号), 为 None 时不使用.
+    pub tally_pin: Option<u32>,
+    /// 按下后切换录制的按钮引脚, 为 None 时不使用.
+    pub button_pin: Option<u32>,
+    /// 按钮接地并使用上拉电阻时为 true, 低电平表示按下.
+    pub button_active_low: bool,
+}
+
+impl Default for GpioSettings {
+    fn default() -> Self {
+        Self {
+            chip: PathBuf::from("/dev/gpiochip0"),
+            tally_pin: None,
+            button_pin: None,
+            button_active_low: true,
+        }
+    }
+}
+
+/// 指示灯在 `elapsed` 时是否应当亮起: 录制时常亮, 过渡阶段闪烁.
+fn tally_level(phase: RecordPhase, elapsed: Duration) -> bool {
+    match phase {
+        RecordPhase::Idle => false,
+        RecordPhase::Recording => true,
+        RecordPhase::Starting | RecordPhase::Stopping => {
+            (elapsed.as_millis() / BLINK_INTERVAL.as_millis()).is_multiple_of(2)
+        }
+    }
+}
+
+/// 按钮消抖. 稳定按下的那一刻返回 true, 松开不算.
+struct Debouncer {
+    stable: bool,
+    candidate: bool,
+    since: Instant,
+}
+
+impl Debouncer {
+    fn new(now: Instant) -> Self {
+        Self {
+            stable: false,
+            candidate: false,
+            since: now,
+        }
+    }
+
+    fn update(&mut self, pressed: bool, now: Instant) -> bool {
+        if pressed != self.candidate {
+            self.candidate = pressed;
+            self.since = now;
+        }
+        if self.candidate != self.stable && now - self.since >= DEBOUNCE {
+            self.stable = self.candidate;
+            return self.stable;
+        }
+        false
+    }
+}
+
+/// 持有 GPIO 线程. 丢弃时线程在下一次采样后退出, 指示灯熄灭.
+pub(crate) struct Gpio {
+    phase: Arc<Mutex<RecordPhase>>,
+    presses: Option<mpsc::UnboundedReceiver<()>>,
+}
+
+impl Gpio {
+    /// 没有配置引脚或无法打开时返回一个什么也不做的实例.
+    pub fn spawn(settings: &GpioSettings) -> Self {
+        let phase = Arc::new(Mutex::new(RecordPhase::Idle));
+        if settings.tally_pin.is_none() && settings.button_pin.is_none() {
+            return Self {
+                phase,
+                presses: None,
+            };
+        }
+        let presses = Self::start(settings, phase.clone());
+        Self { phase, presses }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn start(
+        settings: &GpioSettings,
+        phase: Arc<Mutex<RecordPhase>>,
+    ) -> Option<mpsc::UnboundedReceiver<()>> {
+        let lines = linux::Lines::open(settings)
+            .inspect_err(
+                |e| tracing::warn!(chip = %settings.chip.display(), "gpio unavailable: {}", e),
+            )
+            .ok()?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let spawned = std::thread::Builder::new()
+            .name("gpio".into())
+            .spawn(move || lines.run(phase, tx));
+        if let Err(e) = spawned {
+            tracing::warn!("could not start gpio thread: {}", e);
+            return None;
+        }
+        Some(rx)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn start(_: &GpioSettings, _: Arc<Mutex<RecordPhase>>) -> Option<mpsc::UnboundedReceiver<()>> {
+        tracing::warn!("gpio is only supported on linux");
+        None
+    }
+
+    /// 每次录制状态可能变化后调用.
+    pub fn set_phase(&self, phase: RecordPhase) {
+        *self.phase.lock() = phase;
+    }
+
+    /// 上次调用以来按钮是否被按下过 (多次按下只算一次).
+    pub fn pressed(&mut self) -> bool {
+        let Some(rx) = &mut self.presses else {
+            return false;
+        };
+        let mut pressed = false;
+        while rx.try_recv().is_ok() {
+            pressed = true;
+        }
+        pressed
+    }
+
+    /// 等待下一次按下, 没有按钮或无法读取时永远不返回.
+    pub async fn wait_pressed(&mut self) {
+        if let Some(rx) = &mut self.presses
+            && rx.recv().await.is_some()
+        {
+            return;
+        }
+        std::future::pending().await
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::time::Instant;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    use super::{Debouncer, GpioSettings, POLL_INTERVAL, RecordPhase, tally_level};
+
+    const CONSUMER: &str = "cam-ui";
+
+    pub(super) struct Lines {
+        tally: Option<LineHandle>,
+        button: Option<LineHandle>,
+    }
+
+    impl Lines {
+        pub fn open(settings: &GpioSettings) -> Result<Self, gpio_cdev::Error> {
+            let mut chip = Chip::new(&settings.chip)?;
+            let tally = match settings.tally_pin {
+                Some(pin) => Some(chip.get_line(pin)?.request(
+                    LineRequestFlags::OUTPUT,
+                    0,
+                    CONSUMER,
+                )?),
+                None => None,
+            };
+            let button = match settings.button_pin {
+                Some(pin) => {
+                    let mut flags = LineRequestFlags::INPUT;
+                    if settings.button_active_low {
+                        flags |= LineRequestFlags::ACTIVE_LOW;
+                    }
+                    Some(chip.get_line(pin)?.request(flags, 0, CONSUMER)?)
+                }
+                None => None,
+            };
+            tracing::info!(tally = ?settings.tally_pin, button = ?settings.button_pin, "gpio ready");
+            Ok(Self { tally, button })
+        }
+
+        /// 直到接收端被丢弃才返回.
+        pub fn run(self, phase: Arc<Mutex<RecordPhase>>, presses: UnboundedSender<()>) {
+            let Lines { tally, mut button } = self;
+            let started = Instant::now();
+            let mut debouncer = Debouncer::new(started);
+            let mut lit = None;
+            loop {
+                let now = Instant::now();
+                if let Some(tally) = &tally {
+                    let level = tally_level(*phase.lock(), now - started);
+                    if lit != Some(level) {
+                        if let Err(e) = tally.set_value(level as u8) {
+                            tracing::warn!("could not drive tally light: {}", e);
+                        }
+                        lit = Some(level);
+                    }
+                }
+                if let Some(line) = &button {
+                    match line.get_value() {
+                        Ok(value) => {
+                            if debouncer.update(value == 1, now) {
+                                let _ = presses.send(());
+                            }
+                        }
+                        Err(e) => {
+                            // 指示灯继续工作
+                            tracing::warn!("could not read record button, ignoring it: {}", e);
+                            button = None;
+                        }
+                    }
+                }
+                if presses.is_closed() {
+                    break;
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            if let Some(tally) = &tally {
+                let _ = tally.set_value(0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tally_blinks_while_changing_state() {
+        let ms = Duration::from_millis;
+        assert!(!tally_level(RecordPhase::Idle, ms(0)));
+        assert!(tally_level(RecordPhase::Recording, ms(300)));
+        assert!(tally_level(RecordPhase::Starting, ms(100)));
+        assert!(!tally_level(RecordPhase::Starting, ms(300)));
+        assert!(tally_level(RecordPhase::Stopping, ms(600)));
+    }
+
+    #[test]
+    fn button_is_debounced() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut button = Debouncer::new(start);
+        // 抖动不触发
+        assert!(!button.update(true, at(0)));
+        assert!(!button.update(false, at(10)));
+        assert!(!button.update(true, at(20)));
+        assert!(!button.update(true, at(40)));
+        // 稳定按下后只触发一次
+        assert!(button.update(true, at(50)));
+        assert!(!button.update(true, at(200)));
+        // 松开不触发, 再次按下触发
+        assert!(!button.update(false, at(210)));
+        assert!(!button.update(false, at(250)));
+        assert!(!button.update(true, at(260)));
+        assert!(button.update(true, at(300)));
+    }
+}
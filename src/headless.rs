@@ -0,0 +1,539 @@
+use parking_lot::Mutex;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::battery::{BatteryAlert, BatteryMonitor};
+use crate::config::{Config, LoadedConfig, StopGuard};
+use crate::file::sidecar::Sidecar;
+use crate::inhibit::SleepInhibitor;
+use crate::offload::Uploader;
+use crate::recorder::{Recorder, StopConfirm};
+use crate::signals::shutdown_signal;
+use crate::status::{StatusEvent, StatusHub};
+use crate::thermal::{ThermalLevel, ThermalMonitor};
+use crate::video::bus::BufferStats;
+use crate::video::{
+    self,
+    record::{RecordEvent, RecordProgress, RecordingId},
+    write_speed::WriteChange,
+};
+use crate::webhook::Webhooks;
+
+/// 状态行的输出间隔.
+const STATUS_INTERVAL: Duration = Duration::from_secs(5);
+/// 检查是否到了自动开始录制时间的间隔.
+const AUTO_START_POLL: Duration = Duration::from_millis(200);
+/// 收到退出信号后等待文件封装完成的最长时间.
+const FINALIZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 无界面模式: 只运行采集和录制, 直到收到 SIGINT/SIGTERM.
+/// 状态以 logfmt 格式 (`key=value`) 逐行输出到标准输出.
+pub(crate) fn run(loaded: LoadedConfig, record: bool) -> std::io::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(run_async(loaded, record))
+}
+
+async fn run_async(loaded: LoadedConfig, record: bool) -> std::io::Result<()> {
+    let LoadedConfig {
+        mut config,
+        persisted,
+        path,
+        overrides,
+        warning,
+    } = loaded;
+    if let Some(message) = warning {
+        log("warning", &[("message", message)]);
+    }
+
+    // 管线仍然带有预览分支, 帧写入这里后无人读取
+    let frame_buffer = video::exchange::FrameExchange::default();
+    let audio_level = Arc::new(Mutex::new(-60.0f32));
+    let buffer_stats = Arc::new(Mutex::new(BufferStats::default()));
+    let (rec_cmd_tx, rec_cmd_rx) = mpsc::unbounded_channel();
+    let (rec_event_tx, mut rec_event_rx) = mpsc::unbounded_channel();
+    let gst_thread = video::spawn_gst_thread(
+        config.capture.sources(),
+        frame_buffer,
+        // 缩略图分支默认关闭, 不占用 CPU; 画中画只在合成时有用
+        Default::default(),
+        video::overlay::SharedOverlay::new(config.overlay.clone(), audio_level.clone()),
+        audio_level.clone(),
+        buffer_stats.clone(),
+        rec_cmd_rx,
+        rec_event_tx,
+    );
+
+    let status = StatusHub::spawn(
+        &config.status,
+        audio_level.clone(),
+        &config.recording.output_dir,
+    );
+    let mut engine = Engine {
+        recorder: Recorder::new(rec_cmd_tx),
+        uploader: Uploader::spawn(config.offload.clone()),
+        webhooks: Webhooks::spawn(config.webhook.clone()),
+        #[cfg(feature = "gpio")]
+        gpio: crate::gpio::Gpio::spawn(&config.gpio),
+        #[cfg(feature = "mqtt")]
+        mqtt: crate::mqtt::Mqtt::spawn(&config.mqtt, audio_level.clone(), status.state()),
+        status,
+        inhibitor: SleepInhibitor::new(),
+        drop_warned: false,
+        thermal: ThermalMonitor::new(config.thermal.clone()),
+        battery: BatteryMonitor::new(config.battery.clone()),
+        progress: None,
+        stop_confirm: StopConfirm::default(),
+    };
+    // 与界面一致, 等到第一帧出现后再开始, 失败时按退避重试
+    if record || config.auto_record {
+        engine.recorder.enable_auto_start();
+    }
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    let mut status = tokio::time::interval(STATUS_INTERVAL);
+    let mut auto_start = tokio::time::interval(AUTO_START_POLL);
+    loop {
+        tokio::select! {
+            signal = &mut shutdown => {
+                log("shutdown", &[("signal", signal.to_string())]);
+                break;
+            }
+            event = rec_event_rx.recv() => match event {
+                Some(event) => engine.handle_event(&config, event),
+                None => {
+                    log("exit", &[("reason", "pipeline stopped".to_string())]);
+                    return Err(std::io::Error::other("pipeline stopped"));
+                }
+            },
+            command = engine.remote_command() => engine.run_remote_command(&mut config, command),
+            _ = status.tick() => {
+                let elapsed = engine.recorder.elapsed().unwrap_or_default();
+                let stats = buffer_stats.lock().clone();
+                engine.check_temperature();
+                engine.check_battery(&mut config);
+                let celsius = engine.thermal.current().map(|c| format!("{:.1}", c)).unwrap_or_default();
+                let progress = engine.progress.unwrap_or_default();
+                let battery = engine.battery.status().map(|s| s.percent.to_string()).unwrap_or_default();
+                log("status", &[
+                    ("recording", engine.recorder.is_recording().to_string()),
+                    ("elapsed_s", elapsed.as_secs().to_string()),
+                    ("level_db", format!("{:.1}", *audio_level.lock())),
+                    ("dropped", stats.dropped.to_string()),
+                    ("late", stats.late.to_string()),
+                    ("dropped_frames", stats.frames.dropped.to_string()),
+                    ("temp_c", celsius),
+                    ("battery_pct", battery),
+                    ("size_bytes", progress.bytes.to_string()),
+                    ("bitrate_kbps", (progress.bytes_per_sec * 8 / 1000).to_string()),
+                ]);
+                engine.check_dropped_frames(&config, stats.frames.dropped);
+            }
+            _ = auto_start.tick() => {
+                if engine.recorder.auto_start_due() {
+                    engine.start(&config);
+                }
+                if let Some(e) = engine.recorder.auto_start_error() {
+                    log("error", &[("message", format!("auto-record gave up: {}", e))]);
+                    engine.recorder.dismiss_auto_start_error();
+                }
+            }
+        }
+    }
+
+    // 等待正在录制的文件封装完成后再退出
+    if engine.recorder.is_recording() {
+        engine.recorder.stop(&mut config.slate);
+        let finalize = async {
+            while let Some(event) = rec_event_rx.recv().await {
+                let stopped = matches!(
+                    event,
+                    RecordEvent::Stopped {
+                        id: RecordingId::MAIN,
+                        ..
+                    }
+                );
+                engine.handle_event(&config, event);
+                if stopped {
+                    break;
+                }
+            }
+        };
+        if tokio::time::timeout(FINALIZE_TIMEOUT, finalize)
+            .await
+            .is_err()
+        {
+            log(
+                "error",
+                &[("message", "timed out finalizing recording".to_string())],
+            );
+        }
+    }
+    // 关闭指令通道, gst 线程在封装完成后停止管线并退出
+    drop(engine);
+    gst_thread.join(video::SHUTDOWN_TIMEOUT);
+
+    // 镜次在停止时递增, 保存下来以便下次继续
+    let mut to_save = config;
+    overrides.restore(&mut to_save, &persisted);
+    if to_save != persisted
+        && let Some(path) = path
+        && let Err(e) = to_save.save(&path)
+    {
+        log(
+            "error",
+            &[("message", format!("could not save settings: {}", e))],
+        );
+    }
+    Ok(())
+}
+
+/// 来自 MQTT 或 GPIO 按钮的指令. 两项功能都没有编译时没有任何取值.
+enum RemoteCommand {
+    #[cfg(feature = "mqtt")]
+    Mqtt(crate::mqtt::MqttCommand),
+    #[cfg(feature = "gpio")]
+    ToggleRecord,
+}
+
+/// 无界面模式下代替 `CameraApp` 消费录制事件.
+struct Engine {
+    recorder: Recorder,
+    uploader: Uploader,
+    webhooks: Webhooks,
+    status: StatusHub,
+    #[cfg(feature = "mqtt")]
+    mqtt: crate::mqtt::Mqtt,
+    #[cfg(feature = "gpio")]
+    gpio: crate::gpio::Gpio,
+    inhibitor: SleepInhibitor,
+    /// 本次录制已经提示过丢帧.
+    drop_warned: bool,
+    thermal: ThermalMonitor,
+    battery: BatteryMonitor,
+    /// 正在录制的文件最近一次报告的大小和码率.
+    progress: Option<RecordProgress>,
+    /// 远程停止请求的两步确认.
+    stop_confirm: StopConfirm,
+}
+
+impl Engine {
+    fn start(&mut self, config: &Config) {
+        // 与界面一致: 只有参数和预设完全相同时才记录预设名称
+        let profile = config
+            .active_profile
+            .as_ref()
+            .filter(|name| {
+                config
+                    .profiles
+                    .iter()
+                    .any(|p| &p.name == *name && p.recording == config.recording)
+            })
+            .cloned();
+        self.recorder
+            .start(&config.recording, &config.tags, &config.slate, profile);
+        self.sync_recording_state();
+    }
+
+    /// 录制状态变化后调用: 录制期间阻止系统休眠 (失败只记录警告), 并更新指示灯.
+    fn sync_recording_state(&mut self) {
+        if let Err(e) = self.inhibitor.set_active(self.recorder.is_recording()) {
+            log(
+                "warning",
+                &[("message", format!("could not inhibit sleep: {}", e))],
+            );
+        }
+        #[cfg(feature = "gpio")]
+        self.gpio.set_phase(self.recorder.phase());
+    }
+
+    #[cfg_attr(not(any(feature = "mqtt", feature = "gpio")), allow(dead_code))]
+    fn set_recording(&mut self, config: &mut Config, recording: bool) {
+        if recording {
+            self.start(config);
+        } else {
+            self.stop(config, false);
+        }
+    }
+
+    /// 开启防误停时, 没有 `force` 的请求要在确认时间内再发一次才停止.
+    #[cfg_attr(not(any(feature = "mqtt", feature = "gpio")), allow(dead_code))]
+    fn stop(&mut self, config: &mut Config, force: bool) {
+        if !self.recorder.is_recording() {
+            return;
+        }
+        let guarded = !force && config.behavior.stop_guard != StopGuard::Off;
+        if guarded && !self.stop_confirm.request(Instant::now()) {
+            log(
+                "stop_requested",
+                &[("message", "send stop again to confirm".to_string())],
+            );
+            return;
+        }
+        self.stop_confirm.reset();
+        self.recorder.stop(&mut config.slate);
+        self.sync_recording_state();
+    }
+
+    /// 下一条远程指令. 没有编译相应功能时永远不返回.
+    async fn remote_command(&mut self) -> RemoteCommand {
+        #[cfg(feature = "mqtt")]
+        let mqtt = {
+            let mqtt = &mut self.mqtt;
+            async move { RemoteCommand::Mqtt(mqtt.recv().await) }
+        };
+        #[cfg(not(feature = "mqtt"))]
+        let mqtt = std::future::pending();
+        #[cfg(feature = "gpio")]
+        let gpio = {
+            let gpio = &mut self.gpio;
+            async move {
+                gpio.wait_pressed().await;
+                RemoteCommand::ToggleRecord
+            }
+        };
+        #[cfg(not(feature = "gpio"))]
+        let gpio = std::future::pending();
+        tokio::select! {
+            command = mqtt => command,
+            command = gpio => command,
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "mqtt", feature = "gpio")), allow(unused_variables))]
+    fn run_remote_command(&mut self, config: &mut Config, command: RemoteCommand) {
+        match command {
+            #[cfg(feature = "mqtt")]
+            RemoteCommand::Mqtt(crate::mqtt::MqttCommand::Record(recording)) => {
+                self.set_recording(config, recording)
+            }
+            #[cfg(feature = "mqtt")]
+            RemoteCommand::Mqtt(crate::mqtt::MqttCommand::ForceStop) => self.stop(config, true),
+            #[cfg(feature = "mqtt")]
+            RemoteCommand::Mqtt(crate::mqtt::MqttCommand::Snapshot) => {
+                self.recorder
+                    .snapshot(&config.recording, &config.slate, &config.snapshot)
+            }
+            #[cfg(feature = "mqtt")]
+            RemoteCommand::Mqtt(crate::mqtt::MqttCommand::DumpGraph) => {
+                self.recorder.dump_graph(&config.recording.output_dir)
+            }
+            #[cfg(feature = "gpio")]
+            RemoteCommand::ToggleRecord => {
+                self.set_recording(config, !self.recorder.is_recording())
+            }
+        }
+    }
+
+    fn check_dropped_frames(&mut self, config: &Config, dropped: u64) {
+        let threshold = config.drop_warning_frames;
+        if self.drop_warned || threshold == 0 || !self.recorder.is_recording() {
+            return;
+        }
+        if dropped >= threshold {
+            self.drop_warned = true;
+            self.status.publish(StatusEvent::DroppedFrames { dropped });
+            log(
+                "warning",
+                &[(
+                    "message",
+                    format!(
+                        "{} frames dropped, encoder can't keep up; lower resolution or bitrate",
+                        dropped
+                    ),
+                )],
+            );
+        }
+    }
+
+    fn check_temperature(&mut self) {
+        if let Some(level) = self.thermal.poll(Instant::now())
+            && level > ThermalLevel::Normal
+        {
+            let celsius = self.thermal.current().unwrap_or_default();
+            log(
+                "warning",
+                &[(
+                    "message",
+                    format!(
+                        "soc at {:.0} °C; lower resolution to avoid throttling",
+                        celsius
+                    ),
+                )],
+            );
+        }
+    }
+
+    fn check_battery(&mut self, config: &mut Config) {
+        if let Some(alert) = self.battery.poll(Instant::now()) {
+            let percent = self.battery.status().map_or(0, |s| s.percent);
+            let level = match alert {
+                BatteryAlert::Low => "warning",
+                BatteryAlert::Critical => "error",
+            };
+            log(level, &[("message", format!("battery at {}%", percent))]);
+        }
+        if self.recorder.is_recording() && self.battery.should_stop_recording() {
+            log(
+                "warning",
+                &[(
+                    "message",
+                    "battery critical, stopping recording".to_string(),
+                )],
+            );
+            self.recorder.stop(&mut config.slate);
+            self.sync_recording_state();
+        }
+    }
+
+    fn handle_event(&mut self, config: &Config, event: RecordEvent) {
+        if event.recording().is_some_and(|id| id != RecordingId::MAIN) {
+            return;
+        }
+        self.webhooks.notify(&event);
+        self.status.notify(&event);
+        self.recorder.handle_event(&event);
+        self.sync_recording_state();
+        match event {
+            RecordEvent::PreviewStarted => {
+                // 管线重建后也要重新设置
+                self.recorder.set_denoise(config.recording.denoise);
+                self.recorder.set_sharpen(config.recording.sharpen);
+                self.recorder.set_silence(config.silence);
+                log("preview", &[]);
+            }
+            RecordEvent::Progress { progress, .. } => self.progress = Some(progress),
+            RecordEvent::Started { path, .. } => {
+                self.drop_warned = false;
+                self.thermal.start_recording();
+                self.battery.start_recording();
+                self.progress = None;
+                log("started", &[("path", path.display().to_string())]);
+            }
+            RecordEvent::Stopped {
+                path,
+                duration,
+                size,
+                frames,
+                ..
+            } => {
+                self.progress = None;
+                log(
+                    "stopped",
+                    &[
+                        ("path", path.display().to_string()),
+                        ("duration_s", format!("{:.1}", duration.as_secs_f64())),
+                        ("size_bytes", size.to_string()),
+                        ("dropped_frames", frames.dropped.to_string()),
+                    ],
+                );
+                if let Some(range) = self.thermal.finish_recording()
+                    && let Err(e) = Sidecar::update(&path, |meta| meta.temperature = Some(range))
+                {
+                    log(
+                        "warning",
+                        &[("message", format!("could not save temperature: {}", e))],
+                    );
+                }
+                if let Some(power) = self.battery.finish_recording()
+                    && let Err(e) = Sidecar::update(&path, |meta| meta.power = Some(power))
+                {
+                    log(
+                        "warning",
+                        &[("message", format!("could not save power state: {}", e))],
+                    );
+                }
+                for target in config.offload.auto_targets() {
+                    self.uploader.enqueue(path.clone(), target);
+                }
+            }
+            RecordEvent::Verified { path, result } => {
+                let mut fields = vec![
+                    ("path", path.display().to_string()),
+                    ("ok", result.is_ok().to_string()),
+                ];
+                if let Err(e) = result {
+                    fields.push(("error", e));
+                }
+                log("verified", &fields);
+            }
+            RecordEvent::ScreenShared(area) => {
+                log("screen_shared", &[("area", format!("{:?}", area))])
+            }
+            RecordEvent::SourceSwitched(index) => {
+                log("source_switched", &[("source", (index + 1).to_string())])
+            }
+            RecordEvent::Bars(on) => log("bars", &[("on", on.to_string())]),
+            RecordEvent::Silence(silent) => log("silence", &[("silent", silent.to_string())]),
+            RecordEvent::WriteSpeed { change, rate, .. } => log(
+                "write_speed",
+                &[
+                    (
+                        "change",
+                        match change {
+                            WriteChange::Underrun { .. } => "underrun",
+                            WriteChange::Persistent => "persistent",
+                            WriteChange::Recovered => "recovered",
+                        }
+                        .to_string(),
+                    ),
+                    ("written_bytes_per_sec", rate.written_per_sec.to_string()),
+                    ("produced_bytes_per_sec", rate.produced_per_sec.to_string()),
+                ],
+            ),
+            RecordEvent::Snapshot(path) => log("snapshot", &[("path", path.display().to_string())]),
+            RecordEvent::Burst(paths) => log(
+                "burst",
+                &[
+                    ("count", paths.len().to_string()),
+                    (
+                        "path",
+                        paths
+                            .first()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_default(),
+                    ),
+                ],
+            ),
+            RecordEvent::GraphSaved(files) => log(
+                "graph_saved",
+                &[
+                    ("dot", files.dot.display().to_string()),
+                    (
+                        "png",
+                        files
+                            .png
+                            .as_ref()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_default(),
+                    ),
+                ],
+            ),
+            // 每秒一次, 不写入事件流
+            RecordEvent::Network(_) => {}
+            RecordEvent::Warning(w) => log("warning", &[("message", w)]),
+            RecordEvent::StartBlocked { reason, .. } => {
+                log("start_blocked", &[("reason", reason.to_string())])
+            }
+            RecordEvent::Error(e) => log("error", &[("message", e)]),
+        }
+    }
+}
+
+/// 输出一行 logfmt, 例如 `ts=... event=stopped path=/rec/a.mov size_bytes=1024`.
+fn log(event: &str, fields: &[(&str, String)]) {
+    let mut line = format!("ts={} event={}", chrono::Local::now().to_rfc3339(), event);
+    for (key, value) in fields {
+        // 含空格或引号的值加引号转义
+        if value.is_empty() || value.contains([' ', '"', '=']) {
+            let _ = write!(line, " {}={:?}", key, value);
+        } else {
+            let _ = write!(line, " {}={}", key, value);
+        }
+    }
+    println!("{}", line);
+}
@@ -0,0 +1,282 @@
+//! 界面文字的翻译. 每种语言是一个嵌入的 TOML 文件, 表名和键名用 `.` 连接成查找用的键,
+//! 当前语言缺少的键回退到英文.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "zh-CN")]
+    SimplifiedChinese,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::SimplifiedChinese];
+
+    /// 语言自己的名称, 在任何语言下都这样显示. 加载中文字体之前汉字显示为方框, 所以附上英文.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::SimplifiedChinese => "简体中文 (Chinese)",
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Language::English => include_str!("../locales/en.toml"),
+            Language::SimplifiedChinese => include_str!("../locales/zh-CN.toml"),
+        }
+    }
+}
+
+type Catalog = HashMap<String, String>;
+
+static CATALOGS: LazyLock<Vec<Catalog>> =
+    LazyLock::new(|| Language::ALL.iter().map(|l| parse(l.source())).collect());
+
+/// 当前语言在 [Language::ALL] 中的下标.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+fn parse(source: &str) -> Catalog {
+    let table: toml::Table = match toml::from_str(source) {
+        Ok(table) => table,
+        Err(e) => {
+            tracing::error!("could not parse language file: {}", e);
+            return Catalog::new();
+        }
+    };
+    let mut catalog = Catalog::new();
+    flatten("", &table, &mut catalog);
+    catalog
+}
+
+fn flatten(prefix: &str, table: &toml::Table, out: &mut Catalog) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            toml::Value::Table(table) => flatten(&key, table, out),
+            toml::Value::String(text) => {
+                out.insert(key, text.clone());
+            }
+            other => tracing::warn!(key, "ignoring non-string translation {:?}", other),
+        }
+    }
+}
+
+fn index(language: Language) -> usize {
+    Language::ALL
+        .iter()
+        .position(|l| *l == language)
+        .unwrap_or_default()
+}
+
+/// 立即生效, 下一帧起所有文字使用新语言.
+pub(crate) fn set_language(language: Language) {
+    CURRENT.store(index(language), Ordering::Relaxed);
+}
+
+/// 查找当前语言的文字. 两种语言都没有这个键时返回键本身, 这样漏掉的键一眼就能看出来.
+pub(crate) fn tr(key: &'static str) -> &'static str {
+    let catalogs = &*CATALOGS;
+    catalogs[CURRENT.load(Ordering::Relaxed)]
+        .get(key)
+        .or_else(|| catalogs[index(Language::English)].get(key))
+        .map_or(key, String::as_str)
+}
+
+/// 与 [tr] 相同, 再把 `{name}` 替换为参数.
+pub(crate) fn tr_args(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = tr(key).to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    /// 直接把文字显示在界面上的方法和构造函数.
+    const TEXT_CALLS: [&str; 13] = [
+        "label(",
+        "button(",
+        "small_button(",
+        "checkbox(",
+        "on_hover_text(",
+        "on_disabled_hover_text(",
+        "heading(",
+        "selectable_label(",
+        "colored_label(",
+        "radio_value(",
+        "Window::new(",
+        "Button::new(",
+        "RichText::new(",
+    ];
+    /// 各语言相同的文件格式名.
+    const UNTRANSLATED: [&str; 3] = ["GIF", "JPEG", "PNG"];
+
+    fn keys(language: Language) -> Vec<String> {
+        let mut keys: Vec<_> = parse(language.source()).into_keys().collect();
+        keys.sort();
+        keys
+    }
+
+    /// `dir` 下所有 .rs 文件的路径和内容.
+    fn sources(dir: &Path, out: &mut Vec<(PathBuf, String)>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                sources(&path, out);
+            } else if path.extension().is_some_and(|e| e == "rs") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                out.push((path, source));
+            }
+        }
+    }
+
+    /// 源码中调用 [tr] 和 [tr_args] 时写出的键.
+    fn used_keys(dir: &Path, out: &mut Vec<String>) {
+        let mut files = Vec::new();
+        sources(dir, &mut files);
+        for (_, source) in &files {
+            for call in ["tr(", "tr_args("] {
+                for (start, _) in source.match_indices(call) {
+                    // 跳过 attr( 之类的其他函数和这里的字符串
+                    let before = source[..start].chars().next_back();
+                    if before.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '"') {
+                        continue;
+                    }
+                    // rustfmt 可能把键换到下一行
+                    let rest = source[start + call.len()..].trim_start();
+                    if let Some(rest) = rest.strip_prefix('"')
+                        && let Some(end) = rest.find('"')
+                    {
+                        out.push(rest[..end].to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    /// 直接写在 `call` 参数中的英文字符串. 嵌在其他函数或宏里的不算,
+    /// 比如 [tr] 的键和 format! 中的单位.
+    fn literal_arguments(source: &str, call: &str) -> Vec<String> {
+        let bytes = source.as_bytes();
+        let mut found = Vec::new();
+        for (start, _) in source.match_indices(call) {
+            // 跳过 colored_label( 中的 label( 和 ImageButton::new( 之类
+            if !source[..start].ends_with(['.', ':']) {
+                continue;
+            }
+            // 每层括号是不是函数调用或宏
+            let mut nesting = Vec::new();
+            let mut i = start + call.len();
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'"' => {
+                        let mut end = i + 1;
+                        while bytes[end] != b'"' {
+                            end += if bytes[end] == b'\\' { 2 } else { 1 };
+                        }
+                        let text = &source[i + 1..end];
+                        if !nesting.contains(&true)
+                            && text.chars().any(|c| c.is_ascii_alphabetic())
+                            && !UNTRANSLATED.contains(&text)
+                        {
+                            found.push(text.to_string());
+                        }
+                        i = end;
+                    }
+                    b'(' => {
+                        let before = source[..i].trim_end().chars().next_back();
+                        nesting
+                            .push(before.is_some_and(|c| c.is_alphanumeric() || "_!>".contains(c)));
+                    }
+                    b'[' | b'{' => nesting.push(false),
+                    // 弹空时就是参数列表的右括号
+                    b')' | b']' | b'}' if nesting.pop().is_none() => break,
+                    _ => {}
+                }
+                i += 1;
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn every_language_has_every_key() {
+        let english = keys(Language::English);
+        assert!(!english.is_empty());
+        for language in Language::ALL {
+            let translated = keys(language);
+            let missing: Vec<_> = english.iter().filter(|k| !translated.contains(k)).collect();
+            let extra: Vec<_> = translated.iter().filter(|k| !english.contains(k)).collect();
+            assert!(
+                missing.is_empty(),
+                "{:?} is missing {:?}",
+                language,
+                missing
+            );
+            assert!(
+                extra.is_empty(),
+                "{:?} has unknown keys {:?}",
+                language,
+                extra
+            );
+        }
+    }
+
+    #[test]
+    fn every_used_key_is_translated() {
+        let mut used = Vec::new();
+        used_keys(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut used,
+        );
+        let english = keys(Language::English);
+        let missing: Vec<_> = used.iter().filter(|k| !english.contains(k)).collect();
+        assert!(missing.is_empty(), "keys without text: {:?}", missing);
+    }
+
+    #[test]
+    fn ui_text_goes_through_tr() {
+        let mut files = Vec::new();
+        sources(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src/ui"),
+            &mut files,
+        );
+        let mut literals = Vec::new();
+        for (path, source) in &files {
+            for call in TEXT_CALLS {
+                for text in literal_arguments(source, call) {
+                    literals.push(format!("{}: {:?}", path.display(), text));
+                }
+            }
+        }
+        assert!(literals.is_empty(), "text without tr: {:#?}", literals);
+    }
+
+    #[test]
+    fn falls_back_to_english_and_fills_arguments() {
+        set_language(Language::SimplifiedChinese);
+        let unknown = "no.such.key";
+        assert_eq!(tr(unknown), unknown);
+        set_language(Language::English);
+        assert_eq!(
+            tr_args("toast.recording", &[("path", &"/tmp/a.mov")]),
+            "Recording /tmp/a.mov"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use zbus::blocking::Connection;
+use zbus::zvariant::OwnedFd;
+
+/// 向系统申请阻止休眠/屏保的方式. 抽象出来便于测试.
+pub(crate) trait InhibitBackend {
+    /// 持有期间有效的锁, 交回 [InhibitBackend::release] 时释放.
+    type Lock;
+
+    fn acquire(&mut self, reason: &str) -> Result<Self::Lock, String>;
+    fn release(&mut self, lock: Self::Lock);
+}
+
+/// 录制期间阻止系统休眠和屏幕息屏.
+/// 申请失败只会警告, 不影响录制.
+pub(crate) struct SleepInhibitor<B: InhibitBackend = DbusBackend> {
+    /// 没有 DBus 时为 None, 所有操作都是空操作.
+    backend: Option<B>,
+    lock: Option<B::Lock>,
+    /// 本轮申请已经失败, 停止前不再重试.
+    failed: bool,
+}
+
+impl SleepInhibitor {
+    pub fn new() -> Self {
+        Self::with_backend(DbusBackend::connect())
+    }
+}
+
+impl<B: InhibitBackend> SleepInhibitor<B> {
+    pub fn with_backend(backend: Option<B>) -> Self {
+        Self {
+            backend,
+            lock: None,
+            failed: false,
+        }
+    }
+
+    pub fn is_inhibited(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    /// 每帧以当前是否需要阻止休眠调用, 只在状态变化时申请或释放.
+    /// 申请失败时返回错误, 每次录制最多返回一次.
+    pub fn set_active(&mut self, active: bool) -> Result<(), String> {
+        let Some(backend) = &mut self.backend else {
+            return Ok(());
+        };
+        if !active {
+            if let Some(lock) = self.lock.take() {
+                backend.release(lock);
+            }
+            self.failed = false;
+            return Ok(());
+        }
+        if self.lock.is_some() || self.failed {
+            return Ok(());
+        }
+        match backend.acquire("Recording video") {
+            Ok(lock) => {
+                self.lock = Some(lock);
+                Ok(())
+            }
+            Err(e) => {
+                self.failed = true;
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<B: InhibitBackend> Drop for SleepInhibitor<B> {
+    fn drop(&mut self) {
+        let _ = self.set_active(false);
+    }
+}
+
+/// systemd-logind (阻止休眠) 和 org.freedesktop.ScreenSaver (阻止息屏).
+/// 两者任意一个可用即可.
+pub(crate) struct DbusBackend {
+    system: Option<Connection>,
+    session: Option<Connection>,
+}
+
+/// logind 返回的文件描述符, 关闭即释放; 屏保接口返回的 cookie.
+pub(crate) struct DbusLock {
+    _logind: Option<OwnedFd>,
+    screensaver: Option<u32>,
+}
+
+impl DbusBackend {
+    /// 两条总线都连接不上时返回 None.
+    pub fn connect() -> Option<Self> {
+        let system = Connection::system()
+            .inspect_err(|e| tracing::info!("no system bus: {}", e))
+            .ok();
+        let session = Connection::session()
+            .inspect_err(|e| tracing::info!("no session bus: {}", e))
+            .ok();
+        (system.is_some() || session.is_some()).then_some(Self { system, session })
+    }
+}
+
+impl InhibitBackend for DbusBackend {
+    type Lock = DbusLock;
+
+    fn acquire(&mut self, reason: &str) -> Result<DbusLock, String> {
+        let mut errors = Vec::new();
+        let logind = self.system.as_ref().and_then(|conn| {
+            conn.call_method(
+                Some("org.freedesktop.login1"),
+                "/org/freedesktop/login1",
+                Some("org.freedesktop.login1.Manager"),
+                "Inhibit",
+                &("sleep:idle", "cam-ui", reason, "block"),
+            )
+            .and_then(|reply| reply.body().deserialize::<OwnedFd>())
+            .inspect_err(|e| errors.push(format!("logind: {}", e)))
+            .ok()
+        });
+        let screensaver = self.session.as_ref().and_then(|conn| {
+            conn.call_method(
+                Some("org.freedesktop.ScreenSaver"),
+                "/org/freedesktop/ScreenSaver",
+                Some("org.freedesktop.ScreenSaver"),
+                "Inhibit",
+                &("cam-ui", reason),
+            )
+            .and_then(|reply| reply.body().deserialize::<u32>())
+            .inspect_err(|e| errors.push(format!("screensaver: {}", e)))
+            .ok()
+        });
+        if logind.is_none() && screensaver.is_none() {
+            return Err(errors.join("; "));
+        }
+        Ok(DbusLock {
+            _logind: logind,
+            screensaver,
+        })
+    }
+
+    fn release(&mut self, lock: DbusLock) {
+        if let (Some(cookie), Some(conn)) = (lock.screensaver, &self.session)
+            && let Err(e) = conn.call_method(
+                Some("org.freedesktop.ScreenSaver"),
+                "/org/freedesktop/ScreenSaver",
+                Some("org.freedesktop.ScreenSaver"),
+                "UnInhibit",
+                &(cookie,),
+            )
+        {
+            tracing::warn!("could not release screensaver inhibit: {}", e);
+        }
+        // logind 的锁随文件描述符一起释放
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// 记录调用顺序的假 DBus 接口.
+    #[derive(Default)]
+    struct Calls {
+        acquired: u32,
+        released: Vec<u32>,
+        fail: bool,
+    }
+
+    struct MockBackend(Rc<RefCell<Calls>>);
+
+    impl InhibitBackend for MockBackend {
+        type Lock = u32;
+
+        fn acquire(&mut self, _reason: &str) -> Result<u32, String> {
+            let mut calls = self.0.borrow_mut();
+            if calls.fail {
+                return Err("org.freedesktop.DBus.Error.ServiceUnknown".to_string());
+            }
+            calls.acquired += 1;
+            Ok(calls.acquired)
+        }
+
+        fn release(&mut self, lock: u32) {
+            self.0.borrow_mut().released.push(lock);
+        }
+    }
+
+    fn inhibitor() -> (SleepInhibitor<MockBackend>, Rc<RefCell<Calls>>) {
+        let calls = Rc::new(RefCell::new(Calls::default()));
+        let inhibitor = SleepInhibitor::with_backend(Some(MockBackend(calls.clone())));
+        (inhibitor, calls)
+    }
+
+    #[test]
+    fn acquires_once_and_releases_the_same_lock() {
+        let (mut inhibitor, calls) = inhibitor();
+        for _ in 0..3 {
+            inhibitor.set_active(true).unwrap();
+        }
+        assert!(inhibitor.is_inhibited());
+        assert_eq!(calls.borrow().acquired, 1);
+
+        inhibitor.set_active(false).unwrap();
+        inhibitor.set_active(false).unwrap();
+        assert!(!inhibitor.is_inhibited());
+        assert_eq!(calls.borrow().released, vec![1]);
+
+        inhibitor.set_active(true).unwrap();
+        inhibitor.set_active(false).unwrap();
+        assert_eq!(calls.borrow().released, vec![1, 2]);
+    }
+
+    #[test]
+    fn releases_on_drop() {
+        let (mut inhibitor, calls) = inhibitor();
+        inhibitor.set_active(true).unwrap();
+        drop(inhibitor);
+        assert_eq!(calls.borrow().released, vec![1]);
+    }
+
+    #[test]
+    fn failure_is_reported_once_per_recording() {
+        let (mut inhibitor, calls) = inhibitor();
+        calls.borrow_mut().fail = true;
+        assert!(inhibitor.set_active(true).is_err());
+        assert!(inhibitor.set_active(true).is_ok());
+        assert!(!inhibitor.is_inhibited());
+
+        // 停止后释放不到任何锁, 下一次录制重新申请
+        inhibitor.set_active(false).unwrap();
+        assert!(calls.borrow().released.is_empty());
+        calls.borrow_mut().fail = false;
+        inhibitor.set_active(true).unwrap();
+        assert!(inhibitor.is_inhibited());
+    }
+
+    #[test]
+    fn without_dbus_everything_is_a_no_op() {
+        let mut inhibitor = SleepInhibitor::<MockBackend>::with_backend(None);
+        assert!(inhibitor.set_active(true).is_ok());
+        assert!(!inhibitor.is_inhibited());
+        assert!(inhibitor.set_active(false).is_ok());
+    }
+}
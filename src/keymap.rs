@@ -0,0 +1,605 @@
+//! 可以重新绑定的快捷键. 外接的控制台只能发送固定的按键, 所以每个操作都可以改键.
+//! [Action] 的名称是稳定的, 远程控制也用它表示操作.
+
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::i18n::{tr, tr_args};
+
+/// 可以绑定快捷键的操作. 只在末尾追加, 名称一旦发布就不再修改.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Action {
+    ToggleRecord,
+    Snapshot,
+    PunchIn,
+    SwapPip,
+    Multiview,
+    ProgramOutput,
+    Browser,
+    Console,
+    NightTheme,
+    LockScreen,
+    AeLock,
+    IsoUp,
+    IsoDown,
+    ShutterFaster,
+    ShutterSlower,
+    ZoomIn,
+    ZoomOut,
+    Profile1,
+    Profile2,
+    Profile3,
+    Profile4,
+    Profile5,
+    Profile6,
+    Profile7,
+    Profile8,
+    Profile9,
+    Source1,
+    Source2,
+    Source3,
+    Source4,
+    Fullscreen,
+    Reference,
+    Burst,
+    CopyFrame,
+    Marker,
+    Freeze,
+    Bars,
+    DumpGraph,
+}
+
+const PROFILES: [Action; 9] = [
+    Action::Profile1,
+    Action::Profile2,
+    Action::Profile3,
+    Action::Profile4,
+    Action::Profile5,
+    Action::Profile6,
+    Action::Profile7,
+    Action::Profile8,
+    Action::Profile9,
+];
+
+const SOURCES: [Action; 4] = [
+    Action::Source1,
+    Action::Source2,
+    Action::Source3,
+    Action::Source4,
+];
+
+impl Action {
+    pub const ALL: [Action; 38] = [
+        Action::ToggleRecord,
+        Action::Snapshot,
+        Action::PunchIn,
+        Action::SwapPip,
+        Action::Multiview,
+        Action::ProgramOutput,
+        Action::Browser,
+        Action::Console,
+        Action::NightTheme,
+        Action::LockScreen,
+        Action::AeLock,
+        Action::IsoUp,
+        Action::IsoDown,
+        Action::ShutterFaster,
+        Action::ShutterSlower,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::Profile1,
+        Action::Profile2,
+        Action::Profile3,
+        Action::Profile4,
+        Action::Profile5,
+        Action::Profile6,
+        Action::Profile7,
+        Action::Profile8,
+        Action::Profile9,
+        Action::Source1,
+        Action::Source2,
+        Action::Source3,
+        Action::Source4,
+        Action::Fullscreen,
+        Action::Reference,
+        Action::Burst,
+        Action::CopyFrame,
+        Action::Marker,
+        Action::Freeze,
+        Action::Bars,
+        Action::DumpGraph,
+    ];
+
+    /// 配置文件和远程控制中使用的名称.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::ToggleRecord => "toggle-record",
+            Action::Snapshot => "snapshot",
+            Action::PunchIn => "punch-in",
+            Action::SwapPip => "swap-pip",
+            Action::Multiview => "multiview",
+            Action::ProgramOutput => "program-output",
+            Action::Browser => "browser",
+            Action::Console => "console",
+            Action::NightTheme => "night-theme",
+            Action::LockScreen => "lock-screen",
+            Action::AeLock => "ae-lock",
+            Action::IsoUp => "iso-up",
+            Action::IsoDown => "iso-down",
+            Action::ShutterFaster => "shutter-faster",
+            Action::ShutterSlower => "shutter-slower",
+            Action::ZoomIn => "zoom-in",
+            Action::ZoomOut => "zoom-out",
+            Action::Profile1 => "profile-1",
+            Action::Profile2 => "profile-2",
+            Action::Profile3 => "profile-3",
+            Action::Profile4 => "profile-4",
+            Action::Profile5 => "profile-5",
+            Action::Profile6 => "profile-6",
+            Action::Profile7 => "profile-7",
+            Action::Profile8 => "profile-8",
+            Action::Profile9 => "profile-9",
+            Action::Source1 => "source-1",
+            Action::Source2 => "source-2",
+            Action::Source3 => "source-3",
+            Action::Source4 => "source-4",
+            Action::Fullscreen => "fullscreen",
+            Action::Reference => "reference",
+            Action::Burst => "burst",
+            Action::CopyFrame => "copy-frame",
+            Action::Marker => "marker",
+            Action::Freeze => "freeze",
+            Action::Bars => "bars",
+            Action::DumpGraph => "dump-graph",
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Action::ToggleRecord => tr("action.toggle_record").to_string(),
+            Action::Snapshot => tr("action.snapshot").to_string(),
+            Action::PunchIn => tr("action.punch_in").to_string(),
+            Action::SwapPip => tr("action.swap_pip").to_string(),
+            Action::Multiview => tr("action.multiview").to_string(),
+            Action::ProgramOutput => tr("action.program_output").to_string(),
+            Action::Browser => tr("action.browser").to_string(),
+            Action::Console => tr("action.console").to_string(),
+            Action::NightTheme => tr("action.night_theme").to_string(),
+            Action::LockScreen => tr("action.lock_screen").to_string(),
+            Action::AeLock => tr("action.ae_lock").to_string(),
+            Action::IsoUp => tr("action.iso_up").to_string(),
+            Action::IsoDown => tr("action.iso_down").to_string(),
+            Action::ShutterFaster => tr("action.shutter_faster").to_string(),
+            Action::ShutterSlower => tr("action.shutter_slower").to_string(),
+            Action::ZoomIn => tr("action.zoom_in").to_string(),
+            Action::ZoomOut => tr("action.zoom_out").to_string(),
+            Action::Fullscreen => tr("action.fullscreen").to_string(),
+            Action::Reference => tr("action.reference").to_string(),
+            Action::Burst => tr("action.burst").to_string(),
+            Action::CopyFrame => tr("action.copy_frame").to_string(),
+            Action::Marker => tr("action.marker").to_string(),
+            Action::Freeze => tr("action.freeze").to_string(),
+            Action::Bars => tr("action.bars").to_string(),
+            Action::DumpGraph => tr("action.dump_graph").to_string(),
+            _ => match (self.profile_index(), self.source_index()) {
+                (Some(index), _) => tr_args("action.profile", &[("n", &(index + 1))]),
+                (_, Some(index)) => tr_args("action.source", &[("n", &(index + 1))]),
+                _ => self.name().to_string(),
+            },
+        }
+    }
+
+    /// 切换到第 `index` 个预设的操作, 只有前 9 个有.
+    pub fn profile(index: usize) -> Option<Action> {
+        PROFILES.get(index).copied()
+    }
+
+    pub fn profile_index(self) -> Option<usize> {
+        PROFILES.iter().position(|a| *a == self)
+    }
+
+    /// 切到第 `index` 路采集源的操作, 只有前 4 路有.
+    pub fn source(index: usize) -> Option<Action> {
+        SOURCES.get(index).copied()
+    }
+
+    pub fn source_index(self) -> Option<usize> {
+        SOURCES.iter().position(|a| *a == self)
+    }
+
+    fn default_shortcut(self) -> Option<KeyboardShortcut> {
+        let plain = |key| Some(KeyboardShortcut::new(Modifiers::NONE, key));
+        let command = |key| Some(KeyboardShortcut::new(Modifiers::COMMAND, key));
+        let shift = |key| Some(KeyboardShortcut::new(Modifiers::SHIFT, key));
+        match self {
+            Action::ToggleRecord => plain(Key::R),
+            Action::Snapshot => plain(Key::S),
+            Action::PunchIn => plain(Key::Z),
+            Action::SwapPip => plain(Key::P),
+            Action::Multiview => plain(Key::M),
+            Action::ProgramOutput => plain(Key::O),
+            Action::Browser => plain(Key::B),
+            Action::Console => plain(Key::Backtick),
+            Action::NightTheme => plain(Key::N),
+            Action::LockScreen => command(Key::L),
+            Action::AeLock => plain(Key::L),
+            Action::IsoUp => plain(Key::Plus),
+            Action::IsoDown => plain(Key::Minus),
+            Action::ShutterFaster => plain(Key::CloseBracket),
+            Action::ShutterSlower => plain(Key::OpenBracket),
+            Action::ZoomIn => command(Key::Plus),
+            Action::ZoomOut => command(Key::Minus),
+            Action::Profile1 => plain(Key::Num1),
+            Action::Profile2 => plain(Key::Num2),
+            Action::Profile3 => plain(Key::Num3),
+            Action::Profile4 => plain(Key::Num4),
+            Action::Profile5 => plain(Key::Num5),
+            Action::Profile6 => plain(Key::Num6),
+            Action::Profile7 => plain(Key::Num7),
+            Action::Profile8 => plain(Key::Num8),
+            Action::Profile9 => plain(Key::Num9),
+            Action::Source1 => plain(Key::F1),
+            Action::Source2 => plain(Key::F2),
+            Action::Source3 => plain(Key::F3),
+            Action::Source4 => plain(Key::F4),
+            Action::Fullscreen => plain(Key::F11),
+            Action::Reference => plain(Key::G),
+            Action::Burst => shift(Key::S),
+            Action::CopyFrame => command(Key::C),
+            // M 已经用于多画面
+            Action::Marker => shift(Key::M),
+            Action::Freeze => plain(Key::Space),
+            // B 已经用于文件浏览器
+            Action::Bars => shift(Key::B),
+            Action::DumpGraph => command(Key::D),
+        }
+    }
+}
+
+impl Action {
+    /// 用默认快捷键时一起生效的第二个键. 大多数布局上 + 要按 Shift, 所以 = 也能升 ISO.
+    fn alternate_shortcut(self) -> Option<KeyboardShortcut> {
+        match self {
+            Action::IsoUp => Some(KeyboardShortcut::new(Modifiers::NONE, Key::Equals)),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|a| a.name() == s)
+            .ok_or_else(|| format!("unknown action '{}'", s))
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 与 [Action::name] 相同, 其他配置 (例如手柄映射) 引用操作时使用.
+impl Serialize for Action {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// 例如 "Ctrl+Shift+R". Ctrl 在 macOS 上是 Cmd.
+pub(crate) fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
+    let m = shortcut.modifiers;
+    let mut parts = Vec::new();
+    if m.command || m.ctrl {
+        parts.push("Ctrl");
+    }
+    if m.alt {
+        parts.push("Alt");
+    }
+    if m.shift {
+        parts.push("Shift");
+    }
+    parts.push(shortcut.logical_key.name());
+    parts.join("+")
+}
+
+pub(crate) fn parse_shortcut(text: &str) -> Result<KeyboardShortcut, String> {
+    let mut modifiers = Modifiers::NONE;
+    let mut key = None;
+    for part in text.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => modifiers |= Modifiers::COMMAND,
+            "alt" => modifiers |= Modifiers::ALT,
+            "shift" => modifiers |= Modifiers::SHIFT,
+            _ if key.is_none() => {
+                key = Some(Key::from_name(part).ok_or_else(|| format!("unknown key '{}'", part))?)
+            }
+            _ => return Err(format!("more than one key in '{}'", text)),
+        }
+    }
+    let key = key.ok_or_else(|| format!("no key in '{}'", text))?;
+    Ok(KeyboardShortcut::new(modifiers, key))
+}
+
+/// egui 把 Ctrl+C 和 Ctrl+X 转换为剪贴板事件, 不再产生按键, 这两个快捷键改为匹配事件.
+fn consume(input: &mut egui::InputState, shortcut: &KeyboardShortcut) -> bool {
+    if input.consume_shortcut(shortcut) {
+        return true;
+    }
+    let event = match shortcut.logical_key {
+        Key::C => egui::Event::Copy,
+        Key::X => egui::Event::Cut,
+        _ => return false,
+    };
+    if shortcut.modifiers != Modifiers::COMMAND {
+        return false;
+    }
+    match input.events.iter().position(|e| *e == event) {
+        Some(index) => {
+            input.events.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// 没有 Ctrl 或 Alt 时, 文字输入框自己会用到的键, 例如字母, 数字和方向键.
+/// 输入框有焦点时这些快捷键不生效.
+pub(crate) fn reserved_for_text(shortcut: &KeyboardShortcut) -> bool {
+    let m = shortcut.modifiers;
+    if m.command || m.ctrl {
+        // 复制, 粘贴, 全选和撤销
+        return matches!(
+            shortcut.logical_key,
+            Key::A | Key::C | Key::V | Key::X | Key::Y | Key::Z
+        );
+    }
+    if m.alt {
+        return false;
+    }
+    !matches!(
+        shortcut.logical_key,
+        Key::F1
+            | Key::F2
+            | Key::F3
+            | Key::F4
+            | Key::F5
+            | Key::F6
+            | Key::F7
+            | Key::F8
+            | Key::F9
+            | Key::F10
+            | Key::F11
+            | Key::F12
+            | Key::PageUp
+            | Key::PageDown
+            | Key::Insert
+    )
+}
+
+/// 操作到快捷键的映射. 配置中没有写的操作使用默认快捷键, 写成空字符串表示不绑定.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Keymap {
+    bindings: BTreeMap<Action, Option<KeyboardShortcut>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .into_iter()
+                .map(|a| (a, a.default_shortcut()))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn get(&self, action: Action) -> Option<KeyboardShortcut> {
+        self.bindings.get(&action).copied().flatten()
+    }
+
+    pub fn set(&mut self, action: Action, shortcut: Option<KeyboardShortcut>) {
+        self.bindings.insert(action, shortcut);
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        self.set(action, action.default_shortcut());
+    }
+
+    /// 触发 `action` 的所有快捷键. 改过快捷键后第二个键不再生效.
+    fn shortcuts(&self, action: Action) -> impl Iterator<Item = KeyboardShortcut> {
+        let primary = self.get(action);
+        let alternate = action
+            .alternate_shortcut()
+            .filter(|_| primary.is_some() && primary == action.default_shortcut());
+        primary.into_iter().chain(alternate)
+    }
+
+    /// 与 `action` 使用相同快捷键的其他操作.
+    pub fn conflicts(&self, action: Action) -> Vec<Action> {
+        let mine: Vec<_> = self.shortcuts(action).collect();
+        Action::ALL
+            .into_iter()
+            .filter(|a| *a != action && self.shortcuts(*a).any(|s| mine.contains(&s)))
+            .collect()
+    }
+
+    /// 按下快捷键时的操作. 修饰键多的先匹配, 这样 Ctrl+L 不会同时触发 L.
+    pub fn pressed(&self, input: &mut egui::InputState, typing: bool) -> Vec<Action> {
+        let mut bound: Vec<_> = Action::ALL
+            .into_iter()
+            .flat_map(|a| self.shortcuts(a).map(move |s| (a, s)))
+            .filter(|(_, s)| !typing || !reserved_for_text(s))
+            .collect();
+        bound.sort_by_key(|(_, s)| {
+            let m = s.modifiers;
+            std::cmp::Reverse(m.command as u8 + m.ctrl as u8 + m.alt as u8 + m.shift as u8)
+        });
+        bound
+            .into_iter()
+            .filter(|(_, s)| consume(input, s))
+            .map(|(a, _)| a)
+            .collect()
+    }
+}
+
+impl Serialize for Keymap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let map: BTreeMap<_, _> = self
+            .bindings
+            .iter()
+            .map(|(a, s)| {
+                (
+                    a.name(),
+                    s.as_ref().map(format_shortcut).unwrap_or_default(),
+                )
+            })
+            .collect();
+        map.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = BTreeMap::<String, String>::deserialize(deserializer)?;
+        let mut keymap = Keymap::default();
+        for (name, text) in map {
+            // 无法识别的条目只记录, 不让整个配置加载失败
+            let action = match name.parse::<Action>() {
+                Ok(action) => action,
+                Err(e) => {
+                    tracing::warn!("ignoring keymap entry: {}", e);
+                    continue;
+                }
+            };
+            if text.trim().is_empty() {
+                keymap.set(action, None);
+                continue;
+            }
+            match parse_shortcut(&text) {
+                Ok(shortcut) => keymap.set(action, Some(shortcut)),
+                Err(e) => tracing::warn!(%action, "ignoring shortcut: {}", e),
+            }
+        }
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcuts_round_trip() {
+        for action in Action::ALL {
+            let shortcut = action.default_shortcut().unwrap();
+            assert_eq!(parse_shortcut(&format_shortcut(&shortcut)), Ok(shortcut));
+            assert_eq!(action.name().parse(), Ok(action));
+        }
+        assert_eq!(
+            parse_shortcut("ctrl + shift + F5"),
+            Ok(KeyboardShortcut::new(
+                Modifiers::COMMAND | Modifiers::SHIFT,
+                Key::F5
+            ))
+        );
+        assert!(parse_shortcut("Ctrl").is_err());
+        assert!(parse_shortcut("R+S").is_err());
+    }
+
+    #[test]
+    fn defaults_do_not_conflict() {
+        let keymap = Keymap::default();
+        for action in Action::ALL {
+            assert!(keymap.conflicts(action).is_empty(), "{}", action);
+        }
+        let mut keymap = keymap;
+        keymap.set(Action::Snapshot, keymap.get(Action::ToggleRecord));
+        assert_eq!(keymap.conflicts(Action::ToggleRecord), [Action::Snapshot]);
+    }
+
+    #[test]
+    fn text_keys_are_reserved() {
+        let plain = |key| KeyboardShortcut::new(Modifiers::NONE, key);
+        assert!(reserved_for_text(&plain(Key::R)));
+        assert!(reserved_for_text(&plain(Key::Backspace)));
+        assert!(!reserved_for_text(&plain(Key::F1)));
+        assert!(!reserved_for_text(&KeyboardShortcut::new(
+            Modifiers::COMMAND,
+            Key::L
+        )));
+        assert!(reserved_for_text(&KeyboardShortcut::new(
+            Modifiers::COMMAND,
+            Key::V
+        )));
+    }
+
+    #[test]
+    fn copy_event_counts_as_ctrl_c() {
+        let keymap = Keymap::default();
+        let mut input = egui::InputState::default();
+        input.events.push(egui::Event::Copy);
+        // 输入框有焦点时留给输入框
+        assert!(keymap.pressed(&mut input, true).is_empty());
+        assert_eq!(keymap.pressed(&mut input, false), [Action::CopyFrame]);
+        assert!(input.events.is_empty());
+    }
+
+    #[test]
+    fn equals_raises_iso_until_remapped() {
+        let press = |keymap: &Keymap, key| {
+            let mut input = egui::InputState::default();
+            input.events.push(egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: Modifiers::NONE,
+            });
+            keymap.pressed(&mut input, false)
+        };
+        let mut keymap = Keymap::default();
+        assert_eq!(press(&keymap, Key::Equals), [Action::IsoUp]);
+        assert_eq!(press(&keymap, Key::Plus), [Action::IsoUp]);
+        keymap.set(
+            Action::IsoUp,
+            Some(KeyboardShortcut::new(Modifiers::NONE, Key::U)),
+        );
+        assert!(press(&keymap, Key::Equals).is_empty());
+    }
+
+    #[test]
+    fn missing_entries_keep_their_defaults() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            keymap: Keymap,
+        }
+        let text = "[keymap]\ntoggle-record = \"F9\"\nsnapshot = \"\"\nno-such-action = \"X\"\n";
+        let keymap = toml::from_str::<Wrapper>(text).unwrap().keymap;
+        assert_eq!(
+            keymap.get(Action::ToggleRecord),
+            Some(KeyboardShortcut::new(Modifiers::NONE, Key::F9))
+        );
+        assert_eq!(keymap.get(Action::Snapshot), None);
+        assert_eq!(
+            keymap.get(Action::PunchIn),
+            Action::PunchIn.default_shortcut()
+        );
+    }
+}
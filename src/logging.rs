@@ -0,0 +1,191 @@
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::field::{Field, Visit};
+use tracing::{Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry, reload};
+
+/// 界面日志面板保留的条数.
+const CONSOLE_CAPACITY: usize = 500;
+/// 日志文件按天轮换, 保留最近几天.
+const MAX_LOG_FILES: usize = 7;
+
+/// 设置面板中可选的日志级别.
+pub(crate) const LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+/// 日志面板中的一条记录.
+#[derive(Debug, Clone)]
+pub(crate) struct LogEntry {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub level: Level,
+    /// 所在的 span, 例如 "recording{path=/rec/a.mov}".
+    pub spans: String,
+    pub message: String,
+}
+
+/// 日志面板的数据源, 同时负责运行时修改日志级别.
+#[derive(Clone)]
+pub(crate) struct LogConsole {
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+    filter: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogConsole {
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.entries.lock().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// 只调整本程序的级别, 依赖库固定为 warn.
+    pub fn set_level(&self, level: &str) {
+        if let Err(e) = self.filter.reload(filter_for(level)) {
+            tracing::warn!("could not change log level: {}", e);
+        }
+    }
+}
+
+fn filter_for(level: &str) -> EnvFilter {
+    EnvFilter::new(format!("warn,cam_ui={}", level))
+}
+
+/// 安装全局日志: 标准错误, `output_dir` 下按天轮换的 `cam-ui.<日期>.log`, 以及界面面板.
+/// 设置了 `RUST_LOG` 时以它为准, 否则使用 `level`.
+/// 返回的 guard 需要保持到进程退出, 否则文件中最后的日志会丢失.
+pub(crate) fn init(output_dir: &Path, level: &str) -> (LogConsole, Option<WorkerGuard>) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| filter_for(level));
+    let (filter, filter_handle) = reload::Layer::new(filter);
+
+    let (file_layer, guard) = match file_appender(output_dir) {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer);
+            (Some(layer), Some(guard))
+        }
+        Err(e) => {
+            eprintln!("Log File Error: {}", e);
+            (None, None)
+        }
+    };
+
+    let entries = Arc::new(Mutex::new(VecDeque::with_capacity(CONSOLE_CAPACITY)));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .with(ConsoleLayer {
+            entries: entries.clone(),
+        })
+        .init();
+
+    let console = LogConsole {
+        entries,
+        filter: filter_handle,
+    };
+    (console, guard)
+}
+
+fn file_appender(dir: &Path) -> Result<RollingFileAppender, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("cam-ui")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)
+        .map_err(|e| e.to_string())
+}
+
+/// 把事件写入环形缓冲区, 供界面显示.
+struct ConsoleLayer {
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+}
+
+/// span 创建时格式化好的字段, 存在 span 的扩展数据里.
+struct SpanFields(String);
+
+impl<S> Layer<S> for ConsoleLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::span::Id,
+        ctx: Context<'_, S>,
+    ) {
+        let mut fields = FieldWriter::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields.fields));
+        }
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        let mut spans = String::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if !spans.is_empty() {
+                    spans.push(':');
+                }
+                spans.push_str(span.name());
+                if let Some(fields) = span.extensions().get::<SpanFields>()
+                    && !fields.0.is_empty()
+                {
+                    let _ = write!(spans, "{{{}}}", fields.0.trim_start());
+                }
+            }
+        }
+
+        let mut fields = FieldWriter::default();
+        event.record(&mut fields);
+        let entry = LogEntry {
+            time: chrono::Local::now(),
+            level: *event.metadata().level(),
+            spans,
+            message: fields.message + &fields.fields,
+        };
+
+        let mut entries = self.entries.lock();
+        if entries.len() == CONSOLE_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+/// 把 `message` 和其他字段 (`key=value`) 分开格式化.
+#[derive(Default)]
+struct FieldWriter {
+    message: String,
+    fields: String,
+}
+
+impl Visit for FieldWriter {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
@@ -1,49 +1,175 @@
+mod battery;
+mod cli;
+mod config;
+mod crash;
 mod file;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+#[cfg(feature = "gpio")]
+mod gpio;
+mod headless;
+mod i18n;
 mod icons;
+mod inhibit;
+mod keymap;
+mod logging;
+#[cfg(feature = "midi")]
+mod midi;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod offload;
+mod osc;
+mod recorder;
+mod resources;
+mod signals;
+mod status;
+mod thermal;
 mod ui;
 mod video;
+mod webhook;
 
+use clap::Parser;
 use eframe::egui;
 use parking_lot::Mutex;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
 fn main() -> eframe::Result {
+    let args = cli::Args::parse();
+
     // 1. 初始化 GStreamer
     gstreamer::init().expect("GStreamer init failed");
 
-    // 2. 创建共享图像缓冲区 (RGBA)
-    let frame_buffer = Arc::new(Mutex::new(None));
+    if args.list_devices {
+        cli::print_devices();
+        return Ok(());
+    }
+
+    // 加载保存的设置并应用命令行参数, 采集源需要在启动 gst 线程前确定
+    let path = args.config.clone().or_else(config::config_path);
+    let (persisted, warning) = config::Config::load(path.as_deref());
+    let mut config = persisted.clone();
+    let overrides = args
+        .overrides()
+        .and_then(|o| o.apply(&mut config).map(|()| o))
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        });
+
+    // 日志文件写在输出目录中, 需要在加载配置之后初始化
+    let (log_console, _log_guard) = logging::init(&config.recording.output_dir, &config.log_level);
+    if let Some(message) = &warning {
+        tracing::warn!("{}", message);
+    }
+    let loaded = config::LoadedConfig {
+        config,
+        persisted,
+        path,
+        overrides,
+        warning,
+    };
+
+    if args.benchmark {
+        if !cli::run_benchmark(&loaded.config.recording) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if args.headless {
+        if let Err(e) = headless::run(loaded, args.record) {
+            tracing::error!("headless mode failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // 2. 创建预览帧的三缓冲, 采集线程写入时不用等界面
+    let frame_buffer = video::exchange::FrameExchange::default();
+    // 多画面的缩略图和画中画
+    let monitors = video::MonitorFrames::default();
 
     // 音频电平，通常为 [-60, 0]
     let audio_level = Arc::new(Mutex::new(-60.0f32));
+    // 本次录制的 QoS 统计
+    let buffer_stats = Arc::new(Mutex::new(Default::default()));
+
+    // 预览叠加层的设置, 界面修改后下一帧生效
+    let overlay =
+        video::overlay::SharedOverlay::new(loaded.config.overlay.clone(), audio_level.clone());
 
     // 3. 创建录制指令通道
     // 使用 unbounded_channel 因为指令频率低，且不希望 UI 线程被阻塞
     let (rec_cmd_tx, rec_cmd_rx) = mpsc::unbounded_channel();
+    // 录制事件通道 (gst 线程 -> UI)
+    let (rec_event_tx, rec_event_rx) = mpsc::unbounded_channel();
+
+    // panic 时先封装正在录制的文件, 并写崩溃日志
+    crash::install_panic_hook(
+        rec_cmd_tx.downgrade(),
+        loaded.config.recording.output_dir.clone(),
+    );
 
     // 4. 启动视频采集线程
-    video::spawn_gst_thread(frame_buffer.clone(), audio_level.clone(), rec_cmd_rx);
+    let gst_thread = video::spawn_gst_thread(
+        loaded.config.capture.sources(),
+        frame_buffer.clone(),
+        monitors.clone(),
+        overlay.clone(),
+        audio_level.clone(),
+        buffer_stats.clone(),
+        rec_cmd_rx,
+        rec_event_tx,
+    );
 
     // 5. 运行 egui
+    let window = &loaded.config.window;
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_fullscreen(args.fullscreen || args.kiosk || window.fullscreen)
+        .with_inner_size([1280.0, 720.0])
+        // kiosk 模式下只能通过退出确认关闭
+        .with_close_button(!args.kiosk);
+    // 全屏时使用窗口所在的显示器
+    if let Some(position) = window.position {
+        viewport = viewport.with_position(position);
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            // .with_fullscreen(true) // Kiosk 模式通常全屏
-            .with_inner_size([1280.0, 720.0]),
+        viewport,
         ..Default::default()
     };
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "Professional Camera",
         options,
         Box::new(|cc| {
             // 启用内建的 SVG 支持
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(ui::CameraApp::new(
+            // Ctrl +/- 用于数字变焦, 不缩放界面
+            cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+            let mut app = ui::CameraApp::new(
+                loaded,
                 frame_buffer,
+                monitors,
+                overlay,
                 audio_level,
+                buffer_stats,
                 rec_cmd_tx,
-            )))
+                rec_event_rx,
+                log_console,
+            );
+            app.apply_theme(&cc.egui_ctx);
+            app.apply_language(&cc.egui_ctx);
+            if args.kiosk {
+                app.enable_kiosk();
+            }
+            // Ctrl-C / systemd stop 时同样等待录像封装完成
+            app.watch_signals(cc.egui_ctx.clone());
+            Ok(Box::new(app))
         }),
-    )
+    );
+
+    // 退出时 CameraApp 发出 Shutdown; 等 gst 线程完成录像封装并停止管线后再退出进程
+    gst_thread.join(video::SHUTDOWN_TIMEOUT);
+    result
 }
@@ -0,0 +1,389 @@
+//! MIDI 控制台输入 (`midi` 功能). 推子和旋钮 (CC) 调节连续参数, 按键 (音符或 CC) 触发 [Action].
+//! midir 在自己的线程里回调, 这里每个 CC 只记最新值, 界面每帧取走, 快速推动推子时不会积压.
+
+use midir::{MidiInput, MidiInputConnection};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::i18n::{tr, tr_args};
+use crate::keymap::Action;
+
+const CLIENT_NAME: &str = "cam-ui";
+/// 检查端口列表的间隔. midir 不报告设备拔出, 只能定期检查.
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// 两帧之间最多保留的按键, 多余的丢弃.
+const MAX_PRESSES: usize = 32;
+/// 按键式 CC 的值越过这里算作按下.
+const CC_PRESSED: u8 = 64;
+
+/// 一个 MIDI 控件. 通道从 0 开始, 显示时加 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub(crate) enum MidiControl {
+    Cc { channel: u8, number: u8 },
+    Note { channel: u8, number: u8 },
+}
+
+impl MidiControl {
+    pub fn label(self) -> String {
+        match self {
+            MidiControl::Cc { channel, number } => tr_args(
+                "settings.midi.cc",
+                &[("number", &number), ("channel", &(channel + 1))],
+            ),
+            MidiControl::Note { channel, number } => tr_args(
+                "settings.midi.note",
+                &[("number", &number), ("channel", &(channel + 1))],
+            ),
+        }
+    }
+}
+
+/// 可以用推子或旋钮调节的参数.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum MidiParam {
+    AudioGain,
+    PreviewBrightness,
+    Zoom,
+    Focus,
+}
+
+impl MidiParam {
+    pub const ALL: [MidiParam; 4] = [
+        MidiParam::AudioGain,
+        MidiParam::PreviewBrightness,
+        MidiParam::Zoom,
+        MidiParam::Focus,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MidiParam::AudioGain => tr("settings.midi.audio_gain"),
+            MidiParam::PreviewBrightness => tr("settings.midi.preview_brightness"),
+            MidiParam::Zoom => tr("settings.midi.zoom"),
+            MidiParam::Focus => tr("settings.midi.focus"),
+        }
+    }
+}
+
+/// 配置中写成 `{ param = "zoom" }` 或 `{ action = "toggle-record" }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum MidiTarget {
+    Param(MidiParam),
+    Action(Action),
+}
+
+impl MidiTarget {
+    pub fn label(self) -> String {
+        match self {
+            MidiTarget::Param(param) => param.label().to_string(),
+            MidiTarget::Action(action) => action.label(),
+        }
+    }
+
+    /// 参数只能绑定到 CC, 音符只有按下没有数值.
+    pub fn accepts(self, control: MidiControl) -> bool {
+        matches!(self, MidiTarget::Action(_)) || matches!(control, MidiControl::Cc { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct MidiBinding {
+    pub control: MidiControl,
+    pub target: MidiTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct MidiSettings {
+    pub enabled: bool,
+    /// 连接名称包含这段文字的端口 (不区分大小写). 为空时连接第一个不是 "Midi Through" 的端口.
+    pub port: String,
+    pub bindings: Vec<MidiBinding>,
+}
+
+impl Default for MidiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            port: String::new(),
+            bindings: Vec::new(),
+        }
+    }
+}
+
+impl MidiSettings {
+    /// 一个控件只控制一个目标, 一个目标也只有一个控件, 旧的绑定被替换.
+    pub fn bind(&mut self, control: MidiControl, target: MidiTarget) {
+        self.bindings
+            .retain(|b| b.control != control && b.target != target);
+        self.bindings.push(MidiBinding { control, target });
+    }
+
+    pub fn unbind(&mut self, target: MidiTarget) {
+        self.bindings.retain(|b| b.target != target);
+    }
+
+    pub fn control(&self, target: MidiTarget) -> Option<MidiControl> {
+        self.bindings
+            .iter()
+            .find(|b| b.target == target)
+            .map(|b| b.control)
+    }
+
+    pub fn target(&self, control: MidiControl) -> Option<MidiTarget> {
+        self.bindings
+            .iter()
+            .find(|b| b.control == control)
+            .map(|b| b.target)
+    }
+}
+
+/// 只关心 CC 和音符, 其他消息返回 None. 音符关闭的值为 0.
+fn parse(bytes: &[u8]) -> Option<(MidiControl, u8)> {
+    let (&status, data) = bytes.split_first()?;
+    let channel = status & 0x0f;
+    let number = *data.first()? & 0x7f;
+    let value = *data.get(1)? & 0x7f;
+    match status & 0xf0 {
+        0xb0 => Some((MidiControl::Cc { channel, number }, value)),
+        0x90 => Some((MidiControl::Note { channel, number }, value)),
+        0x80 => Some((MidiControl::Note { channel, number }, 0)),
+        _ => None,
+    }
+}
+
+/// 两帧之间收到的消息.
+#[derive(Debug, Default)]
+pub(crate) struct Received {
+    /// 每个 CC 的最新值, 界面取走之前的新值直接覆盖旧值.
+    pub values: HashMap<MidiControl, u8>,
+    /// 按下的音符和越过一半的 CC, 按收到的顺序.
+    pub presses: Vec<MidiControl>,
+    /// 最近动过的控件, 用于学习.
+    pub last: Option<MidiControl>,
+}
+
+/// 在 midir 的线程里调用. `previous` 是每个 CC 上一次的值, 用来识别按下.
+fn receive(received: &Mutex<Received>, previous: &mut HashMap<MidiControl, u8>, bytes: &[u8]) {
+    let Some((control, value)) = parse(bytes) else {
+        return;
+    };
+    let mut received = received.lock();
+    let pressed = match control {
+        MidiControl::Cc { .. } => {
+            let before = previous.insert(control, value).unwrap_or(0);
+            received.values.insert(control, value);
+            value >= CC_PRESSED && before < CC_PRESSED
+        }
+        MidiControl::Note { .. } => value > 0,
+    };
+    if matches!(control, MidiControl::Cc { .. }) || pressed {
+        received.last = Some(control);
+    }
+    if pressed && received.presses.len() < MAX_PRESSES {
+        received.presses.push(control);
+    }
+}
+
+fn port_matches(filter: &str, name: &str) -> bool {
+    if filter.trim().is_empty() {
+        return !name.contains("Through");
+    }
+    name.to_lowercase().contains(&filter.trim().to_lowercase())
+}
+
+pub(crate) enum MidiEvent {
+    Connected(String),
+    Disconnected(String),
+}
+
+/// 与一个 MIDI 输入端口的连接. 设备拔出后断开, 重新插上后自动连接.
+pub(crate) struct Midi {
+    /// 只用来列出端口, 连接时另外创建.
+    scanner: Option<MidiInput>,
+    ports: Vec<String>,
+    connection: Option<(String, MidiInputConnection<HashMap<MidiControl, u8>>)>,
+    received: Arc<Mutex<Received>>,
+    checked_at: Option<Instant>,
+    /// 连接失败过的端口, 只记录一次警告.
+    failed: Option<String>,
+}
+
+impl Midi {
+    pub fn new() -> Self {
+        Self {
+            scanner: MidiInput::new(CLIENT_NAME)
+                .inspect_err(|e| tracing::warn!("midi input unavailable: {}", e))
+                .ok(),
+            ports: Vec::new(),
+            connection: None,
+            received: Arc::new(Mutex::new(Received::default())),
+            checked_at: None,
+            failed: None,
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.scanner.is_some()
+    }
+
+    /// 最近一次检查时的所有输入端口.
+    pub fn ports(&self) -> &[String] {
+        &self.ports
+    }
+
+    /// 已连接的端口名称.
+    pub fn port(&self) -> Option<&str> {
+        self.connection.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// 每帧调用, 每隔 [CHECK_INTERVAL] 检查端口. 停用时立即断开.
+    pub fn check(&mut self, settings: &MidiSettings) -> Option<MidiEvent> {
+        if !settings.enabled {
+            return self.disconnect();
+        }
+        if self
+            .checked_at
+            .is_some_and(|at| at.elapsed() < CHECK_INTERVAL)
+        {
+            return None;
+        }
+        self.checked_at = Some(Instant::now());
+        let scanner = self.scanner.as_ref()?;
+        self.ports = scanner
+            .ports()
+            .iter()
+            .filter_map(|p| scanner.port_name(p).ok())
+            .collect();
+
+        if let Some(name) = self.port() {
+            if self.ports.iter().any(|p| p == name) && port_matches(&settings.port, name) {
+                return None;
+            }
+            return self.disconnect();
+        }
+        let name = self
+            .ports
+            .iter()
+            .find(|p| port_matches(&settings.port, p))?
+            .clone();
+        match self.connect(&name) {
+            Ok(connection) => {
+                tracing::info!(port = %name, "midi connected");
+                self.failed = None;
+                self.connection = Some((name.clone(), connection));
+                Some(MidiEvent::Connected(name))
+            }
+            Err(e) => {
+                if self.failed.as_ref() != Some(&name) {
+                    tracing::warn!(port = %name, "could not connect midi: {}", e);
+                    self.failed = Some(name);
+                }
+                None
+            }
+        }
+    }
+
+    fn connect(&self, name: &str) -> Result<MidiInputConnection<HashMap<MidiControl, u8>>, String> {
+        let input = MidiInput::new(CLIENT_NAME).map_err(|e| e.to_string())?;
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|p| input.port_name(p).is_ok_and(|n| n == name))
+            .ok_or("port disappeared")?;
+        let received = self.received.clone();
+        input
+            .connect(
+                &port,
+                CLIENT_NAME,
+                move |_, bytes, previous| receive(&received, previous, bytes),
+                HashMap::new(),
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    fn disconnect(&mut self) -> Option<MidiEvent> {
+        let (name, connection) = self.connection.take()?;
+        // 设备已经拔出时也可以关闭
+        connection.close();
+        *self.received.lock() = Received::default();
+        tracing::info!(port = %name, "midi disconnected");
+        Some(MidiEvent::Disconnected(name))
+    }
+
+    /// 取出上一帧以来收到的消息.
+    pub fn take(&self) -> Received {
+        std::mem::take(&mut *self.received.lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FADER: MidiControl = MidiControl::Cc {
+        channel: 0,
+        number: 7,
+    };
+
+    #[test]
+    fn parses_cc_and_notes() {
+        assert_eq!(parse(&[0xb0, 7, 100]), Some((FADER, 100)));
+        let pad = MidiControl::Note {
+            channel: 9,
+            number: 36,
+        };
+        assert_eq!(parse(&[0x99, 36, 90]), Some((pad, 90)));
+        assert_eq!(parse(&[0x89, 36, 64]), Some((pad, 0)));
+        // 弯音和不完整的消息
+        assert_eq!(parse(&[0xe0, 0, 64]), None);
+        assert_eq!(parse(&[0xb0, 7]), None);
+    }
+
+    #[test]
+    fn coalesces_fader_moves() {
+        let received = Mutex::new(Received::default());
+        let mut previous = HashMap::new();
+        for value in 0..=127 {
+            receive(&received, &mut previous, &[0xb0, 7, value]);
+        }
+        let received = received.into_inner();
+        assert_eq!(received.values.len(), 1);
+        assert_eq!(received.values[&FADER], 127);
+        // 推过一半算一次按下
+        assert_eq!(received.presses, [FADER]);
+    }
+
+    #[test]
+    fn binding_replaces_old_control_and_target() {
+        let mut settings = MidiSettings::default();
+        let zoom = MidiTarget::Param(MidiParam::Zoom);
+        let focus = MidiTarget::Param(MidiParam::Focus);
+        settings.bind(FADER, zoom);
+        settings.bind(FADER, focus);
+        assert_eq!(settings.control(zoom), None);
+        assert_eq!(settings.target(FADER), Some(focus));
+        let text = toml::to_string(&settings).unwrap();
+        assert_eq!(toml::from_str::<MidiSettings>(&text).unwrap(), settings);
+    }
+
+    #[test]
+    fn through_port_is_skipped_by_default() {
+        assert!(!port_matches("", "Midi Through:Midi Through Port-0 14:0"));
+        assert!(port_matches("", "nanoKONTROL2:nanoKONTROL2 MIDI 1 20:0"));
+        assert!(port_matches(
+            "nanokontrol",
+            "nanoKONTROL2:nanoKONTROL2 MIDI 1 20:0"
+        ));
+        assert!(!port_matches(
+            "launch",
+            "nanoKONTROL2:nanoKONTROL2 MIDI 1 20:0"
+        ));
+    }
+}
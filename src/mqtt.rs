@@ -0,0 +1,395 @@
+//! MQTT 客户端 (`mqtt` 功能), 接入现场已有的自动化系统.
+//! 在 `camui/<id>/` 下发布保留的状态主题, 并订阅 `cmd/` 下的指令.
+//! 断线后自动重连, 遗嘱消息在异常断开时把状态标记为 `offline`.
+
+use parking_lot::Mutex;
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS, Transport};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+use crate::status::RemoteState;
+
+const AUDIO_INTERVAL: Duration = Duration::from_secs(1);
+const DISK_INTERVAL: Duration = Duration::from_secs(10);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// 断线期间最多积压的请求, 满了以后丢弃新的状态.
+const REQUEST_CAPACITY: usize = 16;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct MqttSettings {
+    pub enabled: bool,
+    /// `mqtt://host:1883`, 或用 `mqtts://host:8883` 加密连接.
+    pub broker: String,
+    /// 同时是主题中的 `<id>`, 每台机器要不同.
+    pub client_id: String,
+    /// 为空时不认证.
+    pub username: String,
+    pub password: String,
+    /// 加密连接时信任的 CA 证书 (PEM), 为 None 时使用系统证书.
+    pub ca_file: Option<PathBuf>,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            broker: String::from("mqtt://localhost:1883"),
+            client_id: String::from("cam-ui"),
+            username: String::new(),
+            password: String::new(),
+            ca_file: None,
+        }
+    }
+}
+
+/// 手动实现, 保证密码不会出现在日志里.
+impl std::fmt::Debug for MqttSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqttSettings")
+            .field("enabled", &self.enabled)
+            .field("broker", &self.broker)
+            .field("client_id", &self.client_id)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("ca_file", &self.ca_file)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Broker {
+    host: String,
+    port: u16,
+    tls: bool,
+}
+
+/// 解析 `mqtt://host[:port]` 或 `mqtts://host[:port]`.
+fn parse_broker(url: &str) -> Result<Broker, String> {
+    let (tls, rest) = if let Some(rest) = url.strip_prefix("mqtts://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("mqtt://") {
+        (false, rest)
+    } else {
+        return Err(format!("unsupported broker url '{}'", url));
+    };
+    let rest = rest.trim_end_matches('/');
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse()
+                .map_err(|_| format!("invalid port in '{}'", url))?;
+            (host, port)
+        }
+        None => (rest, if tls { 8883 } else { 1883 }),
+    };
+    if host.is_empty() {
+        return Err(format!("missing host in '{}'", url));
+    }
+    Ok(Broker {
+        host: host.to_string(),
+        port,
+        tls,
+    })
+}
+
+/// 从 `cmd/` 主题收到的指令, 由界面或无界面模式转换成相应的操作.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MqttCommand {
+    /// `cmd/record`, 内容为 `start` 或 `stop`.
+    Record(bool),
+    /// `cmd/record` 内容为 `force-stop`: 不经过防误停确认, 立即停止.
+    ForceStop,
+    /// `cmd/snapshot`, 内容任意.
+    Snapshot,
+    /// `cmd/debug-graph`, 内容任意: 把管线拓扑图写到输出目录.
+    DumpGraph,
+}
+
+impl MqttCommand {
+    fn parse(command: &str, payload: &[u8]) -> Option<Self> {
+        let payload = std::str::from_utf8(payload).ok()?.trim();
+        match command {
+            "record" if payload.eq_ignore_ascii_case("start") => Some(MqttCommand::Record(true)),
+            "record" if payload.eq_ignore_ascii_case("stop") => Some(MqttCommand::Record(false)),
+            "record" if payload.eq_ignore_ascii_case("force-stop") => Some(MqttCommand::ForceStop),
+            "snapshot" => Some(MqttCommand::Snapshot),
+            "debug-graph" => Some(MqttCommand::DumpGraph),
+            _ => None,
+        }
+    }
+}
+
+/// 在单独的线程中保持连接, 不阻塞界面或录制.
+pub(crate) struct Mqtt {
+    commands: mpsc::UnboundedReceiver<MqttCommand>,
+    connected: Arc<AtomicBool>,
+}
+
+impl Mqtt {
+    /// 设置启用时连接, 地址无效时只记录错误.
+    /// 录制状态和输出目录跟随状态服务器的 `state`.
+    pub fn spawn(
+        settings: &MqttSettings,
+        audio_level: Arc<Mutex<f32>>,
+        state: RemoteState,
+    ) -> Self {
+        let (command_tx, commands) = mpsc::unbounded_channel();
+        let mqtt = Self {
+            commands,
+            connected: Arc::new(AtomicBool::new(false)),
+        };
+        if !settings.enabled {
+            return mqtt;
+        }
+        let options = match options(settings) {
+            Ok(options) => options,
+            Err(e) => {
+                tracing::error!("could not start mqtt client: {}", e);
+                return mqtt;
+            }
+        };
+        let worker = Worker {
+            prefix: format!("camui/{}", settings.client_id),
+            commands: command_tx,
+            recording: state.subscribe(),
+            state,
+            connected: mqtt.connected.clone(),
+            audio_level,
+        };
+        let spawned = std::thread::Builder::new()
+            .name("mqtt".into())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        tracing::error!("could not start mqtt client: {}", e);
+                        return;
+                    }
+                };
+                runtime.block_on(worker.run(options));
+            });
+        if let Err(e) = spawned {
+            tracing::error!("could not start mqtt client: {}", e);
+        }
+        mqtt
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// 界面每帧取出收到的指令.
+    pub fn try_recv(&mut self) -> Option<MqttCommand> {
+        self.commands.try_recv().ok()
+    }
+
+    /// 等待下一条指令, 未启用时永远不返回.
+    pub async fn recv(&mut self) -> MqttCommand {
+        match self.commands.recv().await {
+            Some(command) => command,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+fn options(settings: &MqttSettings) -> Result<MqttOptions, String> {
+    let broker = parse_broker(&settings.broker)?;
+    let mut options = MqttOptions::new(&settings.client_id, broker.host, broker.port);
+    options.set_keep_alive(KEEP_ALIVE);
+    options.set_last_will(LastWill::new(
+        format!("camui/{}/state", settings.client_id),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if !settings.username.is_empty() {
+        options.set_credentials(&settings.username, &settings.password);
+    }
+    if broker.tls {
+        // 依赖树中同时启用了 ring 和 aws-lc-rs, rustls 无法自己选择
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let transport = match &settings.ca_file {
+            Some(path) => {
+                let ca = std::fs::read(path)
+                    .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+                Transport::tls(ca, None, None)
+            }
+            None => Transport::tls_with_default_config(),
+        };
+        options.set_transport(transport);
+    }
+    Ok(options)
+}
+
+struct Worker {
+    /// `camui/<id>`.
+    prefix: String,
+    commands: mpsc::UnboundedSender<MqttCommand>,
+    /// 录制状态变化时重新发布 `state` 主题.
+    recording: watch::Receiver<bool>,
+    state: RemoteState,
+    connected: Arc<AtomicBool>,
+    audio_level: Arc<Mutex<f32>>,
+}
+
+impl Worker {
+    async fn run(mut self, options: MqttOptions) {
+        let broker = format!(
+            "{}:{}",
+            options.broker_address().0,
+            options.broker_address().1
+        );
+        let (client, mut eventloop) = AsyncClient::new(options, REQUEST_CAPACITY);
+        let mut audio = tokio::time::interval(AUDIO_INTERVAL);
+        let mut disk = tokio::time::interval(DISK_INTERVAL);
+        loop {
+            tokio::select! {
+                event = eventloop.poll() => match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        tracing::info!(%broker, "mqtt connected");
+                        self.connected.store(true, Ordering::Relaxed);
+                        // 会话不保留, 每次连接都重新订阅, 并覆盖遗嘱留下的 offline
+                        self.request(client.try_subscribe(self.topic("cmd/#"), QoS::AtLeastOnce));
+                        self.publish_state(&client);
+                        self.publish_disk(&client);
+                    }
+                    // 保留的指令在每次重连时都会收到, 不执行
+                    Ok(Event::Incoming(Packet::Publish(publish))) if publish.retain => {
+                        tracing::warn!(topic = %publish.topic, "ignoring retained mqtt command");
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let command = publish
+                            .topic
+                            .strip_prefix(&self.topic("cmd/"))
+                            .and_then(|c| MqttCommand::parse(c, &publish.payload));
+                        match command {
+                            Some(command) => {
+                                tracing::info!(?command, "mqtt command");
+                                let _ = self.commands.send(command);
+                            }
+                            None => tracing::warn!(topic = %publish.topic, "ignoring mqtt message"),
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        if self.connected.swap(false, Ordering::Relaxed) {
+                            tracing::warn!(%broker, "mqtt disconnected: {}", e);
+                        } else {
+                            tracing::debug!(%broker, "mqtt connection failed: {}", e);
+                        }
+                        // 下一次 poll 会重新连接
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                },
+                Ok(()) = self.recording.changed() => self.publish_state(&client),
+                _ = audio.tick() => {
+                    if self.connected.load(Ordering::Relaxed) {
+                        let db = *self.audio_level.lock();
+                        self.publish(&client, "audio_level", format!("{:.1}", db));
+                    }
+                }
+                _ = disk.tick() => {
+                    if self.connected.load(Ordering::Relaxed) {
+                        self.publish_disk(&client);
+                    }
+                }
+            }
+        }
+    }
+
+    fn topic(&self, name: &str) -> String {
+        format!("{}/{}", self.prefix, name)
+    }
+
+    fn publish_state(&self, client: &AsyncClient) {
+        let state = if *self.recording.borrow() {
+            "recording"
+        } else {
+            "idle"
+        };
+        self.publish(client, "state", state.to_string());
+    }
+
+    fn publish_disk(&self, client: &AsyncClient) {
+        let path = self.state.output_dir();
+        match crate::file::free_space(&path) {
+            Ok(free) => self.publish(client, "disk_free", free.to_string()),
+            Err(e) => tracing::debug!(path = %path.display(), "could not read free space: {}", e),
+        }
+    }
+
+    /// 状态主题都是保留消息, 后连上的订阅者立即得到最新值.
+    fn publish(&self, client: &AsyncClient, name: &str, payload: String) {
+        self.request(client.try_publish(self.topic(name), QoS::AtLeastOnce, true, payload));
+    }
+
+    /// 不等待请求队列, 满了 (断线太久) 时丢弃.
+    fn request(&self, result: Result<(), rumqttc::ClientError>) {
+        if let Err(e) = result {
+            tracing::debug!("mqtt request dropped: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_broker_urls() {
+        assert_eq!(
+            parse_broker("mqtt://broker.local").unwrap(),
+            Broker {
+                host: "broker.local".to_string(),
+                port: 1883,
+                tls: false
+            }
+        );
+        assert_eq!(
+            parse_broker("mqtts://10.0.0.2:8884/").unwrap(),
+            Broker {
+                host: "10.0.0.2".to_string(),
+                port: 8884,
+                tls: true
+            }
+        );
+        assert!(parse_broker("tcp://broker.local").is_err());
+        assert!(parse_broker("mqtt://:1883").is_err());
+        assert!(parse_broker("mqtt://host:port").is_err());
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            MqttCommand::parse("record", b"start"),
+            Some(MqttCommand::Record(true))
+        );
+        assert_eq!(
+            MqttCommand::parse("record", b" STOP\n"),
+            Some(MqttCommand::Record(false))
+        );
+        assert_eq!(
+            MqttCommand::parse("record", b"force-stop"),
+            Some(MqttCommand::ForceStop)
+        );
+        assert_eq!(MqttCommand::parse("record", b"pause"), None);
+        assert_eq!(
+            MqttCommand::parse("snapshot", b""),
+            Some(MqttCommand::Snapshot)
+        );
+        assert_eq!(
+            MqttCommand::parse("debug-graph", b""),
+            Some(MqttCommand::DumpGraph)
+        );
+        assert_eq!(MqttCommand::parse("reboot", b""), None);
+    }
+}
@@ -0,0 +1,294 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+#[cfg(feature = "s3")]
+pub(crate) mod s3;
+pub(crate) mod sftp;
+
+#[cfg(feature = "s3")]
+use s3::S3Settings;
+use sftp::SftpSettings;
+
+/// 失败后最多重试的次数.
+const MAX_ATTEMPTS: u32 = 5;
+/// 第一次重试前的等待时间, 之后每次翻倍.
+const BASE_BACKOFF: Duration = Duration::from_secs(10);
+/// 队列为空或条件不满足时的轮询间隔.
+const IDLE_POLL: Duration = Duration::from_millis(500);
+
+/// 上传目的地.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Target {
+    Sftp,
+    #[cfg(feature = "s3")]
+    S3,
+}
+
+impl Target {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Target::Sftp => "SFTP",
+            #[cfg(feature = "s3")]
+            Target::S3 => "S3",
+        }
+    }
+}
+
+/// 所有上传目的地的配置.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct OffloadSettings {
+    pub sftp: SftpSettings,
+    #[cfg(feature = "s3")]
+    pub s3: S3Settings,
+}
+
+impl OffloadSettings {
+    /// 录制完成后需要自动上传到的目的地.
+    pub fn auto_targets(&self) -> Vec<Target> {
+        let mut targets = Vec::new();
+        if self.sftp.enabled {
+            targets.push(Target::Sftp);
+        }
+        #[cfg(feature = "s3")]
+        if self.s3.enabled {
+            targets.push(Target::S3);
+        }
+        targets
+    }
+
+    fn ready(&self, target: Target) -> bool {
+        match target {
+            Target::Sftp => self.sftp.is_configured() && self.sftp.conditions_met(),
+            #[cfg(feature = "s3")]
+            Target::S3 => self.s3.is_configured(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TransferState {
+    Queued,
+    Active,
+    /// 服务器已确认完整接收.
+    Completed,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TransferItem {
+    pub path: PathBuf,
+    pub target: Target,
+    pub state: TransferState,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// 本次尝试的平均速度 (字节/秒).
+    pub speed: f64,
+    /// 分片上传的进度 (已完成, 总数), 不分片的传输为 `None`.
+    pub parts: Option<(u32, u32)>,
+    pub attempts: u32,
+    /// 上一次失败的原因, 重试期间也保留以便显示.
+    pub last_error: Option<String>,
+    next_attempt: Instant,
+}
+
+impl TransferItem {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total == 0 {
+            0.0
+        } else {
+            (self.bytes_done as f32 / self.bytes_total as f32).min(1.0)
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self.state, TransferState::Queued | TransferState::Active)
+    }
+}
+
+struct Shared {
+    items: Vec<TransferItem>,
+    settings: OffloadSettings,
+}
+
+/// 上传队列, 由单独的后台线程按顺序处理.
+pub(crate) struct Uploader {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Uploader {
+    pub fn spawn(settings: OffloadSettings) -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            items: Vec::new(),
+            settings,
+        }));
+        let shared_c = shared.clone();
+        std::thread::spawn(move || worker(shared_c));
+        Self { shared }
+    }
+
+    pub fn set_settings(&self, settings: OffloadSettings) {
+        self.shared.lock().settings = settings;
+    }
+
+    pub fn enqueue(&self, path: PathBuf, target: Target) {
+        let mut shared = self.shared.lock();
+        if shared
+            .items
+            .iter()
+            .any(|i| i.path == path && i.target == target && i.is_pending())
+        {
+            return;
+        }
+        let bytes_total = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        shared.items.push(TransferItem {
+            path,
+            target,
+            state: TransferState::Queued,
+            bytes_done: 0,
+            bytes_total,
+            speed: 0.0,
+            parts: None,
+            attempts: 0,
+            last_error: None,
+            next_attempt: Instant::now(),
+        });
+    }
+
+    /// 重新排队失败的条目.
+    pub fn retry(&self, path: &Path, target: Target) {
+        let mut shared = self.shared.lock();
+        if let Some(item) = shared.items.iter_mut().find(|i| {
+            i.path == path && i.target == target && matches!(i.state, TransferState::Failed(_))
+        }) {
+            item.state = TransferState::Queued;
+            item.attempts = 0;
+            item.next_attempt = Instant::now();
+        }
+    }
+
+    pub fn clear_finished(&self) {
+        self.shared.lock().items.retain(TransferItem::is_pending);
+    }
+
+    pub fn items(&self) -> Vec<TransferItem> {
+        self.shared.lock().items.clone()
+    }
+
+    /// 该文件是否还在等待或正在上传. 此时不应删除本地文件.
+    pub fn is_pending(&self, path: &Path) -> bool {
+        self.shared
+            .lock()
+            .items
+            .iter()
+            .any(|i| i.path == path && i.is_pending())
+    }
+
+    /// 汇总一个文件在所有目的地的状态: 有失败的算失败, 全部完成才算完成.
+    pub fn status(&self, path: &Path) -> Option<TransferState> {
+        let rank = |s: &TransferState| match s {
+            TransferState::Failed(_) => 3,
+            TransferState::Active => 2,
+            TransferState::Queued => 1,
+            TransferState::Completed => 0,
+        };
+        self.shared
+            .lock()
+            .items
+            .iter()
+            .filter(|i| i.path == path)
+            .map(|i| &i.state)
+            .max_by_key(|s| rank(s))
+            .cloned()
+    }
+}
+
+fn worker(shared: Arc<Mutex<Shared>>) {
+    loop {
+        // 1. 取出下一个到期的条目 (持锁时间尽量短)
+        let next = {
+            let mut shared = shared.lock();
+            let settings = shared.settings.clone();
+            let now = Instant::now();
+            let item = shared.items.iter_mut().find(|i| {
+                i.state == TransferState::Queued
+                    && i.next_attempt <= now
+                    && settings.ready(i.target)
+            });
+            item.map(|item| {
+                item.state = TransferState::Active;
+                item.attempts += 1;
+                item.bytes_done = 0;
+                item.speed = 0.0;
+                (item.path.clone(), item.target, settings)
+            })
+        };
+        let Some((path, target, settings)) = next else {
+            std::thread::sleep(IDLE_POLL);
+            continue;
+        };
+
+        // 2. 执行上传. 不要记录 settings 本身, 其中包含凭据
+        info!(path = %path.display(), target = target.label(), "uploading");
+        let started = Instant::now();
+        let update = |bytes: u64, parts: Option<(u32, u32)>| {
+            let mut shared = shared.lock();
+            if let Some(item) = shared
+                .items
+                .iter_mut()
+                .find(|i| i.path == path && i.target == target)
+            {
+                item.bytes_done = bytes;
+                item.speed = bytes as f64 / started.elapsed().as_secs_f64().max(0.001);
+                item.parts = parts;
+            }
+        };
+        let result = match target {
+            Target::Sftp => sftp::upload(&settings.sftp, &path, |bytes| update(bytes, None)),
+            #[cfg(feature = "s3")]
+            Target::S3 => s3::upload(&settings.s3, &path, |bytes, done, total| {
+                update(bytes, Some((done, total)))
+            }),
+        };
+
+        // 3. 更新状态, 失败时按指数退避重新排队
+        let mut shared = shared.lock();
+        let Some(item) = shared
+            .items
+            .iter_mut()
+            .find(|i| i.path == path && i.target == target)
+        else {
+            continue;
+        };
+        match result {
+            Ok(()) => {
+                info!(path = %path.display(), target = target.label(), "uploaded");
+                item.bytes_done = item.bytes_total;
+                item.state = TransferState::Completed;
+                item.last_error = None;
+            }
+            Err(e) => {
+                warn!(path = %path.display(), attempt = item.attempts, "upload failed: {}", e);
+                item.last_error = Some(e.clone());
+                if item.attempts >= MAX_ATTEMPTS {
+                    item.state = TransferState::Failed(e);
+                } else {
+                    let backoff = BASE_BACKOFF * 2u32.pow(item.attempts - 1);
+                    item.state = TransferState::Queued;
+                    item.next_attempt = Instant::now() + backoff;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use aws_sdk_s3::Client;
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use serde::{Deserialize, Serialize};
+
+/// 分片大小. S3 要求除最后一片外不小于 5 MiB.
+const PART_SIZE: usize = 16 * 1024 * 1024;
+/// 每个分片独立重试的次数.
+const PART_ATTEMPTS: u32 = 4;
+const PART_BACKOFF: Duration = Duration::from_secs(2);
+
+/// S3 兼容对象存储配置.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct S3Settings {
+    /// 录制完成后自动加入上传队列.
+    pub enabled: bool,
+    /// 例如 "https://s3.us-west-002.backblazeb2.com", 为空时使用 AWS.
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// 对象键模板, 支持 `{date}` 和 `{name}`.
+    pub key_template: String,
+}
+
+impl Default for S3Settings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: String::new(),
+            region: String::from("us-east-1"),
+            bucket: String::new(),
+            access_key: String::new(),
+            secret_key: String::new(),
+            key_template: String::from("{date}/{name}"),
+        }
+    }
+}
+
+/// 手动实现, 保证密钥不会出现在日志里.
+impl std::fmt::Debug for S3Settings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Settings")
+            .field("enabled", &self.enabled)
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("access_key", &"<redacted>")
+            .field("secret_key", &"<redacted>")
+            .field("key_template", &self.key_template)
+            .finish()
+    }
+}
+
+impl S3Settings {
+    pub fn is_configured(&self) -> bool {
+        !self.bucket.is_empty() && !self.access_key.is_empty() && !self.secret_key.is_empty()
+    }
+
+    /// `{date}` 取录像文件的修改日期, 而不是上传时的日期.
+    pub fn object_key(&self, path: &Path) -> String {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let date: chrono::DateTime<chrono::Local> = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map(Into::into)
+            .unwrap_or_else(|_| chrono::Local::now());
+        self.key_template
+            .replace("{date}", &date.format("%Y-%m-%d").to_string())
+            .replace("{name}", &name)
+            .trim_start_matches('/')
+            .to_string()
+    }
+
+    fn client(&self) -> Client {
+        let mut builder = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(self.region.clone()))
+            .credentials_provider(Credentials::new(
+                &self.access_key,
+                &self.secret_key,
+                None,
+                None,
+                "cam-ui",
+            ))
+            // MinIO 等自建服务通常不支持虚拟主机风格的域名
+            .force_path_style(true);
+        if !self.endpoint.is_empty() {
+            builder = builder.endpoint_url(&self.endpoint);
+        }
+        Client::from_conf(builder.build())
+    }
+}
+
+/// 分片上传单个文件. `on_progress` 收到 (已确认字节, 已完成分片, 分片总数).
+/// 只有 CompleteMultipartUpload 成功后才返回 `Ok`.
+pub(super) fn upload(
+    settings: &S3Settings,
+    path: &Path,
+    mut on_progress: impl FnMut(u64, u32, u32),
+) -> Result<(), String> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("failed to start runtime: {}", e))?;
+    runtime.block_on(upload_multipart(settings, path, &mut on_progress))
+}
+
+async fn upload_multipart(
+    settings: &S3Settings,
+    path: &Path,
+    on_progress: &mut impl FnMut(u64, u32, u32),
+) -> Result<(), String> {
+    let client = settings.client();
+    let key = settings.object_key(path);
+    let total = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+    let parts_total = total.div_ceil(PART_SIZE as u64).max(1) as u32;
+
+    let created = client
+        .create_multipart_upload()
+        .bucket(&settings.bucket)
+        .key(&key)
+        .send()
+        .await
+        .map_err(|e| format!("create upload failed: {}", DisplayErrorContext(e)))?;
+    let upload_id = created
+        .upload_id()
+        .ok_or("server returned no upload id")?
+        .to_string();
+
+    let parts = match upload_parts(
+        settings,
+        &client,
+        &key,
+        &upload_id,
+        path,
+        parts_total,
+        on_progress,
+    )
+    .await
+    {
+        Ok(parts) => parts,
+        Err(e) => {
+            // 放弃未完成的上传, 避免存储桶里留下计费的碎片
+            let _ = client
+                .abort_multipart_upload()
+                .bucket(&settings.bucket)
+                .key(&key)
+                .upload_id(&upload_id)
+                .send()
+                .await;
+            return Err(e);
+        }
+    };
+
+    client
+        .complete_multipart_upload()
+        .bucket(&settings.bucket)
+        .key(&key)
+        .upload_id(&upload_id)
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .set_parts(Some(parts))
+                .build(),
+        )
+        .send()
+        .await
+        .map_err(|e| format!("complete upload failed: {}", DisplayErrorContext(e)))?;
+    Ok(())
+}
+
+async fn upload_parts(
+    settings: &S3Settings,
+    client: &Client,
+    key: &str,
+    upload_id: &str,
+    path: &Path,
+    parts_total: u32,
+    on_progress: &mut impl FnMut(u64, u32, u32),
+) -> Result<Vec<CompletedPart>, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut parts = Vec::with_capacity(parts_total as usize);
+    let mut sent = 0u64;
+
+    for part_number in 1..=parts_total as i32 {
+        let chunk = read_part(&mut file).map_err(|e| e.to_string())?;
+        let len = chunk.len() as u64;
+
+        let mut attempt = 1;
+        let e_tag = loop {
+            let result = client
+                .upload_part()
+                .bucket(&settings.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(chunk.clone()))
+                .send()
+                .await;
+            match result {
+                Ok(out) => break out.e_tag().map(str::to_string),
+                Err(e) if attempt < PART_ATTEMPTS => {
+                    tracing::warn!(
+                        "upload part {} failed (attempt {}): {}",
+                        part_number,
+                        attempt,
+                        DisplayErrorContext(e)
+                    );
+                    tokio::time::sleep(PART_BACKOFF * 2u32.pow(attempt - 1)).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(format!(
+                        "part {} failed: {}",
+                        part_number,
+                        DisplayErrorContext(e)
+                    ));
+                }
+            }
+        };
+
+        parts.push(
+            CompletedPart::builder()
+                .part_number(part_number)
+                .set_e_tag(e_tag)
+                .build(),
+        );
+        sent += len;
+        on_progress(sent, part_number as u32, parts_total);
+    }
+    Ok(parts)
+}
+
+/// 读取下一个分片, 文件末尾时可能不足 [PART_SIZE].
+fn read_part(file: &mut File) -> std::io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(PART_SIZE);
+    file.by_ref().take(PART_SIZE as u64).read_to_end(&mut buf)?;
+    Ok(buf)
+}
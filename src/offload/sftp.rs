@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// SSH 服务器上传配置. 传输使用 rsync over SSH, 支持断点续传和限速.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SftpSettings {
+    /// 录制完成后自动加入上传队列.
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    /// 私钥路径, 为空时使用 ssh 默认配置.
+    pub key_path: String,
+    /// 为空时使用密钥认证, 否则通过 sshpass 传递.
+    pub password: String,
+    pub remote_dir: String,
+    /// 上传限速 (KB/s), 0 表示不限速.
+    pub bwlimit_kbps: u32,
+    /// 仅在该网络接口连接时上传, 例如 "wlan0". 为空表示不限制.
+    pub require_interface: String,
+}
+
+impl Default for SftpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: 22,
+            user: String::new(),
+            key_path: String::new(),
+            password: String::new(),
+            remote_dir: String::from("."),
+            bwlimit_kbps: 0,
+            require_interface: String::new(),
+        }
+    }
+}
+
+/// 手动实现, 保证密码不会出现在日志里.
+impl std::fmt::Debug for SftpSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SftpSettings")
+            .field("enabled", &self.enabled)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("key_path", &self.key_path)
+            .field("password", &"<redacted>")
+            .field("remote_dir", &self.remote_dir)
+            .field("bwlimit_kbps", &self.bwlimit_kbps)
+            .field("require_interface", &self.require_interface)
+            .finish()
+    }
+}
+
+impl SftpSettings {
+    pub fn is_configured(&self) -> bool {
+        !self.host.is_empty() && !self.user.is_empty()
+    }
+
+    /// 是否满足上传条件 (例如 Wi-Fi 已连接).
+    pub fn conditions_met(&self) -> bool {
+        self.require_interface.is_empty() || interface_up(&self.require_interface)
+    }
+}
+
+/// 通过 sysfs 检查网络接口状态.
+fn interface_up(name: &str) -> bool {
+    std::fs::read_to_string(format!("/sys/class/net/{}/operstate", name))
+        .map(|s| s.trim() == "up")
+        .unwrap_or(false)
+}
+
+/// 上传单个文件. `on_progress` 收到已传输的字节数.
+/// 远端已有的部分文件会被续传并校验.
+pub(super) fn upload(
+    settings: &SftpSettings,
+    path: &Path,
+    mut on_progress: impl FnMut(u64),
+) -> Result<(), String> {
+    let mut ssh = format!(
+        "ssh -p {} -o StrictHostKeyChecking=accept-new -o ConnectTimeout=15",
+        settings.port
+    );
+    if !settings.key_path.is_empty() {
+        ssh.push_str(&format!(" -i {}", quote(&settings.key_path)));
+    }
+
+    let mut cmd = if settings.password.is_empty() {
+        ssh.push_str(" -o BatchMode=yes");
+        Command::new("rsync")
+    } else {
+        // 通过环境变量传递密码, 不出现在进程参数中
+        let mut cmd = Command::new("sshpass");
+        cmd.arg("-e")
+            .arg("rsync")
+            .env("SSHPASS", &settings.password);
+        cmd
+    };
+    cmd.args(["--partial", "--append-verify", "--progress", "--timeout=30"])
+        .arg("--protect-args")
+        .arg("-e")
+        .arg(&ssh);
+    if settings.bwlimit_kbps > 0 {
+        cmd.arg(format!("--bwlimit={}", settings.bwlimit_kbps));
+    }
+    cmd.arg(path).arg(format!(
+        "{}@{}:{}/",
+        settings.user,
+        settings.host,
+        settings.remote_dir.trim_end_matches('/')
+    ));
+
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run rsync: {}", e))?;
+
+    // stderr 要同时读, 否则警告写满管道后 rsync 阻塞, stdout 也就永远不会结束
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr = std::thread::Builder::new()
+        .name("rsync-stderr".into())
+        .spawn(move || {
+            let mut bytes = Vec::new();
+            let _ = stderr.read_to_end(&mut bytes);
+            bytes
+        });
+    let stderr = match stderr {
+        Ok(stderr) => stderr,
+        Err(e) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("failed to read rsync output: {}", e));
+        }
+    };
+
+    read_progress(child.stdout.take().unwrap(), &mut on_progress);
+
+    let status = child.wait().map_err(|e| format!("rsync failed: {}", e))?;
+    let stderr = stderr.join().unwrap_or_default();
+    if status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&stderr);
+        Err(format!(
+            "rsync exited with {}: {}",
+            status,
+            stderr.lines().last().unwrap_or("")
+        ))
+    }
+}
+
+/// rsync 用 '\r' 刷新进度行, 例如 "  1,234,567  45%  1.23MB/s    0:00:12".
+fn read_progress(stdout: impl Read, on_progress: &mut impl FnMut(u64)) {
+    let mut line = Vec::new();
+    for byte in BufReader::new(stdout).bytes() {
+        let Ok(byte) = byte else {
+            break;
+        };
+        if byte == b'\r' || byte == b'\n' {
+            if let Some(bytes) = parse_progress(&String::from_utf8_lossy(&line)) {
+                on_progress(bytes);
+            }
+            line.clear();
+        } else {
+            line.push(byte);
+        }
+    }
+}
+
+/// rsync 自己拆分 `-e` 的命令, 认单引号和双引号但不认反斜杠.
+/// 用单引号包住, 其中的单引号放进双引号里.
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\"'\"'"))
+}
+
+fn parse_progress(line: &str) -> Option<u64> {
+    let mut fields = line.split_whitespace();
+    let bytes = fields.next()?.replace(',', "").parse().ok()?;
+    fields.next().filter(|pct| pct.ends_with('%'))?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_key_paths_for_rsync() {
+        assert_eq!(
+            quote("/home/me/.ssh/id_ed25519"),
+            "'/home/me/.ssh/id_ed25519'"
+        );
+        assert_eq!(quote("/media/My Keys/id"), "'/media/My Keys/id'");
+        assert_eq!(quote("it's $HOME;"), "'it'\"'\"'s $HOME;'");
+    }
+
+    #[test]
+    fn reads_progress_split_by_carriage_returns() {
+        let output = "file.mov\n      32,768   0%    0.00kB/s    0:00:00\r  \
+                      1,234,567  45%  1.23MB/s    0:00:12\r  2,743,482 100%  1.30MB/s    0:00:02 \
+                      (xfr#1, to-chk=0/1)\n";
+        let mut progress = Vec::new();
+        read_progress(output.as_bytes(), &mut |bytes| progress.push(bytes));
+        assert_eq!(progress, [32_768, 1_234_567, 2_743_482]);
+    }
+}
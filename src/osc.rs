@@ -0,0 +1,338 @@
+//! OSC 远程控制, 给 QLab, Companion 等演出软件用.
+//! 在 UDP 端口上监听 `/camui/...` 消息, 可以把录制状态发回给控制端.
+//! 无法解析的包和未知地址都忽略, 只记录调试日志.
+
+use rosc::{OscMessage, OscPacket, OscType};
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use crate::video::record::RecordEvent;
+
+/// 监听线程每隔这么久检查一次是否该退出.
+const STOP_POLL: Duration = Duration::from_millis(200);
+/// UDP 包的最大长度.
+const MAX_PACKET: usize = 65_536;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct OscSettings {
+    pub enabled: bool,
+    /// 在所有网卡上监听这个 UDP 端口.
+    pub port: u16,
+    /// 录制状态变化时把 `/camui/state` 发到这个 `IP:端口`, 为空时不发送.
+    pub feedback: String,
+}
+
+impl Default for OscSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 9000,
+            feedback: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OscCommand {
+    RecordStart,
+    /// 参数为非零数或 true 时不经过防误停确认.
+    RecordStop {
+        force: bool,
+    },
+    Snapshot,
+    /// 变焦倍数, 例如 2.0.
+    Zoom(f32),
+    /// 线性音量倍数, 1.0 为原样.
+    Gain(f64),
+    /// 把管线拓扑图写到输出目录, 用于远程排查.
+    DumpGraph,
+}
+
+impl OscCommand {
+    /// 参数类型不对或缺少参数时返回 None.
+    fn parse(message: &OscMessage) -> Option<Self> {
+        let value = || match message.args.first()? {
+            OscType::Float(v) => Some(*v as f64),
+            OscType::Double(v) => Some(*v),
+            OscType::Int(v) => Some(*v as f64),
+            OscType::Long(v) => Some(*v as f64),
+            _ => None,
+        };
+        let command = match message.addr.as_str() {
+            "/camui/record/start" => OscCommand::RecordStart,
+            "/camui/record/stop" => OscCommand::RecordStop {
+                force: matches!(message.args.first(), Some(OscType::Bool(true)))
+                    || value().is_some_and(|v| v != 0.0),
+            },
+            "/camui/snapshot" => OscCommand::Snapshot,
+            "/camui/zoom" => OscCommand::Zoom(value().filter(|v| v.is_finite())? as f32),
+            "/camui/gain" => OscCommand::Gain(value().filter(|v| v.is_finite())?),
+            "/camui/debug/graph" => OscCommand::DumpGraph,
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+/// 解析一个 UDP 包, 展开其中的所有消息. 包无效时返回空.
+fn decode(packet: &[u8]) -> Vec<OscMessage> {
+    let packet = match rosc::decoder::decode_udp(packet) {
+        Ok((_, packet)) => packet,
+        Err(e) => {
+            tracing::debug!("ignoring malformed osc packet: {:?}", e);
+            return Vec::new();
+        }
+    };
+    // 不用递归, 嵌套很深的包不会耗尽栈
+    let mut messages = Vec::new();
+    let mut pending = vec![packet];
+    while let Some(packet) = pending.pop() {
+        match packet {
+            OscPacket::Message(message) => messages.push(message),
+            OscPacket::Bundle(bundle) => pending.extend(bundle.content.into_iter().rev()),
+        }
+    }
+    messages
+}
+
+struct Listener {
+    stop: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+/// 等待线程退出, 释放端口后才能重新绑定. 最多阻塞 [STOP_POLL].
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// 监听线程和状态反馈. 修改设置后立即生效, 不需要重启.
+pub(crate) struct Osc {
+    settings: OscSettings,
+    listener: Option<Listener>,
+    tx: Sender<OscCommand>,
+    rx: Receiver<OscCommand>,
+    feedback: Option<(UdpSocket, SocketAddr)>,
+    /// 最近一次绑定失败的原因, 显示在设置中.
+    error: Option<String>,
+}
+
+impl Osc {
+    pub fn new(settings: OscSettings) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let mut osc = Self {
+            settings: OscSettings::default(),
+            listener: None,
+            tx,
+            rx,
+            feedback: None,
+            error: None,
+        };
+        osc.apply(settings);
+        osc
+    }
+
+    pub fn set_settings(&mut self, settings: &OscSettings) {
+        if *settings != self.settings {
+            self.apply(settings.clone());
+        }
+    }
+
+    fn apply(&mut self, settings: OscSettings) {
+        let restart = settings.enabled != self.settings.enabled
+            || settings.port != self.settings.port
+            || (settings.enabled && self.listener.is_none());
+        if restart {
+            self.listener = None;
+            self.error = None;
+            if settings.enabled {
+                match self.listen(settings.port) {
+                    Ok(listener) => self.listener = Some(listener),
+                    Err(e) => {
+                        tracing::error!(port = settings.port, "could not listen for osc: {}", e);
+                        self.error = Some(e.to_string());
+                    }
+                }
+            }
+        }
+        self.feedback = None;
+        if settings.enabled && !settings.feedback.is_empty() {
+            self.feedback = feedback_socket(&settings.feedback)
+                .inspect_err(|e| tracing::warn!(feedback = %settings.feedback, "osc feedback disabled: {}", e))
+                .ok();
+        }
+        self.settings = settings;
+    }
+
+    fn listen(&self, port: u16) -> std::io::Result<Listener> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_read_timeout(Some(STOP_POLL))?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_c = stop.clone();
+        let tx = self.tx.clone();
+        let thread = std::thread::Builder::new()
+            .name("osc".into())
+            .spawn(move || receive(socket, tx, stop_c))?;
+        tracing::info!(port, "osc listening");
+        Ok(Listener {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// 取出上一帧以来收到的指令.
+    pub fn try_recv(&self) -> Option<OscCommand> {
+        self.rx.try_recv().ok()
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// 录制开始和停止时发送 `/camui/state "recording"` 或 `"idle"`.
+    pub fn notify(&self, event: &RecordEvent) {
+        let state = match event {
+            RecordEvent::Started { .. } => "recording",
+            RecordEvent::Stopped { .. } => "idle",
+            _ => return,
+        };
+        let Some((socket, target)) = &self.feedback else {
+            return;
+        };
+        let packet = OscPacket::Message(OscMessage {
+            addr: "/camui/state".to_string(),
+            args: vec![OscType::String(state.to_string())],
+        });
+        match rosc::encoder::encode(&packet) {
+            Ok(bytes) => {
+                if let Err(e) = socket.send_to(&bytes, target) {
+                    tracing::debug!(%target, "could not send osc feedback: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("could not encode osc feedback: {:?}", e),
+        }
+    }
+}
+
+/// 反馈只接受 IP 地址, 发送时不会因为解析域名而卡住界面.
+fn feedback_socket(address: &str) -> Result<(UdpSocket, SocketAddr), String> {
+    let target: SocketAddr = address
+        .parse()
+        .map_err(|_| format!("expected IP:port, got '{}'", address))?;
+    let local: SocketAddr = if target.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(local).map_err(|e| e.to_string())?;
+    socket.set_nonblocking(true).map_err(|e| e.to_string())?;
+    Ok((socket, target))
+}
+
+fn receive(socket: UdpSocket, tx: Sender<OscCommand>, stop: Arc<AtomicBool>) {
+    let mut buf = vec![0u8; MAX_PACKET];
+    while !stop.load(Ordering::Relaxed) {
+        let (len, peer) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("osc receive failed: {}", e);
+                std::thread::sleep(STOP_POLL);
+                continue;
+            }
+        };
+        for message in decode(&buf[..len]) {
+            match OscCommand::parse(&message) {
+                Some(command) => {
+                    tracing::debug!(%peer, ?command, "osc command");
+                    if tx.send(command).is_err() {
+                        return;
+                    }
+                }
+                None => tracing::debug!(%peer, addr = %message.addr, "ignoring osc message"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(addr: &str, args: Vec<OscType>) -> OscMessage {
+        OscMessage {
+            addr: addr.to_string(),
+            args,
+        }
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            OscCommand::parse(&message("/camui/record/start", vec![])),
+            Some(OscCommand::RecordStart)
+        );
+        assert_eq!(
+            OscCommand::parse(&message("/camui/record/stop", vec![])),
+            Some(OscCommand::RecordStop { force: false })
+        );
+        assert_eq!(
+            OscCommand::parse(&message("/camui/record/stop", vec![OscType::Int(1)])),
+            Some(OscCommand::RecordStop { force: true })
+        );
+        assert_eq!(
+            OscCommand::parse(&message("/camui/zoom", vec![OscType::Float(2.5)])),
+            Some(OscCommand::Zoom(2.5))
+        );
+        assert_eq!(
+            OscCommand::parse(&message("/camui/gain", vec![OscType::Int(1)])),
+            Some(OscCommand::Gain(1.0))
+        );
+        assert_eq!(
+            OscCommand::parse(&message("/camui/debug/graph", vec![])),
+            Some(OscCommand::DumpGraph)
+        );
+        assert_eq!(OscCommand::parse(&message("/camui/zoom", vec![])), None);
+        assert_eq!(
+            OscCommand::parse(&message("/camui/gain", vec![OscType::Float(f32::NAN)])),
+            None
+        );
+        assert_eq!(OscCommand::parse(&message("/other", vec![])), None);
+    }
+
+    #[test]
+    fn flattens_bundles_and_ignores_garbage() {
+        let packet = OscPacket::Bundle(rosc::OscBundle {
+            timetag: rosc::OscTime {
+                seconds: 0,
+                fractional: 1,
+            },
+            content: vec![
+                OscPacket::Message(message("/camui/snapshot", vec![])),
+                OscPacket::Message(message("/camui/record/stop", vec![])),
+            ],
+        });
+        let bytes = rosc::encoder::encode(&packet).unwrap();
+        let addrs: Vec<_> = decode(&bytes).into_iter().map(|m| m.addr).collect();
+        assert_eq!(addrs, ["/camui/snapshot", "/camui/record/stop"]);
+
+        assert!(decode(b"not osc").is_empty());
+        assert!(decode(&bytes[..bytes.len() - 3]).is_empty());
+    }
+}
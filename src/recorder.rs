@@ -0,0 +1,439 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+use crate::config::RecordingConfig;
+use crate::file::naming::{self, Slate};
+use crate::video::balance::ChannelGains;
+use crate::video::denoise::DenoiseSettings;
+use crate::video::libcamera::LibcameraControl;
+use crate::video::pattern::TestPattern;
+use crate::video::pip::PipLayout;
+use crate::video::record::{ClipTags, RecordCommand, RecordEvent, RecordSettings, RecordingId};
+use crate::video::sharpen::SharpenSettings;
+use crate::video::silence::SilenceSettings;
+use crate::video::snapshot::SnapshotSettings;
+use crate::video::switcher::SourceCommand;
+
+/// 自动开始录制最多尝试的次数.
+const AUTO_START_ATTEMPTS: u32 = 5;
+/// 第一次重试前的等待时间, 之后每次翻倍.
+const AUTO_START_BACKOFF: Duration = Duration::from_secs(2);
+
+/// 防误停: 按住录制键或按钮这么久才停止.
+pub(crate) const STOP_HOLD: Duration = Duration::from_millis(800);
+/// 防误停: 两次停止请求的最大间隔.
+pub(crate) const STOP_CONFIRM_WINDOW: Duration = Duration::from_secs(2);
+
+/// 两步确认停止. 第一次请求只是预备, [STOP_CONFIRM_WINDOW] 内的第二次才停止.
+/// 远程控制无法按住, 所以按住模式下远程请求也用两步确认.
+#[derive(Debug, Default)]
+pub(crate) struct StopConfirm {
+    armed_at: Option<Instant>,
+}
+
+impl StopConfirm {
+    /// 返回 true 时应当停止.
+    pub fn request(&mut self, now: Instant) -> bool {
+        if self.is_armed(now) {
+            self.armed_at = None;
+            true
+        } else {
+            self.armed_at = Some(now);
+            false
+        }
+    }
+
+    pub fn is_armed(&self, now: Instant) -> bool {
+        self.armed_at
+            .is_some_and(|at| now.duration_since(at) <= STOP_CONFIRM_WINDOW)
+    }
+
+    pub fn reset(&mut self) {
+        self.armed_at = None;
+    }
+}
+
+/// 启动后自动录制的进度.
+#[derive(Debug, Clone, PartialEq)]
+enum AutoStart {
+    /// 等待预览出现第一帧.
+    WaitingForFrame,
+    /// 到时间后发出 Start.
+    Due(Instant),
+    /// 已发出 Start, 等待结果.
+    Pending,
+    /// 重试次数用完.
+    Failed(String),
+}
+
+/// 录制的阶段. 开始和停止都要等 gst 线程确认, 中间有一段过渡.
+/// 目前只有 GPIO 指示灯使用.
+#[cfg_attr(not(feature = "gpio"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecordPhase {
+    Idle,
+    /// 已发出 Start, 还没有收到 Started.
+    Starting,
+    Recording,
+    /// 已发出 Stop, 文件还没有封装完成.
+    Stopping,
+}
+
+/// 录制状态机: 把设置转换为发给 gst 线程的指令, 并根据事件跟踪录制状态.
+/// 界面和无界面模式共用.
+pub(crate) struct Recorder {
+    cmd_tx: mpsc::UnboundedSender<RecordCommand>,
+    is_recording: bool,
+    /// gst 线程确认正在写文件: 收到 Started 之后, Stopped 或 Error 之前.
+    confirmed: bool,
+    started_at: Option<Instant>,
+    /// 正在录制的文件使用的预设, 录制中修改参数不影响它.
+    profile: Option<String>,
+    auto_start: Option<AutoStart>,
+    auto_attempts: u32,
+}
+
+impl Recorder {
+    pub fn new(cmd_tx: mpsc::UnboundedSender<RecordCommand>) -> Self {
+        Self {
+            cmd_tx,
+            is_recording: false,
+            confirmed: false,
+            started_at: None,
+            profile: None,
+            auto_start: None,
+            auto_attempts: 0,
+        }
+    }
+
+    /// 预览出现第一帧后自动开始录制, 失败时按退避重试.
+    pub fn enable_auto_start(&mut self) {
+        self.auto_start = Some(AutoStart::WaitingForFrame);
+        self.auto_attempts = 0;
+    }
+
+    /// 到了自动开始的时间时返回 true, 调用方随后应调用 [Recorder::start].
+    pub fn auto_start_due(&mut self) -> bool {
+        match self.auto_start {
+            Some(AutoStart::Due(at)) if Instant::now() >= at => {
+                self.auto_start = Some(AutoStart::Pending);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 自动录制多次失败后的错误, 需要一直显示直到用户关闭.
+    pub fn auto_start_error(&self) -> Option<&str> {
+        match &self.auto_start {
+            Some(AutoStart::Failed(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn dismiss_auto_start_error(&mut self) {
+        if self.auto_start_error().is_some() {
+            self.auto_start = None;
+        }
+    }
+
+    /// 通过 gst 线程设置采集源的控件, 与录制状态无关.
+    pub fn set_source_controls(&self, controls: String) {
+        let _ = self.cmd_tx.send(RecordCommand::SourceControls(controls));
+    }
+
+    /// 在管线中调整红蓝通道, 用于没有白平衡控件的设备.
+    pub fn set_channel_gains(&self, gains: ChannelGains) {
+        let _ = self.cmd_tx.send(RecordCommand::ChannelGains(gains));
+    }
+
+    pub fn set_libcamera_control(&self, control: LibcameraControl) {
+        let _ = self.cmd_tx.send(RecordCommand::Libcamera(control));
+    }
+
+    pub fn set_test_pattern(&self, pattern: TestPattern) {
+        let _ = self.cmd_tx.send(RecordCommand::TestPattern(pattern));
+    }
+
+    /// 录制中也可以切换, 录像不中断.
+    pub fn switch_source(&self, index: usize) {
+        let _ = self
+            .cmd_tx
+            .send(RecordCommand::Source(SourceCommand::Switch(index)));
+    }
+
+    pub fn set_pip_layout(&self, layout: PipLayout) {
+        let _ = self.cmd_tx.send(RecordCommand::PipLayout(layout));
+    }
+
+    pub fn set_multiview(&self, enabled: bool) {
+        let _ = self.cmd_tx.send(RecordCommand::Multiview(enabled));
+    }
+
+    pub fn set_zoom(&self, zoom: f32) {
+        let _ = self.cmd_tx.send(RecordCommand::Zoom(zoom));
+    }
+
+    pub fn set_denoise(&self, settings: DenoiseSettings) {
+        let _ = self.cmd_tx.send(RecordCommand::Denoise(settings));
+    }
+
+    pub fn set_sharpen(&self, settings: SharpenSettings) {
+        let _ = self.cmd_tx.send(RecordCommand::Sharpen(settings));
+    }
+
+    /// 见 [RecordCommand::Bars].
+    pub fn set_bars(&self, ident: Option<String>) {
+        let _ = self.cmd_tx.send(RecordCommand::Bars(ident));
+    }
+
+    pub fn set_silence(&self, settings: SilenceSettings) {
+        let _ = self.cmd_tx.send(RecordCommand::Silence(settings));
+    }
+
+    pub fn set_audio_gain(&self, gain: f64) {
+        let _ = self.cmd_tx.send(RecordCommand::AudioGain(gain));
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.is_recording
+    }
+
+    #[cfg_attr(not(feature = "gpio"), allow(dead_code))]
+    pub fn phase(&self) -> RecordPhase {
+        match (self.is_recording, self.confirmed) {
+            (false, false) => RecordPhase::Idle,
+            (true, false) => RecordPhase::Starting,
+            (true, true) => RecordPhase::Recording,
+            (false, true) => RecordPhase::Stopping,
+        }
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.started_at.map(|t| t.elapsed())
+    }
+
+    /// 用当前参数开始录制. 参数在这里被复制, 之后的修改只影响下一条录像.
+    pub fn start(
+        &mut self,
+        rec: &RecordingConfig,
+        tags: &ClipTags,
+        slate: &Slate,
+        profile: Option<String>,
+    ) {
+        if self.is_recording {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let file_name = naming::clip_filename(slate, timestamp, rec.container.extension());
+        let settings = RecordSettings {
+            res: rec.resolution,
+            enc: rec.encoder,
+            container: rec.container,
+            bitrate_kbps: rec.bitrate_kbps,
+            av1_speed: rec.av1_speed,
+            ffv1_slices: rec.ffv1_slices,
+            mjpeg_quality: rec.mjpeg_quality,
+            bit_depth: rec.bit_depth,
+            timecode: rec.timecode,
+            frame_rate: rec.frame_rate,
+            tags: tags.clone(),
+            slate: slate.clone(),
+            filepath: rec.output_dir.join(file_name),
+            profile,
+        };
+        self.start_with(settings);
+    }
+
+    /// 用已有的设置开始, 例如修复了输出路径后重试 [RecordEvent::StartBlocked].
+    pub fn start_with(&mut self, settings: RecordSettings) {
+        if self.is_recording {
+            return;
+        }
+        // 手动开始时取消还没触发的自动录制
+        if self.auto_start != Some(AutoStart::Pending) {
+            self.auto_start = None;
+        }
+        self.profile = settings.profile.clone();
+
+        let _ = self.cmd_tx.send(RecordCommand::Start {
+            id: RecordingId::MAIN,
+            settings,
+        });
+        self.is_recording = true;
+        self.started_at = Some(Instant::now());
+    }
+
+    /// 静帧与录像使用同样的命名, 保存在同一个目录.
+    pub fn snapshot(&self, rec: &RecordingConfig, slate: &Slate, settings: &SnapshotSettings) {
+        let _ = self.cmd_tx.send(RecordCommand::Snapshot {
+            path: still_path(rec, slate, settings),
+            settings: settings.clone(),
+        });
+    }
+
+    /// 连拍的各帧在静帧的文件名后加上 `_burst_01` 等序号.
+    pub fn burst(&self, rec: &RecordingConfig, slate: &Slate, settings: &SnapshotSettings) {
+        let _ = self.cmd_tx.send(RecordCommand::Burst {
+            path: still_path(rec, slate, settings),
+            settings: settings.clone(),
+        });
+    }
+
+    /// 把管线的拓扑图写到 `dir`, 用于排查问题.
+    pub fn dump_graph(&self, dir: &Path) {
+        let _ = self.cmd_tx.send(RecordCommand::DumpGraph {
+            dir: dir.to_path_buf(),
+        });
+    }
+
+    /// 让 gst 线程收尾并退出, 不等待结果. 之后的指令都会被忽略.
+    pub fn shutdown(&mut self) {
+        self.auto_start = None;
+        let (done_tx, _) = std::sync::mpsc::channel();
+        let _ = self.cmd_tx.send(RecordCommand::Shutdown(done_tx));
+    }
+
+    pub fn stop(&mut self, slate: &mut Slate) {
+        if !self.is_recording {
+            return;
+        }
+        self.auto_start = None;
+        let _ = self.cmd_tx.send(RecordCommand::Stop {
+            id: RecordingId::MAIN,
+        });
+        self.is_recording = false;
+        self.started_at = None;
+        // 发出停止命令时立即递增, 而不是等待 Stopped 事件:
+        // 快速连续启停时下一条录像不会沿用同一个镜次
+        slate.take += 1;
+    }
+
+    /// 根据 gst 线程的反馈修正状态. 录制状态在发出指令时就已更新,
+    /// 这里只处理启动失败: 迟到的 Started 不能覆盖已经发出的 Stop.
+    pub fn handle_event(&mut self, event: &RecordEvent) {
+        match event {
+            RecordEvent::PreviewStarted => {
+                if self.auto_start == Some(AutoStart::WaitingForFrame) {
+                    self.auto_start = Some(AutoStart::Due(Instant::now()));
+                }
+            }
+            RecordEvent::Started { .. } => {
+                self.confirmed = true;
+                if self.auto_start == Some(AutoStart::Pending) {
+                    self.auto_start = None;
+                }
+            }
+            RecordEvent::Error(e) => self.failed(e.clone()),
+            RecordEvent::StartBlocked { reason, .. } => self.failed(reason.to_string()),
+            RecordEvent::Stopped { .. } => self.confirmed = false,
+            RecordEvent::Verified { .. }
+            | RecordEvent::Progress { .. }
+            | RecordEvent::ScreenShared(_)
+            | RecordEvent::Network(_)
+            | RecordEvent::Snapshot(_)
+            | RecordEvent::Burst(_)
+            | RecordEvent::GraphSaved(_)
+            | RecordEvent::SourceSwitched(_)
+            | RecordEvent::Bars(_)
+            | RecordEvent::Silence(_)
+            | RecordEvent::WriteSpeed { .. }
+            | RecordEvent::Warning(_) => {}
+        }
+    }
+
+    /// 启动失败或被拦下, 自动录制时按退避时间重试.
+    fn failed(&mut self, error: String) {
+        self.is_recording = false;
+        self.confirmed = false;
+        self.started_at = None;
+        if self.auto_start == Some(AutoStart::Pending) {
+            self.auto_attempts += 1;
+            self.auto_start = Some(if self.auto_attempts >= AUTO_START_ATTEMPTS {
+                AutoStart::Failed(error)
+            } else {
+                let backoff = AUTO_START_BACKOFF * 2u32.pow(self.auto_attempts - 1);
+                AutoStart::Due(Instant::now() + backoff)
+            });
+        }
+    }
+}
+
+fn still_path(rec: &RecordingConfig, slate: &Slate, settings: &SnapshotSettings) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let file_name = naming::clip_filename(slate, timestamp, settings.format.extension());
+    rec.output_dir.join(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn stop_needs_a_second_request_in_time() {
+        let mut confirm = StopConfirm::default();
+        let t = Instant::now();
+        assert!(!confirm.request(t));
+        assert!(confirm.request(t + Duration::from_secs(1)));
+        // 确认后重新开始计数
+        assert!(!confirm.request(t + Duration::from_millis(1500)));
+        assert!(!confirm.request(t + Duration::from_secs(4)));
+        assert!(confirm.is_armed(t + Duration::from_secs(5)));
+        confirm.reset();
+        assert!(!confirm.request(t + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn phase_waits_for_confirmation() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mut recorder = Recorder::new(tx);
+        let mut slate = Slate::default();
+        let started = RecordEvent::Started {
+            id: RecordingId::MAIN,
+            path: PathBuf::from("a.mov"),
+            timecode: Default::default(),
+        };
+        recorder.start(
+            &RecordingConfig::default(),
+            &ClipTags::default(),
+            &slate,
+            None,
+        );
+        assert_eq!(recorder.phase(), RecordPhase::Starting);
+        recorder.handle_event(&started);
+        assert_eq!(recorder.phase(), RecordPhase::Recording);
+        recorder.stop(&mut slate);
+        assert_eq!(recorder.phase(), RecordPhase::Stopping);
+
+        // 停止后才迟到的 Started 仍然算作正在收尾
+        recorder.handle_event(&RecordEvent::Stopped {
+            id: RecordingId::MAIN,
+            path: PathBuf::from("a.mov"),
+            duration: Duration::ZERO,
+            size: 0,
+            frames: Default::default(),
+        });
+        recorder.start(
+            &RecordingConfig::default(),
+            &ClipTags::default(),
+            &slate,
+            None,
+        );
+        recorder.stop(&mut slate);
+        assert_eq!(recorder.phase(), RecordPhase::Idle);
+        recorder.handle_event(&started);
+        assert_eq!(recorder.phase(), RecordPhase::Stopping);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// 两次读取 /proc 的最短间隔.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 线程按用途粗略分组, 用于判断负载来自界面还是 GStreamer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ThreadKind {
+    /// 主线程, 即 egui 的界面线程.
+    Ui,
+    /// gst 控制线程和 GStreamer 的流线程 (名称形如 "queue0:src").
+    Gst,
+    Other,
+}
+
+impl ThreadKind {
+    fn classify(is_main: bool, comm: &str) -> Self {
+        if is_main {
+            ThreadKind::Ui
+        } else if comm == crate::video::GST_THREAD || comm.contains(':') {
+            ThreadKind::Gst
+        } else {
+            ThreadKind::Other
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThreadKind::Ui => "ui",
+            ThreadKind::Gst => "gst",
+            ThreadKind::Other => "other",
+        }
+    }
+}
+
+/// 最近一个采样周期内的资源占用.
+/// CPU 百分比与 top 相同, 多核时可以超过 100.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ResourceUsage {
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub threads: BTreeMap<ThreadKind, f64>,
+}
+
+/// 从 /proc/self 读取本进程的 CPU 和内存占用, 每秒最多读取一次.
+pub(crate) struct ResourceSampler {
+    ticks_per_sec: f64,
+    page_size: u64,
+    /// 上一次采样的时间和各组累计的 CPU 时钟数.
+    last: Option<(Instant, u64, BTreeMap<ThreadKind, u64>)>,
+    usage: Option<ResourceUsage>,
+}
+
+impl ResourceSampler {
+    pub fn new() -> Self {
+        // SAFETY: sysconf 没有副作用
+        let (ticks, page) = unsafe {
+            (
+                libc::sysconf(libc::_SC_CLK_TCK),
+                libc::sysconf(libc::_SC_PAGESIZE),
+            )
+        };
+        Self {
+            ticks_per_sec: if ticks > 0 { ticks as f64 } else { 100.0 },
+            page_size: if page > 0 { page as u64 } else { 4096 },
+            last: None,
+            usage: None,
+        }
+    }
+
+    /// 最近一次的结果, 第一次采样后要等一个周期才有数据.
+    pub fn usage(&self) -> Option<&ResourceUsage> {
+        self.usage.as_ref()
+    }
+
+    /// 距上次采样不足一秒时什么也不做. 返回是否得到了新的数据.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if let Some((at, ..)) = &self.last
+            && now.duration_since(*at) < SAMPLE_INTERVAL
+        {
+            return false;
+        }
+        let (total, threads) = match read_ticks() {
+            Ok(ticks) => ticks,
+            Err(e) => {
+                tracing::debug!("could not read /proc/self: {}", e);
+                return false;
+            }
+        };
+        let rss_bytes = std::fs::read_to_string("/proc/self/statm")
+            .ok()
+            .and_then(|s| parse_statm_rss(&s))
+            .map_or(0, |pages| pages * self.page_size);
+
+        let updated = if let Some((at, last_total, last_threads)) = &self.last {
+            let elapsed = now.duration_since(*at).as_secs_f64() * self.ticks_per_sec;
+            let percent =
+                |ticks: u64, before: u64| ticks.saturating_sub(before) as f64 / elapsed * 100.0;
+            let threads = threads
+                .iter()
+                .map(|(kind, ticks)| {
+                    let before = last_threads.get(kind).copied().unwrap_or(0);
+                    (*kind, percent(*ticks, before))
+                })
+                .collect();
+            self.usage = Some(ResourceUsage {
+                cpu_percent: percent(total, *last_total),
+                rss_bytes,
+                threads,
+            });
+            true
+        } else {
+            false
+        };
+        self.last = Some((now, total, threads));
+        updated
+    }
+
+    /// 隐藏时丢弃旧数据, 重新打开后从头计算.
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.usage = None;
+    }
+}
+
+/// 进程累计的 CPU 时钟数, 以及按线程分组的累计值.
+/// 退出的线程会让某一组的累计值变小, 这一周期记为 0.
+fn read_ticks() -> std::io::Result<(u64, BTreeMap<ThreadKind, u64>)> {
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed stat");
+    let total =
+        parse_stat_ticks(&std::fs::read_to_string("/proc/self/stat")?).ok_or_else(invalid)?;
+
+    let pid = std::process::id().to_string();
+    let mut threads = BTreeMap::new();
+    for entry in std::fs::read_dir("/proc/self/task")? {
+        let dir = entry?.path();
+        // 线程在读取过程中退出时跳过
+        let Some(ticks) = read_task(&dir) else {
+            continue;
+        };
+        let comm = std::fs::read_to_string(dir.join("comm")).unwrap_or_default();
+        let is_main = dir.file_name().is_some_and(|tid| tid == pid.as_str());
+        *threads
+            .entry(ThreadKind::classify(is_main, comm.trim_end()))
+            .or_default() += ticks;
+    }
+    Ok((total, threads))
+}
+
+fn read_task(dir: &Path) -> Option<u64> {
+    parse_stat_ticks(&std::fs::read_to_string(dir.join("stat")).ok()?)
+}
+
+/// `stat` 中 utime + stime. 进程名可能包含空格和括号, 从最后一个 ')' 之后开始数.
+fn parse_stat_ticks(stat: &str) -> Option<u64> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    let mut fields = rest.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// `statm` 的第二列是常驻内存页数.
+fn parse_statm_rss(statm: &str) -> Option<u64> {
+    statm.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cpu_ticks_from_stat() {
+        let stat = "4242 (cam ui (x)) S 1 4242 4242 0 -1 4194560 2178 0 0 0 \
+                    150 25 0 0 20 0 12 0 123456 987654321 5432 18446744073709551615";
+        assert_eq!(parse_stat_ticks(stat), Some(175));
+        assert_eq!(parse_stat_ticks("garbage"), None);
+    }
+
+    #[test]
+    fn classifies_threads() {
+        assert_eq!(ThreadKind::classify(true, "cam-ui"), ThreadKind::Ui);
+        assert_eq!(ThreadKind::classify(false, "gst"), ThreadKind::Gst);
+        assert_eq!(ThreadKind::classify(false, "q_v:src"), ThreadKind::Gst);
+        assert_eq!(
+            ThreadKind::classify(false, "tokio-runtime-w"),
+            ThreadKind::Other
+        );
+    }
+
+    #[test]
+    fn parses_rss_pages_from_statm() {
+        assert_eq!(
+            parse_statm_rss("61234 5432 1200 10 0 40000 0\n"),
+            Some(5432)
+        );
+    }
+}
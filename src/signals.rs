@@ -0,0 +1,35 @@
+//! SIGINT/SIGTERM 处理. 收到信号后和界面退出走同一条路径:
+//! 停止录制, 等待封装完成, 停止管线后再退出.
+
+/// 等待第一个退出信号, 返回信号名称.
+pub(crate) async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut term = signal(SignalKind::terminate()).expect("SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => "SIGINT",
+            _ = term.recv() => "SIGTERM",
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
+}
+
+/// 在后台线程等待退出信号, 收到后调用 `on_signal`. 用于没有 tokio 运行时的界面模式.
+pub(crate) fn spawn_watcher(on_signal: impl FnOnce(&'static str) + Send + 'static) {
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            tracing::error!("could not install signal handler: {}", e);
+            return;
+        }
+    };
+    std::thread::spawn(move || on_signal(runtime.block_on(shutdown_signal())));
+}
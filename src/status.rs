@@ -0,0 +1,413 @@
+//! 通过 WebSocket 实时推送状态, 给墙上的看板用, 不需要轮询.
+//! 界面 (或无界面模式) 把收到的录制事件转发到这里的广播通道, 每个客户端各自订阅.
+//! 广播从不阻塞发送方, 跟不上的客户端被断开, 不影响其他客户端.
+
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, watch};
+use tokio_tungstenite::tungstenite::{Message, Utf8Bytes};
+
+use crate::video::record::RecordEvent;
+
+/// 消息格式的版本. 只增加字段或事件时不变, 修改或删除字段时加一.
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+/// 每个客户端最多积压的消息. 电平每秒 10 条, 落后几秒后断开.
+const CHANNEL_CAPACITY: usize = 64;
+const AUDIO_INTERVAL: Duration = Duration::from_millis(100);
+const DISK_INTERVAL: Duration = Duration::from_secs(10);
+/// 一条消息这么久还没写出去就认为客户端已经失联.
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct StatusSettings {
+    pub enabled: bool,
+    /// 监听地址, 默认只接受本机连接.
+    pub listen: String,
+}
+
+impl Default for StatusSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: String::from("127.0.0.1:8765"),
+        }
+    }
+}
+
+/// 推送给客户端的每条 JSON 消息, 例如
+/// `{"version":1,"timestamp":"...","event":"audio-level","db":-18.5}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct StatusMessage {
+    pub version: u32,
+    /// RFC 3339 格式.
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub event: StatusEvent,
+}
+
+impl StatusMessage {
+    pub fn new(event: StatusEvent) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            timestamp: chrono::Local::now().to_rfc3339(),
+            event,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub(crate) enum StatusEvent {
+    /// 连接后首先收到, 说明当前状态.
+    Hello {
+        recording: bool,
+    },
+    RecordingStarted {
+        path: String,
+    },
+    RecordingStopped {
+        path: String,
+        duration_secs: f64,
+        size_bytes: u64,
+        dropped_frames: u64,
+    },
+    /// 大约每秒 10 次, 通常在 -60 到 0 之间.
+    AudioLevel {
+        db: f32,
+    },
+    /// 本次录制丢弃的帧达到了提示的阈值.
+    DroppedFrames {
+        dropped: u64,
+    },
+    /// 输出目录所在磁盘的可用空间, 每 10 秒一次.
+    DiskSpace {
+        path: String,
+        free_bytes: u64,
+    },
+    Warning {
+        message: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl StatusEvent {
+    /// 只转发看板关心的录制事件.
+    fn from_record_event(event: &RecordEvent) -> Option<Self> {
+        Some(match event {
+            RecordEvent::Started { path, .. } => StatusEvent::RecordingStarted {
+                path: path.display().to_string(),
+            },
+            RecordEvent::Stopped {
+                path,
+                duration,
+                size,
+                frames,
+                ..
+            } => StatusEvent::RecordingStopped {
+                path: path.display().to_string(),
+                duration_secs: duration.as_secs_f64(),
+                size_bytes: *size,
+                dropped_frames: frames.dropped,
+            },
+            RecordEvent::Warning(message) => StatusEvent::Warning {
+                message: message.clone(),
+            },
+            RecordEvent::Error(message) => StatusEvent::Error {
+                message: message.clone(),
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// 远程状态 (状态服务器和 MQTT) 共用的录制状态和输出目录, 由 [StatusHub] 更新.
+#[derive(Clone)]
+pub(crate) struct RemoteState {
+    recording: watch::Sender<bool>,
+    output_dir: Arc<Mutex<PathBuf>>,
+}
+
+impl RemoteState {
+    fn new(output_dir: &Path) -> Self {
+        Self {
+            recording: watch::Sender::new(false),
+            output_dir: Arc::new(Mutex::new(output_dir.to_path_buf())),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        *self.recording.borrow()
+    }
+
+    /// 开始或停止录制时收到通知.
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.recording.subscribe()
+    }
+
+    /// 报告这里的可用空间.
+    pub fn output_dir(&self) -> PathBuf {
+        self.output_dir.lock().clone()
+    }
+}
+
+/// 所有客户端共用的广播通道. 没有客户端时发布几乎没有开销.
+#[derive(Clone)]
+pub(crate) struct StatusHub {
+    tx: broadcast::Sender<Utf8Bytes>,
+    state: RemoteState,
+}
+
+impl StatusHub {
+    /// 设置启用时在单独的线程中监听, 地址无法绑定时只记录错误.
+    pub fn spawn(
+        settings: &StatusSettings,
+        audio_level: Arc<Mutex<f32>>,
+        output_dir: &Path,
+    ) -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let hub = Self {
+            tx,
+            state: RemoteState::new(output_dir),
+        };
+        if !settings.enabled {
+            return hub;
+        }
+        let listen = settings.listen.clone();
+        let server = hub.clone();
+        let spawned = std::thread::Builder::new()
+            .name("status".into())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        tracing::error!("could not start status server: {}", e);
+                        return;
+                    }
+                };
+                runtime.block_on(server.serve(&listen, audio_level));
+            });
+        if let Err(e) = spawned {
+            tracing::error!("could not start status server: {}", e);
+        }
+        hub
+    }
+
+    pub fn publish(&self, event: StatusEvent) {
+        if self.tx.receiver_count() == 0 {
+            return;
+        }
+        match serde_json::to_string(&StatusMessage::new(event)) {
+            // 没有订阅者时返回错误, 忽略
+            Ok(text) => drop(self.tx.send(text.into())),
+            Err(e) => tracing::warn!("could not encode status: {}", e),
+        }
+    }
+
+    /// 交给 MQTT 等其他报告状态的地方, 与这里看到的始终一致.
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub fn state(&self) -> RemoteState {
+        self.state.clone()
+    }
+
+    /// 与 [crate::webhook::Webhooks::notify] 一样在收到录制事件时调用.
+    /// 共用 [RemoteState] 的 MQTT 随之重新发布状态.
+    pub fn notify(&self, event: &RecordEvent) {
+        match event {
+            RecordEvent::Started { .. } => drop(self.state.recording.send_replace(true)),
+            RecordEvent::Stopped { .. } => drop(self.state.recording.send_replace(false)),
+            _ => {}
+        }
+        if let Some(event) = StatusEvent::from_record_event(event) {
+            self.publish(event);
+        }
+    }
+
+    /// 设置中修改输出目录后调用, 之后报告新目录的可用空间.
+    pub fn set_output_dir(&self, dir: &Path) {
+        *self.state.output_dir.lock() = dir.to_path_buf();
+    }
+
+    async fn serve(self, listen: &str, audio_level: Arc<Mutex<f32>>) {
+        let listener = match TcpListener::bind(listen).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::error!(%listen, "could not listen for status clients: {}", e);
+                return;
+            }
+        };
+        tracing::info!(%listen, "status server listening");
+        tokio::spawn(self.clone().sample(audio_level));
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    let hello = StatusEvent::Hello {
+                        recording: self.state.is_recording(),
+                    };
+                    tokio::spawn(serve_client(stream, peer, self.tx.subscribe(), hello));
+                }
+                Err(e) => tracing::warn!("could not accept status client: {}", e),
+            }
+        }
+    }
+
+    /// 定期发布电平和可用空间, 只在有客户端时读取.
+    async fn sample(self, audio_level: Arc<Mutex<f32>>) {
+        let mut audio = tokio::time::interval(AUDIO_INTERVAL);
+        let mut disk = tokio::time::interval(DISK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = audio.tick() => {
+                    let db = *audio_level.lock();
+                    self.publish(StatusEvent::AudioLevel { db });
+                }
+                _ = disk.tick() => {
+                    if self.tx.receiver_count() == 0 {
+                        continue;
+                    }
+                    let path = self.state.output_dir();
+                    match crate::file::free_space(&path) {
+                        Ok(free_bytes) => self.publish(StatusEvent::DiskSpace {
+                            path: path.display().to_string(),
+                            free_bytes,
+                        }),
+                        Err(e) => tracing::debug!(path = %path.display(), "could not read free space: {}", e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn serve_client(
+    stream: TcpStream,
+    peer: std::net::SocketAddr,
+    mut rx: broadcast::Receiver<Utf8Bytes>,
+    hello: StatusEvent,
+) {
+    let ws = match tokio_tungstenite::accept_async(stream).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            tracing::debug!(%peer, "status handshake failed: {}", e);
+            return;
+        }
+    };
+    tracing::info!(%peer, "status client connected");
+    let (mut sink, mut incoming) = ws.split();
+    let mut next = serde_json::to_string(&StatusMessage::new(hello))
+        .ok()
+        .map(Utf8Bytes::from);
+    loop {
+        if let Some(text) = next.take() {
+            match tokio::time::timeout(SEND_TIMEOUT, sink.send(Message::Text(text))).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    tracing::info!(%peer, "status client gone: {}", e);
+                    return;
+                }
+                Err(_) => {
+                    tracing::warn!(%peer, "dropping stalled status client");
+                    return;
+                }
+            }
+        }
+        tokio::select! {
+            message = rx.recv() => match message {
+                Ok(text) => next = Some(text),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(%peer, skipped, "dropping slow status client");
+                    let _ = sink.close().await;
+                    return;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            // 客户端不需要发送任何内容, 读取只是为了回应 ping 和发现断开
+            message = incoming.next() => match message {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => {
+                    tracing::info!(%peer, "status client disconnected");
+                    return;
+                }
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::record::RecordingId;
+
+    #[test]
+    fn messages_are_flat_and_versioned() {
+        let message = StatusMessage {
+            version: SCHEMA_VERSION,
+            timestamp: "2024-05-01T12:00:00+02:00".to_string(),
+            event: StatusEvent::AudioLevel { db: -18.5 },
+        };
+        let json: serde_json::Value = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": 1,
+                "timestamp": "2024-05-01T12:00:00+02:00",
+                "event": "audio-level",
+                "db": -18.5,
+            })
+        );
+        let text = serde_json::to_string(&message).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StatusMessage>(&text).unwrap(),
+            message
+        );
+    }
+
+    #[test]
+    fn forwards_only_dashboard_events() {
+        let started = RecordEvent::Started {
+            id: RecordingId::MAIN,
+            path: PathBuf::from("/rec/a.mov"),
+            timecode: Default::default(),
+        };
+        assert_eq!(
+            StatusEvent::from_record_event(&started),
+            Some(StatusEvent::RecordingStarted {
+                path: "/rec/a.mov".to_string()
+            })
+        );
+        assert_eq!(
+            StatusEvent::from_record_event(&RecordEvent::PreviewStarted),
+            None
+        );
+    }
+
+    #[test]
+    fn slow_subscriber_does_not_block_others() {
+        let hub = StatusHub::spawn(
+            &StatusSettings::default(),
+            Arc::new(Mutex::new(-60.0)),
+            Path::new("/tmp"),
+        );
+        let mut slow = hub.tx.subscribe();
+        let mut fast = hub.tx.subscribe();
+        for i in 0..CHANNEL_CAPACITY * 2 {
+            hub.publish(StatusEvent::DroppedFrames { dropped: i as u64 });
+            fast.try_recv().unwrap();
+        }
+        assert!(matches!(
+            slow.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(_))
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const THERMAL_DIR: &str = "/sys/class/thermal";
+/// 读取温度的间隔.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// 录制期间写日志的间隔.
+const LOG_INTERVAL: Duration = Duration::from_secs(60);
+/// 温度降到阈值以下这么多才恢复, 避免在阈值附近反复提示.
+const HYSTERESIS: f32 = 2.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ThermalSettings {
+    /// `/sys/class/thermal` 下的目录名 (例如 "thermal_zone0") 或它的 type
+    /// (例如 "cpu-thermal"). 为空时使用第一个.
+    pub zone: String,
+    pub warn_celsius: f32,
+    pub critical_celsius: f32,
+}
+
+impl Default for ThermalSettings {
+    fn default() -> Self {
+        Self {
+            zone: String::new(),
+            warn_celsius: 70.0,
+            critical_celsius: 80.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ThermalLevel {
+    Normal,
+    Warm,
+    Hot,
+}
+
+/// 一次录制期间的最低和最高温度, 写入 sidecar.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TemperatureRange {
+    pub min_celsius: f32,
+    pub max_celsius: f32,
+}
+
+impl TemperatureRange {
+    fn add(range: Option<Self>, celsius: f32) -> Self {
+        match range {
+            Some(r) => Self {
+                min_celsius: r.min_celsius.min(celsius),
+                max_celsius: r.max_celsius.max(celsius),
+            },
+            None => Self {
+                min_celsius: celsius,
+                max_celsius: celsius,
+            },
+        }
+    }
+}
+
+/// 定期读取 SoC 温度. 没有可用的 thermal zone 时所有读数都是 None.
+pub(crate) struct ThermalMonitor {
+    settings: ThermalSettings,
+    /// 所选 zone 的 `temp` 文件.
+    path: Option<PathBuf>,
+    current: Option<f32>,
+    level: ThermalLevel,
+    read_at: Option<Instant>,
+    logged_at: Option<Instant>,
+    /// 正在录制时为 Some.
+    range: Option<Option<TemperatureRange>>,
+}
+
+impl ThermalMonitor {
+    pub fn new(settings: ThermalSettings) -> Self {
+        let path = find_zone(Path::new(THERMAL_DIR), &settings.zone);
+        if path.is_none() {
+            tracing::info!(zone = %settings.zone, "no thermal zone found, temperature is not shown");
+        }
+        Self {
+            settings,
+            path,
+            current: None,
+            level: ThermalLevel::Normal,
+            read_at: None,
+            logged_at: None,
+            range: None,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: ThermalSettings) {
+        if settings.zone != self.settings.zone {
+            self.path = find_zone(Path::new(THERMAL_DIR), &settings.zone);
+            self.current = None;
+            self.read_at = None;
+        }
+        self.settings = settings;
+    }
+
+    /// 最近一次读数, 摄氏度.
+    pub fn current(&self) -> Option<f32> {
+        self.current
+    }
+
+    pub fn level(&self) -> ThermalLevel {
+        self.level
+    }
+
+    pub fn start_recording(&mut self) {
+        self.range = Some(None);
+        self.logged_at = None;
+    }
+
+    /// 结束录制并返回期间的温度范围.
+    pub fn finish_recording(&mut self) -> Option<TemperatureRange> {
+        self.range.take().flatten()
+    }
+
+    /// 到时间时读取一次温度. 温度升到更高的级别时返回新级别, 用于提示.
+    pub fn poll(&mut self, now: Instant) -> Option<ThermalLevel> {
+        let path = self.path.as_ref()?;
+        if self
+            .read_at
+            .is_some_and(|at| now.duration_since(at) < POLL_INTERVAL)
+        {
+            return None;
+        }
+        self.read_at = Some(now);
+        let celsius = match read_celsius(path) {
+            Ok(celsius) => celsius,
+            Err(e) => {
+                tracing::debug!(path = %path.display(), "could not read temperature: {}", e);
+                self.current = None;
+                return None;
+            }
+        };
+        self.current = Some(celsius);
+
+        if let Some(range) = &mut self.range {
+            *range = Some(TemperatureRange::add(*range, celsius));
+            if self
+                .logged_at
+                .is_none_or(|at| now.duration_since(at) >= LOG_INTERVAL)
+            {
+                self.logged_at = Some(now);
+                tracing::info!(celsius, "temperature");
+            }
+        }
+
+        let level = classify(&self.settings, self.level, celsius);
+        let raised = level > self.level;
+        self.level = level;
+        raised.then_some(level)
+    }
+}
+
+fn classify(settings: &ThermalSettings, previous: ThermalLevel, celsius: f32) -> ThermalLevel {
+    // 已经处于某个级别时, 要低于阈值一段才降级
+    let threshold = |limit: f32, level: ThermalLevel| {
+        if previous >= level {
+            limit - HYSTERESIS
+        } else {
+            limit
+        }
+    };
+    if celsius >= threshold(settings.critical_celsius, ThermalLevel::Hot) {
+        ThermalLevel::Hot
+    } else if celsius >= threshold(settings.warn_celsius, ThermalLevel::Warm) {
+        ThermalLevel::Warm
+    } else {
+        ThermalLevel::Normal
+    }
+}
+
+/// 按目录名或 type 查找 zone, `zone` 为空时取按名称排序后的第一个.
+fn find_zone(dir: &Path, zone: &str) -> Option<PathBuf> {
+    let mut zones: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("thermal_zone"))
+        })
+        .collect();
+    zones.sort();
+    zones
+        .into_iter()
+        .find(|path| {
+            zone.is_empty()
+                || path.file_name().is_some_and(|name| name == zone)
+                || std::fs::read_to_string(path.join("type")).is_ok_and(|t| t.trim() == zone)
+        })
+        .map(|path| path.join("temp"))
+}
+
+/// 内核以千分之一摄氏度为单位.
+fn read_celsius(path: &Path) -> std::io::Result<f32> {
+    let text = std::fs::read_to_string(path)?;
+    let millis: i64 = text.trim().parse().map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, text.trim().to_string())
+    })?;
+    Ok(millis as f32 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_use_hysteresis_when_cooling_down() {
+        let settings = ThermalSettings::default();
+        let level = classify(&settings, ThermalLevel::Normal, 69.0);
+        assert_eq!(level, ThermalLevel::Normal);
+        let level = classify(&settings, level, 81.0);
+        assert_eq!(level, ThermalLevel::Hot);
+        // 略低于阈值仍然保持
+        let level = classify(&settings, level, 79.0);
+        assert_eq!(level, ThermalLevel::Hot);
+        let level = classify(&settings, level, 77.0);
+        assert_eq!(level, ThermalLevel::Warm);
+        let level = classify(&settings, level, 67.0);
+        assert_eq!(level, ThermalLevel::Normal);
+    }
+
+    #[test]
+    fn finds_zone_by_name_or_type() {
+        let dir = std::env::temp_dir().join(format!("cam-ui-thermal-{}", std::process::id()));
+        for (zone, kind, temp) in [
+            ("thermal_zone0", "acpitz", "41000"),
+            ("thermal_zone1", "cpu-thermal", "55250"),
+        ] {
+            std::fs::create_dir_all(dir.join(zone)).unwrap();
+            std::fs::write(dir.join(zone).join("type"), format!("{}\n", kind)).unwrap();
+            std::fs::write(dir.join(zone).join("temp"), format!("{}\n", temp)).unwrap();
+        }
+        std::fs::create_dir_all(dir.join("cooling_device0")).unwrap();
+
+        let first = find_zone(&dir, "").unwrap();
+        assert_eq!(read_celsius(&first).unwrap(), 41.0);
+        let cpu = find_zone(&dir, "cpu-thermal").unwrap();
+        assert_eq!(cpu, find_zone(&dir, "thermal_zone1").unwrap());
+        assert_eq!(read_celsius(&cpu).unwrap(), 55.25);
+        assert!(find_zone(&dir, "gpu-thermal").is_none());
+        assert!(find_zone(&dir.join("missing"), "").is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,132 +1,1348 @@
 use eframe::egui;
 use parking_lot::Mutex;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use crate::video::record::{Container, RecordCommand, RecordSettings, Resolution, VideoEncoder};
+use crate::battery::{BatteryMonitor, BatterySettings};
+use crate::config::{
+    BehaviorSettings, CaptureConfig, ChromeSettings, Config, CountdownSettings, LoadedConfig,
+    Overrides, Profile, ProgramSettings, RecordingConfig, ReferenceSettings, TallySettings,
+    ThemeSettings, WindowSettings,
+};
+use crate::file::markers::{Marker, MarkerSettings};
+use crate::file::naming::Slate;
+use crate::file::remaining::RemainingSettings;
+use crate::file::session_log::{SessionLog, Severity};
+use crate::file::sidecar::{Sidecar, TakeRating};
+use crate::file::watch::DirWatcher;
+use crate::i18n::{Language, tr, tr_args};
+use crate::inhibit::SleepInhibitor;
+use crate::keymap::{Action, Keymap};
+use crate::logging::LogConsole;
+use crate::offload::{OffloadSettings, Uploader};
+use crate::osc::{Osc, OscSettings};
+use crate::recorder::{Recorder, StopConfirm};
+use crate::resources::ResourceSampler;
+use crate::status::{StatusEvent, StatusHub, StatusSettings};
+use crate::thermal::{ThermalMonitor, ThermalSettings};
+use crate::video::assist::AssistSettings;
+use crate::video::balance::WhiteBalance;
+use crate::video::benchmark::{Benchmark, BenchmarkReport};
+use crate::video::bus::BufferStats;
+use crate::video::controls::{
+    ControlPanel, Exposure, ExposureBias, ExposureMode, ExposureState, Focus, SensorGain,
+    WhiteBalanceControl,
+};
+use crate::video::denoise::DenoiseSettings;
+use crate::video::exchange::FrameExchange;
+use crate::video::gif::{GifQueue, GifSettings};
+use crate::video::histogram::{ExposureAdvisor, ExposureWarningSettings};
+use crate::video::libcamera::Capabilities;
+use crate::video::overlay::{OverlayConfig, SharedOverlay};
+use crate::video::pipeline::MAX_AUDIO_GAIN;
+use crate::video::record::{ClipTags, RecordCommand, RecordEvent, RecordProgress, RecordingId};
+use crate::video::rtsp::NetworkStats;
+use crate::video::screen::{self, CaptureArea, ScreenBackend};
+use crate::video::sharpen::SharpenSettings;
+use crate::video::shutter::ShutterSpeed;
+use crate::video::silence::SilenceSettings;
+use crate::video::snapshot::SnapshotSettings;
+use crate::video::timecode::SharedTimecode;
+use crate::video::{MonitorFrames, PreviewFrame};
+use crate::webhook::{WebhookSettings, Webhooks};
+
+mod assist;
+mod bars;
+mod battery;
+mod benchmark;
+mod black_frame;
+mod browser;
+mod camera;
+mod chrome;
+mod clipboard;
+mod console;
+mod countdown;
+mod debug;
+mod denoise;
+mod device_controls;
+mod dir_watch;
+mod disk;
+mod exposure_warning;
+mod focus;
+mod fps;
+mod freeze;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod gif;
+#[cfg(feature = "gpio")]
+mod gpio;
+mod language;
+mod latency;
+mod lock;
+mod markers;
+#[cfg(feature = "midi")]
+mod midi;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod multiview;
+mod network_camera;
+mod osc;
+mod overlay;
+mod pip;
+mod profiles;
+mod program;
+mod punch_in;
+mod quit;
+mod reference;
+mod scale;
+mod screen_capture;
+mod session_log;
+mod settings;
+mod sharpen;
+mod shortcuts;
+mod signal;
+mod silence;
+mod snapshot;
+mod sources;
+mod start_blocked;
+mod stop_guard;
+mod tally;
+mod theme;
+mod thermal;
+mod toast;
+mod touch;
+mod transfers;
+mod white_balance;
+mod widgets;
+mod window;
+mod write_speed;
+mod zoom;
+
+use device_controls::DeviceWatch;
+use fps::PreviewRate;
+use latency::LatencyStats;
+use network_camera::ConnectionTest;
+use program::ProgramWindow;
+use punch_in::PreviewView;
+use quit::QuitState;
+use theme::Theme;
+use toast::{ToastLevel, Toasts};
+
+/// 设置最后一次修改后等待多久写盘, 避免拖动数值时每帧都保存.
+const CONFIG_SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// 主界面的布局尺寸, 单位是点. 界面缩放只改变 pixels_per_point, 这些值不需要再乘.
+const EDGE_MARGIN: f32 = 20.0;
+const BOTTOM_BAR_HEIGHT: f32 = 80.0;
+const PARAM_SPACING: f32 = 60.0;
+const PARAM_LABEL_SIZE: f32 = 10.0;
+const PARAM_VALUE_SIZE: f32 = 24.0;
 
 pub struct CameraApp {
-    frame_buffer: Arc<Mutex<Option<egui::ColorImage>>>,
+    frame_buffer: FrameExchange<PreviewFrame>,
+    /// 原样的预览, 节目输出和画中画也使用.
     texture: Option<egui::TextureHandle>,
-    rec_cmd_tx: mpsc::UnboundedSender<RecordCommand>,
-    is_recording: bool,
+    /// 叠加了峰值或斑马纹的预览, 只在主窗口中显示.
+    assist_texture: Option<egui::TextureHandle>,
+    /// 最近一次收到预览帧的时间, 用于判断信号中断.
+    last_frame_at: Option<Instant>,
+    /// 主窗口显示的那一帧的采集时刻.
+    frame_captured_at: Option<Instant>,
+    /// 定格时主窗口停在这一帧.
+    frozen: Option<freeze::Frozen>,
+    /// gst 线程正在输出彩条.
+    bars: bool,
+    /// 录制中持续无声, gst 线程检测.
+    silent: bool,
+    /// 主录像的存储写入跟不上, gst 线程检测.
+    write_underrun: bool,
+    signal_lost: bool,
+    recorder: Recorder,
+    rec_event_rx: mpsc::UnboundedReceiver<RecordEvent>,
     iso: u32,
-    shutter: String,
+    /// V4L2 摄像头的增益控件, 没有时 ISO 显示为 AUTO.
+    gain: Option<SensorGain>,
+    /// ISO 按 1/3 档调整.
+    iso_fine: bool,
+    shutter: ShutterSpeed,
+    /// 正在输入的快门速度, 校验通过后才应用.
+    shutter_edit: Option<String>,
+    /// 不支持手动曝光时为原因.
+    exposure: Result<Exposure, String>,
+    /// 自动曝光还是锁定, 手动.
+    ae_mode: ExposureMode,
+    /// 没有时用增益模拟曝光补偿.
+    bias: Option<ExposureBias>,
+    /// 曝光补偿, 以 1/3 EV 为单位.
+    ev_thirds: i32,
+    ev_scroll: f32,
+    white_balance: WhiteBalance,
+    /// 没有时在管线中调整红蓝增益.
+    wb_control: Option<WhiteBalanceControl>,
+    /// 色温滑块的值.
+    wb_kelvin: u32,
+    /// 下一帧预览到来时按灰卡计算白平衡.
+    grey_card_pending: bool,
+    /// 没有对焦马达时为 None, 不显示对焦.
+    focus: Option<Focus>,
+    focus_position: i32,
+    autofocus: bool,
+    focus_scroll: f32,
+    focus_slider_scroll: f32,
+    /// 模拟一次性对焦时, 到这个时间关掉自动对焦.
+    af_release_at: Option<Instant>,
+    /// 当前送给管线的变焦倍数, 平滑地追向 `zoom_target`.
+    zoom: f32,
+    zoom_target: f32,
+    zoom_scroll: f32,
+    /// 使用 libcamerasrc 时它支持的控件, 这时没有 V4L2 控件.
+    libcamera: Option<Capabilities>,
+    /// 录制开始时的曝光和白平衡, 结束后写入 sidecar.
+    recording_exposure: Option<ExposureState>,
+    recording_white_balance: Option<WhiteBalance>,
     audio_level: Arc<Mutex<f32>>,
+    buffer_stats: Arc<Mutex<BufferStats>>,
+    browser: browser::RecordingsBrowser,
+    show_settings: bool,
+    show_transfers: bool,
+    show_session_log: bool,
+    session_log: SessionLog,
+    /// 会话记录面板中的筛选文字.
+    session_query: String,
+    show_device_controls: bool,
+    /// 启动时检查一次, 没有插件时设置中不显示 DeckLink.
+    decklink_available: bool,
+    /// 录制桌面时的范围, 用于检测拍到自己窗口的无限镜像. 门户授权前为 None.
+    screen_area: Option<CaptureArea>,
+    mirror_checked_at: Instant,
+    mirror_warned: bool,
+    /// 设置中可选的 X11 输出.
+    screen_outputs: Vec<screen::Output>,
+    /// 可以切换的各路采集源, 只有一路时为空.
+    source_names: Vec<String>,
+    active_source: usize,
+    /// 节目音频来自当前的源, 否则总是第一路.
+    audio_follows_video: bool,
+    monitors: MonitorFrames,
+    tile_textures: Vec<Option<egui::TextureHandle>>,
+    /// 多画面窗口, 关闭时缩略图分支也关闭.
+    show_multiview: bool,
+    /// 运行中的管线有画中画时, 它是否合成进录像. 设置只在重启后生效.
+    pip_burned_in: Option<bool>,
+    pip_texture: Option<egui::TextureHandle>,
+    /// 第二个窗口中的节目画面.
+    program: ProgramWindow,
+    program_settings: ProgramSettings,
+    tally_settings: TallySettings,
+    overlay_settings: OverlayConfig,
+    /// 与 gst 线程共享, 修改后立即生效.
+    overlay: SharedOverlay,
+    behavior: BehaviorSettings,
+    /// 正在按住录制键停止: 开始的时间和按住的键.
+    stop_hold: Option<(Instant, egui::Key)>,
+    stop_confirm: StopConfirm,
+    /// 最近一次整屏闪光的开始时间和颜色.
+    tally_flash: Option<(Instant, egui::Color32)>,
+    /// 预览放大的范围, 只影响显示.
+    view: PreviewView,
+    /// 当前多指手势开始的时间, 用来识别新手势.
+    gesture_started_at: Option<f64>,
+    /// 当前手势从预览上开始, 不是从按钮上.
+    gesture_on_preview: bool,
+    chrome: ChromeSettings,
+    /// 最近一次按键, 鼠标移动或触摸的时间, 用于自动隐藏界面.
+    last_activity: Instant,
+    window: WindowSettings,
+    /// 由 `--kiosk` 打开, 不保存到配置.
+    kiosk: bool,
+    /// 最近一次移动指针的时间, kiosk 模式下用来隐藏光标.
+    pointer_moved_at: Instant,
+    /// kiosk 模式下开始按住 Esc 的时间.
+    kiosk_exit_since: Option<Instant>,
+    theme: Theme,
+    theme_settings: ThemeSettings,
+    language: Language,
+    keymap: Keymap,
+    /// 正在等待按键的快捷键.
+    capturing_shortcut: Option<Action>,
+    #[cfg(feature = "gamepad")]
+    gamepad: crate::gamepad::GamepadSettings,
+    /// 系统不支持手柄输入时为 None.
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<crate::gamepad::Gamepads>,
+    /// 摇杆对焦不到一步的余量.
+    #[cfg(feature = "gamepad")]
+    gamepad_focus_carry: f32,
+    #[cfg(feature = "midi")]
+    midi_settings: crate::midi::MidiSettings,
+    #[cfg(feature = "midi")]
+    midi: crate::midi::Midi,
+    /// 等待 MIDI 消息来绑定的目标.
+    #[cfg(feature = "midi")]
+    midi_learn: Option<crate::midi::MidiTarget>,
+    #[cfg(feature = "gpio")]
+    gpio_settings: crate::gpio::GpioSettings,
+    #[cfg(feature = "gpio")]
+    gpio: crate::gpio::Gpio,
+    #[cfg(feature = "mqtt")]
+    mqtt_settings: crate::mqtt::MqttSettings,
+    #[cfg(feature = "mqtt")]
+    mqtt: crate::mqtt::Mqtt,
+    /// 第一次切换到中文时加载.
+    cjk_font_loaded: bool,
+    /// 锁定时只响应解锁操作, 录制和预览照常.
+    locked: bool,
+    unlock_pin: String,
+    pin_entry: String,
+    unlock_hold_since: Option<Instant>,
+    /// 锁定时有人操作, 提示显示到这个时间.
+    lock_notice_until: Option<Instant>,
+    /// 锁定时仍然接收指针事件的区域.
+    lock_target: Option<egui::Rect>,
+    /// 按住录制按钮停止时, 开始按下的时间.
+    stop_hold_since: Option<Instant>,
+    /// 为 None 时在第一帧按显示器推测.
+    ui_scale: Option<f32>,
+    /// 网络摄像头最近一次报告的抖动和往返时间, 其他采集源为 None.
+    network: Option<NetworkStats>,
+    /// 设置中最近一次测试网络摄像头连接.
+    rtsp_test: Option<ConnectionTest>,
+    /// 设备报告的所有控件, 不是 V4L2 摄像头时为 None.
+    control_panel: Option<ControlPanel>,
+    control_refresh_at: Instant,
+    /// 用户在控件面板中改过的值, 按修改顺序排列, 摄像头重新连接后依次恢复.
+    control_changes: Vec<(u32, i32)>,
+    device_watch: DeviceWatch,
+    offload: OffloadSettings,
+    uploader: Uploader,
+    webhook: WebhookSettings,
+    webhooks: Webhooks,
+    status_settings: StatusSettings,
+    status: StatusHub,
+    osc_settings: OscSettings,
+    osc: Osc,
+    /// 本次会话写入录像的元数据标签.
+    tags: ClipTags,
+    /// 下一条录像的场记信息.
+    slate: Slate,
+    /// 最近停止的录像, 可以标记 GOOD / NG.
+    last_clip: Option<PathBuf>,
+    /// 输出路径有问题, 等待用户选择修复办法.
+    start_blocked: Option<start_blocked::StartBlocked>,
+    last_rating: Option<TakeRating>,
+    /// 采集源只在启动时生效, 这里保存修改后的值.
+    capture: CaptureConfig,
+    recording: RecordingConfig,
+    countdown_settings: CountdownSettings,
+    /// 按下录制后正在进行的倒计时.
+    countdown: Option<countdown::Countdown>,
+    snapshot: SnapshotSettings,
+    gif_settings: GifSettings,
+    gifs: GifQueue,
+    /// 正在后台进行的编码器测速.
+    benchmark: Option<Benchmark>,
+    /// 上次测速的结果, 设置面板据此提示配置能否实时编码.
+    benchmark_report: Option<BenchmarkReport>,
+    show_benchmark: bool,
+    marker_settings: MarkerSettings,
+    /// 本次录制中的标记, 停止后写入 sidecar.
+    markers: Vec<Marker>,
+    /// 最近一次打标记的时间, 计数短暂高亮.
+    marker_flash: Option<Instant>,
+    profiles: Vec<Profile>,
+    active_profile: Option<String>,
+    /// 设置面板中新预设的名称输入框.
+    new_profile_name: String,
+    /// 最近一次写盘的配置, 用于判断是否需要保存.
+    saved_config: Config,
+    config_path: Option<PathBuf>,
+    /// 命令行参数, 保存时换回配置文件中的值.
+    overrides: Overrides,
+    auto_record: bool,
+    config_changed_at: Option<Instant>,
+    toasts: Toasts,
+    quit: QuitState,
+    /// 由信号处理线程设置.
+    signalled: Arc<AtomicBool>,
+    inhibitor: SleepInhibitor,
+    log_console: LogConsole,
+    show_console: bool,
+    /// 日志面板只显示这个级别及以上的记录.
+    console_level: tracing::Level,
+    log_level: String,
+    drop_warning_frames: u64,
+    remaining: RemainingSettings,
+    disk: disk::DiskSpace,
+    /// 监视输出目录, 第一帧时创建.
+    dir_watch: Option<DirWatcher>,
+    /// 上一次发给 gst 线程的降噪设置.
+    sent_denoise: Option<DenoiseSettings>,
+    sent_sharpen: Option<SharpenSettings>,
+    audio_gain: f64,
+    silence: SilenceSettings,
+    sent_silence: Option<SilenceSettings>,
+    /// 本次录制已经提示过丢帧.
+    drop_warned: bool,
+    /// 调试用的画面延迟叠加层, 不保存到配置.
+    show_latency: bool,
+    latency: LatencyStats,
+    show_fps: bool,
+    preview_rate: PreviewRate,
+    show_resources: bool,
+    resources: ResourceSampler,
+    cpu_warning_percent: f64,
+    /// 上一次采样已经超过 CPU 阈值.
+    cpu_spiking: bool,
+    thermal_settings: ThermalSettings,
+    thermal: ThermalMonitor,
+    battery_settings: BatterySettings,
+    battery: BatteryMonitor,
+    exposure_warning: ExposureWarningSettings,
+    exposure_advisor: ExposureAdvisor,
+    black_frame: black_frame::BlackFrameWatch,
+    assist: AssistSettings,
+    reference_settings: ReferenceSettings,
+    reference: Option<reference::Reference>,
+    /// 参考图叠加层是否显示, 不保存到配置.
+    show_reference: bool,
+    /// 下一个预览帧成为参考图.
+    reference_capture_pending: bool,
+    /// 设置面板中参考图 PNG 的路径.
+    reference_path: String,
+    clipboard: clipboard::ClipboardWorker,
+    /// 下一帧复制到剪贴板.
+    clipboard_pending: bool,
+    /// 正在录制的文件大小和码率, 每秒更新.
+    progress: Option<RecordProgress>,
+    /// 最近一次录制写入文件的时间码.
+    timecode: Option<SharedTimecode>,
 }
 
 impl CameraApp {
+    #[allow(clippy::too_many_arguments)] // 大多是与 gst 线程共享的状态
     pub fn new(
-        frame_buffer: Arc<Mutex<Option<egui::ColorImage>>>,
+        loaded: LoadedConfig,
+        frame_buffer: FrameExchange<PreviewFrame>,
+        monitors: MonitorFrames,
+        overlay: SharedOverlay,
         audio_level: Arc<Mutex<f32>>,
+        buffer_stats: Arc<Mutex<BufferStats>>,
         rec_cmd_tx: mpsc::UnboundedSender<RecordCommand>,
+        rec_event_rx: mpsc::UnboundedReceiver<RecordEvent>,
+        log_console: LogConsole,
     ) -> Self {
-        Self {
+        let mut toasts = Toasts::new();
+        if let Some(warning) = loaded.warning {
+            toasts.push(ToastLevel::Warning, warning);
+        }
+        let Config {
+            capture,
+            recording,
+            countdown,
+            snapshot,
+            gif,
+            markers,
+            tags,
+            slate,
+            offload,
+            webhook,
+            status,
+            osc,
+            profiles,
+            active_profile,
+            auto_record,
+            log_level,
+            drop_warning_frames,
+            remaining,
+            cpu_warning_percent,
+            thermal,
+            battery,
+            exposure_warning,
+            assist,
+            reference,
+            program,
+            tally,
+            overlay: overlay_settings,
+            behavior,
+            ui_scale,
+            chrome,
+            window,
+            unlock_pin,
+            theme,
+            language,
+            keymap,
+            audio_gain,
+            silence,
+            #[cfg(feature = "gamepad")]
+            gamepad,
+            #[cfg(feature = "midi")]
+            midi,
+            #[cfg(feature = "gpio")]
+            gpio,
+            #[cfg(feature = "mqtt")]
+            mqtt,
+            ..
+        } = loaded.config;
+        let status_hub = StatusHub::spawn(&status, audio_level.clone(), &recording.output_dir);
+        let mut recorder = Recorder::new(rec_cmd_tx);
+        // 管线总是以原样音量启动. 超出范围时 volume 元素会拒绝
+        let audio_gain = audio_gain.clamp(0.0, MAX_AUDIO_GAIN);
+        recorder.set_audio_gain(audio_gain);
+        if auto_record {
+            recorder.enable_auto_start();
+        }
+        // 门户的范围在授权后由 gst 线程报告
+        let screen_area = capture
+            .screen
+            .as_ref()
+            .filter(|s| s.backend == ScreenBackend::X11)
+            .map(|s| s.x11_area());
+        let source_names = capture.source_names();
+        let audio_follows_video = capture.switching.audio_follows_video;
+        let pip_burned_in = capture.sources().pip.map(|p| p.burn_in);
+        let mut app = Self {
+            program: ProgramWindow::new(frame_buffer.clone()),
+            program_settings: program,
+            tally_settings: tally,
+            tally_flash: None,
+            overlay_settings,
+            overlay,
+            behavior,
+            stop_hold: None,
+            stop_confirm: StopConfirm::default(),
+            ui_scale,
+            stop_hold_since: None,
+            theme: Theme::new(&theme),
+            theme_settings: theme,
+            language,
+            keymap,
+            capturing_shortcut: None,
+            #[cfg(feature = "gamepad")]
+            gamepad,
+            #[cfg(feature = "gamepad")]
+            gamepads: crate::gamepad::Gamepads::new(),
+            #[cfg(feature = "gamepad")]
+            gamepad_focus_carry: 0.0,
+            #[cfg(feature = "midi")]
+            midi_settings: midi,
+            #[cfg(feature = "midi")]
+            midi: crate::midi::Midi::new(),
+            #[cfg(feature = "midi")]
+            midi_learn: None,
+            #[cfg(feature = "gpio")]
+            gpio: crate::gpio::Gpio::spawn(&gpio),
+            #[cfg(feature = "gpio")]
+            gpio_settings: gpio,
+            #[cfg(feature = "mqtt")]
+            mqtt: crate::mqtt::Mqtt::spawn(&mqtt, audio_level.clone(), status_hub.state()),
+            #[cfg(feature = "mqtt")]
+            mqtt_settings: mqtt,
+            cjk_font_loaded: false,
+            locked: false,
+            unlock_pin,
+            pin_entry: String::new(),
+            unlock_hold_since: None,
+            lock_notice_until: None,
+            lock_target: None,
+            chrome,
+            last_activity: Instant::now(),
+            window,
+            kiosk: false,
+            pointer_moved_at: Instant::now(),
+            kiosk_exit_since: None,
+            view: PreviewView::default(),
+            gesture_started_at: None,
+            gesture_on_preview: false,
             frame_buffer,
             texture: None,
-            rec_cmd_tx,
-            is_recording: false,
+            assist_texture: None,
+            frame_captured_at: None,
+            frozen: None,
+            bars: false,
+            silent: false,
+            write_underrun: false,
+            last_frame_at: None,
+            signal_lost: false,
+            recorder,
+            rec_event_rx,
             iso: 800,
-            shutter: "1/500".to_string(),
+            gain: None,
+            iso_fine: false,
+            shutter: ShutterSpeed::default(),
+            shutter_edit: None,
+            exposure: Err(String::new()),
+            ae_mode: ExposureMode::Auto,
+            bias: None,
+            ev_thirds: 0,
+            ev_scroll: 0.0,
+            white_balance: WhiteBalance::Auto,
+            wb_control: None,
+            wb_kelvin: 5600,
+            grey_card_pending: false,
+            focus: None,
+            focus_position: 0,
+            autofocus: false,
+            focus_scroll: 0.0,
+            focus_slider_scroll: 0.0,
+            af_release_at: None,
+            zoom: 1.0,
+            zoom_target: 1.0,
+            zoom_scroll: 0.0,
+            libcamera: None,
+            recording_exposure: None,
+            recording_white_balance: None,
             audio_level,
+            buffer_stats,
+            browser: browser::RecordingsBrowser::new(recording.output_dir.clone()),
+            show_settings: false,
+            show_transfers: false,
+            show_session_log: false,
+            session_log: SessionLog::default(),
+            session_query: String::new(),
+            show_device_controls: false,
+            decklink_available: crate::video::decklink::available(),
+            screen_area,
+            mirror_checked_at: Instant::now(),
+            mirror_warned: false,
+            screen_outputs: screen::list_outputs(),
+            source_names,
+            active_source: 0,
+            audio_follows_video,
+            monitors,
+            tile_textures: Vec::new(),
+            show_multiview: false,
+            pip_burned_in,
+            pip_texture: None,
+            network: None,
+            rtsp_test: None,
+            control_panel: None,
+            control_refresh_at: Instant::now(),
+            control_changes: Vec::new(),
+            device_watch: DeviceWatch::new(None),
+            uploader: Uploader::spawn(offload.clone()),
+            offload,
+            webhooks: Webhooks::spawn(webhook.clone()),
+            webhook,
+            status_settings: status,
+            status: status_hub,
+            osc: Osc::new(osc.clone()),
+            osc_settings: osc,
+            tags,
+            slate,
+            last_clip: None,
+            start_blocked: None,
+            last_rating: None,
+            capture,
+            recording,
+            countdown_settings: countdown,
+            countdown: None,
+            snapshot,
+            gif_settings: gif,
+            gifs: GifQueue::spawn(),
+            benchmark: None,
+            benchmark_report: BenchmarkReport::load(),
+            show_benchmark: false,
+            marker_settings: markers,
+            markers: Vec::new(),
+            marker_flash: None,
+            profiles,
+            active_profile,
+            new_profile_name: String::new(),
+            saved_config: loaded.persisted,
+            config_path: loaded.path,
+            overrides: loaded.overrides,
+            auto_record,
+            config_changed_at: None,
+            toasts,
+            quit: QuitState::Idle,
+            signalled: Arc::new(AtomicBool::new(false)),
+            inhibitor: SleepInhibitor::new(),
+            log_console,
+            show_console: false,
+            console_level: tracing::Level::INFO,
+            log_level,
+            drop_warning_frames,
+            remaining,
+            disk: Default::default(),
+            dir_watch: None,
+            sent_denoise: None,
+            sent_sharpen: None,
+            audio_gain,
+            silence,
+            sent_silence: None,
+            drop_warned: false,
+            show_latency: false,
+            latency: LatencyStats::default(),
+            show_fps: false,
+            preview_rate: PreviewRate::default(),
+            show_resources: false,
+            resources: ResourceSampler::new(),
+            cpu_warning_percent,
+            cpu_spiking: false,
+            thermal: ThermalMonitor::new(thermal.clone()),
+            thermal_settings: thermal,
+            battery: BatteryMonitor::new(battery.clone()),
+            battery_settings: battery,
+            exposure_warning,
+            exposure_advisor: ExposureAdvisor::default(),
+            black_frame: Default::default(),
+            assist,
+            reference_settings: reference,
+            reference: None,
+            show_reference: false,
+            reference_capture_pending: false,
+            reference_path: String::new(),
+            clipboard: clipboard::ClipboardWorker::spawn(),
+            clipboard_pending: false,
+            progress: None,
+            timecode: None,
+        };
+        app.log_app_started();
+        app.open_camera_controls();
+        app
+    }
+
+    /// 需要保存的设置快照, 不包含命令行覆盖的值.
+    fn config(&self) -> Config {
+        let mut config = Config {
+            capture: self.capture.clone(),
+            recording: self.recording.clone(),
+            countdown: self.countdown_settings.clone(),
+            snapshot: self.snapshot.clone(),
+            gif: self.gif_settings.clone(),
+            markers: self.marker_settings.clone(),
+            tags: self.tags.clone(),
+            slate: self.slate.clone(),
+            offload: self.offload.clone(),
+            webhook: self.webhook.clone(),
+            status: self.status_settings.clone(),
+            osc: self.osc_settings.clone(),
+            profiles: self.profiles.clone(),
+            active_profile: self.active_profile.clone(),
+            auto_record: self.auto_record,
+            log_level: self.log_level.clone(),
+            drop_warning_frames: self.drop_warning_frames,
+            remaining: self.remaining.clone(),
+            audio_gain: self.audio_gain,
+            silence: self.silence,
+            cpu_warning_percent: self.cpu_warning_percent,
+            thermal: self.thermal_settings.clone(),
+            battery: self.battery_settings.clone(),
+            exposure_warning: self.exposure_warning.clone(),
+            assist: self.assist.clone(),
+            reference: self.reference_settings.clone(),
+            program: self.program_settings.clone(),
+            tally: self.tally_settings.clone(),
+            overlay: self.overlay_settings.clone(),
+            behavior: self.behavior.clone(),
+            ui_scale: self.ui_scale,
+            chrome: self.chrome.clone(),
+            window: self.window.clone(),
+            unlock_pin: self.unlock_pin.clone(),
+            theme: self.theme_settings.clone(),
+            language: self.language,
+            keymap: self.keymap.clone(),
+            #[cfg(feature = "gamepad")]
+            gamepad: self.gamepad.clone(),
+            #[cfg(feature = "midi")]
+            midi: self.midi_settings.clone(),
+            #[cfg(feature = "gpio")]
+            gpio: self.gpio_settings.clone(),
+            #[cfg(feature = "mqtt")]
+            mqtt: self.mqtt_settings.clone(),
+            ..Default::default()
+        };
+        self.overrides.restore(&mut config, &self.saved_config);
+        config
+    }
+
+    /// 设置有变化时保存. `force` 为 false 时等待 [CONFIG_SAVE_DELAY] 后再写盘.
+    fn persist_config(&mut self, force: bool) {
+        let current = self.config();
+        if current == self.saved_config {
+            self.config_changed_at = None;
+            return;
+        }
+        let changed_at = *self.config_changed_at.get_or_insert_with(Instant::now);
+        if !force && changed_at.elapsed() < CONFIG_SAVE_DELAY {
+            return;
+        }
+        let Some(path) = &self.config_path else {
+            return;
+        };
+        if let Err(e) = current.save(path) {
+            tracing::error!("could not save settings: {}", e);
+            self.toasts.push(
+                ToastLevel::Error,
+                tr_args("toast.save_failed", &[("error", &e)]),
+            );
+        }
+        // 保存失败也不再重试, 直到下一次修改
+        self.saved_config = current;
+        self.config_changed_at = None;
+    }
+
+    /// 开始录制时按设置先倒计时, 倒计时中再按一次则取消.
+    fn toggle_recording(&mut self) {
+        if self.recorder.is_recording() {
+            self.recorder.stop(&mut self.slate);
+        } else if self.countdown.is_some() {
+            self.cancel_countdown();
+        } else if self.countdown_settings.seconds > 0 {
+            self.start_countdown();
+        } else {
+            self.start_recording();
+        }
+    }
+
+    fn start_recording(&mut self) {
+        if self.bars_block_recording() {
+            return;
+        }
+        if !self.recorder.is_recording() {
+            let profile = self.matching_profile();
+            self.recorder
+                .start(&self.recording, &self.tags, &self.slate, profile);
+            self.last_clip = None;
+        }
+    }
+
+    /// 丢帧达到阈值时提示一次.
+    fn check_dropped_frames(&mut self) {
+        if self.drop_warned || self.drop_warning_frames == 0 || !self.recorder.is_recording() {
+            return;
+        }
+        let dropped = self.buffer_stats.lock().frames.dropped;
+        if dropped >= self.drop_warning_frames {
+            self.drop_warned = true;
+            self.status.publish(StatusEvent::DroppedFrames { dropped });
+            self.toasts.push(
+                ToastLevel::Warning,
+                tr_args("toast.frames_dropped", &[("count", &dropped)]),
+            );
+        }
+    }
+
+    /// 自动录制放弃后一直显示, 直到用户关闭.
+    fn auto_start_banner(&mut self, ctx: &egui::Context) {
+        let Some(error) = self.recorder.auto_start_error() else {
+            return;
+        };
+        let text = tr_args("banner.auto_record_failed", &[("error", &error)]);
+        let mut dismiss = false;
+        egui::Area::new(egui::Id::new("auto_start_banner"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(egui::Color32::from_rgb(120, 0, 0))
+                    .corner_radius(6.0)
+                    .inner_margin(egui::Margin::symmetric(12, 6))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(text).color(egui::Color32::WHITE));
+                            dismiss = ui.button(tr("banner.dismiss")).clicked();
+                        });
+                    });
+            });
+        if dismiss {
+            self.recorder.dismiss_auto_start_error();
+        }
+    }
+
+    /// 底部栏中的录制时长, 文件大小和码率.
+    fn progress_widgets(&self, ui: &mut egui::Ui) {
+        let elapsed = self.recorder.elapsed().unwrap_or_default().as_secs();
+        // 反色的 REC 标记, 任何主题下都与普通的红色文字不同
+        ui.vertical(|ui| {
+            ui.label(
+                egui::RichText::new(tr("bar.rec"))
+                    .size(PARAM_LABEL_SIZE)
+                    .strong()
+                    .color(self.theme.rec_text)
+                    .background_color(self.theme.rec),
+            );
+            ui.label(
+                egui::RichText::new(format!(
+                    "{:02}:{:02}:{:02}",
+                    elapsed / 3600,
+                    elapsed / 60 % 60,
+                    elapsed % 60
+                ))
+                .size(PARAM_VALUE_SIZE)
+                .strong()
+                .color(self.theme.rec),
+            );
+        });
+        ui.add_space(PARAM_SPACING);
+        let (size, bitrate) = match self.progress {
+            Some(p) => (
+                crate::file::format_size(p.bytes),
+                format!("{:.1} Mb/s", p.bytes_per_sec as f64 * 8.0 / 1e6),
+            ),
+            None => ("—".to_string(), "—".to_string()),
+        };
+        param_widget(ui, &self.theme, tr("bar.size"), &size);
+        ui.add_space(PARAM_SPACING);
+        param_widget(ui, &self.theme, tr("bar.bitrate"), &bitrate)
+            .on_hover_text(tr("bar.bitrate_hint"));
+        ui.add_space(PARAM_SPACING);
+        let timecode = self
+            .timecode
+            .as_ref()
+            .and_then(|tc| *tc.lock())
+            .map_or_else(|| "—".to_string(), |tc| tc.to_string());
+        param_widget(ui, &self.theme, tr("bar.timecode"), &timecode)
+            .on_hover_text(tr("bar.timecode_hint"));
+        ui.add_space(PARAM_SPACING);
+    }
+
+    /// 底部栏中的卷号 / 场次 / 镜次.
+    fn slate_widgets(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.slate;
+        let theme = &self.theme;
+        ui.add_enabled_ui(!self.recorder.is_recording(), |ui| {
+            ui.vertical(|ui| {
+                slate_caption(ui, theme, tr("bar.reel"));
+                let color = if s.reel_is_valid() {
+                    theme.text
+                } else {
+                    theme.alert
+                };
+                ui.add(
+                    egui::TextEdit::singleline(&mut s.reel)
+                        .desired_width(60.0)
+                        .text_color(color)
+                        .hint_text("A001"),
+                )
+                .on_hover_text(tr("bar.reel_hint"));
+            });
+            ui.vertical(|ui| {
+                slate_caption(ui, theme, tr("bar.scene"));
+                let resp = ui.add(egui::TextEdit::singleline(&mut s.scene).desired_width(60.0));
+                // 换场次后镜次从 1 开始
+                if resp.changed() {
+                    s.take = 1;
+                }
+            });
+            ui.vertical(|ui| {
+                slate_caption(ui, theme, tr("bar.take"));
+                ui.add(egui::DragValue::new(&mut s.take).range(1..=999));
+            });
+        });
+    }
+
+    /// 停止录制后为刚录完的镜次标记 GOOD / NG.
+    fn rating_buttons(&mut self, ui: &mut egui::Ui) {
+        let Some(path) = &self.last_clip else {
+            return;
+        };
+        let mut chosen = None;
+        for (rating, text, color) in [
+            (TakeRating::Good, tr("bar.good"), self.theme.good),
+            (TakeRating::NoGood, tr("bar.no_good"), self.theme.alert),
+        ] {
+            let selected = self.last_rating == Some(rating);
+            let label = egui::RichText::new(text).strong().color(color);
+            if ui.selectable_label(selected, label).clicked() {
+                chosen = Some(rating);
+            }
+        }
+        let Some(rating) = chosen else {
+            return;
+        };
+        match Sidecar::update(path, |meta| meta.rating = Some(rating)) {
+            Ok(()) => self.last_rating = Some(rating),
+            Err(e) => self.toasts.push(
+                ToastLevel::Error,
+                tr_args("toast.rating_failed", &[("error", &e)]),
+            ),
         }
     }
 }
 
 impl eframe::App for CameraApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // --- 1. 处理录制快捷键 (R 键) ---
-        if ctx.input(|i| i.key_pressed(egui::Key::R)) {
-            if self.is_recording {
-                // 停止录制
-                let _ = self.rec_cmd_tx.send(RecordCommand::Stop);
-                self.is_recording = false;
-            } else {
-                // 开始录制：配置默认参数
-                let timestamp = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-
-                let settings = RecordSettings {
-                    res: Resolution {
-                        width: 1920,
-                        height: 1080,
-                    },
-                    enc: VideoEncoder::H264,
-                    container: Container::MOV,
-                    filepath: format!("rec_{}.mov", timestamp).into(),
-                };
+        self.apply_ui_scale(ctx);
+        // --- 1. 处理快捷键, 按键可以在设置中修改 ---
+        self.dispatch_shortcuts(ctx);
+        #[cfg(feature = "gamepad")]
+        self.poll_gamepad(ctx);
+        #[cfg(feature = "midi")]
+        self.poll_midi(ctx);
+        #[cfg(feature = "mqtt")]
+        self.poll_mqtt(ctx);
+        self.poll_osc(ctx);
+        #[cfg(feature = "gpio")]
+        self.poll_gpio(ctx);
 
-                let _ = self.rec_cmd_tx.send(RecordCommand::Start(settings));
-                self.is_recording = true;
+        // 处理来自 gst 线程的录制事件
+        while let Ok(event) = self.rec_event_rx.try_recv() {
+            // 界面只跟踪主录像
+            if event.recording().is_some_and(|id| id != RecordingId::MAIN) {
+                continue;
             }
+            self.webhooks.notify(&event);
+            self.status.notify(&event);
+            self.osc.notify(&event);
+            self.recorder.handle_event(&event);
+            match event {
+                RecordEvent::Started { path, timecode, .. } => {
+                    self.log_session(
+                        Severity::Info,
+                        tr_args(
+                            "session.recording_started",
+                            &[("clip", &path.display().to_string())],
+                        ),
+                    );
+                    self.timecode = Some(timecode);
+                    self.markers.clear();
+                    self.flash_tally(self.theme.rec);
+                    self.toasts.push(
+                        ToastLevel::Info,
+                        tr_args("toast.recording", &[("path", &path.display())]),
+                    );
+                    self.drop_warned = false;
+                    self.thermal.start_recording();
+                    self.battery.start_recording();
+                    self.recording_exposure = self.exposure_state();
+                    self.recording_white_balance = Some(self.white_balance);
+                    self.progress = None;
+                    self.write_underrun = false;
+                    self.pip_recording_notice();
+                }
+                RecordEvent::Stopped {
+                    path,
+                    duration,
+                    size,
+                    frames,
+                    ..
+                } => {
+                    // 空文件通常说明编码器或封装器没有正常工作
+                    let level = if size == 0 || frames.dropped > 0 {
+                        ToastLevel::Warning
+                    } else {
+                        ToastLevel::Info
+                    };
+                    let mut text = format!(
+                        "{} — {}, {}",
+                        path.file_name().unwrap_or_default().to_string_lossy(),
+                        format_duration(duration),
+                        crate::file::format_size(size)
+                    );
+                    if frames.dropped > 0 {
+                        text.push_str(", ");
+                        text.push_str(&tr_args(
+                            "toast.clip_dropped",
+                            &[("count", &frames.dropped)],
+                        ));
+                    }
+                    if frames.repeated > 0 || frames.skipped > 0 {
+                        text.push_str(", ");
+                        text.push_str(&tr_args(
+                            "toast.clip_rate",
+                            &[("repeated", &frames.repeated), ("skipped", &frames.skipped)],
+                        ));
+                    }
+                    if level == ToastLevel::Info {
+                        self.flash_tally(self.theme.good);
+                    }
+                    self.log_session(
+                        Severity::Info,
+                        tr_args("session.recording_stopped", &[("clip", &text)]),
+                    );
+                    self.toasts.push(level, text);
+                    self.save_temperature(&path);
+                    self.save_power_log(&path);
+                    self.save_camera_state(&path);
+                    self.save_markers(&path);
+                    self.progress = None;
+                    for target in self.offload.auto_targets() {
+                        self.uploader.enqueue(path.clone(), target);
+                    }
+                    self.last_clip = Some(path);
+                    self.last_rating = None;
+                    self.on_recording_finalized(ctx);
+                }
+                RecordEvent::Verified { path, result } => {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    match result {
+                        Ok(()) => self
+                            .toasts
+                            .amend(&name, ToastLevel::Info, tr("toast.verified")),
+                        Err(e) => self.toasts.amend(
+                            &name,
+                            ToastLevel::Warning,
+                            &tr_args("toast.verification_failed", &[("error", &e)]),
+                        ),
+                    }
+                }
+                RecordEvent::Progress { progress, .. } => self.progress = Some(progress),
+                RecordEvent::PreviewStarted => {
+                    self.latency.reset();
+                    self.preview_rate.reset();
+                    // 管线可能重建过, 而且这时才知道采集的分辨率
+                    self.sent_denoise = None;
+                    self.sent_sharpen = None;
+                    self.sent_silence = None;
+                    // 新的管线总是从摄像头开始
+                    self.bars = false;
+                    self.silent = false;
+                }
+                RecordEvent::Bars(on) => self.on_bars(on),
+                RecordEvent::Silence(silent) => self.on_silence(silent),
+                RecordEvent::WriteSpeed { change, rate, .. } => self.on_write_speed(change, rate),
+                RecordEvent::ScreenShared(area) => self.screen_area = Some(area),
+                RecordEvent::SourceSwitched(index) => {
+                    self.active_source = index;
+                    self.log_session(
+                        Severity::Info,
+                        tr_args("session.source_switched", &[("n", &(index + 1))]),
+                    );
+                }
+                RecordEvent::Network(stats) => self.network = Some(stats),
+                RecordEvent::Snapshot(path) => self.toasts.push(
+                    ToastLevel::Info,
+                    tr_args(
+                        "toast.snapshot",
+                        &[(
+                            "name",
+                            &path.file_name().unwrap_or_default().to_string_lossy(),
+                        )],
+                    ),
+                ),
+                RecordEvent::Burst(paths) => {
+                    let folder = paths
+                        .first()
+                        .and_then(|p| p.parent())
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_default();
+                    self.toasts.push(
+                        ToastLevel::Info,
+                        tr_args(
+                            "toast.burst",
+                            &[("count", &paths.len()), ("folder", &folder)],
+                        ),
+                    )
+                }
+                RecordEvent::GraphSaved(files) => {
+                    let path = files.png.as_ref().unwrap_or(&files.dot);
+                    tracing::info!(dot = %files.dot.display(), "pipeline graph saved");
+                    self.toasts.push(
+                        ToastLevel::Info,
+                        tr_args("toast.graph_saved", &[("path", &path.display())]),
+                    )
+                }
+                RecordEvent::Warning(w) => {
+                    self.toasts.push(ToastLevel::Warning, w);
+                }
+                RecordEvent::StartBlocked {
+                    settings,
+                    reason,
+                    suggestion,
+                    ..
+                } => self.on_start_blocked(settings, reason, suggestion),
+                RecordEvent::Error(e) => {
+                    self.toasts.push(ToastLevel::Error, e);
+                }
+            }
+        }
+        // 自动录制: 第一帧出现后或重试时间到了
+        if !self.recorder.is_recording() && self.recorder.auto_start_due() {
+            self.start_recording();
+        }
+        self.tick_countdown(ctx);
+        self.tick_stop_guard(ctx);
+        self.log_toasts();
+        self.check_dropped_frames();
+        self.sample_resources();
+        self.check_temperature();
+        self.check_battery();
+        self.poll_output_dir();
+        self.poll_disk_space();
+        self.sync_denoise();
+        self.sync_sharpen();
+        self.sync_silence();
+        self.release_autofocus(ctx);
+        self.animate_zoom(ctx);
+        self.check_camera_reconnect();
+        // 录制期间阻止系统休眠, 失败只提示
+        if let Err(e) = self.inhibitor.set_active(self.recorder.is_recording()) {
+            tracing::warn!("could not inhibit sleep: {}", e);
+            self.toasts
+                .push(ToastLevel::Warning, tr("toast.inhibit_failed"));
         }
 
         // 获取当前音频电平
         let current_level = *self.audio_level.lock();
 
         // 1. 获取最新图像并转换为 GPU 纹理
-        if let Some(image) = self.frame_buffer.lock().take() {
-            self.texture = Some(ctx.load_texture("cam_frame", image, Default::default()));
+        let frame = self.frame_buffer.take();
+        if let Some(frame) = frame {
+            if self.grey_card_pending {
+                self.sample_grey_card(&frame.image);
+            }
+            self.update_exposure_warning(&frame.image);
+            self.check_black_frame(&frame.image);
+            // 定格时辅助显示也停在那一帧
+            if !self.is_frozen() {
+                self.update_assist_texture(ctx, &frame.image);
+            }
+            self.update_reference(ctx, &frame.image);
+            self.update_clipboard(&frame.image);
+            self.texture = Some(ctx.load_texture("cam_frame", frame.image, Default::default()));
+            self.note_frame_captured(frame.captured_at);
+            self.last_frame_at = Some(Instant::now());
+            self.latency.record(frame.captured_at.elapsed());
+            self.preview_rate
+                .on_frame(frame.sequence, frame.nominal_fps);
         }
+        self.preview_rate.tick(Instant::now());
+        self.check_signal(ctx);
+        self.check_screen_mirror(ctx);
+        self.poll_clipboard();
+        self.poll_gifs();
+        self.poll_benchmark();
 
         // 2. 全屏背景绘制
         egui::CentralPanel::default()
             .frame(egui::Frame::new().fill(egui::Color32::BLACK))
             .show(ctx, |ui| {
                 let rect = ui.max_rect();
+                self.preview_gestures(ui, rect);
 
                 // 绘制背景图
-                if let Some(texture) = &self.texture {
-                    ui.painter().image(
-                        texture.id(),
-                        rect,
-                        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
-                        egui::Color32::WHITE,
-                    );
+                if let Some(texture) = self.frozen_texture().or_else(|| self.preview_texture()) {
+                    ui.painter()
+                        .image(texture.id(), rect, self.view.uv(), self.theme.preview_tint);
+                }
+                self.reference_overlay(ui, rect);
+                self.punch_in_badge(ui, rect);
+                self.freeze_banner(ui, rect);
+                self.bars_banner(ui, rect);
+                self.pip_overlay(ui, rect);
+                if self.signal_lost {
+                    signal::no_signal_overlay(ui, rect);
+                } else if self.last_frame_at.is_none() {
+                    self.waiting_for_screen_share(ui, rect);
                 }
+                self.stale_overlay(ui, rect);
+                self.tally_overlay(ui, rect);
+
+                self.lock_overlay(ui, rect);
+
+                // 一段时间没有操作后淡出界面, 录制中保留 REC 标记
+                let chrome = self.chrome_opacity(ui.ctx());
+                if chrome < 1.0 {
+                    self.minimal_rec_indicator(ui, rect);
+                }
+                if chrome == 0.0 {
+                    return;
+                }
+                ui.set_opacity(chrome * self.theme.overlay_opacity);
 
                 // 3. 叠加 UI：顶部栏
                 ui.with_layout(egui::Layout::top_down(egui::Align::Min), |ui| {
-                    ui.add_space(20.0);
+                    ui.add_space(EDGE_MARGIN);
                     ui.horizontal(|ui| {
-                        ui.add_space(20.0);
+                        ui.add_space(EDGE_MARGIN);
                         ui.label(
-                            egui::RichText::new("● LIVE")
-                                .color(egui::Color32::RED)
+                            egui::RichText::new(tr("top.live"))
+                                .color(self.theme.alert)
                                 .strong(),
                         );
+                        self.pip_badge(ui);
+                        ui.add_space(EDGE_MARGIN);
+                        self.profile_combo(ui);
+                        ui.add_space(EDGE_MARGIN);
+                        self.source_buttons(ui);
+                        self.multiview_button(ui);
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            ui.add_space(20.0);
+                            ui.add_space(EDGE_MARGIN);
                             // 渲染 SVG 图标
-                            ui.add(
+                            let settings_icon = ui.add(
                                 egui::Image::new(crate::icons::ICON_SETTINGS)
-                                    .tint(egui::Color32::WHITE)
-                                    .max_width(24.0),
+                                    .tint(self.theme.text)
+                                    .max_width(24.0)
+                                    .sense(egui::Sense::click()),
                             );
+                            if settings_icon.clicked() {
+                                self.show_settings = !self.show_settings;
+                            }
+                            self.lock_button(ui);
+                            self.temperature_label(ui);
+                            self.battery_indicator(ui);
+                            #[cfg(feature = "gamepad")]
+                            self.gamepad_indicator(ui);
+                            if self.inhibitor.is_inhibited() {
+                                ui.add(
+                                    egui::Image::new(crate::icons::ICON_AWAKE)
+                                        .tint(self.theme.label)
+                                        .max_width(20.0),
+                                )
+                                .on_hover_text(tr("top.awake"));
+                            }
                         });
                     });
                 });
 
+                // 录制, 静帧和设置的触摸按钮
+                self.touch_controls(ui, rect);
+
                 // 4. 叠加 UI：底部参数区
-                let bottom_bar_height = 80.0;
                 let bottom_rect = egui::Rect::from_min_max(
-                    egui::pos2(rect.min.x, rect.max.y - bottom_bar_height),
+                    egui::pos2(rect.min.x, rect.max.y - BOTTOM_BAR_HEIGHT),
                     rect.max,
                 );
 
                 // 绘制半透明背景
-                ui.painter()
-                    .rect_filled(bottom_rect, 0.0, egui::Color32::from_black_alpha(180));
+                ui.painter().rect_filled(bottom_rect, 0.0, self.theme.bar);
+                self.exposure_chips(ui, bottom_rect);
 
                 #[allow(deprecated)]
                 ui.allocate_ui_at_rect(bottom_rect, |ui| {
                     ui.horizontal_centered(|ui| {
-                        ui.add_space(40.0);
-                        param_widget(ui, "ISO", &self.iso.to_string());
-                        ui.add_space(60.0);
-                        param_widget(ui, "SHUTTER", &self.shutter);
+                        ui.add_space(EDGE_MARGIN * 2.0);
+                        self.iso_widget(ui);
+                        ui.add_space(PARAM_SPACING);
+                        self.shutter_widget(ui);
+                        ui.add_space(PARAM_SPACING);
+                        self.ev_widget(ui);
+                        ui.add_space(PARAM_SPACING);
+                        self.ae_lock_widget(ui);
+                        ui.add_space(PARAM_SPACING);
+                        self.white_balance_widget(ui);
+                        ui.add_space(PARAM_SPACING);
+                        self.zoom_widget(ui);
+                        ui.add_space(PARAM_SPACING);
+                        if self.focus.is_some() {
+                            self.focus_widget(ui);
+                            ui.add_space(PARAM_SPACING);
+                        }
+                        let profile = if self.recorder.is_recording() {
+                            self.recorder.profile().unwrap_or("—").to_string()
+                        } else {
+                            self.profile_label()
+                        };
+                        param_widget(ui, &self.theme, tr("bar.profile"), &profile);
+                        ui.add_space(PARAM_SPACING);
+                        self.remaining_widget(ui);
+                        if self.recorder.is_recording() {
+                            self.progress_widgets(ui);
+                            self.marker_button(ui);
+                        }
+                        // 本次录制中编码器来不及处理而丢弃的帧
+                        if self.recorder.is_recording() {
+                            let buffers = self.buffer_stats.lock().clone();
+                            dropped_widget(ui, &self.theme, &buffers);
+                            ui.add_space(PARAM_SPACING);
+                        }
+                        self.slate_widgets(ui);
+                        ui.add_space(EDGE_MARGIN * 2.0);
+                        self.rating_buttons(ui);
+                        self.gif_button(ui);
                     });
                 });
 
@@ -134,33 +1350,121 @@ impl eframe::App for CameraApp {
                 ui.painter().text(
                     rect.right_top() + egui::vec2(-100.0, 50.0),
                     egui::Align2::RIGHT_TOP,
-                    format!("Audio Volumn: {:.3}", current_level), // 显示三位小数
+                    // 显示三位小数
+                    tr_args(
+                        "top.audio_level",
+                        &[("level", &format!("{:.3}", current_level))],
+                    ),
                     egui::FontId::proportional(20.0),
                     if current_level > 0.9 {
-                        egui::Color32::RED
+                        self.theme.alert
                     } else {
-                        egui::Color32::GREEN
+                        self.theme.good
                     },
                 );
             });
 
+        self.browser.show(ctx, &self.uploader);
+        self.settings_window(ctx);
+        self.benchmark_dialog(ctx);
+        self.transfers_window(ctx);
+        self.session_log_window(ctx);
+        self.device_controls_window(ctx);
+        self.multiview_window(ctx);
+        self.program_window(ctx);
+        self.console_window(ctx);
+        self.debug_overlay(ctx);
+        self.toasts.show(ctx, BOTTOM_BAR_HEIGHT);
+        self.auto_start_banner(ctx);
+        self.black_frame_banner(ctx);
+        self.silence_banner(ctx);
+        self.start_blocked_dialog(ctx);
+        self.countdown_overlay(ctx);
+        self.stop_guard_overlay(ctx);
+        // 在所有控件之后, 隐藏光标不会被悬停效果覆盖
+        self.track_window(ctx);
+        self.handle_close_request(ctx);
+        self.persist_config(false);
+
         // 关键：请求下一帧重绘（实现实时视频）
         ctx.request_repaint();
     }
+
+    fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        self.filter_locked_input(raw_input);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.persist_config(true);
+        // 不依赖 CameraApp 何时被释放, main 在 run_native 返回后等 gst 线程结束
+        self.recorder.shutdown();
+    }
+}
+
+/// 例如 "2 m 14 s".
+fn format_duration(d: std::time::Duration) -> String {
+    let secs = d.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => tr_args("duration.seconds", &[("s", &s)]),
+        (0, m, s) => tr_args("duration.minutes", &[("m", &m), ("s", &s)]),
+        (h, m, s) => tr_args("duration.hours", &[("h", &h), ("m", &m), ("s", &s)]),
+    }
+}
+
+fn slate_caption(ui: &mut egui::Ui, theme: &Theme, label: &str) {
+    ui.label(
+        egui::RichText::new(label)
+            .size(PARAM_LABEL_SIZE)
+            .color(theme.label),
+    );
+}
+
+/// 丢帧数, 不为 0 时显示为红色. 悬停显示 QoS 统计.
+fn dropped_widget(ui: &mut egui::Ui, theme: &Theme, buffers: &BufferStats) -> egui::Response {
+    let frames = buffers.frames;
+    let color = if frames.dropped > 0 {
+        theme.alert
+    } else {
+        theme.text
+    };
+    ui.vertical(|ui| {
+        ui.label(
+            egui::RichText::new(tr("bar.dropped"))
+                .size(PARAM_LABEL_SIZE)
+                .color(theme.label),
+        );
+        ui.label(
+            egui::RichText::new(frames.dropped.to_string())
+                .size(PARAM_VALUE_SIZE)
+                .strong()
+                .color(color),
+        );
+    })
+    .response
+    .on_hover_text(tr_args(
+        "bar.dropped_hint",
+        &[
+            ("encoded", &frames.encoded),
+            ("entered", &frames.entered),
+            ("late", &buffers.late),
+            ("dropped", &buffers.dropped),
+        ],
+    ))
 }
 
-fn param_widget(ui: &mut egui::Ui, label: &str, value: &str) {
+fn param_widget(ui: &mut egui::Ui, theme: &Theme, label: &str, value: &str) -> egui::Response {
     ui.vertical(|ui| {
         ui.label(
             egui::RichText::new(label)
-                .size(10.0)
-                .color(egui::Color32::LIGHT_GRAY),
+                .size(PARAM_LABEL_SIZE)
+                .color(theme.label),
         );
         ui.label(
             egui::RichText::new(value)
-                .size(24.0)
+                .size(PARAM_VALUE_SIZE)
                 .strong()
-                .color(egui::Color32::WHITE),
+                .color(theme.text),
         );
-    });
+    })
+    .response
 }
@@ -0,0 +1,92 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::tr;
+use crate::video::assist::{self, PeakingColor};
+
+impl CameraApp {
+    /// 每个新的预览帧调用. 辅助显示画在副本上, 节目输出仍然是干净的画面.
+    pub(super) fn update_assist_texture(&mut self, ctx: &egui::Context, image: &egui::ColorImage) {
+        if !self.assist.is_active() {
+            self.assist_texture = None;
+            return;
+        }
+        let mut image = image.clone();
+        assist::apply(&mut image, &self.assist);
+        match &mut self.assist_texture {
+            Some(texture) => texture.set(image, Default::default()),
+            None => {
+                self.assist_texture =
+                    Some(ctx.load_texture("cam_assist", image, Default::default()))
+            }
+        }
+    }
+
+    /// 主窗口中绘制的预览. 关闭辅助显示后立即回到原样的画面.
+    pub(super) fn preview_texture(&self) -> Option<&egui::TextureHandle> {
+        match &self.assist_texture {
+            Some(texture) if self.assist.is_active() => Some(texture),
+            _ => self.texture.as_ref(),
+        }
+    }
+
+    /// 设置面板: 峰值和斑马纹, 修改后预览立即更新. 应用预设时随预设切换.
+    pub(super) fn assist_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.assist;
+        egui::Grid::new("assist_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.checkbox(&mut s.peaking, tr("settings.assist.peaking"));
+                ui.end_row();
+
+                ui.label(tr("settings.assist.peaking_color"));
+                ui.horizontal(|ui| {
+                    for color in PeakingColor::ALL {
+                        ui.selectable_value(
+                            &mut s.peaking_color,
+                            color,
+                            peaking_color_label(color),
+                        );
+                    }
+                });
+                ui.end_row();
+
+                ui.label(tr("settings.assist.peaking_intensity"));
+                ui.add(egui::Slider::new(&mut s.peaking_intensity, 0.0..=1.0).step_by(0.05));
+                ui.end_row();
+
+                ui.checkbox(&mut s.zebra, tr("settings.assist.zebra"));
+                ui.end_row();
+
+                ui.label(tr("settings.assist.zebra_threshold"));
+                ui.add(egui::Slider::new(&mut s.zebra_threshold, 50..=100).suffix(" %"));
+                ui.end_row();
+
+                ui.label(tr("settings.assist.zebra_angle"));
+                ui.add(egui::Slider::new(&mut s.zebra_angle, 0..=179).suffix("°"));
+                ui.end_row();
+
+                ui.label(tr("settings.assist.zebra_spacing"));
+                ui.add(egui::Slider::new(&mut s.zebra_spacing, 4..=32).suffix(" px"));
+                ui.end_row();
+            });
+        self.black_frame_section(ui);
+        if ui
+            .button(tr("settings.assist.colorblind"))
+            .on_hover_text(tr("settings.assist.colorblind_hint"))
+            .clicked()
+        {
+            self.assist = self.assist.colorblind_safe();
+        }
+        ui.weak(tr("settings.assist.profiles"));
+    }
+}
+
+fn peaking_color_label(color: PeakingColor) -> &'static str {
+    match color {
+        PeakingColor::Red => tr("settings.assist.red"),
+        PeakingColor::Green => tr("settings.assist.green"),
+        PeakingColor::Blue => tr("settings.assist.blue"),
+        PeakingColor::White => tr("settings.assist.white"),
+    }
+}
@@ -0,0 +1,71 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::file::session_log::Severity;
+use crate::i18n::tr;
+
+impl CameraApp {
+    /// 切换彩条. 录制中不能切到彩条, 由 gst 线程报告实际的状态.
+    pub(super) fn toggle_bars(&mut self) {
+        if self.bars {
+            self.recorder.set_bars(None);
+        } else if self.recorder.is_recording() {
+            self.toasts
+                .push(ToastLevel::Warning, tr("toast.bars_while_recording"));
+        } else {
+            self.recorder.set_bars(Some(self.tags.device_model.clone()));
+        }
+    }
+
+    /// 收到 gst 线程的彩条状态.
+    pub(super) fn on_bars(&mut self, on: bool) {
+        if self.bars != on {
+            let key = if on {
+                "session.bars_on"
+            } else {
+                "session.bars_off"
+            };
+            self.log_session(Severity::Info, tr(key));
+        }
+        self.bars = on;
+    }
+
+    /// 彩条期间不开始录制, 返回 true 表示拦下了.
+    pub(super) fn bars_block_recording(&mut self) -> bool {
+        if self.bars {
+            self.toasts
+                .push(ToastLevel::Warning, tr("toast.bars_block_recording"));
+        }
+        self.bars
+    }
+
+    /// 预览正中醒目的彩条标记, 以免忘了切回摄像头.
+    pub(super) fn bars_banner(&self, ui: &egui::Ui, rect: egui::Rect) {
+        if !self.bars {
+            return;
+        }
+        let painter = ui.painter();
+        let galley = painter.layout_no_wrap(
+            tr("banner.bars").to_string(),
+            egui::FontId::proportional(96.0),
+            egui::Color32::WHITE,
+        );
+        let pos = rect.center() - galley.size() / 2.0;
+        let background = egui::Rect::from_min_size(pos, galley.size()).expand(16.0);
+        painter.rect_filled(background, 8.0, self.theme.alert);
+        painter.galley(pos, galley, egui::Color32::WHITE);
+    }
+
+    /// 预览右键菜单中的彩条项.
+    pub(super) fn bars_menu_item(&mut self, ui: &mut egui::Ui) {
+        let label = if self.bars {
+            tr("action.bars_off")
+        } else {
+            tr("action.bars")
+        };
+        if ui.button(label).clicked() {
+            self.toggle_bars();
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use eframe::egui;
+use std::path::Path;
+use std::time::Instant;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::battery::{BatteryAlert, ChargeState};
+use crate::file::sidecar::Sidecar;
+use crate::i18n::{tr, tr_args};
+
+impl CameraApp {
+    /// 读取电量, 低电量时提示; 开启了自动停止时在电量耗尽前停止录制.
+    pub(super) fn check_battery(&mut self) {
+        if let Some(alert) = self.battery.poll(Instant::now()) {
+            let percent = self.battery.status().map_or(0, |s| s.percent);
+            match alert {
+                BatteryAlert::Low => self.toasts.push(
+                    ToastLevel::Warning,
+                    tr_args("toast.battery_low", &[("percent", &percent)]),
+                ),
+                BatteryAlert::Critical => self.toasts.push(
+                    ToastLevel::Error,
+                    tr_args("toast.battery_critical", &[("percent", &percent)]),
+                ),
+            }
+        }
+        if self.recorder.is_recording() && self.battery.should_stop_recording() {
+            let percent = self.battery.status().map_or(0, |s| s.percent);
+            tracing::warn!(percent, "battery critical, stopping recording");
+            self.toasts.push(
+                ToastLevel::Error,
+                tr_args("toast.battery_stopped", &[("percent", &percent)]),
+            );
+            self.recorder.stop(&mut self.slate);
+        }
+    }
+
+    /// 顶部栏中的电池图标和电量, 没有电池时不显示.
+    pub(super) fn battery_indicator(&self, ui: &mut egui::Ui) {
+        let Some(status) = self.battery.status() else {
+            return;
+        };
+        let charging = matches!(status.state, ChargeState::Charging | ChargeState::Full);
+        let color = if charging || status.percent >= self.battery_settings.warn_percent {
+            egui::Color32::LIGHT_GRAY
+        } else if status.percent > self.battery_settings.stop_percent {
+            egui::Color32::YELLOW
+        } else {
+            egui::Color32::RED
+        };
+        let state = tr(match status.state {
+            ChargeState::Charging => "top.battery_charging",
+            ChargeState::Discharging => "top.battery_discharging",
+            ChargeState::Full => "top.battery_full",
+            ChargeState::Unknown => "top.battery_unknown",
+        });
+        let hover = match status.time_to_empty_secs {
+            Some(secs) => tr_args(
+                "top.battery_left",
+                &[
+                    ("percent", &status.percent),
+                    ("state", &state),
+                    (
+                        "time",
+                        &super::format_duration(std::time::Duration::from_secs(secs)),
+                    ),
+                ],
+            ),
+            None => tr_args(
+                "top.battery",
+                &[("percent", &status.percent), ("state", &state)],
+            ),
+        };
+        ui.label(egui::RichText::new(format!("{}%", status.percent)).color(color))
+            .on_hover_text(&hover);
+        let icon = if charging {
+            crate::icons::ICON_BATTERY_CHARGING
+        } else {
+            crate::icons::ICON_BATTERY
+        };
+        ui.add(egui::Image::new(icon).tint(color).max_width(20.0))
+            .on_hover_text(hover);
+        ui.add_space(12.0);
+    }
+
+    /// 把开始和结束时的电池状态补充到 sidecar.
+    pub(super) fn save_power_log(&mut self, clip: &Path) {
+        let Some(power) = self.battery.finish_recording() else {
+            return;
+        };
+        if let Err(e) = Sidecar::update(clip, |meta| meta.power = Some(power)) {
+            tracing::warn!(path = %clip.display(), "could not save power state: {}", e);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::i18n::{tr, tr_args};
+use crate::video::av1::Realtime;
+use crate::video::benchmark::{self, Benchmark, BenchmarkState};
+
+impl CameraApp {
+    /// 每帧调用. 测速和录制抢 CPU, 开始录制时立即取消.
+    pub(super) fn poll_benchmark(&mut self) {
+        let Some(running) = &self.benchmark else {
+            return;
+        };
+        if self.recorder.is_recording() {
+            running.cancel();
+        }
+        match running.state() {
+            BenchmarkState::Running { .. } => return,
+            BenchmarkState::Done(report) => {
+                self.toasts
+                    .push(ToastLevel::Info, tr("toast.benchmark_done"));
+                self.benchmark_report = Some(report);
+                self.show_benchmark = true;
+            }
+            BenchmarkState::Failed(e) => self.toasts.push(
+                ToastLevel::Warning,
+                tr_args("toast.benchmark_failed", &[("error", &e)]),
+            ),
+            BenchmarkState::Cancelled => {
+                self.toasts
+                    .push(ToastLevel::Info, tr("toast.benchmark_cancelled"));
+            }
+        }
+        self.benchmark = None;
+    }
+
+    /// 设置面板: 开始测速, 查看上次的结果.
+    pub(super) fn benchmark_section(&mut self, ui: &mut egui::Ui) {
+        ui.label(tr("settings.benchmark.hint"));
+        if let Some(running) = &self.benchmark {
+            let (done, total) = match running.state() {
+                BenchmarkState::Running { done, total } => (done, total),
+                _ => (0, 0),
+            };
+            ui.horizontal(|ui| {
+                let fraction = if total > 0 {
+                    done as f32 / total as f32
+                } else {
+                    0.0
+                };
+                ui.add(
+                    egui::ProgressBar::new(fraction)
+                        .desired_width(200.0)
+                        .text(format!("{}/{}", done, total)),
+                );
+                if ui.button(tr("settings.benchmark.cancel")).clicked() {
+                    running.cancel();
+                }
+            });
+            ui.ctx().request_repaint();
+            return;
+        }
+        ui.horizontal(|ui| {
+            let recording = self.recorder.is_recording();
+            let run = ui
+                .add_enabled(!recording, egui::Button::new(tr("settings.benchmark.run")))
+                .on_disabled_hover_text(tr("settings.benchmark.while_recording"));
+            if run.clicked() {
+                tracing::info!("starting encoder benchmark");
+                self.benchmark = Some(Benchmark::spawn(self.recording.encoder_options()));
+            }
+            if let Some(report) = &self.benchmark_report {
+                if ui.button(tr("settings.benchmark.show")).clicked() {
+                    self.show_benchmark = true;
+                }
+                ui.weak(tr_args(
+                    "settings.benchmark.last_run",
+                    &[("time", &report.run_at.format("%Y-%m-%d %H:%M"))],
+                ));
+            }
+        });
+    }
+
+    /// 测速结果的表格, 测完自动打开.
+    pub(super) fn benchmark_dialog(&mut self, ctx: &egui::Context) {
+        if !self.show_benchmark {
+            return;
+        }
+        let Some(report) = &self.benchmark_report else {
+            self.show_benchmark = false;
+            return;
+        };
+        let mut open = true;
+        egui::Window::new(tr("benchmark.title"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(tr_args(
+                    "benchmark.summary",
+                    &[("fps", &benchmark::REFERENCE_FPS)],
+                ));
+                ui.add_space(4.0);
+                egui::Grid::new("benchmark_grid")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong(tr("benchmark.encoder"));
+                        ui.strong(tr("benchmark.resolution"));
+                        ui.strong(tr("benchmark.speed"));
+                        ui.strong(tr("benchmark.cpu"));
+                        ui.end_row();
+                        for result in &report.results {
+                            ui.label(&result.element);
+                            ui.label(format!("{}p", result.res.height));
+                            match &result.error {
+                                Some(e) => {
+                                    ui.colored_label(self.theme.alert, tr("benchmark.failed"))
+                                        .on_hover_text(e);
+                                    ui.label("—");
+                                }
+                                None => {
+                                    let color = match benchmark::sustainable(result.realtime()) {
+                                        Realtime::Likely => self.theme.good,
+                                        Realtime::Marginal => self.theme.warning,
+                                        Realtime::Unlikely => self.theme.alert,
+                                    };
+                                    ui.colored_label(
+                                        color,
+                                        format!(
+                                            "{:.1}× ({:.0} fps)",
+                                            result.realtime(),
+                                            result.fps
+                                        ),
+                                    );
+                                    ui.label(format!("{:.0}%", result.cpu_percent));
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.add_space(4.0);
+                ui.weak(tr_args(
+                    "benchmark.saved",
+                    &[("time", &report.run_at.format("%Y-%m-%d %H:%M"))],
+                ));
+            });
+        if !open {
+            self.show_benchmark = false;
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::file::session_log::Severity;
+use crate::i18n::{tr, tr_args};
+use crate::video::black::{BlackFrameChange, BlackFrameDetector, LumaStats};
+
+/// 录制中关闭提示后, 过这么久画面还是黑的就再次提示.
+const RECORDING_SNOOZE: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+pub(super) struct BlackFrameWatch {
+    detector: BlackFrameDetector,
+    /// 关闭提示的时间. 画面恢复后清除.
+    dismissed_at: Option<Instant>,
+}
+
+impl CameraApp {
+    /// 每个新的预览帧调用. 输出彩条时不检测.
+    pub(super) fn check_black_frame(&mut self, image: &egui::ColorImage) {
+        let settings = &self.assist.black_frame;
+        let watch = &mut self.black_frame;
+        if !settings.enabled || self.bars {
+            watch.detector.reset();
+            watch.dismissed_at = None;
+            return;
+        }
+        let Some(luma) = LumaStats::from_image(image) else {
+            return;
+        };
+        match watch.detector.update(luma, settings, Instant::now()) {
+            Some(BlackFrameChange::Started) => {
+                tracing::warn!(
+                    mean = luma.mean,
+                    deviation = luma.deviation,
+                    "image is black"
+                );
+                self.log_session(Severity::Warning, tr("session.black_started"));
+                if self.recorder.is_recording() {
+                    self.toasts
+                        .push(ToastLevel::Error, tr("toast.black_recording"));
+                }
+            }
+            Some(BlackFrameChange::Ended(duration)) => {
+                self.black_frame.dismissed_at = None;
+                tracing::info!(
+                    duration_s = duration.as_secs_f64(),
+                    "image is no longer black"
+                );
+                self.log_session(
+                    Severity::Info,
+                    tr_args(
+                        "session.black_ended",
+                        &[("time", &super::format_duration(duration))],
+                    ),
+                );
+            }
+            None => {}
+        }
+    }
+
+    /// 画面黑着时在预览上方提示, 可以关闭. 录制中用醒目的颜色, 关闭后过一阵再次出现.
+    pub(super) fn black_frame_banner(&mut self, ctx: &egui::Context) {
+        let watch = &self.black_frame;
+        if !watch.detector.is_black() || self.signal_lost {
+            return;
+        }
+        let recording = self.recorder.is_recording();
+        let snoozed = watch
+            .dismissed_at
+            .is_some_and(|at| !recording || at.elapsed() < RECORDING_SNOOZE);
+        if snoozed {
+            return;
+        }
+        let (fill, key) = if recording {
+            (self.theme.alert, "banner.black_recording")
+        } else {
+            (self.theme.warning, "banner.black")
+        };
+        let mut dismiss = false;
+        egui::Area::new(egui::Id::new("black_frame_banner"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 120.0))
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(fill)
+                    .corner_radius(6.0)
+                    .inner_margin(egui::Margin::symmetric(12, 6))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                egui::RichText::new(tr(key))
+                                    .size(18.0)
+                                    .strong()
+                                    .color(egui::Color32::WHITE),
+                            );
+                            dismiss = ui.button(tr("banner.dismiss")).clicked();
+                        });
+                    });
+            });
+        if dismiss {
+            self.black_frame.dismissed_at = Some(Instant::now());
+        }
+    }
+
+    /// 设置面板: 黑画面提示的开关和阈值.
+    pub(super) fn black_frame_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.assist.black_frame;
+        ui.checkbox(&mut s.enabled, tr("settings.assist.black_frame"))
+            .on_hover_text(tr("settings.assist.black_frame_hint"));
+        ui.add_enabled_ui(s.enabled, |ui| {
+            egui::Grid::new("black_frame_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label(tr("settings.assist.black_max_mean"));
+                    ui.add(egui::Slider::new(&mut s.max_mean, 0.0..=64.0));
+                    ui.end_row();
+
+                    ui.label(tr("settings.assist.black_max_deviation"));
+                    ui.add(egui::Slider::new(&mut s.max_deviation, 0.0..=16.0).step_by(0.5));
+                    ui.end_row();
+
+                    ui.label(tr("settings.assist.black_after"));
+                    ui.add(egui::Slider::new(&mut s.after_secs, 1..=60).suffix(" s"));
+                    ui.end_row();
+                });
+        });
+    }
+}
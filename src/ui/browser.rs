@@ -0,0 +1,354 @@
+use eframe::egui;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::file::export::{ExportProgress, ExportState};
+use crate::file::sidecar::{Sidecar, TakeRating, Verification};
+use crate::file::volumes::Volume;
+use crate::file::watch::DirChanges;
+use crate::file::{self, ClipInfo};
+use crate::i18n::{tr, tr_args};
+use crate::offload::{TransferState, Uploader};
+
+/// 可移动卷的重新扫描间隔. 录像列表由输出目录的监视更新.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 录像浏览器: 列出已录制的文件, 并导出到 U 盘.
+pub(super) struct RecordingsBrowser {
+    pub open: bool,
+    /// 录像保存目录.
+    pub dir: PathBuf,
+    clips: Vec<ClipInfo>,
+    selected: HashSet<PathBuf>,
+    volumes: Vec<Volume>,
+    dest: Option<Volume>,
+    export: Option<Arc<Mutex<ExportProgress>>>,
+    last_scan: Option<Instant>,
+    /// 录像列表需要完整地重新扫描, 关闭时收到的变化等打开时再扫描.
+    stale: bool,
+    /// 删除需要二次确认.
+    confirm_delete: bool,
+}
+
+impl RecordingsBrowser {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            open: false,
+            dir,
+            clips: Vec::new(),
+            selected: HashSet::new(),
+            volumes: Vec::new(),
+            dest: None,
+            export: None,
+            last_scan: None,
+            stale: true,
+            confirm_delete: false,
+        }
+    }
+
+    fn rescan_clips(&mut self) {
+        match file::list_clips(&self.dir) {
+            Ok(clips) => self.clips = clips,
+            Err(e) => {
+                tracing::error!("could not list clips: {}", e);
+                self.clips.clear();
+            }
+        }
+        self.selected
+            .retain(|p| self.clips.iter().any(|c| &c.path == p));
+        self.stale = false;
+    }
+
+    /// 输出目录中的文件有了变化. 录像和 sidecar 逐个更新, 不重新扫描整个目录.
+    pub fn apply(&mut self, changes: DirChanges) {
+        if changes.rescan {
+            self.stale = true;
+        }
+        if self.stale {
+            if self.open {
+                self.rescan_clips();
+            }
+            return;
+        }
+        let sidecar_changed = |clip: &ClipInfo, paths: &HashSet<PathBuf>| {
+            paths.contains(&file::sidecar::sidecar_path(&clip.path))
+        };
+        for clip in &mut self.clips {
+            if sidecar_changed(clip, &changes.removed) {
+                clip.sidecar = None;
+            } else if sidecar_changed(clip, &changes.added) {
+                clip.sidecar = Sidecar::read(&clip.path).ok();
+            }
+        }
+        self.clips.retain(|c| !changes.removed.contains(&c.path));
+        self.selected.retain(|p| !changes.removed.contains(p));
+        for path in changes.added.into_iter().filter(|p| file::is_clip(p)) {
+            self.clips.retain(|c| c.path != path);
+            if let Some(clip) = file::clip_info(path) {
+                self.clips.push(clip);
+            }
+        }
+        file::sort_clips(&mut self.clips);
+    }
+
+    fn rescan_volumes(&mut self) {
+        self.volumes = file::volumes::removable_volumes();
+        if let Some(dest) = &self.dest
+            && !self.volumes.contains(dest)
+        {
+            self.dest = None;
+        }
+        if self.dest.is_none() {
+            self.dest = self.volumes.first().cloned();
+        }
+        self.last_scan = Some(Instant::now());
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, uploader: &Uploader) {
+        if !self.open {
+            return;
+        }
+        if self.stale {
+            self.rescan_clips();
+        }
+        if self.last_scan.is_none_or(|t| t.elapsed() > RESCAN_INTERVAL) {
+            self.rescan_volumes();
+        }
+
+        let mut open = self.open;
+        egui::Window::new(tr("browser.title"))
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                self.clip_list(ui, uploader);
+                ui.separator();
+                self.delete_controls(ui, uploader);
+                self.export_controls(ui);
+            });
+        self.open = open;
+    }
+
+    fn clip_list(&mut self, ui: &mut egui::Ui, uploader: &Uploader) {
+        if self.clips.is_empty() {
+            ui.label(tr("browser.empty"));
+            return;
+        }
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                for clip in &self.clips {
+                    ui.horizontal(|ui| {
+                        let mut checked = self.selected.contains(&clip.path);
+                        if ui.checkbox(&mut checked, clip.file_name()).changed() {
+                            if checked {
+                                self.selected.insert(clip.path.clone());
+                            } else {
+                                self.selected.remove(&clip.path);
+                            }
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(file::format_size(clip.size));
+                            offload_badge(ui, uploader.status(&clip.path));
+                            if let Some(meta) = &clip.sidecar {
+                                verification_badge(ui, meta.verification.as_ref());
+                                rating_badge(ui, meta.rating);
+                                if let Some(slate) = &meta.slate {
+                                    ui.label(slate.label());
+                                }
+                            }
+                        });
+                    });
+                    if let Some(meta) = &clip.sidecar {
+                        ui.indent(clip.path.as_os_str(), |ui| {
+                            ui.weak(sidecar_summary(meta));
+                        });
+                    }
+                }
+            });
+    }
+
+    fn delete_controls(&mut self, ui: &mut egui::Ui, uploader: &Uploader) {
+        if self.selected.is_empty() {
+            self.confirm_delete = false;
+            return;
+        }
+        // 上传尚未完成的文件不允许删除
+        let pending = self
+            .selected
+            .iter()
+            .filter(|p| uploader.is_pending(p))
+            .count();
+        ui.horizontal(|ui| {
+            if pending > 0 {
+                ui.add_enabled(false, egui::Button::new(tr("browser.delete")));
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    tr_args("browser.still_uploading", &[("count", &pending)]),
+                );
+                self.confirm_delete = false;
+            } else if self.confirm_delete {
+                let text = tr_args("browser.confirm_delete", &[("count", &self.selected.len())]);
+                if ui
+                    .button(egui::RichText::new(text).color(egui::Color32::RED))
+                    .clicked()
+                {
+                    for path in self.selected.drain() {
+                        if let Err(e) = std::fs::remove_file(&path) {
+                            tracing::error!(path = %path.display(), "could not delete clip: {}", e);
+                            continue;
+                        }
+                        let _ = std::fs::remove_file(file::sidecar::sidecar_path(&path));
+                    }
+                    self.confirm_delete = false;
+                    self.rescan_clips();
+                }
+                if ui.button(tr("browser.cancel")).clicked() {
+                    self.confirm_delete = false;
+                }
+            } else if ui.button(tr("browser.delete")).clicked() {
+                self.confirm_delete = true;
+            }
+        });
+    }
+
+    fn export_controls(&mut self, ui: &mut egui::Ui) {
+        let busy = self
+            .export
+            .as_ref()
+            .is_some_and(|p| !p.lock().is_finished());
+
+        ui.horizontal(|ui| {
+            ui.label(tr("browser.export_to"));
+            egui::ComboBox::from_id_salt("export_dest")
+                .selected_text(
+                    self.dest
+                        .as_ref()
+                        .map(Volume::display_name)
+                        .unwrap_or_else(|| tr("browser.no_drive").to_string()),
+                )
+                .show_ui(ui, |ui| {
+                    for volume in &self.volumes {
+                        ui.selectable_value(
+                            &mut self.dest,
+                            Some(volume.clone()),
+                            volume.display_name(),
+                        );
+                    }
+                });
+
+            let can_export = !busy && self.dest.is_some() && !self.selected.is_empty();
+            if ui
+                .add_enabled(can_export, egui::Button::new(tr("browser.export")))
+                .clicked()
+                && let Some(dest) = self.dest.clone()
+            {
+                // 保持列表中的顺序
+                let clips = self
+                    .clips
+                    .iter()
+                    .filter(|c| self.selected.contains(&c.path))
+                    .map(|c| c.path.clone())
+                    .collect();
+                self.export = Some(file::export::spawn_export(clips, dest));
+            }
+        });
+
+        let Some(progress) = &self.export else {
+            return;
+        };
+        let p = progress.lock().clone();
+        match &p.state {
+            ExportState::Copying | ExportState::Verifying => {
+                let key = if p.state == ExportState::Copying {
+                    "browser.copying"
+                } else {
+                    "browser.verifying"
+                };
+                ui.label(tr_args(
+                    key,
+                    &[
+                        ("file", &p.current_file),
+                        ("n", &(p.files_done + 1)),
+                        ("total", &p.files_total),
+                    ],
+                ));
+                ui.add(egui::ProgressBar::new(p.file_fraction()).show_percentage());
+                ui.add(egui::ProgressBar::new(p.overall_fraction()).text(tr_args(
+                    "browser.overall",
+                    &[("percent", &format!("{:.0}", p.overall_fraction() * 100.0))],
+                )));
+                ui.ctx().request_repaint();
+            }
+            ExportState::Done => {
+                ui.label(
+                    egui::RichText::new(tr_args("browser.exported", &[("count", &p.files_total)]))
+                        .color(egui::Color32::GREEN),
+                );
+            }
+            ExportState::Failed(reason) => {
+                ui.label(
+                    egui::RichText::new(tr_args("browser.export_failed", &[("error", reason)]))
+                        .color(egui::Color32::RED),
+                );
+            }
+        }
+    }
+}
+
+fn offload_badge(ui: &mut egui::Ui, status: Option<TransferState>) {
+    let (text, color) = match status {
+        None => return,
+        Some(TransferState::Queued) => (tr("browser.offload_queued"), egui::Color32::GRAY),
+        Some(TransferState::Active) => (tr("browser.offload_active"), egui::Color32::LIGHT_BLUE),
+        Some(TransferState::Completed) => (tr("browser.offload_done"), egui::Color32::GREEN),
+        Some(TransferState::Failed(_)) => (tr("browser.offload_failed"), egui::Color32::RED),
+    };
+    ui.colored_label(color, text);
+}
+
+fn verification_badge(ui: &mut egui::Ui, verification: Option<&Verification>) {
+    match verification {
+        None => {}
+        Some(v) if v.is_ok() => {
+            ui.colored_label(egui::Color32::GREEN, "✔")
+                .on_hover_text(tr_args("browser.verified", &[("hash", &v.xxh3)]));
+        }
+        Some(v) => {
+            ui.colored_label(egui::Color32::RED, "⚠")
+                .on_hover_text(v.error.clone().unwrap_or_default());
+        }
+    }
+}
+
+fn rating_badge(ui: &mut egui::Ui, rating: Option<TakeRating>) {
+    match rating {
+        None => {}
+        Some(TakeRating::Good) => {
+            ui.colored_label(egui::Color32::GREEN, tr("bar.good"));
+        }
+        Some(TakeRating::NoGood) => {
+            ui.colored_label(egui::Color32::RED, tr("bar.no_good"));
+        }
+    }
+}
+
+/// 例如 "2026-10-14 09:30 · 2 m 14 s · 1920×1080 H264/MOV 8000 kbps · /dev/video0".
+fn sidecar_summary(meta: &Sidecar) -> String {
+    let s = &meta.settings;
+    format!(
+        "{} · {} · {}×{} {}/{} {} kbps · {}",
+        meta.started_at.format("%Y-%m-%d %H:%M"),
+        super::format_duration(std::time::Duration::from_secs_f64(
+            meta.duration_secs.max(0.0)
+        )),
+        s.width,
+        s.height,
+        s.encoder,
+        s.container,
+        s.bitrate_kbps,
+        meta.device
+    )
+}
@@ -0,0 +1,457 @@
+use eframe::egui;
+use std::path::Path;
+
+use super::widgets::stepper::Stepper;
+use super::{CameraApp, param_widget};
+use crate::file::sidecar::Sidecar;
+use crate::i18n::tr;
+use crate::video::controls::{
+    self, Exposure, ExposureBias, ExposureMode, ExposureState, ISO_STEPS, ISO_THIRD_STEPS,
+    MAX_EV_THIRDS, SensorGain,
+};
+use crate::video::libcamera::{self, Capabilities, LibcameraControl};
+use crate::video::shutter::{self, ShutterSpeed};
+
+/// 滚动这么多点换一档, 大约是鼠标滚轮的一格.
+const SCROLL_STEP: f32 = 40.0;
+
+impl CameraApp {
+    /// 打开视频源对应的摄像头控件. 换了设备时重新调用, 曝光补偿和锁定随之清除.
+    pub(super) fn open_camera_controls(&mut self) {
+        let source = self.capture.sources().video;
+        let device = controls::v4l2_device(&source).map(Path::new);
+        self.gain = device.and_then(SensorGain::open);
+        self.bias = device.and_then(ExposureBias::open);
+        self.libcamera = libcamera::is_libcamera(&source).then(Capabilities::detect);
+        self.exposure = match device {
+            Some(device) => Exposure::open(device),
+            None if self.libcamera.is_some() => Err(tr("bar.libcamera_no_exposure").to_string()),
+            None => Err(tr("bar.exposure_needs_v4l2").to_string()),
+        };
+        self.iso = self
+            .gain
+            .as_ref()
+            .and_then(|g| {
+                g.iso()
+                    .inspect_err(|e| tracing::warn!("could not read sensor gain: {}", e))
+                    .ok()
+            })
+            .unwrap_or(800);
+        self.shutter = self.read_shutter().unwrap_or_default();
+        self.ae_mode = match &self.exposure {
+            Ok(exposure) if !exposure.is_auto().unwrap_or(false) => ExposureMode::Manual,
+            _ => ExposureMode::Auto,
+        };
+        self.open_white_balance(device);
+        self.open_focus(device);
+        self.open_control_panel(device);
+        // 补偿值保存在摄像头里, 上次运行留下的值不应该悄悄生效
+        self.ev_thirds = 0;
+        if let Some(bias) = &self.bias
+            && let Err(e) = bias.set_ev_thirds(0)
+        {
+            tracing::warn!("could not reset exposure compensation: {}", e);
+        }
+    }
+
+    pub(super) fn read_shutter(&self) -> Option<ShutterSpeed> {
+        self.exposure
+            .as_ref()
+            .ok()?
+            .shutter()
+            .inspect_err(|e| tracing::warn!("could not read exposure: {}", e))
+            .ok()
+    }
+
+    /// V4L2 的增益控件或 libcamerasrc 的 `analogue-gain`.
+    fn has_iso(&self) -> bool {
+        self.gain.is_some() || self.libcamera.is_some_and(|c| c.iso)
+    }
+
+    /// V4L2 的曝光控件或 libcamerasrc 的 `exposure-time`.
+    fn has_shutter(&self) -> bool {
+        self.exposure.is_ok() || self.libcamera_shutter()
+    }
+
+    fn libcamera_shutter(&self) -> bool {
+        self.libcamera.is_some_and(|c| c.shutter)
+    }
+
+    /// 摄像头自己支持曝光补偿, 否则用增益模拟.
+    fn native_compensation(&self) -> bool {
+        self.bias.is_some() || self.libcamera.is_some_and(|c| c.compensation)
+    }
+
+    /// 细调时每档 1/3 级.
+    fn iso_steps(&self) -> &'static [u32] {
+        if self.iso_fine {
+            &ISO_THIRD_STEPS
+        } else {
+            &ISO_STEPS
+        }
+    }
+
+    /// 切换到相邻的 ISO 档位.
+    pub(super) fn change_iso(&mut self, delta: i32) {
+        self.set_iso(controls::next_step(self.iso_steps(), self.iso, delta));
+    }
+
+    fn set_iso(&mut self, iso: u32) {
+        if !self.has_iso() || iso == self.iso {
+            return;
+        }
+        let applied = if self.native_compensation() {
+            iso
+        } else {
+            controls::compensated_iso(iso, self.ev_thirds)
+        };
+        self.apply_iso(applied);
+        self.iso = iso;
+    }
+
+    /// 直接设置失败时改用 v4l2src 的 `extra-controls`.
+    fn apply_iso(&self, iso: u32) {
+        match &self.gain {
+            Some(gain) => {
+                if let Err(e) = gain.set_iso(iso) {
+                    tracing::debug!("could not set gain directly, using extra-controls: {}", e);
+                    self.recorder.set_source_controls(gain.extra_controls(iso));
+                }
+            }
+            None => self
+                .recorder
+                .set_libcamera_control(LibcameraControl::Iso(iso)),
+        }
+    }
+
+    /// 调整曝光补偿, `delta` 以 1/3 EV 为单位. 没有补偿控件时按比例调整增益.
+    fn change_ev(&mut self, delta: i32) {
+        let ev_thirds = (self.ev_thirds + delta).clamp(-MAX_EV_THIRDS, MAX_EV_THIRDS);
+        if ev_thirds == self.ev_thirds {
+            return;
+        }
+        if let Some(bias) = &self.bias {
+            if let Err(e) = bias.set_ev_thirds(ev_thirds) {
+                tracing::warn!(ev = %ev_label(ev_thirds), "could not set exposure compensation: {}", e);
+                return;
+            }
+        } else if self.libcamera.is_some_and(|c| c.compensation) {
+            self.recorder
+                .set_libcamera_control(LibcameraControl::Compensation(ev_thirds as f32 / 3.0));
+        } else if self.has_iso() {
+            self.apply_iso(controls::compensated_iso(self.iso, ev_thirds));
+        } else {
+            return;
+        }
+        self.ev_thirds = ev_thirds;
+    }
+
+    /// 自动曝光时冻结当前的曝光时间; 锁定或手动时回到自动曝光.
+    pub(super) fn toggle_ae_lock(&mut self) {
+        // libcamerasrc 不报告自动曝光的曝光时间, 只能从手动回到自动
+        if self.exposure.is_err() && self.libcamera_shutter() {
+            if self.ae_mode != ExposureMode::Auto {
+                self.recorder
+                    .set_libcamera_control(LibcameraControl::Shutter(None));
+                self.ae_mode = ExposureMode::Auto;
+            }
+            return;
+        }
+        let Ok(exposure) = &self.exposure else {
+            return;
+        };
+        if !exposure.has_auto() {
+            return;
+        }
+        let result = match self.ae_mode {
+            ExposureMode::Auto => exposure.lock().map(|shutter| {
+                self.shutter = shutter;
+                ExposureMode::Locked
+            }),
+            ExposureMode::Locked | ExposureMode::Manual => {
+                exposure.set_auto().map(|()| ExposureMode::Auto)
+            }
+        };
+        match result {
+            Ok(mode) => {
+                tracing::info!(?mode, shutter = %self.shutter, "exposure mode changed");
+                self.ae_mode = mode;
+            }
+            Err(e) => tracing::warn!("could not change exposure mode: {}", e),
+        }
+    }
+
+    /// 切换到相邻的快门档位, `delta` 为正时更快.
+    pub(super) fn change_shutter(&mut self, delta: i32) {
+        // 从自动曝光当前的值开始调
+        if self.ae_mode == ExposureMode::Auto
+            && let Some(shutter) = self.read_shutter()
+        {
+            self.shutter = shutter;
+        }
+        let mut shutter = self.shutter;
+        for _ in 0..delta.unsigned_abs() {
+            shutter = if delta > 0 {
+                shutter.next()
+            } else {
+                shutter.prev()
+            };
+        }
+        self.set_shutter(shutter);
+    }
+
+    fn set_shutter(&mut self, shutter: ShutterSpeed) {
+        if shutter == self.shutter && self.ae_mode == ExposureMode::Manual {
+            return;
+        }
+        let result = match &self.exposure {
+            Ok(exposure) => exposure.set_shutter(shutter),
+            Err(_) if self.libcamera_shutter() => {
+                self.recorder
+                    .set_libcamera_control(LibcameraControl::Shutter(Some(shutter)));
+                Ok(())
+            }
+            Err(_) => return,
+        };
+        match result {
+            Ok(()) => {
+                self.shutter = shutter;
+                self.ae_mode = ExposureMode::Manual;
+            }
+            Err(e) => tracing::warn!(%shutter, "could not set exposure: {}", e),
+        }
+    }
+
+    /// 底部栏的 ISO, 滚动或拖动切换档位, 点击选择.
+    pub(super) fn iso_widget(&mut self, ui: &mut egui::Ui) {
+        if !self.has_iso() {
+            ui.add_enabled_ui(false, |ui| {
+                param_widget(ui, &self.theme, tr("bar.iso"), tr("bar.auto"))
+            })
+            .response
+            .on_disabled_hover_text(tr("bar.no_gain"));
+            return;
+        }
+        let steps = self.iso_steps();
+        let options: Vec<String> = steps.iter().map(u32::to_string).collect();
+        let value = self.iso.to_string();
+        let mut fine = self.iso_fine;
+        let output = Stepper::new("iso", tr("bar.iso"), &value)
+            .options(&options, steps.iter().position(|s| *s == self.iso))
+            .fine(&mut fine, tr("bar.iso_fine"))
+            .show(ui, &self.theme);
+        output.response.on_hover_text(tr("bar.iso_hint"));
+        self.iso_fine = fine;
+        if let Some(index) = output.picked {
+            self.set_iso(steps[index]);
+        } else if output.steps != 0 {
+            self.change_iso(output.steps);
+        }
+    }
+
+    /// 底部栏的快门, 滚动或拖动切换档位, 点击选择, 双击输入任意值. 不支持手动曝光时变灰并提示原因.
+    pub(super) fn shutter_widget(&mut self, ui: &mut egui::Ui) {
+        if let Err(reason) = &self.exposure
+            && !self.libcamera_shutter()
+        {
+            ui.add_enabled_ui(false, |ui| {
+                param_widget(ui, &self.theme, tr("bar.shutter"), tr("bar.auto"))
+            })
+            .response
+            .on_disabled_hover_text(reason.as_str());
+            return;
+        }
+        if self.shutter_edit.is_some() {
+            self.shutter_editor(ui);
+            return;
+        }
+        let (text, hint, selected) = match self.ae_mode {
+            ExposureMode::Auto => (
+                tr("bar.auto").to_string(),
+                tr("bar.shutter_auto_hint"),
+                None,
+            ),
+            ExposureMode::Locked | ExposureMode::Manual => (
+                self.shutter.to_string(),
+                tr("bar.shutter_hint"),
+                shutter::STOPS.iter().position(|s| *s == self.shutter),
+            ),
+        };
+        let options: Vec<String> = shutter::STOPS.iter().map(ToString::to_string).collect();
+        let output = Stepper::new("shutter", tr("bar.shutter"), &text)
+            .options(&options, selected)
+            .show(ui, &self.theme);
+        // 双击的两次单击分别打开和关闭快速选择
+        if output.response.double_clicked() {
+            self.shutter_edit = Some(self.shutter.to_string());
+            return;
+        }
+        output.response.on_hover_text(hint);
+        if let Some(index) = output.picked {
+            self.set_shutter(shutter::STOPS[index]);
+        } else if output.steps != 0 {
+            self.change_shutter(output.steps);
+        }
+    }
+
+    /// 输入快门速度. 无法解析时标红并显示原因, 按 Enter 应用, Esc 或失去焦点时放弃.
+    fn shutter_editor(&mut self, ui: &mut egui::Ui) {
+        let Some(text) = &mut self.shutter_edit else {
+            return;
+        };
+        let parsed = text.parse::<ShutterSpeed>();
+        let response = ui
+            .vertical(|ui| {
+                ui.label(
+                    egui::RichText::new(tr("bar.shutter"))
+                        .size(10.0)
+                        .color(self.theme.label),
+                );
+                let color = if parsed.is_ok() {
+                    self.theme.text
+                } else {
+                    self.theme.alert
+                };
+                ui.add(
+                    egui::TextEdit::singleline(text)
+                        .font(egui::FontId::proportional(24.0))
+                        .text_color(color)
+                        .desired_width(90.0),
+                )
+            })
+            .inner;
+        if !response.has_focus() && !response.lost_focus() {
+            response.request_focus();
+        }
+        if let Err(reason) = &parsed {
+            response.clone().on_hover_text(reason.as_str());
+        }
+        if !response.lost_focus() {
+            return;
+        }
+        let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+        match parsed {
+            Ok(shutter) if enter => {
+                self.shutter_edit = None;
+                self.set_shutter(shutter);
+            }
+            // 输入有误时按 Enter 保持编辑
+            Err(_) if enter => response.request_focus(),
+            _ => self.shutter_edit = None,
+        }
+    }
+
+    /// 底部栏的曝光补偿, 只在自动曝光时可调.
+    pub(super) fn ev_widget(&mut self, ui: &mut egui::Ui) {
+        let text = ev_label(self.ev_thirds);
+        let unavailable = if !self.native_compensation() && !self.has_iso() {
+            Some(tr("bar.no_compensation"))
+        } else if self.ae_mode != ExposureMode::Auto {
+            Some(tr("bar.compensation_auto_only"))
+        } else {
+            None
+        };
+        if let Some(reason) = unavailable {
+            ui.add_enabled_ui(false, |ui| {
+                param_widget(ui, &self.theme, tr("bar.ev"), &text)
+            })
+            .response
+            .on_disabled_hover_text(reason);
+            return;
+        }
+        let hint = if self.native_compensation() {
+            tr("bar.ev_hint")
+        } else {
+            tr("bar.ev_emulated_hint")
+        };
+        let response = param_widget(ui, &self.theme, tr("bar.ev"), &text).on_hover_text(hint);
+        let steps = scroll_steps(ui, &response, &mut self.ev_scroll);
+        if steps != 0 {
+            self.change_ev(steps);
+        }
+    }
+
+    /// 底部栏的 AE 锁定, 点击或按 L 切换.
+    pub(super) fn ae_lock_widget(&mut self, ui: &mut egui::Ui) {
+        let text = match self.ae_mode {
+            ExposureMode::Auto => tr("bar.auto"),
+            ExposureMode::Locked => tr("bar.locked"),
+            ExposureMode::Manual => tr("bar.manual"),
+        };
+        let unavailable = match &self.exposure {
+            Err(_) if self.libcamera_shutter() && self.ae_mode == ExposureMode::Auto => {
+                Some(tr("bar.libcamera_no_lock"))
+            }
+            Err(_) if self.libcamera_shutter() => None,
+            Err(reason) => Some(reason.as_str()),
+            Ok(exposure) if !exposure.has_auto() => Some(tr("bar.no_auto_exposure")),
+            Ok(_) => None,
+        };
+        if let Some(reason) = unavailable {
+            ui.add_enabled_ui(false, |ui| {
+                param_widget(ui, &self.theme, tr("bar.ae"), text)
+            })
+            .response
+            .on_disabled_hover_text(reason);
+            return;
+        }
+        let hint = match self.ae_mode {
+            ExposureMode::Auto => tr("bar.ae_lock_hint"),
+            ExposureMode::Locked | ExposureMode::Manual => tr("bar.ae_unlock_hint"),
+        };
+        let response = param_widget(ui, &self.theme, tr("bar.ae"), text)
+            .interact(egui::Sense::click())
+            .on_hover_text(hint);
+        if response.clicked() {
+            self.toggle_ae_lock();
+        }
+    }
+
+    /// 没有曝光和增益控件, 也没有补偿时返回 None.
+    pub(super) fn exposure_state(&self) -> Option<ExposureState> {
+        if !self.has_shutter() && !self.has_iso() && self.ev_thirds == 0 {
+            return None;
+        }
+        Some(ExposureState {
+            mode: self.ae_mode,
+            compensation_ev: self.ev_thirds as f32 / 3.0,
+            shutter: (self.ae_mode != ExposureMode::Auto).then(|| self.shutter.to_string()),
+            iso: self.has_iso().then_some(self.iso),
+        })
+    }
+
+    /// 把录制开始时的曝光和白平衡写入 sidecar.
+    pub(super) fn save_camera_state(&mut self, clip: &Path) {
+        let exposure = self.recording_exposure.take();
+        let white_balance = self.recording_white_balance.take();
+        if exposure.is_none() && white_balance.is_none() {
+            return;
+        }
+        if let Err(e) = Sidecar::update(clip, |meta| {
+            meta.exposure = exposure;
+            meta.white_balance = white_balance;
+        }) {
+            tracing::warn!(path = %clip.display(), "could not save camera state: {}", e);
+        }
+    }
+}
+
+/// 悬停时累计滚动距离, 返回应该切换的档位数.
+pub(super) fn scroll_steps(ui: &egui::Ui, response: &egui::Response, scroll: &mut f32) -> i32 {
+    if !response.hovered() {
+        *scroll = 0.0;
+        return 0;
+    }
+    *scroll += ui.input(|i| i.raw_scroll_delta.y);
+    let steps = (*scroll / SCROLL_STEP).trunc();
+    *scroll -= steps * SCROLL_STEP;
+    steps as i32
+}
+
+/// 例如 "+0.7", 没有补偿时为 "±0".
+fn ev_label(ev_thirds: i32) -> String {
+    if ev_thirds == 0 {
+        return "±0".to_string();
+    }
+    format!("{:+.1}", ev_thirds as f32 / 3.0)
+}
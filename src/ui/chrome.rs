@@ -0,0 +1,84 @@
+use eframe::egui;
+use std::time::Instant;
+
+use super::CameraApp;
+use crate::i18n::tr;
+
+/// 淡入淡出的时间.
+const FADE_SECS: f32 = 0.3;
+
+impl CameraApp {
+    /// 界面元素当前的不透明度. 任何按键, 鼠标移动或触摸都重新计时.
+    pub(super) fn chrome_opacity(&mut self, ctx: &egui::Context) -> f32 {
+        let active =
+            ctx.input(|i| !i.events.is_empty() || i.pointer.is_moving() || i.any_touches());
+        let now = Instant::now();
+        if active {
+            self.last_activity = now;
+        }
+        let visible = match self.chrome.hide_after() {
+            None => true,
+            Some(_) if self.chrome.keep_with_panels && self.any_panel_open() => true,
+            Some(after) => {
+                let idle = now.duration_since(self.last_activity);
+                // 即使以后不再为了视频每帧重绘, 到时间时也要重绘一次开始淡出
+                if idle < after {
+                    ctx.request_repaint_after(after - idle);
+                }
+                idle < after
+            }
+        };
+        // animate_bool 在过渡期间自己请求重绘
+        ctx.animate_bool_with_time(egui::Id::new("chrome_opacity"), visible, FADE_SECS)
+    }
+
+    fn any_panel_open(&self) -> bool {
+        self.show_settings
+            || self.show_transfers
+            || self.show_device_controls
+            || self.show_console
+            || self.show_multiview
+            || self.browser.open
+    }
+
+    /// 界面隐藏后录制中仍然显示的红点和时长.
+    pub(super) fn minimal_rec_indicator(&self, ui: &egui::Ui, rect: egui::Rect) {
+        let Some(elapsed) = self.recorder.elapsed() else {
+            return;
+        };
+        let secs = elapsed.as_secs();
+        let center =
+            rect.left_top() + egui::vec2(super::EDGE_MARGIN + 8.0, super::EDGE_MARGIN + 8.0);
+        ui.painter().circle_filled(center, 8.0, self.theme.rec);
+        ui.painter().text(
+            center + egui::vec2(16.0, 0.0),
+            egui::Align2::LEFT_CENTER,
+            format!(
+                "{:02}:{:02}:{:02}",
+                secs / 3600,
+                secs % 3600 / 60,
+                secs % 60
+            ),
+            egui::FontId::monospace(18.0),
+            self.theme.rec,
+        );
+    }
+
+    /// 设置面板: 自动隐藏界面.
+    pub(super) fn chrome_section(&mut self, ui: &mut egui::Ui) {
+        let c = &mut self.chrome;
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.display.hide_after"));
+            ui.add(
+                egui::DragValue::new(&mut c.hide_after_secs)
+                    .range(0..=600)
+                    .suffix(" s"),
+            )
+            .on_hover_text(tr("settings.display.hide_after_hint"));
+        });
+        ui.checkbox(
+            &mut c.keep_with_panels,
+            tr("settings.display.keep_with_panels"),
+        );
+    }
+}
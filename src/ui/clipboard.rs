@@ -0,0 +1,110 @@
+use eframe::egui;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::i18n::{tr, tr_args};
+
+/// 把画面放到系统剪贴板的线程. arboard 在 Linux 上要先编码为 PNG,
+/// 剪贴板管理器也可能很慢, 都不放在界面线程.
+pub(super) struct ClipboardWorker {
+    tx: Sender<egui::ColorImage>,
+    results: Receiver<Result<(), String>>,
+}
+
+impl ClipboardWorker {
+    pub fn spawn() -> Self {
+        let (tx, images) = mpsc::channel::<egui::ColorImage>();
+        let (result_tx, results) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("clipboard".into())
+            .spawn(move || {
+                // X11 和 Wayland 上只有剪贴板还在时别的程序才能粘贴, 所以一直持有
+                let mut clipboard = None;
+                for image in images {
+                    let result = copy(&mut clipboard, image);
+                    if let Err(e) = &result {
+                        tracing::warn!("could not copy the frame: {}", e);
+                    }
+                    if result_tx.send(result).is_err() {
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("could not start clipboard thread: {}", e);
+        }
+        Self { tx, results }
+    }
+
+    /// 线程没有启动时返回 false.
+    fn send(&self, image: egui::ColorImage) -> bool {
+        self.tx.send(image).is_ok()
+    }
+}
+
+/// 第一次使用时才连接剪贴板, 没有显示服务器时返回错误.
+fn copy(slot: &mut Option<arboard::Clipboard>, image: egui::ColorImage) -> Result<(), String> {
+    let mut clipboard = match slot.take() {
+        Some(clipboard) => clipboard,
+        None => arboard::Clipboard::new().map_err(|e| e.to_string())?,
+    };
+    let [width, height] = image.size;
+    let bytes: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|c| c.to_srgba_unmultiplied())
+        .collect();
+    let result = clipboard
+        .set_image(arboard::ImageData {
+            width,
+            height,
+            bytes: bytes.into(),
+        })
+        .map_err(|e| e.to_string());
+    *slot = Some(clipboard);
+    result
+}
+
+impl CameraApp {
+    /// 在下一帧到达时复制, 不包含辅助显示和参考图.
+    pub(super) fn copy_frame(&mut self) {
+        self.clipboard_pending = true;
+    }
+
+    pub(super) fn update_clipboard(&mut self, image: &egui::ColorImage) {
+        if !std::mem::take(&mut self.clipboard_pending) {
+            return;
+        }
+        if !self.clipboard.send(image.clone()) {
+            self.toasts.push(
+                ToastLevel::Warning,
+                tr_args("toast.copy_failed", &[("error", &"no clipboard thread")]),
+            );
+        }
+    }
+
+    /// 每帧调用, 提示复制的结果.
+    pub(super) fn poll_clipboard(&mut self) {
+        while let Ok(result) = self.clipboard.results.try_recv() {
+            match result {
+                Ok(()) => self.toasts.push(ToastLevel::Info, tr("toast.frame_copied")),
+                Err(e) => self.toasts.push(
+                    ToastLevel::Warning,
+                    tr_args("toast.copy_failed", &[("error", &e)]),
+                ),
+            }
+        }
+    }
+
+    /// 预览上的右键菜单.
+    pub(super) fn preview_menu(&mut self, response: &egui::Response) {
+        response.context_menu(|ui| {
+            if ui.button(tr("action.copy_frame")).clicked() {
+                self.copy_frame();
+            }
+            self.freeze_menu_item(ui);
+            self.bars_menu_item(ui);
+        });
+    }
+}
@@ -0,0 +1,75 @@
+use eframe::egui;
+use tracing::Level;
+
+use super::CameraApp;
+use crate::i18n::tr;
+
+const FILTER_LEVELS: [Level; 5] = [
+    Level::ERROR,
+    Level::WARN,
+    Level::INFO,
+    Level::DEBUG,
+    Level::TRACE,
+];
+
+impl CameraApp {
+    /// 日志面板, 按 ` 键打开. 显示最近的几百条日志, 现场排查不需要 SSH.
+    pub(super) fn console_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_console;
+        egui::Window::new(tr("console.title"))
+            .open(&mut open)
+            .default_width(640.0)
+            .default_height(320.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(tr("console.level"));
+                    egui::ComboBox::from_id_salt("console_level")
+                        .selected_text(self.console_level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in FILTER_LEVELS {
+                                ui.selectable_value(&mut self.console_level, level, level.as_str());
+                            }
+                        });
+                    if ui.button(tr("console.clear")).clicked() {
+                        self.log_console.clear();
+                    }
+                });
+                ui.separator();
+
+                let entries = self.log_console.entries();
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        // 级别越详细越 "大", 只显示不超过所选级别的记录
+                        for entry in entries.iter().filter(|e| e.level <= self.console_level) {
+                            let mut text = format!(
+                                "{} {:>5} ",
+                                entry.time.format("%H:%M:%S%.3f"),
+                                entry.level
+                            );
+                            if !entry.spans.is_empty() {
+                                text.push_str(&entry.spans);
+                                text.push_str(": ");
+                            }
+                            text.push_str(&entry.message);
+                            ui.label(
+                                egui::RichText::new(text)
+                                    .monospace()
+                                    .color(level_color(entry.level)),
+                            );
+                        }
+                    });
+            });
+        self.show_console = open;
+    }
+}
+
+fn level_color(level: Level) -> egui::Color32 {
+    match level {
+        Level::ERROR => egui::Color32::RED,
+        Level::WARN => egui::Color32::YELLOW,
+        Level::INFO => egui::Color32::WHITE,
+        _ => egui::Color32::GRAY,
+    }
+}
@@ -0,0 +1,133 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+use crate::video::beep;
+
+/// 设置中可选的倒计时秒数, 0 表示关闭.
+const CHOICES: [u32; 4] = [0, 3, 5, 10];
+const NUMERAL_SIZE: f32 = 220.0;
+const BEEP_LENGTH: Duration = Duration::from_millis(120);
+const BEEP_FREQ: f64 = 880.0;
+/// 最后一秒的提示音高一个八度.
+const FINAL_BEEP_FREQ: f64 = 1760.0;
+
+pub(super) struct Countdown {
+    ends_at: Instant,
+    /// 上一次显示的秒数, 变化时响一声.
+    shown: u64,
+}
+
+impl Countdown {
+    fn remaining(&self) -> Duration {
+        self.ends_at.saturating_duration_since(Instant::now())
+    }
+
+    /// 向上取整的剩余秒数, 屏幕上显示 3, 2, 1.
+    fn remaining_secs(&self) -> u64 {
+        self.remaining().as_millis().div_ceil(1000) as u64
+    }
+}
+
+impl CameraApp {
+    pub(super) fn start_countdown(&mut self) {
+        let seconds = self.countdown_settings.seconds;
+        tracing::info!(seconds, "recording countdown started");
+        self.countdown = Some(Countdown {
+            ends_at: Instant::now() + Duration::from_secs(seconds.into()),
+            shown: u64::MAX,
+        });
+    }
+
+    pub(super) fn cancel_countdown(&mut self) {
+        if self.countdown.take().is_some() {
+            tracing::info!("recording countdown cancelled");
+        }
+    }
+
+    /// 每帧调用: 到时间后才真正开始录制, 录像的开始时间因此不包括倒计时.
+    pub(super) fn tick_countdown(&mut self, ctx: &egui::Context) {
+        let Some(countdown) = &mut self.countdown else {
+            return;
+        };
+        let secs = countdown.remaining_secs();
+        if secs == 0 {
+            self.countdown = None;
+            self.start_recording();
+            return;
+        }
+        if secs != countdown.shown {
+            countdown.shown = secs;
+            if self.countdown_settings.beep {
+                let freq = if secs == 1 {
+                    FINAL_BEEP_FREQ
+                } else {
+                    BEEP_FREQ
+                };
+                beep::play(freq, BEEP_LENGTH);
+            }
+        }
+        // 在下一个整秒时重绘
+        let until_next = countdown
+            .remaining()
+            .saturating_sub(Duration::from_secs(secs - 1));
+        ctx.request_repaint_after(until_next);
+    }
+
+    /// 预览中央的大号数字.
+    pub(super) fn countdown_overlay(&self, ctx: &egui::Context) {
+        let Some(countdown) = &self.countdown else {
+            return;
+        };
+        egui::Area::new(egui::Id::new("countdown"))
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(
+                        egui::RichText::new(countdown.remaining_secs().to_string())
+                            .size(NUMERAL_SIZE)
+                            .strong()
+                            .color(self.theme.rec),
+                    );
+                    ui.label(
+                        egui::RichText::new(tr("banner.countdown_cancel"))
+                            .color(self.theme.text)
+                            .background_color(egui::Color32::from_black_alpha(160)),
+                    );
+                });
+            });
+    }
+
+    /// 设置面板: 倒计时的长度和提示音.
+    pub(super) fn countdown_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.countdown_settings;
+        egui::Grid::new("countdown_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("settings.countdown.seconds"));
+                egui::ComboBox::from_id_salt("countdown_seconds")
+                    .selected_text(seconds_label(s.seconds))
+                    .show_ui(ui, |ui| {
+                        for seconds in CHOICES {
+                            ui.selectable_value(&mut s.seconds, seconds, seconds_label(seconds));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label(tr("settings.countdown.beep"));
+                ui.add_enabled(s.seconds > 0, egui::Checkbox::without_text(&mut s.beep));
+                ui.end_row();
+            });
+        ui.weak(tr("settings.countdown.hint"));
+    }
+}
+
+fn seconds_label(seconds: u32) -> String {
+    if seconds == 0 {
+        tr("settings.countdown.off").to_string()
+    } else {
+        tr_args("settings.countdown.after", &[("seconds", &seconds)])
+    }
+}
@@ -0,0 +1,174 @@
+use eframe::egui;
+use std::time::Instant;
+
+use super::CameraApp;
+
+impl CameraApp {
+    /// 资源占用只在叠加层打开时采样, 录制期间超过阈值记一条警告.
+    pub(super) fn sample_resources(&mut self) {
+        if !self.show_resources {
+            self.resources.reset();
+            self.cpu_spiking = false;
+            return;
+        }
+        if !self.resources.poll(Instant::now()) {
+            return;
+        }
+        let Some(usage) = self.resources.usage() else {
+            return;
+        };
+        let threshold = self.cpu_warning_percent;
+        let spiking = threshold > 0.0 && usage.cpu_percent > threshold;
+        // 只在越过阈值时记录, 持续高负载不刷屏
+        if spiking && !self.cpu_spiking && self.recorder.is_recording() {
+            tracing::warn!(
+                cpu = format_args!("{:.0}%", usage.cpu_percent),
+                threshold = format_args!("{:.0}%", threshold),
+                "cpu usage spike while recording"
+            );
+        }
+        self.cpu_spiking = spiking;
+    }
+
+    /// 调试用的叠加层, 在设置面板的 Debug 中打开.
+    pub(super) fn debug_overlay(&self, ctx: &egui::Context) {
+        const WHITE: egui::Color32 = egui::Color32::WHITE;
+        // 每行由若干段不同颜色的文字组成
+        let mut lines: Vec<Vec<(String, egui::Color32)>> = Vec::new();
+        if self.show_fps {
+            let cam = self.preview_rate.cam.fps();
+            let ui = self.preview_rate.ui.fps();
+            let color = if self.preview_rate.cam_is_slow() {
+                egui::Color32::RED
+            } else {
+                WHITE
+            };
+            let mut line = vec![
+                ("cam ".to_string(), WHITE),
+                (cam.map_or("—".to_string(), |f| format!("{:.2}", f)), color),
+                (
+                    format!(
+                        " / ui {}",
+                        ui.map_or("—".to_string(), |f| format!("{:.0}", f))
+                    ),
+                    WHITE,
+                ),
+            ];
+            if let Some(nominal) = self.preview_rate.nominal {
+                line.push((format!("  (caps {:.2})", nominal), egui::Color32::GRAY));
+            }
+            lines.push(line);
+        }
+        if self.show_latency {
+            let l = &self.latency;
+            let text = if l.samples == 0 {
+                "latency  waiting for frames".to_string()
+            } else {
+                format!(
+                    "latency  cur {:.1} ms  avg {:.1} ms  max {:.1} ms",
+                    l.current, l.average, l.max
+                )
+            };
+            lines.push(vec![(text, WHITE)]);
+            if let Some(age) = self.frame_age() {
+                let color = if age >= super::signal::STALE_AFTER {
+                    egui::Color32::YELLOW
+                } else {
+                    WHITE
+                };
+                lines.push(vec![(
+                    format!("frame age  {:.0} ms", age.as_secs_f64() * 1000.0),
+                    color,
+                )]);
+            }
+            if let Some(n) = &self.network {
+                let rtt = n
+                    .round_trip_ms
+                    .map_or("—".to_string(), |r| format!("{:.1} ms", r));
+                let color = if n.lost > 0 {
+                    egui::Color32::YELLOW
+                } else {
+                    WHITE
+                };
+                lines.push(vec![(
+                    format!(
+                        "network  jitter {:.1} ms  rtt {}  lost {}",
+                        n.jitter_ms, rtt, n.lost
+                    ),
+                    color,
+                )]);
+            }
+        }
+        if self.show_resources {
+            match self.resources.usage() {
+                Some(usage) => {
+                    let color = if self.cpu_spiking {
+                        egui::Color32::YELLOW
+                    } else {
+                        WHITE
+                    };
+                    lines.push(vec![(
+                        format!(
+                            "cpu {:.0}%  rss {}",
+                            usage.cpu_percent,
+                            crate::file::format_size(usage.rss_bytes)
+                        ),
+                        color,
+                    )]);
+                    let threads: Vec<String> = usage
+                        .threads
+                        .iter()
+                        .map(|(kind, percent)| format!("{} {:.0}%", kind.label(), percent))
+                        .collect();
+                    lines.push(vec![(format!("  {}", threads.join("  ")), WHITE)]);
+                }
+                None => lines.push(vec![("cpu  sampling…".to_string(), WHITE)]),
+            }
+            // 写入队列两端的速度, 余量为负说明存储跟不上
+            if self.recorder.is_recording()
+                && let Some(write) = self.progress.map(|p| p.write)
+                && let Some(margin) = write.margin()
+            {
+                let color = if self.write_underrun {
+                    egui::Color32::RED
+                } else if margin < -0.05 {
+                    egui::Color32::YELLOW
+                } else {
+                    WHITE
+                };
+                lines.push(vec![(
+                    format!(
+                        "write {}/s of {}/s  margin {:+.0}%  backlog {}",
+                        crate::file::format_size(write.written_per_sec),
+                        crate::file::format_size(write.produced_per_sec),
+                        margin * 100.0,
+                        crate::file::format_size(write.backlog_bytes)
+                    ),
+                    color,
+                )]);
+            }
+        }
+        if lines.is_empty() {
+            return;
+        }
+        egui::Area::new(egui::Id::new("debug_overlay"))
+            .anchor(egui::Align2::LEFT_TOP, egui::vec2(20.0, 60.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(egui::Color32::from_black_alpha(180))
+                    .corner_radius(4.0)
+                    .inner_margin(egui::Margin::symmetric(8, 4))
+                    .show(ui, |ui| {
+                        for line in lines {
+                            ui.horizontal(|ui| {
+                                ui.spacing_mut().item_spacing.x = 0.0;
+                                for (text, color) in line {
+                                    ui.label(egui::RichText::new(text).monospace().color(color));
+                                }
+                            });
+                        }
+                    });
+            });
+    }
+}
@@ -0,0 +1,80 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+use crate::video::denoise::{DenoiseFilter, DenoiseStrength};
+
+impl CameraApp {
+    /// 每帧调用, 设置变了 (包括切换预设) 就发给 gst 线程. 管线重建后重新发送.
+    pub(super) fn sync_denoise(&mut self) {
+        let denoise = self.recording.denoise;
+        if self.sent_denoise != Some(denoise) {
+            self.recorder.set_denoise(denoise);
+            self.sent_denoise = Some(denoise);
+        }
+    }
+
+    /// 设置面板: 降噪开关, 强度和大致的 CPU 占用.
+    pub(super) fn denoise_section(&mut self, ui: &mut egui::Ui) {
+        let filter = DenoiseFilter::detect();
+        let r = &mut self.recording;
+        ui.horizontal(|ui| {
+            ui.add_enabled(
+                filter.is_some(),
+                egui::Checkbox::new(&mut r.denoise.enabled, tr("settings.recording.denoise")),
+            )
+            .on_hover_text(tr("settings.recording.denoise_hint"));
+            ui.add_enabled_ui(r.denoise.enabled, |ui| {
+                egui::ComboBox::from_id_salt("denoise_strength")
+                    .selected_text(strength_label(r.denoise.strength))
+                    .show_ui(ui, |ui| {
+                        for strength in DenoiseStrength::ALL {
+                            ui.selectable_value(
+                                &mut r.denoise.strength,
+                                strength,
+                                strength_label(strength),
+                            );
+                        }
+                    });
+            });
+        });
+        let Some(filter) = filter else {
+            ui.colored_label(self.theme.warning, tr("settings.recording.denoise_missing"));
+            return;
+        };
+        let res = r.resolution;
+        if !filter.allowed(res.width, res.height) {
+            ui.colored_label(
+                self.theme.warning,
+                tr_args(
+                    "settings.recording.denoise_too_large",
+                    &[("width", &res.width), ("height", &res.height)],
+                ),
+            );
+        } else if r.denoise.enabled {
+            let text = match filter.cpu_percent(r.denoise.strength, res) {
+                Some(percent) => tr_args(
+                    "settings.recording.denoise_cpu",
+                    &[
+                        ("filter", &filter.element()),
+                        ("percent", &(percent.round() as u32)),
+                    ],
+                ),
+                None => tr_args(
+                    "settings.recording.denoise_hardware",
+                    &[("filter", &filter.element())],
+                ),
+            };
+            ui.weak(text);
+        }
+    }
+}
+
+fn strength_label(strength: DenoiseStrength) -> String {
+    match strength {
+        DenoiseStrength::Light => tr("settings.recording.denoise_light"),
+        DenoiseStrength::Medium => tr("settings.recording.denoise_medium"),
+        DenoiseStrength::Strong => tr("settings.recording.denoise_strong"),
+    }
+    .to_string()
+}
@@ -0,0 +1,260 @@
+use eframe::egui;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::file::session_log::Severity;
+use crate::i18n::{tr, tr_args};
+use crate::video::controls::{ControlKind, ControlPanel, DeviceControl, ExposureMode};
+
+/// 面板打开时重新读取控件的间隔, 自动模式会改变其他控件的值和是否可用.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+/// 检查摄像头是否重新连接的间隔.
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 用设备节点的 inode 判断摄像头是否重新插拔, udev 每次连接都会重新创建节点.
+pub(super) struct DeviceWatch {
+    path: Option<PathBuf>,
+    inode: Option<u64>,
+    checked_at: Instant,
+}
+
+impl DeviceWatch {
+    pub fn new(path: Option<&Path>) -> Self {
+        let path = path.map(Path::to_path_buf);
+        let inode = path.as_deref().and_then(node_inode);
+        Self {
+            path,
+            inode,
+            checked_at: Instant::now(),
+        }
+    }
+
+    /// 设备节点重新出现或换成了新的节点时返回 true.
+    fn poll(&mut self, now: Instant) -> bool {
+        if now < self.checked_at + DEVICE_POLL_INTERVAL {
+            return false;
+        }
+        self.checked_at = now;
+        let Some(path) = &self.path else {
+            return false;
+        };
+        let inode = node_inode(path);
+        if self.inode.is_some() && inode.is_none() {
+            tracing::warn!(device = %path.display(), "camera disconnected");
+        }
+        let reconnected = inode.is_some() && inode != self.inode;
+        self.inode = inode;
+        reconnected
+    }
+}
+
+fn node_inode(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).ok().map(|m| m.ino())
+}
+
+impl CameraApp {
+    pub(super) fn open_control_panel(&mut self, device: Option<&Path>) {
+        self.control_panel = device.and_then(|device| {
+            ControlPanel::open(device)
+                .inspect_err(|e| tracing::warn!("could not enumerate camera controls: {}", e))
+                .ok()
+        });
+        self.device_watch = DeviceWatch::new(device);
+    }
+
+    /// 摄像头重新连接后重新打开控件, 并按修改顺序恢复用户在面板中改过的值.
+    pub(super) fn check_camera_reconnect(&mut self) {
+        if !self.device_watch.poll(Instant::now()) {
+            return;
+        }
+        tracing::info!("camera reconnected, reopening controls");
+        self.open_camera_controls();
+        let Some(panel) = &mut self.control_panel else {
+            return;
+        };
+        let mut restored = 0;
+        for &(id, value) in &self.control_changes {
+            match panel.set(id, value) {
+                Ok(()) => restored += 1,
+                Err(e) => tracing::warn!(id, value, "could not restore camera control: {}", e),
+            }
+        }
+        if restored > 0 {
+            self.sync_camera_widgets();
+        }
+        let text = tr_args("toast.camera_reconnected", &[("count", &restored)]);
+        self.log_session(Severity::Info, text.clone());
+        self.toasts.push(ToastLevel::Info, text);
+    }
+
+    fn set_device_control(&mut self, id: u32, value: i32, reset: bool) {
+        let Some(panel) = &mut self.control_panel else {
+            return;
+        };
+        let Some(control) = panel.controls.iter().find(|c| c.id == id) else {
+            return;
+        };
+        let (name, remember) = (control.name.clone(), control.kind != ControlKind::Button);
+        match panel.set(id, value) {
+            Ok(()) => {
+                tracing::info!(control = %name, value, "camera control changed");
+                self.control_changes.retain(|&(changed, _)| changed != id);
+                if remember && !reset {
+                    self.control_changes.push((id, value));
+                }
+                self.sync_camera_widgets();
+            }
+            Err(e) => {
+                tracing::warn!(control = %name, value, "could not set camera control: {}", e);
+                self.toasts.push(
+                    ToastLevel::Warning,
+                    tr_args("toast.control_failed", &[("name", &name), ("error", &e)]),
+                );
+            }
+        }
+    }
+
+    /// 面板可以改到底部栏的 ISO, 快门和对焦, 改完后从设备重新读取.
+    fn sync_camera_widgets(&mut self) {
+        if let Some(iso) = self.gain.as_ref().and_then(|g| g.iso().ok()) {
+            self.iso = iso;
+        }
+        if let Some(shutter) = self.read_shutter() {
+            self.shutter = shutter;
+        }
+        if let Ok(exposure) = &self.exposure
+            && let Ok(auto) = exposure.is_auto()
+        {
+            if auto {
+                self.ae_mode = ExposureMode::Auto;
+            } else if self.ae_mode == ExposureMode::Auto {
+                self.ae_mode = ExposureMode::Manual;
+            }
+        }
+        if let Some(focus) = &self.focus {
+            self.autofocus = focus.is_auto().unwrap_or(self.autofocus);
+            self.focus_position = focus.position().unwrap_or(self.focus_position);
+        }
+    }
+
+    /// 设备报告的所有控件: 整数为滑块, 开关为复选框, 菜单为下拉框.
+    pub(super) fn device_controls_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_device_controls;
+        let mut action = None;
+        egui::Window::new(tr("device_controls.title"))
+            .open(&mut open)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                let Some(panel) = &mut self.control_panel else {
+                    ui.label(tr("device_controls.no_camera"));
+                    return;
+                };
+                let now = Instant::now();
+                if now >= self.control_refresh_at {
+                    panel.refresh();
+                    self.control_refresh_at = now + REFRESH_INTERVAL;
+                }
+                ui.ctx().request_repaint_after(REFRESH_INTERVAL);
+                if panel.controls.is_empty() {
+                    ui.label(tr("device_controls.empty"));
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("device_controls")
+                        .num_columns(3)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for control in &panel.controls {
+                                ui.label(&control.name);
+                                let editor = ui.add_enabled_ui(control.is_enabled(), |ui| {
+                                    control_editor(ui, control)
+                                });
+                                if let Some(value) = editor.inner {
+                                    action = Some((control.id, value, false));
+                                }
+                                editor
+                                    .response
+                                    .on_disabled_hover_text(if control.read_only {
+                                        tr("device_controls.read_only")
+                                    } else {
+                                        tr("device_controls.inactive")
+                                    });
+                                if control.kind != ControlKind::Button
+                                    && ui
+                                        .add_enabled(
+                                            control.is_enabled()
+                                                && control.value != control.default,
+                                            egui::Button::new("↺").small(),
+                                        )
+                                        .on_hover_text(tr_args(
+                                            "device_controls.reset",
+                                            &[("value", &value_label(control, control.default))],
+                                        ))
+                                        .clicked()
+                                {
+                                    action = Some((control.id, control.default, true));
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+                if !self.control_changes.is_empty() {
+                    ui.separator();
+                    ui.label(tr_args(
+                        "device_controls.restored",
+                        &[("count", &self.control_changes.len())],
+                    ));
+                }
+            });
+        self.show_device_controls = open;
+        if let Some((id, value, reset)) = action {
+            self.set_device_control(id, value, reset);
+        }
+    }
+}
+
+/// 返回用户选择的新值.
+fn control_editor(ui: &mut egui::Ui, control: &DeviceControl) -> Option<i32> {
+    let mut value = control.value;
+    let changed = match &control.kind {
+        ControlKind::Integer { min, max, step } => ui
+            .add(egui::Slider::new(&mut value, *min..=*max).step_by(*step as f64))
+            .changed(),
+        ControlKind::Boolean => {
+            let mut on = value != 0;
+            let changed = ui.checkbox(&mut on, "").changed();
+            value = on as i32;
+            changed
+        }
+        ControlKind::Menu(items) => {
+            egui::ComboBox::from_id_salt(("device_control", control.id))
+                .selected_text(value_label(control, value))
+                .show_ui(ui, |ui| {
+                    for (index, name) in items {
+                        ui.selectable_value(&mut value, *index, name);
+                    }
+                });
+            value != control.value
+        }
+        ControlKind::Button => ui.button(tr("device_controls.run")).clicked(),
+    };
+    changed.then_some(value)
+}
+
+fn value_label(control: &DeviceControl, value: i32) -> String {
+    match &control.kind {
+        ControlKind::Boolean => tr(if value != 0 {
+            "device_controls.on"
+        } else {
+            "device_controls.off"
+        })
+        .to_string(),
+        ControlKind::Menu(items) => items
+            .iter()
+            .find(|(index, _)| *index == value)
+            .map_or_else(|| value.to_string(), |(_, name)| name.clone()),
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,23 @@
+use super::CameraApp;
+use crate::file::watch::DirWatcher;
+
+impl CameraApp {
+    /// 每帧调用. 换了输出目录时改为监视新的目录, 目录里有变化时更新录像浏览器和剩余空间.
+    pub(super) fn poll_output_dir(&mut self) {
+        let dir = &self.recording.output_dir;
+        if self.dir_watch.as_ref().is_none_or(|w| w.dir() != dir) {
+            self.dir_watch = Some(DirWatcher::spawn(dir));
+        }
+        let Some(changes) = self.dir_watch.as_ref().and_then(DirWatcher::poll) else {
+            return;
+        };
+        tracing::debug!(
+            added = changes.added.len(),
+            removed = changes.removed.len(),
+            rescan = changes.rescan,
+            "output directory changed"
+        );
+        self.disk.refresh();
+        self.browser.apply(changes);
+    }
+}
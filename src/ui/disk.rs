@@ -0,0 +1,119 @@
+use eframe::egui;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::{CameraApp, PARAM_LABEL_SIZE, PARAM_SPACING, PARAM_VALUE_SIZE};
+use crate::file::remaining::{self, RemainingLevel};
+use crate::i18n::{tr, tr_args};
+
+/// 查看剩余空间的间隔. 换了输出目录时立即查看.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 输出目录所在磁盘的剩余空间.
+#[derive(Default)]
+pub(super) struct DiskSpace {
+    dir: PathBuf,
+    free: Option<u64>,
+    checked_at: Option<Instant>,
+}
+
+impl DiskSpace {
+    /// 下一帧重新查看, 例如输出目录里有文件被删除.
+    pub fn refresh(&mut self) {
+        self.checked_at = None;
+    }
+}
+
+impl CameraApp {
+    /// 每帧调用, 定时查看输出目录的剩余空间.
+    pub(super) fn poll_disk_space(&mut self) {
+        let dir = &self.recording.output_dir;
+        let disk = &mut self.disk;
+        if disk.dir == *dir
+            && disk
+                .checked_at
+                .is_some_and(|at| at.elapsed() < POLL_INTERVAL)
+        {
+            return;
+        }
+        disk.dir = dir.clone();
+        disk.checked_at = Some(Instant::now());
+        disk.free = match crate::file::free_space(dir) {
+            Ok(free) => Some(free),
+            Err(e) => {
+                if disk.free.is_some() {
+                    tracing::warn!(dir = %dir.display(), "could not read free space: {}", e);
+                }
+                None
+            }
+        };
+    }
+
+    /// 还能录多久, 以及是否按实际写入速度估算.
+    fn remaining_time(&self) -> Option<(Duration, bool)> {
+        let free = self.disk.free?;
+        if let (Some(elapsed), Some(progress)) = (self.recorder.elapsed(), self.progress)
+            && elapsed >= remaining::MEASURE_AFTER
+            && let Some(left) = remaining::at_write_rate(free, progress.bytes, elapsed)
+        {
+            return Some((left, true));
+        }
+        let r = &self.recording;
+        let kbps = r
+            .encoder
+            .estimated_kbps(r.resolution, r.bitrate_kbps, r.mjpeg_quality);
+        Some((remaining::at_bitrate(free, kbps), false))
+    }
+
+    /// 底栏中的剩余录制时间, 少于设置的分钟数时变黄或变红.
+    pub(super) fn remaining_widget(&self, ui: &mut egui::Ui) {
+        let (Some((left, measured)), Some(free)) = (self.remaining_time(), self.disk.free) else {
+            return;
+        };
+        let color = match self.remaining.level(left) {
+            RemainingLevel::Normal => self.theme.text,
+            RemainingLevel::Low => self.theme.warning,
+            RemainingLevel::Critical => self.theme.alert,
+        };
+        let minutes = left.as_secs() / 60;
+        let hint = if measured {
+            "bar.remaining_measured"
+        } else {
+            "bar.remaining_nominal"
+        };
+        ui.vertical(|ui| {
+            ui.label(
+                egui::RichText::new(tr("bar.remaining"))
+                    .size(PARAM_LABEL_SIZE)
+                    .color(self.theme.label),
+            );
+            ui.label(
+                egui::RichText::new(format!("{}:{:02}", minutes / 60, minutes % 60))
+                    .size(PARAM_VALUE_SIZE)
+                    .strong()
+                    .color(color),
+            );
+        })
+        .response
+        .on_hover_text(tr_args(
+            hint,
+            &[
+                ("free", &crate::file::format_size(free)),
+                ("time", &super::format_duration(left)),
+            ],
+        ));
+        ui.add_space(PARAM_SPACING);
+    }
+
+    /// 设置面板: 剩余时间变色的阈值.
+    pub(super) fn remaining_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.remaining;
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.recording.remaining_warn"));
+            ui.add(egui::DragValue::new(&mut s.warn_minutes).range(0..=600));
+            ui.label(tr("settings.recording.remaining_alert"));
+            ui.add(egui::DragValue::new(&mut s.alert_minutes).range(0..=600));
+            ui.label(tr("settings.recording.remaining_minutes"));
+        });
+    }
+}
@@ -0,0 +1,91 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+use crate::video::histogram::{ExposureWarning, Histogram};
+
+/// 提示与底部栏之间的距离.
+const CHIP_GAP: f32 = 8.0;
+const CHIP_PADDING: egui::Vec2 = egui::vec2(8.0, 4.0);
+
+impl CameraApp {
+    /// 每个新的预览帧调用.
+    pub(super) fn update_exposure_warning(&mut self, image: &egui::ColorImage) {
+        if !self.exposure_warning.is_enabled() {
+            self.exposure_advisor.reset();
+            return;
+        }
+        self.exposure_advisor
+            .update(&Histogram::from_image(image), &self.exposure_warning);
+    }
+
+    /// 在底部栏上方居中显示, 例如 "HIGHLIGHTS CLIPPING 6%". 打开斑马纹时不显示.
+    pub(super) fn exposure_chips(&self, ui: &egui::Ui, bottom_rect: egui::Rect) {
+        if self.signal_lost || !self.exposure_warning.is_enabled() || self.assist.zebra {
+            return;
+        }
+        let galleys: Vec<_> = self
+            .exposure_advisor
+            .warnings()
+            .map(|warning| {
+                let (key, percent) = match warning {
+                    ExposureWarning::Clipping(percent) => ("bar.clipping", percent),
+                    ExposureWarning::Crushed(percent) => ("bar.crushed", percent),
+                };
+                let text = tr_args(key, &[("percent", &format!("{:.0}", percent))]);
+                ui.painter().layout_no_wrap(
+                    text,
+                    egui::FontId::proportional(14.0),
+                    self.theme.warning,
+                )
+            })
+            .collect();
+        if galleys.is_empty() {
+            return;
+        }
+        let width: f32 = galleys
+            .iter()
+            .map(|g| g.size().x + CHIP_PADDING.x * 2.0)
+            .sum::<f32>()
+            + CHIP_GAP * (galleys.len() - 1) as f32;
+        let mut x = bottom_rect.center().x - width / 2.0;
+        for galley in galleys {
+            let size = galley.size() + CHIP_PADDING * 2.0;
+            let chip = egui::Rect::from_min_size(
+                egui::pos2(x, bottom_rect.min.y - CHIP_GAP - size.y),
+                size,
+            );
+            ui.painter().rect_filled(chip, 4.0, self.theme.bar);
+            ui.painter()
+                .galley(chip.min + CHIP_PADDING, galley, self.theme.warning);
+            x += size.x + CHIP_GAP;
+        }
+    }
+
+    /// 设置面板: 过曝和死黑的阈值.
+    pub(super) fn exposure_warning_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.exposure_warning;
+        egui::Grid::new("exposure_warning_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("settings.display.clipping_percent"));
+                ui.add(
+                    egui::DragValue::new(&mut s.clipping_percent)
+                        .range(0.0..=100.0)
+                        .speed(0.5)
+                        .suffix(" %"),
+                );
+                ui.end_row();
+
+                ui.label(tr("settings.display.crushed_percent"));
+                ui.add(
+                    egui::DragValue::new(&mut s.crushed_percent)
+                        .range(0.0..=100.0)
+                        .speed(0.5)
+                        .suffix(" %"),
+                );
+                ui.end_row();
+            });
+        ui.weak(tr("settings.display.exposure_warning_hint"));
+    }
+}
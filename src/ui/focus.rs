@@ -0,0 +1,157 @@
+use eframe::egui;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::camera::scroll_steps;
+use super::{CameraApp, param_widget};
+use crate::i18n::tr;
+use crate::video::controls::Focus;
+
+/// 没有一次性对焦按钮的摄像头, 打开连续自动对焦这么久再关掉.
+const AF_TRIGGER_DURATION: Duration = Duration::from_millis(1500);
+/// 滚动一格移动范围的 1%.
+const SCROLL_PERCENT: i32 = 1;
+
+enum FocusAction {
+    Position(i32),
+    Auto(bool),
+    Trigger,
+}
+
+impl CameraApp {
+    /// 没有对焦马达时界面上不显示对焦.
+    pub(super) fn open_focus(&mut self, device: Option<&Path>) {
+        self.focus = device.and_then(Focus::open);
+        self.af_release_at = None;
+        let Some(focus) = &self.focus else {
+            return;
+        };
+        self.autofocus = focus.is_auto().unwrap_or(false);
+        self.focus_position = focus
+            .position()
+            .inspect_err(|e| tracing::warn!("could not read focus: {}", e))
+            .unwrap_or(focus.min);
+    }
+
+    pub(super) fn set_focus_position(&mut self, position: i32) {
+        let Some(focus) = &self.focus else {
+            return;
+        };
+        let position = position.clamp(focus.min, focus.max);
+        match focus.set_position(position) {
+            Ok(()) => {
+                self.focus_position = position;
+                self.autofocus = false;
+                self.af_release_at = None;
+            }
+            Err(e) => tracing::warn!(position, "could not set focus: {}", e),
+        }
+    }
+
+    fn set_autofocus(&mut self, on: bool) {
+        let Some(focus) = &self.focus else {
+            return;
+        };
+        if let Err(e) = focus.set_auto(on) {
+            tracing::warn!(on, "could not switch autofocus: {}", e);
+            return;
+        }
+        self.autofocus = on;
+        self.af_release_at = None;
+        // 关掉时停在自动对焦找到的位置
+        if !on && let Ok(position) = focus.position() {
+            self.focus_position = position;
+        }
+    }
+
+    fn trigger_autofocus(&mut self) {
+        let Some(focus) = &self.focus else {
+            return;
+        };
+        match focus.trigger() {
+            Ok(true) => {}
+            Ok(false) if focus.has_auto() => {
+                self.set_autofocus(true);
+                self.af_release_at = Some(Instant::now() + AF_TRIGGER_DURATION);
+            }
+            Ok(false) => {}
+            Err(e) => tracing::warn!("could not trigger autofocus: {}", e),
+        }
+    }
+
+    /// 模拟的一次性对焦到时间后关掉自动对焦.
+    pub(super) fn release_autofocus(&mut self, ctx: &egui::Context) {
+        let Some(at) = self.af_release_at else {
+            return;
+        };
+        let now = Instant::now();
+        if now < at {
+            ctx.request_repaint_after(at - now);
+            return;
+        }
+        self.set_autofocus(false);
+    }
+
+    /// 底部栏的对焦位置, 悬停时滚动微调, 点击打开滑块和自动对焦.
+    pub(super) fn focus_widget(&mut self, ui: &mut egui::Ui) {
+        let Some(focus) = &self.focus else {
+            return;
+        };
+        let text = if self.autofocus {
+            tr("bar.af").to_string()
+        } else {
+            format!("{:.0}%", focus.percent(self.focus_position))
+        };
+        let response = param_widget(ui, &self.theme, tr("bar.focus"), &text)
+            .interact(egui::Sense::click())
+            .on_hover_text(tr("bar.focus_hint"));
+        let fine = ((focus.max - focus.min) * SCROLL_PERCENT / 100).max(focus.step);
+
+        let mut action = None;
+        let steps = scroll_steps(ui, &response, &mut self.focus_scroll);
+        if steps != 0 {
+            action = Some(FocusAction::Position(self.focus_position + steps * fine));
+        }
+        let (min, max) = (focus.min, focus.max);
+        egui::Popup::menu(&response)
+            .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+            .show(|ui| {
+                let mut position = self.focus_position;
+                let slider = ui
+                    .add(
+                        egui::Slider::new(&mut position, min..=max)
+                            .step_by(focus.step as f64)
+                            .custom_formatter(|value, _| {
+                                format!("{:.0}%", focus.percent(value as i32))
+                            }),
+                    )
+                    .on_hover_text(tr("bar.slider_hint"));
+                let steps = scroll_steps(ui, &slider, &mut self.focus_slider_scroll);
+                if slider.changed() {
+                    action = Some(FocusAction::Position(position));
+                } else if steps != 0 {
+                    action = Some(FocusAction::Position(self.focus_position + steps * fine));
+                }
+                if focus.has_auto() {
+                    let mut autofocus = self.autofocus;
+                    if ui.checkbox(&mut autofocus, tr("bar.autofocus")).changed() {
+                        action = Some(FocusAction::Auto(autofocus));
+                    }
+                }
+                if focus.can_trigger()
+                    && ui
+                        .add_enabled(!self.autofocus, egui::Button::new(tr("bar.trigger_af")))
+                        .on_hover_text(tr("bar.trigger_af_hint"))
+                        .clicked()
+                {
+                    action = Some(FocusAction::Trigger);
+                }
+            });
+        match action {
+            Some(FocusAction::Position(position)) => self.set_focus_position(position),
+            Some(FocusAction::Auto(on)) => self.set_autofocus(on),
+            Some(FocusAction::Trigger) => self.trigger_autofocus(),
+            None => {}
+        }
+    }
+}
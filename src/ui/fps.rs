@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+
+/// 指数加权平均的时间常数.
+const TIME_CONSTANT: Duration = Duration::from_secs(1);
+/// 至少间隔这么久才计算一次, 避免每帧的抖动.
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 帧率计, 对每段时间内的瞬时帧率做约 1 秒的指数加权平均.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct FpsMeter {
+    /// 上一次计算的时间和当时的累计帧数.
+    last: Option<(Instant, u64)>,
+    fps: Option<f64>,
+}
+
+impl FpsMeter {
+    /// `total` 是累计帧数, 只会增加.
+    pub fn update(&mut self, now: Instant, total: u64) {
+        let Some((at, before)) = self.last else {
+            self.last = Some((now, total));
+            return;
+        };
+        let elapsed = now.duration_since(at);
+        if elapsed < MIN_INTERVAL {
+            return;
+        }
+        let rate = total.saturating_sub(before) as f64 / elapsed.as_secs_f64();
+        let alpha = 1.0 - (-elapsed.as_secs_f64() / TIME_CONSTANT.as_secs_f64()).exp();
+        self.fps = Some(match self.fps {
+            Some(fps) => fps + alpha * (rate - fps),
+            None => rate,
+        });
+        self.last = Some((now, total));
+    }
+
+    pub fn fps(&self) -> Option<f64> {
+        self.fps
+    }
+}
+
+/// 采集帧率 (appsink 收到的帧) 和界面帧率 (实际上传为纹理的帧).
+/// 前者低说明是采集的问题, 只有后者低说明是渲染的问题.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct PreviewRate {
+    pub cam: FpsMeter,
+    pub ui: FpsMeter,
+    /// 最近一帧的序号.
+    sequence: u64,
+    /// 上传过的纹理数.
+    painted: u64,
+    pub nominal: Option<f64>,
+}
+
+impl PreviewRate {
+    /// 每上传一帧新纹理时调用.
+    pub fn on_frame(&mut self, sequence: u64, nominal: Option<f64>) {
+        self.sequence = sequence;
+        self.painted += 1;
+        self.nominal = nominal;
+    }
+
+    /// 每次重绘时调用, 没有新帧时帧率随之下降.
+    pub fn tick(&mut self, now: Instant) {
+        self.cam.update(now, self.sequence);
+        self.ui.update(now, self.painted);
+    }
+
+    /// 采集帧率比协商的帧率低 10% 以上.
+    pub fn cam_is_slow(&self) -> bool {
+        match (self.cam.fps(), self.nominal) {
+            (Some(fps), Some(nominal)) => fps < nominal * 0.9,
+            _ => false,
+        }
+    }
+
+    /// 管线重新开始出帧时清零, 序号也从头开始.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Local};
+use eframe::egui;
+use std::time::Instant;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+
+/// 定格时主窗口显示的画面. 管线和录制照常进行, 节目输出和画中画仍是实时画面.
+pub(super) struct Frozen {
+    /// 定格时显示的纹理. 实时预览每帧换一个新纹理, 这个不会被覆盖.
+    texture: egui::TextureHandle,
+    /// 这一帧的采集时刻.
+    captured: DateTime<Local>,
+    /// 录制中定格时写入文件的时间码.
+    timecode: Option<String>,
+}
+
+impl CameraApp {
+    /// 空格键定格或恢复预览. 恢复后直接显示最新的一帧, 不回放定格期间的画面.
+    pub(super) fn toggle_freeze(&mut self) {
+        if self.frozen.take().is_some() {
+            tracing::info!("preview unfrozen");
+            return;
+        }
+        let (Some(texture), Some(captured_at)) =
+            (self.preview_texture().cloned(), self.frame_captured_at)
+        else {
+            return;
+        };
+        let captured = Local::now() - captured_at.elapsed();
+        let timecode = self
+            .timecode
+            .as_ref()
+            .and_then(|tc| *tc.lock())
+            .map(|tc| tc.to_string());
+        tracing::info!(captured = %captured.format("%H:%M:%S%.3f"), "banner.frozen");
+        self.frozen = Some(Frozen {
+            texture,
+            captured,
+            timecode,
+        });
+    }
+
+    pub(super) fn frozen_texture(&self) -> Option<&egui::TextureHandle> {
+        self.frozen.as_ref().map(|f| &f.texture)
+    }
+
+    /// 新的一帧到达时是否更新只在主窗口中显示的画面.
+    pub(super) fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// 记下主窗口正在显示的画面的采集时刻, 定格时据此显示时间.
+    pub(super) fn note_frame_captured(&mut self, at: Instant) {
+        if self.frozen.is_none() {
+            self.frame_captured_at = Some(at);
+        }
+    }
+
+    /// 预览顶部醒目的定格标记, 带这一帧的时间.
+    pub(super) fn freeze_banner(&self, ui: &egui::Ui, rect: egui::Rect) {
+        let Some(frozen) = &self.frozen else {
+            return;
+        };
+        let time = frozen.captured.format("%H:%M:%S%.3f").to_string();
+        let text = match &frozen.timecode {
+            Some(tc) => tr_args("banner.frozen_tc", &[("time", &time), ("tc", tc)]),
+            None => tr_args("banner.frozen", &[("time", &time)]),
+        };
+        let painter = ui.painter();
+        let galley =
+            painter.layout_no_wrap(text, egui::FontId::proportional(22.0), egui::Color32::WHITE);
+        let pos = egui::pos2(
+            rect.center().x - galley.size().x / 2.0,
+            rect.top() + super::EDGE_MARGIN * 3.0,
+        );
+        let background = egui::Rect::from_min_size(pos, galley.size()).expand(8.0);
+        painter.rect_filled(background, 4.0, self.theme.alert);
+        painter.galley(pos, galley, egui::Color32::WHITE);
+    }
+
+    /// 预览右键菜单中的定格项.
+    pub(super) fn freeze_menu_item(&mut self, ui: &mut egui::Ui) {
+        let label = if self.is_frozen() {
+            tr("action.unfreeze")
+        } else {
+            tr("action.freeze")
+        };
+        if ui.button(label).clicked() {
+            self.toggle_freeze();
+        }
+    }
+}
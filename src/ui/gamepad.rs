@@ -0,0 +1,168 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::gamepad::{GamepadSettings, PadAxis, PadButton, PadEvent, apply_deadzone};
+use crate::i18n::{tr, tr_args};
+use crate::keymap::Action;
+use crate::video::zoom::{MAX_ZOOM, MIN_ZOOM};
+
+/// 手柄事件不会唤醒界面, 有手柄时至少这么久重绘一次来读取.
+const POLL_INTERVAL: Duration = Duration::from_millis(30);
+/// 摇杆推到底时每秒改变的变焦倍数.
+const ZOOM_SPEED: f32 = 2.0;
+/// 摇杆推到底时每秒移动对焦范围的这个比例.
+const FOCUS_SPEED: f32 = 0.5;
+
+impl CameraApp {
+    /// 每帧调用, 执行手柄按键并按摇杆调整变焦和对焦. 屏幕锁定时不响应.
+    pub(super) fn poll_gamepad(&mut self, ctx: &egui::Context) {
+        let Some(pads) = &mut self.gamepads else {
+            return;
+        };
+        let events = pads.poll();
+        let connected = pads.name().is_some();
+        let settings = &self.gamepad;
+        let zoom = settings.zoom_axis.map_or(0.0, |axis| {
+            apply_deadzone(pads.axis(axis), settings.deadzone)
+        });
+        let focus = settings.focus_axis.map_or(0.0, |axis| {
+            apply_deadzone(pads.axis(axis), settings.deadzone)
+        });
+        if connected {
+            ctx.request_repaint_after(POLL_INTERVAL);
+        }
+
+        for event in events {
+            match event {
+                PadEvent::Connected(name) => self.toasts.push(
+                    ToastLevel::Info,
+                    tr_args("toast.gamepad_connected", &[("name", &name)]),
+                ),
+                PadEvent::Disconnected(name) => self.toasts.push(
+                    ToastLevel::Warning,
+                    tr_args("toast.gamepad_disconnected", &[("name", &name)]),
+                ),
+                PadEvent::Pressed(button) => {
+                    // 与键盘一样让隐藏的界面重新出现
+                    self.last_activity = Instant::now();
+                    if !self.gamepad.enabled || self.locked {
+                        continue;
+                    }
+                    if let Some(action) = self.gamepad.buttons.get(&button).copied() {
+                        self.run_action(ctx, action);
+                    }
+                }
+            }
+        }
+        if !self.gamepad.enabled || self.locked {
+            return;
+        }
+
+        let dt = ctx.input(|i| i.stable_dt).min(0.1);
+        if zoom != 0.0 {
+            self.last_activity = Instant::now();
+            self.zoom_target =
+                (self.zoom_target + zoom * ZOOM_SPEED * dt).clamp(MIN_ZOOM, MAX_ZOOM);
+        }
+        if focus != 0.0
+            && let Some(range) = self.focus.as_ref().map(|f| (f.max - f.min) as f32)
+        {
+            self.last_activity = Instant::now();
+            // 每帧的移动量常常不到一步, 余下的留到下一帧
+            self.gamepad_focus_carry += focus * range * FOCUS_SPEED * dt;
+            let steps = self.gamepad_focus_carry.trunc();
+            if steps != 0.0 {
+                self.gamepad_focus_carry -= steps;
+                self.set_focus_position(self.focus_position + steps as i32);
+            }
+        } else {
+            self.gamepad_focus_carry = 0.0;
+        }
+    }
+
+    /// 顶部栏中的手柄图标, 没有连接手柄或已停用时不显示.
+    pub(super) fn gamepad_indicator(&self, ui: &mut egui::Ui) {
+        if !self.gamepad.enabled {
+            return;
+        }
+        let Some(name) = self.gamepads.as_ref().and_then(|p| p.name()) else {
+            return;
+        };
+        ui.add(
+            egui::Image::new(crate::icons::ICON_GAMEPAD)
+                .tint(self.theme.label)
+                .max_width(22.0),
+        )
+        .on_hover_text(tr_args("top.gamepad", &[("name", &name)]));
+    }
+
+    /// 设置面板: 手柄按键映射和摇杆. 按下手柄上的键时对应的行高亮.
+    pub(super) fn gamepad_section(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.gamepad.enabled, tr("settings.gamepad.enabled"));
+        let pads = self.gamepads.as_ref();
+        match pads.and_then(|p| p.name()) {
+            Some(name) => ui.label(tr_args("settings.gamepad.connected", &[("name", &name)])),
+            None if pads.is_none() => ui.weak(tr("settings.gamepad.unavailable")),
+            None => ui.weak(tr("settings.gamepad.none")),
+        };
+
+        egui::Grid::new("gamepad_grid")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for button in PadButton::ALL {
+                    let label = egui::RichText::new(button.label()).monospace();
+                    if pads.is_some_and(|p| p.is_pressed(button)) {
+                        ui.label(label.strong().color(self.theme.rec));
+                    } else {
+                        ui.label(label);
+                    }
+                    let mut action = self.gamepad.buttons.get(&button).copied();
+                    egui::ComboBox::from_id_salt(("gamepad_button", button))
+                        .selected_text(action_text(action))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut action, None, action_text(None));
+                            for a in Action::ALL {
+                                ui.selectable_value(&mut action, Some(a), a.label());
+                            }
+                        });
+                    match action {
+                        Some(action) => self.gamepad.buttons.insert(button, action),
+                        None => self.gamepad.buttons.remove(&button),
+                    };
+                    ui.end_row();
+                }
+
+                ui.label(tr("settings.gamepad.zoom_axis"));
+                axis_combo(ui, "gamepad_zoom", &mut self.gamepad.zoom_axis);
+                ui.end_row();
+                ui.label(tr("settings.gamepad.focus_axis"));
+                axis_combo(ui, "gamepad_focus", &mut self.gamepad.focus_axis);
+                ui.end_row();
+                ui.label(tr("settings.gamepad.deadzone"));
+                ui.add(egui::Slider::new(&mut self.gamepad.deadzone, 0.0..=0.5));
+                ui.end_row();
+            });
+        if ui.button(tr("settings.gamepad.reset")).clicked() {
+            self.gamepad = GamepadSettings::default();
+        }
+    }
+}
+
+fn action_text(action: Option<Action>) -> String {
+    action.map_or_else(|| tr("settings.keymap.none").to_string(), Action::label)
+}
+
+fn axis_combo(ui: &mut egui::Ui, id: &str, axis: &mut Option<PadAxis>) {
+    let text = axis.map_or(tr("settings.gamepad.no_axis"), PadAxis::label);
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(text)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(axis, None, tr("settings.gamepad.no_axis"));
+            for a in PadAxis::ALL {
+                ui.selectable_value(axis, Some(a), a.label());
+            }
+        });
+}
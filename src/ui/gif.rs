@@ -0,0 +1,76 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::i18n::{tr, tr_args};
+use crate::video::gif::{FRAME_RATES, GifState, SECONDS, WIDTHS};
+
+impl CameraApp {
+    /// 底栏上评分按钮旁边: 把刚录完的镜次末尾导出为 GIF, 导出期间显示进度.
+    pub(super) fn gif_button(&mut self, ui: &mut egui::Ui) {
+        if let Some((fraction, queued)) = self.gifs.progress() {
+            let mut text = format!("GIF {:.0}%", fraction * 100.0);
+            if queued > 0 {
+                text.push_str(&format!(" +{}", queued));
+            }
+            ui.add(
+                egui::ProgressBar::new(fraction)
+                    .desired_width(90.0)
+                    .text(text),
+            );
+            ui.ctx().request_repaint();
+            return;
+        }
+        let Some(path) = &self.last_clip else {
+            return;
+        };
+        let hint = tr_args("bar.gif_hint", &[("seconds", &self.gif_settings.seconds)]);
+        if ui.button("GIF").on_hover_text(hint).clicked() {
+            let path = path.clone();
+            self.gifs.push(&path, &self.gif_settings);
+        }
+    }
+
+    /// 每帧调用, 提示导出的结果.
+    pub(super) fn poll_gifs(&mut self) {
+        for job in self.gifs.take_finished() {
+            match job.state {
+                GifState::Failed(e) => self.toasts.push(
+                    ToastLevel::Warning,
+                    tr_args("toast.gif_failed", &[("error", &e)]),
+                ),
+                _ => self.toasts.push(
+                    ToastLevel::Info,
+                    tr_args(
+                        "toast.gif_saved",
+                        &[("path", &job.output.display().to_string())],
+                    ),
+                ),
+            }
+        }
+    }
+
+    /// 设置面板: 导出的长度, 宽度和帧率.
+    pub(super) fn gif_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.gif_settings;
+        egui::Grid::new("gif_grid").num_columns(2).show(ui, |ui| {
+            ui.label(tr("settings.gif.seconds"));
+            ui.add(egui::Slider::new(&mut s.seconds, SECONDS).suffix(" s"));
+            ui.end_row();
+
+            ui.label(tr("settings.gif.width"));
+            ui.add(
+                egui::DragValue::new(&mut s.width)
+                    .range(WIDTHS)
+                    .speed(8)
+                    .suffix(" px"),
+            );
+            ui.end_row();
+
+            ui.label(tr("settings.gif.fps"));
+            ui.add(egui::DragValue::new(&mut s.fps).range(FRAME_RATES));
+            ui.end_row();
+        });
+        ui.weak(tr("settings.gif.hint"));
+    }
+}
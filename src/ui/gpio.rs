@@ -0,0 +1,18 @@
+use eframe::egui;
+use std::time::Instant;
+
+use super::CameraApp;
+use crate::keymap::Action;
+
+impl CameraApp {
+    /// 每帧调用: 按钮与快捷键一样切换录制, 屏幕锁定时不响应. 指示灯跟随录制阶段.
+    pub(super) fn poll_gpio(&mut self, ctx: &egui::Context) {
+        if self.gpio.pressed() {
+            self.last_activity = Instant::now();
+            if !self.locked {
+                self.run_action(ctx, Action::ToggleRecord);
+            }
+        }
+        self.gpio.set_phase(self.recorder.phase());
+    }
+}
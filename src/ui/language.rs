@@ -0,0 +1,67 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::i18n::{self, Language, tr};
+
+/// 内建字体没有汉字, 按顺序尝试常见发行版中的中文字体.
+const CJK_FONTS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+];
+
+impl CameraApp {
+    /// 启动时和切换语言后调用, 需要时加载中文字体.
+    pub fn apply_language(&mut self, ctx: &egui::Context) {
+        i18n::set_language(self.language);
+        tracing::debug!(language = ?self.language, "language");
+        if self.language == Language::English || self.cjk_font_loaded {
+            return;
+        }
+        let Some((path, data)) = CJK_FONTS
+            .iter()
+            .find_map(|path| std::fs::read(path).ok().map(|data| (path, data)))
+        else {
+            tracing::warn!("no cjk font found");
+            self.toasts
+                .push(ToastLevel::Warning, tr("toast.no_cjk_font"));
+            return;
+        };
+        tracing::info!(font = %path, "loaded cjk font");
+        // 作为回退字体, 拉丁字母仍然使用内建字体
+        ctx.add_font(egui::epaint::text::FontInsert::new(
+            "cjk",
+            egui::FontData::from_owned(data),
+            [egui::FontFamily::Proportional, egui::FontFamily::Monospace]
+                .into_iter()
+                .map(|family| egui::epaint::text::InsertFontFamily {
+                    family,
+                    priority: egui::epaint::text::FontPriority::Lowest,
+                })
+                .collect(),
+        ));
+        self.cjk_font_loaded = true;
+    }
+
+    /// 设置面板: 界面语言, 立即生效.
+    pub(super) fn language_section(&mut self, ui: &mut egui::Ui) {
+        let before = self.language;
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.display.language"));
+            egui::ComboBox::from_id_salt("language")
+                .selected_text(self.language.native_name())
+                .show_ui(ui, |ui| {
+                    for language in Language::ALL {
+                        ui.selectable_value(&mut self.language, language, language.native_name());
+                    }
+                });
+        });
+        if self.language != before {
+            self.apply_language(ui.ctx());
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+/// 平均值的平滑系数, 越小越平稳.
+const SMOOTHING: f64 = 0.1;
+
+/// 从采集到贴上纹理的延迟统计, 单位毫秒.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct LatencyStats {
+    pub current: f64,
+    /// 指数移动平均.
+    pub average: f64,
+    pub max: f64,
+    pub samples: u64,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_secs_f64() * 1000.0;
+        self.current = ms;
+        self.average = match self.samples {
+            0 => ms,
+            _ => self.average + SMOOTHING * (ms - self.average),
+        };
+        self.max = self.max.max(ms);
+        self.samples += 1;
+    }
+
+    /// 管线重新开始出帧时清零, 旧的最大值不再有意义.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -0,0 +1,214 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use super::{BOTTOM_BAR_HEIGHT, CameraApp, EDGE_MARGIN};
+use crate::i18n::{tr, tr_args};
+
+/// 按住锁图标这么久解锁.
+const UNLOCK_HOLD: Duration = Duration::from_secs(2);
+/// 锁定时有人操作后提示显示的时间.
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+const LOCK_DIAMETER: f32 = 64.0;
+const MAX_PIN_LEN: usize = 12;
+
+impl CameraApp {
+    /// Ctrl+L 或顶部栏的锁图标锁定. 录制和预览不受影响, 只是不再响应输入.
+    pub(super) fn lock_screen(&mut self) {
+        self.locked = true;
+        self.unlock_hold_since = None;
+        self.pin_entry.clear();
+        tracing::info!("screen locked");
+    }
+
+    fn unlock_screen(&mut self) {
+        self.locked = false;
+        self.unlock_hold_since = None;
+        self.pin_entry.clear();
+        self.lock_notice_until = None;
+        tracing::info!("screen unlocked");
+    }
+
+    /// 在 egui 处理输入之前调用. 锁定时只保留落在锁图标上的指针事件和输入 PIN 用的按键,
+    /// 其余丢弃并提示. 关闭窗口和信号不是输入事件, 不受影响.
+    pub(super) fn filter_locked_input(&mut self, raw: &mut egui::RawInput) {
+        if !self.locked {
+            return;
+        }
+        let target = self.lock_target;
+        let inside = |pos: egui::Pos2| target.is_some_and(|r| r.contains(pos));
+        let mut poked = false;
+        raw.events.retain(|event| {
+            let keep = match event {
+                egui::Event::PointerMoved(pos) | egui::Event::Touch { pos, .. } => inside(*pos),
+                // 松开总是保留, 否则按住锁图标后在外面松开会一直按着
+                egui::Event::PointerButton { pos, pressed, .. } => !pressed || inside(*pos),
+                egui::Event::PointerGone | egui::Event::WindowFocused(_) => true,
+                egui::Event::Text(text) => text.chars().all(|c| c.is_ascii_digit()),
+                egui::Event::Key { key, .. } => {
+                    matches!(key, egui::Key::Backspace | egui::Key::Enter)
+                }
+                _ => false,
+            };
+            poked |= !keep;
+            keep
+        });
+        raw.hovered_files.clear();
+        raw.dropped_files.clear();
+        if poked {
+            self.lock_notice_until = Some(Instant::now() + NOTICE_DURATION);
+        }
+    }
+
+    /// 锁定时的锁图标, 按住或输入 PIN 解锁. 界面自动隐藏时也显示.
+    pub(super) fn lock_overlay(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        if !self.locked {
+            self.lock_target = None;
+            return;
+        }
+        self.pin_input(ui.ctx());
+        if !self.locked {
+            return;
+        }
+        let center = rect.center_bottom()
+            - egui::vec2(0.0, BOTTOM_BAR_HEIGHT + EDGE_MARGIN + LOCK_DIAMETER / 2.0);
+        let target = egui::Rect::from_center_size(center, egui::Vec2::splat(LOCK_DIAMETER));
+        self.lock_target = Some(target);
+        let response = ui.interact(
+            target,
+            ui.id().with("unlock"),
+            egui::Sense::click_and_drag(),
+        );
+        let mut hold = None;
+        if response.is_pointer_button_down_on() {
+            let since = *self.unlock_hold_since.get_or_insert_with(Instant::now);
+            if since.elapsed() >= UNLOCK_HOLD {
+                self.unlock_screen();
+                return;
+            }
+            hold = Some(since.elapsed().as_secs_f32() / UNLOCK_HOLD.as_secs_f32());
+            self.lock_notice_until = Some(Instant::now() + NOTICE_DURATION);
+            ui.ctx().request_repaint();
+        } else {
+            self.unlock_hold_since = None;
+        }
+
+        let painter = ui.painter();
+        let radius = LOCK_DIAMETER / 2.0;
+        painter.circle_filled(center, radius, self.theme.bar);
+        egui::Image::new(crate::icons::ICON_LOCK)
+            .tint(self.theme.text)
+            .paint_at(
+                ui,
+                egui::Rect::from_center_size(center, egui::Vec2::splat(radius)),
+            );
+        if let Some(fraction) = hold {
+            let points = (0..=48)
+                .map(|i| {
+                    let angle = -std::f32::consts::FRAC_PI_2
+                        + std::f32::consts::TAU * fraction * i as f32 / 48.0;
+                    center + (radius - 3.0) * egui::vec2(angle.cos(), angle.sin())
+                })
+                .collect();
+            painter.add(egui::Shape::line(
+                points,
+                egui::Stroke::new(4.0, self.theme.text),
+            ));
+        }
+
+        match self.lock_notice_until {
+            Some(until) if Instant::now() < until => {
+                let text = if self.unlock_pin.is_empty() {
+                    tr("banner.locked").to_string()
+                } else {
+                    tr_args(
+                        "banner.locked_pin",
+                        &[("pin", &"•".repeat(self.pin_entry.len()))],
+                    )
+                };
+                painter.text(
+                    center - egui::vec2(0.0, radius + 12.0),
+                    egui::Align2::CENTER_BOTTOM,
+                    text,
+                    egui::FontId::proportional(20.0),
+                    self.theme.text,
+                );
+                ui.ctx().request_repaint_after(until - Instant::now());
+            }
+            _ => self.lock_notice_until = None,
+        }
+    }
+
+    /// 设置了 PIN 时, 输入正确的数字解锁. Enter 确认输入错误时清空.
+    fn pin_input(&mut self, ctx: &egui::Context) {
+        if self.unlock_pin.is_empty() {
+            return;
+        }
+        let events = ctx.input(|i| i.events.clone());
+        for event in events {
+            match event {
+                egui::Event::Text(text) => {
+                    for c in text.chars().filter(char::is_ascii_digit) {
+                        if self.pin_entry.len() < MAX_PIN_LEN {
+                            self.pin_entry.push(c);
+                        }
+                    }
+                    self.lock_notice_until = Some(Instant::now() + NOTICE_DURATION);
+                }
+                egui::Event::Key {
+                    key: egui::Key::Backspace,
+                    pressed: true,
+                    ..
+                } => {
+                    self.pin_entry.pop();
+                }
+                egui::Event::Key {
+                    key: egui::Key::Enter,
+                    pressed: true,
+                    ..
+                } if !self.pin_entry.is_empty() => {
+                    tracing::warn!("wrong unlock pin");
+                    self.pin_entry.clear();
+                }
+                _ => {}
+            }
+            if self.pin_entry == self.unlock_pin {
+                self.unlock_screen();
+                return;
+            }
+        }
+    }
+
+    /// 顶部栏中锁定屏幕的按钮.
+    pub(super) fn lock_button(&mut self, ui: &mut egui::Ui) {
+        let response = ui
+            .add(
+                egui::Image::new(crate::icons::ICON_LOCK)
+                    .tint(self.theme.text)
+                    .max_width(24.0)
+                    .sense(egui::Sense::click()),
+            )
+            .on_hover_text(tr("top.lock_hint"));
+        if response.clicked() {
+            self.lock_screen();
+        }
+    }
+
+    /// 设置面板: 解锁 PIN.
+    pub(super) fn lock_section(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.display.unlock_pin"));
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.unlock_pin)
+                    .password(true)
+                    .hint_text(tr("settings.display.unlock_pin_hint"))
+                    .desired_width(100.0),
+            );
+            if response.changed() {
+                self.unlock_pin.retain(|c| c.is_ascii_digit());
+                self.unlock_pin.truncate(MAX_PIN_LEN);
+            }
+        })
+        .response
+        .on_hover_text(tr("settings.display.unlock_pin_help"));
+    }
+}
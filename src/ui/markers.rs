@@ -0,0 +1,148 @@
+use eframe::egui;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::toast::ToastLevel;
+use super::{CameraApp, PARAM_SPACING};
+use crate::file::markers::{self, Marker, MarkerExport};
+use crate::file::session_log::Severity;
+use crate::file::sidecar::Sidecar;
+use crate::i18n::{tr, tr_args};
+use crate::keymap::{self, Action};
+
+/// 打标记后计数高亮的时间.
+const FLASH: Duration = Duration::from_millis(600);
+
+impl CameraApp {
+    /// 在当前录制的这一刻打一个标记. 没有录制时只提示.
+    pub(super) fn add_marker(&mut self, label: Option<String>) {
+        let Some(elapsed) = self.recorder.elapsed() else {
+            self.toasts.push(ToastLevel::Info, tr("toast.marker_idle"));
+            return;
+        };
+        let timecode = self
+            .timecode
+            .as_ref()
+            .and_then(|tc| *tc.lock())
+            .map(|tc| tc.to_string());
+        let at = timecode
+            .clone()
+            .unwrap_or_else(|| super::format_duration(elapsed));
+        self.markers.push(Marker {
+            secs: elapsed.as_secs_f64(),
+            timecode,
+            label,
+        });
+        self.marker_flash = Some(Instant::now());
+        let mut text = tr_args("toast.marker", &[("n", &self.markers.len()), ("at", &at)]);
+        if let Some(label) = self.markers.last().and_then(|m| m.label.as_ref()) {
+            text.push_str(&format!(" — {}", label));
+        }
+        self.log_session(Severity::Info, text.clone());
+        self.toasts.push(ToastLevel::Info, text);
+    }
+
+    /// 底栏上录制进度旁边的标记按钮和计数, 右键选择标签.
+    pub(super) fn marker_button(&mut self, ui: &mut egui::Ui) {
+        let flashing = self.marker_flash.is_some_and(|t| t.elapsed() < FLASH);
+        let color = if flashing {
+            self.theme.good
+        } else {
+            self.theme.text
+        };
+        let text = egui::RichText::new(format!("{} {}", tr("bar.marker"), self.markers.len()))
+            .strong()
+            .color(color);
+        let key = self
+            .keymap
+            .get(Action::Marker)
+            .map(|shortcut| keymap::format_shortcut(&shortcut))
+            .unwrap_or_else(|| "—".to_string());
+        let response = ui
+            .button(text)
+            .on_hover_text(tr_args("bar.marker_hint", &[("key", &key)]));
+        let mut chosen = response.clicked().then_some(None);
+        response.context_menu(|ui| {
+            for label in &self.marker_settings.labels {
+                if ui.button(label).clicked() {
+                    chosen = Some(Some(label.clone()));
+                }
+            }
+        });
+        if let Some(label) = chosen {
+            self.add_marker(label);
+        }
+        if flashing {
+            ui.ctx().request_repaint_after(FLASH);
+        }
+        ui.add_space(PARAM_SPACING);
+    }
+
+    /// 把这次录制的标记写入 sidecar, 并按设置导出列表.
+    pub(super) fn save_markers(&mut self, clip: &Path) {
+        let list = std::mem::take(&mut self.markers);
+        if list.is_empty() {
+            return;
+        }
+        if let Err(e) = Sidecar::update(clip, |meta| meta.markers = list.clone()) {
+            tracing::warn!(path = %clip.display(), "could not save markers: {}", e);
+        }
+        match markers::export(clip, &list, self.marker_settings.export) {
+            Ok(Some(path)) => {
+                tracing::info!(path = %path.display(), count = list.len(), "markers exported")
+            }
+            Ok(None) => {}
+            Err(e) => self.toasts.push(
+                ToastLevel::Warning,
+                tr_args("toast.marker_export_failed", &[("error", &e)]),
+            ),
+        }
+    }
+
+    /// 设置面板: 标记的快捷标签和导出格式.
+    pub(super) fn marker_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.marker_settings;
+        egui::Grid::new("marker_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("settings.markers.export"));
+                egui::ComboBox::from_id_salt("marker_export")
+                    .selected_text(export_label(s.export))
+                    .show_ui(ui, |ui| {
+                        for export in MarkerExport::ALL {
+                            ui.selectable_value(&mut s.export, export, export_label(export));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label(tr("settings.markers.labels"));
+                ui.vertical(|ui| {
+                    let mut removed = None;
+                    for (i, label) in s.labels.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(label).desired_width(120.0));
+                            if ui.small_button(tr("settings.sources.remove")).clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = removed {
+                        s.labels.remove(i);
+                    }
+                    if ui.small_button(tr("settings.markers.add")).clicked() {
+                        s.labels.push(String::new());
+                    }
+                });
+                ui.end_row();
+            });
+        ui.weak(tr("settings.markers.hint"));
+    }
+}
+
+fn export_label(export: MarkerExport) -> &'static str {
+    match export {
+        MarkerExport::None => tr("settings.markers.sidecar_only"),
+        MarkerExport::Csv => "CSV",
+        MarkerExport::Edl => "EDL (Resolve)",
+    }
+}
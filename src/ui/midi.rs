@@ -0,0 +1,154 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use super::theme::MIN_PREVIEW_BRIGHTNESS;
+use super::toast::ToastLevel;
+use crate::i18n::{tr, tr_args};
+use crate::keymap::Action;
+use crate::midi::{MidiEvent, MidiParam, MidiTarget};
+use crate::video::pipeline::MAX_AUDIO_GAIN;
+use crate::video::zoom::{MAX_ZOOM, MIN_ZOOM};
+
+/// midir 的回调不会唤醒界面, 连接后至少这么久重绘一次来读取.
+const POLL_INTERVAL: Duration = Duration::from_millis(30);
+
+impl CameraApp {
+    /// 每帧调用, 处理连接变化和收到的消息. 学习时下一个控件绑定到选中的目标,
+    /// 屏幕锁定时消息都忽略.
+    pub(super) fn poll_midi(&mut self, ctx: &egui::Context) {
+        match self.midi.check(&self.midi_settings) {
+            Some(MidiEvent::Connected(name)) => self.toasts.push(
+                ToastLevel::Info,
+                tr_args("toast.midi_connected", &[("name", &name)]),
+            ),
+            Some(MidiEvent::Disconnected(name)) => self.toasts.push(
+                ToastLevel::Warning,
+                tr_args("toast.midi_disconnected", &[("name", &name)]),
+            ),
+            None => {}
+        }
+        if self.midi.port().is_none() {
+            return;
+        }
+        ctx.request_repaint_after(POLL_INTERVAL);
+
+        let received = self.midi.take();
+        if let Some(target) = self.midi_learn {
+            // 学习时不执行任何绑定
+            if let Some(control) = received.last.filter(|c| target.accepts(*c)) {
+                tracing::info!(?control, ?target, "midi learned");
+                self.midi_settings.bind(control, target);
+                self.midi_learn = None;
+            }
+            return;
+        }
+        if self.locked || (received.values.is_empty() && received.presses.is_empty()) {
+            return;
+        }
+        self.last_activity = Instant::now();
+        for control in received.presses {
+            if let Some(MidiTarget::Action(action)) = self.midi_settings.target(control) {
+                self.run_action(ctx, action);
+            }
+        }
+        for (control, value) in received.values {
+            if let Some(MidiTarget::Param(param)) = self.midi_settings.target(control) {
+                self.set_midi_param(ctx, param, value as f32 / 127.0);
+            }
+        }
+    }
+
+    /// `fraction` 为 0 到 1, 对应参数的整个范围.
+    fn set_midi_param(&mut self, ctx: &egui::Context, param: MidiParam, fraction: f32) {
+        match param {
+            MidiParam::AudioGain => self.set_audio_gain(fraction as f64 * MAX_AUDIO_GAIN),
+            MidiParam::PreviewBrightness => {
+                self.theme_settings.preview_brightness =
+                    MIN_PREVIEW_BRIGHTNESS + (1.0 - MIN_PREVIEW_BRIGHTNESS) * fraction;
+                self.apply_theme(ctx);
+            }
+            MidiParam::Zoom => self.zoom_target = MIN_ZOOM + (MAX_ZOOM - MIN_ZOOM) * fraction,
+            MidiParam::Focus => {
+                if let Some(focus) = &self.focus {
+                    let position =
+                        focus.min + ((focus.max - focus.min) as f32 * fraction).round() as i32;
+                    self.set_focus_position(position);
+                }
+            }
+        }
+    }
+
+    /// 设置面板: 端口和每个参数, 操作绑定的控件. 点 "学习" 后动一下控制台上的推子或按键.
+    pub(super) fn midi_section(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.midi_settings.enabled, tr("settings.midi.enabled"));
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.midi.port"));
+            let selected = if self.midi_settings.port.is_empty() {
+                tr("settings.midi.any_port").to_string()
+            } else {
+                self.midi_settings.port.clone()
+            };
+            egui::ComboBox::from_id_salt("midi_port")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.midi_settings.port,
+                        String::new(),
+                        tr("settings.midi.any_port"),
+                    );
+                    for port in self.midi.ports() {
+                        ui.selectable_value(&mut self.midi_settings.port, port.clone(), port);
+                    }
+                });
+        });
+        match self.midi.port() {
+            Some(name) => ui.label(tr_args("settings.midi.connected", &[("name", &name)])),
+            None if !self.midi.is_available() => ui.weak(tr("settings.midi.unavailable")),
+            None => ui.weak(tr("settings.midi.none")),
+        };
+        ui.weak(tr("settings.midi.hint"));
+
+        let targets = MidiParam::ALL
+            .into_iter()
+            .map(MidiTarget::Param)
+            .chain(Action::ALL.into_iter().map(MidiTarget::Action));
+        let mut learn = None;
+        let mut clear = None;
+        egui::Grid::new("midi_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for target in targets {
+                    ui.label(target.label());
+                    let text = if self.midi_learn == Some(target) {
+                        egui::RichText::new(tr("settings.midi.waiting")).italics()
+                    } else {
+                        let text = match self.midi_settings.control(target) {
+                            Some(control) => control.label(),
+                            None => tr("settings.keymap.none").to_string(),
+                        };
+                        egui::RichText::new(text).monospace()
+                    };
+                    ui.label(text);
+                    ui.horizontal(|ui| {
+                        if ui.small_button(tr("settings.midi.learn")).clicked() {
+                            learn = Some(target);
+                        }
+                        if ui.small_button(tr("settings.keymap.clear")).clicked() {
+                            clear = Some(target);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        if let Some(target) = learn {
+            // 再点一次取消. 丢掉点击之前收到的消息
+            self.midi.take();
+            self.midi_learn = (self.midi_learn != Some(target)).then_some(target);
+        }
+        if let Some(target) = clear {
+            self.midi_settings.unbind(target);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+use crate::keymap::Action;
+use crate::mqtt::MqttCommand;
+
+impl CameraApp {
+    /// 每帧调用, 把收到的指令当作对应的操作执行. 远程控制不受屏幕锁定影响.
+    pub(super) fn poll_mqtt(&mut self, ctx: &egui::Context) {
+        while let Some(command) = self.mqtt.try_recv() {
+            match command {
+                // 与当前状态相同时不切换, 重复发送 start 不会停止录制
+                MqttCommand::Record(start) => {
+                    if start != self.recorder.is_recording() {
+                        self.run_action(ctx, Action::ToggleRecord);
+                    }
+                }
+                MqttCommand::ForceStop if self.recorder.is_recording() => self.request_stop(true),
+                MqttCommand::ForceStop => {}
+                MqttCommand::Snapshot => self.run_action(ctx, Action::Snapshot),
+                MqttCommand::DumpGraph => self.run_action(ctx, Action::DumpGraph),
+            }
+        }
+    }
+
+    /// 设置面板: 服务器和认证. 修改后要重启才生效.
+    pub(super) fn mqtt_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.mqtt_settings;
+
+        ui.checkbox(&mut s.enabled, tr("settings.mqtt.enabled"));
+        egui::Grid::new("mqtt_grid").num_columns(2).show(ui, |ui| {
+            ui.label(tr("settings.mqtt.broker"));
+            ui.add(egui::TextEdit::singleline(&mut s.broker).hint_text("mqtt://localhost:1883"));
+            ui.end_row();
+
+            ui.label(tr("settings.mqtt.client_id"));
+            ui.text_edit_singleline(&mut s.client_id);
+            ui.end_row();
+
+            ui.label(tr("settings.mqtt.username"));
+            ui.text_edit_singleline(&mut s.username);
+            ui.end_row();
+
+            ui.label(tr("settings.mqtt.password"));
+            ui.add(egui::TextEdit::singleline(&mut s.password).password(true));
+            ui.end_row();
+
+            ui.label(tr("settings.mqtt.ca_file"));
+            let mut ca = s
+                .ca_file
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            if ui
+                .add(egui::TextEdit::singleline(&mut ca).hint_text(tr("settings.mqtt.system_ca")))
+                .changed()
+            {
+                s.ca_file = (!ca.is_empty()).then(|| ca.into());
+            }
+            ui.end_row();
+        });
+        ui.weak(tr_args(
+            "settings.mqtt.hint",
+            &[("prefix", &format!("camui/{}", s.client_id))],
+        ));
+        if self.mqtt.is_connected() {
+            ui.label(tr("settings.mqtt.connected"));
+        } else if s.enabled {
+            ui.weak(tr("settings.mqtt.disconnected"));
+        }
+        ui.weak(tr("settings.common.restart"));
+    }
+}
@@ -0,0 +1,176 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::tr;
+
+/// 缩略图的宽高比, 与管线中的缩略图一致.
+const TILE_ASPECT: f32 = 16.0 / 9.0;
+/// 电平表的下限, 与主界面一样低于 -60 dB 当作静音.
+const METER_FLOOR_DB: f32 = -60.0;
+
+impl CameraApp {
+    /// 打开时才让 gst 线程生成缩略图.
+    pub(super) fn toggle_multiview(&mut self) {
+        self.set_multiview(!self.show_multiview);
+    }
+
+    fn set_multiview(&mut self, open: bool) {
+        if open == self.show_multiview {
+            return;
+        }
+        self.show_multiview = open;
+        self.recorder.set_multiview(open);
+        if !open {
+            self.monitors.tiles.lock().clear();
+            self.tile_textures.clear();
+        }
+    }
+
+    /// 顶部栏中打开多画面的按钮, 只有多路采集源时显示.
+    pub(super) fn multiview_button(&mut self, ui: &mut egui::Ui) {
+        if self.source_names.is_empty() {
+            return;
+        }
+        if ui
+            .selectable_label(self.show_multiview, "▦")
+            .on_hover_text(tr("top.multiview_hint"))
+            .clicked()
+        {
+            self.toggle_multiview();
+        }
+    }
+
+    /// 所有采集源的缩略图, 当前节目的源用红框标出, 点击切换.
+    pub(super) fn multiview_window(&mut self, ctx: &egui::Context) {
+        if !self.show_multiview {
+            return;
+        }
+        {
+            let mut tiles = self.monitors.tiles.lock();
+            self.tile_textures.resize(self.source_names.len(), None);
+            for (index, tile) in tiles.iter_mut().enumerate() {
+                if let (Some(image), Some(texture)) =
+                    (tile.take(), self.tile_textures.get_mut(index))
+                {
+                    *texture =
+                        Some(ctx.load_texture(format!("tile{}", index), image, Default::default()));
+                }
+            }
+        }
+        let level = *self.audio_level.lock();
+        let program_audio = if self.audio_follows_video {
+            self.active_source
+        } else {
+            0
+        };
+        let columns = if self.source_names.len() <= 4 { 2 } else { 3 };
+        let mut open = true;
+        let mut chosen = None;
+        egui::Window::new(tr("multiview.title"))
+            .open(&mut open)
+            .default_width(660.0)
+            .show(ctx, |ui| {
+                let spacing = ui.spacing().item_spacing.x;
+                let width = ((ui.available_width() - spacing * (columns - 1) as f32)
+                    / columns as f32)
+                    .max(80.0);
+                let size = egui::vec2(width, width / TILE_ASPECT);
+                egui::Grid::new("multiview_grid")
+                    .num_columns(columns)
+                    .show(ui, |ui| {
+                        for (index, name) in self.source_names.iter().enumerate() {
+                            let (rect, response) =
+                                ui.allocate_exact_size(size, egui::Sense::click());
+                            let meter = (index == program_audio).then_some(level);
+                            draw_tile(
+                                ui,
+                                rect,
+                                self.tile_textures.get(index).and_then(Option::as_ref),
+                                &format!("{} {}", index + 1, name),
+                                index == self.active_source,
+                                meter,
+                            );
+                            if response.on_hover_text(tr("top.cut_hint")).clicked() {
+                                chosen = Some(index);
+                            }
+                            if (index + 1) % columns == 0 {
+                                ui.end_row();
+                            }
+                        }
+                    });
+                ui.ctx().request_repaint();
+            });
+        if !open {
+            self.set_multiview(false);
+        }
+        if let Some(index) = chosen {
+            self.cut_to(index);
+        }
+    }
+}
+
+fn draw_tile(
+    ui: &egui::Ui,
+    rect: egui::Rect,
+    texture: Option<&egui::TextureHandle>,
+    label: &str,
+    live: bool,
+    meter_db: Option<f32>,
+) {
+    let painter = ui.painter();
+    match texture {
+        Some(texture) => {
+            painter.image(
+                texture.id(),
+                rect,
+                egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                egui::Color32::WHITE,
+            );
+        }
+        None => {
+            painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                tr("multiview.no_picture"),
+                egui::FontId::proportional(14.0),
+                egui::Color32::GRAY,
+            );
+        }
+    }
+    let label_rect = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), 20.0));
+    painter.rect_filled(label_rect, 0.0, egui::Color32::from_black_alpha(160));
+    painter.text(
+        label_rect.left_center() + egui::vec2(6.0, 0.0),
+        egui::Align2::LEFT_CENTER,
+        label,
+        egui::FontId::proportional(13.0),
+        egui::Color32::WHITE,
+    );
+    if let Some(db) = meter_db {
+        let fraction = ((db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0);
+        let track = egui::Rect::from_min_max(
+            egui::pos2(rect.min.x + 6.0, rect.max.y - 10.0),
+            egui::pos2(rect.max.x - 6.0, rect.max.y - 4.0),
+        );
+        painter.rect_filled(track, 2.0, egui::Color32::from_black_alpha(160));
+        let color = if db > -3.0 {
+            egui::Color32::RED
+        } else if db > -12.0 {
+            egui::Color32::YELLOW
+        } else {
+            egui::Color32::GREEN
+        };
+        let mut fill = track;
+        fill.set_width(track.width() * fraction);
+        painter.rect_filled(fill, 2.0, color);
+    }
+    if live {
+        painter.rect_stroke(
+            rect,
+            0.0,
+            egui::Stroke::new(3.0, egui::Color32::RED),
+            egui::StrokeKind::Inside,
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use eframe::egui;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+use super::CameraApp;
+use crate::i18n::tr;
+use crate::video::rtsp::{self, RtspConfig, StreamInfo};
+
+/// 在后台线程打开流, 结果在设置面板中显示.
+pub(super) struct ConnectionTest {
+    /// 测试时的设置, 改动后结果不再对应当前设置.
+    config: RtspConfig,
+    /// 完成前为 None.
+    result: Arc<Mutex<Option<Result<StreamInfo, String>>>>,
+    /// 结果中的音频是否已经填进设置.
+    applied: bool,
+}
+
+impl ConnectionTest {
+    fn start(config: RtspConfig) -> Self {
+        let result = Arc::new(Mutex::new(None));
+        let result_c = result.clone();
+        let config_c = config.clone();
+        std::thread::spawn(move || {
+            let found = rtsp::discover(&config_c);
+            match &found {
+                Ok(info) => tracing::info!(?info, "network camera test succeeded"),
+                Err(e) => tracing::warn!("network camera test failed: {}", e),
+            }
+            *result_c.lock() = Some(found);
+        });
+        Self {
+            config,
+            result,
+            applied: false,
+        }
+    }
+}
+
+impl CameraApp {
+    /// 地址, 认证和抖动缓冲, 以及保存前检查能否连接的按钮.
+    pub(super) fn network_camera_section(&mut self, ui: &mut egui::Ui) {
+        let Some(r) = &mut self.capture.rtsp else {
+            return;
+        };
+        egui::Grid::new("rtsp_grid").num_columns(2).show(ui, |ui| {
+            ui.label(tr("settings.rtsp.uri"));
+            ui.add(
+                egui::TextEdit::singleline(&mut r.uri)
+                    .hint_text("rtsp://192.168.1.20:554/stream1")
+                    .desired_width(260.0),
+            );
+            ui.end_row();
+
+            ui.label(tr("settings.common.user"));
+            ui.text_edit_singleline(&mut r.username)
+                .on_hover_text(tr("settings.rtsp.user_hint"));
+            ui.end_row();
+
+            ui.label(tr("settings.common.password"));
+            ui.add(egui::TextEdit::singleline(&mut r.password).password(true));
+            ui.end_row();
+
+            ui.label(tr("settings.rtsp.latency"));
+            ui.add(
+                egui::DragValue::new(&mut r.latency_ms)
+                    .range(0..=5000)
+                    .suffix(" ms"),
+            )
+            .on_hover_text(tr("settings.rtsp.latency_hint"));
+            ui.end_row();
+
+            ui.label(tr("settings.common.audio"));
+            ui.checkbox(&mut r.audio, tr("settings.rtsp.audio"));
+            ui.end_row();
+        });
+
+        let testing = self
+            .rtsp_test
+            .as_ref()
+            .is_some_and(|t| t.result.lock().is_none());
+        let mut found_audio = None;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !testing && r.uri.starts_with("rtsp"),
+                    egui::Button::new(tr("settings.rtsp.test")),
+                )
+                .clicked()
+            {
+                self.rtsp_test = Some(ConnectionTest::start(r.clone()));
+            }
+            let Some(test) = &self.rtsp_test else {
+                return;
+            };
+            if test.config.uri != r.uri
+                || test.config.username != r.username
+                || test.config.password != r.password
+            {
+                return;
+            }
+            match &*test.result.lock() {
+                None => {
+                    ui.spinner();
+                    ui.label(tr("settings.rtsp.connecting"));
+                    ui.ctx().request_repaint();
+                }
+                Some(Ok(info)) => {
+                    let audio = info
+                        .audio
+                        .as_deref()
+                        .unwrap_or(tr("settings.rtsp.no_audio"));
+                    ui.colored_label(
+                        egui::Color32::GREEN,
+                        format!("{}×{} {}, {}", info.width, info.height, info.codec, audio),
+                    );
+                    found_audio = Some(info.audio.is_some());
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::RED, tr("settings.rtsp.failed"))
+                        .on_hover_text(e);
+                }
+            };
+        });
+        // 测试结果只在第一次显示时填写音频, 之后用户仍可以自己改
+        if let Some(audio) = found_audio
+            && let Some(test) = &mut self.rtsp_test
+            && !test.applied
+        {
+            test.applied = true;
+            r.audio = audio;
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+use crate::keymap::Action;
+use crate::osc::OscCommand;
+use crate::video::zoom::{MAX_ZOOM, MIN_ZOOM};
+
+impl CameraApp {
+    /// 每帧调用, 执行收到的指令. 与 MQTT 一样不受屏幕锁定影响.
+    pub(super) fn poll_osc(&mut self, ctx: &egui::Context) {
+        // 拖动端口或输入地址时不反复重新绑定, 松开后再生效
+        if !ctx.is_using_pointer() && !ctx.wants_keyboard_input() {
+            self.osc.set_settings(&self.osc_settings);
+        }
+        while let Some(command) = self.osc.try_recv() {
+            match command {
+                OscCommand::RecordStart if !self.recorder.is_recording() => {
+                    self.run_action(ctx, Action::ToggleRecord)
+                }
+                OscCommand::RecordStop { force } if self.recorder.is_recording() => {
+                    self.request_stop(force)
+                }
+                OscCommand::RecordStart | OscCommand::RecordStop { .. } => {}
+                OscCommand::Snapshot => self.run_action(ctx, Action::Snapshot),
+                OscCommand::Zoom(zoom) => self.zoom_target = zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+                OscCommand::Gain(gain) => self.set_audio_gain(gain),
+                OscCommand::DumpGraph => self.run_action(ctx, Action::DumpGraph),
+            }
+        }
+    }
+
+    /// 设置面板: 端口和反馈地址, 修改后立即生效.
+    pub(super) fn osc_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.osc_settings;
+
+        ui.checkbox(&mut s.enabled, tr("settings.osc.enabled"));
+        egui::Grid::new("osc_grid").num_columns(2).show(ui, |ui| {
+            ui.label(tr("settings.osc.port"));
+            ui.add(egui::DragValue::new(&mut s.port).range(1024..=65535));
+            ui.end_row();
+
+            ui.label(tr("settings.osc.feedback"));
+            ui.add(egui::TextEdit::singleline(&mut s.feedback).hint_text("192.168.1.20:53001"))
+                .on_hover_text(tr("settings.osc.feedback_hint"));
+            ui.end_row();
+        });
+        if let Some(e) = self.osc.error() {
+            ui.colored_label(
+                egui::Color32::RED,
+                tr_args("settings.osc.error", &[("error", &e)]),
+            );
+        }
+        ui.weak(tr("settings.osc.hint"));
+    }
+}
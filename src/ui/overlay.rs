@@ -0,0 +1,35 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::tr;
+use crate::video::pip::Corner;
+
+impl CameraApp {
+    /// 设置面板: 画进视频的叠加层, 不用重启管线.
+    pub(super) fn overlay_section(&mut self, ui: &mut egui::Ui) {
+        let before = self.overlay_settings.clone();
+        let o = &mut self.overlay_settings;
+        ui.checkbox(&mut o.thirds, tr("settings.display.overlay_thirds"))
+            .on_hover_text(tr("settings.display.overlay_hint"));
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut o.meter.enabled, tr("settings.display.overlay_meter"))
+                .on_hover_text(tr("settings.display.overlay_hint"));
+            ui.add_enabled_ui(o.meter.enabled, |ui| {
+                egui::ComboBox::from_id_salt("overlay_meter_corner")
+                    .selected_text(o.meter.corner.label())
+                    .show_ui(ui, |ui| {
+                        for corner in Corner::ALL {
+                            ui.selectable_value(&mut o.meter.corner, corner, corner.label());
+                        }
+                    });
+            });
+        });
+        ui.add_enabled_ui(o.meter.enabled, |ui| {
+            ui.checkbox(&mut o.meter.burn_in, tr("settings.display.overlay_burn_in"))
+                .on_hover_text(tr("settings.display.overlay_burn_in_hint"));
+        });
+        if *o != before {
+            self.overlay.set(o.clone());
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::i18n::tr;
+use crate::video::pip::{self, Corner, PipConfig};
+
+impl CameraApp {
+    /// 布局可以在运行中修改, 只在界面上叠加时由界面自己绘制.
+    fn send_pip_layout(&mut self) {
+        if let Some(p) = &self.capture.pip {
+            self.recorder.set_pip_layout(p.layout);
+        }
+    }
+
+    /// 交换主画面和小画面, 默认是 P 键.
+    pub(super) fn swap_pip(&mut self) {
+        if self.pip_burned_in.is_none() {
+            return;
+        }
+        if let Some(p) = &mut self.capture.pip {
+            p.layout.swapped = !p.layout.swapped;
+            tracing::info!(swapped = p.layout.swapped, "picture-in-picture swapped");
+        }
+        self.send_pip_layout();
+    }
+
+    /// 只用于监看时在预览上画出小画面. 交换后小画面铺满, 主画面缩到角落.
+    pub(super) fn pip_overlay(&mut self, ui: &egui::Ui, rect: egui::Rect) {
+        if self.pip_burned_in != Some(false) {
+            return;
+        }
+        let Some(config) = &self.capture.pip else {
+            return;
+        };
+        if let Some(image) = self.monitors.inset.lock().take() {
+            self.pip_texture = Some(
+                ui.ctx()
+                    .load_texture("pip_frame", image, Default::default()),
+            );
+        }
+        draw_pip(
+            ui.painter(),
+            rect,
+            config,
+            self.texture.as_ref(),
+            self.pip_texture.as_ref(),
+        );
+    }
+
+    /// 录像中合成了小画面时, 顶部栏中的红色标记.
+    pub(super) fn pip_badge(&self, ui: &mut egui::Ui) {
+        if self.pip_burned_in != Some(true) || !self.recorder.is_recording() {
+            return;
+        }
+        ui.label(
+            egui::RichText::new(tr("top.pip"))
+                .color(egui::Color32::WHITE)
+                .background_color(egui::Color32::from_rgb(180, 30, 30))
+                .strong(),
+        )
+        .on_hover_text(tr("top.pip_hint"));
+    }
+
+    /// 开始录制时提示小画面也会录进去.
+    pub(super) fn pip_recording_notice(&mut self) {
+        if self.pip_burned_in == Some(true) {
+            self.toasts
+                .push(ToastLevel::Warning, tr("toast.pip_burned_in"));
+        }
+    }
+
+    /// 设置面板: 小画面的源, 位置, 大小和边框.
+    pub(super) fn pip_section(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.capture.pip.is_some();
+        if ui
+            .checkbox(&mut enabled, tr("settings.pip.enabled"))
+            .changed()
+        {
+            self.capture.pip = enabled.then(PipConfig::default);
+        }
+        let Some(p) = &mut self.capture.pip else {
+            return;
+        };
+        let before = p.layout;
+        egui::Grid::new("pip_grid").num_columns(2).show(ui, |ui| {
+            ui.label(tr("settings.pip.source"));
+            ui.add(
+                egui::TextEdit::singleline(&mut p.source).hint_text("v4l2src device=/dev/video2"),
+            );
+            ui.end_row();
+
+            ui.label(tr("settings.pip.corner"));
+            egui::ComboBox::from_id_salt("pip_corner")
+                .selected_text(p.layout.corner.label())
+                .show_ui(ui, |ui| {
+                    for corner in Corner::ALL {
+                        ui.selectable_value(&mut p.layout.corner, corner, corner.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label(tr("settings.pip.size"));
+            ui.add(
+                egui::Slider::new(
+                    &mut p.layout.size_percent,
+                    pip::MIN_SIZE_PERCENT..=pip::MAX_SIZE_PERCENT,
+                )
+                .suffix(" %"),
+            );
+            ui.end_row();
+
+            ui.label(tr("settings.pip.border"));
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgb(&mut p.border_color);
+                ui.add(
+                    egui::DragValue::new(&mut p.border_px)
+                        .range(0..=32)
+                        .suffix(" px"),
+                );
+            });
+            ui.end_row();
+        });
+        ui.checkbox(&mut p.layout.swapped, tr("settings.pip.swap"));
+        ui.checkbox(&mut p.burn_in, tr("settings.pip.burn_in"))
+            .on_hover_text(tr("settings.pip.burn_in_hint"));
+        if p.burn_in {
+            ui.horizontal(|ui| {
+                ui.label(tr("settings.common.format"));
+                ui.add(egui::DragValue::new(&mut p.width).range(16..=7680));
+                ui.label("×");
+                ui.add(egui::DragValue::new(&mut p.height).range(16..=4320));
+            });
+        }
+        if p.layout != before {
+            self.send_pip_layout();
+        }
+    }
+}
+
+/// 在主画面上画出小画面, 节目窗口也使用.
+pub(super) fn draw_pip(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    config: &PipConfig,
+    main: Option<&egui::TextureHandle>,
+    inset: Option<&egui::TextureHandle>,
+) {
+    let area = pip::inset_rect(rect.width() as u32, rect.height() as u32, &config.layout);
+    let small = egui::Rect::from_min_size(
+        rect.min + egui::vec2(area.x as f32, area.y as f32),
+        egui::vec2(area.width as f32, area.height as f32),
+    );
+    let (full, corner) = if config.layout.swapped {
+        (inset, main)
+    } else {
+        (None, inset)
+    };
+    let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    if let Some(texture) = full {
+        painter.image(texture.id(), rect, uv, egui::Color32::WHITE);
+    }
+    match corner {
+        Some(texture) => {
+            painter.image(texture.id(), small, uv, egui::Color32::WHITE);
+        }
+        None => {
+            painter.rect_filled(small, 0.0, egui::Color32::from_gray(20));
+        }
+    }
+    if config.border_px > 0 {
+        let [r, g, b] = config.border_color;
+        painter.rect_stroke(
+            small,
+            0.0,
+            egui::Stroke::new(config.border_px as f32, egui::Color32::from_rgb(r, g, b)),
+            egui::StrokeKind::Outside,
+        );
+    }
+}
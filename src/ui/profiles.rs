@@ -0,0 +1,163 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::config::{Profile, RecordingConfig};
+use crate::i18n::{tr, tr_args};
+use crate::keymap::{self, Action, Keymap};
+
+impl CameraApp {
+    fn active_profile(&self) -> Option<&Profile> {
+        let name = self.active_profile.as_ref()?;
+        self.profiles.iter().find(|p| &p.name == name)
+    }
+
+    /// 参数与预设完全一致时返回预设名称, 写入 sidecar.
+    pub(super) fn matching_profile(&self) -> Option<String> {
+        self.active_profile()
+            .filter(|p| p.recording == self.recording)
+            .map(|p| p.name.clone())
+    }
+
+    /// 底部栏显示的预设名称, 当前参数被修改过时加 "*".
+    pub(super) fn profile_label(&self) -> String {
+        match self.active_profile() {
+            None => "—".to_string(),
+            Some(p) if p.recording == self.recording => p.name.clone(),
+            Some(p) => format!("{}*", p.name),
+        }
+    }
+
+    /// 应用预设. 正在录制的文件已经固定了参数, 不受影响.
+    pub(super) fn apply_profile(&mut self, index: usize) {
+        let Some(profile) = self.profiles.get(index) else {
+            return;
+        };
+        let name = profile.name.clone();
+        self.recording = profile.recording.clone();
+        if let Some(assist) = &profile.assist {
+            self.assist = assist.clone();
+        }
+        self.browser.dir = self.recording.output_dir.clone();
+        let text = if self.recorder.is_recording() {
+            tr_args("toast.profile_next", &[("name", &name)])
+        } else {
+            tr_args("toast.profile", &[("name", &name)])
+        };
+        self.toasts.push(ToastLevel::Info, text);
+        self.active_profile = Some(name);
+    }
+
+    /// 顶部栏中的预设下拉框.
+    pub(super) fn profile_combo(&mut self, ui: &mut egui::Ui) {
+        let mut chosen = None;
+        egui::ComboBox::from_id_salt("profile_combo")
+            .selected_text(self.profile_label())
+            .show_ui(ui, |ui| {
+                for (index, p) in self.profiles.iter().enumerate() {
+                    let selected = self.active_profile.as_ref() == Some(&p.name);
+                    if ui
+                        .selectable_label(selected, key_hint(&self.keymap, index, &p.name))
+                        .clicked()
+                    {
+                        chosen = Some(index);
+                    }
+                }
+                if self.profiles.is_empty() {
+                    ui.weak(tr("top.no_profiles"));
+                }
+            });
+        if let Some(index) = chosen {
+            self.apply_profile(index);
+        }
+    }
+
+    /// 设置面板: 用当前参数新建, 更新或删除预设.
+    pub(super) fn profiles_section(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_profile_name)
+                    .hint_text(tr("settings.profiles.name_hint")),
+            );
+            let name = self.new_profile_name.trim().to_string();
+            if ui
+                .add_enabled(
+                    !name.is_empty(),
+                    egui::Button::new(tr("settings.profiles.save")),
+                )
+                .clicked()
+            {
+                self.save_profile(name);
+                self.new_profile_name.clear();
+            }
+        });
+
+        let mut update = None;
+        let mut remove = None;
+        egui::Grid::new("profiles_grid")
+            .num_columns(3)
+            .show(ui, |ui| {
+                for (index, p) in self.profiles.iter().enumerate() {
+                    ui.label(key_hint(&self.keymap, index, &p.name));
+                    ui.weak(summary(&p.recording));
+                    ui.horizontal(|ui| {
+                        if ui
+                            .small_button(tr("settings.profiles.update"))
+                            .on_hover_text(tr("settings.profiles.update_hint"))
+                            .clicked()
+                        {
+                            update = Some(index);
+                        }
+                        if ui.small_button("🗑").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+        if let Some(index) = update {
+            let profile = &mut self.profiles[index];
+            profile.recording = self.recording.clone();
+            profile.assist = Some(self.assist.clone());
+            self.active_profile = Some(profile.name.clone());
+        }
+        if let Some(index) = remove {
+            let removed = self.profiles.remove(index);
+            if self.active_profile.as_ref() == Some(&removed.name) {
+                self.active_profile = None;
+            }
+        }
+        ui.weak(tr("settings.profiles.keys"));
+    }
+
+    /// 以当前录制参数和辅助显示保存预设, 同名时覆盖.
+    fn save_profile(&mut self, name: String) {
+        let profile = Profile {
+            name: name.clone(),
+            recording: self.recording.clone(),
+            assist: Some(self.assist.clone()),
+        };
+        match self.profiles.iter_mut().find(|p| p.name == name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        self.active_profile = Some(name);
+    }
+}
+
+/// 例如 "4K master  [1]".
+fn key_hint(keymap: &Keymap, index: usize, name: &str) -> String {
+    match Action::profile(index).and_then(|a| keymap.get(a)) {
+        Some(shortcut) => format!("{}  [{}]", name, keymap::format_shortcut(&shortcut)),
+        None => name.to_string(),
+    }
+}
+
+/// 例如 "1920×1080 H264/MOV 8000 kbps".
+fn summary(r: &RecordingConfig) -> String {
+    format!(
+        "{}×{} {}/{:?} {} kbps",
+        r.resolution.width, r.resolution.height, r.encoder, r.container, r.bitrate_kbps
+    )
+}
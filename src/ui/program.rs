@@ -0,0 +1,202 @@
+use eframe::egui;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use super::pip::draw_pip;
+use super::toast::ToastLevel;
+use crate::i18n::tr;
+use crate::video::PreviewFrame;
+use crate::video::exchange::FrameExchange;
+use crate::video::pip::PipConfig;
+
+/// 主窗口超过这么久没有更新纹理 (例如被最小化), 节目窗口自己从缓冲区取帧.
+const STALE_AFTER: Duration = Duration::from_millis(250);
+
+/// 节目窗口显示的内容, 由主窗口每帧填写.
+#[derive(Default)]
+struct ProgramFeed {
+    /// 与主窗口相同的纹理, 不再重复上传.
+    texture: Option<egui::TextureHandle>,
+    /// 需要叠加的画中画和它的纹理.
+    pip: Option<(PipConfig, Option<egui::TextureHandle>)>,
+    updated_at: Option<Instant>,
+    fullscreen: bool,
+    /// 第一次全屏时所在显示器的尺寸, 之后改变说明显示器已经断开.
+    monitor: Option<egui::Vec2>,
+    /// 由节目窗口设置, 主窗口看到后关闭它.
+    closed: Option<CloseReason>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CloseReason {
+    User,
+    MonitorLost,
+}
+
+/// 第二个无边框窗口, 只有画面. 使用独立的 deferred viewport,
+/// 主窗口被遮挡或最小化时仍然继续绘制.
+pub(super) struct ProgramWindow {
+    open: bool,
+    feed: Arc<Mutex<ProgramFeed>>,
+    frame_buffer: FrameExchange<PreviewFrame>,
+}
+
+impl ProgramWindow {
+    pub fn new(frame_buffer: FrameExchange<PreviewFrame>) -> Self {
+        Self {
+            open: false,
+            feed: Arc::default(),
+            frame_buffer,
+        }
+    }
+}
+
+impl CameraApp {
+    pub(super) fn toggle_program_window(&mut self) {
+        self.program.open = !self.program.open;
+        let mut feed = self.program.feed.lock();
+        feed.fullscreen = self.program_settings.fullscreen;
+        feed.monitor = None;
+        feed.closed = None;
+        tracing::info!(open = self.program.open, "program output");
+    }
+
+    /// 每帧调用, 把最新的纹理交给节目窗口并保持它打开.
+    pub(super) fn program_window(&mut self, ctx: &egui::Context) {
+        if !self.program.open {
+            return;
+        }
+        let closed = {
+            let mut feed = self.program.feed.lock();
+            feed.texture = self.texture.clone();
+            if self.last_frame_at.is_some() {
+                feed.updated_at = Some(Instant::now());
+            }
+            feed.pip = match (&self.capture.pip, self.pip_burned_in) {
+                (Some(config), Some(false)) if self.program_settings.overlays => {
+                    Some((config.clone(), self.pip_texture.clone()))
+                }
+                _ => None,
+            };
+            feed.closed.take()
+        };
+        match closed {
+            Some(CloseReason::User) => {
+                self.program.open = false;
+                return;
+            }
+            Some(CloseReason::MonitorLost) => {
+                self.program.open = false;
+                tracing::warn!("program output monitor disappeared");
+                self.toasts
+                    .push(ToastLevel::Warning, tr("toast.program_monitor_lost"));
+                return;
+            }
+            None => {}
+        }
+        let feed = self.program.feed.clone();
+        let frame_buffer = self.program.frame_buffer.clone();
+        ctx.show_viewport_deferred(
+            egui::ViewportId::from_hash_of("program_output"),
+            egui::ViewportBuilder::default()
+                .with_title("Program")
+                .with_decorations(false)
+                .with_inner_size([960.0, 540.0])
+                .with_fullscreen(self.program_settings.fullscreen),
+            move |ctx, _class| show_program(ctx, &feed, &frame_buffer),
+        );
+    }
+
+    /// 设置面板: 打开节目窗口和它的显示方式.
+    pub(super) fn program_section(&mut self, ui: &mut egui::Ui) {
+        let label = if self.program.open {
+            tr("settings.program.close")
+        } else {
+            tr("settings.program.open")
+        };
+        if ui
+            .button(label)
+            .on_hover_text(tr("settings.program.toggle_hint"))
+            .clicked()
+        {
+            self.toggle_program_window();
+        }
+        let s = &mut self.program_settings;
+        ui.checkbox(&mut s.fullscreen, tr("settings.program.fullscreen"))
+            .on_hover_text(tr("settings.program.fullscreen_hint"));
+        ui.checkbox(&mut s.overlays, tr("settings.program.overlays"))
+            .on_hover_text(tr("settings.program.overlays_hint"));
+    }
+}
+
+fn show_program(
+    ctx: &egui::Context,
+    feed: &Mutex<ProgramFeed>,
+    frame_buffer: &FrameExchange<PreviewFrame>,
+) {
+    let mut feed = feed.lock();
+    if ctx.input(|i| i.viewport().close_requested() || i.key_pressed(egui::Key::Escape)) {
+        feed.closed = Some(CloseReason::User);
+        return;
+    }
+    // 全屏所在的显示器断开后, 窗口会被系统移到别的显示器上
+    let monitor = ctx.input(|i| i.viewport().monitor_size);
+    if feed.fullscreen {
+        match (feed.monitor, monitor) {
+            (None, Some(size)) => feed.monitor = Some(size),
+            (Some(known), current) if current != Some(known) => {
+                feed.closed = Some(CloseReason::MonitorLost);
+                return;
+            }
+            _ => {}
+        }
+    }
+    if feed.updated_at.is_none_or(|at| at.elapsed() >= STALE_AFTER)
+        && let Some(frame) = frame_buffer.take()
+    {
+        match &mut feed.texture {
+            Some(texture) => texture.set(frame.image, Default::default()),
+            None => {
+                feed.texture = Some(ctx.load_texture("cam_frame", frame.image, Default::default()))
+            }
+        }
+    }
+
+    let toggle_fullscreen = ctx.input(|i| {
+        i.key_pressed(egui::Key::F11)
+            || i.pointer
+                .button_double_clicked(egui::PointerButton::Primary)
+    });
+    if toggle_fullscreen {
+        feed.fullscreen = !feed.fullscreen;
+        feed.monitor = None;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(feed.fullscreen));
+    }
+    egui::CentralPanel::default()
+        .frame(egui::Frame::new().fill(egui::Color32::BLACK))
+        .show(ctx, |ui| {
+            let rect = ui.max_rect();
+            let response = ui.interact(rect, ui.id().with("program"), egui::Sense::drag());
+            // 没有标题栏, 拖动画面移动窗口
+            if response.drag_started() && !feed.fullscreen {
+                ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
+            }
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            if let Some(texture) = &feed.texture {
+                ui.painter()
+                    .image(texture.id(), rect, uv, egui::Color32::WHITE);
+            }
+            if let Some((config, inset)) = &feed.pip {
+                draw_pip(
+                    ui.painter(),
+                    rect,
+                    config,
+                    feed.texture.as_ref(),
+                    inset.as_ref(),
+                );
+            }
+        });
+    ctx.request_repaint();
+}
@@ -0,0 +1,163 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::tr_args;
+
+/// 只放大预览, 用于检查对焦, 不影响录制. 与数字变焦不同.
+const MAX_PUNCH_IN: f32 = 8.0;
+/// Z 键一次放大到的倍数.
+const KEY_PUNCH_IN: f32 = 3.0;
+
+/// 预览显示的画面范围, 以纹理坐标表示. 按键和手势都修改它.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct PreviewView {
+    scale: f32,
+    /// 显示范围的中心, 0..1.
+    center: egui::Pos2,
+}
+
+impl Default for PreviewView {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            center: egui::pos2(0.5, 0.5),
+        }
+    }
+}
+
+impl PreviewView {
+    pub fn is_fit(&self) -> bool {
+        self.scale <= 1.0
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// 绘制预览纹理时使用的 UV 范围.
+    pub fn uv(&self) -> egui::Rect {
+        egui::Rect::from_center_size(self.center, egui::Vec2::splat(1.0 / self.scale))
+    }
+
+    /// 以 `anchor` (在显示区域中的比例位置, 0..1) 为中心缩放, 手指下的画面保持不动.
+    pub fn zoom_about(&mut self, factor: f32, anchor: egui::Vec2) {
+        let uv = self.uv();
+        let point = uv.min + anchor * uv.size();
+        self.scale = (self.scale * factor).clamp(1.0, MAX_PUNCH_IN);
+        let size = 1.0 / self.scale;
+        self.center = point - anchor * size + egui::Vec2::splat(size / 2.0);
+        self.clamp();
+    }
+
+    /// `delta` 是手指移动的距离占显示区域的比例, 画面跟着手指走.
+    pub fn pan(&mut self, delta: egui::Vec2) {
+        self.center -= delta / self.scale;
+        self.clamp();
+    }
+
+    /// 显示范围不能离开画面.
+    fn clamp(&mut self) {
+        let half = 0.5 / self.scale;
+        self.center.x = self.center.x.clamp(half, 1.0 - half);
+        self.center.y = self.center.y.clamp(half, 1.0 - half);
+    }
+}
+
+impl CameraApp {
+    /// Z 键在适合窗口和放大之间切换.
+    pub(super) fn toggle_punch_in(&mut self) {
+        self.view = if self.view.is_fit() {
+            let mut view = PreviewView::default();
+            view.zoom_about(KEY_PUNCH_IN, egui::vec2(0.5, 0.5));
+            view
+        } else {
+            PreviewView::default()
+        };
+    }
+
+    /// 在预览上捏合缩放, 放大后单指拖动平移, 双击恢复. 必须在其他控件之前调用,
+    /// 这样从按钮上开始的手势由按钮处理.
+    pub(super) fn preview_gestures(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        let response = ui.interact(rect, ui.id().with("preview"), egui::Sense::click_and_drag());
+        self.preview_menu(&response);
+        if response.double_clicked() {
+            self.view = PreviewView::default();
+            return;
+        }
+        let size = rect.size();
+        match ui.ctx().multi_touch() {
+            Some(touch) => {
+                // 只在手势开始时判断一次起点
+                if self.gesture_started_at != Some(touch.start_time) {
+                    self.gesture_started_at = Some(touch.start_time);
+                    self.gesture_on_preview = response.hovered()
+                        && ui.ctx().layer_id_at(touch.start_pos) == Some(ui.layer_id());
+                }
+                if !self.gesture_on_preview {
+                    return;
+                }
+                let anchor = ((touch.center_pos - rect.min) / size)
+                    .clamp(egui::Vec2::ZERO, egui::Vec2::splat(1.0));
+                self.view.zoom_about(touch.zoom_delta, anchor);
+                self.view.pan(touch.translation_delta / size);
+            }
+            None => {
+                self.gesture_started_at = None;
+                if response.dragged() && !self.view.is_fit() {
+                    self.view.pan(response.drag_delta() / size);
+                }
+            }
+        }
+    }
+
+    /// 放大时在左下角显示倍数, 避免误以为录制也放大了.
+    pub(super) fn punch_in_badge(&self, ui: &egui::Ui, rect: egui::Rect) {
+        if self.view.is_fit() {
+            return;
+        }
+        ui.painter().text(
+            rect.left_bottom() + egui::vec2(20.0, -(super::BOTTOM_BAR_HEIGHT + 20.0)),
+            egui::Align2::LEFT_BOTTOM,
+            tr_args(
+                "banner.punch_in",
+                &[("scale", &format!("{:.1}", self.view.scale()))],
+            ),
+            egui::FontId::proportional(16.0),
+            self.theme.warning,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zooming_keeps_the_point_under_the_fingers() {
+        let mut view = PreviewView::default();
+        view.zoom_about(2.0, egui::vec2(0.25, 0.25));
+        // 放大前 (0.25, 0.25) 处的画面仍然在 (0.25, 0.25)
+        let uv = view.uv();
+        let point = uv.min + egui::vec2(0.25, 0.25) * uv.size();
+        assert!((point.x - 0.25).abs() < 1e-5 && (point.y - 0.25).abs() < 1e-5);
+        assert_eq!(view.scale(), 2.0);
+    }
+
+    #[test]
+    fn never_leaves_the_frame() {
+        let mut view = PreviewView::default();
+        view.zoom_about(4.0, egui::vec2(0.5, 0.5));
+        view.pan(egui::vec2(10.0, -10.0));
+        let uv = view.uv();
+        assert!(uv.min.x >= 0.0 && uv.max.y <= 1.0);
+        assert_eq!(uv.min.x, 0.0);
+        assert_eq!(uv.max.y, 1.0);
+
+        view.zoom_about(0.01, egui::vec2(0.5, 0.5));
+        assert!(view.is_fit());
+        assert_eq!(
+            view.uv(),
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0))
+        );
+    }
+}
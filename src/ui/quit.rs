@@ -0,0 +1,146 @@
+use eframe::egui;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use crate::i18n::tr;
+
+/// 确认退出后等待录像封装完成的最长时间.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 关闭窗口的流程.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum QuitState {
+    Idle,
+    /// 正在录制或 kiosk 模式下请求关闭, 等待用户确认.
+    Confirming,
+    /// 已发出 Stop, 等待 Stopped 事件.
+    Finalizing(Instant),
+    /// 可以关闭, 不再拦截.
+    Closing,
+}
+
+impl CameraApp {
+    /// 收到 SIGINT/SIGTERM 时不经确认直接走退出流程.
+    pub fn watch_signals(&mut self, ctx: egui::Context) {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.signalled = flag.clone();
+        crate::signals::spawn_watcher(move |signal| {
+            tracing::info!("received {}, shutting down", signal);
+            flag.store(true, Ordering::Relaxed);
+            ctx.request_repaint();
+        });
+    }
+
+    /// 拦截窗口关闭: 正在录制时先确认, 停止录制并等待封装完成后再关闭.
+    pub(super) fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if self.signalled.swap(false, Ordering::Relaxed) {
+            match self.quit {
+                QuitState::Finalizing(_) | QuitState::Closing => {}
+                _ if self.recorder.is_recording() => self.finalize_and_quit(),
+                _ => self.close(ctx),
+            }
+        }
+
+        if ctx.input(|i| i.viewport().close_requested()) {
+            match self.quit {
+                QuitState::Closing => {}
+                // kiosk 模式下只能长按 Esc 退出
+                QuitState::Idle if self.kiosk => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                    self.kiosk_close_refused();
+                }
+                QuitState::Idle if !self.recorder.is_recording() => {}
+                QuitState::Idle => {
+                    self.quit = QuitState::Confirming;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                }
+                QuitState::Confirming | QuitState::Finalizing(_) => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                }
+            }
+        }
+
+        if let QuitState::Finalizing(since) = self.quit
+            && since.elapsed() > QUIT_TIMEOUT
+        {
+            tracing::warn!("timed out waiting for recording to finish, quitting anyway");
+            self.close(ctx);
+        }
+
+        match self.quit {
+            QuitState::Confirming => self.quit_dialog(ctx),
+            QuitState::Finalizing(_) => {
+                egui::Modal::new(egui::Id::new("quit_finalizing")).show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(tr("quit.finalizing"));
+                    });
+                });
+            }
+            QuitState::Idle | QuitState::Closing => {}
+        }
+    }
+
+    /// 不管是否在录制都先询问, kiosk 模式下长按 Esc 时使用.
+    pub(super) fn confirm_quit(&mut self) {
+        if self.quit == QuitState::Idle {
+            self.quit = QuitState::Confirming;
+        }
+    }
+
+    /// 收到 Stopped 事件时调用.
+    pub(super) fn on_recording_finalized(&mut self, ctx: &egui::Context) {
+        if let QuitState::Finalizing(_) = self.quit {
+            self.close(ctx);
+        }
+    }
+
+    fn quit_dialog(&mut self, ctx: &egui::Context) {
+        let mut choice = None;
+        let recording = self.recorder.is_recording();
+        let response = egui::Modal::new(egui::Id::new("quit_confirm")).show(ctx, |ui| {
+            if recording {
+                ui.heading(tr("quit.recording_title"));
+                ui.label(tr("quit.recording_body"));
+            } else {
+                ui.heading(tr("quit.title"));
+            }
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                let (confirm, cancel) = if recording {
+                    (tr("quit.stop_and_quit"), tr("quit.keep_recording"))
+                } else {
+                    (tr("quit.quit"), tr("quit.cancel"))
+                };
+                if ui.button(confirm).clicked() {
+                    choice = Some(true);
+                }
+                if ui.button(cancel).clicked() {
+                    choice = Some(false);
+                }
+            });
+        });
+        if response.should_close() && choice.is_none() {
+            choice = Some(false);
+        }
+        match choice {
+            // 录制可能已经因为出错而停止
+            Some(true) if !self.recorder.is_recording() => self.close(ctx),
+            Some(true) => self.finalize_and_quit(),
+            Some(false) => self.quit = QuitState::Idle,
+            None => {}
+        }
+    }
+
+    fn finalize_and_quit(&mut self) {
+        self.recorder.stop(&mut self.slate);
+        self.quit = QuitState::Finalizing(Instant::now());
+    }
+
+    fn close(&mut self, ctx: &egui::Context) {
+        self.quit = QuitState::Closing;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+}
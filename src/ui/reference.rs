@@ -0,0 +1,216 @@
+use eframe::egui;
+use std::path::Path;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::config::ReferenceMode;
+use crate::i18n::{tr, tr_args};
+
+/// 叠加时允许的不透明度, 太淡看不出, 太浓看不清实时画面.
+const OPACITY_RANGE: std::ops::RangeInclusive<f32> = 0.1..=0.6;
+
+/// 取景参考图. 只画在主窗口的预览上, 不进入录像和节目输出.
+pub(super) struct Reference {
+    image: egui::ColorImage,
+    texture: egui::TextureHandle,
+    /// 差值模式下最近一帧与参考图的差.
+    difference: Option<egui::TextureHandle>,
+}
+
+impl CameraApp {
+    /// 还没有参考图时截取下一帧, 否则切换显示.
+    pub(super) fn toggle_reference(&mut self) {
+        if self.reference.is_some() {
+            self.show_reference = !self.show_reference;
+        } else {
+            self.reference_capture_pending = true;
+        }
+    }
+
+    fn set_reference(&mut self, ctx: &egui::Context, image: egui::ColorImage) {
+        let texture = ctx.load_texture("reference", image.clone(), Default::default());
+        self.reference = Some(Reference {
+            image,
+            texture,
+            difference: None,
+        });
+        self.show_reference = true;
+    }
+
+    fn load_reference(&mut self, ctx: &egui::Context, path: &Path) {
+        match crate::file::read_png(path) {
+            Ok(image) => {
+                tracing::info!(path = %path.display(), "loaded reference image");
+                self.set_reference(ctx, image);
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.display(), "could not load reference image: {}", e);
+                self.toasts.push(
+                    ToastLevel::Warning,
+                    tr_args("toast.reference_failed", &[("error", &e)]),
+                );
+            }
+        }
+    }
+
+    /// 每个新的预览帧调用, 使用没有叠加辅助显示的画面.
+    pub(super) fn update_reference(&mut self, ctx: &egui::Context, image: &egui::ColorImage) {
+        if std::mem::take(&mut self.reference_capture_pending) {
+            self.set_reference(ctx, image.clone());
+            return;
+        }
+        let showing = self.show_reference;
+        let mode = self.reference_settings.mode;
+        let Some(reference) = &mut self.reference else {
+            return;
+        };
+        if !showing || mode != ReferenceMode::Difference {
+            reference.difference = None;
+            return;
+        }
+        let image = difference(image, &reference.image);
+        match &mut reference.difference {
+            Some(texture) => texture.set(image, Default::default()),
+            None => {
+                reference.difference =
+                    Some(ctx.load_texture("reference_difference", image, Default::default()))
+            }
+        }
+    }
+
+    /// 画在预览之上, 与预览使用相同的放大范围.
+    pub(super) fn reference_overlay(&self, ui: &egui::Ui, rect: egui::Rect) {
+        let Some(reference) = self.reference.as_ref().filter(|_| self.show_reference) else {
+            return;
+        };
+        let (texture, tint) = match (self.reference_settings.mode, &reference.difference) {
+            (ReferenceMode::Difference, Some(difference)) => (difference, egui::Color32::WHITE),
+            (ReferenceMode::Difference, None) => return,
+            (ReferenceMode::Overlay, _) => {
+                let opacity = self
+                    .reference_settings
+                    .opacity
+                    .clamp(*OPACITY_RANGE.start(), *OPACITY_RANGE.end());
+                (
+                    &reference.texture,
+                    egui::Color32::WHITE.gamma_multiply(opacity),
+                )
+            }
+        };
+        ui.painter().image(texture.id(), rect, self.view.uv(), tint);
+    }
+
+    /// 设置面板: 截取或读取参考图, 叠加方式.
+    pub(super) fn reference_section(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button(tr("settings.reference.capture")).clicked() {
+                self.reference_capture_pending = true;
+            }
+            ui.add(
+                egui::TextEdit::singleline(&mut self.reference_path)
+                    .hint_text(tr("settings.reference.path_hint")),
+            );
+            let path = self.reference_path.trim().to_string();
+            if ui
+                .add_enabled(
+                    !path.is_empty(),
+                    egui::Button::new(tr("settings.reference.load")),
+                )
+                .clicked()
+            {
+                self.load_reference(ui.ctx(), Path::new(&path));
+            }
+        });
+        ui.add_enabled_ui(self.reference.is_some(), |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_reference, tr("settings.reference.show"));
+                if ui.button(tr("settings.reference.clear")).clicked() {
+                    self.reference = None;
+                    self.show_reference = false;
+                }
+            });
+        });
+        let s = &mut self.reference_settings;
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut s.mode,
+                ReferenceMode::Overlay,
+                tr("settings.reference.overlay"),
+            );
+            ui.radio_value(
+                &mut s.mode,
+                ReferenceMode::Difference,
+                tr("settings.reference.difference"),
+            );
+        });
+        ui.add_enabled_ui(s.mode == ReferenceMode::Overlay, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr("settings.reference.opacity"));
+                let mut percent = s.opacity * 100.0;
+                if ui
+                    .add(
+                        egui::Slider::new(
+                            &mut percent,
+                            OPACITY_RANGE.start() * 100.0..=OPACITY_RANGE.end() * 100.0,
+                        )
+                        .step_by(5.0)
+                        .suffix(" %"),
+                    )
+                    .changed()
+                {
+                    s.opacity = percent / 100.0;
+                }
+            });
+        });
+        ui.weak(tr("settings.reference.hint"));
+    }
+}
+
+/// 逐通道的差的绝对值. 尺寸不同时按比例取参考图中对应的像素.
+fn difference(live: &egui::ColorImage, reference: &egui::ColorImage) -> egui::ColorImage {
+    let [width, height] = live.size;
+    let [ref_width, ref_height] = reference.size;
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let ry = (y * ref_height / height.max(1)).min(ref_height.saturating_sub(1));
+        for x in 0..width {
+            let rx = (x * ref_width / width.max(1)).min(ref_width.saturating_sub(1));
+            let [r, g, b, _] = live.pixels[y * width + x].to_array();
+            let [rr, rg, rb, _] = reference
+                .pixels
+                .get(ry * ref_width + rx)
+                .map_or([0; 4], |p| p.to_array());
+            pixels.push(egui::Color32::from_rgb(
+                r.abs_diff(rr),
+                g.abs_diff(rg),
+                b.abs_diff(rb),
+            ));
+        }
+    }
+    egui::ColorImage::new(live.size, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difference_is_black_where_aligned() {
+        let grey = egui::Color32::from_gray(100);
+        let live = egui::ColorImage::new([4, 2], vec![grey; 8]);
+        assert!(
+            difference(&live, &live)
+                .pixels
+                .iter()
+                .all(|p| *p == egui::Color32::BLACK)
+        );
+        // 参考图只有一半大小时按比例对应
+        let mut pixels = vec![grey; 2];
+        pixels[1] = egui::Color32::from_gray(40);
+        let reference = egui::ColorImage::new([2, 1], pixels);
+        let diff = difference(&live, &reference);
+        assert_eq!(diff.pixels[1], egui::Color32::BLACK);
+        assert_eq!(diff.pixels[2], egui::Color32::from_gray(60));
+        assert_eq!(diff.pixels[7], egui::Color32::from_gray(60));
+    }
+}
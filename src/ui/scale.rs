@@ -0,0 +1,95 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::tr;
+
+pub(super) const MIN_UI_SCALE: f32 = 0.75;
+pub(super) const MAX_UI_SCALE: f32 = 2.0;
+/// 触摸屏上按钮和滑块的最小高度, 单位是物理像素.
+const MIN_HIT_PIXELS: f32 = 40.0;
+
+/// 没有配置时按显示器推测初始缩放. 系统已经按 DPI 缩放时 (`native_ppp` 大于 1)
+/// 不再额外放大, 只有小屏幕缩小.
+pub(crate) fn initial_scale(native_ppp: f32, monitor_points: egui::Vec2) -> f32 {
+    let physical_height = monitor_points.y * native_ppp;
+    let scale = if physical_height <= 600.0 {
+        MIN_UI_SCALE
+    } else if native_ppp > 1.0 {
+        1.0
+    } else if physical_height >= 2000.0 {
+        2.0
+    } else if physical_height >= 1400.0 {
+        1.5
+    } else {
+        1.0
+    };
+    scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+}
+
+impl CameraApp {
+    /// 每帧调用. 缩放通过 `pixels_per_point` 统一生效, 布局常量都以点为单位.
+    pub(super) fn apply_ui_scale(&mut self, ctx: &egui::Context) {
+        let (native, monitor) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (
+                viewport.native_pixels_per_point.unwrap_or(1.0),
+                viewport.monitor_size,
+            )
+        });
+        let scale = match self.ui_scale {
+            Some(scale) => scale,
+            // 显示器尺寸在第一帧可能还不知道
+            None => match monitor {
+                Some(monitor) => {
+                    let scale = initial_scale(native, monitor);
+                    tracing::info!(native, scale, "detected ui scale");
+                    *self.ui_scale.insert(scale)
+                }
+                None => return,
+            },
+        };
+        let ppp = native * scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        if (ctx.pixels_per_point() - ppp).abs() < 0.001 {
+            return;
+        }
+        ctx.set_pixels_per_point(ppp);
+        let base = egui::Style::default().spacing.interact_size.y;
+        ctx.style_mut(|style| {
+            style.spacing.interact_size.y = base.max(MIN_HIT_PIXELS / ppp);
+        });
+    }
+
+    /// 设置面板: 界面缩放, 拖动时立即生效.
+    pub(super) fn display_section(&mut self, ui: &mut egui::Ui) {
+        let mut scale = self.ui_scale.unwrap_or(1.0);
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.display.ui_scale"));
+            if ui
+                .add(
+                    egui::Slider::new(&mut scale, MIN_UI_SCALE..=MAX_UI_SCALE)
+                        .step_by(0.05)
+                        .suffix("×"),
+                )
+                .changed()
+            {
+                self.ui_scale = Some(scale);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_a_scale_from_the_display() {
+        // 5 寸 800×480 监视器
+        assert_eq!(initial_scale(1.0, egui::vec2(800.0, 480.0)), 0.75);
+        assert_eq!(initial_scale(1.0, egui::vec2(1920.0, 1080.0)), 1.0);
+        // 没有系统缩放的 4K 显示器
+        assert_eq!(initial_scale(1.0, egui::vec2(3840.0, 2160.0)), 2.0);
+        // 系统已经按 2 倍缩放
+        assert_eq!(initial_scale(2.0, egui::vec2(1920.0, 1080.0)), 1.0);
+    }
+}
@@ -0,0 +1,64 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::i18n::tr;
+use crate::video::screen::{Region, ScreenBackend};
+
+/// 窗口会被拖动, 定期检查是否进入了录制范围.
+const MIRROR_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+impl CameraApp {
+    /// 录制桌面时拍到自己的窗口会出现无限镜像, 窗口进入录制范围时提示一次.
+    pub(super) fn check_screen_mirror(&mut self, ctx: &egui::Context) {
+        let Some(area) = self.screen_area else {
+            return;
+        };
+        let now = Instant::now();
+        if now < self.mirror_checked_at + MIRROR_CHECK_INTERVAL {
+            return;
+        }
+        self.mirror_checked_at = now;
+        let window = ctx.input(|i| {
+            let viewport = i.viewport();
+            let scale = viewport.native_pixels_per_point.unwrap_or(1.0);
+            viewport.outer_rect.map(|rect| rect * scale)
+        });
+        let Some(window) = window else {
+            return;
+        };
+        let window = Region {
+            x: window.min.x.round() as i32,
+            y: window.min.y.round() as i32,
+            width: window.width().round().max(0.0) as u32,
+            height: window.height().round().max(0.0) as u32,
+        };
+        let mirrored = area.contains_any(&window);
+        if mirrored && !self.mirror_warned {
+            tracing::warn!(?area, ?window, "screen capture includes the app window");
+            self.toasts
+                .push(ToastLevel::Warning, tr("toast.screen_mirror"));
+        }
+        self.mirror_warned = mirrored;
+    }
+
+    /// 门户的对话框打开期间还没有画面.
+    pub(super) fn waiting_for_screen_share(&self, ui: &egui::Ui, rect: egui::Rect) {
+        let portal = self
+            .capture
+            .screen
+            .as_ref()
+            .is_some_and(|s| s.backend == ScreenBackend::Portal);
+        if !portal || self.screen_area.is_some() {
+            return;
+        }
+        ui.painter().text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            tr("banner.screen_permission"),
+            egui::FontId::proportional(24.0),
+            egui::Color32::LIGHT_GRAY,
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::config::CaptureConfig;
+use crate::file::session_log::Severity;
+use crate::i18n::{tr, tr_args};
+use crate::video::rtsp;
+
+impl CameraApp {
+    /// 写一条会话记录, 文件在当前的录像目录中.
+    pub(super) fn log_session(&mut self, severity: Severity, text: impl Into<String>) {
+        self.session_log
+            .push(&self.recording.output_dir, severity, text);
+    }
+
+    /// 每帧调用: 提示过的警告和错误都记入会话记录.
+    pub(super) fn log_toasts(&mut self) {
+        for (level, text) in self.toasts.take_unlogged() {
+            let severity = match level {
+                ToastLevel::Info => Severity::Info,
+                ToastLevel::Warning => Severity::Warning,
+                ToastLevel::Error => Severity::Error,
+            };
+            self.log_session(severity, text);
+        }
+    }
+
+    pub(super) fn log_app_started(&mut self) {
+        let text = tr_args(
+            "session.app_started",
+            &[
+                ("version", &env!("CARGO_PKG_VERSION")),
+                ("source", &source_summary(&self.capture)),
+            ],
+        );
+        self.log_session(Severity::Info, text);
+    }
+
+    /// 会话记录面板, 可以按子串筛选并导出.
+    pub(super) fn session_log_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_session_log;
+        let mut export = false;
+        egui::Window::new(tr("session.title"))
+            .id(egui::Id::new("session_log"))
+            .open(&mut open)
+            .default_width(560.0)
+            .default_height(320.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.session_query)
+                            .hint_text(tr("session.search"))
+                            .desired_width(240.0),
+                    );
+                    export = ui
+                        .button(tr("session.export"))
+                        .on_hover_text(tr("session.export_hint"))
+                        .clicked();
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for entry in self
+                            .session_log
+                            .entries()
+                            .iter()
+                            .filter(|e| e.matches(&self.session_query))
+                        {
+                            let color = match entry.severity {
+                                Severity::Info => self.theme.text,
+                                Severity::Warning => self.theme.warning,
+                                Severity::Error => self.theme.alert,
+                            };
+                            ui.label(
+                                egui::RichText::new(entry.to_string())
+                                    .monospace()
+                                    .color(color),
+                            );
+                        }
+                    });
+            });
+        self.show_session_log = open;
+
+        if export {
+            match self
+                .session_log
+                .export(&self.recording.output_dir, &self.session_query)
+            {
+                Ok(path) => self.toasts.push(
+                    ToastLevel::Info,
+                    tr_args("session.exported", &[("path", &path.display().to_string())]),
+                ),
+                Err(e) => self.toasts.push(
+                    ToastLevel::Warning,
+                    tr_args("session.export_failed", &[("error", &e)]),
+                ),
+            }
+        }
+    }
+}
+
+/// 记录中显示的采集源, 例如 "v4l2src device=/dev/video0".
+fn source_summary(capture: &CaptureConfig) -> String {
+    if capture.decklink.is_some() {
+        "DeckLink".to_string()
+    } else if capture.screen.is_some() {
+        tr("session.screen").to_string()
+    } else if let Some(rtsp) = &capture.rtsp {
+        rtsp::redact(&rtsp.uri)
+    } else {
+        rtsp::redact(&capture.video_source)
+    }
+}
@@ -0,0 +1,1007 @@
+use eframe::egui;
+use std::path::PathBuf;
+
+use super::CameraApp;
+use crate::file::remaining;
+use crate::i18n::{tr, tr_args};
+use crate::video::av1::{self, Av1Encoder, Realtime};
+use crate::video::benchmark;
+use crate::video::decklink::{CHANNEL_COUNTS, Connection, DeckLinkConfig, MODES};
+use crate::video::ffv1;
+use crate::video::pattern::{self, TestPattern, TestSourceConfig};
+use crate::video::pipeline::MAX_AUDIO_GAIN;
+use crate::video::prores::ProResProfile;
+use crate::video::record::{
+    BitDepth, Container, FrameRateMode, PlaybackIssue, Resolution, VideoEncoder, playback_issues,
+};
+use crate::video::rtsp::RtspConfig;
+use crate::video::screen::{self, Region, ScreenBackend, ScreenConfig};
+use crate::video::timecode::TimecodeSource;
+
+/// 录制分辨率的可选项.
+const RESOLUTIONS: &[(u32, u32)] = &[(1280, 720), (1920, 1080), (3840, 2160)];
+
+impl CameraApp {
+    /// 设置面板, 点击右上角齿轮图标打开.
+    pub(super) fn settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        let offload_before = self.offload.clone();
+        let thermal_before = self.thermal_settings.clone();
+        let battery_before = self.battery_settings.clone();
+        egui::Window::new(tr("settings.title"))
+            .id(egui::Id::new("settings"))
+            .open(&mut open)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                header("recording", tr("settings.header.recording"))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        self.recording_section(ui);
+                        ui.separator();
+                        self.countdown_section(ui);
+                        ui.separator();
+                        self.snapshot_section(ui);
+                        ui.separator();
+                        self.gif_section(ui);
+                        ui.separator();
+                        self.marker_section(ui);
+                    });
+                header("behavior", tr("settings.header.behavior"))
+                    .show(ui, |ui| self.behavior_section(ui));
+                header("profiles", tr("settings.header.profiles"))
+                    .show(ui, |ui| self.profiles_section(ui));
+                header("capture", tr("settings.header.capture"))
+                    .show(ui, |ui| self.capture_section(ui));
+                header("sources", tr("settings.header.sources")).show(ui, |ui| {
+                    self.switching_section(ui);
+                    ui.weak(tr("settings.sources.note"));
+                });
+                header("pip", tr("settings.header.pip")).show(ui, |ui| {
+                    self.pip_section(ui);
+                    ui.weak(tr("settings.pip.note"));
+                });
+                header("display", tr("settings.header.display")).show(ui, |ui| {
+                    self.language_section(ui);
+                    self.display_section(ui);
+                    self.window_section(ui);
+                    self.theme_section(ui);
+                    self.chrome_section(ui);
+                    self.tally_section(ui);
+                    self.overlay_section(ui);
+                    self.exposure_warning_section(ui);
+                    self.lock_section(ui);
+                });
+                header("assist", tr("settings.header.assist")).show(ui, |ui| {
+                    self.assist_section(ui);
+                    ui.separator();
+                    self.reference_section(ui);
+                });
+                header("program", tr("settings.header.program"))
+                    .show(ui, |ui| self.program_section(ui));
+                header("tags", tr("settings.header.tags")).show(ui, |ui| self.tags_section(ui));
+                header("sftp", tr("settings.header.sftp"))
+                    .default_open(true)
+                    .show(ui, |ui| self.sftp_section(ui));
+                #[cfg(feature = "s3")]
+                header("s3", tr("settings.header.s3")).show(ui, |ui| self.s3_section(ui));
+                header("webhook", tr("settings.header.webhook"))
+                    .show(ui, |ui| self.webhook_section(ui));
+                header("status", tr("settings.header.status"))
+                    .show(ui, |ui| self.status_section(ui));
+                #[cfg(feature = "mqtt")]
+                header("mqtt", tr("settings.header.mqtt")).show(ui, |ui| self.mqtt_section(ui));
+                header("osc", tr("settings.header.osc")).show(ui, |ui| self.osc_section(ui));
+                header("battery", tr("settings.header.battery"))
+                    .show(ui, |ui| self.battery_section(ui));
+                header("thermal", tr("settings.header.thermal"))
+                    .show(ui, |ui| self.thermal_section(ui));
+                header("logging", tr("settings.header.logging"))
+                    .show(ui, |ui| self.logging_section(ui));
+                header("keymap", tr("settings.header.keymap"))
+                    .show(ui, |ui| self.keymap_section(ui));
+                #[cfg(feature = "gamepad")]
+                header("gamepad", tr("settings.header.gamepad"))
+                    .show(ui, |ui| self.gamepad_section(ui));
+                #[cfg(feature = "midi")]
+                header("midi", tr("settings.header.midi")).show(ui, |ui| self.midi_section(ui));
+                header("benchmark", tr("settings.header.benchmark"))
+                    .show(ui, |ui| self.benchmark_section(ui));
+                header("debug", tr("settings.header.debug")).show(ui, |ui| self.debug_section(ui));
+                if ui.button(tr("settings.transfers")).clicked() {
+                    self.show_transfers = true;
+                }
+                if ui.button(tr("settings.session_log")).clicked() {
+                    self.show_session_log = true;
+                }
+                if ui.button(tr("settings.camera_controls")).clicked() {
+                    self.show_device_controls = true;
+                }
+            });
+        self.show_settings = open;
+
+        if self.battery_settings != battery_before {
+            self.battery.set_settings(self.battery_settings.clone());
+        }
+        if self.thermal_settings != thermal_before {
+            self.thermal.set_settings(self.thermal_settings.clone());
+        }
+        if self.offload != offload_before {
+            self.uploader.set_settings(self.offload.clone());
+        }
+    }
+
+    fn recording_section(&mut self, ui: &mut egui::Ui) {
+        let r = &mut self.recording;
+        let mut new_dir = None;
+        ui.add_enabled_ui(!self.recorder.is_recording(), |ui| {
+            egui::Grid::new("recording_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label(tr("settings.recording.resolution"));
+                    egui::ComboBox::from_id_salt("resolution")
+                        .selected_text(format!("{}×{}", r.resolution.width, r.resolution.height))
+                        .show_ui(ui, |ui| {
+                            for &(width, height) in RESOLUTIONS {
+                                ui.selectable_value(
+                                    &mut r.resolution,
+                                    Resolution { width, height },
+                                    format!("{}×{}", width, height),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("settings.recording.encoder"));
+                    egui::ComboBox::from_id_salt("encoder")
+                        .selected_text(r.encoder.to_string())
+                        .show_ui(ui, |ui| {
+                            for enc in VideoEncoder::ALL {
+                                // ProRes 的档次在下一行选择
+                                let selected = std::mem::discriminant(&r.encoder)
+                                    == std::mem::discriminant(&enc);
+                                let label = match enc {
+                                    VideoEncoder::ProRes { .. } => "ProRes".to_string(),
+                                    _ => enc.to_string(),
+                                };
+                                if ui.selectable_label(selected, label).clicked() && !selected {
+                                    r.encoder = enc;
+                                }
+                            }
+                        });
+                    ui.end_row();
+
+                    if let VideoEncoder::ProRes { profile } = &mut r.encoder {
+                        ui.label(tr("settings.recording.prores_profile"));
+                        egui::ComboBox::from_id_salt("prores_profile")
+                            .selected_text(profile.label())
+                            .show_ui(ui, |ui| {
+                                for p in ProResProfile::ALL {
+                                    ui.selectable_value(profile, p, p.label());
+                                }
+                            });
+                        ui.end_row();
+                    }
+                    // 换成当前容器放不下的编码时, 改用第一个可用的容器
+                    if !r.container.supports(r.encoder)
+                        && let Some(c) = Container::ALL.into_iter().find(|c| c.supports(r.encoder))
+                    {
+                        r.container = c;
+                    }
+
+                    if r.encoder == VideoEncoder::Ffv1 {
+                        ui.label(tr("settings.recording.ffv1_slices"));
+                        egui::ComboBox::from_id_salt("ffv1_slices")
+                            .selected_text(r.ffv1_slices.to_string())
+                            .show_ui(ui, |ui| {
+                                for n in ffv1::SLICES {
+                                    ui.selectable_value(&mut r.ffv1_slices, n, n.to_string());
+                                }
+                            })
+                            .response
+                            .on_hover_text(tr("settings.recording.ffv1_slices_hint"));
+                        ui.end_row();
+                    }
+
+                    if r.encoder == VideoEncoder::Mjpeg {
+                        ui.label(tr("settings.recording.mjpeg_quality"));
+                        ui.add(egui::Slider::new(&mut r.mjpeg_quality, 1..=100))
+                            .on_hover_text(tr("settings.recording.mjpeg_quality_hint"));
+                        ui.end_row();
+                    }
+
+                    if r.encoder == VideoEncoder::Av1 {
+                        ui.label(tr("settings.recording.av1_speed"));
+                        ui.add(egui::Slider::new(&mut r.av1_speed, av1::SPEEDS))
+                            .on_hover_text(tr("settings.recording.av1_speed_hint"));
+                        ui.end_row();
+                    }
+
+                    ui.label(tr("settings.recording.bit_depth"));
+                    egui::ComboBox::from_id_salt("bit_depth")
+                        .selected_text(bit_depth_label(r.bit_depth))
+                        .show_ui(ui, |ui| {
+                            for depth in [BitDepth::Auto, BitDepth::Eight, BitDepth::Ten] {
+                                ui.selectable_value(
+                                    &mut r.bit_depth,
+                                    depth,
+                                    bit_depth_label(depth),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("settings.recording.bit_depth_hint"));
+                    ui.end_row();
+
+                    ui.label(tr("settings.recording.timecode"));
+                    egui::ComboBox::from_id_salt("timecode")
+                        .selected_text(timecode_label(r.timecode))
+                        .show_ui(ui, |ui| {
+                            for source in [TimecodeSource::TimeOfDay, TimecodeSource::Zero] {
+                                ui.selectable_value(
+                                    &mut r.timecode,
+                                    source,
+                                    timecode_label(source),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("settings.recording.timecode_hint"));
+                    ui.end_row();
+
+                    ui.label(tr("settings.recording.frame_rate"));
+                    egui::ComboBox::from_id_salt("frame_rate")
+                        .selected_text(frame_rate_label(r.frame_rate))
+                        .show_ui(ui, |ui| {
+                            let fixed = FrameRateMode::FIXED.map(FrameRateMode::Fixed);
+                            for mode in [FrameRateMode::Source]
+                                .into_iter()
+                                .chain(fixed)
+                                .chain([FrameRateMode::Variable])
+                            {
+                                ui.selectable_value(
+                                    &mut r.frame_rate,
+                                    mode,
+                                    frame_rate_label(mode),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("settings.recording.frame_rate_hint"));
+                    ui.end_row();
+
+                    ui.label(tr("settings.recording.container"));
+                    egui::ComboBox::from_id_salt("container")
+                        .selected_text(format!("{:?}", r.container))
+                        .show_ui(ui, |ui| {
+                            for c in Container::ALL {
+                                ui.add_enabled_ui(c.supports(r.encoder), |ui| {
+                                    ui.selectable_value(&mut r.container, c, format!("{:?}", c));
+                                });
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("settings.recording.bitrate"));
+                    // ProRes, FFV1 和 MJPEG 不使用设置的码率, 只显示估算值
+                    let hint = match r.encoder {
+                        VideoEncoder::ProRes { .. } => "settings.recording.prores_bitrate_hint",
+                        VideoEncoder::Ffv1 => "settings.recording.ffv1_bitrate_hint",
+                        _ => "settings.recording.mjpeg_bitrate_hint",
+                    };
+                    match r.encoder.fixed_kbps(r.resolution, r.mjpeg_quality) {
+                        Some(kbps) => {
+                            let mbps = kbps / 1000;
+                            ui.label(tr_args(
+                                "settings.recording.estimated_bitrate",
+                                &[("mbps", &mbps)],
+                            ))
+                            .on_hover_text(tr(hint));
+                        }
+                        None => {
+                            ui.add(
+                                egui::DragValue::new(&mut r.bitrate_kbps)
+                                    .range(256..=100_000)
+                                    .speed(50),
+                            );
+                        }
+                    }
+                    ui.end_row();
+
+                    ui.label(tr("settings.recording.output_dir"));
+                    let mut dir = r.output_dir.display().to_string();
+                    if ui.text_edit_singleline(&mut dir).changed() {
+                        new_dir = Some(PathBuf::from(dir));
+                    }
+                    ui.end_row();
+                });
+        });
+        for issue in playback_issues(r.encoder, r.container) {
+            let text = match issue {
+                PlaybackIssue::MatroskaOnApple => tr("settings.recording.mkv_on_apple"),
+                PlaybackIssue::Hevc => tr("settings.recording.hevc_players"),
+                PlaybackIssue::Av1 => tr("settings.recording.av1_players"),
+                PlaybackIssue::Vp9 => tr("settings.recording.vp9_players"),
+            };
+            ui.colored_label(self.theme.warning, text);
+        }
+        if r.bit_depth == BitDepth::Ten && !r.encoder.supports_ten_bit() {
+            ui.colored_label(
+                self.theme.alert,
+                tr_args(
+                    "settings.recording.ten_bit_unsupported",
+                    &[("encoder", &r.encoder.to_string())],
+                ),
+            );
+        }
+        if r.encoder == VideoEncoder::Ffv1 {
+            let mbps = ffv1::estimated_kbps(r.resolution, ffv1::ASSUMED_FPS) / 1000;
+            ui.colored_label(
+                self.theme.alert,
+                tr_args("settings.recording.ffv1_warning", &[("mbps", &mbps)]),
+            );
+        }
+        // 按估算的码率换算剩余空间还能录多久
+        if let Ok(free) = crate::file::free_space(&r.output_dir) {
+            let kbps = r
+                .encoder
+                .estimated_kbps(r.resolution, r.bitrate_kbps, r.mjpeg_quality);
+            ui.weak(tr_args(
+                "settings.recording.disk_left",
+                &[
+                    ("free", &crate::file::format_size(free)),
+                    (
+                        "time",
+                        &super::format_duration(remaining::at_bitrate(free, kbps)),
+                    ),
+                ],
+            ));
+        }
+        // 测过速时按实测的结果提示, 比按分辨率的粗略判断准
+        let measured = self
+            .benchmark_report
+            .as_ref()
+            .and_then(|b| b.realtime(r.encoder, r.resolution));
+        if let Some(speed) = measured {
+            let (color, key) = match benchmark::sustainable(speed) {
+                Realtime::Likely => (self.theme.good, "settings.recording.benchmark_realtime"),
+                Realtime::Marginal => (self.theme.warning, "settings.recording.benchmark_marginal"),
+                Realtime::Unlikely => (self.theme.alert, "settings.recording.benchmark_too_slow"),
+            };
+            ui.colored_label(color, tr_args(key, &[("speed", &format!("{:.1}", speed))]));
+        }
+        if r.encoder == VideoEncoder::Av1 {
+            match Av1Encoder::detect() {
+                Some(_) if measured.is_some() => {}
+                Some(enc) => {
+                    let (color, key) = match av1::realtime(r.resolution, r.av1_speed) {
+                        Realtime::Likely => (self.theme.good, "settings.recording.av1_realtime"),
+                        Realtime::Marginal => {
+                            (self.theme.warning, "settings.recording.av1_marginal")
+                        }
+                        Realtime::Unlikely => (self.theme.alert, "settings.recording.av1_too_slow"),
+                    };
+                    ui.colored_label(color, tr_args(key, &[("encoder", &enc.element())]));
+                }
+                None => {
+                    ui.colored_label(self.theme.alert, tr("settings.recording.av1_missing"));
+                }
+            }
+        }
+        if let Some(dir) = new_dir {
+            self.set_output_dir(dir);
+        }
+        self.denoise_section(ui);
+        self.sharpen_section(ui);
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.recording.warn_after"));
+            ui.add(egui::DragValue::new(&mut self.drop_warning_frames).range(0..=10_000));
+            ui.label(tr("settings.recording.dropped_frames"));
+        });
+        self.remaining_section(ui);
+        ui.checkbox(&mut self.auto_record, tr("settings.recording.auto_record"))
+            .on_hover_text(tr("settings.recording.auto_record_hint"));
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.recording.audio_gain"));
+            let mut gain = self.audio_gain;
+            let slider = ui.add(
+                egui::Slider::new(&mut gain, 0.0..=MAX_AUDIO_GAIN)
+                    .fixed_decimals(2)
+                    .suffix("×"),
+            );
+            if slider.changed() {
+                self.set_audio_gain(gain);
+            }
+        });
+        self.silence_section(ui);
+    }
+
+    /// 新的录像保存到这里, 录像浏览器和远程状态也跟着切换.
+    pub(super) fn set_output_dir(&mut self, dir: PathBuf) {
+        self.recording.output_dir = dir;
+        self.browser.dir = self.recording.output_dir.clone();
+        // MQTT 从状态服务器共用的状态中读取
+        self.status.set_output_dir(&self.recording.output_dir);
+    }
+
+    /// 立即生效, 录制中也可以调整.
+    pub(super) fn set_audio_gain(&mut self, gain: f64) {
+        self.audio_gain = gain.clamp(0.0, MAX_AUDIO_GAIN);
+        self.recorder.set_audio_gain(self.audio_gain);
+    }
+
+    fn logging_section(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.logging.level"));
+            let before = self.log_level.clone();
+            egui::ComboBox::from_id_salt("log_level")
+                .selected_text(self.log_level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in crate::logging::LEVELS {
+                        ui.selectable_value(&mut self.log_level, level.to_string(), *level);
+                    }
+                });
+            if self.log_level != before {
+                self.log_console.set_level(&self.log_level);
+            }
+            if ui.button(tr("settings.logging.show")).clicked() {
+                self.show_console = true;
+            }
+        });
+        if std::env::var_os("RUST_LOG").is_some() {
+            ui.weak(tr("settings.logging.rust_log"));
+        }
+        ui.weak(tr("settings.logging.files"));
+    }
+
+    fn battery_section(&mut self, ui: &mut egui::Ui) {
+        let b = &mut self.battery_settings;
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.battery.warn_below"));
+            ui.add(
+                egui::DragValue::new(&mut b.warn_percent)
+                    .range(0..=100)
+                    .suffix(" %"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut b.auto_stop, tr("settings.battery.auto_stop"));
+            ui.add_enabled(
+                b.auto_stop,
+                egui::DragValue::new(&mut b.stop_percent)
+                    .range(1..=50)
+                    .suffix(" %"),
+            );
+        })
+        .response
+        .on_hover_text(tr("settings.battery.auto_stop_hint"));
+        if self.battery.status().is_none() {
+            ui.weak(tr("settings.battery.missing"));
+        }
+    }
+
+    fn thermal_section(&mut self, ui: &mut egui::Ui) {
+        let t = &mut self.thermal_settings;
+        egui::Grid::new("thermal_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("settings.thermal.zone"));
+                ui.add(
+                    egui::TextEdit::singleline(&mut t.zone)
+                        .hint_text(tr("settings.thermal.zone_hint")),
+                )
+                .on_hover_text(tr("settings.thermal.zone_help"));
+                ui.end_row();
+                ui.label(tr("settings.thermal.warn_at"));
+                ui.add(
+                    egui::DragValue::new(&mut t.warn_celsius)
+                        .range(30.0..=120.0)
+                        .suffix(" °C"),
+                );
+                ui.end_row();
+                ui.label(tr("settings.thermal.critical_at"));
+                ui.add(
+                    egui::DragValue::new(&mut t.critical_celsius)
+                        .range(30.0..=120.0)
+                        .suffix(" °C"),
+                );
+                ui.end_row();
+            });
+        if self.thermal.current().is_none() {
+            ui.weak(tr("settings.thermal.missing"));
+        }
+    }
+
+    fn debug_section(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.show_fps, tr("settings.debug.fps"))
+            .on_hover_text(tr("settings.debug.fps_hint"));
+        ui.checkbox(&mut self.show_latency, tr("settings.debug.latency"))
+            .on_hover_text(tr("settings.debug.latency_hint"));
+        ui.checkbox(&mut self.show_resources, tr("settings.debug.resources"))
+            .on_hover_text(tr("settings.debug.resources_hint"));
+        ui.add_enabled_ui(self.show_resources, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr("settings.debug.warn_above"));
+                ui.add(
+                    egui::DragValue::new(&mut self.cpu_warning_percent)
+                        .range(0.0..=1600.0)
+                        .suffix(" %"),
+                );
+                ui.label(tr("settings.debug.cpu"));
+            });
+        });
+        if ui
+            .button(tr("settings.debug.graph"))
+            .on_hover_text(tr("settings.debug.graph_hint"))
+            .clicked()
+        {
+            self.recorder.dump_graph(&self.recording.output_dir);
+        }
+    }
+
+    fn capture_section(&mut self, ui: &mut egui::Ui) {
+        let available = self.decklink_available;
+        let c = &mut self.capture;
+        // 没有插件时不显示, 除非配置里已经选了 DeckLink, 这样还能关掉它
+        if available || c.decklink.is_some() {
+            let mut enabled = c.decklink.is_some();
+            if ui
+                .checkbox(&mut enabled, tr("settings.capture.decklink"))
+                .changed()
+            {
+                c.decklink = enabled.then(DeckLinkConfig::default);
+                c.screen = None;
+                c.rtsp = None;
+            }
+        }
+        let mut screen = c.screen.is_some();
+        if ui
+            .checkbox(&mut screen, tr("settings.capture.screen"))
+            .changed()
+        {
+            c.screen = screen.then(ScreenConfig::default);
+            c.decklink = None;
+            c.rtsp = None;
+        }
+        let mut network = c.rtsp.is_some();
+        if ui
+            .checkbox(&mut network, tr("settings.capture.rtsp"))
+            .changed()
+        {
+            c.rtsp = network.then(RtspConfig::default);
+            c.decklink = None;
+            c.screen = None;
+        }
+        egui::Grid::new("capture_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("settings.capture.video_source"));
+                ui.add_enabled(
+                    c.decklink.is_none() && c.screen.is_none() && c.rtsp.is_none(),
+                    egui::TextEdit::singleline(&mut c.video_source)
+                        .hint_text("v4l2src device=/dev/video0"),
+                );
+                ui.end_row();
+
+                ui.label(tr("settings.capture.audio_source"));
+                ui.add_enabled(
+                    c.decklink.is_none() && !c.rtsp.as_ref().is_some_and(|r| r.audio),
+                    egui::TextEdit::singleline(&mut c.audio_source).hint_text("alsasrc"),
+                );
+                ui.end_row();
+            });
+        if let Some(d) = &mut c.decklink {
+            decklink_grid(ui, d, available);
+        }
+        if let Some(s) = &mut c.screen {
+            screen_grid(ui, s, &self.screen_outputs);
+        }
+        let test_source = c.decklink.is_none()
+            && c.screen.is_none()
+            && c.rtsp.is_none()
+            && pattern::is_test_source(&c.video_source);
+        if test_source && test_source_grid(ui, &mut c.test_source) {
+            self.recorder.set_test_pattern(c.test_source.pattern);
+        }
+        self.network_camera_section(ui);
+        ui.weak(tr("settings.common.restart"));
+    }
+
+    fn tags_section(&mut self, ui: &mut egui::Ui) {
+        let t = &mut self.tags;
+        egui::Grid::new("tags_grid").num_columns(2).show(ui, |ui| {
+            ui.label(tr("settings.tags.operator"));
+            ui.text_edit_singleline(&mut t.artist);
+            ui.end_row();
+
+            ui.label(tr("settings.tags.camera_model"));
+            ui.add(
+                egui::TextEdit::singleline(&mut t.device_model)
+                    .hint_text(tr("settings.tags.camera_model_hint")),
+            );
+            ui.end_row();
+        });
+        ui.weak(tr("settings.tags.note"));
+    }
+
+    fn webhook_section(&mut self, ui: &mut egui::Ui) {
+        let before = self.webhook.clone();
+        let s = &mut self.webhook;
+
+        ui.checkbox(&mut s.enabled, tr("settings.webhook.enabled"));
+        egui::Grid::new("webhook_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("settings.webhook.url"));
+                ui.add(
+                    egui::TextEdit::singleline(&mut s.url).hint_text("https://example.com/hook"),
+                );
+                ui.end_row();
+
+                ui.label(tr("settings.webhook.secret"));
+                ui.add(egui::TextEdit::singleline(&mut s.secret).password(true))
+                    .on_hover_text(tr("settings.webhook.secret_hint"));
+                ui.end_row();
+            });
+        if self.webhook != before {
+            self.webhooks.set_settings(self.webhook.clone());
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !self.webhook.url.is_empty(),
+                    egui::Button::new(tr("settings.webhook.send_test")),
+                )
+                .clicked()
+            {
+                self.webhooks.send_test();
+            }
+            if let Some(status) = self.webhooks.last_status() {
+                let ago = status.at.elapsed().unwrap_or_default().as_secs();
+                match status.result {
+                    Ok(code) => ui.colored_label(
+                        egui::Color32::GREEN,
+                        tr_args(
+                            "settings.webhook.delivered",
+                            &[("event", &status.event), ("code", &code), ("ago", &ago)],
+                        ),
+                    ),
+                    Err(e) => ui
+                        .colored_label(
+                            egui::Color32::RED,
+                            tr_args(
+                                "settings.webhook.failed",
+                                &[("event", &status.event), ("ago", &ago)],
+                            ),
+                        )
+                        .on_hover_text(e),
+                };
+            }
+        });
+    }
+
+    /// 修改后要重启才生效, 服务只在启动时监听.
+    fn status_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.status_settings;
+
+        ui.checkbox(&mut s.enabled, tr("settings.status.enabled"));
+        egui::Grid::new("status_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("settings.status.listen"));
+                ui.add(egui::TextEdit::singleline(&mut s.listen).hint_text("127.0.0.1:8765"));
+                ui.end_row();
+            });
+        ui.weak(tr_args(
+            "settings.status.hint",
+            &[
+                ("url", &format!("ws://{}", s.listen)),
+                ("version", &crate::status::SCHEMA_VERSION),
+            ],
+        ));
+        ui.weak(tr("settings.common.restart"));
+    }
+
+    fn sftp_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.offload.sftp;
+
+        ui.checkbox(&mut s.enabled, tr("settings.common.auto_upload"));
+        egui::Grid::new("sftp_grid").num_columns(2).show(ui, |ui| {
+            ui.label(tr("settings.sftp.host"));
+            ui.text_edit_singleline(&mut s.host);
+            ui.end_row();
+
+            ui.label(tr("settings.sftp.port"));
+            ui.add(egui::DragValue::new(&mut s.port).range(1..=65535));
+            ui.end_row();
+
+            ui.label(tr("settings.common.user"));
+            ui.text_edit_singleline(&mut s.user);
+            ui.end_row();
+
+            ui.label(tr("settings.sftp.key"));
+            ui.add(egui::TextEdit::singleline(&mut s.key_path).hint_text("~/.ssh/id_ed25519"));
+            ui.end_row();
+
+            ui.label(tr("settings.common.password"));
+            ui.add(
+                egui::TextEdit::singleline(&mut s.password)
+                    .password(true)
+                    .hint_text(tr("settings.sftp.password_hint")),
+            );
+            ui.end_row();
+
+            ui.label(tr("settings.sftp.remote_dir"));
+            ui.text_edit_singleline(&mut s.remote_dir);
+            ui.end_row();
+
+            ui.label(tr("settings.sftp.limit"));
+            ui.add(egui::DragValue::new(&mut s.bwlimit_kbps).speed(50))
+                .on_hover_text(tr("settings.sftp.limit_hint"));
+            ui.end_row();
+
+            ui.label(tr("settings.sftp.interface"));
+            ui.add(
+                egui::TextEdit::singleline(&mut s.require_interface)
+                    .hint_text(tr("settings.sftp.interface_hint")),
+            );
+            ui.end_row();
+        });
+
+        if !s.conditions_met() {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                tr_args(
+                    "settings.sftp.waiting",
+                    &[("interface", &s.require_interface)],
+                ),
+            );
+        }
+    }
+
+    #[cfg(feature = "s3")]
+    fn s3_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.offload.s3;
+
+        ui.checkbox(&mut s.enabled, tr("settings.common.auto_upload"));
+        egui::Grid::new("s3_grid").num_columns(2).show(ui, |ui| {
+            ui.label(tr("settings.s3.endpoint"));
+            ui.add(
+                egui::TextEdit::singleline(&mut s.endpoint)
+                    .hint_text(tr("settings.s3.endpoint_hint")),
+            );
+            ui.end_row();
+
+            ui.label(tr("settings.s3.region"));
+            ui.text_edit_singleline(&mut s.region);
+            ui.end_row();
+
+            ui.label(tr("settings.s3.bucket"));
+            ui.text_edit_singleline(&mut s.bucket);
+            ui.end_row();
+
+            ui.label(tr("settings.s3.access_key"));
+            ui.text_edit_singleline(&mut s.access_key);
+            ui.end_row();
+
+            ui.label(tr("settings.s3.secret_key"));
+            ui.add(egui::TextEdit::singleline(&mut s.secret_key).password(true));
+            ui.end_row();
+
+            ui.label(tr("settings.s3.key_template"));
+            ui.text_edit_singleline(&mut s.key_template)
+                .on_hover_text(tr("settings.s3.key_template_hint"));
+            ui.end_row();
+        });
+    }
+}
+
+/// 标题随语言变化, 用固定的 id 保持展开状态.
+fn header(id: &str, title: &'static str) -> egui::CollapsingHeader {
+    egui::CollapsingHeader::new(title).id_salt(id)
+}
+
+fn decklink_grid(ui: &mut egui::Ui, d: &mut DeckLinkConfig, available: bool) {
+    egui::Grid::new("decklink_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label(tr("settings.decklink.device"));
+            ui.add(egui::DragValue::new(&mut d.device_number).range(0..=15));
+            ui.end_row();
+
+            ui.label(tr("settings.decklink.connection"));
+            egui::ComboBox::from_id_salt("decklink_connection")
+                .selected_text(d.connection.label())
+                .show_ui(ui, |ui| {
+                    for connection in Connection::ALL {
+                        ui.selectable_value(&mut d.connection, connection, connection.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label(tr("settings.decklink.mode"));
+            let mode_label = MODES
+                .iter()
+                .find(|(nick, _)| *nick == d.mode)
+                .map_or(d.mode.as_str(), |(_, label)| label);
+            egui::ComboBox::from_id_salt("decklink_mode")
+                .selected_text(mode_label)
+                .show_ui(ui, |ui| {
+                    for (nick, label) in MODES {
+                        ui.selectable_value(&mut d.mode, nick.to_string(), *label);
+                    }
+                });
+            ui.end_row();
+
+            ui.label(tr("settings.decklink.embedded_audio"));
+            egui::ComboBox::from_id_salt("decklink_channels")
+                .selected_text(channels_label(d.channels))
+                .show_ui(ui, |ui| {
+                    for channels in CHANNEL_COUNTS {
+                        ui.selectable_value(&mut d.channels, channels, channels_label(channels));
+                    }
+                });
+            ui.end_row();
+
+            ui.label(tr("settings.decklink.record_channels"));
+            ui.add_enabled_ui(d.pairs() > 1, |ui| {
+                d.audio_pair = d.audio_pair.clamp(1, d.pairs().max(1));
+                egui::ComboBox::from_id_salt("decklink_pair")
+                    .selected_text(pair_label(d.audio_pair))
+                    .show_ui(ui, |ui| {
+                        for pair in 1..=d.pairs() {
+                            ui.selectable_value(&mut d.audio_pair, pair, pair_label(pair));
+                        }
+                    });
+            });
+            ui.end_row();
+        });
+    if !available {
+        ui.colored_label(egui::Color32::YELLOW, tr("settings.decklink.missing"));
+    }
+}
+
+/// 例如 "Ch 3–4".
+fn pair_label(pair: u32) -> String {
+    tr_args(
+        "settings.decklink.pair",
+        &[("first", &(pair * 2 - 1)), ("last", &(pair * 2))],
+    )
+}
+
+fn channels_label(channels: u32) -> String {
+    tr_args("settings.decklink.channels", &[("count", &channels)])
+}
+
+/// 返回图案是否改变, 图案可以在运行中切换.
+fn test_source_grid(ui: &mut egui::Ui, t: &mut TestSourceConfig) -> bool {
+    let before = t.pattern;
+    egui::Grid::new("test_source_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label(tr("settings.test_source.pattern"));
+            egui::ComboBox::from_id_salt("test_pattern")
+                .selected_text(t.pattern.label())
+                .show_ui(ui, |ui| {
+                    for p in TestPattern::ALL {
+                        ui.selectable_value(&mut t.pattern, p, p.label());
+                    }
+                })
+                .response
+                .on_hover_text(tr("settings.test_source.pattern_hint"));
+            ui.end_row();
+
+            ui.label(tr("settings.test_source.overlay"));
+            ui.checkbox(&mut t.time_overlay, tr("settings.test_source.running_time"));
+            ui.end_row();
+        });
+    t.pattern != before
+}
+
+fn screen_grid(ui: &mut egui::Ui, s: &mut ScreenConfig, outputs: &[screen::Output]) {
+    egui::Grid::new("screen_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label(tr("settings.screen.backend"));
+            egui::ComboBox::from_id_salt("screen_backend")
+                .selected_text(s.backend.label())
+                .show_ui(ui, |ui| {
+                    for backend in ScreenBackend::ALL {
+                        ui.selectable_value(&mut s.backend, backend, backend.label());
+                    }
+                });
+            ui.end_row();
+
+            // 门户由用户在系统对话框中选择屏幕
+            if s.backend == ScreenBackend::X11 {
+                ui.label(tr("settings.screen.output"));
+                let mut region = s.region.is_some();
+                ui.add_enabled_ui(!region, |ui| {
+                    egui::ComboBox::from_id_salt("screen_output")
+                        .selected_text(
+                            s.output
+                                .as_deref()
+                                .unwrap_or(tr("settings.screen.whole_screen")),
+                        )
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut s.output,
+                                None,
+                                tr("settings.screen.whole_screen"),
+                            );
+                            for output in outputs {
+                                ui.selectable_value(
+                                    &mut s.output,
+                                    Some(output.name.clone()),
+                                    format!(
+                                        "{} ({}×{})",
+                                        output.name, output.region.width, output.region.height
+                                    ),
+                                );
+                            }
+                        });
+                });
+                ui.end_row();
+
+                ui.label(tr("settings.screen.region"));
+                if ui
+                    .checkbox(&mut region, tr("settings.screen.custom"))
+                    .changed()
+                {
+                    s.region = region.then_some(Region {
+                        x: 0,
+                        y: 0,
+                        width: 1280,
+                        height: 720,
+                    });
+                }
+                ui.end_row();
+                if let Some(r) = &mut s.region {
+                    ui.label("");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut r.x).prefix("x "));
+                        ui.add(egui::DragValue::new(&mut r.y).prefix("y "));
+                        ui.add(egui::DragValue::new(&mut r.width).range(16..=8192));
+                        ui.label("×");
+                        ui.add(egui::DragValue::new(&mut r.height).range(16..=8192));
+                    });
+                    ui.end_row();
+                }
+            }
+
+            ui.label(tr("settings.screen.cursor"));
+            ui.checkbox(&mut s.show_cursor, tr("settings.screen.show_cursor"));
+            ui.end_row();
+
+            ui.label(tr("settings.screen.frame_rate"));
+            ui.add(
+                egui::DragValue::new(&mut s.fps)
+                    .range(1..=60)
+                    .suffix(" fps"),
+            );
+            ui.end_row();
+        });
+}
+
+fn bit_depth_label(depth: BitDepth) -> &'static str {
+    match depth {
+        BitDepth::Auto => tr("settings.recording.bit_depth_auto"),
+        BitDepth::Eight => "8 bit",
+        BitDepth::Ten => "10 bit",
+    }
+}
+
+fn frame_rate_label(mode: FrameRateMode) -> String {
+    match mode {
+        FrameRateMode::Source => tr("settings.recording.frame_rate_source").to_string(),
+        FrameRateMode::Fixed(fps) => format!("{} fps", fps),
+        FrameRateMode::Variable => tr("settings.recording.frame_rate_variable").to_string(),
+    }
+}
+
+fn timecode_label(source: TimecodeSource) -> &'static str {
+    match source {
+        TimecodeSource::TimeOfDay => tr("settings.recording.timecode_time_of_day"),
+        TimecodeSource::Zero => tr("settings.recording.timecode_zero"),
+    }
+}
@@ -0,0 +1,72 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+use crate::video::sharpen::{MAX_RADIUS, SharpenFilter, SharpenTarget};
+
+impl CameraApp {
+    /// 每帧调用, 锐化参数变了就发给 gst 线程. 预设也保存锐化参数, 切换预设时同样会发送.
+    /// 预览管线重建后重新发送. 录制中只有强度和半径立即作用于这段录像,
+    /// 开关和作用的分支从下一段开始.
+    pub(super) fn sync_sharpen(&mut self) {
+        let sharpen = self.recording.sharpen;
+        if self.sent_sharpen != Some(sharpen) {
+            self.recorder.set_sharpen(sharpen);
+            self.sent_sharpen = Some(sharpen);
+        }
+    }
+
+    /// 设置面板: 锐化开关, 强度, 半径, 作用的分支和大致的 CPU 占用.
+    pub(super) fn sharpen_section(&mut self, ui: &mut egui::Ui) {
+        let filter = SharpenFilter::detect();
+        let recording = self.recorder.is_recording();
+        let s = &mut self.recording.sharpen;
+        ui.add_enabled(
+            filter.is_some(),
+            egui::Checkbox::new(&mut s.enabled, tr("settings.recording.sharpen")),
+        )
+        .on_hover_text(tr("settings.recording.sharpen_hint"));
+        let Some(filter) = filter else {
+            ui.colored_label(self.theme.warning, tr("settings.recording.sharpen_missing"));
+            return;
+        };
+        if !s.enabled {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.recording.sharpen_amount"));
+            ui.add(egui::Slider::new(&mut s.amount, 0.0..=1.0).fixed_decimals(2));
+            ui.label(tr("settings.recording.sharpen_radius"));
+            ui.add(
+                egui::Slider::new(&mut s.radius, 0.1..=MAX_RADIUS)
+                    .fixed_decimals(1)
+                    .suffix(" px"),
+            );
+        });
+        ui.horizontal(|ui| {
+            for target in SharpenTarget::ALL {
+                let key = match target {
+                    SharpenTarget::Recording => "settings.recording.sharpen_recording",
+                    SharpenTarget::Both => "settings.recording.sharpen_both",
+                };
+                ui.radio_value(&mut s.target, target, tr(key));
+            }
+        });
+        // 只锐化录像时画面不是预览的样子, 要放大检查白边得作用于两个分支
+        let hint = match s.target {
+            SharpenTarget::Recording if recording => "settings.recording.sharpen_next_clip",
+            SharpenTarget::Recording => "settings.recording.sharpen_recording_hint",
+            SharpenTarget::Both => "settings.recording.sharpen_both_hint",
+        };
+        ui.weak(tr(hint));
+        // tee 之前按采集的分辨率处理, 这里用录像的分辨率估算
+        let percent = filter.cpu_percent(s, self.recording.resolution);
+        ui.weak(tr_args(
+            "settings.recording.sharpen_cpu",
+            &[
+                ("filter", &filter.element()),
+                ("percent", &(percent.round() as u32)),
+            ],
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::i18n::{tr, tr_args};
+use crate::keymap::{self, Action, Keymap};
+
+impl CameraApp {
+    /// 每帧调用, 执行按下的快捷键. 屏幕锁定或正在录入新快捷键时不响应,
+    /// 输入框有焦点时只响应输入框不使用的键.
+    pub(super) fn dispatch_shortcuts(&mut self, ctx: &egui::Context) {
+        if self.locked {
+            return;
+        }
+        if let Some(action) = self.capturing_shortcut {
+            self.capture_shortcut(ctx, action);
+            return;
+        }
+        // 倒计时中按任意键取消, 这个键不再触发其他操作
+        if self.countdown.is_some() && ctx.input(|i| i.events.iter().any(is_key_press)) {
+            self.cancel_countdown();
+            return;
+        }
+        let typing = ctx.wants_keyboard_input();
+        let actions = ctx.input_mut(|i| self.keymap.pressed(i, typing));
+        for action in actions {
+            // 从键盘停止录制时按设置需要按住或再按一次
+            if action == Action::ToggleRecord
+                && self.recorder.is_recording()
+                && let Some(shortcut) = self.keymap.get(action)
+            {
+                self.key_stop(shortcut.logical_key);
+                continue;
+            }
+            self.run_action(ctx, action);
+        }
+    }
+
+    /// 快捷键和远程控制共用.
+    pub(crate) fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        tracing::debug!(%action, "action");
+        match action {
+            Action::ToggleRecord if self.recorder.is_recording() => self.request_stop(false),
+            Action::ToggleRecord => self.toggle_recording(),
+            Action::Snapshot => {
+                self.recorder
+                    .snapshot(&self.recording, &self.slate, &self.snapshot)
+            }
+            Action::Burst => self
+                .recorder
+                .burst(&self.recording, &self.slate, &self.snapshot),
+            Action::PunchIn => self.toggle_punch_in(),
+            Action::SwapPip => self.swap_pip(),
+            Action::Multiview => {
+                if !self.source_names.is_empty() {
+                    self.toggle_multiview();
+                }
+            }
+            Action::ProgramOutput => self.toggle_program_window(),
+            Action::Browser => self.browser.open = !self.browser.open,
+            Action::Console => self.show_console = !self.show_console,
+            Action::NightTheme => self.toggle_night_theme(ctx),
+            Action::LockScreen => self.lock_screen(),
+            Action::AeLock => self.toggle_ae_lock(),
+            Action::IsoUp => self.change_iso(1),
+            Action::IsoDown => self.change_iso(-1),
+            Action::ShutterFaster => self.change_shutter(1),
+            Action::ShutterSlower => self.change_shutter(-1),
+            Action::ZoomIn => self.step_zoom(1),
+            Action::ZoomOut => self.step_zoom(-1),
+            Action::Fullscreen => self.toggle_fullscreen(ctx),
+            Action::Reference => self.toggle_reference(),
+            Action::CopyFrame => self.copy_frame(),
+            Action::Marker => self.add_marker(None),
+            Action::Freeze => self.toggle_freeze(),
+            Action::Bars => self.toggle_bars(),
+            Action::DumpGraph => self.recorder.dump_graph(&self.recording.output_dir),
+            _ => {
+                if let Some(index) = action.profile_index() {
+                    self.apply_profile(index);
+                } else if let Some(index) = action.source_index() {
+                    self.cut_to(index);
+                }
+            }
+        }
+    }
+
+    /// 下一个按下的键成为 `action` 的快捷键, Escape 取消.
+    fn capture_shortcut(&mut self, ctx: &egui::Context, action: Action) {
+        let pressed = ctx.input_mut(|i| {
+            let index = i.events.iter().position(|e| {
+                matches!(
+                    e,
+                    egui::Event::Key {
+                        pressed: true,
+                        repeat: false,
+                        ..
+                    }
+                )
+            })?;
+            match i.events.remove(index) {
+                egui::Event::Key { key, modifiers, .. } => {
+                    Some(egui::KeyboardShortcut::new(modifiers, key))
+                }
+                _ => None,
+            }
+        });
+        let Some(shortcut) = pressed else {
+            return;
+        };
+        self.capturing_shortcut = None;
+        if shortcut.logical_key == egui::Key::Escape && shortcut.modifiers.is_none() {
+            return;
+        }
+        // 只保留 Ctrl, Alt 和 Shift, Ctrl 统一记为 command
+        let m = shortcut.modifiers;
+        let mut modifiers = egui::Modifiers::NONE;
+        if m.command || m.ctrl || m.mac_cmd {
+            modifiers |= egui::Modifiers::COMMAND;
+        }
+        if m.alt {
+            modifiers |= egui::Modifiers::ALT;
+        }
+        if m.shift {
+            modifiers |= egui::Modifiers::SHIFT;
+        }
+        let shortcut = egui::KeyboardShortcut::new(modifiers, shortcut.logical_key);
+        tracing::info!(%action, shortcut = %keymap::format_shortcut(&shortcut), "shortcut changed");
+        self.keymap.set(action, Some(shortcut));
+        let conflicts = self.keymap.conflicts(action);
+        if !conflicts.is_empty() {
+            self.toasts
+                .push(ToastLevel::Warning, conflict_text(&conflicts));
+        }
+    }
+
+    /// 设置面板: 每个操作的快捷键, 冲突的用红色标出.
+    pub(super) fn keymap_section(&mut self, ui: &mut egui::Ui) {
+        ui.weak(tr("settings.keymap.hint"));
+        let mut capture = None;
+        let mut clear = None;
+        let mut reset = None;
+        egui::Grid::new("keymap_grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for action in Action::ALL {
+                    ui.label(action.label());
+                    let conflicts = self.keymap.conflicts(action);
+                    let text = if self.capturing_shortcut == Some(action) {
+                        egui::RichText::new(tr("settings.keymap.press")).italics()
+                    } else {
+                        let text = match self.keymap.get(action) {
+                            Some(shortcut) => keymap::format_shortcut(&shortcut),
+                            None => tr("settings.keymap.none").to_string(),
+                        };
+                        let text = egui::RichText::new(text).monospace();
+                        if conflicts.is_empty() {
+                            text
+                        } else {
+                            text.color(self.theme.alert)
+                        }
+                    };
+                    let mut response =
+                        ui.add(egui::Button::new(text).min_size(egui::vec2(90.0, 0.0)));
+                    if !conflicts.is_empty() {
+                        response = response.on_hover_text(conflict_text(&conflicts));
+                    }
+                    if response.clicked() {
+                        capture = Some(action);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.small_button(tr("settings.keymap.clear")).clicked() {
+                            clear = Some(action);
+                        }
+                        if ui.small_button(tr("settings.keymap.reset")).clicked() {
+                            reset = Some(action);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        if let Some(action) = capture {
+            self.capturing_shortcut = Some(action);
+        }
+        if let Some(action) = clear {
+            self.keymap.set(action, None);
+        }
+        if let Some(action) = reset {
+            self.keymap.reset(action);
+        }
+        if ui.button(tr("settings.keymap.reset_all")).clicked() {
+            self.keymap = Keymap::default();
+        }
+    }
+}
+
+fn conflict_text(conflicts: &[Action]) -> String {
+    let names: Vec<_> = conflicts.iter().map(|a| a.label()).collect();
+    tr_args(
+        "settings.keymap.conflict",
+        &[("actions", &names.join(", "))],
+    )
+}
+
+fn is_key_press(event: &egui::Event) -> bool {
+    matches!(
+        event,
+        egui::Event::Key {
+            pressed: true,
+            repeat: false,
+            ..
+        }
+    )
+}
@@ -0,0 +1,87 @@
+use eframe::egui;
+use std::time::Duration;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+
+/// 超过这么久没有新的预览帧时认为信号中断.
+/// DeckLink 的 SDI 信号中断时采集卡不再输出帧, 同样在这里处理.
+const NO_SIGNAL_AFTER: Duration = Duration::from_secs(1);
+/// 显示的画面比这更旧时变暗并显示已经过去多久, 能看出比 NO SIGNAL 更短的卡顿.
+pub(super) const STALE_AFTER: Duration = Duration::from_millis(500);
+/// 画面过时后更新经过时间的间隔.
+const STALE_REPAINT: Duration = Duration::from_millis(100);
+
+impl CameraApp {
+    /// 只在收到过第一帧之后判断, 启动时不会误报.
+    pub(super) fn check_signal(&mut self, ctx: &egui::Context) {
+        let Some(at) = self.last_frame_at else {
+            return;
+        };
+        let lost = at.elapsed() >= NO_SIGNAL_AFTER;
+        if lost != self.signal_lost {
+            if lost {
+                tracing::warn!("video signal lost");
+            } else {
+                tracing::info!("video signal restored");
+            }
+            self.signal_lost = lost;
+        }
+        // 没有新帧时界面不会重绘, 需要自己安排下一次检查
+        if !lost {
+            ctx.request_repaint_after(NO_SIGNAL_AFTER.saturating_sub(at.elapsed()));
+        }
+        match self.frame_age() {
+            Some(age) if age >= STALE_AFTER => ctx.request_repaint_after(STALE_REPAINT),
+            Some(age) => ctx.request_repaint_after(STALE_AFTER - age),
+            None => {}
+        }
+    }
+
+    /// 主窗口显示的画面从采集到现在经过的时间. 定格时不算.
+    pub(super) fn frame_age(&self) -> Option<Duration> {
+        if self.is_frozen() {
+            return None;
+        }
+        self.frame_captured_at.map(|at| at.elapsed())
+    }
+
+    /// 画面过时时稍微变暗, 并显示最后一帧是多久以前的. 收到新帧后立即消失.
+    pub(super) fn stale_overlay(&self, ui: &egui::Ui, rect: egui::Rect) {
+        let Some(age) = self.frame_age().filter(|age| *age >= STALE_AFTER) else {
+            return;
+        };
+        let painter = ui.painter();
+        // NO SIGNAL 已经把画面压暗了
+        if !self.signal_lost {
+            painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(60));
+        }
+        let text = tr_args(
+            "banner.stale",
+            &[("age", &format!("{:.1}", age.as_secs_f32()))],
+        );
+        let galley =
+            painter.layout_no_wrap(text, egui::FontId::proportional(16.0), self.theme.warning);
+        let pos = rect.right_top()
+            + egui::vec2(
+                -super::EDGE_MARGIN - galley.size().x,
+                super::EDGE_MARGIN * 3.0,
+            );
+        let background = egui::Rect::from_min_size(pos, galley.size()).expand(6.0);
+        painter.rect_filled(background, 4.0, egui::Color32::from_black_alpha(180));
+        painter.galley(pos, galley, self.theme.warning);
+    }
+}
+
+/// 盖在最后一帧上的 NO SIGNAL 提示.
+pub(super) fn no_signal_overlay(ui: &egui::Ui, rect: egui::Rect) {
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+    painter.text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        tr("banner.no_signal"),
+        egui::FontId::proportional(48.0),
+        egui::Color32::WHITE,
+    );
+}
@@ -0,0 +1,64 @@
+use eframe::egui;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::file::session_log::Severity;
+use crate::i18n::tr;
+
+impl CameraApp {
+    /// 每帧调用, 设置变了就发给 gst 线程. 管线重建后重新发送.
+    pub(super) fn sync_silence(&mut self) {
+        if self.sent_silence != Some(self.silence) {
+            self.recorder.set_silence(self.silence);
+            self.sent_silence = Some(self.silence);
+        }
+    }
+
+    pub(super) fn on_silence(&mut self, silent: bool) {
+        self.silent = silent;
+        if silent {
+            self.toasts.push(ToastLevel::Error, tr("toast.silence"));
+            self.log_session(Severity::Warning, tr("session.silence_started"));
+        } else {
+            self.log_session(Severity::Info, tr("session.silence_ended"));
+        }
+    }
+
+    /// 无声期间一直显示, 不能关闭.
+    pub(super) fn silence_banner(&self, ctx: &egui::Context) {
+        if !self.silent || !self.recorder.is_recording() {
+            return;
+        }
+        egui::Area::new(egui::Id::new("silence_banner"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 170.0))
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(self.theme.alert)
+                    .corner_radius(6.0)
+                    .inner_margin(egui::Margin::symmetric(16, 8))
+                    .show(ui, |ui| {
+                        ui.label(
+                            egui::RichText::new(tr("banner.silence"))
+                                .size(24.0)
+                                .strong()
+                                .color(egui::Color32::WHITE),
+                        );
+                    });
+            });
+    }
+
+    /// 设置面板: 无声提示的开关, 阈值和时长.
+    pub(super) fn silence_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.silence;
+        ui.checkbox(&mut s.enabled, tr("settings.recording.silence"))
+            .on_hover_text(tr("settings.recording.silence_hint"));
+        ui.add_enabled_ui(s.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr("settings.recording.silence_threshold"));
+                ui.add(egui::Slider::new(&mut s.threshold_db, -80.0..=-20.0).suffix(" dBFS"));
+                ui.label(tr("settings.recording.silence_after"));
+                ui.add(egui::Slider::new(&mut s.after_secs, 1..=120).suffix(" s"));
+            });
+        });
+    }
+}
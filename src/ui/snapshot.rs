@@ -0,0 +1,61 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+use crate::keymap::{self, Action};
+use crate::video::snapshot::{MAX_BURST, StillFormat, StillResolution};
+
+impl CameraApp {
+    /// 设置面板: 静帧的格式和分辨率, 连拍帧数. 下一次拍摄时生效.
+    pub(super) fn snapshot_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.snapshot;
+        egui::Grid::new("snapshot_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(tr("settings.snapshot.format"));
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut s.format, StillFormat::Png, "PNG");
+                    ui.radio_value(&mut s.format, StillFormat::Jpeg, "JPEG");
+                });
+                ui.end_row();
+
+                if s.format == StillFormat::Jpeg {
+                    ui.label(tr("settings.snapshot.quality"));
+                    ui.add(egui::Slider::new(&mut s.jpeg_quality, 50..=100));
+                    ui.end_row();
+                }
+
+                ui.label(tr("settings.snapshot.resolution"));
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut s.resolution,
+                        StillResolution::Source,
+                        tr("settings.snapshot.source"),
+                    );
+                    ui.radio_value(
+                        &mut s.resolution,
+                        StillResolution::Preview,
+                        tr("settings.snapshot.preview"),
+                    );
+                });
+                ui.end_row();
+
+                ui.label(tr("settings.snapshot.burst"));
+                ui.add(egui::DragValue::new(&mut s.burst_frames).range(2..=MAX_BURST));
+                ui.end_row();
+            });
+        let key = |action| {
+            self.keymap
+                .get(action)
+                .map(|shortcut| keymap::format_shortcut(&shortcut))
+                .unwrap_or_else(|| "—".to_string())
+        };
+        ui.weak(tr_args(
+            "settings.snapshot.hint",
+            &[
+                ("still", &key(Action::Snapshot)),
+                ("burst", &key(Action::Burst)),
+            ],
+        ));
+    }
+}
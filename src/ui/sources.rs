@@ -0,0 +1,112 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::tr;
+use crate::keymap::{self, Action};
+use crate::video::switcher::AlternateSource;
+
+impl CameraApp {
+    pub(super) fn cut_to(&mut self, index: usize) {
+        if index >= self.source_names.len() || index == self.active_source {
+            return;
+        }
+        tracing::info!(source = %self.source_names[index], "cut");
+        self.recorder.switch_source(index);
+    }
+
+    /// 顶部栏中的源列表, 点击立即切换, 当前的源高亮. 只有一路时不显示.
+    pub(super) fn source_buttons(&mut self, ui: &mut egui::Ui) {
+        let mut chosen = None;
+        for (index, name) in self.source_names.iter().enumerate() {
+            let text = match Action::source(index).and_then(|a| self.keymap.get(a)) {
+                Some(shortcut) => format!("{} {}", keymap::format_shortcut(&shortcut), name),
+                None => name.clone(),
+            };
+            let active = index == self.active_source;
+            let button = egui::Button::new(egui::RichText::new(text).strong())
+                .selected(active)
+                .fill(if active {
+                    egui::Color32::from_rgb(180, 30, 30)
+                } else {
+                    egui::Color32::from_black_alpha(160)
+                });
+            if ui.add(button).on_hover_text(tr("top.cut_hint")).clicked() {
+                chosen = Some(index);
+            }
+        }
+        if let Some(index) = chosen {
+            self.cut_to(index);
+        }
+    }
+
+    /// 设置面板: 其余各路的采集源和切换方式.
+    pub(super) fn switching_section(&mut self, ui: &mut egui::Ui) {
+        let s = &mut self.capture.switching;
+        let mut remove = None;
+        egui::Grid::new("switching_grid")
+            .num_columns(4)
+            .show(ui, |ui| {
+                for (index, source) in s.sources.iter_mut().enumerate() {
+                    ui.label(format!("{}", index + 2));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut source.name)
+                            .hint_text(tr("settings.sources.name_hint"))
+                            .desired_width(80.0),
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut source.video_source)
+                            .hint_text("v4l2src device=/dev/video2"),
+                    );
+                    if ui.small_button(tr("settings.sources.remove")).clicked() {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
+
+                    if s.audio_follows_video {
+                        ui.label("");
+                        ui.label(tr("settings.common.audio"));
+                        let mut audio = source.audio_source.clone().unwrap_or_default();
+                        if ui
+                            .add(
+                                egui::TextEdit::singleline(&mut audio)
+                                    .hint_text(tr("settings.sources.audio_hint")),
+                            )
+                            .changed()
+                        {
+                            source.audio_source = (!audio.is_empty()).then_some(audio);
+                        }
+                        ui.end_row();
+                    }
+                }
+            });
+        if let Some(index) = remove {
+            s.sources.remove(index);
+        }
+        if ui.button(tr("settings.sources.add")).clicked() {
+            s.sources.push(AlternateSource::default());
+        }
+        if !s.sources.is_empty() {
+            ui.checkbox(
+                &mut s.audio_follows_video,
+                tr("settings.sources.audio_follows_video"),
+            );
+            ui.checkbox(&mut s.pause_inactive, tr("settings.sources.pause_inactive"))
+                .on_hover_text(tr("settings.sources.pause_inactive_hint"));
+            ui.checkbox(&mut s.multiview, tr("settings.sources.multiview"))
+                .on_hover_text(tr("settings.sources.multiview_hint"));
+            ui.horizontal(|ui| {
+                ui.label(tr("settings.common.format"));
+                ui.add(egui::DragValue::new(&mut s.width).range(16..=7680));
+                ui.label("×");
+                ui.add(egui::DragValue::new(&mut s.height).range(16..=4320));
+                ui.add(
+                    egui::DragValue::new(&mut s.framerate)
+                        .range(1..=120)
+                        .suffix(" fps"),
+                );
+            })
+            .response
+            .on_hover_text(tr("settings.sources.format_hint"));
+        }
+    }
+}
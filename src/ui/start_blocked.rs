@@ -0,0 +1,114 @@
+use eframe::egui;
+use std::path::PathBuf;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::file::preflight::{BlockReason, Suggestion};
+use crate::file::session_log::Severity;
+use crate::i18n::{tr, tr_args};
+use crate::video::record::RecordSettings;
+
+/// gst 线程拦下的录制, 等待用户选择修复办法.
+pub(super) struct StartBlocked {
+    settings: Box<RecordSettings>,
+    reason: BlockReason,
+    suggestion: Suggestion,
+    /// 换一个目录时输入的路径.
+    folder: String,
+}
+
+impl CameraApp {
+    pub(super) fn on_start_blocked(
+        &mut self,
+        settings: Box<RecordSettings>,
+        reason: BlockReason,
+        suggestion: Suggestion,
+    ) {
+        self.log_session(
+            Severity::Warning,
+            tr_args("session.start_blocked", &[("reason", &reason)]),
+        );
+        self.start_blocked = Some(StartBlocked {
+            folder: self.recording.output_dir.display().to_string(),
+            settings,
+            reason,
+            suggestion,
+        });
+    }
+
+    pub(super) fn start_blocked_dialog(&mut self, ctx: &egui::Context) {
+        let Some(blocked) = &mut self.start_blocked else {
+            return;
+        };
+        let mut accept = false;
+        let mut cancel = false;
+        let response = egui::Modal::new(egui::Id::new("start_blocked")).show(ctx, |ui| {
+            ui.heading(tr("start_blocked.title"));
+            let (key, path) = match &blocked.reason {
+                BlockReason::Exists(path) => ("start_blocked.exists", path),
+                BlockReason::MissingDir(dir) => ("start_blocked.missing_dir", dir),
+                BlockReason::NotWritable(dir) => ("start_blocked.not_writable", dir),
+                BlockReason::ReadOnly(dir) => ("start_blocked.read_only", dir),
+            };
+            ui.label(tr_args(key, &[("path", &path.display())]));
+            ui.add_space(8.0);
+            let label = match &blocked.suggestion {
+                Suggestion::Rename(path) => tr_args(
+                    "start_blocked.rename",
+                    &[(
+                        "name",
+                        &path.file_name().unwrap_or_default().to_string_lossy(),
+                    )],
+                ),
+                Suggestion::CreateDir(_) => tr("start_blocked.create_dir").to_string(),
+                Suggestion::ChooseFolder => {
+                    ui.horizontal(|ui| {
+                        ui.label(tr("start_blocked.folder"));
+                        ui.text_edit_singleline(&mut blocked.folder);
+                    });
+                    tr("start_blocked.use_folder").to_string()
+                }
+            };
+            ui.horizontal(|ui| {
+                accept = ui.button(label).clicked();
+                cancel = ui.button(tr("start_blocked.cancel")).clicked();
+            });
+        });
+        if cancel || response.should_close() {
+            self.start_blocked = None;
+        } else if accept && let Some(blocked) = self.start_blocked.take() {
+            self.resolve_start_blocked(blocked);
+        }
+    }
+
+    /// 按建议修复后用原来的设置重新开始. 仍然有问题时 gst 线程会再次拦下.
+    fn resolve_start_blocked(&mut self, blocked: StartBlocked) {
+        let mut settings = *blocked.settings;
+        match blocked.suggestion {
+            Suggestion::Rename(path) => settings.filepath = path,
+            Suggestion::CreateDir(dir) => {
+                if let Err(e) = std::fs::create_dir_all(&dir) {
+                    tracing::warn!(dir = %dir.display(), "could not create directory: {}", e);
+                    self.toasts.push(
+                        ToastLevel::Error,
+                        tr_args("toast.create_dir_failed", &[("error", &e)]),
+                    );
+                    return;
+                }
+            }
+            Suggestion::ChooseFolder => {
+                let dir = PathBuf::from(blocked.folder.trim());
+                if let Some(name) = settings.filepath.file_name() {
+                    settings.filepath = dir.join(name);
+                }
+                self.set_output_dir(dir);
+            }
+        }
+        if self.bars_block_recording() {
+            return;
+        }
+        tracing::info!(path = %settings.filepath.display(), "retrying blocked recording");
+        self.recorder.start_with(settings);
+        self.last_clip = None;
+    }
+}
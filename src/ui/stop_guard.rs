@@ -0,0 +1,144 @@
+use eframe::egui;
+use std::time::Instant;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::config::StopGuard;
+use crate::i18n::{tr, tr_args};
+use crate::recorder::{STOP_CONFIRM_WINDOW, STOP_HOLD};
+
+const RING_RADIUS: f32 = 48.0;
+
+impl CameraApp {
+    /// 录制中按下录制键. 按住模式下要一直按着, 直到 [tick_stop_guard] 停止录制.
+    pub(super) fn key_stop(&mut self, key: egui::Key) {
+        match self.behavior.stop_guard {
+            StopGuard::Off => self.stop_now(),
+            // 按住时的自动重复不重新计时
+            StopGuard::Hold if self.stop_hold.is_none() => {
+                self.stop_hold = Some((Instant::now(), key));
+            }
+            StopGuard::Hold => {}
+            StopGuard::Confirm => self.request_stop(false),
+        }
+    }
+
+    /// 远程控制, 手柄和 GPIO 按钮的停止请求. 无法按住, 所以按住模式下也用两步确认.
+    pub(crate) fn request_stop(&mut self, force: bool) {
+        let guarded = !force && self.behavior.stop_guard != StopGuard::Off;
+        if !guarded || self.stop_confirm.request(Instant::now()) {
+            self.stop_now();
+        } else {
+            tracing::info!("stop requested, waiting for confirmation");
+        }
+    }
+
+    fn stop_now(&mut self) {
+        self.stop_hold = None;
+        self.stop_confirm.reset();
+        self.recorder.stop(&mut self.slate);
+    }
+
+    /// 每帧调用: 按够时间后停止, 提前松开则取消.
+    pub(super) fn tick_stop_guard(&mut self, ctx: &egui::Context) {
+        if !self.recorder.is_recording() {
+            self.stop_hold = None;
+            self.stop_confirm.reset();
+            return;
+        }
+        let Some((since, key)) = self.stop_hold else {
+            if self.stop_confirm.is_armed(Instant::now()) {
+                ctx.request_repaint_after(STOP_CONFIRM_WINDOW);
+            }
+            return;
+        };
+        if since.elapsed() >= STOP_HOLD {
+            self.stop_now();
+        } else if !ctx.input(|i| i.key_down(key)) {
+            self.stop_hold = None;
+            self.toasts.push(
+                ToastLevel::Info,
+                tr_args("toast.hold_to_stop", &[("key", &key.name())]),
+            );
+        } else {
+            ctx.request_repaint();
+        }
+    }
+
+    /// 预览中央: 按住时的进度圈, 或等待第二次确认的提示.
+    pub(super) fn stop_guard_overlay(&self, ctx: &egui::Context) {
+        let hold = self
+            .stop_hold
+            .map(|(since, _)| since.elapsed().as_secs_f32() / STOP_HOLD.as_secs_f32());
+        let armed = self.stop_confirm.is_armed(Instant::now());
+        if hold.is_none() && !armed {
+            return;
+        }
+        egui::Area::new(egui::Id::new("stop_guard"))
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    if let Some(fraction) = hold {
+                        let (rect, _) = ui.allocate_exact_size(
+                            egui::Vec2::splat(RING_RADIUS * 2.0),
+                            egui::Sense::hover(),
+                        );
+                        let center = rect.center();
+                        let points = (0..=48)
+                            .map(|i| {
+                                let angle = -std::f32::consts::FRAC_PI_2
+                                    + std::f32::consts::TAU * fraction.min(1.0) * i as f32 / 48.0;
+                                center + RING_RADIUS * egui::vec2(angle.cos(), angle.sin())
+                            })
+                            .collect();
+                        ui.painter().circle_filled(
+                            center,
+                            RING_RADIUS,
+                            egui::Color32::from_black_alpha(160),
+                        );
+                        ui.painter().add(egui::Shape::line(
+                            points,
+                            egui::Stroke::new(6.0, self.theme.rec),
+                        ));
+                    }
+                    let text = if hold.is_some() {
+                        tr("banner.keep_holding")
+                    } else {
+                        tr("banner.confirm_stop")
+                    };
+                    ui.label(
+                        egui::RichText::new(text)
+                            .strong()
+                            .color(self.theme.text)
+                            .background_color(egui::Color32::from_black_alpha(160)),
+                    );
+                });
+            });
+    }
+
+    /// 设置面板: 防误停.
+    pub(super) fn behavior_section(&mut self, ui: &mut egui::Ui) {
+        let b = &mut self.behavior;
+        ui.label(tr("settings.behavior.stop_guard"));
+        ui.radio_value(
+            &mut b.stop_guard,
+            StopGuard::Off,
+            tr("settings.behavior.off"),
+        );
+        ui.radio_value(
+            &mut b.stop_guard,
+            StopGuard::Hold,
+            tr_args("settings.behavior.hold", &[("ms", &STOP_HOLD.as_millis())]),
+        );
+        ui.radio_value(
+            &mut b.stop_guard,
+            StopGuard::Confirm,
+            tr_args(
+                "settings.behavior.confirm",
+                &[("seconds", &STOP_CONFIRM_WINDOW.as_secs())],
+            ),
+        );
+        ui.weak(tr("settings.behavior.hint"));
+    }
+}
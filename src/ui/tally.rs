@@ -0,0 +1,88 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use crate::config::TallyBorder;
+use crate::i18n::tr;
+
+const BORDER_WIDTH: f32 = 4.0;
+/// 明暗变化一次的周期.
+const PULSE_PERIOD: f32 = 2.0;
+/// 闪光从最亮淡到消失的时间, 不影响判断曝光.
+const FLASH: Duration = Duration::from_millis(300);
+/// 满强度时闪光的最大不透明度, 只是提示, 不遮住画面.
+const FLASH_ALPHA: f32 = 0.45;
+
+impl CameraApp {
+    /// 开始录制时闪红色, 文件成功封装后闪绿色.
+    pub(super) fn flash_tally(&mut self, color: egui::Color32) {
+        if self.tally_settings.flash {
+            self.tally_flash = Some((Instant::now(), color));
+        }
+    }
+
+    /// 画在预览之上: 录制中的红框和正在淡出的闪光.
+    pub(super) fn tally_overlay(&mut self, ui: &egui::Ui, rect: egui::Rect) {
+        let intensity = self.tally_settings.intensity.clamp(0.0, 1.0);
+        if let Some((since, color)) = self.tally_flash {
+            let t = since.elapsed().as_secs_f32() / FLASH.as_secs_f32();
+            if t >= 1.0 {
+                self.tally_flash = None;
+            } else {
+                let alpha = (1.0 - t) * FLASH_ALPHA * intensity;
+                ui.painter()
+                    .rect_filled(rect, 0.0, color.gamma_multiply(alpha));
+                ui.ctx().request_repaint();
+            }
+        }
+        if !self.recorder.is_recording() {
+            return;
+        }
+        let alpha = match self.tally_settings.border {
+            TallyBorder::Off => return,
+            TallyBorder::Solid => intensity,
+            TallyBorder::Pulse => {
+                ui.ctx().request_repaint();
+                let phase = ui.input(|i| i.time) as f32 / PULSE_PERIOD * std::f32::consts::TAU;
+                intensity * (0.65 + 0.35 * phase.sin())
+            }
+        };
+        ui.painter().rect_stroke(
+            rect.shrink(BORDER_WIDTH / 2.0),
+            0.0,
+            egui::Stroke::new(BORDER_WIDTH, self.theme.rec.gamma_multiply(alpha)),
+            egui::StrokeKind::Middle,
+        );
+    }
+
+    /// 设置面板: 录制时的红框和闪光.
+    pub(super) fn tally_section(&mut self, ui: &mut egui::Ui) {
+        let t = &mut self.tally_settings;
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.display.tally_border"));
+            ui.radio_value(
+                &mut t.border,
+                TallyBorder::Off,
+                tr("settings.display.tally_off"),
+            );
+            ui.radio_value(
+                &mut t.border,
+                TallyBorder::Solid,
+                tr("settings.display.tally_solid"),
+            );
+            ui.radio_value(
+                &mut t.border,
+                TallyBorder::Pulse,
+                tr("settings.display.tally_pulse"),
+            );
+        });
+        ui.checkbox(&mut t.flash, tr("settings.display.tally_flash"))
+            .on_hover_text(tr("settings.display.tally_flash_hint"));
+        ui.add_enabled_ui(t.border != TallyBorder::Off || t.flash, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr("settings.display.tally_intensity"));
+                ui.add(egui::Slider::new(&mut t.intensity, 0.2..=1.0));
+            });
+        });
+    }
+}
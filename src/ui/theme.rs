@@ -0,0 +1,139 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::config::{ThemeName, ThemeSettings};
+use crate::i18n::tr;
+
+/// 主界面叠加层使用的颜色. 绘制时从这里取色, 不直接写 `Color32` 常量.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Theme {
+    pub text: egui::Color32,
+    pub label: egui::Color32,
+    /// 顶部和底部栏的底色.
+    pub bar: egui::Color32,
+    /// 按钮等圆形控件的底色.
+    pub button: egui::Color32,
+    pub good: egui::Color32,
+    pub warning: egui::Color32,
+    pub alert: egui::Color32,
+    /// 录制标记在所有主题中都是最亮的红色, 并且反色显示, 与其他红色文字区分.
+    pub rec: egui::Color32,
+    pub rec_text: egui::Color32,
+    /// 叠加层整体的不透明度.
+    pub overlay_opacity: f32,
+    /// 绘制预览时的着色, 白色表示原样显示.
+    pub preview_tint: egui::Color32,
+}
+
+/// 预览亮度的下限, 再暗就看不清画面了.
+pub(super) const MIN_PREVIEW_BRIGHTNESS: f32 = 0.1;
+
+impl Theme {
+    pub fn new(settings: &ThemeSettings) -> Self {
+        let theme = match settings.name {
+            ThemeName::Standard => Self {
+                text: egui::Color32::WHITE,
+                label: egui::Color32::LIGHT_GRAY,
+                bar: egui::Color32::from_black_alpha(180),
+                button: egui::Color32::from_black_alpha(160),
+                good: egui::Color32::GREEN,
+                warning: egui::Color32::YELLOW,
+                alert: egui::Color32::RED,
+                rec: egui::Color32::from_rgb(230, 40, 40),
+                rec_text: egui::Color32::WHITE,
+                overlay_opacity: 1.0,
+                preview_tint: egui::Color32::WHITE,
+            },
+            // 暗红色, 保护暗适应
+            ThemeName::Night => Self {
+                text: egui::Color32::from_rgb(170, 30, 20),
+                label: egui::Color32::from_rgb(110, 20, 15),
+                bar: egui::Color32::from_black_alpha(230),
+                button: egui::Color32::from_black_alpha(220),
+                good: egui::Color32::from_rgb(120, 60, 20),
+                warning: egui::Color32::from_rgb(170, 70, 0),
+                alert: egui::Color32::from_rgb(210, 0, 0),
+                rec: egui::Color32::from_rgb(255, 30, 30),
+                rec_text: egui::Color32::BLACK,
+                overlay_opacity: 0.7,
+                preview_tint: if settings.dim_preview {
+                    egui::Color32::from_gray(110)
+                } else {
+                    egui::Color32::WHITE
+                },
+            },
+        };
+        let brightness = settings
+            .preview_brightness
+            .clamp(MIN_PREVIEW_BRIGHTNESS, 1.0);
+        let [r, g, b, a] = theme.preview_tint.to_array();
+        let scale = |c: u8| (c as f32 * brightness).round() as u8;
+        Self {
+            preview_tint: egui::Color32::from_rgba_premultiplied(scale(r), scale(g), scale(b), a),
+            ..theme
+        }
+    }
+
+    /// 设置面板等窗口的外观.
+    fn visuals(&self, name: ThemeName) -> egui::Visuals {
+        let mut visuals = egui::Visuals::dark();
+        if name == ThemeName::Night {
+            visuals.override_text_color = Some(self.text);
+            visuals.window_fill = egui::Color32::BLACK;
+            visuals.panel_fill = egui::Color32::BLACK;
+            visuals.extreme_bg_color = egui::Color32::BLACK;
+            visuals.selection.bg_fill = egui::Color32::from_rgb(80, 10, 5);
+            visuals.hyperlink_color = self.text;
+        }
+        visuals
+    }
+}
+
+impl CameraApp {
+    /// 主题改变后调用, 同时更新窗口的外观.
+    pub fn apply_theme(&mut self, ctx: &egui::Context) {
+        self.theme = Theme::new(&self.theme_settings);
+        ctx.set_visuals(self.theme.visuals(self.theme_settings.name));
+        tracing::debug!(theme = ?self.theme_settings.name, "theme");
+    }
+
+    /// N 键在标准和夜间主题之间切换.
+    pub(super) fn toggle_night_theme(&mut self, ctx: &egui::Context) {
+        self.theme_settings.name = match self.theme_settings.name {
+            ThemeName::Standard => ThemeName::Night,
+            ThemeName::Night => ThemeName::Standard,
+        };
+        self.apply_theme(ctx);
+    }
+
+    /// 设置面板: 主题.
+    pub(super) fn theme_section(&mut self, ui: &mut egui::Ui) {
+        let before = self.theme_settings.clone();
+        let t = &mut self.theme_settings;
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.display.theme"));
+            ui.selectable_value(
+                &mut t.name,
+                ThemeName::Standard,
+                tr("settings.display.standard"),
+            );
+            ui.selectable_value(&mut t.name, ThemeName::Night, tr("settings.display.night"));
+        });
+        ui.add_enabled(
+            t.name == ThemeName::Night,
+            egui::Checkbox::new(&mut t.dim_preview, tr("settings.display.dim_preview")),
+        );
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.display.preview_brightness"));
+            ui.add(
+                egui::Slider::new(&mut t.preview_brightness, MIN_PREVIEW_BRIGHTNESS..=1.0)
+                    .fixed_decimals(2),
+            );
+        })
+        .response
+        .on_hover_text(tr("settings.display.preview_brightness_hint"));
+        if self.theme_settings != before {
+            self.apply_theme(ui.ctx());
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use eframe::egui;
+use std::path::Path;
+use std::time::Instant;
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::file::sidecar::Sidecar;
+use crate::i18n::{tr, tr_args};
+use crate::thermal::ThermalLevel;
+
+impl CameraApp {
+    /// 读取温度, 升到警告或临界级别时提示降低分辨率.
+    pub(super) fn check_temperature(&mut self) {
+        let Some(level) = self.thermal.poll(Instant::now()) else {
+            return;
+        };
+        let celsius = self.thermal.current().unwrap_or_default();
+        match level {
+            ThermalLevel::Normal => {}
+            ThermalLevel::Warm => self.toasts.push(
+                ToastLevel::Warning,
+                tr_args(
+                    "toast.thermal_warm",
+                    &[("celsius", &format!("{:.0}", celsius))],
+                ),
+            ),
+            ThermalLevel::Hot => {
+                tracing::warn!(celsius, "soc temperature critical");
+                self.toasts.push(
+                    ToastLevel::Error,
+                    tr_args(
+                        "toast.thermal_hot",
+                        &[("celsius", &format!("{:.0}", celsius))],
+                    ),
+                );
+            }
+        }
+    }
+
+    /// 顶部栏中的温度, 没有 thermal zone 时不显示.
+    pub(super) fn temperature_label(&self, ui: &mut egui::Ui) {
+        let Some(celsius) = self.thermal.current() else {
+            return;
+        };
+        let color = match self.thermal.level() {
+            ThermalLevel::Normal => egui::Color32::LIGHT_GRAY,
+            ThermalLevel::Warm => egui::Color32::YELLOW,
+            ThermalLevel::Hot => egui::Color32::RED,
+        };
+        ui.label(egui::RichText::new(format!("{:.0} °C", celsius)).color(color))
+            .on_hover_text(tr("top.temperature_hint"));
+        ui.add_space(12.0);
+    }
+
+    /// 把这次录制期间的温度范围补充到 sidecar.
+    pub(super) fn save_temperature(&mut self, clip: &Path) {
+        let Some(range) = self.thermal.finish_recording() else {
+            return;
+        };
+        if let Err(e) = Sidecar::update(clip, |meta| meta.temperature = Some(range)) {
+            tracing::warn!(path = %clip.display(), "could not save temperature: {}", e);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+/// 提示显示时长.
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+/// 最多同时显示的提示数量, 多余的丢弃最旧的.
+const MAX_TOASTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ToastLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+struct Toast {
+    level: ToastLevel,
+    text: String,
+    shown_at: Instant,
+}
+
+/// 底部参数栏上方的短暂提示.
+pub(super) struct Toasts {
+    items: Vec<Toast>,
+    /// 还没有写入会话记录的警告和错误.
+    unlogged: Vec<(ToastLevel, String)>,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            unlogged: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, level: ToastLevel, text: impl Into<String>) {
+        if self.items.len() >= MAX_TOASTS {
+            self.items.remove(0);
+        }
+        let text = text.into();
+        if level != ToastLevel::Info {
+            self.unlogged.push((level, text.clone()));
+        }
+        self.items.push(Toast {
+            level,
+            text,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// 取出上次调用之后出现的警告和错误.
+    pub fn take_unlogged(&mut self) -> Vec<(ToastLevel, String)> {
+        std::mem::take(&mut self.unlogged)
+    }
+
+    /// 给以 `prefix` 开头的提示追加内容并重新计时, 已经消失时重新显示.
+    pub fn amend(&mut self, prefix: &str, level: ToastLevel, suffix: &str) {
+        match self.items.iter_mut().find(|t| t.text.starts_with(prefix)) {
+            Some(toast) => {
+                toast.text = format!("{}, {}", toast.text, suffix);
+                if level != ToastLevel::Info {
+                    toast.level = level;
+                    self.unlogged
+                        .push((level, format!("{} — {}", prefix, suffix)));
+                }
+                toast.shown_at = Instant::now();
+            }
+            None => self.push(level, format!("{} — {}", prefix, suffix)),
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, bottom_margin: f32) {
+        self.items.retain(|t| t.shown_at.elapsed() < TOAST_DURATION);
+        if self.items.is_empty() {
+            return;
+        }
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(
+                egui::Align2::CENTER_BOTTOM,
+                egui::vec2(0.0, -bottom_margin - 10.0),
+            )
+            .interactable(false)
+            .show(ctx, |ui| {
+                for toast in &self.items {
+                    let color = match toast.level {
+                        ToastLevel::Info => egui::Color32::WHITE,
+                        ToastLevel::Warning => egui::Color32::YELLOW,
+                        ToastLevel::Error => egui::Color32::RED,
+                    };
+                    egui::Frame::new()
+                        .fill(egui::Color32::from_black_alpha(200))
+                        .corner_radius(6.0)
+                        .inner_margin(egui::Margin::symmetric(12, 6))
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(&toast.text).color(color).size(16.0));
+                        });
+                }
+            });
+    }
+}
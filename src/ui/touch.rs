@@ -0,0 +1,148 @@
+use eframe::egui;
+use std::time::Instant;
+
+use super::theme::Theme;
+use super::{BOTTOM_BAR_HEIGHT, CameraApp, EDGE_MARGIN};
+use crate::i18n::tr;
+use crate::recorder::STOP_HOLD;
+
+/// 录制按钮和旁边小按钮的直径, 单位是点.
+const RECORD_DIAMETER: f32 = 72.0;
+const SMALL_DIAMETER: f32 = 52.0;
+const BUTTON_GAP: f32 = 12.0;
+/// 录制按钮上方至少留给顶部栏的高度.
+const TOP_BAR_HEIGHT: f32 = 60.0;
+
+impl CameraApp {
+    /// 预览右下角的触摸按钮: 录制, 静帧和设置, 叠加在画面上方, 不覆盖底部参数区.
+    /// 快捷键照常可用.
+    pub(super) fn touch_controls(&mut self, ui: &mut egui::Ui, rect: egui::Rect) {
+        // 窗口很矮时整列缩小, 仍然放在参数区上方
+        let column = RECORD_DIAMETER + (SMALL_DIAMETER + BUTTON_GAP) * 2.0 + BUTTON_GAP;
+        let room = rect.height() - BOTTOM_BAR_HEIGHT - TOP_BAR_HEIGHT - EDGE_MARGIN;
+        let shrink = (room / column).clamp(0.5, 1.0);
+        let record = RECORD_DIAMETER * shrink;
+        let small = SMALL_DIAMETER * shrink;
+        let gap = BUTTON_GAP * shrink;
+
+        let right = rect.max.x - EDGE_MARGIN - record / 2.0;
+        let record_center = egui::pos2(
+            right,
+            rect.max.y - BOTTOM_BAR_HEIGHT - EDGE_MARGIN - record / 2.0,
+        );
+        let snapshot_center = record_center - egui::vec2(0.0, (record + small) / 2.0 + gap);
+        let settings_center = snapshot_center - egui::vec2(0.0, small + gap);
+
+        self.record_button(ui, record_center, record / 2.0);
+        if round_button(
+            ui,
+            &self.theme,
+            "touch_snapshot",
+            snapshot_center,
+            small / 2.0,
+            crate::icons::ICON_CAMERA,
+        )
+        .on_hover_text(tr("action.snapshot"))
+        .clicked()
+        {
+            self.recorder
+                .snapshot(&self.recording, &self.slate, &self.snapshot);
+        }
+        if round_button(
+            ui,
+            &self.theme,
+            "touch_settings",
+            settings_center,
+            small / 2.0,
+            crate::icons::ICON_SETTINGS,
+        )
+        .on_hover_text(tr("settings.title"))
+        .clicked()
+        {
+            self.show_settings = !self.show_settings;
+        }
+    }
+
+    /// 红圈, 录制中填满. 停止要按住 [STOP_HOLD], 圈上显示进度.
+    fn record_button(&mut self, ui: &mut egui::Ui, center: egui::Pos2, radius: f32) {
+        let rect = egui::Rect::from_center_size(center, egui::Vec2::splat(radius * 2.0));
+        let response = ui
+            .interact(
+                rect,
+                ui.id().with("touch_record"),
+                egui::Sense::click_and_drag(),
+            )
+            .on_hover_text(if self.recorder.is_recording() {
+                tr("top.touch_stop_hint")
+            } else {
+                tr("top.touch_record_hint")
+            });
+        let recording = self.recorder.is_recording();
+        let mut hold = None;
+        if recording {
+            if response.is_pointer_button_down_on() {
+                let since = *self.stop_hold_since.get_or_insert_with(Instant::now);
+                let held = since.elapsed();
+                if held >= STOP_HOLD {
+                    self.stop_hold_since = None;
+                    self.toggle_recording();
+                } else {
+                    hold = Some(held.as_secs_f32() / STOP_HOLD.as_secs_f32());
+                    ui.ctx().request_repaint();
+                }
+            } else {
+                self.stop_hold_since = None;
+            }
+        } else if response.clicked() {
+            self.toggle_recording();
+        }
+
+        let painter = ui.painter();
+        painter.circle_filled(center, radius, self.theme.button);
+        painter.circle_stroke(
+            center,
+            radius * 0.85,
+            egui::Stroke::new(radius * 0.12, self.theme.rec),
+        );
+        if recording {
+            painter.circle_filled(center, radius * 0.6, self.theme.rec);
+        }
+        if let Some(fraction) = hold {
+            let points = (0..=48)
+                .map(|i| {
+                    let angle = -std::f32::consts::FRAC_PI_2
+                        + std::f32::consts::TAU * fraction * i as f32 / 48.0;
+                    center + radius * 0.97 * egui::vec2(angle.cos(), angle.sin())
+                })
+                .collect();
+            painter.add(egui::Shape::line(
+                points,
+                egui::Stroke::new(radius * 0.1, self.theme.text),
+            ));
+        }
+    }
+}
+
+/// 半透明深色底的圆形图标按钮, 在亮的画面上也看得清.
+fn round_button(
+    ui: &mut egui::Ui,
+    theme: &Theme,
+    id: &str,
+    center: egui::Pos2,
+    radius: f32,
+    icon: &'static str,
+) -> egui::Response {
+    let rect = egui::Rect::from_center_size(center, egui::Vec2::splat(radius * 2.0));
+    let response = ui.interact(rect, ui.id().with(id), egui::Sense::click());
+    let fill = if response.is_pointer_button_down_on() {
+        theme.bar
+    } else {
+        theme.button
+    };
+    ui.painter().circle_filled(center, radius, fill);
+    egui::Image::new(icon).tint(theme.text).paint_at(
+        ui,
+        egui::Rect::from_center_size(center, egui::Vec2::splat(radius)),
+    );
+    response
+}
@@ -0,0 +1,79 @@
+use eframe::egui;
+
+use super::CameraApp;
+use crate::i18n::{tr, tr_args};
+use crate::offload::TransferState;
+
+impl CameraApp {
+    /// 上传队列面板: 排队中/进行中/已完成/失败的条目.
+    pub(super) fn transfers_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_transfers;
+        egui::Window::new(tr("transfers.title"))
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let items = self.uploader.items();
+                if items.is_empty() {
+                    ui.label(tr("transfers.empty"));
+                }
+                for item in &items {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} → {}", item.file_name(), item.target.label()));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            match &item.state {
+                                TransferState::Queued => match &item.last_error {
+                                    Some(e) => {
+                                        ui.label(tr_args(
+                                            "transfers.retry_pending",
+                                            &[("n", &item.attempts)],
+                                        ))
+                                        .on_hover_text(e);
+                                    }
+                                    None => {
+                                        ui.label(tr("transfers.queued"));
+                                    }
+                                },
+                                TransferState::Active => {
+                                    ui.label(format!(
+                                        "{}/s",
+                                        crate::file::format_size(item.speed as u64)
+                                    ));
+                                    if let Some((done, total)) = item.parts {
+                                        ui.label(tr_args(
+                                            "transfers.part",
+                                            &[("done", &done), ("total", &total)],
+                                        ));
+                                    }
+                                    ui.add(
+                                        egui::ProgressBar::new(item.fraction())
+                                            .desired_width(120.0)
+                                            .show_percentage(),
+                                    );
+                                }
+                                TransferState::Completed => {
+                                    ui.colored_label(egui::Color32::GREEN, tr("transfers.done"));
+                                }
+                                TransferState::Failed(reason) => {
+                                    if ui.small_button(tr("transfers.retry")).clicked() {
+                                        self.uploader.retry(&item.path, item.target);
+                                    }
+                                    ui.colored_label(egui::Color32::RED, tr("transfers.failed"))
+                                        .on_hover_text(reason);
+                                }
+                            }
+                        });
+                    });
+                }
+                if items.iter().any(|i| !i.is_pending()) {
+                    ui.separator();
+                    if ui.button(tr("transfers.clear_finished")).clicked() {
+                        self.uploader.clear_finished();
+                    }
+                }
+                if items.iter().any(|i| i.is_pending()) {
+                    ui.ctx().request_repaint();
+                }
+            });
+        self.show_transfers = open;
+    }
+}
@@ -0,0 +1,132 @@
+use eframe::egui;
+use std::path::Path;
+
+use super::toast::ToastLevel;
+use super::{CameraApp, param_widget};
+use crate::i18n::{tr, tr_args};
+use crate::video::balance::{self, KELVIN_RANGE, WhiteBalance};
+use crate::video::controls::WhiteBalanceControl;
+use crate::video::libcamera::LibcameraControl;
+
+impl CameraApp {
+    /// 打开设备的色温控件并回到自动白平衡. 不支持自动的摄像头保持当前色温.
+    pub(super) fn open_white_balance(&mut self, device: Option<&Path>) {
+        self.wb_control = device.and_then(WhiteBalanceControl::open);
+        self.white_balance = match &self.wb_control {
+            Some(control) if control.set_auto().is_err() => control
+                .kelvin()
+                .map_or(WhiteBalance::Auto, WhiteBalance::Kelvin),
+            _ => WhiteBalance::Auto,
+        };
+        if let Some(kelvin) = self.white_balance.kelvin() {
+            self.wb_kelvin = kelvin;
+        }
+        self.recorder
+            .set_channel_gains(balance::ChannelGains::default());
+    }
+
+    /// 有色温控件时设置摄像头, 否则在管线中调整红蓝增益.
+    fn set_white_balance(&mut self, white_balance: WhiteBalance) {
+        let result = match &self.wb_control {
+            Some(control) => match white_balance {
+                WhiteBalance::Auto => control.set_auto(),
+                WhiteBalance::GreyCard(gains) => {
+                    control.set_kelvin(balance::kelvin_for_gains(gains))
+                }
+                _ => control.set_kelvin(white_balance.kelvin().unwrap_or(self.wb_kelvin)),
+            },
+            None if self
+                .libcamera
+                .is_some_and(|c| c.handles_white_balance(white_balance)) =>
+            {
+                self.recorder
+                    .set_channel_gains(balance::ChannelGains::default());
+                self.recorder
+                    .set_libcamera_control(LibcameraControl::WhiteBalance(white_balance));
+                Ok(())
+            }
+            None => {
+                self.recorder.set_channel_gains(white_balance.gains());
+                Ok(())
+            }
+        };
+        match result {
+            Ok(()) => {
+                tracing::info!(white_balance = %white_balance.name(), "white balance changed");
+                self.white_balance = white_balance;
+                if let Some(kelvin) = white_balance.kelvin() {
+                    self.wb_kelvin = kelvin;
+                }
+            }
+            Err(e) => tracing::warn!(
+                white_balance = %white_balance.name(),
+                "could not set white balance: {}",
+                e
+            ),
+        }
+    }
+
+    /// 用预览帧中央的灰卡计算白平衡. 预览已经经过当前的白平衡, 所以在当前增益上叠加.
+    pub(super) fn sample_grey_card(&mut self, image: &egui::ColorImage) {
+        self.grey_card_pending = false;
+        let Some(measured) = balance::grey_card_gains(image) else {
+            self.toasts.push(ToastLevel::Warning, tr("toast.grey_card"));
+            return;
+        };
+        let current = match (&self.wb_control, self.white_balance) {
+            (Some(control), WhiteBalance::Auto) => {
+                balance::kelvin_gains(control.kelvin().unwrap_or(self.wb_kelvin))
+            }
+            (_, white_balance) => white_balance.gains(),
+        };
+        self.set_white_balance(WhiteBalance::GreyCard(current.then(measured)));
+    }
+
+    /// 底部栏的白平衡, 点击选择预设, 色温或灰卡.
+    pub(super) fn white_balance_widget(&mut self, ui: &mut egui::Ui) {
+        let response = param_widget(ui, &self.theme, tr("bar.wb"), &self.white_balance.label())
+            .interact(egui::Sense::click())
+            .on_hover_text(tr_args(
+                "bar.wb_hint",
+                &[("name", &self.white_balance.name())],
+            ));
+        let mut chosen = None;
+        egui::Popup::menu(&response)
+            .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+            .show(|ui| {
+                for preset in WhiteBalance::PRESETS {
+                    if ui
+                        .selectable_label(self.white_balance == preset, preset.name())
+                        .clicked()
+                    {
+                        chosen = Some(preset);
+                    }
+                }
+                ui.separator();
+                let slider = ui.add(
+                    egui::Slider::new(&mut self.wb_kelvin, KELVIN_RANGE)
+                        .step_by(100.0)
+                        .suffix(" K"),
+                );
+                if slider.changed() {
+                    chosen = Some(WhiteBalance::Kelvin(self.wb_kelvin));
+                }
+                ui.separator();
+                let label = if self.grey_card_pending {
+                    tr("bar.grey_card_sampling")
+                } else {
+                    tr("bar.grey_card")
+                };
+                if ui
+                    .button(label)
+                    .on_hover_text(tr("bar.grey_card_hint"))
+                    .clicked()
+                {
+                    self.grey_card_pending = true;
+                }
+            });
+        if let Some(white_balance) = chosen {
+            self.set_white_balance(white_balance);
+        }
+    }
+}
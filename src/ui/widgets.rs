@@ -0,0 +1,3 @@
+//! 几个功能共用的控件.
+
+pub(super) mod stepper;
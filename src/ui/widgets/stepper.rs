@@ -0,0 +1,201 @@
+use eframe::egui;
+
+use crate::ui::param_widget;
+use crate::ui::theme::Theme;
+
+/// 拖动这么多点换一档.
+const DRAG_STEP: f32 = 24.0;
+/// 滚动这么多点换一档, 大约是鼠标滚轮的一格.
+const SCROLL_STEP: f32 = 40.0;
+/// 快速选择中每行的档位数.
+const COLUMNS: usize = 4;
+/// 背景比文字向外多出的距离.
+const PADDING: f32 = 4.0;
+
+/// 底部栏上按档位调整的数值, 例如 ISO 和快门.
+/// 悬停时滚动, 拖动或获得焦点后按方向键逐档调整, 点击 (或 Enter) 打开快速选择.
+pub(in crate::ui) struct Stepper<'a> {
+    id_salt: &'static str,
+    label: &'a str,
+    value: &'a str,
+    options: &'a [String],
+    selected: Option<usize>,
+    fine: Option<(&'a mut bool, &'a str)>,
+}
+
+pub(in crate::ui) struct StepperResponse {
+    pub response: egui::Response,
+    /// 本帧应该切换的档位数, 为正时增大.
+    pub steps: i32,
+    /// 在快速选择中点击的项的序号.
+    pub picked: Option<usize>,
+}
+
+/// 滚动和拖动不满一格时的累计距离.
+#[derive(Clone, Copy, Default)]
+struct Pending {
+    scroll: f32,
+    drag: f32,
+}
+
+impl<'a> Stepper<'a> {
+    pub fn new(id_salt: &'static str, label: &'a str, value: &'a str) -> Self {
+        Self {
+            id_salt,
+            label,
+            value,
+            options: &[],
+            selected: None,
+            fine: None,
+        }
+    }
+
+    /// 快速选择中的各项和当前选中的一项. 没有选项时点击不打开弹出框.
+    pub fn options(mut self, options: &'a [String], selected: Option<usize>) -> Self {
+        self.options = options;
+        self.selected = selected;
+        self
+    }
+
+    /// 在快速选择中显示细调开关.
+    pub fn fine(mut self, fine: &'a mut bool, label: &'a str) -> Self {
+        self.fine = Some((fine, label));
+        self
+    }
+
+    pub fn show(self, ui: &mut egui::Ui, theme: &Theme) -> StepperResponse {
+        let id = ui.make_persistent_id(self.id_salt);
+        let popup_id = id.with("popup");
+        let background = ui.painter().add(egui::Shape::Noop);
+        let rect = param_widget(ui, theme, self.label, self.value).rect;
+        let response = ui.interact(rect, id, egui::Sense::click_and_drag());
+
+        let mut steps = self.pending_steps(ui, &response, id);
+        let mut toggle = response.clicked();
+        if response.has_focus() {
+            // 方向键用来调整, 不移动焦点
+            ui.memory_mut(|m| {
+                m.set_focus_lock_filter(
+                    id,
+                    egui::EventFilter {
+                        horizontal_arrows: true,
+                        vertical_arrows: true,
+                        ..Default::default()
+                    },
+                )
+            });
+            ui.input(|i| {
+                use egui::Key;
+                for key in [Key::ArrowUp, Key::ArrowRight] {
+                    steps += i.num_presses(key) as i32;
+                }
+                for key in [Key::ArrowDown, Key::ArrowLeft] {
+                    steps -= i.num_presses(key) as i32;
+                }
+                toggle |= i.key_pressed(Key::Enter) || i.key_pressed(Key::Space);
+            });
+        }
+
+        if self.options.is_empty() {
+            toggle = false;
+        }
+        if toggle {
+            egui::Popup::toggle_id(ui.ctx(), popup_id);
+        }
+        let open = egui::Popup::is_id_open(ui.ctx(), popup_id);
+        self.paint_background(ui, &response, background, open);
+
+        let mut picked = None;
+        let Self {
+            options,
+            selected,
+            fine,
+            ..
+        } = self;
+        egui::Popup::menu(&response)
+            .id(popup_id)
+            // 点击已经在上面处理过
+            .open_memory(None)
+            .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+            .show(|ui| {
+                egui::Grid::new(id.with("options")).show(ui, |ui| {
+                    for (index, option) in options.iter().enumerate() {
+                        if ui
+                            .selectable_label(selected == Some(index), option)
+                            .clicked()
+                        {
+                            picked = Some(index);
+                        }
+                        if (index + 1) % COLUMNS == 0 {
+                            ui.end_row();
+                        }
+                    }
+                });
+                if let Some((fine, label)) = fine {
+                    ui.separator();
+                    ui.checkbox(fine, label);
+                }
+            });
+        if picked.is_some() {
+            egui::Popup::close_id(ui.ctx(), popup_id);
+        }
+
+        StepperResponse {
+            response,
+            steps,
+            picked,
+        }
+    }
+
+    /// 悬停时的滚动和按住拖动 (向右或向上为增大), 满一格才换档.
+    fn pending_steps(&self, ui: &egui::Ui, response: &egui::Response, id: egui::Id) -> i32 {
+        let mut pending = ui.data(|d| d.get_temp::<Pending>(id).unwrap_or_default());
+        if response.hovered() {
+            pending.scroll += ui.input(|i| i.raw_scroll_delta.y);
+        } else {
+            pending.scroll = 0.0;
+        }
+        if response.dragged() {
+            let delta = response.drag_delta();
+            pending.drag += delta.x - delta.y;
+        } else {
+            pending.drag = 0.0;
+        }
+        let scroll = (pending.scroll / SCROLL_STEP).trunc();
+        pending.scroll -= scroll * SCROLL_STEP;
+        let drag = (pending.drag / DRAG_STEP).trunc();
+        pending.drag -= drag * DRAG_STEP;
+        ui.data_mut(|d| d.insert_temp(id, pending));
+        (scroll + drag) as i32
+    }
+
+    /// 悬停, 按下或打开快速选择时加底色, 有焦点时加边框.
+    fn paint_background(
+        &self,
+        ui: &egui::Ui,
+        response: &egui::Response,
+        background: egui::layers::ShapeIdx,
+        open: bool,
+    ) {
+        let active = open || response.is_pointer_button_down_on() || response.dragged();
+        if !(active || response.hovered() || response.has_focus()) {
+            return;
+        }
+        let visuals = ui.style().interact_selectable(response, active);
+        let stroke = if response.has_focus() {
+            ui.visuals().selection.stroke
+        } else {
+            egui::Stroke::NONE
+        };
+        ui.painter().set(
+            background,
+            egui::epaint::RectShape::new(
+                response.rect.expand(PADDING),
+                visuals.corner_radius,
+                visuals.weak_bg_fill,
+                stroke,
+                egui::StrokeKind::Outside,
+            ),
+        );
+    }
+}
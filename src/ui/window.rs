@@ -0,0 +1,91 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::i18n::tr;
+
+/// kiosk 模式下指针这么久不动就隐藏光标.
+const KIOSK_CURSOR_HIDE: Duration = Duration::from_secs(3);
+/// kiosk 模式下按住 Esc 这么久后询问是否退出.
+const KIOSK_EXIT_HOLD: Duration = Duration::from_secs(3);
+
+impl CameraApp {
+    /// 由 `--kiosk` 调用: 始终全屏, 只能通过退出确认关闭窗口.
+    pub fn enable_kiosk(&mut self) {
+        self.kiosk = true;
+        self.window.fullscreen = true;
+    }
+
+    /// kiosk 模式下不允许退出全屏.
+    pub(super) fn toggle_fullscreen(&mut self, ctx: &egui::Context) {
+        if self.kiosk {
+            return;
+        }
+        let fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(self.window.fullscreen));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+    }
+
+    /// 每帧在所有控件之后调用: 记录全屏状态和窗口位置, kiosk 模式下隐藏光标和处理长按退出.
+    pub(super) fn track_window(&mut self, ctx: &egui::Context) {
+        let (fullscreen, position, moved, escape) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (
+                viewport.fullscreen,
+                viewport.outer_rect.map(|r| r.min.round()),
+                i.events
+                    .iter()
+                    .any(|e| matches!(e, egui::Event::PointerMoved(_) | egui::Event::Touch { .. })),
+                i.key_down(egui::Key::Escape),
+            )
+        });
+        // 也跟随窗口管理器的切换, 重启后恢复同样的状态
+        if let Some(fullscreen) = fullscreen
+            && !self.kiosk
+        {
+            self.window.fullscreen = fullscreen;
+        }
+        if let Some(position) = position {
+            self.window.position = Some([position.x, position.y]);
+        }
+
+        if !self.kiosk {
+            return;
+        }
+        let now = Instant::now();
+        if moved {
+            self.pointer_moved_at = now;
+        }
+        if now - self.pointer_moved_at >= KIOSK_CURSOR_HIDE {
+            ctx.set_cursor_icon(egui::CursorIcon::None);
+        }
+        if !escape {
+            self.kiosk_exit_since = None;
+        } else if now - *self.kiosk_exit_since.get_or_insert(now) >= KIOSK_EXIT_HOLD {
+            self.kiosk_exit_since = None;
+            self.confirm_quit();
+        }
+    }
+
+    /// kiosk 模式下窗口管理器请求关闭 (例如 Alt-F4) 时提示如何退出.
+    pub(super) fn kiosk_close_refused(&mut self) {
+        tracing::info!("ignoring close request in kiosk mode");
+        self.toasts.push(ToastLevel::Info, tr("toast.kiosk_quit"));
+    }
+
+    /// 设置面板: 全屏开关.
+    pub(super) fn window_section(&mut self, ui: &mut egui::Ui) {
+        let mut fullscreen = self.window.fullscreen;
+        let response = ui.add_enabled(
+            !self.kiosk,
+            egui::Checkbox::new(&mut fullscreen, tr("settings.display.fullscreen")),
+        );
+        if response.changed() {
+            ui.ctx()
+                .send_viewport_cmd(egui::ViewportCommand::Fullscreen(fullscreen));
+        }
+        if self.kiosk {
+            ui.weak(tr("settings.display.kiosk"));
+        }
+    }
+}
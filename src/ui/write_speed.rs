@@ -0,0 +1,32 @@
+use super::CameraApp;
+use super::toast::ToastLevel;
+use crate::file::session_log::Severity;
+use crate::i18n::{tr, tr_args};
+use crate::video::write_speed::{WriteChange, WriteRate};
+
+impl CameraApp {
+    /// 存储写入跟不上时提示, 持续跟不上时建议降低码率或者换存储.
+    pub(super) fn on_write_speed(&mut self, change: WriteChange, rate: WriteRate) {
+        let written = crate::file::format_size(rate.written_per_sec);
+        let produced = crate::file::format_size(rate.produced_per_sec);
+        let args: [(&str, &dyn std::fmt::Display); 2] =
+            [("written", &written), ("produced", &produced)];
+        match change {
+            WriteChange::Underrun { .. } => {
+                self.write_underrun = true;
+                self.toasts
+                    .push(ToastLevel::Warning, tr_args("toast.write_underrun", &args));
+                self.log_session(Severity::Warning, tr_args("session.write_underrun", &args));
+            }
+            WriteChange::Persistent => {
+                self.toasts
+                    .push(ToastLevel::Error, tr("toast.write_persistent"));
+                self.log_session(Severity::Error, tr("session.write_persistent"));
+            }
+            WriteChange::Recovered => {
+                self.write_underrun = false;
+                self.log_session(Severity::Info, tr("session.write_recovered"));
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use eframe::egui;
+
+use super::camera::scroll_steps;
+use super::{CameraApp, param_widget};
+use crate::i18n::tr;
+use crate::video::zoom::{MAX_ZOOM, MIN_ZOOM};
+
+/// 按键和滚轮每次改变的倍数.
+const ZOOM_STEP: f32 = 0.25;
+/// 平滑过渡的速度, 大约 0.3 秒到达目标.
+const ZOOM_RATE: f32 = 10.0;
+
+impl CameraApp {
+    /// 改变目标倍数, 实际倍数在之后几帧内平滑过渡.
+    pub(super) fn step_zoom(&mut self, steps: i32) {
+        self.zoom_target = (self.zoom_target + steps as f32 * ZOOM_STEP).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// 每帧向目标倍数靠近一点.
+    pub(super) fn animate_zoom(&mut self, ctx: &egui::Context) {
+        let diff = self.zoom_target - self.zoom;
+        if diff == 0.0 {
+            return;
+        }
+        if diff.abs() < 0.005 {
+            self.zoom = self.zoom_target;
+        } else {
+            let dt = ctx.input(|i| i.stable_dt).min(0.1);
+            self.zoom += diff * (1.0 - (-dt * ZOOM_RATE).exp());
+            ctx.request_repaint();
+        }
+        self.recorder.set_zoom(self.zoom);
+    }
+
+    /// 底部栏的数字变焦, 悬停时滚动或按 Ctrl +/- 调整, 点击打开滑块.
+    pub(super) fn zoom_widget(&mut self, ui: &mut egui::Ui) {
+        let response = param_widget(
+            ui,
+            &self.theme,
+            tr("bar.zoom"),
+            &format!("{:.1}×", self.zoom),
+        )
+        .interact(egui::Sense::click())
+        .on_hover_text(tr("bar.zoom_hint"));
+        let steps = scroll_steps(ui, &response, &mut self.zoom_scroll);
+        if steps != 0 {
+            self.step_zoom(steps);
+        }
+        egui::Popup::menu(&response)
+            .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+            .show(|ui| {
+                let mut zoom = self.zoom_target;
+                let slider = ui.add(
+                    egui::Slider::new(&mut zoom, MIN_ZOOM..=MAX_ZOOM)
+                        .step_by(0.1)
+                        .suffix("×"),
+                );
+                // 拖动时直接跟手, 不做过渡
+                if slider.changed() {
+                    self.zoom_target = zoom;
+                    self.zoom = zoom;
+                    self.recorder.set_zoom(zoom);
+                }
+                if ui
+                    .add_enabled(
+                        self.zoom_target > MIN_ZOOM,
+                        egui::Button::new(tr("bar.zoom_reset")),
+                    )
+                    .clicked()
+                {
+                    self.zoom_target = MIN_ZOOM;
+                }
+            });
+    }
+}
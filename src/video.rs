@@ -1,195 +1,912 @@
 use eframe::egui;
+use futures_util::StreamExt;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use gstreamer_app as gst_app;
 use gstreamer_video as gst_video;
 use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
 
+use crate::file::preflight;
+use crate::file::sidecar::SidecarAudio;
+
+pub(crate) mod assist;
+pub(crate) mod av1;
+pub(crate) mod balance;
+pub(crate) mod bars;
+pub(crate) mod beep;
+pub(crate) mod benchmark;
+pub(crate) mod black;
+pub(crate) mod bus;
+mod bypass;
+pub(crate) mod controls;
+pub(crate) mod decklink;
+pub(crate) mod denoise;
+pub(crate) mod devices;
+pub(crate) mod exchange;
+pub(crate) mod ffv1;
+pub(crate) mod gif;
+pub(crate) mod graph;
+pub(crate) mod histogram;
+pub(crate) mod libcamera;
+pub(crate) mod multiview;
+pub(crate) mod overlay;
+pub(crate) mod pattern;
+pub(crate) mod pip;
+pub(crate) mod pipeline;
+pub(crate) mod prores;
 pub(crate) mod record;
+pub(crate) mod rtsp;
+pub(crate) mod screen;
+pub(crate) mod sharpen;
+pub(crate) mod shutter;
+pub(crate) mod silence;
+pub(crate) mod snapshot;
+pub(crate) mod switcher;
+pub(crate) mod timecode;
+mod verify;
+pub(crate) mod write_speed;
+pub(crate) mod zoom;
+
+#[cfg(test)]
+mod tests;
+
+use pipeline::{
+    AUDIO_METER, AudioSettings, CaptureSources, PipelineBuilder, PipelineHandles, PreviewSettings,
+};
+
+/// 退出时等待录像封装完成的最长时间, 超时后直接停止管线.
+const FINALIZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 网络摄像头的统计信息发送给界面的间隔.
+const NETWORK_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 退出时等待 gst 线程结束的最长时间, 比封装的等待稍长.
+pub(crate) const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 检查切换源, 网络统计和录制进度的间隔. 指令和总线消息不受它限制, 到达即处理.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// gst 线程的名称, 崩溃处理据此判断 panic 是否发生在 gst 线程.
+pub(crate) const GST_THREAD: &str = "gst";
+
+/// 一帧预览图像和它被采集的时刻.
+pub(crate) struct PreviewFrame {
+    pub image: egui::ColorImage,
+    /// 由 buffer 的 PTS 换算到本机时钟, 用于估算画面延迟.
+    pub captured_at: Instant,
+    /// appsink 收到的第几帧, 从 1 开始. 界面可能跳过帧, 据此计算采集帧率.
+    pub sequence: u64,
+    /// caps 中协商的帧率, 可变帧率时为 None.
+    pub nominal_fps: Option<f64>,
+}
+
+/// 主预览以外送给界面的画面.
+#[derive(Clone, Default)]
+pub(crate) struct MonitorFrames {
+    /// 多画面的缩略图.
+    pub tiles: multiview::Tiles,
+    /// 只在界面上叠加的画中画小画面.
+    pub inset: Arc<Mutex<Option<egui::ColorImage>>>,
+}
+
+/// [spawn_gst_thread] 启动的线程.
+pub(crate) struct GstThread {
+    handle: std::thread::JoinHandle<()>,
+    /// 线程结束 (包括 panic) 时发送端被丢弃.
+    exited: std::sync::mpsc::Receiver<()>,
+}
+
+impl GstThread {
+    /// 等待线程完成收尾, 最多等 `timeout`. 超时返回 false, 线程随进程结束.
+    pub fn join(self, timeout: Duration) -> bool {
+        match self.exited.recv_timeout(timeout) {
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                warn!(?timeout, "gst thread did not exit in time");
+                false
+            }
+            _ => {
+                let _ = self.handle.join();
+                true
+            }
+        }
+    }
+}
 
-pub fn spawn_gst_thread(
-    buffer: Arc<Mutex<Option<egui::ColorImage>>>,
+/// 返回的线程在收到 [record::RecordCommand::Shutdown] 或指令通道关闭,
+/// 并且正在录制的文件封装完成, 管线停止后结束.
+#[allow(clippy::too_many_arguments)] // 大多是与界面共享的状态
+pub(crate) fn spawn_gst_thread(
+    sources: CaptureSources,
+    buffer: exchange::FrameExchange<PreviewFrame>,
+    monitors: MonitorFrames,
+    overlay: overlay::SharedOverlay,
     audio_level: Arc<Mutex<f32>>,
+    buffer_stats: Arc<Mutex<bus::BufferStats>>,
     mut rec_cmd_rx: mpsc::UnboundedReceiver<record::RecordCommand>,
+    rec_event_tx: mpsc::UnboundedSender<record::RecordEvent>,
+) -> GstThread {
+    let (exited_tx, exited) = std::sync::mpsc::channel();
+    let handle = std::thread::Builder::new()
+        .name(GST_THREAD.to_string())
+        .spawn(move || {
+            let _exited = exited_tx;
+            let _span = tracing::info_span!("pipeline").entered();
+            let mut sources = sources;
+            // 会话要保持到管线结束
+            let _screen_share =
+                sources.screen_portal.take().and_then(
+                    |options| match screen::request_portal_stream(options.show_cursor) {
+                        Ok(stream) => {
+                            sources.video = stream.source(options.fps);
+                            let _ =
+                                rec_event_tx.send(record::RecordEvent::ScreenShared(stream.area));
+                            Some(stream)
+                        }
+                        Err(e) => {
+                            error!("screen share failed: {}", e);
+                            let _ = rec_event_tx.send(record::RecordEvent::Error(format!(
+                                "Screen share failed: {}",
+                                e
+                            )));
+                            None
+                        }
+                    },
+                );
+            // 网络摄像头断开后重新建立整条管线
+            let reconnect = rtsp::is_rtsp(&sources.video);
+            let denoise_filter = denoise::DenoiseFilter::detect();
+            let sharpen_filter = sharpen::SharpenFilter::detect();
+            info!(denoise = ?denoise_filter, sharpen = ?sharpen_filter, "processing filters");
+            let mut attempts = 0;
+            loop {
+                let builder = PipelineBuilder::new(sources.clone())
+                    .preview(PreviewSettings::default())
+                    .audio(AudioSettings::default())
+                    .denoise(denoise_filter)
+                    .sharpen(sharpen_filter);
+                info!(description = %rtsp::redact(&builder.description()), "building pipeline");
+                let (pipeline, handles) = builder.build().expect("Pipeline error");
+                connect_preview(
+                    &handles,
+                    buffer.clone(),
+                    overlay.clone(),
+                    rec_event_tx.clone(),
+                );
+                multiview::connect(&pipeline, monitors.tiles.clone());
+                pip::connect_inset(&pipeline, monitors.inset.clone());
+                let started = Instant::now();
+                let exit = run_loop(
+                    &pipeline,
+                    &handles,
+                    audio_level.clone(),
+                    buffer_stats.clone(),
+                    overlay.clone(),
+                    &mut rec_cmd_rx,
+                    rec_event_tx.clone(),
+                );
+                if !reconnect || !matches!(exit, LoopExit::SourceFailed | LoopExit::Eos) {
+                    break;
+                }
+                if started.elapsed() >= rtsp::STABLE_AFTER {
+                    attempts = 0;
+                }
+                attempts += 1;
+                let delay = rtsp::reconnect_delay(attempts);
+                warn!(attempt = attempts, delay = ?delay, "network camera lost, reconnecting");
+                let _ = rec_event_tx.send(record::RecordEvent::Warning(format!(
+                    "Network camera lost — reconnecting in {}s",
+                    delay.as_secs()
+                )));
+                if !wait_for_reconnect(&mut rec_cmd_rx, &rec_event_tx, delay) {
+                    break;
+                }
+            }
+            info!("gst thread exiting");
+        })
+        .expect("failed to spawn gst thread");
+    GstThread { handle, exited }
+}
+
+/// 等待重连期间没有管线, 录制指令直接报错. 收到退出指令或通道关闭时返回 false.
+fn wait_for_reconnect(
+    rec_cmd_rx: &mut mpsc::UnboundedReceiver<record::RecordCommand>,
+    rec_event_tx: &mpsc::UnboundedSender<record::RecordEvent>,
+    delay: Duration,
+) -> bool {
+    block_on(async {
+        let timeout = tokio::time::sleep(delay);
+        tokio::pin!(timeout);
+        loop {
+            tokio::select! {
+                _ = &mut timeout => return true,
+                cmd = rec_cmd_rx.recv() => match cmd {
+                    Some(record::RecordCommand::Shutdown(done)) => {
+                        let _ = done.send(());
+                        return false;
+                    }
+                    Some(record::RecordCommand::Start { .. }) => {
+                        let _ = rec_event_tx.send(record::RecordEvent::Error(
+                            "The network camera is reconnecting".to_string(),
+                        ));
+                    }
+                    Some(command) => debug!(?command, "ignoring command while reconnecting"),
+                    None => return false,
+                },
+            }
+        }
+    })
+}
+
+/// [run_loop] 结束的原因.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LoopExit {
+    /// 指令通道关闭或收到退出指令.
+    Closed,
+    Eos,
+    /// 视频源 (或它内部的元素) 报错, 网络摄像头可以重连.
+    SourceFailed,
+    Failed,
+}
+
+/// 把预览帧送进共享的三缓冲, 并按 `overlay` 的设置在预览上叠加参考线.
+/// 收到第一帧时发送 [record::RecordEvent::PreviewStarted].
+pub(crate) fn connect_preview(
+    handles: &PipelineHandles,
+    buffer: exchange::FrameExchange<PreviewFrame>,
+    overlay: overlay::SharedOverlay,
+    event_tx: mpsc::UnboundedSender<record::RecordEvent>,
 ) {
-    std::thread::spawn(move || {
-        // 采集 RGBA 原始像素，适配 egui
-        let pipeline_str = r#"
-            videotestsrc name=src is-live=true !
-            video/x-raw !
-            videoconvert !
-            tee name=t_v
-
-            t_v. ! queue name=q_prev !
-            videoscale !
-            video/x-raw,width=1280,height=720 !
-            cairooverlay name=overlay !
-            videoconvert !
-            video/x-raw,format=RGBA !
-            appsink name=sink sync=false
-
-
-            osxaudiosrc !
-            audioconvert !
-            audioresample !
-            tee name=t_a
-
-            t_a. ! queue !
-            level name=audio_meter interval=50000000 !
-            fakesink
-            "#;
-        let pipeline = gst::parse::launch(pipeline_str)
-            .expect("Pipeline error")
-            .dynamic_cast::<gst::Pipeline>()
-            .unwrap();
-
-        let video_tee = pipeline.by_name("t_v").unwrap();
-        let audio_tee = pipeline.by_name("t_a").unwrap();
-
-        let sink = pipeline
-            .by_name("sink")
-            .unwrap()
-            .dynamic_cast::<gst_app::AppSink>()
-            .expect("Sink error");
-        sink.set_callbacks(
-            gst_app::AppSinkCallbacks::builder()
-                .new_sample(move |sink| {
-                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
-                    let buffer_gst = sample.buffer().ok_or(gst::FlowError::Error)?;
-                    let caps = sample.caps().expect("No caps");
-                    let info = gst_video::VideoInfo::from_caps(caps).expect("Invalid caps");
-
-                    let map = buffer_gst
-                        .map_readable()
-                        .map_err(|_| gst::FlowError::Error)?;
-
-                    // 构建 egui 兼容的图像格式
-                    let pixels = map.as_slice();
-                    let color_image = egui::ColorImage::from_rgba_unmultiplied(
-                        [info.width() as usize, info.height() as usize],
-                        pixels,
-                    );
-
-                    *buffer.lock() = Some(color_image);
-                    Ok(gst::FlowSuccess::Ok)
-                })
-                .build(),
-        );
-
-        let overlay = pipeline
-            .dynamic_cast_ref::<gst::Pipeline>()
-            .unwrap()
-            .by_name("overlay")
-            .unwrap();
-        overlay.connect("draw", false, draw_overlay);
-
-        pipeline.set_state(gst::State::Playing).ok();
-
-        let mut current_recording: Option<record::ActiveRecording> = None;
-        let bus = pipeline.bus().unwrap();
+    let mut sequence = 0;
+    handles.appsink.set_callbacks(
+        gst_app::AppSinkCallbacks::builder()
+            .new_sample(move |sink| {
+                let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                let buffer_gst = sample.buffer().ok_or(gst::FlowError::Error)?;
+                let caps = sample.caps().expect("No caps");
+                let info = gst_video::VideoInfo::from_caps(caps).expect("Invalid caps");
+
+                let map = buffer_gst
+                    .map_readable()
+                    .map_err(|_| gst::FlowError::Error)?;
+
+                // 构建 egui 兼容的图像格式
+                let pixels = map.as_slice();
+                let color_image = egui::ColorImage::from_rgba_unmultiplied(
+                    [info.width() as usize, info.height() as usize],
+                    pixels,
+                );
 
+                let captured_at = capture_instant(sink, buffer_gst.pts());
+                let fps = info.fps();
+                sequence += 1;
+                buffer.publish(PreviewFrame {
+                    image: color_image,
+                    captured_at,
+                    sequence,
+                    nominal_fps: (fps.numer() > 0 && fps.denom() > 0)
+                        .then(|| fps.numer() as f64 / fps.denom() as f64),
+                });
+                if sequence == 1 {
+                    let _ = event_tx.send(record::RecordEvent::PreviewStarted);
+                }
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build(),
+    );
+
+    overlay::connect(&handles.overlay, overlay);
+}
+
+/// buffer 的 PTS 是相对管线 base time 的运行时间, 与时钟当前运行时间之差
+/// 就是这一帧从采集到送达 appsink 经过的时间. 拿不到时钟时当作刚采集.
+fn capture_instant(sink: &gst_app::AppSink, pts: Option<gst::ClockTime>) -> Instant {
+    let now = Instant::now();
+    let (Some(clock), Some(base_time), Some(pts)) = (sink.clock(), sink.base_time(), pts) else {
+        return now;
+    };
+    let running_time = clock.time().saturating_sub(base_time);
+    let age = running_time.saturating_sub(pts);
+    now.checked_sub(Duration::from_nanos(age.nseconds()))
+        .unwrap_or(now)
+}
+
+/// 启动管线并处理录制指令, 直到管线出错, 收到 EOS 或指令通道关闭.
+/// 在调用线程上运行, 测试可以注入自己的通道直接驱动.
+///
+/// 指令, 总线消息和定时检查在同一个单线程 tokio 运行时中等待, 哪个先到就处理哪个,
+/// 空闲时不再轮询.
+pub(crate) fn run_loop(
+    pipeline: &gst::Pipeline,
+    handles: &PipelineHandles,
+    audio_level: Arc<Mutex<f32>>,
+    buffer_stats: Arc<Mutex<bus::BufferStats>>,
+    overlay: overlay::SharedOverlay,
+    rec_cmd_rx: &mut mpsc::UnboundedReceiver<record::RecordCommand>,
+    rec_event_tx: mpsc::UnboundedSender<record::RecordEvent>,
+) -> LoopExit {
+    match pipeline.set_state(gst::State::Playing) {
+        Ok(_) => info!("pipeline playing"),
+        Err(e) => error!(element = %pipeline.name(), "state change failed: {}", e),
+    }
+    if let Some(switcher) = &handles.switcher {
+        switcher.start();
+    }
+    handles.bars.start();
+
+    let mut state = LoopState {
+        pipeline,
+        handles,
+        audio_level,
+        overlay,
+        rec_event_tx,
+        recordings: HashMap::new(),
+        finalizing: Vec::new(),
+        shutdown_done: None,
+        monitor: bus::BusMonitor::new(buffer_stats),
+        source: pipeline.by_name(pipeline::SOURCE),
+        network_at: Instant::now(),
+        denoise: None,
+        sharpen: Default::default(),
+        bars: false,
+        silence_settings: Default::default(),
+        silence: Default::default(),
+    };
+    // 总线消息改由 BusStream 的同步处理器转发, 它被丢弃时恢复原样
+    let mut messages = pipeline.bus().unwrap().stream();
+    let mut tick = tokio::time::interval(POLL_INTERVAL);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    let exit = block_on(async {
         loop {
-            // 1. 处理来自 UI 的指令 (非阻塞)
-            while let Ok(cmd) = rec_cmd_rx.try_recv() {
-                match cmd {
-                    record::RecordCommand::Start(settings) => {
-                        if current_recording.is_none() {
-                            match record::start_recording(
-                                &pipeline, &video_tee, &audio_tee, settings,
-                            ) {
-                                Ok(active) => current_recording = Some(active),
-                                Err(e) => eprintln!("Start Rec Error: {}", e),
-                            }
+            tokio::select! {
+                cmd = rec_cmd_rx.recv() => match cmd {
+                    Some(cmd) => {
+                        if state.on_command(cmd) {
+                            break LoopExit::Closed;
                         }
                     }
-                    record::RecordCommand::Stop => {
-                        if let Some(active) = current_recording.take() {
-                            // 这里调用之前定义的 stop_recording
-                            record::stop_recording(&pipeline, &video_tee, &audio_tee, active);
-                        }
+                    // UI 已退出 (或测试结束), 收尾后退出
+                    None => break LoopExit::Closed,
+                },
+                Some(msg) = messages.next() => {
+                    if let Some(exit) = state.on_message(&msg) {
+                        break exit;
                     }
                 }
+                _ = tick.tick() => state.on_tick(),
             }
+        }
+    });
+    drop(messages);
+    state.finish();
+    exit
+}
+
+/// [run_loop] 中跨越多次等待的状态.
+struct LoopState<'a> {
+    pipeline: &'a gst::Pipeline,
+    handles: &'a PipelineHandles,
+    audio_level: Arc<Mutex<f32>>,
+    overlay: overlay::SharedOverlay,
+    rec_event_tx: mpsc::UnboundedSender<record::RecordEvent>,
+    recordings: HashMap<record::RecordingId, record::ActiveRecording>,
+    /// 已经停止但可能还在封装的录像.
+    finalizing: Vec<record::Finalizing>,
+    shutdown_done: Option<std::sync::mpsc::Sender<()>>,
+    monitor: bus::BusMonitor,
+    source: Option<gst::Element>,
+    network_at: Instant,
+    /// 正在生效的降噪, 新的录像把它写入 sidecar.
+    denoise: Option<String>,
+    /// 界面最近一次发来的锐化设置, 只锐化录像时新的录像按它加上锐化.
+    sharpen: sharpen::SharpenSettings,
+    /// 正在输出彩条.
+    bars: bool,
+    /// 界面最近一次发来的无声检测设置.
+    silence_settings: silence::SilenceSettings,
+    silence: silence::SilenceDetector,
+}
+
+impl LoopState<'_> {
+    fn send(&self, event: record::RecordEvent) {
+        let _ = self.rec_event_tx.send(event);
+    }
 
-            // 2. 处理总线消息 (带超时的轮询，防止 CPU 占用 100%)
-            if let Some(msg) = bus.timed_pop(gst::ClockTime::from_mseconds(10)) {
-                use gst::MessageView;
-                match msg.view() {
-                    MessageView::Error(err) => {
-                        eprintln!("Pipeline Error: {}", err.error());
-                        break; // 发生错误退出循环
+    /// 处理一条来自 UI 的指令, 收到退出指令时返回 true.
+    fn on_command(&mut self, cmd: record::RecordCommand) -> bool {
+        let pipeline = self.pipeline;
+        let handles = self.handles;
+        match cmd {
+            record::RecordCommand::Start { id, settings } => {
+                if self.recordings.contains_key(&id) {
+                    debug!(id = id.0, "already recording");
+                    return false;
+                }
+                if self.bars {
+                    warn!(id = id.0, "not recording color bars");
+                    self.send(record::RecordEvent::Error(
+                        "Color bars are on — switch back to the camera before recording"
+                            .to_string(),
+                    ));
+                    return false;
+                }
+                if let Err((reason, suggestion)) = preflight::check(&settings.filepath) {
+                    warn!(id = id.0, ?suggestion, "recording blocked: {}", reason);
+                    self.send(record::RecordEvent::StartBlocked {
+                        id,
+                        settings: Box::new(settings),
+                        reason,
+                        suggestion,
+                    });
+                    return false;
+                }
+                let burn_in = self.overlay.burns_in().then_some(&self.overlay);
+                match record::start_recording(
+                    pipeline,
+                    &handles.video_tee,
+                    &handles.audio_tee,
+                    id,
+                    settings,
+                    burn_in,
+                    self.processing(),
+                ) {
+                    Ok(active) => {
+                        // QoS 统计覆盖从第一个录像开始到最后一个停止
+                        if self.recordings.is_empty() {
+                            self.monitor.reset();
+                        }
+                        self.send(record::RecordEvent::Started {
+                            id,
+                            path: active.path().clone(),
+                            timecode: active.timecode(),
+                        });
+                        self.recordings.insert(id, active);
+                    }
+                    Err(e) => {
+                        error!(id = id.0, "could not start recording: {}", e);
+                        self.send(record::RecordEvent::Error(e.to_string()));
                     }
-                    MessageView::Eos(_) => break, // 收到结束信号退出
-
-                    // 处理音频电平消息
-                    MessageView::Element(ext)
-                        // 确认消息来源是我们管线中命名的 "audio_meter"
-                        if ext
-                            .src()
-                            .map(|s| s.name() == "audio_meter")
-                            .unwrap_or(false) =>
-                    {
-                        if let Some(structure) = ext.structure() {
-                            if let Ok(rms_array) = structure.get::<gst::glib::ValueArray>("rms") {
-                                if let Some(val_value) = rms_array.get(0) {
-                                    if let Ok(db) = val_value.get::<f64>() {
-                                        *audio_level.lock() = db as f32;
-                                    }
-                                }
-                            }
+                }
+            }
+            record::RecordCommand::Stop { id } => {
+                if let Some(active) = self.recordings.remove(&id) {
+                    // 丢掉已经完成的
+                    self.finalizing.retain(|done| {
+                        matches!(done.try_recv(), Err(std::sync::mpsc::TryRecvError::Empty))
+                    });
+                    self.stop(active);
+                }
+            }
+            record::RecordCommand::SourceControls(controls) => {
+                set_source_controls(pipeline, &controls);
+            }
+            record::RecordCommand::Libcamera(control) => {
+                if let Some(src) = pipeline.by_name(pipeline::SOURCE) {
+                    libcamera::apply(&src, control);
+                }
+            }
+            record::RecordCommand::TestPattern(test_pattern) => {
+                if let Some(src) = &self.source {
+                    pattern::set_pattern(src, test_pattern);
+                }
+            }
+            record::RecordCommand::Source(switcher::SourceCommand::Switch(index)) => {
+                let result = match &handles.switcher {
+                    Some(switcher) => switcher.switch(index),
+                    None => Err("only one source is configured".to_string()),
+                };
+                if let Err(e) = result {
+                    warn!(source = index + 1, "could not switch source: {}", e);
+                    self.send(record::RecordEvent::Warning(format!(
+                        "Could not switch source: {}",
+                        e
+                    )));
+                }
+            }
+            record::RecordCommand::PipLayout(layout) => {
+                if let Some(pip) = &handles.pip {
+                    pip.apply(&layout);
+                }
+            }
+            record::RecordCommand::Multiview(enabled) => multiview::set_enabled(pipeline, enabled),
+            record::RecordCommand::ChannelGains(gains) => handles.balance.set(gains),
+            record::RecordCommand::Zoom(zoom) => handles.zoom.set(zoom),
+            record::RecordCommand::Denoise(settings) => self.set_denoise(settings),
+            record::RecordCommand::Sharpen(settings) => self.set_sharpen(settings),
+            record::RecordCommand::Bars(ident) => self.set_bars(ident),
+            record::RecordCommand::Silence(settings) => self.set_silence(settings),
+            record::RecordCommand::AudioGain(gain) => {
+                handles.audio_gain.set_property("volume", gain)
+            }
+            record::RecordCommand::Snapshot { path, settings } => {
+                let event = match snapshot::save(pipeline, &path, &settings) {
+                    Ok(()) => record::RecordEvent::Snapshot(path),
+                    Err(e) => record::RecordEvent::Error(format!("Snapshot failed: {}", e)),
+                };
+                self.send(event);
+            }
+            // 连拍在后台完成. 失败只作为警告, 错误会让界面认为录制已经中断
+            record::RecordCommand::Burst { path, settings } => {
+                let tx = self.rec_event_tx.clone();
+                let started = snapshot::burst(pipeline, path, settings, move |result| {
+                    let _ = tx.send(match result {
+                        Ok(paths) => record::RecordEvent::Burst(paths),
+                        Err(e) => record::RecordEvent::Warning(format!("Burst failed: {}", e)),
+                    });
+                });
+                if let Err(e) = started {
+                    self.send(record::RecordEvent::Warning(format!("Burst failed: {}", e)));
+                }
+            }
+            record::RecordCommand::DumpGraph { dir } => {
+                let tx = self.rec_event_tx.clone();
+                let started = graph::save(pipeline, &dir, move |result| {
+                    let _ = tx.send(match result {
+                        Ok(files) => record::RecordEvent::GraphSaved(files),
+                        Err(e) => record::RecordEvent::Warning(format!(
+                            "Could not write pipeline graph: {}",
+                            e
+                        )),
+                    });
+                });
+                if let Err(e) = started {
+                    self.send(record::RecordEvent::Warning(format!(
+                        "Could not write pipeline graph: {}",
+                        e
+                    )));
+                }
+            }
+            record::RecordCommand::Shutdown(done) => {
+                self.shutdown_done = Some(done);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 没有可用的滤镜, 或者画面太大而不能使用软件降噪时提示.
+    fn set_denoise(&mut self, settings: denoise::DenoiseSettings) {
+        let Some(denoiser) = &self.handles.denoise else {
+            if settings.enabled {
+                warn!("no denoise filter is installed");
+                self.send(record::RecordEvent::Warning(
+                    "Denoise unavailable: install gst-plugins-bad (va) or gst-plugins-good"
+                        .to_string(),
+                ));
+            }
+            return;
+        };
+        let state = denoiser.set(settings);
+        info!(state = ?state, "denoise set");
+        self.denoise = match state {
+            denoise::DenoiseState::On(label) => Some(label),
+            denoise::DenoiseState::Off => None,
+            denoise::DenoiseState::TooLarge { width, height } => {
+                self.send(record::RecordEvent::Warning(format!(
+                    "Denoise turned off: {}x{} is too large for software denoise",
+                    width, height
+                )));
+                None
+            }
+        };
+    }
+
+    fn set_sharpen(&mut self, settings: sharpen::SharpenSettings) {
+        self.sharpen = settings;
+        let Some(sharpener) = &self.handles.sharpen else {
+            if settings.enabled {
+                warn!("no sharpen filter is installed");
+                self.send(record::RecordEvent::Warning(
+                    "Sharpening unavailable: install frei0r-plugins or gst-plugins-bad".to_string(),
+                ));
+            }
+            return;
+        };
+        sharpener.set(&settings);
+        for active in self.recordings.values_mut() {
+            active.update_sharpen(settings);
+        }
+        info!(settings = ?settings, "sharpen set");
+    }
+
+    fn set_bars(&mut self, ident: Option<String>) {
+        if ident.is_some() && !self.recordings.is_empty() {
+            warn!("not switching to color bars while recording");
+            self.send(record::RecordEvent::Warning(
+                "Stop recording before switching to color bars".to_string(),
+            ));
+        } else {
+            let ident = ident.map(|ident| match ident.trim() {
+                "" => record::source_device(self.pipeline),
+                name => name.to_string(),
+            });
+            self.handles.bars.set(ident.as_deref());
+            self.bars = ident.is_some();
+        }
+        self.send(record::RecordEvent::Bars(self.bars));
+    }
+
+    fn set_silence(&mut self, settings: silence::SilenceSettings) {
+        self.silence_settings = settings;
+        info!(settings = ?settings, "silence detection set");
+    }
+
+    /// 每条电平消息调用. 只在录制中, 而且输入音量没有调成 0 时检测.
+    fn check_silence(&mut self, db: f64) {
+        let muted = self.handles.audio_gain.property::<f64>("volume") <= 0.0;
+        if !self.silence_settings.enabled || muted || self.recordings.is_empty() {
+            self.end_silence();
+            return;
+        }
+        let now = Instant::now();
+        match self.silence.update(db, &self.silence_settings, now) {
+            Some(silence::SilenceChange::Started { since }) => {
+                warn!(db, "no audio detected");
+                for active in self.recordings.values_mut() {
+                    active.silence_started(since);
+                }
+                self.send(record::RecordEvent::Silence(true));
+            }
+            Some(silence::SilenceChange::Ended) => {
+                info!(db, "audio is back");
+                for active in self.recordings.values_mut() {
+                    active.silence_ended(now);
+                }
+                self.send(record::RecordEvent::Silence(false));
+            }
+            None => {}
+        }
+    }
+
+    /// 停止检测. 还在提示时通知界面, 没有停止的录像记下声音恢复的时间.
+    fn end_silence(&mut self) {
+        if self.silence.reset() {
+            let now = Instant::now();
+            for active in self.recordings.values_mut() {
+                active.silence_ended(now);
+            }
+            self.send(record::RecordEvent::Silence(false));
+        }
+    }
+
+    /// 新的录像使用的画面处理.
+    fn processing(&self) -> record::Processing {
+        let sharpen = match &self.handles.sharpen {
+            Some(sharpener) if self.sharpen.enabled => Some((sharpener.filter(), self.sharpen)),
+            _ => None,
+        };
+        record::Processing {
+            denoise: self.denoise.clone(),
+            sharpen,
+        }
+    }
+
+    /// 处理一条总线消息, 管线出错或收到 EOS 时返回退出原因.
+    fn on_message(&mut self, msg: &gst::Message) -> Option<LoopExit> {
+        use gst::MessageView;
+        match msg.view() {
+            MessageView::Error(err) => {
+                error!(element = %source_name(msg), "pipeline error: {}", err.error());
+                self.send(record::RecordEvent::Error(err.error().to_string()));
+                return Some(if from_source(msg, self.source.as_ref()) {
+                    LoopExit::SourceFailed
+                } else {
+                    LoopExit::Failed
+                });
+            }
+            MessageView::Eos(_) => return Some(LoopExit::Eos),
+            MessageView::Warning(w) => {
+                let element = source_name(msg);
+                let text = w.error().to_string();
+                warn!(element = %element, debug = ?w.debug(), "pipeline warning: {}", text);
+                if let Some(toast) = self.monitor.on_warning(&element, &text, Instant::now()) {
+                    self.send(record::RecordEvent::Warning(toast));
+                }
+            }
+            MessageView::Qos(qos) => {
+                let element = source_name(msg);
+                let (_processed, dropped) = qos.stats();
+                let dropped = u64::try_from(dropped.value()).ok();
+                debug!(element = %element, ?dropped, "qos");
+                self.monitor.on_qos(&element, dropped);
+            }
+
+            // 处理音频电平消息
+            MessageView::Element(ext)
+                // 确认消息来源是我们管线中命名的电平元素
+                if ext
+                    .src()
+                    .map(|s| s.name() == AUDIO_METER)
+                    .unwrap_or(false) =>
+            {
+                if let Some(s) = ext.structure()
+                    && let Ok(rms) = s.get::<gst::glib::ValueArray>("rms")
+                    && let Some(v) = rms.first()
+                    && let Ok(db) = v.get::<f64>()
+                {
+                    *self.audio_level.lock() = db as f32;
+                    self.check_silence(db);
+                }
+            }
+            _ => (),
+        }
+        None
+    }
+
+    /// 定时检查: 切换源是否完成, 网络统计, 录制进度和存储速度.
+    fn on_tick(&mut self) {
+        if let Some(index) = self.handles.switcher.as_ref().and_then(|s| s.poll()) {
+            self.send(record::RecordEvent::SourceSwitched(index));
+        }
+
+        if let Some(src) = &self.source
+            && self.network_at.elapsed() >= NETWORK_STATS_INTERVAL
+        {
+            self.network_at = Instant::now();
+            if let Some(stats) = rtsp::network_stats(src) {
+                self.send(record::RecordEvent::Network(stats));
+            }
+        }
+
+        let mut storage_full = Vec::new();
+        for (&id, active) in &mut self.recordings {
+            // 界面显示的丢帧数来自主录像
+            if id == record::RecordingId::MAIN {
+                self.monitor.set_frames(active.frame_stats());
+            }
+            if let Some(progress) = active.progress(Instant::now()) {
+                let _ = self
+                    .rec_event_tx
+                    .send(record::RecordEvent::Progress { id, progress });
+                let mb_per_sec = progress.bytes_per_sec as f64 / 1e6;
+                if let Some((change, rate)) = active.check_write_speed() {
+                    let written_mb_per_sec = rate.written_per_sec as f64 / 1e6;
+                    let produced_mb_per_sec = rate.produced_per_sec as f64 / 1e6;
+                    match change {
+                        write_speed::WriteChange::Underrun { .. } => warn!(
+                            id = id.0,
+                            written_mb_per_sec,
+                            produced_mb_per_sec,
+                            "storage is writing slower than the stream bitrate"
+                        ),
+                        write_speed::WriteChange::Persistent => warn!(
+                            id = id.0,
+                            written_mb_per_sec,
+                            produced_mb_per_sec,
+                            "storage has been too slow for a while"
+                        ),
+                        write_speed::WriteChange::Recovered => {
+                            info!(
+                                id = id.0,
+                                written_mb_per_sec, produced_mb_per_sec, "storage caught up"
+                            )
                         }
                     }
-                    _ => (),
+                    let _ = self.rec_event_tx.send(record::RecordEvent::WriteSpeed {
+                        id,
+                        change,
+                        rate,
+                    });
+                }
+                match active.check_storage() {
+                    Some(record::StorageCheck::Slow) => {
+                        warn!(
+                            id = id.0,
+                            mb_per_sec, "storage is not keeping up with the encoder"
+                        );
+                        let _ = self.rec_event_tx.send(record::RecordEvent::Warning(format!(
+                            "Storage can't keep up: writing only {:.1} MB/s, frames may be dropped",
+                            mb_per_sec
+                        )));
+                    }
+                    Some(record::StorageCheck::Full) => storage_full.push((id, mb_per_sec)),
+                    None => {}
                 }
             }
+        }
+        // 写入队列满了, 继续录下去只会不断丢帧
+        for (id, mb_per_sec) in storage_full {
+            let Some(active) = self.recordings.remove(&id) else {
+                continue;
+            };
+            error!(
+                id = id.0,
+                mb_per_sec, "storage too slow, stopping recording"
+            );
+            self.send(record::RecordEvent::Warning(format!(
+                "Recording stopped: storage can't keep up ({:.1} MB/s)",
+                mb_per_sec
+            )));
+            self.stop(active);
+        }
+    }
 
-            // NOTE: 如果需要极高性能，可以移除 sleep
-            // 但在带有指令轮询的循环中，适当的微小延迟是有益的
+    fn stop(&mut self, active: record::ActiveRecording) {
+        self.finalizing.push(record::stop_recording(
+            self.pipeline,
+            &self.handles.video_tee,
+            &self.handles.audio_tee,
+            active,
+            self.monitor.snapshot(),
+            self.audio_state(),
+            self.rec_event_tx.clone(),
+        ));
+    }
+
+    /// 写进 sidecar 的输入音量.
+    fn audio_state(&self) -> SidecarAudio {
+        let gain = self.handles.audio_gain.property::<f64>("volume");
+        SidecarAudio {
+            gain,
+            muted: gain <= 0.0 || self.handles.audio_gain.property::<bool>("mute"),
         }
-        // 3. 退出前的清理 (防止程序崩溃导致文件损坏)
-        if let Some(active) = current_recording.take() {
-            record::stop_recording(&pipeline, &video_tee, &audio_tee, active);
+    }
+
+    /// 退出前的清理 (防止程序崩溃导致文件损坏).
+    fn finish(mut self) {
+        for active in std::mem::take(&mut self.recordings).into_values() {
+            self.stop(active);
         }
-        let _ = pipeline.set_state(gst::State::Null);
-    });
+        // 封装需要数据继续流动, 必须等它完成后才能停止管线
+        let deadline = Instant::now() + FINALIZE_TIMEOUT;
+        for done in self.finalizing {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if done.recv_timeout(remaining).is_err() {
+                warn!("timed out waiting for recording to finish");
+            }
+        }
+        if let Err(e) = self.pipeline.set_state(gst::State::Null) {
+            warn!(element = %self.pipeline.name(), "state change failed: {}", e);
+        }
+        info!("pipeline stopped");
+        if let Some(done) = self.shutdown_done {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// 在当前线程上运行一个只带计时器的 tokio 运行时. gst 线程本身不在任何运行时中.
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("failed to build gst thread runtime")
+        .block_on(future)
+}
+
+/// 通过 v4l2src 的 `extra-controls` 属性设置摄像头控件, 设备已打开时立即生效.
+fn set_source_controls(pipeline: &gst::Pipeline, controls: &str) {
+    let Some(src) = pipeline.by_name(pipeline::SOURCE) else {
+        return;
+    };
+    if src.find_property("extra-controls").is_none() {
+        warn!(element = %src.name(), "source has no extra-controls property");
+        return;
+    }
+    match controls.parse::<gst::Structure>() {
+        Ok(structure) => {
+            debug!(element = %src.name(), %controls, "setting source controls");
+            src.set_property("extra-controls", structure);
+        }
+        Err(e) => warn!(%controls, "invalid source controls: {}", e),
+    }
+}
+
+/// rtspsrc 是一个 bin, 错误通常来自它内部的元素.
+fn from_source(msg: &gst::Message, source: Option<&gst::Element>) -> bool {
+    let (Some(src), Some(source)) = (msg.src(), source) else {
+        return false;
+    };
+    src == source.upcast_ref::<gst::Object>() || src.has_as_ancestor(source)
 }
 
-fn draw_overlay(values: &[cairo::glib::Value]) -> Option<cairo::glib::Value> {
-    // values[0]: cairooverlay 元素本身
-    // values[1]: cairo::Context
-    // values[2]: timestamp
-    // values[3]: duration
-    let _overlay = values[0].get::<gst::Element>().unwrap();
-    let cr = values[1].get::<cairo::Context>().unwrap();
-
-    // 获取当前分辨率
-    // NOTE: 可以缓存这些信息以提高性能
-    let pad = _overlay.static_pad("sink").unwrap();
-    let caps = pad.current_caps().unwrap();
-    let structure = caps.structure(0).unwrap();
-    let width = structure.get::<i32>("width").unwrap() as f64;
-    let height = structure.get::<i32>("height").unwrap() as f64;
-
-    // --- 开始绘制 ---
-
-    // 绘制三分参考线
-    cr.set_source_rgba(1.0, 1.0, 1.0, 0.5); // 白色，0.5 透明度
-    cr.set_line_width(2.0);
-    // 垂直线
-    for i in 1..3 {
-        let x = width / 3.0 * i as f64;
-        cr.move_to(x, 0.0);
-        cr.line_to(x, height);
-    }
-    // 水平线
-    for i in 1..3 {
-        let y = height / 3.0 * i as f64;
-        cr.move_to(0.0, y);
-        cr.line_to(width, y);
-    }
-    cr.stroke().expect("Stroke failed");
-
-    None
+fn source_name(msg: &gst::Message) -> String {
+    msg.src()
+        .map(|s| s.name().to_string())
+        .unwrap_or_else(|| "pipeline".to_string())
 }
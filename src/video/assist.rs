@@ -0,0 +1,209 @@
+//! 对焦峰值和斑马纹, 直接画在送给界面的预览帧上, 不影响录制.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use super::black::BlackFrameSettings;
+
+/// 灵敏度为 0 和 1 时的边缘阈值 (相邻像素的亮度差).
+const PEAKING_THRESHOLD: (f32, f32) = (96.0, 16.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PeakingColor {
+    #[default]
+    Red,
+    Green,
+    Blue,
+    White,
+}
+
+impl PeakingColor {
+    pub const ALL: [PeakingColor; 4] = [
+        PeakingColor::Red,
+        PeakingColor::Green,
+        PeakingColor::Blue,
+        PeakingColor::White,
+    ];
+
+    pub fn color(self) -> egui::Color32 {
+        match self {
+            PeakingColor::Red => egui::Color32::from_rgb(255, 0, 0),
+            PeakingColor::Green => egui::Color32::from_rgb(0, 255, 0),
+            PeakingColor::Blue => egui::Color32::from_rgb(0, 96, 255),
+            PeakingColor::White => egui::Color32::WHITE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct AssistSettings {
+    pub peaking: bool,
+    pub peaking_color: PeakingColor,
+    /// 0 到 1, 越大标出的边缘越多.
+    pub peaking_intensity: f32,
+    pub zebra: bool,
+    /// 亮度达到这个百分比时显示斑马纹.
+    pub zebra_threshold: u8,
+    /// 条纹的角度, 0 为竖直条纹, 顺时针为正.
+    pub zebra_angle: u16,
+    /// 相邻条纹的间距, 单位是预览像素.
+    pub zebra_spacing: u32,
+    pub black_frame: BlackFrameSettings,
+}
+
+impl Default for AssistSettings {
+    fn default() -> Self {
+        Self {
+            peaking: false,
+            peaking_color: PeakingColor::Red,
+            peaking_intensity: 0.5,
+            zebra: false,
+            zebra_threshold: 95,
+            zebra_angle: 45,
+            zebra_spacing: 8,
+            black_frame: BlackFrameSettings::default(),
+        }
+    }
+}
+
+impl AssistSettings {
+    /// 红绿色盲也容易分辨: 蓝色峰值, 较宽的斑马纹. 不改变开关和阈值.
+    pub fn colorblind_safe(&self) -> Self {
+        Self {
+            peaking_color: PeakingColor::Blue,
+            zebra_angle: 45,
+            zebra_spacing: 12,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.peaking || self.zebra
+    }
+}
+
+fn luma(pixel: egui::Color32) -> u8 {
+    let [r, g, b, _] = pixel.to_array();
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) as u8
+}
+
+/// 在 `image` 上叠加打开的辅助显示. 先按原图计算亮度, 峰值不会把斑马纹当成边缘.
+pub(crate) fn apply(image: &mut egui::ColorImage, settings: &AssistSettings) {
+    if !settings.is_active() {
+        return;
+    }
+    let [width, height] = image.size;
+    let luma: Vec<u8> = image.pixels.iter().map(|p| luma(*p)).collect();
+
+    if settings.zebra {
+        let threshold = (settings.zebra_threshold.min(100) as f32 * 2.55).round() as u8;
+        let angle = (settings.zebra_angle as f32).to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let spacing = settings.zebra_spacing.max(2) as f32;
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                if luma[i] < threshold {
+                    continue;
+                }
+                let phase = (x as f32 * cos + y as f32 * sin) / spacing;
+                if phase.rem_euclid(1.0) < 0.5 {
+                    image.pixels[i] = egui::Color32::BLACK;
+                }
+            }
+        }
+    }
+
+    if settings.peaking && width > 2 && height > 2 {
+        let t = settings.peaking_intensity.clamp(0.0, 1.0);
+        let threshold = PEAKING_THRESHOLD.0 + (PEAKING_THRESHOLD.1 - PEAKING_THRESHOLD.0) * t;
+        let color = settings.peaking_color.color();
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let i = y * width + x;
+                let dx = luma[i + 1].abs_diff(luma[i - 1]);
+                let dy = luma[i + width].abs_diff(luma[i - width]);
+                if dx.max(dy) as f32 >= threshold {
+                    image.pixels[i] = color;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 左半黑, 右半白.
+    fn split(width: usize, height: usize) -> egui::ColorImage {
+        let pixels = (0..width * height)
+            .map(|i| {
+                if i % width < width / 2 {
+                    egui::Color32::BLACK
+                } else {
+                    egui::Color32::WHITE
+                }
+            })
+            .collect();
+        egui::ColorImage::new([width, height], pixels)
+    }
+
+    #[test]
+    fn peaking_marks_edges_in_the_chosen_color() {
+        let mut image = split(8, 4);
+        let settings = AssistSettings {
+            peaking: true,
+            peaking_color: PeakingColor::Green,
+            ..Default::default()
+        };
+        apply(&mut image, &settings);
+        let green = PeakingColor::Green.color();
+        let row: Vec<_> = image.pixels[8..16].iter().map(|p| *p == green).collect();
+        assert_eq!(row, [false, false, false, true, true, false, false, false]);
+    }
+
+    #[test]
+    fn zebra_stripes_only_bright_areas() {
+        let mut image = split(16, 16);
+        let settings = AssistSettings {
+            zebra: true,
+            zebra_angle: 0,
+            zebra_spacing: 4,
+            ..Default::default()
+        };
+        apply(&mut image, &settings);
+        // 暗部不变, 亮部每 4 列中有 2 列变黑
+        let row = &image.pixels[..16];
+        assert!(row[..8].iter().all(|p| *p == egui::Color32::BLACK));
+        let black = row[8..]
+            .iter()
+            .filter(|p| **p == egui::Color32::BLACK)
+            .count();
+        assert_eq!(black, 4);
+
+        // 阈值 100% 时只有纯白才有条纹
+        let mut grey = egui::ColorImage::new([4, 4], vec![egui::Color32::from_gray(250); 16]);
+        let strict = AssistSettings {
+            zebra_threshold: 100,
+            ..settings
+        };
+        apply(&mut grey, &strict);
+        assert!(grey.pixels.iter().all(|p| *p != egui::Color32::BLACK));
+    }
+
+    #[test]
+    fn colorblind_preset_keeps_toggles() {
+        let settings = AssistSettings {
+            peaking: true,
+            zebra_threshold: 80,
+            ..Default::default()
+        };
+        let safe = settings.colorblind_safe();
+        assert!(safe.peaking);
+        assert_eq!(safe.zebra_threshold, 80);
+        assert_eq!(safe.peaking_color, PeakingColor::Blue);
+    }
+}
@@ -0,0 +1,101 @@
+//! AV1 软件编码. 按 svtav1enc, rav1enc, av1enc (libaom) 的顺序选用第一个安装了的编码器,
+//! 统一的速度预设映射到各自的属性上. 三者都很吃 CPU, 能否实时编码主要看分辨率.
+
+use gstreamer as gst;
+
+use super::record::Resolution;
+
+/// 速度预设的范围, 越大越快, 画质越差.
+pub(crate) const SPEEDS: std::ops::RangeInclusive<u8> = 0..=10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Av1Encoder {
+    SvtAv1,
+    Rav1e,
+    Aom,
+}
+
+impl Av1Encoder {
+    const PREFERENCE: [Av1Encoder; 3] = [Av1Encoder::SvtAv1, Av1Encoder::Rav1e, Av1Encoder::Aom];
+
+    /// 安装了的最佳编码器, 都没有时返回 None.
+    pub fn detect() -> Option<Self> {
+        Self::PREFERENCE
+            .into_iter()
+            .find(|e| gst::ElementFactory::find(e.element()).is_some())
+    }
+
+    pub fn element(self) -> &'static str {
+        match self {
+            Av1Encoder::SvtAv1 => "svtav1enc",
+            Av1Encoder::Rav1e => "rav1enc",
+            Av1Encoder::Aom => "av1enc",
+        }
+    }
+
+    /// gst-launch 语法的编码器描述.
+    pub fn description(self, speed: u8, bitrate_kbps: u32) -> String {
+        let speed = speed.min(*SPEEDS.end());
+        match self {
+            // preset 0 到 13, 低于 4 的只适合离线编码
+            Av1Encoder::SvtAv1 => format!(
+                "svtav1enc preset={} target-bitrate={}",
+                speed + 3,
+                bitrate_kbps
+            ),
+            // rav1enc 的码率单位是 bit/s
+            Av1Encoder::Rav1e => format!(
+                "rav1enc speed-preset={} low-latency=true bitrate={}",
+                speed,
+                bitrate_kbps as u64 * 1000
+            ),
+            Av1Encoder::Aom => format!(
+                "av1enc usage-profile=realtime end-usage=cbr cpu-used={} target-bitrate={}",
+                speed.min(8),
+                bitrate_kbps
+            ),
+        }
+    }
+}
+
+/// 软件编码能否跟上实时, 只是按分辨率和速度的粗略判断.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Realtime {
+    Likely,
+    /// 取决于 CPU, 留意丢帧提示.
+    Marginal,
+    Unlikely,
+}
+
+pub(crate) fn realtime(res: Resolution, speed: u8) -> Realtime {
+    let pixels = res.width as u64 * res.height as u64;
+    let fast = speed >= 6;
+    if pixels > 1920 * 1080 {
+        Realtime::Unlikely
+    } else if pixels > 1280 * 720 {
+        if fast {
+            Realtime::Marginal
+        } else {
+            Realtime::Unlikely
+        }
+    } else if fast {
+        Realtime::Likely
+    } else {
+        Realtime::Marginal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn realtime_depends_on_resolution_and_speed() {
+        let res = |width, height| Resolution { width, height };
+        assert_eq!(realtime(res(1280, 720), 8), Realtime::Likely);
+        assert_eq!(realtime(res(1280, 720), 2), Realtime::Marginal);
+        assert_eq!(realtime(res(1920, 1080), 8), Realtime::Marginal);
+        assert_eq!(realtime(res(1920, 1080), 4), Realtime::Unlikely);
+        assert_eq!(realtime(res(3840, 2160), 10), Realtime::Unlikely);
+    }
+}
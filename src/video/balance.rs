@@ -0,0 +1,406 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use gstreamer_video::prelude::*;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 手动色温的范围 (K).
+pub(crate) const KELVIN_RANGE: std::ops::RangeInclusive<u32> = 2000..=10_000;
+/// 认为预览画面已经按这个色温校正过 (sRGB 的 D65).
+pub(crate) const REFERENCE_KELVIN: u32 = 6500;
+/// BT.601 中 Cb, Cr 与 B-Y, R-Y 的比例 (已换算到 8 位有限范围).
+const CB_SCALE: f32 = 0.577;
+const CR_SCALE: f32 = 0.729;
+
+/// 白平衡模式, 也写入 sidecar.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum WhiteBalance {
+    Auto,
+    Daylight,
+    Tungsten,
+    Shade,
+    Fluorescent,
+    Kelvin(u32),
+    /// 灰卡一键白平衡得到的增益.
+    GreyCard(ChannelGains),
+}
+
+impl WhiteBalance {
+    pub const PRESETS: [WhiteBalance; 5] = [
+        WhiteBalance::Auto,
+        WhiteBalance::Daylight,
+        WhiteBalance::Tungsten,
+        WhiteBalance::Shade,
+        WhiteBalance::Fluorescent,
+    ];
+
+    /// 预设和手动色温对应的色温, 自动和灰卡为 None.
+    pub fn kelvin(self) -> Option<u32> {
+        match self {
+            WhiteBalance::Auto | WhiteBalance::GreyCard(_) => None,
+            WhiteBalance::Daylight => Some(5600),
+            WhiteBalance::Tungsten => Some(3200),
+            WhiteBalance::Shade => Some(7500),
+            WhiteBalance::Fluorescent => Some(4000),
+            WhiteBalance::Kelvin(kelvin) => Some(kelvin),
+        }
+    }
+
+    /// 没有色温控件时在管线中使用的增益, 自动时不处理.
+    pub fn gains(self) -> ChannelGains {
+        match self {
+            WhiteBalance::Auto => ChannelGains::default(),
+            WhiteBalance::GreyCard(gains) => gains,
+            _ => kelvin_gains(self.kelvin().unwrap_or(REFERENCE_KELVIN)),
+        }
+    }
+
+    /// 底部栏显示的简称.
+    pub fn label(self) -> String {
+        match self {
+            WhiteBalance::Auto => "AUTO".to_string(),
+            WhiteBalance::Daylight => "DAY".to_string(),
+            WhiteBalance::Tungsten => "TUNG".to_string(),
+            WhiteBalance::Shade => "SHADE".to_string(),
+            WhiteBalance::Fluorescent => "FLUO".to_string(),
+            WhiteBalance::Kelvin(kelvin) => format!("{}K", kelvin),
+            WhiteBalance::GreyCard(_) => "CARD".to_string(),
+        }
+    }
+
+    pub fn name(self) -> String {
+        match (self, self.kelvin()) {
+            (WhiteBalance::Auto, _) => "Auto".to_string(),
+            (WhiteBalance::Daylight, Some(k)) => format!("Daylight {}K", k),
+            (WhiteBalance::Tungsten, Some(k)) => format!("Tungsten {}K", k),
+            (WhiteBalance::Shade, Some(k)) => format!("Shade {}K", k),
+            (WhiteBalance::Fluorescent, Some(k)) => format!("Fluorescent {}K", k),
+            (WhiteBalance::GreyCard(_), _) => "Grey card".to_string(),
+            (_, kelvin) => format!("{}K", kelvin.unwrap_or(REFERENCE_KELVIN)),
+        }
+    }
+}
+
+/// 红, 蓝通道相对绿通道的增益.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ChannelGains {
+    pub red: f32,
+    pub blue: f32,
+}
+
+impl Default for ChannelGains {
+    fn default() -> Self {
+        Self {
+            red: 1.0,
+            blue: 1.0,
+        }
+    }
+}
+
+impl ChannelGains {
+    fn is_identity(self) -> bool {
+        (self.red - 1.0).abs() < 0.005 && (self.blue - 1.0).abs() < 0.005
+    }
+
+    /// 先应用 `self` 再应用 `other`.
+    pub fn then(self, other: ChannelGains) -> ChannelGains {
+        ChannelGains {
+            red: self.red * other.red,
+            blue: self.blue * other.blue,
+        }
+    }
+}
+
+/// 黑体辐射在某个色温下的颜色, 使用 Tanner Helland 的拟合.
+fn blackbody(kelvin: u32) -> [f32; 3] {
+    let t = kelvin.clamp(*KELVIN_RANGE.start(), 40_000) as f32 / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    [red, green, blue].map(|c| c.clamp(1.0, 255.0))
+}
+
+/// 把在 `kelvin` 光源下拍到的画面校正为中性的增益.
+pub(crate) fn kelvin_gains(kelvin: u32) -> ChannelGains {
+    let [r, g, b] = blackbody(kelvin);
+    let [r0, g0, b0] = blackbody(REFERENCE_KELVIN);
+    ChannelGains {
+        red: (r0 / g0) / (r / g),
+        blue: (b0 / g0) / (b / g),
+    }
+}
+
+/// 与增益最接近的色温, 用于只有色温控件的摄像头.
+pub(crate) fn kelvin_for_gains(gains: ChannelGains) -> u32 {
+    let distance = |kelvin: u32| {
+        let k = kelvin_gains(kelvin);
+        (k.red.ln() - gains.red.ln()).powi(2) + (k.blue.ln() - gains.blue.ln()).powi(2)
+    };
+    KELVIN_RANGE
+        .step_by(50)
+        .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+        .unwrap_or(REFERENCE_KELVIN)
+}
+
+/// 对预览画面中央 1/5 的区域取平均, 计算让它变成中性灰的增益.
+/// 太暗或过曝时返回 None.
+pub(crate) fn grey_card_gains(image: &egui::ColorImage) -> Option<ChannelGains> {
+    let [width, height] = image.size;
+    let (w, h) = ((width / 5).max(1), (height / 5).max(1));
+    let (x0, y0) = ((width - w) / 2, (height - h) / 2);
+    let mut sum = [0u64; 3];
+    for y in y0..y0 + h {
+        for pixel in &image.pixels[y * width + x0..y * width + x0 + w] {
+            let [r, g, b, _] = pixel.to_array();
+            sum[0] += r as u64;
+            sum[1] += g as u64;
+            sum[2] += b as u64;
+        }
+    }
+    let count = (w * h) as f32;
+    let [r, g, b] = sum.map(|s| s as f32 / count);
+    if r.min(g).min(b) < 20.0 || r.max(g).max(b) > 250.0 {
+        return None;
+    }
+    Some(ChannelGains {
+        red: (g / r).clamp(0.25, 4.0),
+        blue: (g / b).clamp(0.25, 4.0),
+    })
+}
+
+/// 在视频 tee 之前按增益调整红蓝通道, 用于没有白平衡控件的设备.
+/// `videobalance` 只能调色相和饱和度, 做不到分通道的增益, 所以在 pad probe 里处理.
+pub(crate) struct ChannelBalance {
+    gains: Arc<Mutex<ChannelGains>>,
+}
+
+impl ChannelBalance {
+    pub fn attach(pad: &gst::Pad) -> Self {
+        let gains = Arc::new(Mutex::new(ChannelGains::default()));
+        let shared = gains.clone();
+        let warned = AtomicBool::new(false);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let gains = *shared.lock();
+            if gains.is_identity() {
+                return gst::PadProbeReturn::Ok;
+            }
+            let Some(video) = pad
+                .current_caps()
+                .and_then(|caps| gst_video::VideoInfo::from_caps(&caps).ok())
+            else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Some(buffer) = info.buffer_mut() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            if let Err(e) = apply_gains(buffer.make_mut(), &video, gains)
+                && !warned.swap(true, Ordering::Relaxed)
+            {
+                tracing::warn!(format = ?video.format(), "cannot apply white balance: {}", e);
+            }
+            gst::PadProbeReturn::Ok
+        });
+        Self { gains }
+    }
+
+    pub fn set(&self, gains: ChannelGains) {
+        *self.gains.lock() = gains;
+    }
+}
+
+/// 一个 8 位分量在帧中的位置.
+#[derive(Debug, Clone, Copy)]
+struct Component {
+    plane: u32,
+    offset: usize,
+    pstride: usize,
+    stride: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Component {
+    fn of(info: &gst_video::VideoInfo, index: u8) -> Self {
+        Self {
+            plane: info.comp_plane(index),
+            offset: info.comp_poffset(index) as usize,
+            pstride: info.comp_pstride(index).max(0) as usize,
+            stride: info.comp_stride(index).max(0) as usize,
+            width: info.comp_width(index) as usize,
+            height: info.comp_height(index) as usize,
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.stride + self.offset + x * self.pstride
+    }
+}
+
+fn apply_gains(
+    buffer: &mut gst::BufferRef,
+    info: &gst_video::VideoInfo,
+    gains: ChannelGains,
+) -> Result<(), String> {
+    let format = info.format_info();
+    let supported = !format.is_tiled()
+        && !format.has_palette()
+        && format.bits() == 8
+        && format.pixel_stride()[..3].iter().all(|&s| s > 0)
+        && (format.is_rgb() || format.is_yuv());
+    if !supported {
+        return Err("only 8-bit RGB and YUV formats are supported".to_string());
+    }
+    let mut frame = gst_video::VideoFrameRef::from_buffer_ref_writable(buffer, info)
+        .map_err(|e| e.to_string())?;
+    let info = frame.info().clone();
+    if format.is_rgb() {
+        for (index, gain) in [(0, gains.red), (2, gains.blue)] {
+            let c = Component::of(&info, index);
+            let data = frame.plane_data_mut(c.plane).map_err(|e| e.to_string())?;
+            scale_component(data, c, gain);
+        }
+        return Ok(());
+    }
+    let (y, u, v) = (
+        Component::of(&info, 0),
+        Component::of(&info, 1),
+        Component::of(&info, 2),
+    );
+    let mut luma = vec![0u8; u.width];
+    for row in 0..u.height {
+        {
+            let data = frame.plane_data(y.plane).map_err(|e| e.to_string())?;
+            for (x, value) in luma.iter_mut().enumerate() {
+                let (lx, ly) = (x * y.width / u.width, row * y.height / u.height);
+                *value = data[y.index(lx, ly)];
+            }
+        }
+        for (c, gain, scale) in [(u, gains.blue, CB_SCALE), (v, gains.red, CR_SCALE)] {
+            let data = frame.plane_data_mut(c.plane).map_err(|e| e.to_string())?;
+            shift_chroma(data, c, row, &luma, gain, scale);
+        }
+    }
+    Ok(())
+}
+
+/// RGB 分量直接乘以增益.
+fn scale_component(data: &mut [u8], c: Component, gain: f32) {
+    let table: Vec<u8> = (0..=255)
+        .map(|v| (v as f32 * gain).round().clamp(0.0, 255.0) as u8)
+        .collect();
+    for y in 0..c.height {
+        for x in 0..c.width {
+            let i = c.index(x, y);
+            data[i] = table[data[i] as usize];
+        }
+    }
+}
+
+/// 近似地把 R (或 B) 乘以 `gain` 后的色度: C' = gain·C + scale·(gain-1)·Y, 忽略亮度的变化.
+fn shift_chroma(data: &mut [u8], c: Component, row: usize, luma: &[u8], gain: f32, scale: f32) {
+    for (x, &y) in luma.iter().enumerate().take(c.width) {
+        let i = c.index(x, row);
+        let chroma = data[i] as f32 - 128.0;
+        let shifted = gain * chroma + scale * (gain - 1.0) * (y as f32 - 16.0);
+        data[i] = (shifted + 128.0).round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kelvin_gains_warm_and_cool_light() {
+        let neutral = kelvin_gains(REFERENCE_KELVIN);
+        assert!(neutral.is_identity());
+        // 钨丝灯偏暖: 减红加蓝
+        let tungsten = kelvin_gains(3200);
+        assert!(tungsten.red < 0.8 && tungsten.blue > 1.3);
+        // 阴影偏冷: 加红减蓝
+        let shade = kelvin_gains(7500);
+        assert!(shade.red > 1.0 && shade.blue < 1.0);
+    }
+
+    #[test]
+    fn kelvin_round_trips_through_gains() {
+        for kelvin in [2500, 3200, 4000, 5600, 6500, 7500, 9000] {
+            let found = kelvin_for_gains(kelvin_gains(kelvin));
+            assert!(found.abs_diff(kelvin) <= 50, "{} -> {}", kelvin, found);
+        }
+    }
+
+    #[test]
+    fn grey_card_neutralises_the_center() {
+        let mut image = egui::ColorImage::new([100, 50], vec![egui::Color32::BLACK; 5000]);
+        for y in 20..30 {
+            for x in 40..60 {
+                image.pixels[y * 100 + x] = egui::Color32::from_rgb(200, 100, 50);
+            }
+        }
+        let gains = grey_card_gains(&image).unwrap();
+        assert!((gains.red - 0.5).abs() < 0.01);
+        assert!((gains.blue - 2.0).abs() < 0.01);
+        // 太暗的区域没法测
+        let dark = egui::ColorImage::new([100, 50], vec![egui::Color32::from_gray(5); 5000]);
+        assert!(grey_card_gains(&dark).is_none());
+    }
+
+    #[test]
+    fn scales_interleaved_rgb_and_shifts_chroma() {
+        // 2x1 的 RGBx
+        let mut rgbx = [100, 100, 100, 0, 200, 50, 100, 0];
+        let red = Component {
+            plane: 0,
+            offset: 0,
+            pstride: 4,
+            stride: 8,
+            width: 2,
+            height: 1,
+        };
+        scale_component(&mut rgbx, red, 2.0);
+        assert_eq!(rgbx, [200, 100, 100, 0, 255, 50, 100, 0]);
+
+        // 灰色像素没有色度, 增益为 1 时不变
+        let cb = Component {
+            plane: 0,
+            offset: 0,
+            pstride: 1,
+            stride: 1,
+            width: 1,
+            height: 1,
+        };
+        let mut chroma = [128];
+        shift_chroma(&mut chroma, cb, 0, &[120], 1.0, CB_SCALE);
+        assert_eq!(chroma, [128]);
+        shift_chroma(&mut chroma, cb, 0, &[120], 1.5, CB_SCALE);
+        assert!(chroma[0] > 128);
+    }
+
+    #[test]
+    fn white_balance_serializes_for_sidecar() {
+        let json = serde_json::to_string(&WhiteBalance::Kelvin(4500)).unwrap();
+        assert_eq!(json, r#"{"kelvin":4500}"#);
+        assert_eq!(
+            serde_json::to_string(&WhiteBalance::Daylight).unwrap(),
+            r#""daylight""#
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! 校准下游监视器用的彩条: SMPTE 彩条加上机位名和时间, 音频换成 1 kHz 参考音.
+//! 选择器紧挨在两个 tee 之前, 预览, 节目输出和录制分支都切过去.
+//! 白平衡增益挂在摄像头那一路上, 彩条不受影响.
+//!
+//! 不用时彩条和参考音的源暂停, 不占 CPU. 退出彩条只是切回选择器的第一路,
+//! 摄像头一直在运行, 立即恢复.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+/// 彩条和音频选择器, 以及两个源和标识叠加层的名字.
+const VIDEO_SELECTOR: &str = "bars_vsel";
+const AUDIO_SELECTOR: &str = "bars_asel";
+const VIDEO_SOURCE: &str = "bars_src";
+const TONE_SOURCE: &str = "bars_tone";
+const IDENT: &str = "bars_ident";
+/// 彩条的分辨率和帧率. 下游的预览和缩略图分支都会缩放.
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+const FRAMERATE: u32 = 30;
+/// 参考音的频率和电平 (-20 dBFS).
+const TONE_HZ: u32 = 1000;
+const TONE_VOLUME: f64 = 0.1;
+
+/// 以 `!` 结尾, 接在摄像头一路和视频 tee 之间. 彩条一路是选择器的第二个输入.
+pub(super) fn video_input() -> String {
+    format!(
+        "{sel}.sink_0
+        videotestsrc name={src} is-live=true pattern=smpte !
+        video/x-raw,width={width},height={height},framerate={fps}/1 !
+        clockoverlay name={ident} time-format=\"%Y-%m-%d %H:%M:%S\" shaded-background=true
+            halignment=center valignment=bottom font-desc=\"Sans 36\" !
+        videoconvert ! queue ! {sel}.sink_1
+        input-selector name={sel} !",
+        sel = VIDEO_SELECTOR,
+        src = VIDEO_SOURCE,
+        width = WIDTH,
+        height = HEIGHT,
+        fps = FRAMERATE,
+        ident = IDENT,
+    )
+}
+
+/// 与 [video_input] 相同, 接在音量和音频 tee 之间.
+pub(super) fn audio_input() -> String {
+    format!(
+        "{sel}.sink_0
+        audiotestsrc name={src} is-live=true wave=sine freq={hz} volume={volume} !
+        audioconvert ! audioresample ! queue ! {sel}.sink_1
+        input-selector name={sel} !",
+        sel = AUDIO_SELECTOR,
+        src = TONE_SOURCE,
+        hz = TONE_HZ,
+        volume = TONE_VOLUME,
+    )
+}
+
+pub(crate) struct ColorBars {
+    video: gst::Element,
+    audio: gst::Element,
+    sources: [gst::Element; 2],
+    ident: gst::Element,
+}
+
+impl ColorBars {
+    pub fn attach(pipeline: &gst::Pipeline) -> Result<Self, String> {
+        let by_name = |name: &str| {
+            pipeline
+                .by_name(name)
+                .ok_or_else(|| format!("pipeline has no element named '{}'", name))
+        };
+        Ok(Self {
+            video: by_name(VIDEO_SELECTOR)?,
+            audio: by_name(AUDIO_SELECTOR)?,
+            sources: [by_name(VIDEO_SOURCE)?, by_name(TONE_SOURCE)?],
+            ident: by_name(IDENT)?,
+        })
+    }
+
+    /// 摄像头一路在选择器上的输入, 白平衡挂在这里.
+    pub fn camera_pad(&self) -> Option<gst::Pad> {
+        self.video.static_pad("sink_0")
+    }
+
+    /// 管线开始播放后调用, 选中摄像头并暂停彩条.
+    pub fn start(&self) {
+        self.select(false);
+        for src in &self.sources {
+            set_state(src, gst::State::Paused);
+        }
+    }
+
+    /// 进入彩条时给出画面上的标识.
+    pub fn set(&self, ident: Option<&str>) {
+        match ident {
+            Some(ident) => {
+                self.ident.set_property("text", ident);
+                for src in &self.sources {
+                    set_state(src, gst::State::Playing);
+                }
+                self.select(true);
+                tracing::info!(ident, "color bars on");
+            }
+            None => {
+                self.select(false);
+                for src in &self.sources {
+                    set_state(src, gst::State::Paused);
+                }
+                tracing::info!("color bars off");
+            }
+        }
+    }
+
+    fn select(&self, bars: bool) {
+        let pad = if bars { "sink_1" } else { "sink_0" };
+        for selector in [&self.video, &self.audio] {
+            match selector.static_pad(pad) {
+                Some(pad) => selector.set_property("active-pad", &pad),
+                None => tracing::warn!(element = %selector.name(), pad, "selector has no such pad"),
+            }
+        }
+    }
+}
+
+fn set_state(src: &gst::Element, state: gst::State) {
+    if let Err(e) = src.set_state(state) {
+        tracing::warn!(element = %src.name(), ?state, "could not change state: {}", e);
+    }
+}
@@ -0,0 +1,34 @@
+//! 倒计时的提示音. 每次响声用一条独立的短管线播放, 播完即销毁, 与采集管线无关.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::time::Duration;
+
+const RATE: u32 = 48_000;
+
+/// 在后台播放一声正弦波, 没有音频输出设备时只记录日志.
+pub(crate) fn play(freq: f64, length: Duration) {
+    let samples = (RATE as f64 * length.as_secs_f64()) as u32;
+    let description = format!(
+        "audiotestsrc wave=sine freq={} volume=0.4 num-buffers=1 samplesperbuffer={} ! \
+         audio/x-raw,rate={} ! audioconvert ! autoaudiosink",
+        freq, samples, RATE
+    );
+    std::thread::spawn(move || {
+        if let Err(e) = run(&description) {
+            tracing::debug!("could not play beep: {}", e);
+        }
+    });
+}
+
+fn run(description: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let pipeline = gst::parse::launch(description)?;
+    let bus = pipeline.bus().ok_or("beep pipeline has no bus")?;
+    pipeline.set_state(gst::State::Playing)?;
+    bus.timed_pop_filtered(
+        gst::ClockTime::from_seconds(2),
+        &[gst::MessageType::Eos, gst::MessageType::Error],
+    );
+    pipeline.set_state(gst::State::Null)?;
+    Ok(())
+}
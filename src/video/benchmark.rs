@@ -0,0 +1,447 @@
+//! 编码器测速: 把生成的测试画面用每个安装了的编码器在几种分辨率下离线编码一小段,
+//! 测出这台机器能达到的帧率和 CPU 占用. 结果保存为 JSON, 设置面板据此提示当前的
+//! 配置能否实时编码.
+//!
+//! 每一项用一条独立的短管线, 与采集管线无关. 编码输出写到临时目录, 测完就删掉.
+//! CPU 占用是整个进程的, 界面里的预览也算在内.
+
+use chrono::{DateTime, Local};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::av1::Realtime;
+use super::record::{self, EncoderOptions, Resolution, VideoEncoder};
+
+/// 测试的分辨率, 与设置面板的可选项相同.
+pub(crate) const RESOLUTIONS: [Resolution; 3] = [
+    Resolution {
+        width: 1280,
+        height: 720,
+    },
+    Resolution {
+        width: 1920,
+        height: 1080,
+    },
+    Resolution {
+        width: 3840,
+        height: 2160,
+    },
+];
+/// "实时" 对应的帧率.
+pub(crate) const REFERENCE_FPS: f64 = 30.0;
+/// 快于实时但不到这个倍数时, 预览和其他处理可能挤占掉余量.
+const HEADROOM: f64 = 1.25;
+/// 每一项编码的帧数.
+const FRAMES: u32 = 120;
+/// 很慢的编码器 (例如 4K 的 AV1) 最多测这么久, 按已经编码的帧数计算.
+const CASE_TIMEOUT: Duration = Duration::from_secs(20);
+/// 等待总线消息的间隔, 期间检查是否取消.
+const POLL: gst::ClockTime = gst::ClockTime::from_mseconds(100);
+/// 编码器在测速管线中的名字.
+const ENCODER: &str = "bench_enc";
+const REPORT_FILE: &str = "benchmark.json";
+
+/// 一个编码器在一种分辨率下的结果.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct BenchmarkResult {
+    pub encoder: VideoEncoder,
+    /// 实际使用的插件, 例如 x264enc.
+    pub element: String,
+    pub res: Resolution,
+    pub fps: f64,
+    /// 与 top 相同, 多核时可以超过 100.
+    pub cpu_percent: f64,
+    /// 管线出错时的原因, 这时帧率没有意义.
+    pub error: Option<String>,
+}
+
+impl BenchmarkResult {
+    /// 编码速度是 [REFERENCE_FPS] 的几倍.
+    pub fn realtime(&self) -> f64 {
+        self.fps / REFERENCE_FPS
+    }
+
+    /// 例如 "x264enc 1080p: 1.8× realtime".
+    pub fn label(&self) -> String {
+        match &self.error {
+            Some(e) => format!("{} {}p: failed ({})", self.element, self.res.height, e),
+            None => format!(
+                "{} {}p: {:.1}× realtime, cpu {:.0}%",
+                self.element,
+                self.res.height,
+                self.realtime(),
+                self.cpu_percent
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct BenchmarkReport {
+    pub run_at: DateTime<Local>,
+    pub app_version: String,
+    pub results: Vec<BenchmarkResult>,
+}
+
+impl BenchmarkReport {
+    /// 保存在配置文件旁边.
+    pub fn path() -> Option<PathBuf> {
+        crate::config::config_path().map(|p| p.with_file_name(REPORT_FILE))
+    }
+
+    /// 没有测过或者文件损坏时返回 None.
+    pub fn load() -> Option<Self> {
+        let json = std::fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&json)
+            .inspect_err(|e| tracing::warn!("could not read benchmark report: {}", e))
+            .ok()
+    }
+
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let path = Self::path().ok_or_else(|| std::io::Error::other("no config directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+
+    /// 估计这个编码器在 `res` 下是实时的几倍. 按像素数从像素数最接近的结果换算,
+    /// 没有测过这个编码器时返回 None. ProRes 的各个档次算作同一个编码器.
+    pub fn realtime(&self, encoder: VideoEncoder, res: Resolution) -> Option<f64> {
+        let pixels = |r: Resolution| r.width as f64 * r.height as f64;
+        let target = pixels(res);
+        self.results
+            .iter()
+            .filter(|r| {
+                r.error.is_none()
+                    && std::mem::discriminant(&r.encoder) == std::mem::discriminant(&encoder)
+            })
+            .min_by(|a, b| {
+                let distance = |r: &BenchmarkResult| (pixels(r.res) / target).ln().abs();
+                distance(a).total_cmp(&distance(b))
+            })
+            .map(|r| r.realtime() * pixels(r.res) / target)
+    }
+}
+
+/// 按测出的速度判断能否跟上实时.
+pub(crate) fn sustainable(realtime: f64) -> Realtime {
+    if realtime < 1.0 {
+        Realtime::Unlikely
+    } else if realtime < HEADROOM {
+        Realtime::Marginal
+    } else {
+        Realtime::Likely
+    }
+}
+
+/// 后台测速的状态.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BenchmarkState {
+    /// 已经完成的项数和总项数.
+    Running {
+        done: usize,
+        total: usize,
+    },
+    Done(BenchmarkReport),
+    Failed(String),
+    Cancelled,
+}
+
+/// 在后台线程中测速, 界面每帧查看状态.
+pub(crate) struct Benchmark {
+    state: Arc<Mutex<BenchmarkState>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Benchmark {
+    pub fn spawn(options: EncoderOptions) -> Self {
+        let state = Arc::new(Mutex::new(BenchmarkState::Running { done: 0, total: 0 }));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (shared, cancelled) = (state.clone(), cancel.clone());
+        let spawned = std::thread::Builder::new()
+            .name("benchmark".into())
+            .spawn(move || {
+                let result = run(options, &cancelled, |done, total| {
+                    *shared.lock() = BenchmarkState::Running { done, total };
+                });
+                *shared.lock() = match result {
+                    Ok(report) => BenchmarkState::Done(report),
+                    Err(_) if cancelled.load(Ordering::Relaxed) => BenchmarkState::Cancelled,
+                    Err(e) => BenchmarkState::Failed(e),
+                };
+            });
+        if let Err(e) = spawned {
+            *state.lock() = BenchmarkState::Failed(e.to_string());
+        }
+        Self { state, cancel }
+    }
+
+    pub fn state(&self) -> BenchmarkState {
+        self.state.lock().clone()
+    }
+
+    /// 当前这一项最多再跑 [POLL] 就停下, 不保存结果.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Benchmark {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// 依次测试每个安装了的编码器, 完成后保存报告. `progress` 收到已完成的项数和总项数.
+pub(crate) fn run(
+    options: EncoderOptions,
+    cancel: &AtomicBool,
+    progress: impl Fn(usize, usize),
+) -> Result<BenchmarkReport, String> {
+    let cases: Vec<_> = VideoEncoder::ALL
+        .into_iter()
+        .filter_map(|enc| match record::encoder_description(enc, options) {
+            Ok(description) if is_installed(&description) => Some((enc, description)),
+            Ok(description) => {
+                tracing::info!(encoder = %description, "encoder not installed, skipping");
+                None
+            }
+            Err(e) => {
+                tracing::info!(encoder = %enc, "skipping: {}", e);
+                None
+            }
+        })
+        .flat_map(|(enc, description)| RESOLUTIONS.map(|res| (enc, description.clone(), res)))
+        .collect();
+    if cases.is_empty() {
+        return Err("no encoders installed".to_string());
+    }
+
+    let dir = TempDir::create()?;
+    let mut results = Vec::with_capacity(cases.len());
+    progress(0, cases.len());
+    for (i, (enc, description, res)) in cases.iter().enumerate() {
+        let output = dir.0.join(format!("case{}", i));
+        let measured = measure(*enc, description, *res, &output, cancel);
+        let _ = std::fs::remove_file(&output);
+        if cancel.load(Ordering::Relaxed) {
+            return Err("cancelled".to_string());
+        }
+        let element = description
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let result = match measured {
+            Ok((fps, cpu_percent)) => BenchmarkResult {
+                encoder: *enc,
+                element,
+                res: *res,
+                fps,
+                cpu_percent,
+                error: None,
+            },
+            Err(e) => BenchmarkResult {
+                encoder: *enc,
+                element,
+                res: *res,
+                fps: 0.0,
+                cpu_percent: 0.0,
+                error: Some(e),
+            },
+        };
+        tracing::info!("benchmark {}", result.label());
+        results.push(result);
+        progress(i + 1, cases.len());
+    }
+
+    let report = BenchmarkReport {
+        run_at: Local::now(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        results,
+    };
+    match report.save() {
+        Ok(path) => tracing::info!(path = %path.display(), "saved benchmark report"),
+        Err(e) => tracing::warn!("could not save benchmark report: {}", e),
+    }
+    Ok(report)
+}
+
+fn is_installed(description: &str) -> bool {
+    description
+        .split_whitespace()
+        .next()
+        .is_some_and(|e| gst::ElementFactory::find(e).is_some())
+}
+
+/// 编码一段测试画面, 返回帧率和 CPU 占用. 从第一帧进入编码器开始计时, 不算管线启动.
+fn measure(
+    enc: VideoEncoder,
+    description: &str,
+    res: Resolution,
+    output: &Path,
+    cancel: &AtomicBool,
+) -> Result<(f64, f64), String> {
+    // 画面要有运动, 静止的彩条对帧间编码器太容易
+    let launch = format!(
+        "videotestsrc num-buffers={frames} pattern=smpte horizontal-speed=8 !
+        video/x-raw,width={width},height={height},framerate={fps}/1 !
+        videoconvert ! video/x-raw,format={format} !
+        {encoder} name={name} !
+        filesink sync=false location=\"{path}\"",
+        frames = FRAMES,
+        width = res.width,
+        height = res.height,
+        fps = REFERENCE_FPS as u32,
+        format = record::input_format(enc, false).unwrap_or("I420"),
+        encoder = description,
+        name = ENCODER,
+        path = output.display(),
+    );
+    let pipeline = gst::parse::launch(&launch)
+        .map_err(|e| e.to_string())?
+        .dynamic_cast::<gst::Pipeline>()
+        .map_err(|_| "not a pipeline".to_string())?;
+    let sink = pipeline
+        .by_name(ENCODER)
+        .and_then(|e| e.static_pad("sink"))
+        .ok_or("encoder has no sink pad")?;
+
+    let frames = Arc::new(AtomicU64::new(0));
+    let first = Arc::new(Mutex::new(None::<(Instant, Duration)>));
+    let (counted, started) = (frames.clone(), first.clone());
+    sink.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+        if counted.fetch_add(1, Ordering::Relaxed) == 0 {
+            *started.lock() = Some((Instant::now(), cpu_time()));
+        }
+        gst::PadProbeReturn::Ok
+    });
+
+    let bus = pipeline.bus().ok_or("benchmark pipeline has no bus")?;
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + CASE_TIMEOUT;
+    let outcome = loop {
+        if cancel.load(Ordering::Relaxed) {
+            break Err("cancelled".to_string());
+        }
+        if Instant::now() >= deadline {
+            break Ok(());
+        }
+        let Some(message) =
+            bus.timed_pop_filtered(POLL, &[gst::MessageType::Eos, gst::MessageType::Error])
+        else {
+            continue;
+        };
+        match message.view() {
+            gst::MessageView::Error(err) => break Err(err.error().to_string()),
+            _ => break Ok(()),
+        }
+    };
+    let (ended, cpu_end) = (Instant::now(), cpu_time());
+    let _ = pipeline.set_state(gst::State::Null);
+    outcome?;
+
+    let (started_at, cpu_start) = first.lock().ok_or("no frames reached the encoder")?;
+    let elapsed = ended.saturating_duration_since(started_at).as_secs_f64();
+    if elapsed <= 0.0 {
+        return Err("encoding finished too quickly to measure".to_string());
+    }
+    let cpu = cpu_end.saturating_sub(cpu_start).as_secs_f64();
+    Ok((
+        frames.load(Ordering::Relaxed) as f64 / elapsed,
+        cpu / elapsed * 100.0,
+    ))
+}
+
+/// 进程累计的用户态和内核态 CPU 时间.
+fn cpu_time() -> Duration {
+    // SAFETY: getrusage 只写入我们提供的结构体
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return Duration::ZERO;
+    }
+    let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000);
+    time(usage.ru_utime) + time(usage.ru_stime)
+}
+
+/// 编码输出的临时目录, 测完或者出错时连同里面的文件一起删掉.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn create() -> Result<Self, String> {
+        let dir = std::env::temp_dir().join(format!("cam-ui-benchmark-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            tracing::warn!(dir = %self.0.display(), "could not remove benchmark files: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(encoder: VideoEncoder, height: u32, fps: f64) -> BenchmarkResult {
+        BenchmarkResult {
+            encoder,
+            element: "x264enc".to_string(),
+            res: Resolution {
+                width: height * 16 / 9,
+                height,
+            },
+            fps,
+            cpu_percent: 100.0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn estimate_scales_the_closest_result_by_pixels() {
+        let report = BenchmarkReport {
+            results: vec![
+                result(VideoEncoder::H264, 720, 120.0),
+                result(VideoEncoder::H264, 1080, 54.0),
+                BenchmarkResult {
+                    error: Some("out of memory".to_string()),
+                    ..result(VideoEncoder::H264, 2160, 0.0)
+                },
+            ],
+            ..Default::default()
+        };
+        let uhd = Resolution {
+            width: 3840,
+            height: 2160,
+        };
+        let hd = Resolution {
+            width: 1920,
+            height: 1080,
+        };
+        assert_eq!(report.realtime(VideoEncoder::H264, hd), Some(1.8));
+        // 失败的 4K 结果不算, 从 1080p 按四倍的像素数换算
+        let estimate = report.realtime(VideoEncoder::H264, uhd).unwrap();
+        assert!((estimate - 0.45).abs() < 1e-9, "{}", estimate);
+        assert_eq!(report.realtime(VideoEncoder::Vp9, hd), None);
+        assert_eq!(
+            report.results[1].label(),
+            "x264enc 1080p: 1.8× realtime, cpu 100%"
+        );
+    }
+}
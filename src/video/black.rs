@@ -0,0 +1,175 @@
+//! 黑画面检测: 忘了摘镜头盖, 或者 SDI 信号是黑场时提示.
+//! 故意拍的暗场景也有细节和噪点, 所以除了平均亮度低, 还要求亮度几乎没有起伏.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// 横竖都每隔这么多像素采样一次.
+const STRIDE: usize = 8;
+
+/// 属于画面辅助设置, 随预设切换.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct BlackFrameSettings {
+    pub enabled: bool,
+    /// 平均亮度 (0 到 255) 不超过这个值.
+    pub max_mean: f32,
+    /// 并且亮度的标准差不超过这个值.
+    pub max_deviation: f32,
+    /// 持续这么多秒才提示.
+    pub after_secs: u32,
+}
+
+impl Default for BlackFrameSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_mean: 16.0,
+            max_deviation: 3.0,
+            after_secs: 3,
+        }
+    }
+}
+
+impl BlackFrameSettings {
+    fn is_black(&self, luma: LumaStats) -> bool {
+        luma.mean <= self.max_mean && luma.deviation <= self.max_deviation
+    }
+}
+
+/// 一帧的 BT.709 亮度的平均值和标准差.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct LumaStats {
+    pub mean: f32,
+    pub deviation: f32,
+}
+
+impl LumaStats {
+    /// 隔行隔列采样, 空图像返回 None.
+    pub fn from_image(image: &egui::ColorImage) -> Option<Self> {
+        let [width, height] = image.size;
+        let (mut sum, mut sum_sq, mut count) = (0.0f64, 0.0f64, 0u32);
+        for y in (0..height).step_by(STRIDE) {
+            for pixel in image.pixels[y * width..(y + 1) * width]
+                .iter()
+                .step_by(STRIDE)
+            {
+                let [r, g, b, _] = pixel.to_array();
+                let luma = 0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64;
+                sum += luma;
+                sum_sq += luma * luma;
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return None;
+        }
+        let mean = sum / count as f64;
+        let variance = (sum_sq / count as f64 - mean * mean).max(0.0);
+        Some(Self {
+            mean: mean as f32,
+            deviation: variance.sqrt() as f32,
+        })
+    }
+}
+
+/// [BlackFrameDetector::update] 报告的变化.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BlackFrameChange {
+    /// 画面已经黑了 [BlackFrameSettings::after_secs] 秒.
+    Started,
+    /// 恢复了正常的画面, 带上从变黑开始的时长.
+    Ended(Duration),
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BlackFrameDetector {
+    /// 连续黑画面的开始时间.
+    since: Option<Instant>,
+    active: bool,
+}
+
+impl BlackFrameDetector {
+    /// 每个预览帧调用一次.
+    pub fn update(
+        &mut self,
+        luma: LumaStats,
+        settings: &BlackFrameSettings,
+        now: Instant,
+    ) -> Option<BlackFrameChange> {
+        if !settings.is_black(luma) {
+            let since = self.since.take()?;
+            return std::mem::take(&mut self.active)
+                .then(|| BlackFrameChange::Ended(now.saturating_duration_since(since)));
+        }
+        let since = *self.since.get_or_insert(now);
+        let after = Duration::from_secs(settings.after_secs as u64);
+        if !self.active && now.saturating_duration_since(since) >= after {
+            self.active = true;
+            return Some(BlackFrameChange::Started);
+        }
+        None
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.active
+    }
+
+    /// 关闭检测或没有画面时调用, 不报告结束.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_low_mean_and_flat_image() {
+        let black = egui::ColorImage::new([32, 32], vec![egui::Color32::from_gray(4); 1024]);
+        let luma = LumaStats::from_image(&black).unwrap();
+        assert!(BlackFrameSettings::default().is_black(luma));
+
+        // 暗但有细节: 一半是 0, 一半是 24, 平均值很低但起伏大
+        let pixels = (0..1024)
+            .map(|i| egui::Color32::from_gray(if (i / 8) % 2 == 0 { 0 } else { 24 }))
+            .collect();
+        let dark = egui::ColorImage::new([32, 32], pixels);
+        let luma = LumaStats::from_image(&dark).unwrap();
+        assert!(luma.mean <= 16.0);
+        assert!(!BlackFrameSettings::default().is_black(luma));
+    }
+
+    #[test]
+    fn warns_after_the_delay_and_reports_the_end() {
+        let settings = BlackFrameSettings::default();
+        let black = LumaStats {
+            mean: 2.0,
+            deviation: 0.5,
+        };
+        let normal = LumaStats {
+            mean: 90.0,
+            deviation: 40.0,
+        };
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut detector = BlackFrameDetector::default();
+        assert_eq!(detector.update(black, &settings, at(0)), None);
+        assert_eq!(detector.update(black, &settings, at(2)), None);
+        assert_eq!(
+            detector.update(black, &settings, at(3)),
+            Some(BlackFrameChange::Started)
+        );
+        assert_eq!(detector.update(black, &settings, at(4)), None);
+        assert_eq!(
+            detector.update(normal, &settings, at(10)),
+            Some(BlackFrameChange::Ended(Duration::from_secs(10)))
+        );
+        // 短暂的黑场不提示
+        detector.update(black, &settings, at(11));
+        assert_eq!(detector.update(normal, &settings, at(12)), None);
+        assert!(!detector.is_black());
+    }
+}
@@ -0,0 +1,207 @@
+//! 管线总线上的 QoS 和警告. QoS 消息按元素统计迟到和丢弃的 buffer, 录完写入 sidecar;
+//! 警告提示给用户, 同一个元素出问题时往往每帧一条, 所以要限流.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 同一个元素的警告在这段时间内只提示一次, 其余的计数后合并到下一次提示.
+const WARNING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 从开始录制起迟到和被丢弃的 buffer 数, 来自 QoS 消息. 写入 sidecar.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct BufferStats {
+    /// QoS 消息数, 每条对应一个迟到的 buffer.
+    pub late: u64,
+    pub dropped: u64,
+    /// 按元素名分别统计.
+    pub elements: BTreeMap<String, ElementQos>,
+    /// 录制分支自己的帧计数.
+    pub frames: FrameStats,
+}
+
+/// 进入录制分支的帧和实际编码的帧. 编码器跟不上时录制队列丢弃旧帧.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct FrameStats {
+    pub entered: u64,
+    pub encoded: u64,
+    pub dropped: u64,
+    /// 为保持恒定帧率重复的帧和跳过的帧, 不算作丢帧.
+    pub repeated: u64,
+    pub skipped: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ElementQos {
+    pub late: u64,
+    pub dropped: u64,
+}
+
+/// 统计总线上的 QoS 消息, 并对警告限流.
+pub(super) struct BusMonitor {
+    stats: Arc<Mutex<BufferStats>>,
+    /// 每个元素最近一次 QoS 消息中的累计丢弃数, 用于计算增量.
+    last_dropped: HashMap<String, u64>,
+    warnings: HashMap<String, WarningState>,
+}
+
+struct WarningState {
+    shown_at: Instant,
+    suppressed: u32,
+}
+
+impl BusMonitor {
+    pub fn new(stats: Arc<Mutex<BufferStats>>) -> Self {
+        Self {
+            stats,
+            last_dropped: HashMap::new(),
+            warnings: HashMap::new(),
+        }
+    }
+
+    /// 开始新的录制时清零计数.
+    pub fn reset(&mut self) {
+        *self.stats.lock() = BufferStats::default();
+    }
+
+    pub fn snapshot(&self) -> BufferStats {
+        self.stats.lock().clone()
+    }
+
+    pub fn set_frames(&mut self, frames: FrameStats) {
+        self.stats.lock().frames = frames;
+    }
+
+    /// `dropped_total` 是消息中该元素的累计丢弃数, 未知时为 None.
+    pub fn on_qos(&mut self, element: &str, dropped_total: Option<u64>) {
+        let dropped = match dropped_total {
+            Some(total) => {
+                let last = self.last_dropped.insert(element.to_string(), total);
+                total.saturating_sub(last.unwrap_or(0))
+            }
+            None => 0,
+        };
+        let mut stats = self.stats.lock();
+        stats.late += 1;
+        stats.dropped += dropped;
+        let counts = stats.elements.entry(element.to_string()).or_default();
+        counts.late += 1;
+        counts.dropped += dropped;
+    }
+
+    /// 返回需要提示给用户的文本, 被限流时返回 None.
+    pub fn on_warning(&mut self, element: &str, message: &str, now: Instant) -> Option<String> {
+        match self.warnings.get_mut(element) {
+            Some(state) if now.duration_since(state.shown_at) < WARNING_INTERVAL => {
+                state.suppressed += 1;
+                None
+            }
+            Some(state) => {
+                let text = match state.suppressed {
+                    0 => format!("{}: {}", element, message),
+                    n => format!("{}: {} (+{} similar)", element, message, n),
+                };
+                state.shown_at = now;
+                state.suppressed = 0;
+                Some(text)
+            }
+            None => {
+                self.warnings.insert(
+                    element.to_string(),
+                    WarningState {
+                        shown_at: now,
+                        suppressed: 0,
+                    },
+                );
+                Some(format!("{}: {}", element, message))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> BusMonitor {
+        BusMonitor::new(Arc::default())
+    }
+
+    #[test]
+    fn counts_dropped_buffers_as_deltas_per_element() {
+        let mut monitor = monitor();
+        monitor.on_qos("queue", Some(5));
+        monitor.on_qos("queue", Some(8));
+        monitor.on_qos("sink", None);
+        let stats = monitor.snapshot();
+        assert_eq!((stats.late, stats.dropped), (3, 8));
+        assert_eq!(
+            stats.elements["queue"],
+            ElementQos {
+                late: 2,
+                dropped: 8
+            }
+        );
+        assert_eq!(
+            stats.elements["sink"],
+            ElementQos {
+                late: 1,
+                dropped: 0
+            }
+        );
+        // 元素重启后计数从头开始, 不会算成负数
+        monitor.on_qos("queue", Some(2));
+        assert_eq!(monitor.snapshot().elements["queue"].dropped, 8);
+    }
+
+    #[test]
+    fn reset_clears_the_stats_but_keeps_counting_deltas() {
+        let mut monitor = monitor();
+        monitor.on_qos("queue", Some(5));
+        monitor.set_frames(FrameStats {
+            entered: 10,
+            ..Default::default()
+        });
+        monitor.reset();
+        assert_eq!(monitor.snapshot(), BufferStats::default());
+        monitor.on_qos("queue", Some(6));
+        assert_eq!(monitor.snapshot().dropped, 1);
+    }
+
+    #[test]
+    fn merges_warnings_inside_the_interval() {
+        let mut monitor = monitor();
+        let start = Instant::now();
+        assert_eq!(
+            monitor.on_warning("src", "lost sync", start).as_deref(),
+            Some("src: lost sync")
+        );
+        assert_eq!(
+            monitor.on_warning("src", "lost sync", start + Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            monitor.on_warning("src", "lost sync", start + Duration::from_secs(9)),
+            None
+        );
+        // 其他元素不受影响
+        assert!(monitor.on_warning("enc", "slow", start).is_some());
+        assert_eq!(
+            monitor
+                .on_warning("src", "lost sync", start + WARNING_INTERVAL)
+                .as_deref(),
+            Some("src: lost sync (+2 similar)")
+        );
+        assert_eq!(
+            monitor
+                .on_warning("src", "lost sync", start + WARNING_INTERVAL * 2)
+                .as_deref(),
+            Some("src: lost sync")
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! tee 之前可以在运行中开关的一段处理. output-selector 把帧送到直通或处理其中一路,
+//! input-selector 从同一路取回, 切换不需要重建管线. 关闭时处理的元素收不到帧, 不占 CPU.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+
+/// 以 `!` 结尾的一段描述, 第一路直通, 第二路经过 `filter`.
+/// 选择器命名为 `{prefix}_split` 和 `{prefix}_join`.
+pub(super) fn description(prefix: &str, filter: &str) -> String {
+    format!(
+        "output-selector name={prefix}_split pad-negotiation-mode=active
+        {prefix}_split. ! queue ! {prefix}_join.
+        {prefix}_split. ! queue ! {filter} ! {prefix}_join.
+        input-selector name={prefix}_join !",
+    )
+}
+
+pub(super) struct Bypass {
+    split: gst::Element,
+    join: gst::Element,
+    /// 直通和处理两路在两个选择器上的 pad.
+    bypass: (gst::Pad, gst::Pad),
+    filtered: (gst::Pad, gst::Pad),
+}
+
+impl Bypass {
+    /// 刚构建时走直通的一路.
+    pub fn attach(pipeline: &gst::Pipeline, prefix: &str) -> Result<Self, String> {
+        let by_name = |name: String| {
+            pipeline
+                .by_name(&name)
+                .ok_or_else(|| format!("pipeline has no element named '{}'", name))
+        };
+        let split = by_name(format!("{}_split", prefix))?;
+        let join = by_name(format!("{}_join", prefix))?;
+        let pad = |el: &gst::Element, name: &str| {
+            el.static_pad(name)
+                .ok_or_else(|| format!("{} has no pad {}", el.name(), name))
+        };
+        let bypass = Self {
+            bypass: (pad(&split, "src_0")?, pad(&join, "sink_0")?),
+            filtered: (pad(&split, "src_1")?, pad(&join, "sink_1")?),
+            split,
+            join,
+        };
+        bypass.select(false);
+        Ok(bypass)
+    }
+
+    pub fn select(&self, filtered: bool) {
+        let (out, input) = if filtered {
+            &self.filtered
+        } else {
+            &self.bypass
+        };
+        self.split.set_property("active-pad", out);
+        self.join.set_property("active-pad", input);
+    }
+
+    /// 协商好的输入分辨率.
+    pub fn input_size(&self) -> Option<(u32, u32)> {
+        let caps = self.split.static_pad("sink")?.current_caps()?;
+        let info = gst_video::VideoInfo::from_caps(&caps).ok()?;
+        Some((info.width(), info.height()))
+    }
+}
@@ -0,0 +1,890 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use super::shutter::ShutterSpeed;
+
+/// 界面上可选的 ISO 档位.
+pub(crate) const ISO_STEPS: [u32; 7] = [100, 200, 400, 800, 1600, 3200, 6400];
+/// 细调时的 1/3 档 ISO, 包含 [ISO_STEPS] 中的所有档位.
+pub(crate) const ISO_THIRD_STEPS: [u32; 19] = [
+    100, 125, 160, 200, 250, 320, 400, 500, 640, 800, 1000, 1250, 1600, 2000, 2500, 3200, 4000,
+    5000, 6400,
+];
+
+const V4L2_CID_GAIN: u32 = 0x0098_0913;
+const V4L2_CID_AUTO_WHITE_BALANCE: u32 = 0x0098_090c;
+/// 单位为 K.
+const V4L2_CID_WHITE_BALANCE_TEMPERATURE: u32 = 0x0098_091a;
+const V4L2_CID_ISO_SENSITIVITY: u32 = 0x009a_0917;
+const V4L2_CID_EXPOSURE_AUTO: u32 = 0x009a_0901;
+/// 单位为 100 µs.
+const V4L2_CID_EXPOSURE_ABSOLUTE: u32 = 0x009a_0902;
+const V4L2_EXPOSURE_AUTO: i32 = 0;
+const V4L2_EXPOSURE_MANUAL: i32 = 1;
+const V4L2_EXPOSURE_APERTURE_PRIORITY: i32 = 3;
+/// 整数菜单, 单位为 0.001 EV.
+const V4L2_CID_AUTO_EXPOSURE_BIAS: u32 = 0x009a_0913;
+/// 曝光控件的单位换算成微秒.
+const MICROS_PER_EXPOSURE_UNIT: u64 = 100;
+
+const V4L2_CID_FOCUS_ABSOLUTE: u32 = 0x009a_090a;
+const V4L2_CID_FOCUS_AUTO: u32 = 0x009a_090c;
+/// 按钮控件, 触发一次自动对焦. UVC 摄像头通常没有.
+const V4L2_CID_AUTO_FOCUS_START: u32 = 0x009a_091c;
+
+const V4L2_CTRL_TYPE_INTEGER: u32 = 1;
+const V4L2_CTRL_TYPE_BOOLEAN: u32 = 2;
+const V4L2_CTRL_TYPE_MENU: u32 = 3;
+const V4L2_CTRL_TYPE_BUTTON: u32 = 4;
+const V4L2_CTRL_TYPE_CTRL_CLASS: u32 = 6;
+const V4L2_CTRL_TYPE_INTEGER_MENU: u32 = 9;
+const V4L2_CTRL_FLAG_DISABLED: u32 = 0x0001;
+const V4L2_CTRL_FLAG_GRABBED: u32 = 0x0002;
+const V4L2_CTRL_FLAG_READ_ONLY: u32 = 0x0004;
+const V4L2_CTRL_FLAG_INACTIVE: u32 = 0x0010;
+const V4L2_CTRL_FLAG_WRITE_ONLY: u32 = 0x0040;
+/// 与控件 ID 按位或, 查询下一个控件.
+const V4L2_CTRL_FLAG_NEXT_CTRL: u32 = 0x8000_0000;
+
+/// 从 gst-launch 语法的视频源中取出 V4L2 设备路径, 不是 v4l2src 时返回 None.
+pub(crate) fn v4l2_device(source: &str) -> Option<&str> {
+    let mut words = source.split_whitespace();
+    if words.next()? != "v4l2src" {
+        return None;
+    }
+    // 没有指定时 v4l2src 使用 /dev/video0
+    Some(
+        words
+            .find_map(|w| w.strip_prefix("device="))
+            .unwrap_or("/dev/video0"),
+    )
+}
+
+/// 曝光补偿的范围, 以 1/3 EV 为一档, 即 ±3 EV.
+pub(crate) const MAX_EV_THIRDS: i32 = 9;
+
+/// ISO 档位与控件值之间的换算.
+#[derive(Debug, Clone, PartialEq)]
+enum Mapping {
+    /// 控件值就是 ISO.
+    Iso { min: i32, max: i32 },
+    /// 整数菜单, 每一项 (序号, ISO) 对应一个 ISO 值.
+    IsoMenu(Vec<(i32, i64)>),
+    /// 没有单位的增益, ISO 档位按对数均匀分布在 [min, max] 上.
+    Gain { min: i32, max: i32, step: i32 },
+}
+
+impl Mapping {
+    fn to_value(&self, iso: u32) -> i32 {
+        match self {
+            Mapping::Iso { min, max } => (iso as i32).clamp(*min, *max),
+            Mapping::IsoMenu(items) => items
+                .iter()
+                .min_by_key(|(_, value)| value.abs_diff(iso as i64))
+                .map_or(0, |(index, _)| *index),
+            Mapping::Gain { min, max, step } => {
+                // 档位之间按对数插值, 曝光补偿模拟出的 ISO 不一定在档位上
+                let (low, high) = (ISO_STEPS[0] as f64, ISO_STEPS[ISO_STEPS.len() - 1] as f64);
+                let fraction = ((iso as f64).clamp(low, high) / low).ln() / (high / low).ln();
+                let span = (*max - *min) as f64;
+                let raw = *min as f64 + span * fraction;
+                // 对齐到控件的步进
+                let step = (*step).max(1) as f64;
+                let snapped = *min as f64 + ((raw - *min as f64) / step).round() * step;
+                (snapped as i32).clamp(*min, *max)
+            }
+        }
+    }
+
+    fn to_iso(&self, value: i32) -> u32 {
+        match self {
+            Mapping::Iso { .. } => value.max(0) as u32,
+            Mapping::IsoMenu(items) => items
+                .iter()
+                .find(|(index, _)| *index == value)
+                .map_or(0, |(_, iso)| *iso as u32),
+            Mapping::Gain { min, max, .. } => {
+                if max <= min {
+                    return ISO_STEPS[0];
+                }
+                let fraction = ((value - min) as f64 / (max - min) as f64).clamp(0.0, 1.0);
+                let (low, high) = (ISO_STEPS[0] as f64, ISO_STEPS[ISO_STEPS.len() - 1] as f64);
+                let iso = low * (high / low).powf(fraction);
+                ISO_THIRD_STEPS[nearest(&ISO_THIRD_STEPS, iso.round() as u32)]
+            }
+        }
+    }
+}
+
+/// 最接近 `value` 的档位序号.
+fn nearest(steps: &[u32], value: u32) -> usize {
+    steps
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, step)| step.abs_diff(value))
+        .map_or(0, |(index, _)| index)
+}
+
+/// `steps` 中与 `value` 相邻的档位, `delta` 为 +1 或 -1, 到头时不变.
+pub(crate) fn next_step(steps: &[u32], value: u32, delta: i32) -> u32 {
+    let index = nearest(steps, value) as i32 + delta;
+    steps[index.clamp(0, steps.len() as i32 - 1) as usize]
+}
+
+/// 没有曝光补偿控件时用增益模拟: 每 +1 EV 把 ISO 翻倍.
+pub(crate) fn compensated_iso(iso: u32, ev_thirds: i32) -> u32 {
+    (iso as f64 * 2f64.powf(ev_thirds as f64 / 3.0)).round() as u32
+}
+
+/// 菜单中最接近 `ev_thirds` 的一项的序号.
+fn bias_index(items: &[(i32, i64)], ev_thirds: i32) -> Option<i32> {
+    let milli_ev = (ev_thirds as f64 * 1000.0 / 3.0).round() as i64;
+    items
+        .iter()
+        .min_by_key(|(_, value)| value.abs_diff(milli_ev))
+        .map(|(index, _)| *index)
+}
+
+/// 快门速度换算成控件值 (100 µs), 限制在控件范围内.
+fn exposure_value(speed: ShutterSpeed, min: i32, max: i32) -> i32 {
+    let units = (speed.micros() as f64 / MICROS_PER_EXPOSURE_UNIT as f64).round();
+    (units.min(i32::MAX as f64) as i32).clamp(min, max.max(min))
+}
+
+/// 控件值换算成最接近的标准快门档位.
+fn shutter_step(value: i32) -> ShutterSpeed {
+    ShutterSpeed::nearest(value.max(1) as u64 * MICROS_PER_EXPOSURE_UNIT)
+}
+
+/// 摄像头的增益控件. 优先使用 ISO 感光度, 没有时使用增益.
+pub(crate) struct SensorGain {
+    file: File,
+    cid: u32,
+    /// `extra-controls` 中的字段名.
+    name: &'static str,
+    mapping: Mapping,
+}
+
+impl SensorGain {
+    /// 设备没有可用的控件时返回 None.
+    pub fn open(device: &Path) -> Option<Self> {
+        let file = match open_device(device) {
+            Ok(file) => file,
+            Err(e) => {
+                tracing::info!(device = %device.display(), "could not open device for controls: {}", e);
+                return None;
+            }
+        };
+        for (cid, name) in [
+            (V4L2_CID_ISO_SENSITIVITY, "iso_sensitivity"),
+            (V4L2_CID_GAIN, "gain"),
+        ] {
+            let Ok(ctrl) = query_control(&file, cid) else {
+                continue;
+            };
+            if ctrl.flags & V4L2_CTRL_FLAG_DISABLED != 0 {
+                continue;
+            }
+            let mapping = match (cid, ctrl.type_) {
+                (V4L2_CID_ISO_SENSITIVITY, V4L2_CTRL_TYPE_INTEGER) => Mapping::Iso {
+                    min: ctrl.minimum,
+                    max: ctrl.maximum,
+                },
+                (V4L2_CID_ISO_SENSITIVITY, V4L2_CTRL_TYPE_INTEGER_MENU) => {
+                    let items: Vec<_> = (ctrl.minimum..=ctrl.maximum)
+                        .filter_map(|index| Some((index, query_menu(&file, cid, index).ok()?)))
+                        .collect();
+                    if items.is_empty() {
+                        continue;
+                    }
+                    Mapping::IsoMenu(items)
+                }
+                (V4L2_CID_GAIN, V4L2_CTRL_TYPE_INTEGER) if ctrl.maximum > ctrl.minimum => {
+                    Mapping::Gain {
+                        min: ctrl.minimum,
+                        max: ctrl.maximum,
+                        step: ctrl.step,
+                    }
+                }
+                _ => continue,
+            };
+            tracing::info!(device = %device.display(), control = name, ?mapping, "using sensor gain control");
+            return Some(Self {
+                file,
+                cid,
+                name,
+                mapping,
+            });
+        }
+        tracing::info!(device = %device.display(), "device has no gain control");
+        None
+    }
+
+    /// 当前的值换算成最接近的 ISO.
+    pub fn iso(&self) -> io::Result<u32> {
+        get_control(&self.file, self.cid).map(|value| self.mapping.to_iso(value))
+    }
+
+    pub fn set_iso(&self, iso: u32) -> io::Result<()> {
+        set_control(&self.file, self.cid, self.mapping.to_value(iso))
+    }
+
+    /// 直接设置失败时, 通过 v4l2src 的 `extra-controls` 属性设置, 例如 "c,gain=42".
+    pub fn extra_controls(&self, iso: u32) -> String {
+        format!("c,{}={}", self.name, self.mapping.to_value(iso))
+    }
+}
+
+/// 曝光控制方式, 也写入 sidecar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExposureMode {
+    Auto,
+    /// 冻结了自动曝光当时的值.
+    Locked,
+    /// 手动选择的快门.
+    Manual,
+}
+
+/// 录制时的曝光状态.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ExposureState {
+    pub mode: ExposureMode,
+    /// 自动曝光时的补偿, 单位 EV.
+    pub compensation_ev: f32,
+    /// 锁定或手动时的快门, 例如 "1/500".
+    pub shutter: Option<String>,
+    /// 界面上选择的 ISO, 摄像头没有增益控件时为 None.
+    #[serde(default)]
+    pub iso: Option<u32>,
+}
+
+/// 曝光时间控件. 打开时保持摄像头当前的曝光模式.
+pub(crate) struct Exposure {
+    file: File,
+    min: i32,
+    max: i32,
+    /// 回到自动曝光时使用的模式, 只支持手动曝光的摄像头为 None.
+    auto_mode: Option<i32>,
+}
+
+impl Exposure {
+    /// 设备不支持手动曝光时返回原因, 显示在界面的提示中.
+    pub fn open(device: &Path) -> Result<Self, String> {
+        let file = open_device(device)
+            .map_err(|e| format!("Could not open {}: {}", device.display(), e))?;
+        let ctrl = query_control(&file, V4L2_CID_EXPOSURE_ABSOLUTE)
+            .map_err(|_| "This camera has no manual exposure control".to_string())?;
+        if ctrl.flags & V4L2_CTRL_FLAG_DISABLED != 0 {
+            return Err("Manual exposure is disabled by the driver".to_string());
+        }
+        // 有些摄像头只有手动曝光, 没有模式控件. UVC 摄像头通常只支持光圈优先
+        let auto_mode = query_control(&file, V4L2_CID_EXPOSURE_AUTO)
+            .ok()
+            .map(|mode| match get_control(&file, V4L2_CID_EXPOSURE_AUTO) {
+                Ok(current) if current != V4L2_EXPOSURE_MANUAL => current,
+                _ if mode.maximum >= V4L2_EXPOSURE_APERTURE_PRIORITY => {
+                    V4L2_EXPOSURE_APERTURE_PRIORITY
+                }
+                _ => V4L2_EXPOSURE_AUTO,
+            });
+        tracing::info!(device = %device.display(), min = ctrl.minimum, max = ctrl.maximum, ?auto_mode, "using exposure control");
+        Ok(Self {
+            file,
+            min: ctrl.minimum,
+            max: ctrl.maximum,
+            auto_mode,
+        })
+    }
+
+    pub fn has_auto(&self) -> bool {
+        self.auto_mode.is_some()
+    }
+
+    /// 摄像头当前是否在自动曝光.
+    pub fn is_auto(&self) -> io::Result<bool> {
+        if self.auto_mode.is_none() {
+            return Ok(false);
+        }
+        get_control(&self.file, V4L2_CID_EXPOSURE_AUTO).map(|mode| mode != V4L2_EXPOSURE_MANUAL)
+    }
+
+    pub fn set_auto(&self) -> io::Result<()> {
+        let mode = self.auto_mode.ok_or(io::ErrorKind::Unsupported)?;
+        set_control(&self.file, V4L2_CID_EXPOSURE_AUTO, mode)
+    }
+
+    /// 读取自动曝光当前的曝光时间, 切换到手动并写回, 返回对应的快门档位.
+    pub fn lock(&self) -> io::Result<ShutterSpeed> {
+        let value = get_control(&self.file, V4L2_CID_EXPOSURE_ABSOLUTE)?;
+        self.set_manual()?;
+        // 有些驱动切换模式时会重置曝光时间
+        set_control(&self.file, V4L2_CID_EXPOSURE_ABSOLUTE, value)?;
+        Ok(shutter_step(value))
+    }
+
+    fn set_manual(&self) -> io::Result<()> {
+        if self.auto_mode.is_none() {
+            return Ok(());
+        }
+        set_control(&self.file, V4L2_CID_EXPOSURE_AUTO, V4L2_EXPOSURE_MANUAL)
+    }
+
+    /// 当前曝光时间对应的快门档位.
+    pub fn shutter(&self) -> io::Result<ShutterSpeed> {
+        get_control(&self.file, V4L2_CID_EXPOSURE_ABSOLUTE).map(shutter_step)
+    }
+
+    /// 设置快门时切换到手动曝光.
+    pub fn set_shutter(&self, speed: ShutterSpeed) -> io::Result<()> {
+        self.set_manual()?;
+        set_control(
+            &self.file,
+            V4L2_CID_EXPOSURE_ABSOLUTE,
+            exposure_value(speed, self.min, self.max),
+        )
+    }
+}
+
+/// 自动曝光补偿控件.
+pub(crate) struct ExposureBias {
+    file: File,
+    /// (序号, 0.001 EV).
+    items: Vec<(i32, i64)>,
+}
+
+impl ExposureBias {
+    /// 设备没有补偿控件时返回 None, 此时用增益模拟.
+    pub fn open(device: &Path) -> Option<Self> {
+        let file = open_device(device).ok()?;
+        let ctrl = query_control(&file, V4L2_CID_AUTO_EXPOSURE_BIAS).ok()?;
+        if ctrl.flags & V4L2_CTRL_FLAG_DISABLED != 0 || ctrl.type_ != V4L2_CTRL_TYPE_INTEGER_MENU {
+            return None;
+        }
+        let items: Vec<_> = (ctrl.minimum..=ctrl.maximum)
+            .filter_map(|index| {
+                Some((
+                    index,
+                    query_menu(&file, V4L2_CID_AUTO_EXPOSURE_BIAS, index).ok()?,
+                ))
+            })
+            .collect();
+        if items.is_empty() {
+            return None;
+        }
+        tracing::info!(device = %device.display(), items = items.len(), "using exposure bias control");
+        Some(Self { file, items })
+    }
+
+    pub fn set_ev_thirds(&self, ev_thirds: i32) -> io::Result<()> {
+        let index = bias_index(&self.items, ev_thirds).ok_or(io::ErrorKind::InvalidInput)?;
+        set_control(&self.file, V4L2_CID_AUTO_EXPOSURE_BIAS, index)
+    }
+}
+
+/// 白平衡色温控件.
+pub(crate) struct WhiteBalanceControl {
+    file: File,
+    min: i32,
+    max: i32,
+    has_auto: bool,
+}
+
+impl WhiteBalanceControl {
+    /// 设备没有色温控件时返回 None, 此时在管线中调整增益.
+    pub fn open(device: &Path) -> Option<Self> {
+        let file = open_device(device).ok()?;
+        let ctrl = query_control(&file, V4L2_CID_WHITE_BALANCE_TEMPERATURE).ok()?;
+        if ctrl.flags & V4L2_CTRL_FLAG_DISABLED != 0 {
+            return None;
+        }
+        let has_auto = query_control(&file, V4L2_CID_AUTO_WHITE_BALANCE).is_ok();
+        tracing::info!(device = %device.display(), min = ctrl.minimum, max = ctrl.maximum, "using white balance temperature control");
+        Some(Self {
+            file,
+            min: ctrl.minimum,
+            max: ctrl.maximum,
+            has_auto,
+        })
+    }
+
+    /// 当前色温. 自动白平衡时有些驱动读到的是上次手动设置的值.
+    pub fn kelvin(&self) -> io::Result<u32> {
+        get_control(&self.file, V4L2_CID_WHITE_BALANCE_TEMPERATURE).map(|k| k.max(0) as u32)
+    }
+
+    pub fn set_auto(&self) -> io::Result<()> {
+        if !self.has_auto {
+            return Err(io::ErrorKind::Unsupported.into());
+        }
+        set_control(&self.file, V4L2_CID_AUTO_WHITE_BALANCE, 1)
+    }
+
+    /// 关闭自动白平衡并设置色温, 超出范围时取边界.
+    pub fn set_kelvin(&self, kelvin: u32) -> io::Result<()> {
+        if self.has_auto {
+            set_control(&self.file, V4L2_CID_AUTO_WHITE_BALANCE, 0)?;
+        }
+        let value = (kelvin.min(i32::MAX as u32) as i32).clamp(self.min, self.max.max(self.min));
+        set_control(&self.file, V4L2_CID_WHITE_BALANCE_TEMPERATURE, value)
+    }
+}
+
+/// 对焦马达的控件.
+pub(crate) struct Focus {
+    file: File,
+    pub min: i32,
+    pub max: i32,
+    pub step: i32,
+    has_auto: bool,
+    has_start: bool,
+}
+
+impl Focus {
+    /// 没有对焦马达的设备返回 None, 界面上不显示对焦.
+    pub fn open(device: &Path) -> Option<Self> {
+        let file = open_device(device).ok()?;
+        let ctrl = query_control(&file, V4L2_CID_FOCUS_ABSOLUTE).ok()?;
+        if ctrl.flags & V4L2_CTRL_FLAG_DISABLED != 0 || ctrl.maximum <= ctrl.minimum {
+            return None;
+        }
+        let has_auto = query_control(&file, V4L2_CID_FOCUS_AUTO).is_ok();
+        let has_start = query_control(&file, V4L2_CID_AUTO_FOCUS_START).is_ok();
+        tracing::info!(device = %device.display(), min = ctrl.minimum, max = ctrl.maximum, has_auto, has_start, "using focus control");
+        Some(Self {
+            file,
+            min: ctrl.minimum,
+            max: ctrl.maximum,
+            step: ctrl.step.max(1),
+            has_auto,
+            has_start,
+        })
+    }
+
+    pub fn has_auto(&self) -> bool {
+        self.has_auto
+    }
+
+    /// 有一次性对焦按钮, 或者可以用连续自动对焦模拟.
+    pub fn can_trigger(&self) -> bool {
+        self.has_start || self.has_auto
+    }
+
+    pub fn position(&self) -> io::Result<i32> {
+        get_control(&self.file, V4L2_CID_FOCUS_ABSOLUTE)
+    }
+
+    /// 自动对焦时驱动会拒绝手动位置, 所以先关闭自动对焦.
+    pub fn set_position(&self, position: i32) -> io::Result<()> {
+        if self.has_auto {
+            set_control(&self.file, V4L2_CID_FOCUS_AUTO, 0)?;
+        }
+        set_control(
+            &self.file,
+            V4L2_CID_FOCUS_ABSOLUTE,
+            position.clamp(self.min, self.max),
+        )
+    }
+
+    pub fn is_auto(&self) -> io::Result<bool> {
+        if !self.has_auto {
+            return Ok(false);
+        }
+        get_control(&self.file, V4L2_CID_FOCUS_AUTO).map(|on| on != 0)
+    }
+
+    pub fn set_auto(&self, on: bool) -> io::Result<()> {
+        if !self.has_auto {
+            return Err(io::ErrorKind::Unsupported.into());
+        }
+        set_control(&self.file, V4L2_CID_FOCUS_AUTO, on as i32)
+    }
+
+    /// 触发一次自动对焦. 返回 false 表示设备没有这个按钮,
+    /// 需要调用方打开连续自动对焦一小段时间再关掉.
+    pub fn trigger(&self) -> io::Result<bool> {
+        if !self.has_start {
+            return Ok(false);
+        }
+        set_control(&self.file, V4L2_CID_AUTO_FOCUS_START, 1).map(|()| true)
+    }
+
+    /// 对焦位置在范围中的百分比.
+    pub fn percent(&self, position: i32) -> f32 {
+        focus_percent(position, self.min, self.max)
+    }
+}
+
+fn focus_percent(position: i32, min: i32, max: i32) -> f32 {
+    if max <= min {
+        return 0.0;
+    }
+    (position.clamp(min, max) - min) as f32 * 100.0 / (max - min) as f32
+}
+
+/// 通用控件面板中控件的类型.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ControlKind {
+    Integer {
+        min: i32,
+        max: i32,
+        step: i32,
+    },
+    Boolean,
+    /// 菜单项的序号和名称, 序号不一定连续.
+    Menu(Vec<(i32, String)>),
+    Button,
+}
+
+/// 设备报告的一个控件.
+#[derive(Debug, Clone)]
+pub(crate) struct DeviceControl {
+    pub id: u32,
+    pub name: String,
+    pub kind: ControlKind,
+    pub default: i32,
+    pub value: i32,
+    /// 只读或被其他进程占用, 不能设置.
+    pub read_only: bool,
+    /// 当前被其他控件覆盖, 例如自动曝光时的曝光时间.
+    pub inactive: bool,
+}
+
+impl DeviceControl {
+    pub fn is_enabled(&self) -> bool {
+        !self.read_only && !self.inactive
+    }
+}
+
+/// 通用 V4L2 控件面板, 列出设备报告的所有控件.
+pub(crate) struct ControlPanel {
+    file: File,
+    pub controls: Vec<DeviceControl>,
+}
+
+impl ControlPanel {
+    pub fn open(device: &Path) -> io::Result<Self> {
+        let file = open_device(device)?;
+        let controls = enumerate_controls(&file);
+        tracing::info!(device = %device.display(), count = controls.len(), "enumerated camera controls");
+        Ok(Self { file, controls })
+    }
+
+    /// 重新读取所有控件的值和标志, 自动模式会改变其他控件是否可用.
+    pub fn refresh(&mut self) {
+        for control in &mut self.controls {
+            if let Ok(ctrl) = query_control(&self.file, control.id) {
+                control.read_only = ctrl_read_only(ctrl.flags);
+                control.inactive = ctrl.flags & V4L2_CTRL_FLAG_INACTIVE != 0;
+            }
+            if control.kind != ControlKind::Button
+                && let Ok(value) = get_control(&self.file, control.id)
+            {
+                control.value = value;
+            }
+        }
+    }
+
+    /// 设置控件, 按钮控件的值被忽略.
+    pub fn set(&mut self, id: u32, value: i32) -> io::Result<()> {
+        let result = set_control(&self.file, id, value);
+        self.refresh();
+        result
+    }
+}
+
+fn ctrl_read_only(flags: u32) -> bool {
+    flags & (V4L2_CTRL_FLAG_READ_ONLY | V4L2_CTRL_FLAG_GRABBED) != 0
+}
+
+/// 控件类型, 不支持的类型 (字符串, 64 位整数, 复合控件) 和分组标题返回 None.
+fn control_kind(file: &File, ctrl: &QueryCtrl) -> Option<ControlKind> {
+    match ctrl.type_ {
+        V4L2_CTRL_TYPE_INTEGER => Some(ControlKind::Integer {
+            min: ctrl.minimum,
+            max: ctrl.maximum,
+            step: ctrl.step.max(1),
+        }),
+        V4L2_CTRL_TYPE_BOOLEAN => Some(ControlKind::Boolean),
+        V4L2_CTRL_TYPE_BUTTON => Some(ControlKind::Button),
+        V4L2_CTRL_TYPE_MENU | V4L2_CTRL_TYPE_INTEGER_MENU => {
+            let items: Vec<_> = (ctrl.minimum..=ctrl.maximum)
+                .filter_map(|index| {
+                    let value = query_menu_raw(file, ctrl.id, index).ok()?;
+                    let name = if ctrl.type_ == V4L2_CTRL_TYPE_MENU {
+                        c_string(&value)
+                    } else {
+                        i64::from_ne_bytes(value[..8].try_into().unwrap()).to_string()
+                    };
+                    Some((index, name))
+                })
+                .collect();
+            (!items.is_empty()).then_some(ControlKind::Menu(items))
+        }
+        _ => None,
+    }
+}
+
+fn enumerate_controls(file: &File) -> Vec<DeviceControl> {
+    let mut controls = Vec::new();
+    let mut id = 0;
+    while let Ok(ctrl) = query_control(file, id | V4L2_CTRL_FLAG_NEXT_CTRL) {
+        id = ctrl.id;
+        if ctrl.type_ == V4L2_CTRL_TYPE_CTRL_CLASS || ctrl.flags & V4L2_CTRL_FLAG_DISABLED != 0 {
+            continue;
+        }
+        let Some(kind) = control_kind(file, &ctrl) else {
+            tracing::debug!(
+                id = ctrl.id,
+                type_ = ctrl.type_,
+                "skipping unsupported control"
+            );
+            continue;
+        };
+        let value = if kind == ControlKind::Button || ctrl.flags & V4L2_CTRL_FLAG_WRITE_ONLY != 0 {
+            ctrl.default_value
+        } else {
+            get_control(file, ctrl.id).unwrap_or(ctrl.default_value)
+        };
+        controls.push(DeviceControl {
+            id: ctrl.id,
+            name: c_string(&ctrl.name),
+            kind,
+            default: ctrl.default_value,
+            value,
+            read_only: ctrl_read_only(ctrl.flags),
+            inactive: ctrl.flags & V4L2_CTRL_FLAG_INACTIVE != 0,
+        });
+    }
+    controls
+}
+
+/// 内核结构体中以 NUL 结尾的字符串.
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn open_device(device: &Path) -> io::Result<File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(device)
+}
+
+// 以下结构体与 videodev2.h 中的布局一致, 部分字段只由内核填写
+#[repr(C)]
+#[derive(Default)]
+#[allow(dead_code)]
+struct QueryCtrl {
+    id: u32,
+    type_: u32,
+    name: [u8; 32],
+    minimum: i32,
+    maximum: i32,
+    step: i32,
+    default_value: i32,
+    flags: u32,
+    reserved: [u32; 2],
+}
+
+#[repr(C)]
+struct Control {
+    id: u32,
+    value: i32,
+}
+
+#[repr(C, packed)]
+#[allow(dead_code)]
+struct QueryMenu {
+    id: u32,
+    index: u32,
+    /// 联合体: 菜单名或整数菜单的值 (i64).
+    value: [u8; 32],
+    reserved: u32,
+}
+
+/// 对应内核头文件中的 `_IOWR('V', nr, T)`.
+#[cfg(target_os = "linux")]
+const fn iowr<T>(nr: u64) -> u64 {
+    (3 << 30) | ((std::mem::size_of::<T>() as u64) << 16) | ((b'V' as u64) << 8) | nr
+}
+
+#[cfg(target_os = "linux")]
+fn ioctl<T>(file: &File, request: u64, arg: &mut T) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    // SAFETY: arg 的布局与内核结构体一致, 在调用期间有效
+    if unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg as *mut T) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn ioctl<T>(_file: &File, _request: u64, _arg: &mut T) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(target_os = "linux"))]
+const fn iowr<T>(_nr: u64) -> u64 {
+    0
+}
+
+fn query_control(file: &File, cid: u32) -> io::Result<QueryCtrl> {
+    let mut ctrl = QueryCtrl {
+        id: cid,
+        ..Default::default()
+    };
+    ioctl(file, iowr::<QueryCtrl>(36), &mut ctrl)?;
+    Ok(ctrl)
+}
+
+fn query_menu_raw(file: &File, cid: u32, index: i32) -> io::Result<[u8; 32]> {
+    let mut menu = QueryMenu {
+        id: cid,
+        index: index as u32,
+        value: [0; 32],
+        reserved: 0,
+    };
+    ioctl(file, iowr::<QueryMenu>(37), &mut menu)?;
+    Ok(menu.value)
+}
+
+fn query_menu(file: &File, cid: u32, index: i32) -> io::Result<i64> {
+    let value = query_menu_raw(file, cid, index)?;
+    Ok(i64::from_ne_bytes(value[..8].try_into().unwrap()))
+}
+
+fn get_control(file: &File, cid: u32) -> io::Result<i32> {
+    let mut ctrl = Control { id: cid, value: 0 };
+    ioctl(file, iowr::<Control>(27), &mut ctrl)?;
+    Ok(ctrl.value)
+}
+
+fn set_control(file: &File, cid: u32, value: i32) -> io::Result<()> {
+    let mut ctrl = Control { id: cid, value };
+    ioctl(file, iowr::<Control>(28), &mut ctrl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_v4l2_device_in_source() {
+        assert_eq!(
+            v4l2_device("v4l2src name=src device=/dev/video2 io-mode=2"),
+            Some("/dev/video2")
+        );
+        assert_eq!(v4l2_device("v4l2src name=src"), Some("/dev/video0"));
+        assert_eq!(v4l2_device("videotestsrc name=src is-live=true"), None);
+    }
+
+    #[test]
+    fn iso_steps_span_the_gain_range() {
+        let gain = Mapping::Gain {
+            min: 0,
+            max: 100,
+            step: 1,
+        };
+        assert_eq!(gain.to_value(100), 0);
+        assert_eq!(gain.to_value(800), 50);
+        assert_eq!(gain.to_value(6400), 100);
+        for iso in ISO_THIRD_STEPS {
+            assert_eq!(gain.to_iso(gain.to_value(iso)), iso);
+        }
+        // 步进为 16 时对齐到 16 的倍数
+        let coarse = Mapping::Gain {
+            min: 16,
+            max: 248,
+            step: 16,
+        };
+        assert_eq!((coarse.to_value(400) - 16) % 16, 0);
+    }
+
+    #[test]
+    fn iso_menu_picks_the_nearest_item() {
+        let menu = Mapping::IsoMenu(vec![(0, 100), (1, 200), (2, 400), (3, 800)]);
+        assert_eq!(menu.to_value(1600), 3);
+        assert_eq!(menu.to_value(200), 1);
+        assert_eq!(menu.to_iso(2), 400);
+    }
+
+    #[test]
+    fn steps_stop_at_the_ends() {
+        assert_eq!(next_step(&ISO_STEPS, 800, 1), 1600);
+        assert_eq!(next_step(&ISO_STEPS, 6400, 1), 6400);
+        assert_eq!(next_step(&ISO_THIRD_STEPS, 800, 1), 1000);
+        assert_eq!(next_step(&ISO_STEPS, 100, -1), 100);
+        assert_eq!(next_step(&ISO_STEPS, 750, -1), 400);
+    }
+
+    #[test]
+    fn compensation_scales_gain_or_picks_bias_item() {
+        assert_eq!(compensated_iso(800, 0), 800);
+        assert_eq!(compensated_iso(800, 3), 1600);
+        assert_eq!(compensated_iso(800, -6), 200);
+        assert_eq!(compensated_iso(100, 1), 126);
+        // 模拟出的 ISO 在档位之间时增益也在档位之间
+        let gain = Mapping::Gain {
+            min: 0,
+            max: 100,
+            step: 1,
+        };
+        assert!(gain.to_value(126) > gain.to_value(100));
+        assert!(gain.to_value(126) < gain.to_value(200));
+
+        let items = [(0, -2000), (1, -1000), (2, 0), (3, 1000), (4, 2000)];
+        assert_eq!(bias_index(&items, 0), Some(2));
+        assert_eq!(bias_index(&items, 3), Some(3));
+        assert_eq!(bias_index(&items, -2), Some(1));
+        assert_eq!(bias_index(&items, MAX_EV_THIRDS), Some(4));
+        assert_eq!(bias_index(&[], 1), None);
+    }
+
+    #[test]
+    fn focus_position_as_percentage() {
+        assert_eq!(focus_percent(0, 0, 250), 0.0);
+        assert_eq!(focus_percent(125, 0, 250), 50.0);
+        assert_eq!(focus_percent(300, 0, 250), 100.0);
+        assert_eq!(focus_percent(15, 10, 20), 50.0);
+        assert_eq!(focus_percent(5, 5, 5), 0.0);
+    }
+
+    #[test]
+    fn converts_shutter_to_exposure_units() {
+        use ShutterSpeed::{Fraction, Seconds};
+        assert_eq!(exposure_value(Fraction(500), 1, 5000), 20);
+        assert_eq!(exposure_value(Fraction(60), 1, 5000), 167);
+        assert_eq!(exposure_value(Seconds(2.0), 1, 50_000), 20_000);
+        // 超出范围时取边界
+        assert_eq!(exposure_value(Fraction(4000), 3, 5000), 3);
+        assert_eq!(exposure_value(Fraction(25), 1, 300), 300);
+        for stop in crate::video::shutter::STOPS {
+            assert_eq!(shutter_step(exposure_value(*stop, 1, 50_000)), *stop);
+        }
+    }
+
+    #[test]
+    fn reads_nul_terminated_names() {
+        let mut name = [0u8; 32];
+        name[..8].copy_from_slice(b"Contrast");
+        assert_eq!(c_string(&name), "Contrast");
+        assert_eq!(c_string(b"no terminator"), "no terminator");
+        assert_eq!(c_string(&[0; 32]), "");
+    }
+
+    #[test]
+    fn grabbed_controls_are_read_only() {
+        assert!(ctrl_read_only(V4L2_CTRL_FLAG_READ_ONLY));
+        assert!(ctrl_read_only(
+            V4L2_CTRL_FLAG_GRABBED | V4L2_CTRL_FLAG_INACTIVE
+        ));
+        assert!(!ctrl_read_only(V4L2_CTRL_FLAG_INACTIVE));
+        assert!(!ctrl_read_only(0));
+    }
+}
@@ -0,0 +1,208 @@
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+
+/// Blackmagic DeckLink 采集卡的 SDI/HDMI 输入.
+const VIDEO_ELEMENT: &str = "decklinkvideosrc";
+const AUDIO_ELEMENT: &str = "decklinkaudiosrc";
+
+/// 界面上可选的视频模式, `decklinkvideosrc` 的 `mode` 属性的 nick 和显示名称.
+pub(crate) const MODES: &[(&str, &str)] = &[
+    ("auto", "Auto-detect"),
+    ("1080p2398", "1080p 23.98"),
+    ("1080p24", "1080p 24"),
+    ("1080p25", "1080p 25"),
+    ("1080p2997", "1080p 29.97"),
+    ("1080p30", "1080p 30"),
+    ("1080p50", "1080p 50"),
+    ("1080p5994", "1080p 59.94"),
+    ("1080p60", "1080p 60"),
+    ("1080i50", "1080i 50"),
+    ("1080i5994", "1080i 59.94"),
+    ("720p50", "720p 50"),
+    ("720p5994", "720p 59.94"),
+    ("2160p25", "2160p 25"),
+    ("2160p2997", "2160p 29.97"),
+    ("2160p50", "2160p 50"),
+];
+
+/// 嵌入音频可以采集的声道数.
+pub(crate) const CHANNEL_COUNTS: [u32; 3] = [2, 8, 16];
+
+/// 是否安装了 DeckLink 插件, 没有时设置中不显示.
+pub(crate) fn available() -> bool {
+    gst::ElementFactory::find(VIDEO_ELEMENT).is_some()
+        && gst::ElementFactory::find(AUDIO_ELEMENT).is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Connection {
+    /// 由卡选择有信号的接口.
+    #[default]
+    Auto,
+    Sdi,
+    Hdmi,
+}
+
+impl Connection {
+    pub const ALL: [Connection; 3] = [Connection::Auto, Connection::Sdi, Connection::Hdmi];
+
+    /// `connection` 属性的 nick.
+    fn nick(self) -> &'static str {
+        match self {
+            Connection::Auto => "auto",
+            Connection::Sdi => "sdi",
+            Connection::Hdmi => "hdmi",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Connection::Auto => "Auto",
+            Connection::Sdi => "SDI",
+            Connection::Hdmi => "HDMI",
+        }
+    }
+}
+
+/// DeckLink 输入. 设置后代替视频源和音频源, 仍然接到同样的 tee 上.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct DeckLinkConfig {
+    /// 第几块卡或卡上的第几路输入, 从 0 开始.
+    pub device_number: u32,
+    pub connection: Connection,
+    /// [MODES] 中的 nick, "auto" 时由卡检测输入的格式.
+    pub mode: String,
+    /// 采集的嵌入音频声道数, 见 [CHANNEL_COUNTS].
+    pub channels: u32,
+    /// 送去录制和电平表的一对声道, 1 表示第 1, 2 声道.
+    pub audio_pair: u32,
+}
+
+impl Default for DeckLinkConfig {
+    fn default() -> Self {
+        Self {
+            device_number: 0,
+            connection: Connection::Auto,
+            mode: "auto".to_string(),
+            channels: 2,
+            audio_pair: 1,
+        }
+    }
+}
+
+impl DeckLinkConfig {
+    /// 每一对声道都可以选择.
+    pub fn pairs(&self) -> u32 {
+        self.channels / 2
+    }
+
+    /// 信号中断时不输出黑帧, 预览停住后显示 NO SIGNAL.
+    pub fn video_source(&self) -> String {
+        format!(
+            "{} device-number={} connection={} mode={} drop-no-signal-frames=true",
+            VIDEO_ELEMENT,
+            self.device_number,
+            self.connection.nick(),
+            self.mode
+        )
+    }
+
+    /// 多于两个声道时用 audiomixmatrix 取出选择的一对.
+    pub fn audio_source(&self) -> String {
+        let channels = if CHANNEL_COUNTS.contains(&self.channels) {
+            self.channels
+        } else {
+            2
+        };
+        let src = format!(
+            "{} device-number={} channels={}",
+            AUDIO_ELEMENT, self.device_number, channels
+        );
+        if channels == 2 {
+            return src;
+        }
+        let pair = self.audio_pair.clamp(1, channels / 2);
+        format!(
+            "{} ! audiomixmatrix in-channels={} out-channels=2 matrix=\"{}\"",
+            src,
+            channels,
+            pair_matrix(channels, pair)
+        )
+    }
+}
+
+/// 每个输出声道一行, 选中的输入声道为 1.
+fn pair_matrix(channels: u32, pair: u32) -> String {
+    let first = (pair - 1) * 2;
+    let rows: Vec<String> = (0..2)
+        .map(|out| {
+            let row: Vec<&str> = (0..channels)
+                .map(|input| {
+                    if input == first + out {
+                        "(float)1.0"
+                    } else {
+                        "(float)0.0"
+                    }
+                })
+                .collect();
+            format!("<{}>", row.join(", "))
+        })
+        .collect();
+    format!("<{}>", rows.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_the_video_source() {
+        let config = DeckLinkConfig {
+            device_number: 1,
+            connection: Connection::Sdi,
+            mode: "1080i50".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.video_source(),
+            "decklinkvideosrc device-number=1 connection=sdi mode=1080i50 drop-no-signal-frames=true"
+        );
+    }
+
+    #[test]
+    fn picks_the_selected_audio_pair() {
+        let stereo = DeckLinkConfig::default();
+        assert_eq!(
+            stereo.audio_source(),
+            "decklinkaudiosrc device-number=0 channels=2"
+        );
+        let config = DeckLinkConfig {
+            channels: 8,
+            audio_pair: 2,
+            ..Default::default()
+        };
+        assert_eq!(config.pairs(), 4);
+        let zero = "(float)0.0";
+        let one = "(float)1.0";
+        assert_eq!(
+            pair_matrix(8, 2),
+            format!(
+                "<<{z}, {z}, {o}, {z}, {z}, {z}, {z}, {z}>, <{z}, {z}, {z}, {o}, {z}, {z}, {z}, {z}>>",
+                z = zero,
+                o = one
+            )
+        );
+        assert!(config.audio_source().starts_with(
+            "decklinkaudiosrc device-number=0 channels=8 ! audiomixmatrix in-channels=8 out-channels=2"
+        ));
+        // 超出范围的一对按最后一对处理
+        let out_of_range = DeckLinkConfig {
+            channels: 8,
+            audio_pair: 9,
+            ..Default::default()
+        };
+        assert!(out_of_range.audio_source().contains(&pair_matrix(8, 4)));
+    }
+}
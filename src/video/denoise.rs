@@ -0,0 +1,198 @@
+//! 弱光下的降噪, 放在视频 tee 之前, 预览和录像都经过. 噪点少了, 编码后的码率也低.
+//! 按 vapostproc (VA-API 硬件), videomedian (软件) 的顺序选用第一个可用的.
+//! 运行中开关见 [super::bypass].
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use super::bypass::{self, Bypass};
+use super::record::Resolution;
+
+/// 管线中选择器名字的前缀和降噪元素的名字.
+const PREFIX: &str = "dn";
+const FILTER: &str = "denoise";
+/// 软件降噪只用于不超过 1080p 的画面, 更大时自动关闭.
+pub(crate) const SOFTWARE_MAX_PIXELS: u64 = 1920 * 1080;
+/// 估算 CPU 占用时假定的帧率.
+const ASSUMED_FPS: f64 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum DenoiseStrength {
+    Light,
+    #[default]
+    Medium,
+    Strong,
+}
+
+impl DenoiseStrength {
+    pub const ALL: [DenoiseStrength; 3] = [
+        DenoiseStrength::Light,
+        DenoiseStrength::Medium,
+        DenoiseStrength::Strong,
+    ];
+
+    fn fraction(self) -> f64 {
+        match self {
+            DenoiseStrength::Light => 0.25,
+            DenoiseStrength::Medium => 0.5,
+            DenoiseStrength::Strong => 0.8,
+        }
+    }
+}
+
+/// 属于录制设置, 随预设切换. 修改后立即生效.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct DenoiseSettings {
+    pub enabled: bool,
+    pub strength: DenoiseStrength,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DenoiseFilter {
+    /// VA-API 的后处理, 驱动支持降噪时才可用.
+    Va,
+    /// 中值滤波, 轻度只处理亮度.
+    Median,
+}
+
+impl DenoiseFilter {
+    /// 可用的最佳滤镜, 都没有时返回 None. 检查 VA-API 需要创建元素, 结果只算一次.
+    pub fn detect() -> Option<Self> {
+        static DETECTED: OnceLock<Option<DenoiseFilter>> = OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            let va = gst::ElementFactory::make(DenoiseFilter::Va.element())
+                .build()
+                .is_ok_and(|el| el.has_property("denoise"));
+            if va {
+                Some(DenoiseFilter::Va)
+            } else if gst::ElementFactory::find(DenoiseFilter::Median.element()).is_some() {
+                Some(DenoiseFilter::Median)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn element(self) -> &'static str {
+        match self {
+            DenoiseFilter::Va => "vapostproc",
+            DenoiseFilter::Median => "videomedian",
+        }
+    }
+
+    pub fn is_hardware(self) -> bool {
+        self == DenoiseFilter::Va
+    }
+
+    /// 软件滤镜在这个分辨率下是否会自动关闭.
+    pub fn allowed(self, width: u32, height: u32) -> bool {
+        self.is_hardware() || width as u64 * height as u64 <= SOFTWARE_MAX_PIXELS
+    }
+
+    /// 软件滤镜大约占用一个 CPU 核心的百分之几, 硬件滤镜返回 None.
+    /// 按每百万像素每帧的耗时粗略估算.
+    pub fn cpu_percent(self, strength: DenoiseStrength, res: Resolution) -> Option<f64> {
+        let ms_per_megapixel = match (self, strength) {
+            (DenoiseFilter::Va, _) => return None,
+            (DenoiseFilter::Median, DenoiseStrength::Light) => 3.0,
+            (DenoiseFilter::Median, DenoiseStrength::Medium) => 4.5,
+            (DenoiseFilter::Median, DenoiseStrength::Strong) => 12.0,
+        };
+        let megapixels = res.width as f64 * res.height as f64 / 1e6;
+        Some(ms_per_megapixel * megapixels * ASSUMED_FPS / 10.0)
+    }
+
+    /// 按强度设置滤镜的属性, 运行中也可以修改.
+    fn apply(self, el: &gst::Element, strength: DenoiseStrength) {
+        match self {
+            // 降噪的范围由驱动决定
+            DenoiseFilter::Va => {
+                let Some(spec) = el
+                    .find_property("denoise")
+                    .and_then(|p| p.downcast::<gst::glib::ParamSpecFloat>().ok())
+                else {
+                    return;
+                };
+                let (min, max) = (spec.minimum() as f64, spec.maximum() as f64);
+                el.set_property("denoise", (min + (max - min) * strength.fraction()) as f32);
+            }
+            DenoiseFilter::Median => {
+                let (size, lum_only) = match strength {
+                    DenoiseStrength::Light => (5u32, true),
+                    DenoiseStrength::Medium => (5, false),
+                    DenoiseStrength::Strong => (9, false),
+                };
+                el.set_property_from_str("filtersize", &size.to_string());
+                el.set_property("lum-only", lum_only);
+            }
+        }
+    }
+
+    /// 写入 sidecar 的说明, 例如 "videomedian Medium".
+    fn label(self, strength: DenoiseStrength) -> String {
+        format!("{} {:?}", self.element(), strength)
+    }
+}
+
+/// 插在 tee 之前的一段描述, 以 `!` 结尾.
+pub(super) fn description(filter: DenoiseFilter) -> String {
+    bypass::description(
+        PREFIX,
+        &format!(
+            "videoconvert ! {} name={} ! videoconvert",
+            filter.element(),
+            FILTER
+        ),
+    )
+}
+
+/// [Denoiser::set] 的结果.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DenoiseState {
+    Off,
+    /// 生效的滤镜和强度, 见 [DenoiseFilter::label].
+    On(String),
+    /// 画面超过 [SOFTWARE_MAX_PIXELS], 软件降噪已关闭.
+    TooLarge {
+        width: u32,
+        height: u32,
+    },
+}
+
+pub(crate) struct Denoiser {
+    filter: DenoiseFilter,
+    bypass: Bypass,
+    element: gst::Element,
+}
+
+impl Denoiser {
+    pub fn attach(pipeline: &gst::Pipeline, filter: DenoiseFilter) -> Result<Self, String> {
+        Ok(Self {
+            filter,
+            bypass: Bypass::attach(pipeline, PREFIX)?,
+            element: pipeline
+                .by_name(FILTER)
+                .ok_or_else(|| format!("pipeline has no element named '{}'", FILTER))?,
+        })
+    }
+
+    /// 软件滤镜遇到过大的画面时保持关闭.
+    pub fn set(&self, settings: DenoiseSettings) -> DenoiseState {
+        if !settings.enabled {
+            self.bypass.select(false);
+            return DenoiseState::Off;
+        }
+        if let Some((width, height)) = self.bypass.input_size()
+            && !self.filter.allowed(width, height)
+        {
+            self.bypass.select(false);
+            return DenoiseState::TooLarge { width, height };
+        }
+        self.filter.apply(&self.element, settings.strength);
+        self.bypass.select(true);
+        DenoiseState::On(self.filter.label(settings.strength))
+    }
+}
@@ -0,0 +1,113 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+use super::libcamera;
+
+/// 系统中可用的摄像头.
+#[derive(Debug, Clone)]
+pub(crate) struct Camera {
+    pub name: String,
+    /// 例如 "/dev/video0", 只有 V4L2 设备有.
+    pub path: Option<String>,
+    /// libcamerasrc 的 `camera-name`, 只有 libcamera 设备有.
+    pub libcamera_id: Option<String>,
+}
+
+/// `--backend` 选择的采集方式.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub(crate) enum Backend {
+    /// 按名称找到的摄像头决定.
+    #[default]
+    Auto,
+    V4l2,
+    /// 树莓派的 CSI 摄像头.
+    Libcamera,
+}
+
+/// 通过 DeviceMonitor 列出摄像头. 需要先初始化 GStreamer.
+pub(crate) fn list_cameras() -> Vec<Camera> {
+    let monitor = gst::DeviceMonitor::new();
+    monitor.add_filter(Some("Video/Source"), None);
+    if let Err(e) = monitor.start() {
+        tracing::error!("could not start device monitor: {}", e);
+        return Vec::new();
+    }
+    let devices = monitor.devices();
+    monitor.stop();
+
+    devices
+        .iter()
+        .map(|device| Camera {
+            name: device.display_name().to_string(),
+            path: device.properties().and_then(|p| {
+                p.get::<String>("api.v4l2.path")
+                    .or_else(|_| p.get::<String>("device.path"))
+                    .ok()
+            }),
+            libcamera_id: libcamera_id(device),
+        })
+        .collect()
+}
+
+/// libcamera 的设备不带属性, 只能从它创建的元素上读出相机 ID.
+fn libcamera_id(device: &gst::Device) -> Option<String> {
+    let element = device.create_element(None).ok()?;
+    if element.factory()?.name() != libcamera::ELEMENT {
+        return None;
+    }
+    element.property::<Option<String>>("camera-name")
+}
+
+impl Camera {
+    fn matches(&self, backend: Backend) -> bool {
+        match backend {
+            Backend::Auto => true,
+            Backend::V4l2 => self.path.is_some(),
+            Backend::Libcamera => self.libcamera_id.is_some(),
+        }
+    }
+}
+
+/// 把 `--device` 参数 (设备路径或名称) 和 `--backend` 转换为 gst-launch 语法的视频源.
+/// 没有指定设备时使用该方式下的第一个摄像头.
+pub(crate) fn source_for(device: Option<&str>, backend: Backend) -> Result<String, String> {
+    if let Some(path) = device.filter(|d| d.starts_with("/dev/")) {
+        return match backend {
+            Backend::Libcamera => Err(
+                "libcamera cameras are selected by name, not by device path (use --list-devices)"
+                    .to_string(),
+            ),
+            _ => Ok(format!("v4l2src device={}", path)),
+        };
+    }
+    if backend == Backend::Libcamera && !libcamera::available() {
+        return Err(
+            "libcamerasrc is not installed (install the libcamera GStreamer plugin)".to_string(),
+        );
+    }
+    let cameras = list_cameras();
+    let found = cameras
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.matches(backend))
+        .find(|(_, c)| device.is_none_or(|name| c.name.eq_ignore_ascii_case(name)));
+    let Some((index, camera)) = found else {
+        return Err(match device {
+            Some(name) => format!(
+                "no camera named '{}' (use --list-devices to see available ones)",
+                name
+            ),
+            None => "no camera found for this backend (use --list-devices to see available ones)"
+                .to_string(),
+        });
+    };
+    if let Some(id) = &camera.libcamera_id {
+        return Ok(libcamera::source(id));
+    }
+    match &camera.path {
+        Some(path) => Ok(format!("v4l2src device={}", path)),
+        // macOS 的摄像头没有设备路径, 按枚举顺序选择
+        None if cfg!(target_os = "macos") => Ok(format!("avfvideosrc device-index={}", index)),
+        None => Err(format!("camera '{}' has no device path", camera.name)),
+    }
+}
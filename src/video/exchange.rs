@@ -0,0 +1,184 @@
+//! 采集线程和界面之间交换预览帧的三缓冲. 只用一个 `Mutex<Option<_>>` 时, 界面上传纹理期间
+//! 持有锁, appsink 的回调就要等它, 负载高时预览一顿一顿的.
+//!
+//! 三个槽分别属于写入端, 读取端和 "最新完成的一帧", 交换时只原子地交换槽的编号.
+//! 两端永远不会同时碰同一个槽, 写入端从不等待读取端, 读取端拿到的总是最新完成的一帧.
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// [Slots::back] 中表示这一帧还没被取走的标志位.
+const FRESH: usize = 0b100;
+const INDEX: usize = 0b011;
+
+/// 克隆后共享同一组槽. 可以有多个写入端 (网络摄像头重连后的新管线) 和多个读取端
+/// (主窗口和节目输出窗口), 同一端之间互相排队, 写入端和读取端之间互不等待.
+pub(crate) struct FrameExchange<T> {
+    slots: Arc<Slots<T>>,
+}
+
+struct Slots<T> {
+    /// 由下面的编号保证同一时刻只有一端访问同一个槽, 这些锁不会有竞争.
+    frames: [Mutex<Option<T>>; 3],
+    /// 最新完成的一帧所在的槽, 以及 [FRESH].
+    back: AtomicUsize,
+    /// 写入端正在写的槽, 只有写入端使用.
+    write: Mutex<usize>,
+    /// 读取端持有的槽, 只有读取端使用.
+    read: Mutex<usize>,
+}
+
+impl<T> Default for FrameExchange<T> {
+    fn default() -> Self {
+        Self {
+            slots: Arc::new(Slots {
+                frames: Default::default(),
+                back: AtomicUsize::new(1),
+                write: Mutex::new(0),
+                read: Mutex::new(2),
+            }),
+        }
+    }
+}
+
+impl<T> Clone for FrameExchange<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+        }
+    }
+}
+
+impl<T> FrameExchange<T> {
+    /// 发布新的一帧. 上一帧还没被取走时它会被丢弃.
+    pub fn publish(&self, frame: T) {
+        let slots = &*self.slots;
+        let mut write = slots.write.lock();
+        let stale = slots.frames[*write].lock().replace(frame);
+        *write = slots.back.swap(*write | FRESH, Ordering::AcqRel) & INDEX;
+        drop(write);
+        // 没被取走的旧帧很大, 在锁外释放
+        drop(stale);
+    }
+
+    /// 取走最新完成的一帧, 上次取过以后还没有新帧时返回 None.
+    pub fn take(&self) -> Option<T> {
+        let slots = &*self.slots;
+        let mut read = slots.read.lock();
+        // 只有写入端会设置 FRESH, 看到以后再交换也一定还是新帧
+        if slots.back.load(Ordering::Acquire) & FRESH != 0 {
+            *read = slots.back.swap(*read, Ordering::AcqRel) & INDEX;
+        }
+        slots.frames[*read].lock().take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn reader_gets_the_newest_frame_once() {
+        let exchange = FrameExchange::default();
+        assert_eq!(exchange.take(), None);
+        exchange.publish(1);
+        exchange.publish(2);
+        assert_eq!(exchange.take(), Some(2));
+        assert_eq!(exchange.take(), None);
+        exchange.publish(3);
+        assert_eq!(exchange.clone().take(), Some(3));
+        for n in 4..10 {
+            exchange.publish(n);
+        }
+        assert_eq!(exchange.take(), Some(9));
+    }
+
+    #[test]
+    fn frames_stay_in_order_across_threads() {
+        const COUNT: u32 = 100_000;
+        let exchange = FrameExchange::default();
+        let writer = exchange.clone();
+        let producer = std::thread::spawn(move || {
+            for n in 1..=COUNT {
+                writer.publish(n);
+            }
+        });
+        let mut last = 0;
+        while last < COUNT {
+            if let Some(n) = exchange.take() {
+                assert!(n > last, "{} after {}", n, last);
+                last = n;
+            }
+        }
+        producer.join().unwrap();
+    }
+
+    const FRAME_BYTES: usize = 1920 * 1080 * 4;
+    const FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+    const FRAMES: u32 = 180;
+    /// 负载高时上传一帧 1080p 纹理的时间.
+    const UPLOAD: Duration = Duration::from_millis(8);
+
+    /// 写入端以 60 fps 发布 1080p 的帧, 读取端以略低的刷新率取帧并模拟上传.
+    /// 返回写入端每次发布等待的最长和平均时间.
+    fn producer_waits(
+        publish: &(dyn Fn(Vec<u8>) + Sync),
+        consume: &(dyn Fn() + Sync),
+    ) -> (Duration, Duration) {
+        let done = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    consume();
+                    std::thread::sleep(Duration::from_millis(16));
+                }
+            });
+            let start = Instant::now();
+            let mut waits = Vec::with_capacity(FRAMES as usize);
+            for n in 0..FRAMES {
+                let frame = vec![n as u8; FRAME_BYTES];
+                let before = Instant::now();
+                publish(frame);
+                waits.push(before.elapsed());
+                let next = start + FRAME_INTERVAL * (n + 1);
+                std::thread::sleep(next.saturating_duration_since(Instant::now()));
+            }
+            done.store(true, Ordering::Relaxed);
+            let max = waits.iter().max().copied().unwrap_or_default();
+            (max, waits.iter().sum::<Duration>() / FRAMES)
+        })
+    }
+
+    fn upload(frame: &[u8]) {
+        assert_eq!(frame.len(), FRAME_BYTES);
+        std::thread::sleep(UPLOAD);
+    }
+
+    /// 对比原来的单个锁: 读取端像节目输出窗口那样上传期间持有锁.
+    #[test]
+    #[ignore = "benchmark, run with --ignored --nocapture"]
+    fn contention_at_1080p60() {
+        let slot = Mutex::new(None::<Vec<u8>>);
+        let (mutex_max, mutex_mean) = producer_waits(&|frame| *slot.lock() = Some(frame), &|| {
+            let mut slot = slot.lock();
+            if let Some(frame) = slot.take() {
+                upload(&frame);
+            }
+        });
+        let exchange = FrameExchange::default();
+        let (exchange_max, exchange_mean) =
+            producer_waits(&|frame| exchange.publish(frame), &|| {
+                if let Some(frame) = exchange.take() {
+                    upload(&frame);
+                }
+            });
+        println!("producer wait with a mutex:  max {mutex_max:?}, mean {mutex_mean:?}");
+        println!("producer wait with exchange: max {exchange_max:?}, mean {exchange_mean:?}");
+        assert!(mutex_max >= UPLOAD / 2, "the mutex never contended");
+        assert!(exchange_max < UPLOAD / 2);
+        assert!(exchange_mean < mutex_mean);
+    }
+}
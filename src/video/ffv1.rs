@@ -0,0 +1,38 @@
+//! FFV1 无损编码, 用于存档. 只能封装为 MKV, 音频用 FLAC.
+//! 码率只取决于画面内容, 高清时每秒几百 Mbit, 对存储的写入速度要求很高.
+
+use super::record::Resolution;
+
+/// libav 支持的切片数, 切片越多可用的 CPU 核心越多, 压缩率略低.
+pub(crate) const SLICES: [u8; 6] = [4, 6, 9, 12, 16, 24];
+/// 估算码率时假定的帧率.
+pub(crate) const ASSUMED_FPS: f64 = 30.0;
+/// 无损压缩后约为原始 4:2:0 画面的一半, 噪点多的画面更大.
+const COMPRESSION_RATIO: f64 = 0.5;
+
+/// gst-launch 语法的编码器描述.
+pub(crate) fn description(slices: u8) -> String {
+    // 多个切片需要 FFV1 version 2 以上, libav 会自动选用
+    format!("avenc_ffv1 slices={}", slices)
+}
+
+/// 粗略估算的码率 (kbit/s), 实际码率随内容有很大变化.
+pub(crate) fn estimated_kbps(res: Resolution, fps: f64) -> u32 {
+    let raw_bits = res.width as f64 * res.height as f64 * 12.0 * fps;
+    (raw_bits * COMPRESSION_RATIO / 1000.0).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hd_estimate_is_hundreds_of_megabits() {
+        let hd = Resolution {
+            width: 1920,
+            height: 1080,
+        };
+        let kbps = estimated_kbps(hd, ASSUMED_FPS);
+        assert!((300_000..400_000).contains(&kbps), "{}", kbps);
+    }
+}
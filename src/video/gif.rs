@@ -0,0 +1,336 @@
+//! 把录像的最后几秒导出为 GIF, 方便贴到聊天软件里.
+//! 任务在后台线程中逐个处理: 解码, 缩放和降帧率交给 GStreamer, 编码用 image.
+//! 没有动画 WebP 编码器可用 (GStreamer 的 webpenc 只能编码单张), 所以只支持 GIF.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_pbutils as gst_pbutils;
+use gstreamer_video as gst_video;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+
+use crate::file::format_size;
+
+pub(crate) const SECONDS: RangeInclusive<u32> = 2..=10;
+pub(crate) const WIDTHS: RangeInclusive<u32> = 160..=1280;
+pub(crate) const FRAME_RATES: RangeInclusive<u32> = 5..=30;
+/// 估计的大小超过这个值时拒绝导出.
+const MAX_BYTES: u64 = 40 * 1024 * 1024;
+const DISCOVER_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(30);
+/// 预卷和每一帧最多等待的时间.
+const STEP_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(10);
+/// NeuQuant 量化的速度, 1 最慢最好, 30 最快.
+const QUANTIZE_SPEED: i32 = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct GifSettings {
+    /// 从录像末尾往前截取的秒数.
+    pub seconds: u32,
+    /// 输出宽度, 高度按比例.
+    pub width: u32,
+    pub fps: u32,
+}
+
+impl Default for GifSettings {
+    fn default() -> Self {
+        Self {
+            seconds: 4,
+            width: 480,
+            fps: 12,
+        }
+    }
+}
+
+/// 按每像素一个字节粗略估计, 实际的 GIF 通常更小.
+fn estimate_bytes(settings: &GifSettings, source: (u32, u32), duration: gst::ClockTime) -> u64 {
+    let (width, height) = source;
+    let out_height = settings.width as u64 * height as u64 / width.max(1) as u64;
+    let seconds = duration.seconds_f64().min(settings.seconds as f64);
+    let frames = (seconds * settings.fps as f64).ceil() as u64;
+    settings.width as u64 * out_height * frames
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GifState {
+    Queued,
+    /// 已完成的比例.
+    Running(f32),
+    Done,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GifJob {
+    id: u64,
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub state: GifState,
+}
+
+impl GifJob {
+    fn is_finished(&self) -> bool {
+        matches!(self.state, GifState::Done | GifState::Failed(_))
+    }
+}
+
+/// 导出队列. 任务按加入的顺序在同一个低优先级线程中处理, 不影响录制.
+pub(crate) struct GifQueue {
+    jobs: Arc<Mutex<Vec<GifJob>>>,
+    tx: Sender<(u64, PathBuf, PathBuf, GifSettings)>,
+    next_id: u64,
+}
+
+impl GifQueue {
+    pub fn spawn() -> Self {
+        let jobs = Arc::new(Mutex::new(Vec::<GifJob>::new()));
+        let (tx, rx) = mpsc::channel::<(u64, PathBuf, PathBuf, GifSettings)>();
+        let shared = jobs.clone();
+        let spawned = std::thread::Builder::new()
+            .name("gif".into())
+            .spawn(move || {
+                super::verify::lower_priority();
+                let set = |id: u64, state: GifState| {
+                    if let Some(job) = shared.lock().iter_mut().find(|j| j.id == id) {
+                        job.state = state;
+                    }
+                };
+                for (id, source, output, settings) in rx {
+                    set(id, GifState::Running(0.0));
+                    let state = match export(&source, &output, &settings, |f| {
+                        set(id, GifState::Running(f))
+                    }) {
+                        Ok(()) => {
+                            tracing::info!(path = %output.display(), "exported gif");
+                            GifState::Done
+                        }
+                        Err(e) => {
+                            tracing::warn!(source = %source.display(), "gif export failed: {}", e);
+                            GifState::Failed(e)
+                        }
+                    };
+                    set(id, state);
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::error!("could not start gif thread: {}", e);
+        }
+        Self {
+            jobs,
+            tx,
+            next_id: 0,
+        }
+    }
+
+    /// 输出保存在录像旁边, 扩展名改为 `.gif`.
+    pub fn push(&mut self, source: &Path, settings: &GifSettings) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let output = source.with_extension("gif");
+        let mut job = GifJob {
+            id,
+            source: source.to_path_buf(),
+            output: output.clone(),
+            state: GifState::Queued,
+        };
+        if self
+            .tx
+            .send((id, job.source.clone(), output, settings.clone()))
+            .is_err()
+        {
+            job.state = GifState::Failed("export thread is not running".to_string());
+        }
+        self.jobs.lock().push(job);
+    }
+
+    /// 正在进行的任务的进度, 以及还在排队的任务数.
+    pub fn progress(&self) -> Option<(f32, usize)> {
+        let jobs = self.jobs.lock();
+        let queued = jobs.iter().filter(|j| j.state == GifState::Queued).count();
+        let running = jobs.iter().find_map(|j| match j.state {
+            GifState::Running(f) => Some(f),
+            _ => None,
+        });
+        match running {
+            Some(f) => Some((f, queued)),
+            None if queued > 0 => Some((0.0, queued - 1)),
+            None => None,
+        }
+    }
+
+    /// 取出已经结束的任务, 每个只返回一次.
+    pub fn take_finished(&self) -> Vec<GifJob> {
+        let mut jobs = self.jobs.lock();
+        let (finished, pending) = jobs.drain(..).partition(GifJob::is_finished);
+        *jobs = pending;
+        finished
+    }
+}
+
+/// 录像的时长和画面尺寸.
+fn probe(path: &Path) -> Result<(gst::ClockTime, (u32, u32)), String> {
+    let uri = gst::glib::filename_to_uri(path, None).map_err(|e| e.to_string())?;
+    let discoverer = gst_pbutils::Discoverer::new(DISCOVER_TIMEOUT).map_err(|e| e.to_string())?;
+    let info = discoverer.discover_uri(&uri).map_err(|e| e.to_string())?;
+    let duration = info
+        .duration()
+        .filter(|d| !d.is_zero())
+        .ok_or("the clip has no duration")?;
+    let video = info
+        .video_streams()
+        .into_iter()
+        .next()
+        .ok_or("the clip has no video")?;
+    Ok((duration, (video.width(), video.height())))
+}
+
+fn export(
+    source: &Path,
+    output: &Path,
+    settings: &GifSettings,
+    progress: impl Fn(f32),
+) -> Result<(), String> {
+    let (duration, size) = probe(source)?;
+    let estimate = estimate_bytes(settings, size, duration);
+    if estimate > MAX_BYTES {
+        return Err(format!(
+            "it would be about {} (limit {}); choose fewer seconds or a smaller width",
+            format_size(estimate),
+            format_size(MAX_BYTES)
+        ));
+    }
+    let length = duration.min(gst::ClockTime::from_seconds(settings.seconds as u64));
+    let start = duration.saturating_sub(length);
+
+    let pipeline = gst::parse::launch(&format!(
+        "filesrc name=src ! decodebin ! videoconvert ! videoscale ! videorate \
+         ! video/x-raw,format=RGBA,width={},pixel-aspect-ratio=1/1,framerate={}/1 \
+         ! appsink name=frames sync=false",
+        settings.width, settings.fps
+    ))
+    .map_err(|e| e.to_string())?
+    .downcast::<gst::Pipeline>()
+    .map_err(|_| "not a pipeline")?;
+    pipeline
+        .by_name("src")
+        .ok_or("no file source")?
+        .set_property("location", source.to_string_lossy().as_ref());
+    let appsink = pipeline
+        .by_name("frames")
+        .and_then(|e| e.downcast::<gst_app::AppSink>().ok())
+        .ok_or("no frame sink")?;
+
+    let result = encode(&pipeline, &appsink, start, length, settings, progress);
+    let _ = pipeline.set_state(gst::State::Null);
+    let encoded = result?;
+    std::fs::write(output, encoded).map_err(|e| format!("{}: {}", output.display(), e))
+}
+
+/// 跳到 `start` 后逐帧编码, 直到文件结束.
+fn encode(
+    pipeline: &gst::Pipeline,
+    appsink: &gst_app::AppSink,
+    start: gst::ClockTime,
+    length: gst::ClockTime,
+    settings: &GifSettings,
+    progress: impl Fn(f32),
+) -> Result<Vec<u8>, String> {
+    pipeline
+        .set_state(gst::State::Paused)
+        .map_err(|_| pipeline_error(pipeline))?;
+    // 预卷完成后才能精确跳转
+    let (prerolled, _, _) = pipeline.state(STEP_TIMEOUT);
+    prerolled.map_err(|_| pipeline_error(pipeline))?;
+    pipeline
+        .seek_simple(gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE, start)
+        .map_err(|e| format!("could not seek: {}", e))?;
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|_| pipeline_error(pipeline))?;
+
+    let mut encoded = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut encoded, QUANTIZE_SPEED);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| e.to_string())?;
+        let delay = Delay::from_numer_denom_ms(1000, settings.fps);
+        while let Some(sample) = appsink.try_pull_sample(STEP_TIMEOUT) {
+            let image = rgba(&sample)?;
+            encoder
+                .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                .map_err(|e| format!("could not encode: {}", e))?;
+            if let Some(pts) = sample.buffer().and_then(|b| b.pts()) {
+                let done = pts.saturating_sub(start).seconds_f64() / length.seconds_f64();
+                progress(done.clamp(0.0, 1.0) as f32);
+            }
+        }
+    }
+    if !appsink.is_eos() {
+        return Err(pipeline_error(pipeline));
+    }
+    Ok(encoded)
+}
+
+/// 去掉每行的填充.
+fn rgba(sample: &gst::Sample) -> Result<RgbaImage, String> {
+    let info = sample
+        .caps()
+        .and_then(|caps| gst_video::VideoInfo::from_caps(caps).ok())
+        .ok_or("frame has no size")?;
+    let buffer = sample.buffer().ok_or("frame has no data")?;
+    let map = buffer
+        .map_readable()
+        .map_err(|_| "could not read a frame")?;
+    let (width, height) = (info.width(), info.height());
+    let stride = info.stride()[0] as usize;
+    let row = width as usize * 4;
+    let mut pixels = Vec::with_capacity(row * height as usize);
+    for y in 0..height as usize {
+        let line = map
+            .get(y * stride..y * stride + row)
+            .ok_or("frame is truncated")?;
+        pixels.extend_from_slice(line);
+    }
+    RgbaImage::from_raw(width, height, pixels).ok_or_else(|| "frame size mismatch".to_string())
+}
+
+/// 总线上的第一个错误, 没有时说明是超时.
+fn pipeline_error(pipeline: &gst::Pipeline) -> String {
+    pipeline
+        .bus()
+        .and_then(|bus| bus.pop_filtered(&[gst::MessageType::Error]))
+        .and_then(|message| match message.view() {
+            gst::MessageView::Error(err) => Some(err.error().to_string()),
+            _ => None,
+        })
+        .unwrap_or_else(|| "decoding stalled".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_uses_the_shorter_of_clip_and_setting() {
+        let settings = GifSettings::default();
+        // 480×270, 4 秒 12 帧
+        let long = estimate_bytes(&settings, (1920, 1080), gst::ClockTime::from_seconds(60));
+        assert_eq!(long, 480 * 270 * 48);
+        let short = estimate_bytes(&settings, (1920, 1080), gst::ClockTime::from_seconds(2));
+        assert_eq!(short, 480 * 270 * 24);
+        let huge = GifSettings {
+            seconds: 10,
+            width: 1280,
+            fps: 30,
+        };
+        assert!(estimate_bytes(&huge, (3840, 2160), gst::ClockTime::from_seconds(60)) > MAX_BYTES);
+    }
+}
@@ -0,0 +1,76 @@
+//! 调试用的管线拓扑图. 用户报告 "录不了" 时, 看实际的元素, caps 和状态比看日志快.
+//! 录制分支是管线的子 bin, 正在录制时一起画出来.
+//!
+//! 不依赖 GST_DEBUG_DUMP_DOT_DIR, 直接取 DOT 文本写到输出目录.
+//! 装了 graphviz 时顺便渲染成 PNG.
+
+use chrono::Local;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 写出的文件.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphFiles {
+    pub dot: PathBuf,
+    /// 没有安装 graphviz 或者渲染失败时为 None.
+    pub png: Option<PathBuf>,
+}
+
+/// 在 gst 线程中取出 DOT 文本, 写文件和渲染在后台进行, 完成后调用 `done`.
+pub(crate) fn save(
+    pipeline: &gst::Pipeline,
+    dir: &Path,
+    done: impl FnOnce(Result<GraphFiles, String>) + Send + 'static,
+) -> Result<(), String> {
+    let data = pipeline.debug_to_dot_data(gst::DebugGraphDetails::all());
+    let dot = dir.join(format!(
+        "cam-ui-pipeline-{}.dot",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    std::thread::Builder::new()
+        .name("graph".into())
+        .spawn(move || {
+            let result = std::fs::write(&dot, data.as_str())
+                .map_err(|e| format!("{}: {}", dot.display(), e))
+                .map(|()| GraphFiles {
+                    png: render(&dot),
+                    dot,
+                });
+            done(result);
+        })
+        .map(drop)
+        .map_err(|e| e.to_string())
+}
+
+/// 用 graphviz 的 `dot` 渲染到同名的 `.png`.
+fn render(dot: &Path) -> Option<PathBuf> {
+    let png = dot.with_extension("png");
+    match Command::new("dot")
+        .arg("-Tpng")
+        .arg("-o")
+        .arg(&png)
+        .arg(dot)
+        .output()
+    {
+        Ok(output) if output.status.success() => Some(png),
+        Ok(output) => {
+            tracing::warn!(
+                status = %output.status,
+                "graphviz failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            tracing::debug!("graphviz is not installed, keeping only the dot file");
+            None
+        }
+        Err(e) => {
+            tracing::warn!("could not run graphviz: {}", e);
+            None
+        }
+    }
+}
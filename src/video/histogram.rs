@@ -0,0 +1,235 @@
+//! 预览画面的亮度直方图, 用来提示高光过曝和暗部死黑.
+//! 只统计显示给界面的预览帧, 与录制的画面可能有细微差别.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// 直方图的格数, 每格 4 级亮度.
+const BINS: usize = 64;
+/// 最亮和最暗的这么多格分别算过曝和死黑.
+const EDGE_BINS: usize = 2;
+/// 横竖都每隔这么多像素采样一次, 1080p 时约 13 万个点.
+const STRIDE: usize = 4;
+/// 每帧的新比例在平滑值中所占的权重.
+const SMOOTHING: f32 = 0.2;
+/// 超过阈值后, 降到阈值的这个比例以下才停止提示, 避免在阈值附近闪烁.
+const RELEASE_RATIO: f32 = 0.7;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ExposureWarningSettings {
+    /// 最亮两格的像素超过这个百分比时提示过曝, 0 表示不提示.
+    pub clipping_percent: f32,
+    /// 最暗两格的像素超过这个百分比时提示死黑, 0 表示不提示.
+    pub crushed_percent: f32,
+}
+
+impl Default for ExposureWarningSettings {
+    fn default() -> Self {
+        Self {
+            clipping_percent: 5.0,
+            crushed_percent: 10.0,
+        }
+    }
+}
+
+impl ExposureWarningSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.clipping_percent > 0.0 || self.crushed_percent > 0.0
+    }
+}
+
+/// BT.709 亮度的直方图.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Histogram {
+    bins: [u32; BINS],
+}
+
+impl Histogram {
+    /// 隔行隔列采样, 不需要每个像素.
+    pub fn from_image(image: &egui::ColorImage) -> Self {
+        let [width, height] = image.size;
+        let mut bins = [0u32; BINS];
+        for y in (0..height).step_by(STRIDE) {
+            for pixel in image.pixels[y * width..(y + 1) * width]
+                .iter()
+                .step_by(STRIDE)
+            {
+                let [r, g, b, _] = pixel.to_array();
+                let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+                let bin = (luma as usize * BINS / 256).min(BINS - 1);
+                bins[bin] += 1;
+            }
+        }
+        Self { bins }
+    }
+
+    fn total(&self) -> u32 {
+        self.bins.iter().sum()
+    }
+
+    fn fraction(&self, count: u32) -> f32 {
+        match self.total() {
+            0 => 0.0,
+            total => count as f32 / total as f32,
+        }
+    }
+
+    /// 落在最亮两格中的像素比例.
+    pub fn clipped(&self) -> f32 {
+        self.fraction(self.bins[BINS - EDGE_BINS..].iter().sum())
+    }
+
+    /// 落在最暗两格中的像素比例.
+    pub fn crushed(&self) -> f32 {
+        self.fraction(self.bins[..EDGE_BINS].iter().sum())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ExposureWarning {
+    /// 过曝像素的百分比.
+    Clipping(f32),
+    /// 死黑像素的百分比.
+    Crushed(f32),
+}
+
+/// 一种提示的平滑比例和开关状态.
+#[derive(Debug, Default)]
+struct Level {
+    smoothed: Option<f32>,
+    active: bool,
+}
+
+impl Level {
+    fn update(&mut self, fraction: f32, threshold_percent: f32) {
+        let smoothed = match self.smoothed {
+            Some(previous) => previous + (fraction - previous) * SMOOTHING,
+            None => fraction,
+        };
+        self.smoothed = Some(smoothed);
+        let percent = smoothed * 100.0;
+        self.active = if threshold_percent <= 0.0 {
+            false
+        } else if self.active {
+            percent >= threshold_percent * RELEASE_RATIO
+        } else {
+            percent >= threshold_percent
+        };
+    }
+
+    fn percent(&self) -> Option<f32> {
+        self.active
+            .then(|| self.smoothed.unwrap_or_default() * 100.0)
+    }
+}
+
+/// 每个预览帧更新一次, 比例经过平滑并带回差, 提示不会随画面跳动.
+#[derive(Debug, Default)]
+pub(crate) struct ExposureAdvisor {
+    clipped: Level,
+    crushed: Level,
+}
+
+impl ExposureAdvisor {
+    pub fn update(&mut self, histogram: &Histogram, settings: &ExposureWarningSettings) {
+        self.clipped
+            .update(histogram.clipped(), settings.clipping_percent);
+        self.crushed
+            .update(histogram.crushed(), settings.crushed_percent);
+    }
+
+    /// 高反差的画面可能同时过曝和死黑.
+    pub fn warnings(&self) -> impl Iterator<Item = ExposureWarning> {
+        let clipped = self.clipped.percent().map(ExposureWarning::Clipping);
+        let crushed = self.crushed.percent().map(ExposureWarning::Crushed);
+        clipped.into_iter().chain(crushed)
+    }
+
+    /// 关闭提示或没有画面时调用, 之后从头开始平滑.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 最暗两格 `dark` 个像素, 最亮两格 `bright` 个, 其余都在中间.
+    fn histogram(dark: u32, bright: u32, total: u32) -> Histogram {
+        let mut bins = [0; BINS];
+        bins[0] = dark;
+        bins[BINS - 1] = bright;
+        bins[BINS / 2] = total - dark - bright;
+        Histogram { bins }
+    }
+
+    #[test]
+    fn counts_the_edge_bins() {
+        let h = histogram(20, 6, 100);
+        assert!((h.clipped() - 0.06).abs() < 1e-6);
+        assert!((h.crushed() - 0.2).abs() < 1e-6);
+        assert_eq!(Histogram { bins: [0; BINS] }.clipped(), 0.0);
+    }
+
+    #[test]
+    fn bins_image_luma() {
+        let white = egui::ColorImage::new([16, 16], vec![egui::Color32::WHITE; 256]);
+        assert_eq!(Histogram::from_image(&white).clipped(), 1.0);
+        let black = egui::ColorImage::new([16, 16], vec![egui::Color32::BLACK; 256]);
+        assert_eq!(Histogram::from_image(&black).crushed(), 1.0);
+        let grey = egui::ColorImage::new([16, 16], vec![egui::Color32::from_gray(128); 256]);
+        let h = Histogram::from_image(&grey);
+        assert_eq!((h.clipped(), h.crushed()), (0.0, 0.0));
+    }
+
+    #[test]
+    fn warns_with_hysteresis() {
+        let settings = ExposureWarningSettings::default();
+        let mut advisor = ExposureAdvisor::default();
+        advisor.update(&histogram(0, 8, 100), &settings);
+        assert!(matches!(
+            advisor.warnings().collect::<Vec<_>>()[..],
+            [ExposureWarning::Clipping(p)] if (p - 8.0).abs() < 1e-3
+        ));
+        // 稍低于阈值时保持提示
+        for _ in 0..20 {
+            advisor.update(&histogram(0, 4, 100), &settings);
+        }
+        assert_eq!(advisor.warnings().count(), 1);
+        // 明显降低后停止
+        for _ in 0..20 {
+            advisor.update(&histogram(0, 1, 100), &settings);
+        }
+        assert_eq!(advisor.warnings().count(), 0);
+        // 再次接近阈值但没有超过时不提示
+        for _ in 0..20 {
+            advisor.update(&histogram(0, 4, 100), &settings);
+        }
+        assert_eq!(advisor.warnings().count(), 0);
+    }
+
+    #[test]
+    fn smooths_single_frame_spikes() {
+        let settings = ExposureWarningSettings::default();
+        let mut advisor = ExposureAdvisor::default();
+        advisor.update(&histogram(0, 0, 100), &settings);
+        advisor.update(&histogram(0, 20, 100), &settings);
+        assert_eq!(advisor.warnings().count(), 0);
+    }
+
+    #[test]
+    fn zero_threshold_disables_a_warning() {
+        let settings = ExposureWarningSettings {
+            clipping_percent: 0.0,
+            crushed_percent: 10.0,
+        };
+        let mut advisor = ExposureAdvisor::default();
+        advisor.update(&histogram(50, 50, 100), &settings);
+        assert!(matches!(
+            advisor.warnings().collect::<Vec<_>>()[..],
+            [ExposureWarning::Crushed(_)]
+        ));
+    }
+}
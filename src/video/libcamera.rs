@@ -0,0 +1,197 @@
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use tracing::{debug, warn};
+
+use super::balance::{self, REFERENCE_KELVIN, WhiteBalance};
+use super::shutter::ShutterSpeed;
+
+/// 树莓派 CSI 摄像头不能用 v4l2src 采集, 需要这个元素.
+pub(crate) const ELEMENT: &str = "libcamerasrc";
+/// analogue-gain 为 1.0 时对应的 ISO.
+const BASE_ISO: f32 = 100.0;
+
+/// gst-launch 语法的视频源是否使用 libcamerasrc.
+pub(crate) fn is_libcamera(source: &str) -> bool {
+    source.split_whitespace().next() == Some(ELEMENT)
+}
+
+/// 是否安装了 libcamera 的 GStreamer 插件. 桌面系统上通常没有, 不影响其他采集源.
+pub(crate) fn available() -> bool {
+    gst::ElementFactory::find(ELEMENT).is_some()
+}
+
+/// 按 libcamera 的相机 ID 选择摄像头, ID 中常有 `/` 和 `@`, 需要加引号.
+pub(crate) fn source(camera: &str) -> String {
+    format!(
+        "{} camera-name=\"{}\"",
+        ELEMENT,
+        camera.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// libcamerasrc 支持的控件. 控件通过元素属性设置而不是 V4L2 ioctl,
+/// 旧版本的插件没有这些属性, 缺少的控件在界面上变灰.
+/// 对焦 (`af-mode`, `lens-position`) 暂不支持, 界面上不显示.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Capabilities {
+    /// ISO → `analogue-gain`, ISO 100 对应 1.0.
+    pub iso: bool,
+    /// 快门 → `ae-enable=false` 加 `exposure-time` (微秒), 回到自动曝光时 `ae-enable=true`.
+    /// libcamerasrc 不报告自动曝光当前的曝光时间, 所以不能锁定.
+    pub shutter: bool,
+    /// 曝光补偿 → `exposure-value`, 单位为 EV.
+    pub compensation: bool,
+    /// 白平衡预设 → `awb-mode`: 日光 daylight, 钨丝灯 tungsten, 阴影 cloudy, 荧光灯 fluorescent.
+    pub white_balance: bool,
+    /// 色温和灰卡 → `awb-enable=false` 加 `colour-temperature` (K).
+    pub colour_temperature: bool,
+}
+
+impl Capabilities {
+    /// 创建一个临时元素检查属性, 不会打开摄像头.
+    pub fn detect() -> Self {
+        let Ok(src) = gst::ElementFactory::make(ELEMENT).build() else {
+            warn!("libcamerasrc is not installed");
+            return Self::default();
+        };
+        let has = |name: &str| src.find_property(name).is_some();
+        let caps = Self {
+            iso: has("analogue-gain"),
+            shutter: has("ae-enable") && has("exposure-time"),
+            compensation: has("exposure-value"),
+            white_balance: has("awb-enable") && has("awb-mode"),
+            colour_temperature: has("awb-enable") && has("colour-temperature"),
+        };
+        tracing::info!(?caps, "using libcamera controls");
+        caps
+    }
+
+    /// 不支持时由管线中的红蓝增益代替.
+    pub fn handles_white_balance(&self, white_balance: WhiteBalance) -> bool {
+        match awb_mode(white_balance) {
+            Some(_) => self.white_balance,
+            None => self.colour_temperature,
+        }
+    }
+}
+
+/// 通过 libcamerasrc 属性设置的控件.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LibcameraControl {
+    Iso(u32),
+    /// None 表示回到自动曝光.
+    Shutter(Option<ShutterSpeed>),
+    /// 单位为 EV.
+    Compensation(f32),
+    WhiteBalance(WhiteBalance),
+}
+
+fn analogue_gain(iso: u32) -> f32 {
+    iso as f32 / BASE_ISO
+}
+
+/// 预设对应的 `awb-mode`, 色温和灰卡返回 None.
+fn awb_mode(white_balance: WhiteBalance) -> Option<&'static str> {
+    match white_balance {
+        WhiteBalance::Auto => Some("auto"),
+        WhiteBalance::Daylight => Some("daylight"),
+        WhiteBalance::Tungsten => Some("tungsten"),
+        WhiteBalance::Shade => Some("cloudy"),
+        WhiteBalance::Fluorescent => Some("fluorescent"),
+        WhiteBalance::Kelvin(_) | WhiteBalance::GreyCard(_) => None,
+    }
+}
+
+/// 运行中设置的属性会随下一个采集请求生效.
+pub(crate) fn apply(src: &gst::Element, control: LibcameraControl) {
+    debug!(element = %src.name(), ?control, "setting libcamera control");
+    match control {
+        LibcameraControl::Iso(iso) => set(src, "analogue-gain", analogue_gain(iso).to_value()),
+        LibcameraControl::Shutter(Some(shutter)) => {
+            set(src, "ae-enable", false.to_value());
+            let micros = shutter.micros().min(i32::MAX as u64) as i32;
+            set(src, "exposure-time", micros.to_value());
+        }
+        LibcameraControl::Shutter(None) => set(src, "ae-enable", true.to_value()),
+        LibcameraControl::Compensation(ev) => set(src, "exposure-value", ev.to_value()),
+        LibcameraControl::WhiteBalance(white_balance) => match awb_mode(white_balance) {
+            Some(mode) => {
+                set(src, "awb-enable", true.to_value());
+                set_enum(src, "awb-mode", mode);
+            }
+            None => {
+                let kelvin = match white_balance {
+                    WhiteBalance::GreyCard(gains) => balance::kelvin_for_gains(gains),
+                    _ => white_balance.kelvin().unwrap_or(REFERENCE_KELVIN),
+                };
+                set(src, "awb-enable", false.to_value());
+                set(src, "colour-temperature", (kelvin as i32).to_value());
+            }
+        },
+    }
+}
+
+/// 不同版本的属性类型不完全一样 (int/int64, float/double), 按属性的类型转换.
+fn set(src: &gst::Element, name: &str, value: glib::Value) {
+    let Some(pspec) = src.find_property(name) else {
+        warn!(element = %src.name(), property = name, "source has no such property");
+        return;
+    };
+    match value.transform_with_type(pspec.value_type()) {
+        Ok(value) => src.set_property_from_value(name, &value),
+        Err(e) => warn!(element = %src.name(), property = name, "could not convert value: {}", e),
+    }
+}
+
+fn set_enum(src: &gst::Element, name: &str, nick: &str) {
+    let Some(pspec) = src.find_property(name) else {
+        warn!(element = %src.name(), property = name, "source has no such property");
+        return;
+    };
+    match glib::EnumClass::with_type(pspec.value_type()).and_then(|c| c.to_value_by_nick(nick)) {
+        Some(value) => src.set_property_from_value(name, &value),
+        None => warn!(element = %src.name(), property = name, nick, "unknown enum value"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::balance::ChannelGains;
+
+    #[test]
+    fn recognises_libcamera_sources() {
+        assert!(is_libcamera("libcamerasrc"));
+        assert!(is_libcamera("libcamerasrc camera-name=\"/base/imx708@1a\""));
+        assert!(!is_libcamera("v4l2src device=/dev/video0"));
+        assert!(!is_libcamera("videotestsrc is-live=true"));
+    }
+
+    #[test]
+    fn quotes_the_camera_name() {
+        assert_eq!(
+            source("/base/soc/i2c0mux/i2c@1/imx708@1a"),
+            "libcamerasrc camera-name=\"/base/soc/i2c0mux/i2c@1/imx708@1a\""
+        );
+        assert_eq!(source("a\"b"), "libcamerasrc camera-name=\"a\\\"b\"");
+    }
+
+    #[test]
+    fn maps_iso_and_white_balance() {
+        assert_eq!(analogue_gain(100), 1.0);
+        assert_eq!(analogue_gain(800), 8.0);
+        assert_eq!(awb_mode(WhiteBalance::Shade), Some("cloudy"));
+        assert_eq!(awb_mode(WhiteBalance::Kelvin(4500)), None);
+
+        let presets_only = Capabilities {
+            white_balance: true,
+            ..Default::default()
+        };
+        assert!(presets_only.handles_white_balance(WhiteBalance::Daylight));
+        assert!(!presets_only.handles_white_balance(WhiteBalance::Kelvin(4500)));
+        assert!(
+            !presets_only.handles_white_balance(WhiteBalance::GreyCard(ChannelGains::default()))
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// 每一路的缩略预览, 下标与采集源相同. 还没有帧时为 None.
+pub(crate) type Tiles = Arc<Mutex<Vec<Option<egui::ColorImage>>>>;
+
+const TILE_WIDTH: u32 = 320;
+const TILE_HEIGHT: u32 = 180;
+/// 缩略图不需要满帧率.
+const TILE_FPS: u32 = 10;
+
+fn sink_name(index: usize) -> String {
+    format!("tile_sink{}", index)
+}
+
+fn valve_name(index: usize) -> String {
+    format!("tile_valve{}", index)
+}
+
+/// 从每一路的 tee 分出的低分辨率分支. 关闭多画面时 valve 丢弃数据, 后面的缩放不再占用 CPU.
+pub(crate) fn tile_branch(tee: &str, index: usize) -> String {
+    format!(
+        "{tee}. ! queue max-size-buffers=1 leaky=downstream ! valve name={valve} drop=true ! videorate ! videoscale ! videoconvert ! video/x-raw,format=RGBA,width={w},height={h},framerate={fps}/1 ! appsink name={sink} sync=false max-buffers=1 drop=true",
+        tee = tee,
+        valve = valve_name(index),
+        w = TILE_WIDTH,
+        h = TILE_HEIGHT,
+        fps = TILE_FPS,
+        sink = sink_name(index),
+    )
+}
+
+/// 把每一路的缩略图送进共享缓冲区. 管线中没有多画面分支时什么也不做.
+pub(crate) fn connect(pipeline: &gst::Pipeline, tiles: Tiles) {
+    let mut index = 0;
+    while let Some(sink) = pipeline
+        .by_name(&sink_name(index))
+        .and_then(|s| s.dynamic_cast::<gst_app::AppSink>().ok())
+    {
+        let tiles = tiles.clone();
+        sink.set_callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+                    let caps = sample.caps().ok_or(gst::FlowError::Error)?;
+                    let info =
+                        gst_video::VideoInfo::from_caps(caps).map_err(|_| gst::FlowError::Error)?;
+                    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+                    let image = egui::ColorImage::from_rgba_unmultiplied(
+                        [info.width() as usize, info.height() as usize],
+                        map.as_slice(),
+                    );
+                    let mut tiles = tiles.lock();
+                    if tiles.len() <= index {
+                        tiles.resize(index + 1, None);
+                    }
+                    tiles[index] = Some(image);
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+        index += 1;
+    }
+}
+
+/// 打开或关闭所有缩略图分支.
+pub(crate) fn set_enabled(pipeline: &gst::Pipeline, enabled: bool) {
+    let mut index = 0;
+    while let Some(valve) = pipeline.by_name(&valve_name(index)) {
+        valve.set_property("drop", !enabled);
+        index += 1;
+    }
+    tracing::debug!(enabled, tiles = index, "multiview");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_branches_start_closed() {
+        let branch = tile_branch("src_t1", 1);
+        assert!(branch.starts_with("src_t1. ! queue"));
+        assert!(branch.contains("valve name=tile_valve1 drop=true"));
+        assert!(branch.ends_with("appsink name=tile_sink1 sync=false max-buffers=1 drop=true"));
+    }
+}
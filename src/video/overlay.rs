@@ -0,0 +1,276 @@
+//! cairooverlay 画在视频上的叠加层. 设置由界面随时写入, 流线程每一帧读取;
+//! 拿不到锁时沿用上一帧的设置, 绘制从不等待界面.
+//!
+//! 预览分支总有叠加层. 录制分支只在开始录制时启用了电平表烧录才有,
+//! 而且只画电平表, 其余情况下录像是干净的.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::pip::Corner;
+
+/// 录制分支中叠加层的名字.
+pub(super) const BURN_IN: &str = "burn_in";
+
+/// 电平表的下限, 与界面上的一样低于 -60 dB 当作静音.
+const METER_FLOOR_DB: f32 = -60.0;
+/// 电平下降的速度, 上升则立即跟上.
+const METER_FALL_DB_PER_SEC: f32 = 24.0;
+/// 电平表的宽度和高度占画面宽度的比例.
+const METER_WIDTH: f64 = 0.25;
+const METER_HEIGHT: f64 = 0.015;
+/// 离画面边缘的距离, 占画面宽度的比例.
+const METER_MARGIN: f64 = 0.025;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct OverlayConfig {
+    /// 三分参考线.
+    pub thirds: bool,
+    pub meter: MeterOverlay,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            thirds: true,
+            meter: MeterOverlay::default(),
+        }
+    }
+}
+
+/// 画进画面的电平表, 转发或录下的画面中也能看到电平.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct MeterOverlay {
+    pub enabled: bool,
+    pub corner: Corner,
+    /// 同时烧录进录像. 在开始录制时决定, 录制中修改只影响下一条.
+    pub burn_in: bool,
+}
+
+/// 界面和流线程共享的叠加设置, 克隆后指向同一份.
+/// 电平与界面的电平表是同一个值, 由 gst 线程根据 level 消息更新.
+#[derive(Clone)]
+pub(crate) struct SharedOverlay {
+    config: Arc<Mutex<OverlayConfig>>,
+    level: Arc<Mutex<f32>>,
+}
+
+impl Default for SharedOverlay {
+    fn default() -> Self {
+        Self::new(
+            OverlayConfig::default(),
+            Arc::new(Mutex::new(METER_FLOOR_DB)),
+        )
+    }
+}
+
+impl SharedOverlay {
+    pub fn new(config: OverlayConfig, level: Arc<Mutex<f32>>) -> Self {
+        Self {
+            config: Arc::new(Mutex::new(config)),
+            level,
+        }
+    }
+
+    /// 下一帧生效.
+    pub fn set(&self, config: OverlayConfig) {
+        *self.config.lock() = config;
+    }
+
+    /// 是否在新的录像中烧录电平表.
+    pub(super) fn burns_in(&self) -> bool {
+        let config = self.config.lock();
+        config.meter.enabled && config.meter.burn_in
+    }
+
+    /// 界面正在写入时返回 None.
+    fn try_get(&self) -> Option<OverlayConfig> {
+        self.config.try_lock().map(|config| config.clone())
+    }
+
+    /// 测试中模拟界面长时间持有锁.
+    #[cfg(test)]
+    pub(super) fn lock(&self) -> parking_lot::MutexGuard<'_, OverlayConfig> {
+        self.config.lock()
+    }
+}
+
+/// 流线程中跨帧的状态, 只有流线程使用, 不会争用.
+#[derive(Default)]
+struct DrawState {
+    config: OverlayConfig,
+    meter: Ballistics,
+}
+
+/// 在预览分支的 cairooverlay 中按 `shared` 的设置绘制.
+pub(super) fn connect(overlay: &gst::Element, shared: SharedOverlay) {
+    connect_with(overlay, shared, false);
+}
+
+/// 录制分支的叠加层只画电平表.
+pub(super) fn connect_burn_in(overlay: &gst::Element, shared: SharedOverlay) {
+    connect_with(overlay, shared, true);
+}
+
+fn connect_with(overlay: &gst::Element, shared: SharedOverlay, meter_only: bool) {
+    let state = Mutex::new(DrawState {
+        config: shared.try_get().unwrap_or_default(),
+        meter: Ballistics::default(),
+    });
+    overlay.connect("draw", false, move |values| {
+        let mut state = state.lock();
+        if let Some(latest) = shared.try_get() {
+            state.config = latest;
+        }
+        if meter_only {
+            state.config.thirds = false;
+        }
+        draw(values, &mut state, &shared.level);
+        None
+    });
+}
+
+fn draw(values: &[gst::glib::Value], state: &mut DrawState, level: &Mutex<f32>) {
+    let config = &state.config;
+    if !config.thirds && !config.meter.enabled {
+        return;
+    }
+    // values[0]: cairooverlay 元素本身
+    // values[1]: cairo::Context
+    // values[2]: timestamp
+    // values[3]: duration
+    let (Ok(overlay), Ok(cr)) = (
+        values[0].get::<gst::Element>(),
+        values[1].get::<cairo::Context>(),
+    ) else {
+        return;
+    };
+    let Some((width, height)) = frame_size(&overlay) else {
+        return;
+    };
+
+    if config.meter.enabled {
+        let timestamp = values[2].get::<u64>().unwrap_or_default();
+        // 拿不到锁时这一帧只按时间回落
+        let db = level.try_lock().map(|db| *db);
+        let shown = state.meter.update(db, Duration::from_nanos(timestamp));
+        draw_meter(&cr, width, height, config.meter.corner, shown);
+    }
+
+    if config.thirds {
+        cr.set_source_rgba(1.0, 1.0, 1.0, 0.5); // 白色，0.5 透明度
+        cr.set_line_width(2.0);
+        for i in 1..3 {
+            // 垂直线
+            let x = width / 3.0 * i as f64;
+            cr.move_to(x, 0.0);
+            cr.line_to(x, height);
+            // 水平线
+            let y = height / 3.0 * i as f64;
+            cr.move_to(0.0, y);
+            cr.line_to(width, y);
+        }
+        if let Err(e) = cr.stroke() {
+            tracing::debug!("overlay stroke failed: {}", e);
+        }
+    }
+}
+
+/// 输入端协商的分辨率.
+fn frame_size(overlay: &gst::Element) -> Option<(f64, f64)> {
+    let caps = overlay.static_pad("sink")?.current_caps()?;
+    let structure = caps.structure(0)?;
+    let width = structure.get::<i32>("width").ok()?;
+    let height = structure.get::<i32>("height").ok()?;
+    Some((width as f64, height as f64))
+}
+
+/// 横向的电平条, 颜色分段与常见的数字电平表相同.
+fn draw_meter(cr: &cairo::Context, width: f64, height: f64, corner: Corner, db: f32) {
+    let w = width * METER_WIDTH;
+    let h = (width * METER_HEIGHT).max(4.0);
+    let margin = width * METER_MARGIN;
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => margin,
+        Corner::TopRight | Corner::BottomRight => width - margin - w,
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => margin,
+        Corner::BottomLeft | Corner::BottomRight => height - margin - h,
+    };
+
+    cr.set_source_rgba(0.0, 0.0, 0.0, 0.6);
+    cr.rectangle(x - 2.0, y - 2.0, w + 4.0, h + 4.0);
+    let _ = cr.fill();
+
+    let fill = meter_fraction(db) * w;
+    // -18 dB 以下绿色, -6 dB 以下黄色, 以上红色
+    for (from, to, (r, g, b)) in [
+        (METER_FLOOR_DB, -18.0, (0.2, 0.8, 0.3)),
+        (-18.0, -6.0, (0.95, 0.8, 0.2)),
+        (-6.0, 0.0, (0.9, 0.2, 0.2)),
+    ] {
+        let start = meter_fraction(from) * w;
+        let end = (meter_fraction(to) * w).min(fill);
+        if end > start {
+            cr.set_source_rgb(r, g, b);
+            cr.rectangle(x + start, y, end - start, h);
+            let _ = cr.fill();
+        }
+    }
+}
+
+/// dBFS 在电平条上的位置, 0 到 1.
+fn meter_fraction(db: f32) -> f64 {
+    ((db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0) as f64
+}
+
+/// 电平表的动态: 上升立即显示, 下降按 [METER_FALL_DB_PER_SEC] 回落.
+/// level 消息的间隔比帧间隔长, 这样每一帧都有变化, 看起来是连续的.
+#[derive(Debug, Default)]
+struct Ballistics {
+    /// 上一帧的时间戳和显示的电平.
+    last: Option<(Duration, f32)>,
+}
+
+impl Ballistics {
+    /// `db` 为 None 时只按时间回落.
+    fn update(&mut self, db: Option<f32>, at: Duration) -> f32 {
+        let shown = match self.last {
+            None => db.unwrap_or(METER_FLOOR_DB),
+            Some((before, shown)) => {
+                let fallen =
+                    shown - METER_FALL_DB_PER_SEC * at.saturating_sub(before).as_secs_f32();
+                db.map_or(fallen, |db| db.max(fallen))
+            }
+        }
+        .max(METER_FLOOR_DB);
+        self.last = Some((at, shown));
+        shown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meter_rises_at_once_and_falls_smoothly() {
+        let mut meter = Ballistics::default();
+        let ms = Duration::from_millis;
+        assert_eq!(meter.update(Some(-40.0), ms(0)), -40.0);
+        assert_eq!(meter.update(Some(-6.0), ms(33)), -6.0);
+        // 半秒后回落 12 dB, 而不是直接跳到新的电平
+        assert_eq!(meter.update(Some(-50.0), ms(533)), -18.0);
+        // 没有新的电平时继续回落, 不低于下限
+        assert_eq!(meter.update(None, ms(10_000)), METER_FLOOR_DB);
+        assert_eq!(meter_fraction(-30.0), 0.5);
+        assert_eq!(meter_fraction(6.0), 1.0);
+    }
+}
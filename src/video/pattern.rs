@@ -0,0 +1,133 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 没有摄像头时的测试源, 也用来检查整条信号链.
+const ELEMENT: &str = "videotestsrc";
+
+/// gst-launch 语法的视频源是否是测试源.
+pub(crate) fn is_test_source(source: &str) -> bool {
+    source.split_whitespace().next() == Some(ELEMENT)
+}
+
+/// `videotestsrc` 的 `pattern` 属性.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum TestPattern {
+    #[default]
+    Smpte,
+    Smpte100,
+    /// 移动的球, 跳动说明丢了帧.
+    Ball,
+    Snow,
+    Checkers,
+    Black,
+    White,
+    Red,
+    Green,
+    Blue,
+}
+
+impl TestPattern {
+    pub const ALL: [TestPattern; 10] = [
+        TestPattern::Smpte,
+        TestPattern::Smpte100,
+        TestPattern::Ball,
+        TestPattern::Snow,
+        TestPattern::Checkers,
+        TestPattern::Black,
+        TestPattern::White,
+        TestPattern::Red,
+        TestPattern::Green,
+        TestPattern::Blue,
+    ];
+
+    /// `pattern` 属性的 nick.
+    fn nick(self) -> &'static str {
+        match self {
+            TestPattern::Smpte => "smpte",
+            TestPattern::Smpte100 => "smpte100",
+            TestPattern::Ball => "ball",
+            TestPattern::Snow => "snow",
+            TestPattern::Checkers => "checkers-8",
+            TestPattern::Black => "black",
+            TestPattern::White => "white",
+            TestPattern::Red => "red",
+            TestPattern::Green => "green",
+            TestPattern::Blue => "blue",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TestPattern::Smpte => "SMPTE bars",
+            TestPattern::Smpte100 => "SMPTE 100% bars",
+            TestPattern::Ball => "Moving ball",
+            TestPattern::Snow => "Snow",
+            TestPattern::Checkers => "Checkers",
+            TestPattern::Black => "Black",
+            TestPattern::White => "White",
+            TestPattern::Red => "Red",
+            TestPattern::Green => "Green",
+            TestPattern::Blue => "Blue",
+        }
+    }
+}
+
+/// 测试源的设置, 视频源不是 `videotestsrc` 时不使用.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct TestSourceConfig {
+    pub pattern: TestPattern,
+    /// 在画面上叠加运行时间, 录下来的测试片段可以自己说明来源.
+    pub time_overlay: bool,
+}
+
+impl TestSourceConfig {
+    /// 加在测试源的属性后面, `source` 中已有的 `pattern` 被覆盖.
+    pub fn apply_to(&self, source: &str) -> String {
+        let mut source = format!("{} pattern={}", source, self.pattern.nick());
+        if self.time_overlay {
+            source +=
+                " ! timeoverlay halignment=right valignment=bottom font-desc=\"Monospace 24\"";
+        }
+        source
+    }
+}
+
+/// 运行中切换图案, 不需要重建管线. 当前的源不是测试源时忽略.
+pub(crate) fn set_pattern(src: &gst::Element, pattern: TestPattern) {
+    if src.factory().is_none_or(|f| f.name() != ELEMENT) {
+        return;
+    }
+    tracing::debug!(element = %src.name(), ?pattern, "setting test pattern");
+    src.set_property_from_str("pattern", pattern.nick());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_pattern_and_overlay() {
+        let plain = TestSourceConfig {
+            pattern: TestPattern::Ball,
+            time_overlay: false,
+        };
+        assert_eq!(
+            plain.apply_to("videotestsrc is-live=true"),
+            "videotestsrc is-live=true pattern=ball"
+        );
+        let overlay = TestSourceConfig {
+            time_overlay: true,
+            ..plain
+        };
+        assert!(
+            overlay
+                .apply_to("videotestsrc is-live=true")
+                .starts_with("videotestsrc is-live=true pattern=ball ! timeoverlay")
+        );
+        assert!(is_test_source("videotestsrc is-live=true"));
+        assert!(!is_test_source("v4l2src device=/dev/video0"));
+    }
+}
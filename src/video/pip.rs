@@ -0,0 +1,337 @@
+use eframe::egui;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// 合成主画面和小画面的 compositor, 在视频 tee 之前.
+const COMPOSITOR: &str = "pip";
+/// 小画面的源, 与主采集源的 `src` 区分.
+const INSET_SOURCE: &str = "pip_src";
+/// 只在界面上显示时小画面送到这里.
+const INSET_SINK: &str = "pip_sink";
+/// compositor 的三个输入: 主画面, 边框, 小画面.
+const MAIN_PAD: &str = "sink_0";
+const BORDER_PAD: &str = "sink_1";
+const INSET_PAD: &str = "sink_2";
+
+pub(crate) const MIN_SIZE_PERCENT: u32 = 10;
+pub(crate) const MAX_SIZE_PERCENT: u32 = 50;
+/// 小画面离画面边缘的距离, 占画面宽度的比例.
+const MARGIN: f32 = 0.025;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl Corner {
+    pub const ALL: [Corner; 4] = [
+        Corner::TopLeft,
+        Corner::TopRight,
+        Corner::BottomLeft,
+        Corner::BottomRight,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Corner::TopLeft => "Top left",
+            Corner::TopRight => "Top right",
+            Corner::BottomLeft => "Bottom left",
+            Corner::BottomRight => "Bottom right",
+        }
+    }
+}
+
+/// 运行中可以改变的布局, 设置面板和快捷键修改后发送给 gst 线程.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipLayout {
+    pub corner: Corner,
+    /// 小画面宽度占画面宽度的百分比.
+    pub size_percent: u32,
+    /// 交换主画面和小画面.
+    pub swapped: bool,
+}
+
+impl Default for PipLayout {
+    fn default() -> Self {
+        Self {
+            corner: Corner::BottomRight,
+            size_percent: 25,
+            swapped: false,
+        }
+    }
+}
+
+/// 画中画: 在主画面上叠加第二个采集源.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PipConfig {
+    /// gst-launch 语法的小画面源, 例如屏幕录制或第二个摄像头.
+    pub source: String,
+    #[serde(flatten)]
+    pub layout: PipLayout,
+    /// 边框颜色 (RGB) 和宽度, 宽度为 0 时没有边框.
+    pub border_color: [u8; 3],
+    pub border_px: u32,
+    /// 在 tee 之前合成, 录像中也有小画面. 否则只在界面上叠加, 用于监看.
+    pub burn_in: bool,
+    /// 合成后的画面尺寸, 只在 `burn_in` 时使用.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for PipConfig {
+    fn default() -> Self {
+        Self {
+            source: String::new(),
+            layout: PipLayout::default(),
+            border_color: [255, 255, 255],
+            border_px: 4,
+            burn_in: false,
+            width: 1920,
+            height: 1080,
+        }
+    }
+}
+
+/// 画面中的矩形, 单位为像素.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    fn grow(self, by: i32) -> Self {
+        Self {
+            x: self.x - by,
+            y: self.y - by,
+            width: self.width + by * 2,
+            height: self.height + by * 2,
+        }
+    }
+}
+
+/// 小画面的位置, 保持与外框相同的宽高比.
+pub(crate) fn inset_rect(width: u32, height: u32, layout: &PipLayout) -> Rect {
+    let percent = layout
+        .size_percent
+        .clamp(MIN_SIZE_PERCENT, MAX_SIZE_PERCENT) as f32
+        / 100.0;
+    let w = (width as f32 * percent).round() as i32;
+    let h = (height as f32 * percent).round() as i32;
+    let margin = (width as f32 * MARGIN).round() as i32;
+    let (width, height) = (width as i32, height as i32);
+    let x = match layout.corner {
+        Corner::TopLeft | Corner::BottomLeft => margin,
+        Corner::TopRight | Corner::BottomRight => width - margin - w,
+    };
+    let y = match layout.corner {
+        Corner::TopLeft | Corner::TopRight => margin,
+        Corner::BottomLeft | Corner::BottomRight => height - margin - h,
+    };
+    Rect {
+        x,
+        y,
+        width: w,
+        height: h,
+    }
+}
+
+impl PipConfig {
+    /// 合成时: compositor 和它的三个输入, 以 compositor 的输出结束.
+    /// 只在界面上显示时: 原样返回主画面, 小画面单独送到一个 appsink.
+    pub fn video_input(&self, main: &str) -> String {
+        let inset = format!("{} name={}", self.source, INSET_SOURCE);
+        if !self.burn_in {
+            return format!(
+                "{inset} ! videoconvert ! videoscale ! video/x-raw,format=RGBA,width=640,height=360 ! appsink name={sink} sync=false max-buffers=1 drop=true\n{main}",
+                inset = inset,
+                sink = INSET_SINK,
+                main = main
+            );
+        }
+        let [r, g, b] = self.border_color;
+        format!(
+            "compositor name={pip} background=black\n\
+             {main} ! videoconvert ! queue ! {pip}.{main_pad}\n\
+             videotestsrc is-live=true pattern=solid-color foreground-color=0x{argb:08x} ! video/x-raw,width=16,height=16 ! queue ! {pip}.{border_pad}\n\
+             {inset} ! videoconvert ! queue ! {pip}.{inset_pad}\n\
+             {pip}.",
+            pip = COMPOSITOR,
+            main = main,
+            main_pad = MAIN_PAD,
+            argb = 0xff00_0000u32 | (r as u32) << 16 | (g as u32) << 8 | b as u32,
+            border_pad = BORDER_PAD,
+            inset = inset,
+            inset_pad = INSET_PAD,
+        )
+    }
+}
+
+/// 管线中 compositor 的输入 pad, 布局改变时直接修改 pad 属性, 不需要重新协商.
+pub(crate) struct PipCompositor {
+    main: gst::Pad,
+    border: gst::Pad,
+    inset: gst::Pad,
+    width: u32,
+    height: u32,
+    border_px: u32,
+}
+
+impl PipCompositor {
+    /// 只在界面上显示时返回 None.
+    pub fn attach(pipeline: &gst::Pipeline, config: &PipConfig) -> Result<Option<Self>, String> {
+        if !config.burn_in {
+            return Ok(None);
+        }
+        let compositor = pipeline
+            .by_name(COMPOSITOR)
+            .ok_or("pipeline has no picture-in-picture compositor")?;
+        let pad = |name: &str| {
+            compositor
+                .static_pad(name)
+                .ok_or_else(|| format!("compositor has no pad '{}'", name))
+        };
+        let pip = Self {
+            main: pad(MAIN_PAD)?,
+            border: pad(BORDER_PAD)?,
+            inset: pad(INSET_PAD)?,
+            width: config.width,
+            height: config.height,
+            border_px: config.border_px,
+        };
+        pip.apply(&config.layout);
+        Ok(Some(pip))
+    }
+
+    pub fn apply(&self, layout: &PipLayout) {
+        let full = Rect {
+            x: 0,
+            y: 0,
+            width: self.width as i32,
+            height: self.height as i32,
+        };
+        let small = inset_rect(self.width, self.height, layout);
+        let (main, inset) = if layout.swapped {
+            (small, full)
+        } else {
+            (full, small)
+        };
+        place(&self.main, main, if layout.swapped { 2 } else { 0 });
+        place(&self.inset, inset, if layout.swapped { 0 } else { 2 });
+        place(&self.border, small.grow(self.border_px as i32), 1);
+        self.border
+            .set_property("alpha", if self.border_px > 0 { 1.0f64 } else { 0.0 });
+        tracing::debug!(?layout, "picture-in-picture layout");
+    }
+}
+
+fn place(pad: &gst::Pad, rect: Rect, zorder: u32) {
+    pad.set_property("xpos", rect.x);
+    pad.set_property("ypos", rect.y);
+    pad.set_property("width", rect.width);
+    pad.set_property("height", rect.height);
+    pad.set_property("zorder", zorder);
+}
+
+/// 只在界面上显示时, 把小画面送进共享缓冲区.
+pub(crate) fn connect_inset(pipeline: &gst::Pipeline, inset: Arc<Mutex<Option<egui::ColorImage>>>) {
+    let Some(sink) = pipeline
+        .by_name(INSET_SINK)
+        .and_then(|s| s.dynamic_cast::<gst_app::AppSink>().ok())
+    else {
+        return;
+    };
+    sink.set_callbacks(
+        gst_app::AppSinkCallbacks::builder()
+            .new_sample(move |sink| {
+                let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+                let caps = sample.caps().ok_or(gst::FlowError::Error)?;
+                let info =
+                    gst_video::VideoInfo::from_caps(caps).map_err(|_| gst::FlowError::Error)?;
+                let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+                *inset.lock() = Some(egui::ColorImage::from_rgba_unmultiplied(
+                    [info.width() as usize, info.height() as usize],
+                    map.as_slice(),
+                ));
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn places_the_inset_in_each_corner() {
+        let layout = |corner| PipLayout {
+            corner,
+            size_percent: 25,
+            swapped: false,
+        };
+        assert_eq!(
+            inset_rect(1920, 1080, &layout(Corner::TopLeft)),
+            Rect {
+                x: 48,
+                y: 48,
+                width: 480,
+                height: 270
+            }
+        );
+        assert_eq!(
+            inset_rect(1920, 1080, &layout(Corner::BottomRight)),
+            Rect {
+                x: 1920 - 48 - 480,
+                y: 1080 - 48 - 270,
+                width: 480,
+                height: 270
+            }
+        );
+        // 超出范围的大小按上限处理
+        let huge = PipLayout {
+            size_percent: 90,
+            ..layout(Corner::TopLeft)
+        };
+        assert_eq!(inset_rect(1920, 1080, &huge).width, 960);
+    }
+
+    #[test]
+    fn burn_in_composites_before_the_tee() {
+        let config = PipConfig {
+            source: "ximagesrc".to_string(),
+            burn_in: true,
+            border_color: [255, 0, 0],
+            ..Default::default()
+        };
+        let input = config.video_input("v4l2src name=src");
+        assert!(input.starts_with("compositor name=pip"));
+        assert!(input.contains("v4l2src name=src ! videoconvert ! queue ! pip.sink_0"));
+        assert!(input.contains("foreground-color=0xffff0000"));
+        assert!(input.contains("ximagesrc name=pip_src ! videoconvert ! queue ! pip.sink_2"));
+        assert!(input.ends_with("pip."));
+
+        let monitor = PipConfig {
+            burn_in: false,
+            ..config
+        };
+        let input = monitor.video_input("v4l2src name=src");
+        assert!(input.contains("appsink name=pip_sink"));
+        assert!(input.ends_with("\nv4l2src name=src"));
+    }
+}
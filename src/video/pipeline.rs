@@ -0,0 +1,367 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+
+use super::balance::ChannelBalance;
+use super::bars::{self, ColorBars};
+use super::denoise::{self, DenoiseFilter, Denoiser};
+use super::pip::{PipCompositor, PipConfig};
+use super::screen::PortalOptions;
+use super::sharpen::{self, SharpenFilter, Sharpener};
+use super::snapshot::STILL_SINK;
+use super::switcher::{SourceSwitcher, SwitchConfig};
+use super::zoom::DigitalZoom;
+
+/// 管线中各元素的名称, 录制分支和总线处理按名称查找.
+pub(crate) const SOURCE: &str = "src";
+pub(crate) const VIDEO_TEE: &str = "t_v";
+pub(crate) const AUDIO_TEE: &str = "t_a";
+pub(crate) const PREVIEW_SINK: &str = "sink";
+pub(crate) const OVERLAY: &str = "overlay";
+pub(crate) const AUDIO_METER: &str = "audio_meter";
+pub(crate) const AUDIO_GAIN: &str = "audio_gain";
+pub(crate) const ZOOM_CROP: &str = "zoom_crop";
+pub(crate) const ZOOM_CAPS: &str = "zoom_caps";
+
+/// 管线的采集源, 测试时可以替换成 `videotestsrc` / `audiotestsrc`.
+#[derive(Debug, Clone)]
+pub(crate) struct CaptureSources {
+    /// 必须命名为 `src`, 用于读取设备名.
+    pub video: String,
+    pub audio: String,
+    /// 设置时视频源要等门户授权后才能确定, gst 线程在构建管线前申请.
+    pub screen_portal: Option<PortalOptions>,
+    /// 设置时 `video` 是第一路, 和其余各路一起接到选择器上, 可以在运行中切换.
+    pub switch: Option<SwitchConfig>,
+    /// 画中画的小画面, 叠加在上面的 (可能已经切换过的) 视频上.
+    pub pip: Option<PipConfig>,
+}
+
+impl Default for CaptureSources {
+    fn default() -> Self {
+        Self {
+            video: format!("videotestsrc name={} is-live=true", SOURCE),
+            audio: "osxaudiosrc".to_string(),
+            screen_portal: None,
+            switch: None,
+            pip: None,
+        }
+    }
+}
+
+/// 预览分支输出给 egui 的尺寸.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PreviewSettings {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct AudioSettings {
+    /// 电平消息的间隔 (纳秒).
+    pub meter_interval_ns: u64,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            meter_interval_ns: 50_000_000,
+        }
+    }
+}
+
+/// 输入音量倍数的上限, 1 为原样.
+pub(crate) const MAX_AUDIO_GAIN: f64 = 2.0;
+
+/// 构建好的管线中需要单独操作的元素.
+pub(crate) struct PipelineHandles {
+    pub video_tee: gst::Element,
+    pub audio_tee: gst::Element,
+    /// `volume` 元素, 在 tee 之前, 电平表和录音都受它影响.
+    pub audio_gain: gst::Element,
+    pub appsink: gst_app::AppSink,
+    pub overlay: gst::Element,
+    /// 挂在彩条选择器的摄像头一路上, 预览和录制都会经过.
+    pub balance: ChannelBalance,
+    pub bars: ColorBars,
+    pub zoom: DigitalZoom,
+    /// 只有构建时指定了降噪滤镜才有.
+    pub denoise: Option<Denoiser>,
+    /// 在降噪之后. 只有构建时指定了锐化滤镜才有.
+    pub sharpen: Option<Sharpener>,
+    /// 只有多路采集源时才有.
+    pub switcher: Option<SourceSwitcher>,
+    /// 只有合成进录像的画中画才有.
+    pub pip: Option<PipCompositor>,
+}
+
+/// 根据采集源, 预览和音频设置生成管线描述并构建管线.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PipelineBuilder {
+    sources: CaptureSources,
+    preview: PreviewSettings,
+    audio: AudioSettings,
+    denoise: Option<DenoiseFilter>,
+    sharpen: Option<SharpenFilter>,
+}
+
+impl PipelineBuilder {
+    pub fn new(sources: CaptureSources) -> Self {
+        Self {
+            sources,
+            ..Default::default()
+        }
+    }
+
+    pub fn preview(mut self, preview: PreviewSettings) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    pub fn audio(mut self, audio: AudioSettings) -> Self {
+        self.audio = audio;
+        self
+    }
+
+    /// 在 tee 之前预留降噪的一路, 默认关闭, 运行中由 [Denoiser] 开关.
+    pub fn denoise(mut self, filter: Option<DenoiseFilter>) -> Self {
+        self.denoise = filter;
+        self
+    }
+
+    /// 在降噪之后预留锐化的一路, 默认关闭. 只锐化录像时不经过这里.
+    pub fn sharpen(mut self, filter: Option<SharpenFilter>) -> Self {
+        self.sharpen = filter;
+        self
+    }
+
+    /// `gst::parse::launch` 使用的描述字符串.
+    pub fn description(&self) -> String {
+        let (mut video_input, audio_input) = match &self.sources.switch {
+            Some(switch) => (
+                switch.video_input(&self.sources.video),
+                switch.audio_input(&self.sources.audio),
+            ),
+            None => (self.sources.video.clone(), self.sources.audio.clone()),
+        };
+        if let Some(pip) = &self.sources.pip {
+            video_input = pip.video_input(&video_input);
+        }
+        let denoise = self.denoise.map(denoise::description).unwrap_or_default();
+        let sharpen = self.sharpen.map(sharpen::description).unwrap_or_default();
+        // 预览分支输出 RGBA 原始像素，适配 egui
+        format!(
+            r#"
+            {video_input} !
+            video/x-raw !
+            videoconvert !
+            videocrop name={crop} !
+            videoscale !
+            capsfilter name={zoom_caps} !
+            {denoise}
+            {sharpen}
+            {bars_video}
+            tee name={video_tee}
+
+            {video_tee}. ! queue name=q_prev !
+            videoscale !
+            video/x-raw,width={pw},height={ph} !
+            cairooverlay name={overlay} !
+            videoconvert !
+            video/x-raw,format=RGBA !
+            appsink name={sink} sync=false
+
+            {video_tee}. ! queue max-size-buffers=1 leaky=downstream !
+            fakesink name={still} sync=false async=false
+
+            {audio_input} !
+            audioconvert !
+            audioresample !
+            volume name={gain} !
+            {bars_audio}
+            tee name={audio_tee}
+
+            {audio_tee}. ! queue !
+            level name={meter} interval={interval} !
+            fakesink
+            "#,
+            video_input = video_input,
+            denoise = denoise,
+            sharpen = sharpen,
+            bars_video = bars::video_input(),
+            bars_audio = bars::audio_input(),
+            audio_input = audio_input,
+            crop = ZOOM_CROP,
+            zoom_caps = ZOOM_CAPS,
+            video_tee = VIDEO_TEE,
+            audio_tee = AUDIO_TEE,
+            gain = AUDIO_GAIN,
+            pw = self.preview.width,
+            ph = self.preview.height,
+            overlay = OVERLAY,
+            sink = PREVIEW_SINK,
+            still = STILL_SINK,
+            meter = AUDIO_METER,
+            interval = self.audio.meter_interval_ns,
+        )
+    }
+
+    /// 可能的错误: [glib::Error] (描述无法解析或缺少插件), 缺少某个命名元素.
+    pub fn build(
+        &self,
+    ) -> Result<(gst::Pipeline, PipelineHandles), Box<dyn std::error::Error + Send + Sync>> {
+        let pipeline = gst::parse::launch(&self.description())?
+            .dynamic_cast::<gst::Pipeline>()
+            .map_err(|_| "description is not a pipeline")?;
+
+        let by_name = |name: &str| {
+            pipeline
+                .by_name(name)
+                .ok_or_else(|| format!("pipeline has no element named '{}'", name))
+        };
+        let video_tee = by_name(VIDEO_TEE)?;
+        let bars = ColorBars::attach(&pipeline)?;
+        let balance =
+            ChannelBalance::attach(&bars.camera_pad().ok_or("bars selector has no camera pad")?);
+        let zoom = DigitalZoom::attach(by_name(ZOOM_CROP)?, by_name(ZOOM_CAPS)?)?;
+        let denoise = match self.denoise {
+            Some(filter) => Some(Denoiser::attach(&pipeline, filter)?),
+            None => None,
+        };
+        let sharpen = match self.sharpen {
+            Some(filter) => Some(Sharpener::attach(&pipeline, filter)?),
+            None => None,
+        };
+        let switcher = match &self.sources.switch {
+            Some(switch) => Some(SourceSwitcher::attach(&pipeline, switch)?),
+            None => None,
+        };
+        let pip = match &self.sources.pip {
+            Some(config) => PipCompositor::attach(&pipeline, config)?,
+            None => None,
+        };
+        let handles = PipelineHandles {
+            video_tee,
+            audio_tee: by_name(AUDIO_TEE)?,
+            audio_gain: by_name(AUDIO_GAIN)?,
+            appsink: by_name(PREVIEW_SINK)?
+                .dynamic_cast::<gst_app::AppSink>()
+                .map_err(|_| "preview sink is not an appsink")?,
+            overlay: by_name(OVERLAY)?,
+            balance,
+            bars,
+            zoom,
+            denoise,
+            sharpen,
+            switcher,
+            pip,
+        };
+        Ok((pipeline, handles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn description_uses_sources_and_settings() {
+        let desc = PipelineBuilder::new(CaptureSources {
+            video: "v4l2src name=src device=/dev/video2".to_string(),
+            audio: "alsasrc device=hw:1".to_string(),
+            screen_portal: None,
+            switch: None,
+            pip: None,
+        })
+        .preview(PreviewSettings {
+            width: 640,
+            height: 360,
+        })
+        .audio(AudioSettings {
+            meter_interval_ns: 100_000_000,
+        })
+        .description();
+
+        assert!(desc.contains("v4l2src name=src device=/dev/video2 !"));
+        assert!(desc.contains("alsasrc device=hw:1 !"));
+        assert!(desc.contains("video/x-raw,width=640,height=360"));
+        assert!(desc.contains("interval=100000000"));
+    }
+
+    #[test]
+    fn description_names_every_handle() {
+        let desc = PipelineBuilder::default().description();
+        for name in [
+            VIDEO_TEE,
+            AUDIO_TEE,
+            PREVIEW_SINK,
+            OVERLAY,
+            AUDIO_METER,
+            AUDIO_GAIN,
+            ZOOM_CROP,
+            ZOOM_CAPS,
+        ] {
+            assert!(desc.contains(&format!("name={}", name)), "missing {}", name);
+        }
+        // 默认采集源必须带上 `src` 名称
+        assert!(desc.contains(&format!("name={}", SOURCE)));
+        assert!(!desc.contains("output-selector"));
+        // 彩条总在两个 tee 之前
+        let bars = desc.find("input-selector name=bars_vsel").unwrap();
+        assert!(bars < desc.find(&format!("tee name={}", VIDEO_TEE)).unwrap());
+        assert!(desc.contains("audiotestsrc name=bars_tone"));
+    }
+
+    #[test]
+    fn denoise_and_sharpen_sit_between_zoom_and_tee() {
+        let desc = PipelineBuilder::default()
+            .denoise(Some(DenoiseFilter::Median))
+            .description();
+        let zoom = desc.find(&format!("name={}", ZOOM_CAPS)).unwrap();
+        let filter = desc.find("videomedian name=denoise").unwrap();
+        let tee = desc.find(&format!("tee name={}", VIDEO_TEE)).unwrap();
+        assert!(zoom < filter && filter < tee);
+
+        let desc = PipelineBuilder::default()
+            .denoise(Some(DenoiseFilter::Median))
+            .sharpen(Some(SharpenFilter::GaussianBlur))
+            .description();
+        let denoise = desc.find("name=denoise").unwrap();
+        let sharpen = desc.find("gaussianblur name=sharpen").unwrap();
+        assert!(denoise < sharpen && sharpen < desc.find("tee name=").unwrap());
+    }
+
+    #[test]
+    #[ignore = "needs GStreamer base/good plugins"]
+    fn build_links_expected_graph() {
+        gst::init().unwrap();
+        let (pipeline, handles) = PipelineBuilder::new(CaptureSources {
+            video: format!("videotestsrc name={} is-live=true", SOURCE),
+            audio: "audiotestsrc is-live=true".to_string(),
+            screen_portal: None,
+            switch: None,
+            pip: None,
+        })
+        .build()
+        .unwrap();
+
+        assert!(pipeline.by_name(SOURCE).is_some());
+        assert!(pipeline.by_name(AUDIO_METER).is_some());
+        // tee 只有通往预览/电平的一个分支, 录制分支按需申请
+        assert_eq!(handles.video_tee.src_pads().len(), 1);
+        assert_eq!(handles.audio_tee.src_pads().len(), 1);
+        assert_eq!(
+            handles.overlay.factory().unwrap().name().as_str(),
+            "cairooverlay"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Apple ProRes 编码, 只能封装为 MOV. 优先使用 avenc_prores_ks, 没有时退回 avenc_prores.
+//! 四种档次都是 4:2:2 10 bit, 码率是固定的, 只由分辨率和帧率决定.
+
+use gstreamer as gst;
+use serde::{Deserialize, Serialize};
+
+use super::record::Resolution;
+
+/// 编码器需要的输入格式.
+pub(crate) const PIXEL_FORMAT: &str = "I422_10LE";
+/// 估算码率时假定的帧率, Apple 公布的数字按 29.97 fps 计算.
+pub(crate) const ASSUMED_FPS: f64 = 29.97;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum ProResProfile {
+    Proxy,
+    Lt,
+    /// ProRes 422.
+    Standard,
+    #[default]
+    Hq,
+}
+
+impl ProResProfile {
+    pub const ALL: [ProResProfile; 4] = [
+        ProResProfile::Proxy,
+        ProResProfile::Lt,
+        ProResProfile::Standard,
+        ProResProfile::Hq,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ProResProfile::Proxy => "Proxy",
+            ProResProfile::Lt => "LT",
+            ProResProfile::Standard => "422",
+            ProResProfile::Hq => "HQ",
+        }
+    }
+
+    /// 编码器 `profile` 属性的取值.
+    fn nick(self) -> &'static str {
+        match self {
+            ProResProfile::Proxy => "proxy",
+            ProResProfile::Lt => "lt",
+            ProResProfile::Standard => "standard",
+            ProResProfile::Hq => "hq",
+        }
+    }
+
+    /// 按 Apple 的 1080p 29.97 fps 码率和像素数估算 (kbit/s).
+    pub fn kbps(self, res: Resolution, fps: f64) -> u32 {
+        let base = match self {
+            ProResProfile::Proxy => 45_000.0,
+            ProResProfile::Lt => 102_000.0,
+            ProResProfile::Standard => 147_000.0,
+            ProResProfile::Hq => 220_000.0,
+        };
+        let pixels = res.width as f64 * res.height as f64 / (1920.0 * 1080.0);
+        (base * pixels * fps / 29.97).round() as u32
+    }
+
+    /// gst-launch 语法的编码器描述, 两个编码器都没有时返回 None.
+    pub fn description(self) -> Option<String> {
+        ["avenc_prores_ks", "avenc_prores"]
+            .into_iter()
+            .find(|e| gst::ElementFactory::find(e).is_some())
+            .map(|e| format!("{} profile={}", e, self.nick()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitrate_scales_with_pixels_and_frame_rate() {
+        let hd = Resolution {
+            width: 1920,
+            height: 1080,
+        };
+        assert_eq!(ProResProfile::Hq.kbps(hd, 29.97), 220_000);
+        // Apple 公布的 HQ 1080p25 约为 184 Mbit/s
+        let kbps = ProResProfile::Hq.kbps(hd, 25.0);
+        assert!((180_000..190_000).contains(&kbps), "{}", kbps);
+        let uhd = Resolution {
+            width: 3840,
+            height: 2160,
+        };
+        assert_eq!(ProResProfile::Proxy.kbps(uhd, 29.97), 180_000);
+    }
+}
@@ -1,27 +1,455 @@
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, Timelike};
 
 use gstreamer as gst;
 use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use super::av1::Av1Encoder;
+use super::balance::ChannelGains;
+use super::bus::{BufferStats, FrameStats};
+use super::denoise::DenoiseSettings;
+use super::ffv1;
+use super::libcamera::LibcameraControl;
+use super::overlay::{self, SharedOverlay};
+use super::pattern::TestPattern;
+use super::pip::PipLayout;
+use super::prores::{self, PIXEL_FORMAT, ProResProfile};
+use super::sharpen::{self, SharpenFilter, SharpenSettings};
+use super::silence::{Silence, SilenceSettings};
+use super::snapshot::SnapshotSettings;
+use super::switcher::SourceCommand;
+use super::timecode::{self, FrameRate, SharedTimecode, Timecode, TimecodeSource};
+use super::write_speed::{WriteChange, WriteCounters, WriteMonitor, WriteRate, WriteUnderrun};
+use crate::file::naming::Slate;
+use crate::file::preflight::{BlockReason, Suggestion};
+use crate::file::sidecar::{SCHEMA_VERSION, Sidecar, SidecarAudio, SidecarSettings};
+
+/// 同时进行的各个录像的编号, 事件据此对应到录像. 界面目前只使用 [RecordingId::MAIN].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecordingId(pub u32);
+
+impl RecordingId {
+    pub const MAIN: RecordingId = RecordingId(0);
+}
 
 #[derive(Debug, Clone)]
 pub enum RecordCommand {
-    Start(RecordSettings),
-    Stop,
+    /// 开始一个新的录像. 同一编号已经在录制时忽略.
+    Start {
+        id: RecordingId,
+        settings: RecordSettings,
+    },
+    Stop {
+        id: RecordingId,
+    },
+    /// 设置采集源的 V4L2 控件, 例如 "c,gain=42".
+    SourceControls(String),
+    /// 设置 libcamerasrc 的控件属性.
+    Libcamera(LibcameraControl),
+    /// 切换测试源的图案.
+    TestPattern(TestPattern),
+    /// 在多路采集源之间切换, 不中断录制.
+    Source(SourceCommand),
+    /// 打开或关闭多画面的缩略图分支.
+    Multiview(bool),
+    /// 画中画的位置, 大小和主次. 只在界面上叠加时 gst 线程忽略.
+    PipLayout(PipLayout),
+    /// 没有白平衡控件时在管线中使用的红蓝增益.
+    ChannelGains(ChannelGains),
+    /// 数字变焦倍数, 预览和录制一起裁剪.
+    Zoom(f32),
+    /// 打开, 关闭降噪或者修改强度, 不需要重建管线.
+    Denoise(DenoiseSettings),
+    /// 锐化的设置. tee 之前的锐化立即开关, 只锐化录像时从下一条开始.
+    Sharpen(SharpenSettings),
+    /// 切换到彩条和参考音, 带上画面上的机位名 (为空时用设备名). None 切回摄像头.
+    /// 录制中不能切换, 彩条期间不能开始录制.
+    Bars(Option<String>),
+    /// 录制中的无声检测开关和阈值.
+    Silence(SilenceSettings),
+    /// 输入音量倍数, 见 [super::pipeline::MAX_AUDIO_GAIN].
+    AudioGain(f64),
+    /// 把当前画面保存为静帧.
+    Snapshot {
+        path: PathBuf,
+        settings: SnapshotSettings,
+    },
+    /// 从下一帧开始连拍, 各帧的文件名见 [super::snapshot::burst_path].
+    Burst {
+        path: PathBuf,
+        settings: SnapshotSettings,
+    },
+    /// 把管线 (包括正在录制的分支) 的拓扑图写到这个目录, 见 [super::graph].
+    DumpGraph {
+        dir: PathBuf,
+    },
+    /// 停止录制并等待封装完成, 停止管线后通知并退出 gst 线程. 用于退出和崩溃时的收尾.
+    Shutdown(std::sync::mpsc::Sender<()>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// 录制期间报告文件大小的间隔.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// 封装和文件之间的写入队列. 存储跟不上时队列堆积, 满了以后编码器前的队列开始丢帧.
+const WRITE_BUFFER_BYTES: u32 = 64 * 1024 * 1024;
+/// 写入队列占用超过这个比例时提示一次.
+const STORAGE_SLOW: f64 = 0.5;
+/// 超过这个比例时停止录制, 以免继续丢帧.
+const STORAGE_FULL: f64 = 0.9;
+/// 录制分支中 videorate 的名字.
+const RATE: &str = "rate_v";
+/// 采集源是可变帧率又要求恒定帧率时使用的帧率.
+const FALLBACK_RATE: FrameRate = FrameRate { num: 30, den: 1 };
+
+/// gst 线程反馈给 UI 的录制事件.
+#[derive(Debug, Clone)]
+pub enum RecordEvent {
+    /// 预览收到第一帧, 说明管线已经在正常运行.
+    PreviewStarted,
+    Started {
+        id: RecordingId,
+        path: PathBuf,
+        /// 写入文件的时间码, 采集源没有固定帧率时一直是 None.
+        timecode: SharedTimecode,
+    },
+    /// 输出路径有可以预见的问题, 没有开始录制. 带回原来的设置, 界面修复后用它重新开始.
+    StartBlocked {
+        id: RecordingId,
+        settings: Box<RecordSettings>,
+        reason: BlockReason,
+        suggestion: Suggestion,
+    },
+    /// 录制期间每秒一次.
+    Progress {
+        id: RecordingId,
+        progress: RecordProgress,
+    },
+    /// 文件已完成封装并关闭, 可以安全读取.
+    Stopped {
+        id: RecordingId,
+        path: PathBuf,
+        duration: Duration,
+        size: u64,
+        frames: FrameStats,
+    },
+    /// 后台校验完成, 结果同时写入 sidecar.
+    Verified {
+        path: PathBuf,
+        result: Result<(), String>,
+    },
+    /// 门户授权了屏幕共享, 报告录制的范围.
+    ScreenShared(super::screen::CaptureArea),
+    /// 切换到了第几路采集源, 从 0 开始.
+    SourceSwitched(usize),
+    /// 彩条打开或关闭了.
+    Bars(bool),
+    /// 录制中无声持续了设置的时长 (true), 或者声音恢复了.
+    Silence(bool),
+    /// 存储的写入速度跟不上, 或者又跟上了.
+    WriteSpeed {
+        id: RecordingId,
+        change: WriteChange,
+        rate: WriteRate,
+    },
+    /// 网络摄像头每秒一次.
+    Network(super::rtsp::NetworkStats),
+    /// 静帧已经写入.
+    Snapshot(PathBuf),
+    /// 连拍的各帧都已写入.
+    Burst(Vec<PathBuf>),
+    /// 管线拓扑图已经写入.
+    GraphSaved(super::graph::GraphFiles),
+    /// 管线警告, 已经按元素限流, 直接提示给用户.
+    Warning(String),
+    Error(String),
+}
+
+impl RecordEvent {
+    /// 属于某个录像的事件返回它的编号.
+    pub fn recording(&self) -> Option<RecordingId> {
+        match self {
+            RecordEvent::Started { id, .. }
+            | RecordEvent::StartBlocked { id, .. }
+            | RecordEvent::Progress { id, .. }
+            | RecordEvent::WriteSpeed { id, .. }
+            | RecordEvent::Stopped { id, .. } => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+/// 正在写入的文件大小和最近一秒写入的速率.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RecordProgress {
+    pub bytes: u64,
+    pub bytes_per_sec: u64,
+    /// 写入队列两端的速度, 调试叠加层显示.
+    pub write: WriteRate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub(crate) enum VideoEncoder {
     H264,
     H265,
+    /// 编码器见 [super::av1].
+    Av1,
+    Vp9,
+    /// 只能封装为 MOV, 码率由档次决定, 忽略设置的码率.
+    ProRes {
+        profile: ProResProfile,
+    },
+    /// 无损, 只能封装为 MKV, 音频用 FLAC.
+    Ffv1,
+    /// 每一帧都是独立的 JPEG, 封装为 MKV 或 AVI. CPU 占用很低, 文件很大.
+    Mjpeg,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl std::fmt::Display for VideoEncoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VideoEncoder::ProRes { profile } => write!(f, "ProRes {}", profile.label()),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// 估算音频的码率 (kbit/s).
+const AUDIO_KBPS: u32 = 128;
+
+impl VideoEncoder {
+    /// 界面中的顺序. ProRes 使用默认的档次.
+    pub const ALL: [VideoEncoder; 7] = [
+        VideoEncoder::H264,
+        VideoEncoder::H265,
+        VideoEncoder::Av1,
+        VideoEncoder::Vp9,
+        VideoEncoder::ProRes {
+            profile: ProResProfile::Hq,
+        },
+        VideoEncoder::Ffv1,
+        VideoEncoder::Mjpeg,
+    ];
+
+    /// 不使用设置的码率的编码器, 估算它们的视频码率 (kbit/s).
+    pub fn fixed_kbps(self, res: Resolution, mjpeg_quality: u8) -> Option<u32> {
+        match self {
+            VideoEncoder::ProRes { profile } => Some(profile.kbps(res, prores::ASSUMED_FPS)),
+            VideoEncoder::Ffv1 => Some(ffv1::estimated_kbps(res, ffv1::ASSUMED_FPS)),
+            VideoEncoder::Mjpeg => Some(mjpeg_kbps(res, mjpeg_quality)),
+            _ => None,
+        }
+    }
+
+    /// 估算录制的总码率 (kbit/s), 用于估计磁盘还能录多久.
+    pub fn estimated_kbps(self, res: Resolution, bitrate_kbps: u32, mjpeg_quality: u8) -> u32 {
+        self.fixed_kbps(res, mjpeg_quality).unwrap_or(bitrate_kbps) + AUDIO_KBPS
+    }
+}
+
+/// 按每像素的比特数粗略估算, 质量 85 的 1080p30 约 100 Mbit/s.
+fn mjpeg_kbps(res: Resolution, quality: u8) -> u32 {
+    let bits_per_pixel = quality.clamp(1, 100) as f64 / 100.0 * 2.0;
+    (res.width as f64 * res.height as f64 * bits_per_pixel * 30.0 / 1000.0).round() as u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
 pub(crate) enum Container {
     MP4,
     MOV,
+    MKV,
+    /// 音频为 Opus.
+    WebM,
+    /// 音频为 PCM.
+    AVI,
+}
+
+impl Container {
+    pub const ALL: [Container; 5] = [
+        Container::MOV,
+        Container::MP4,
+        Container::MKV,
+        Container::WebM,
+        Container::AVI,
+    ];
+
+    /// 录像文件的扩展名.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Container::MP4 => "mp4",
+            Container::MOV => "mov",
+            Container::MKV => "mkv",
+            Container::WebM => "webm",
+            Container::AVI => "avi",
+        }
+    }
+
+    /// 能否封装这种视频编码.
+    pub fn supports(self, enc: VideoEncoder) -> bool {
+        match enc {
+            VideoEncoder::H264 | VideoEncoder::H265 => {
+                matches!(self, Container::MP4 | Container::MOV | Container::MKV)
+            }
+            VideoEncoder::Av1 | VideoEncoder::Vp9 => {
+                matches!(self, Container::MP4 | Container::MKV | Container::WebM)
+            }
+            VideoEncoder::ProRes { .. } => self == Container::MOV,
+            VideoEncoder::Ffv1 => self == Container::MKV,
+            VideoEncoder::Mjpeg => matches!(self, Container::MKV | Container::AVI),
+        }
+    }
+}
+
+/// 录制的位深偏好.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum BitDepth {
+    /// 采集源是 10 bit 且编码器支持时保留 10 bit.
+    #[default]
+    Auto,
+    Eight,
+    Ten,
 }
 
+impl VideoEncoder {
+    /// 能编码 10 bit 的编码器. ProRes 总是 10 bit, FFV1 保留采集源的格式.
+    pub fn supports_ten_bit(self) -> bool {
+        !matches!(self, VideoEncoder::H264 | VideoEncoder::Mjpeg)
+    }
+}
+
+/// 录像的帧率. 有些摄像头输出可变帧率, 录像与单独录制的声音会逐渐错开.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum FrameRateMode {
+    /// 用 videorate 补帧或丢帧, 恒定为采集源协商的帧率.
+    #[default]
+    Source,
+    /// 恒定为给定的每秒帧数.
+    Fixed(u32),
+    /// 保留摄像头的原始时间戳.
+    Variable,
+}
+
+impl FrameRateMode {
+    pub const FIXED: [u32; 5] = [24, 25, 30, 50, 60];
+}
+
+/// 录像要恒定在的帧率, 保留可变帧率时返回 None.
+/// 采集源没有固定帧率时按 [FALLBACK_RATE] 录制.
+pub(crate) fn target_rate(mode: FrameRateMode, source: Option<FrameRate>) -> Option<FrameRate> {
+    match mode {
+        FrameRateMode::Source => Some(source.unwrap_or(FALLBACK_RATE)),
+        FrameRateMode::Fixed(fps) => Some(FrameRate {
+            num: fps.max(1),
+            den: 1,
+        }),
+        FrameRateMode::Variable => None,
+    }
+}
+
+/// 按偏好和采集源的位深决定是否录制 10 bit, 编码器不支持时返回错误.
+pub(crate) fn resolve_ten_bit(
+    pref: BitDepth,
+    source_ten_bit: bool,
+    enc: VideoEncoder,
+) -> Result<bool, String> {
+    match pref {
+        BitDepth::Auto => Ok(source_ten_bit && enc.supports_ten_bit()),
+        BitDepth::Eight => Ok(false),
+        BitDepth::Ten if enc.supports_ten_bit() => Ok(true),
+        BitDepth::Ten => Err(format!(
+            "{} can't record 10-bit video, use H265, Av1 or Vp9",
+            enc
+        )),
+    }
+}
+
+/// 视频 tee 上协商好的格式.
+fn source_info(video_tee: &gst::Element) -> Option<gst_video::VideoInfo> {
+    video_tee
+        .static_pad("sink")
+        .and_then(|pad| pad.current_caps())
+        .and_then(|caps| gst_video::VideoInfo::from_caps(&caps).ok())
+}
+
+/// 格式是否超过 8 bit, 例如 P010 或 v210.
+fn is_ten_bit(info: &gst_video::VideoInfo) -> bool {
+    info.format_info().depth()[0] > 8
+}
+
+/// 固定帧率的采集源的帧率, 可变帧率时返回 None.
+fn frame_rate(info: &gst_video::VideoInfo) -> Option<FrameRate> {
+    let fps = info.fps();
+    (fps.numer() > 0 && fps.denom() > 0).then(|| FrameRate {
+        num: fps.numer() as u32,
+        den: fps.denom() as u32,
+    })
+}
+
+/// 按设置的来源算出第一帧的时间码.
+fn start_timecode(source: TimecodeSource, rate: FrameRate, now: DateTime<Local>) -> Timecode {
+    match source {
+        TimecodeSource::TimeOfDay => {
+            let seconds = now.num_seconds_from_midnight() as f64 + now.nanosecond() as f64 / 1e9;
+            Timecode::from_time_of_day(seconds, rate)
+        }
+        TimecodeSource::Zero => Timecode::from_frame_count(0, rate),
+    }
+}
+
+/// 不支持的组合返回说明可用容器的错误信息.
+pub(crate) fn check_pairing(enc: VideoEncoder, container: Container) -> Result<(), String> {
+    if container.supports(enc) {
+        return Ok(());
+    }
+    let allowed: Vec<String> = Container::ALL
+        .into_iter()
+        .filter(|c| c.supports(enc))
+        .map(|c| format!("{:?}", c))
+        .collect();
+    Err(format!(
+        "{} can't be recorded to {:?}, use {}",
+        enc,
+        container,
+        allowed.join(", ")
+    ))
+}
+
+/// 已知有常见播放器打不开的编码和容器组合, 录制前在设置中提示.
 #[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PlaybackIssue {
+    /// QuickTime 和 iOS 不支持 Matroska.
+    MatroskaOnApple,
+    /// Windows 要另装 HEVC 扩展, 旧手机和多数浏览器播放不了 H.265.
+    Hevc,
+    /// 较新的浏览器和 VLC 可以播放, QuickTime 和旧设备不行.
+    Av1,
+    /// 浏览器和 VLC 可以播放, QuickTime 和剪辑软件大多不行.
+    Vp9,
+}
+
+pub(crate) fn playback_issues(enc: VideoEncoder, container: Container) -> Vec<PlaybackIssue> {
+    let mut issues = Vec::new();
+    if container == Container::MKV {
+        issues.push(PlaybackIssue::MatroskaOnApple);
+    }
+    match enc {
+        VideoEncoder::H264 => {}
+        VideoEncoder::H265 => issues.push(PlaybackIssue::Hevc),
+        VideoEncoder::Av1 => issues.push(PlaybackIssue::Av1),
+        VideoEncoder::Vp9 => issues.push(PlaybackIssue::Vp9),
+        VideoEncoder::ProRes { .. } | VideoEncoder::Ffv1 | VideoEncoder::Mjpeg => {}
+    }
+    issues
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Resolution {
     pub width: u32,
     pub height: u32,
@@ -32,63 +460,529 @@ pub(crate) struct RecordSettings {
     pub res: Resolution,
     pub enc: VideoEncoder,
     pub container: Container,
+    /// 视频码率 (kbit/s).
+    pub bitrate_kbps: u32,
+    /// AV1 的速度预设, 见 [super::av1::SPEEDS].
+    pub av1_speed: u8,
+    /// FFV1 的切片数, 见 [ffv1::SLICES].
+    pub ffv1_slices: u8,
+    /// Motion JPEG 的质量, 1 到 100.
+    pub mjpeg_quality: u8,
+    pub bit_depth: BitDepth,
+    pub timecode: TimecodeSource,
+    pub frame_rate: FrameRateMode,
     pub filepath: PathBuf,
+    pub tags: ClipTags,
+    pub slate: Slate,
+    /// 开始录制时使用的预设名称.
+    pub profile: Option<String>,
+}
+
+/// 写入容器的元数据标签. 创建时间和软件版本会自动添加.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ClipTags {
+    /// 操作员姓名, 写入 artist.
+    pub artist: String,
+    /// 摄像机型号, 为空时使用采集源的设备名.
+    pub device_model: String,
+    /// (纬度, 经度), 有 GPS 定位时填写.
+    pub location: Option<(f64, f64)>,
+}
+
+impl From<&RecordSettings> for SidecarSettings {
+    fn from(settings: &RecordSettings) -> Self {
+        Self {
+            encoder: settings.enc.to_string(),
+            container: format!("{:?}", settings.container),
+            width: settings.res.width,
+            height: settings.res.height,
+            bitrate_kbps: settings.bitrate_kbps,
+            denoise: None,
+            sharpen: None,
+        }
+    }
 }
 
 /// 内部结构, 用于记住当前正在录制的组件, 以便后续释放.
 pub(super) struct ActiveRecording {
+    id: RecordingId,
     bin: gst::Element,
     video_tee_pad: gst::Pad,
     audio_tee_pad: gst::Pad,
+    settings: RecordSettings,
+    device: String,
+    started_at: Instant,
+    started_wall: DateTime<Local>,
+    /// 从开始到封装完成的整个过程.
+    span: tracing::Span,
+    frames: Arc<FrameCounters>,
+    /// 上一次报告进度的时间和当时的文件大小.
+    last_progress: (Instant, u64),
+    write_queue: gst::Element,
+    /// 已经提示过存储跟不上.
+    storage_warned: bool,
+    write_counters: Arc<WriteCounters>,
+    write_monitor: WriteMonitor,
+    /// 上次报告进度时发现的变化, 由 [ActiveRecording::check_write_speed] 取走.
+    write_change: Option<(WriteChange, WriteRate)>,
+    /// 录制中写入跟不上的时间段.
+    write_underruns: Vec<WriteUnderrun>,
+    timecode: SharedTimecode,
+    processing: Processing,
+    /// 录制中检测到的无声片段.
+    silence: Vec<Silence>,
+}
+
+/// 开始录制时的画面处理, 写入 sidecar.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Processing {
+    /// 生效的降噪, 见 [super::denoise::DenoiseState::On].
+    pub denoise: Option<String>,
+    /// 打开时才有. 只锐化录像时加在这条录像的分支上.
+    pub sharpen: Option<(SharpenFilter, SharpenSettings)>,
+}
+
+/// [ActiveRecording::check_storage] 的结果.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum StorageCheck {
+    /// 写入队列在堆积, 只提示一次.
+    Slow,
+    /// 写入队列快满了, 应当停止录制.
+    Full,
+}
+
+impl ActiveRecording {
+    pub fn path(&self) -> &PathBuf {
+        &self.settings.filepath
+    }
+
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frames.stats()
+    }
+
+    pub fn timecode(&self) -> SharedTimecode {
+        self.timecode.clone()
+    }
+
+    /// 开始无声的时间早于录像开始时从 0 算起.
+    pub fn silence_started(&mut self, since: Instant) {
+        let offset = since.saturating_duration_since(self.started_at);
+        self.silence.push(Silence {
+            at: self.started_wall + chrono::TimeDelta::from_std(offset).unwrap_or_default(),
+            start_secs: offset.as_secs_f64(),
+            end_secs: None,
+        });
+    }
+
+    pub fn silence_ended(&mut self, now: Instant) {
+        if let Some(last) = self.silence.last_mut()
+            && last.end_secs.is_none()
+        {
+            last.end_secs = Some(now.saturating_duration_since(self.started_at).as_secs_f64());
+        }
+    }
+
+    /// 录制中修改锐化的强度和半径时跟着改. 开关和目标不同时这条录像保持原样.
+    pub fn update_sharpen(&mut self, settings: SharpenSettings) {
+        let Some((filter, current)) = &mut self.processing.sharpen else {
+            return;
+        };
+        if !settings.enabled || settings.target != current.target {
+            return;
+        }
+        if settings.recording_only()
+            && let Some(el) = self
+                .bin
+                .downcast_ref::<gst::Bin>()
+                .and_then(|bin| bin.by_name(sharpen::RECORD_FILTER))
+        {
+            filter.apply(&el, &settings);
+        }
+        *current = settings;
+    }
+
+    /// 距上次报告满一秒时查看文件大小, 否则返回 None.
+    pub fn progress(&mut self, now: Instant) -> Option<RecordProgress> {
+        let (at, before) = self.last_progress;
+        let elapsed = now.duration_since(at);
+        if elapsed < PROGRESS_INTERVAL {
+            return None;
+        }
+        let bytes = std::fs::metadata(self.path()).map_or(before, |m| m.len());
+        self.last_progress = (now, bytes);
+        let backlog = self.write_queue.property::<u32>("current-level-bytes") as u64;
+        let (write, change) = self
+            .write_monitor
+            .update(now, self.write_counters.totals(), backlog);
+        self.track_underrun(now, write, change);
+        self.write_change = change.map(|c| (c, write));
+        Some(RecordProgress {
+            bytes,
+            bytes_per_sec: (bytes.saturating_sub(before) as f64 / elapsed.as_secs_f64()) as u64,
+            write,
+        })
+    }
+
+    /// 写入速度跟不上或者恢复时返回, 每次报告进度后调用.
+    pub fn check_write_speed(&mut self) -> Option<(WriteChange, WriteRate)> {
+        self.write_change.take()
+    }
+
+    /// 记下写入跟不上的时间段和其中最低的写入速度.
+    fn track_underrun(&mut self, now: Instant, rate: WriteRate, change: Option<WriteChange>) {
+        match change {
+            Some(WriteChange::Underrun { since }) => {
+                let offset = since.saturating_duration_since(self.started_at);
+                self.write_underruns.push(WriteUnderrun {
+                    at: self.started_wall + chrono::TimeDelta::from_std(offset).unwrap_or_default(),
+                    start_secs: offset.as_secs_f64(),
+                    end_secs: None,
+                    written_bytes_per_sec: rate.written_per_sec,
+                    produced_bytes_per_sec: rate.produced_per_sec,
+                });
+            }
+            Some(WriteChange::Recovered) => {
+                if let Some(last) = self.write_underruns.last_mut() {
+                    last.end_secs =
+                        Some(now.saturating_duration_since(self.started_at).as_secs_f64());
+                }
+            }
+            Some(WriteChange::Persistent) | None => {
+                if let Some(last) = self.write_underruns.last_mut()
+                    && last.end_secs.is_none()
+                    && rate.written_per_sec < last.written_bytes_per_sec
+                {
+                    last.written_bytes_per_sec = rate.written_per_sec;
+                    last.produced_bytes_per_sec = rate.produced_per_sec;
+                }
+            }
+        }
+    }
+
+    /// 按写入队列的占用判断存储能否跟上编码的速度, 每次报告进度后调用.
+    pub fn check_storage(&mut self) -> Option<StorageCheck> {
+        let level = self.write_queue.property::<u32>("current-level-bytes");
+        let fill = level as f64 / WRITE_BUFFER_BYTES as f64;
+        if fill >= STORAGE_FULL {
+            Some(StorageCheck::Full)
+        } else if fill >= STORAGE_SLOW && !self.storage_warned {
+            self.storage_warned = true;
+            Some(StorageCheck::Slow)
+        } else {
+            None
+        }
+    }
+}
+
+/// 录制分支的帧计数, 由 pad probe 在流线程中更新.
+pub(super) struct FrameCounters {
+    /// 进入录制队列.
+    entered: AtomicU64,
+    /// 离开录制队列.
+    dequeued: AtomicU64,
+    encoded: AtomicU64,
+    queue: gst::Element,
+    /// 恒定帧率时的 videorate, 自己记录补帧和丢帧的数量.
+    rate: Option<gst::Element>,
+}
+
+impl FrameCounters {
+    /// 在录制队列的两端和编码器输出端安装计数 probe.
+    fn attach(bin: &gst::Bin) -> Result<Arc<Self>, gst::glib::BoolError> {
+        let missing = |name| gst::glib::bool_error!("recording bin has no {}", name);
+        let queue = bin.by_name("q_v").ok_or_else(|| missing("q_v"))?;
+        let encoder = bin.by_name("enc_v").ok_or_else(|| missing("enc_v"))?;
+        let counters = Arc::new(Self {
+            entered: AtomicU64::new(0),
+            dequeued: AtomicU64::new(0),
+            encoded: AtomicU64::new(0),
+            queue: queue.clone(),
+            rate: bin.by_name(RATE),
+        });
+        counters.count(queue.static_pad("sink"), |c| &c.entered)?;
+        counters.count(queue.static_pad("src"), |c| &c.dequeued)?;
+        counters.count(encoder.static_pad("src"), |c| &c.encoded)?;
+        Ok(counters)
+    }
+
+    fn count(
+        self: &Arc<Self>,
+        pad: Option<gst::Pad>,
+        counter: fn(&Self) -> &AtomicU64,
+    ) -> Result<(), gst::glib::BoolError> {
+        let pad = pad.ok_or_else(|| gst::glib::bool_error!("recording bin is missing a pad"))?;
+        let counters = self.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+            counter(&counters).fetch_add(1, Ordering::Relaxed);
+            gst::PadProbeReturn::Ok
+        });
+        Ok(())
+    }
+
+    /// 丢弃的帧 = 进入队列 - 离开队列 - 仍在队列中. 封装完成后队列为空, 结果准确.
+    fn stats(&self) -> FrameStats {
+        let entered = self.entered.load(Ordering::Relaxed);
+        let dequeued = self.dequeued.load(Ordering::Relaxed);
+        let queued = self.queue.property::<u32>("current-level-buffers") as u64;
+        let rate = |name| self.rate.as_ref().map_or(0, |r| r.property::<u64>(name));
+        FrameStats {
+            entered,
+            encoded: self.encoded.load(Ordering::Relaxed),
+            dropped: entered.saturating_sub(dequeued).saturating_sub(queued),
+            repeated: rate("duplicate"),
+            skipped: rate("drop"),
+        }
+    }
+}
+
+/// 编码器用到的设置, 录制和 [super::benchmark] 共用.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct EncoderOptions {
+    pub bitrate_kbps: u32,
+    pub av1_speed: u8,
+    pub ffv1_slices: u8,
+    pub mjpeg_quality: u8,
+}
+
+impl RecordSettings {
+    fn encoder_options(&self) -> EncoderOptions {
+        EncoderOptions {
+            bitrate_kbps: self.bitrate_kbps,
+            av1_speed: self.av1_speed,
+            ffv1_slices: self.ffv1_slices,
+            mjpeg_quality: self.mjpeg_quality,
+        }
+    }
+}
+
+/// gst-launch 语法的编码器描述, 编码器没有安装时返回原因.
+pub(super) fn encoder_description(
+    enc: VideoEncoder,
+    options: EncoderOptions,
+) -> Result<String, &'static str> {
+    Ok(match enc {
+        VideoEncoder::H264 => format!("x264enc tune=zerolatency bitrate={}", options.bitrate_kbps),
+        VideoEncoder::H265 => format!("x265enc tune=zerolatency bitrate={}", options.bitrate_kbps),
+        VideoEncoder::Av1 => Av1Encoder::detect()
+            .ok_or("no AV1 encoder installed (svtav1enc, rav1enc or av1enc)")?
+            .description(options.av1_speed, options.bitrate_kbps),
+        // deadline=1 即 realtime, 码率单位是 bit/s
+        VideoEncoder::Vp9 => format!(
+            "vp9enc deadline=1 cpu-used=8 row-mt=true end-usage=cbr target-bitrate={}",
+            options.bitrate_kbps as u64 * 1000
+        ),
+        VideoEncoder::ProRes { profile } => profile
+            .description()
+            .ok_or("no ProRes encoder installed (avenc_prores_ks or avenc_prores)")?,
+        VideoEncoder::Ffv1 => ffv1::description(options.ffv1_slices),
+        VideoEncoder::Mjpeg => format!("jpegenc quality={}", options.mjpeg_quality.clamp(1, 100)),
+    })
+}
+
+/// 编码器输入的像素格式, None 表示保留采集源的格式.
+/// NOTE: format=I420 修复 QuickTime Player 打不开 MP4 的问题
+pub(super) fn input_format(enc: VideoEncoder, ten_bit: bool) -> Option<&'static str> {
+    match enc {
+        VideoEncoder::ProRes { .. } => Some(PIXEL_FORMAT),
+        // FFV1 保留采集源的像素格式, 不做有损的色度下采样
+        VideoEncoder::Ffv1 => None,
+        _ if ten_bit => Some("I420_10LE"),
+        _ => Some("I420"),
+    }
+}
+
+/// 采集源的设备名, 没有 `device` 属性的源 (如 videotestsrc) 使用插件名.
+pub(super) fn source_device(pipeline: &gst::Pipeline) -> String {
+    let Some(src) = pipeline.by_name(super::pipeline::SOURCE) else {
+        return String::new();
+    };
+    if src.has_property("device")
+        && let Ok(device) = src.property_value("device").get::<String>()
+    {
+        return device;
+    }
+    src.factory()
+        .map(|f| f.name().to_string())
+        .unwrap_or_default()
+}
+
+fn apply_tags(setter: &gst::TagSetter, tags: &ClipTags, device: &str) {
+    let mode = gst::TagMergeMode::Replace;
+    if let Some(now) = gst::DateTime::new_now_local_time() {
+        setter.add_tag::<gst::tags::DateTime>(&now, mode);
+    }
+    let software = format!("cam-ui {}", env!("CARGO_PKG_VERSION"));
+    setter.add_tag::<gst::tags::ApplicationName>(&software.as_str(), mode);
+    setter.add_tag::<gst::tags::Encoder>(&software.as_str(), mode);
+    if !tags.artist.is_empty() {
+        setter.add_tag::<gst::tags::Artist>(&tags.artist.as_str(), mode);
+    }
+    let model = if tags.device_model.is_empty() {
+        device
+    } else {
+        tags.device_model.as_str()
+    };
+    if !model.is_empty() {
+        setter.add_tag::<gst::tags::DeviceModel>(&model, mode);
+    }
+    if let Some((lat, lon)) = tags.location {
+        setter.add_tag::<gst::tags::GeoLocationLatitude>(&lat, mode);
+        setter.add_tag::<gst::tags::GeoLocationLongitude>(&lon, mode);
+    }
 }
 
 /// 可能的错误: [BoolError], [PadLinkError].
+/// 每个录像有自己的 bin 和 tee 的请求 pad, 可以同时录制多个, 互不影响.
+/// 给出 `burn_in` 时把电平表画进录像.
 pub(super) fn start_recording(
     pipeline: &gst::Pipeline,
     video_tee: &gst::Element,
     audio_tee: &gst::Element,
+    id: RecordingId,
     settings: RecordSettings,
+    burn_in: Option<&SharedOverlay>,
+    processing: Processing,
 ) -> Result<ActiveRecording, Box<dyn std::error::Error + Send + Sync>> {
+    check_pairing(settings.enc, settings.container)?;
+    let source = source_info(video_tee);
+    let ten_bit = resolve_ten_bit(
+        settings.bit_depth,
+        source.as_ref().is_some_and(is_ten_bit),
+        settings.enc,
+    )?;
+    let source_rate = source.as_ref().and_then(frame_rate);
+    let constant = target_rate(settings.frame_rate, source_rate);
+    // timecodestamper 需要固定的帧率, 恒定帧率时按录像的帧率计数
+    let rate = constant.or(source_rate);
+    match (source_rate, constant) {
+        (None, Some(rate)) => warn!(
+            fps = rate.nominal(),
+            "source has no fixed frame rate, recording at a constant rate"
+        ),
+        (None, None) => warn!("source has no fixed frame rate, recording without a timecode track"),
+        _ => {}
+    }
     // 1. 根据配置映射插件名称
-    let enc_plugin = match settings.enc {
-        VideoEncoder::H264 => "x264enc tune=zerolatency",
-        VideoEncoder::H265 => "x265enc tune=zerolatency",
+    let enc_plugin = encoder_description(settings.enc, settings.encoder_options())?;
+    let pixel_format = input_format(settings.enc, ten_bit)
+        .map(|f| format!(",format={}", f))
+        .unwrap_or_default();
+    // x265 按下游的 profile 选择 main10, VP9 和 AV1 编码器按输入格式自动选择
+    let profile_caps = match settings.enc {
+        VideoEncoder::H265 if ten_bit => "video/x-h265,profile=main-10 !",
+        _ => "",
+    };
+    // 编码器输出 Annex B 字节流, 由解析器转换为封装需要的格式.
+    // H.265 必须是 hvc1 (参数集放在 sample entry 里), QuickTime 和 iOS 不认 hev1
+    // ProRes, FFV1 和 JPEG 每一帧都是关键帧, 直接交给封装
+    let parse_plugin = match settings.enc {
+        VideoEncoder::H264 => "h264parse ! video/x-h264,stream-format=avc,alignment=au !",
+        VideoEncoder::H265 => "h265parse ! video/x-h265,stream-format=hvc1,alignment=au !",
+        VideoEncoder::Av1 => "av1parse ! video/x-av1,stream-format=obu-stream,alignment=tu !",
+        VideoEncoder::Vp9 => "vp9parse !",
+        VideoEncoder::ProRes { .. } | VideoEncoder::Ffv1 | VideoEncoder::Mjpeg => "",
     };
     let mux_plugin = match settings.container {
         Container::MP4 => "mp4mux faststart=true", // 加上 faststart 提高兼容性
         Container::MOV => "qtmux",
+        Container::MKV => "matroskamux",
+        Container::WebM => "webmmux",
+        Container::AVI => "avimux",
+    };
+    let audio_plugin = match (settings.enc, settings.container) {
+        (VideoEncoder::Ffv1, _) => "flacenc ! flacparse",
+        (_, Container::WebM) => "opusenc ! opusparse",
+        (_, Container::AVI) => "audio/x-raw,format=S16LE",
+        _ => "fdkaacenc ! aacparse",
+    };
+    let path_str = settings.filepath.to_string_lossy().into_owned();
+    // cairooverlay 只接受 RGB 格式, 画完再转换为编码器的格式
+    let scaled = format!(
+        "video/x-raw,width={},height={}",
+        settings.res.width, settings.res.height
+    );
+    // videorate 按时间戳补帧或丢帧, 使每一帧的时长相同
+    let rate_v = match constant {
+        Some(rate) => format!(
+            "videorate name={} ! video/x-raw,framerate={}/{} !",
+            RATE, rate.num, rate.den
+        ),
+        None => String::new(),
+    };
+    // 只锐化录像时在缩放到录像的尺寸之后锐化
+    let sharpen_v = match processing.sharpen {
+        Some((filter, s)) if s.recording_only() => format!(
+            "{} ! {} !",
+            scaled,
+            filter.description(sharpen::RECORD_FILTER)
+        ),
+        _ => String::new(),
+    };
+    let burn_in_v = match burn_in {
+        Some(_) => format!(
+            "{} ! cairooverlay name={} ! videoconvert !",
+            scaled,
+            overlay::BURN_IN
+        ),
+        None => String::new(),
     };
-    let path_str = settings.filepath.to_string_lossy();
 
     // 2. 构造录制分支字符串 (Bin)
     // 流程：队列缓冲 -> 格式转换 -> 缩放尺寸 -> 编码 -> 封装 -> 写入文件
-    // NOTE: format=I420 修复 QuickTime Player 打不开 MP4 的问题
+    // 编码器跟不上时队列丢弃最旧的帧, 而不是阻塞 tee 拖慢预览
     let bin_desc = format!(
         "bin.(
-            queue name=q_v !
+            queue name=q_v leaky=downstream !
+            {rate_v}
             videoconvert !
             videoscale !
-            video/x-raw,width={w},height={h},format=I420 !
-            {enc_v} !
+            {sharpen_v}
+            {burn_in_v}
+            {scaled}{format} !
+            {stamper}
+            {enc_v} name=enc_v !
+            {profile_v}
+            {parse_v}
             mux.video_0
 
             queue name=q_a !
             audioconvert !
             audioresample !
-            fdkaacenc !
-            aacparse !
+            {enc_a} !
             mux.audio_0
 
             {mux} name=mux !
+            queue name=q_write max-size-buffers=0 max-size-time=0 max-size-bytes={write_buffer} !
             filesink location={path}
         )",
-        w = settings.res.width,
-        h = settings.res.height,
+        format = pixel_format,
+        stamper = rate.map(timecode::stamper_description).unwrap_or_default(),
         enc_v = enc_plugin,
+        profile_v = profile_caps,
+        parse_v = parse_plugin,
+        enc_a = audio_plugin,
         mux = mux_plugin,
+        write_buffer = WRITE_BUFFER_BYTES,
         path = path_str
     );
     let bin = gst::parse::bin_from_description(&bin_desc, false)?;
+
+    // 3. 写入容器元数据 (各个封装器都实现了 TagSetter)
+    let device = source_device(pipeline);
+    if let Some(setter) = bin
+        .by_name("mux")
+        .and_then(|m| m.dynamic_cast::<gst::TagSetter>().ok())
+    {
+        apply_tags(&setter, &settings.tags, &device);
+    }
+
+    if let Some((filter, s)) = processing.sharpen
+        && let Some(el) = bin.by_name(sharpen::RECORD_FILTER)
+    {
+        filter.apply(&el, &s);
+    }
+
     pipeline.add(&bin)?;
 
     // 添加 Ghost Pads
@@ -106,6 +1000,29 @@ pub(super) fn start_recording(
     a_ghost_pad.set_active(true)?;
     bin.add_pad(&a_ghost_pad)?;
 
+    if let Some(shared) = burn_in {
+        let el = bin
+            .by_name(overlay::BURN_IN)
+            .ok_or("recording bin has no burn-in overlay")?;
+        overlay::connect_burn_in(&el, shared.clone());
+    }
+
+    let frames = FrameCounters::attach(&bin)?;
+    // qtmux 看到时间码 meta 后自动写入 tmcd 轨道
+    let started_wall = Local::now();
+    let timecode = match (rate, bin.by_name(timecode::STAMPER)) {
+        (Some(rate), Some(stamper)) => timecode::attach(
+            &stamper,
+            rate,
+            start_timecode(settings.timecode, rate, started_wall),
+        )?,
+        _ => SharedTimecode::default(),
+    };
+    let write_queue = bin
+        .by_name("q_write")
+        .ok_or("recording bin has no write queue")?;
+    let write_counters = WriteCounters::attach(&write_queue)?;
+
     let video_tee_pad = video_tee.request_pad_simple("src_%u").unwrap();
     video_tee_pad.link(&v_ghost_pad)?;
 
@@ -113,21 +1030,66 @@ pub(super) fn start_recording(
     audio_tee_pad.link(&a_ghost_pad)?;
 
     // 启动该分支的状态 (同步到父管线的 Playing 状态)
-    bin.sync_state_with_parent()?;
+    if let Err(e) = bin.sync_state_with_parent() {
+        warn!(element = %bin.name(), "state change failed: {}", e);
+        return Err(e.into());
+    }
+
+    let span = tracing::info_span!("recording", id = id.0, path = %settings.filepath.display());
+    span.in_scope(|| {
+        info!(
+            encoder = ?settings.enc,
+            container = ?settings.container,
+            width = settings.res.width,
+            height = settings.res.height,
+            bitrate_kbps = settings.bitrate_kbps,
+            ten_bit,
+            fps = constant.map(|r| r.num as f64 / r.den as f64),
+            burn_in = burn_in.is_some(),
+            profile = settings.profile.as_deref(),
+            slate = %settings.slate.label(),
+            device = %device,
+            "recording started"
+        )
+    });
 
     Ok(ActiveRecording {
+        id,
         bin: bin.into(),
         video_tee_pad,
         audio_tee_pad,
+        device,
+        settings,
+        started_at: Instant::now(),
+        started_wall,
+        span,
+        frames,
+        last_progress: (Instant::now(), 0),
+        write_queue,
+        storage_warned: false,
+        write_counters,
+        write_monitor: WriteMonitor::default(),
+        write_change: None,
+        write_underruns: Vec::new(),
+        timecode,
+        processing,
+        silence: Vec::new(),
     })
 }
 
+/// [stop_recording] 返回的接收端, 文件封装完成并发出 Stopped 后收到消息.
+pub(super) type Finalizing = std::sync::mpsc::Receiver<()>;
+
 pub(super) fn stop_recording(
     pipeline: &gst::Pipeline,
     video_tee: &gst::Element,
     audio_tee: &gst::Element,
     active: ActiveRecording,
-) {
+    buffers: BufferStats,
+    audio: SidecarAudio,
+    event_tx: mpsc::UnboundedSender<RecordEvent>,
+) -> Finalizing {
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
     // GStreamer 对象（Element, Pad等）内部是引用计数，克隆代价很小
     let pipeline_c = pipeline.clone();
     let bin_el = active.bin.clone();
@@ -135,11 +1097,46 @@ pub(super) fn stop_recording(
     let a_tee_src = active.audio_tee_pad.clone();
     let vt_clone = video_tee.clone();
     let at_clone = audio_tee.clone();
+    let duration = active.started_at.elapsed();
+    let sidecar = Sidecar {
+        schema_version: SCHEMA_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        clip: active
+            .path()
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        started_at: active.started_wall,
+        duration_secs: duration.as_secs_f64(),
+        device: active.device.clone(),
+        settings: SidecarSettings {
+            denoise: active.processing.denoise.clone(),
+            sharpen: active
+                .processing
+                .sharpen
+                .map(|(filter, settings)| filter.label(&settings)),
+            ..SidecarSettings::from(&active.settings)
+        },
+        slate: Some(active.settings.slate.clone()),
+        profile: active.settings.profile.clone(),
+        buffers: Some(buffers),
+        audio: Some(audio),
+        silence: active.silence.clone(),
+        write_underruns: active.write_underruns.clone(),
+        ..Default::default()
+    };
+    let id = active.id;
+    let path = active.path().clone();
+    let frames = active.frames.clone();
+    let span = active.span.clone();
+    span.in_scope(|| info!(duration_s = duration.as_secs_f64(), "stopping recording"));
 
     v_tee_src
         .clone()
         .add_probe(gst::PadProbeType::IDLE, move |v_src, _info| {
-            println!("Tee pad is idle, starting safe teardown...");
+            let _enter = span.enter();
+            debug!("tee pad is idle, starting teardown");
 
             let bin = bin_el.clone().dynamic_cast::<gst::Bin>().unwrap();
 
@@ -160,19 +1157,59 @@ pub(super) fn stop_recording(
             let vp_for_cleanup = v_tee_src.clone();
             let ap_for_cleanup = a_tee_src.clone();
             let pipe_for_cleanup = pipeline_c.clone();
+            let path_for_event = path.clone();
+            let event_tx = event_tx.clone();
+            let mut sidecar = sidecar.clone();
+            let done_tx = done_tx.clone();
+            let frames = frames.clone();
+            let span = span.clone();
 
             std::thread::spawn(move || {
+                let _enter = span.enter();
                 // 给编码器排空数据的时间
                 std::thread::sleep(std::time::Duration::from_millis(600));
+                // 排空后队列为空, 这时的计数就是最终结果
+                let frames = frames.stats();
 
-                bin_for_cleanup.set_state(gst::State::Null).ok();
+                if let Err(e) = bin_for_cleanup.set_state(gst::State::Null) {
+                    warn!(element = %bin_for_cleanup.name(), "state change failed: {}", e);
+                }
                 tv_for_cleanup.release_request_pad(&vp_for_cleanup);
                 ta_for_cleanup.release_request_pad(&ap_for_cleanup);
                 pipe_for_cleanup.remove(&bin_for_cleanup).ok();
 
-                println!("AV Recording Stopped and cleaned up.");
+                let size = std::fs::metadata(&path_for_event)
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+
+                sidecar.stopped_at = Local::now();
+                sidecar.size_bytes = size;
+                if let Some(buffers) = &mut sidecar.buffers {
+                    buffers.frames = frames;
+                }
+                if let Err(e) = sidecar.write(&path_for_event) {
+                    error!("could not write sidecar: {}", e);
+                }
+                info!(
+                    duration_s = duration.as_secs_f64(),
+                    size_bytes = size,
+                    frames_dropped = frames.dropped,
+                    frames_repeated = frames.repeated,
+                    frames_skipped = frames.skipped,
+                    "recording finalized"
+                );
+                let _ = event_tx.send(RecordEvent::Stopped {
+                    id,
+                    path: path_for_event.clone(),
+                    duration,
+                    size,
+                    frames,
+                });
+                let _ = done_tx.send(());
+                super::verify::spawn(path_for_event, event_tx);
             });
 
             gst::PadProbeReturn::Remove
         });
+    done_rx
 }
@@ -0,0 +1,445 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::os::fd::AsRawFd;
+
+use zbus::blocking::Connection;
+use zbus::zvariant::{ObjectPath, OwnedFd, OwnedValue, Value};
+
+use super::pipeline::SOURCE;
+
+/// 屏幕录制默认限制的帧率, 避免高刷新率的屏幕让编码器满负荷.
+pub(crate) const DEFAULT_FPS: u32 = 30;
+
+/// X11 直接用 ximagesrc; Wayland 需要通过 xdg-desktop-portal 申请, 由用户在系统对话框中选择屏幕.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ScreenBackend {
+    #[default]
+    X11,
+    Portal,
+}
+
+impl ScreenBackend {
+    pub const ALL: [ScreenBackend; 2] = [ScreenBackend::X11, ScreenBackend::Portal];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ScreenBackend::X11 => "X11",
+            ScreenBackend::Portal => "Wayland (portal)",
+        }
+    }
+}
+
+/// 屏幕坐标中的矩形, 单位为像素.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn overlaps(&self, other: &Region) -> bool {
+        let right = |r: &Region| r.x as i64 + r.width as i64;
+        let bottom = |r: &Region| r.y as i64 + r.height as i64;
+        (self.x as i64) < right(other)
+            && (other.x as i64) < right(self)
+            && (self.y as i64) < bottom(other)
+            && (other.y as i64) < bottom(self)
+    }
+}
+
+/// 实际录制的范围, 用于判断是否拍到了自己的窗口.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CaptureArea {
+    /// 整个屏幕, 或者门户没有报告位置.
+    Whole,
+    Region(Region),
+}
+
+impl CaptureArea {
+    pub fn contains_any(&self, window: &Region) -> bool {
+        match self {
+            CaptureArea::Whole => true,
+            CaptureArea::Region(region) => region.overlaps(window),
+        }
+    }
+}
+
+/// 桌面录制. 设置后代替视频源, 仍然使用设置中的音频源.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ScreenConfig {
+    pub backend: ScreenBackend,
+    /// X11 的输出名称 (xrandr 中的名称), 例如 "HDMI-1". 没有区域和输出时录制整个屏幕.
+    pub output: Option<String>,
+    /// 手动指定的区域, 优先于输出.
+    pub region: Option<Region>,
+    pub show_cursor: bool,
+    pub fps: u32,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        Self {
+            backend: ScreenBackend::X11,
+            output: None,
+            region: None,
+            show_cursor: true,
+            fps: DEFAULT_FPS,
+        }
+    }
+}
+
+impl ScreenConfig {
+    /// X11 录制的区域, 输出不存在时录制整个屏幕.
+    pub fn x11_area(&self) -> CaptureArea {
+        let region = self.region.or_else(|| {
+            let name = self.output.as_ref()?;
+            let region = list_outputs()
+                .into_iter()
+                .find(|o| &o.name == name)
+                .map(|o| o.region);
+            if region.is_none() {
+                tracing::warn!(output = %name, "screen output not found, capturing the whole screen");
+            }
+            region
+        });
+        region.map_or(CaptureArea::Whole, CaptureArea::Region)
+    }
+
+    /// ximagesrc 的结束坐标是包含在内的.
+    pub fn x11_source(&self) -> String {
+        let mut source = format!(
+            "ximagesrc use-damage=false show-pointer={} name={}",
+            self.show_cursor, SOURCE
+        );
+        if let CaptureArea::Region(r) = self.x11_area() {
+            source += &format!(
+                " startx={} starty={} endx={} endy={}",
+                r.x.max(0),
+                r.y.max(0),
+                r.x.max(0) + r.width.max(1) as i32 - 1,
+                r.y.max(0) + r.height.max(1) as i32 - 1
+            );
+        }
+        source + &rate_cap(self.fps)
+    }
+}
+
+/// 屏幕内容不变时源不一定输出新帧, videorate 补成固定帧率.
+fn rate_cap(fps: u32) -> String {
+    format!(
+        " ! videorate ! video/x-raw,framerate={}/1",
+        fps.clamp(1, 60)
+    )
+}
+
+/// 门户失败或被取消时使用的黑色画面, 程序仍然可以运行.
+pub(crate) fn fallback_source() -> String {
+    format!("videotestsrc is-live=true pattern=black name={}", SOURCE)
+}
+
+/// xrandr 报告的输出.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Output {
+    pub name: String,
+    pub region: Region,
+}
+
+/// 通过 `xrandr --listmonitors` 列出 X11 的输出, 没有 xrandr 或不是 X11 时为空.
+pub(crate) fn list_outputs() -> Vec<Output> {
+    match std::process::Command::new("xrandr")
+        .arg("--listmonitors")
+        .output()
+    {
+        Ok(out) if out.status.success() => parse_monitors(&String::from_utf8_lossy(&out.stdout)),
+        Ok(out) => {
+            tracing::debug!(status = %out.status, "xrandr failed");
+            Vec::new()
+        }
+        Err(e) => {
+            tracing::debug!("could not run xrandr: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// 每行形如 ` 0: +*eDP-1 1920/344x1080/193+0+0  eDP-1`.
+fn parse_monitors(text: &str) -> Vec<Output> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let geometry = words.get(2)?;
+            let name = words.last()?;
+            let (width, rest) = geometry.split_once('x')?;
+            let mut rest = rest.split('+');
+            let height = rest.next()?;
+            let x = rest.next()?.parse().ok()?;
+            let y = rest.next()?.parse().ok()?;
+            let physical = |s: &str| s.split('/').next()?.parse().ok();
+            Some(Output {
+                name: name.to_string(),
+                region: Region {
+                    x,
+                    y,
+                    width: physical(width)?,
+                    height: physical(height)?,
+                },
+            })
+        })
+        .collect()
+}
+
+/// 门户的屏幕共享只有 gst 线程需要的这几个选项.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PortalOptions {
+    pub show_cursor: bool,
+    pub fps: u32,
+}
+
+const PORTAL: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SCREEN_CAST: &str = "org.freedesktop.portal.ScreenCast";
+/// SelectSources 的 types: 1 为显示器.
+const SOURCE_MONITOR: u32 = 1;
+/// SelectSources 的 cursor_mode: 1 隐藏, 2 画进画面.
+const CURSOR_HIDDEN: u32 = 1;
+const CURSOR_EMBEDDED: u32 = 2;
+
+type Results = HashMap<String, OwnedValue>;
+
+/// 门户授权的 PipeWire 流.
+pub(crate) struct PortalStream {
+    /// 会话随连接关闭而结束, 录制期间必须保持.
+    _connection: Connection,
+    fd: OwnedFd,
+    node: u32,
+    pub area: CaptureArea,
+}
+
+impl PortalStream {
+    /// pipewiresrc 会复制文件描述符, 但原来的也要保持打开直到管线启动.
+    pub fn source(&self, fps: u32) -> String {
+        format!(
+            "pipewiresrc fd={} path={} always-copy=true name={}{}",
+            self.fd.as_raw_fd(),
+            self.node,
+            SOURCE,
+            rate_cap(fps)
+        )
+    }
+}
+
+/// 通过 xdg-desktop-portal 申请屏幕共享, 阻塞到用户在系统对话框中做出选择.
+/// 在 gst 线程上调用, 界面不会卡住.
+pub(crate) fn request_portal_stream(show_cursor: bool) -> Result<PortalStream, String> {
+    let connection = Connection::session().map_err(|e| format!("no session bus: {}", e))?;
+    let sender = connection
+        .unique_name()
+        .ok_or("no unique bus name")?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let portal = Portal {
+        connection: &connection,
+        sender,
+    };
+
+    let token = format!("camui{}", std::process::id());
+    let results = portal.request(
+        "CreateSession",
+        &(HashMap::from([
+            ("handle_token", Value::from("camui_create")),
+            ("session_handle_token", Value::from(token.as_str())),
+        ]),),
+        "camui_create",
+    )?;
+    let session: String = results
+        .get("session_handle")
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| String::try_from(v).ok())
+        .ok_or("portal returned no session")?;
+    let session = ObjectPath::try_from(session.as_str()).map_err(|e| e.to_string())?;
+
+    let cursor_mode = if show_cursor {
+        CURSOR_EMBEDDED
+    } else {
+        CURSOR_HIDDEN
+    };
+    portal.request(
+        "SelectSources",
+        &(
+            &session,
+            HashMap::from([
+                ("handle_token", Value::from("camui_select")),
+                ("types", Value::from(SOURCE_MONITOR)),
+                ("cursor_mode", Value::from(cursor_mode)),
+                ("multiple", Value::from(false)),
+            ]),
+        ),
+        "camui_select",
+    )?;
+
+    let results = portal.request(
+        "Start",
+        &(
+            &session,
+            "",
+            HashMap::from([("handle_token", Value::from("camui_start"))]),
+        ),
+        "camui_start",
+    )?;
+    let streams: Vec<(u32, Results)> = results
+        .get("streams")
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| v.try_into().ok())
+        .ok_or("portal returned no streams")?;
+    let (node, properties) = streams.into_iter().next().ok_or("no screen was shared")?;
+    let pair = |key: &str| {
+        properties
+            .get(key)
+            .and_then(|v| v.try_clone().ok())
+            .and_then(|v| <(i32, i32)>::try_from(v).ok())
+    };
+    let area = match (pair("position"), pair("size")) {
+        (Some((x, y)), Some((width, height))) => CaptureArea::Region(Region {
+            x,
+            y,
+            width: width.max(0) as u32,
+            height: height.max(0) as u32,
+        }),
+        _ => CaptureArea::Whole,
+    };
+
+    let fd = connection
+        .call_method(
+            Some(PORTAL),
+            PORTAL_PATH,
+            Some(SCREEN_CAST),
+            "OpenPipeWireRemote",
+            &(&session, HashMap::<&str, Value>::new()),
+        )
+        .and_then(|reply| reply.body().deserialize::<OwnedFd>())
+        .map_err(|e| format!("could not open the PipeWire remote: {}", e))?;
+    tracing::info!(node, ?area, "screen share granted");
+    Ok(PortalStream {
+        _connection: connection,
+        fd,
+        node,
+        area,
+    })
+}
+
+struct Portal<'a> {
+    connection: &'a Connection,
+    /// 去掉 ':' 并把 '.' 换成 '_' 的唯一名称, 用于推算 Request 对象的路径.
+    sender: String,
+}
+
+impl Portal<'_> {
+    /// 门户的方法立即返回, 结果通过 Request 对象的 Response 信号送达.
+    /// 先订阅信号再调用, 避免错过很快返回的结果.
+    fn request<B>(&self, method: &str, body: &B, token: &str) -> Result<Results, String>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        let path = format!("{}/request/{}/{}", PORTAL_PATH, self.sender, token);
+        let request = zbus::blocking::Proxy::new(
+            self.connection,
+            PORTAL,
+            path.as_str(),
+            "org.freedesktop.portal.Request",
+        )
+        .map_err(|e| e.to_string())?;
+        let mut responses = request
+            .receive_signal("Response")
+            .map_err(|e| e.to_string())?;
+        self.connection
+            .call_method(Some(PORTAL), PORTAL_PATH, Some(SCREEN_CAST), method, body)
+            .map_err(|e| format!("{} failed: {}", method, e))?;
+        let message = responses
+            .next()
+            .ok_or_else(|| format!("portal dropped the {} request", method))?;
+        let (code, results): (u32, Results) =
+            message.body().deserialize().map_err(|e| e.to_string())?;
+        match code {
+            0 => Ok(results),
+            1 => Err("screen share was cancelled".to_string()),
+            _ => Err(format!("{} was refused by the portal", method)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_xrandr_monitors() {
+        let text = "Monitors: 2\n 0: +*eDP-1 1920/344x1080/193+0+0  eDP-1\n 1: +HDMI-1 2560/597x1440/336+1920+0  HDMI-1\n";
+        assert_eq!(
+            parse_monitors(text),
+            vec![
+                Output {
+                    name: "eDP-1".to_string(),
+                    region: Region {
+                        x: 0,
+                        y: 0,
+                        width: 1920,
+                        height: 1080,
+                    },
+                },
+                Output {
+                    name: "HDMI-1".to_string(),
+                    region: Region {
+                        x: 1920,
+                        y: 0,
+                        width: 2560,
+                        height: 1440,
+                    },
+                },
+            ]
+        );
+        assert!(parse_monitors("Monitors: 0\n").is_empty());
+    }
+
+    #[test]
+    fn builds_an_x11_source_for_a_region() {
+        let config = ScreenConfig {
+            region: Some(Region {
+                x: 100,
+                y: 50,
+                width: 640,
+                height: 480,
+            }),
+            show_cursor: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.x11_source(),
+            "ximagesrc use-damage=false show-pointer=false name=src startx=100 starty=50 endx=739 endy=529 ! videorate ! video/x-raw,framerate=30/1"
+        );
+    }
+
+    #[test]
+    fn detects_overlap_with_the_window() {
+        let left = Region {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        };
+        let window = Region {
+            x: 1900,
+            y: 100,
+            width: 800,
+            height: 600,
+        };
+        assert!(CaptureArea::Region(left).contains_any(&window));
+        let moved = Region { x: 1920, ..window };
+        assert!(!CaptureArea::Region(left).contains_any(&moved));
+        assert!(CaptureArea::Whole.contains_any(&moved));
+    }
+}
@@ -0,0 +1,179 @@
+//! 镜头或传感器偏软时的轻度锐化 (unsharp mask). 可以只加在录制分支上,
+//! 也可以放在 tee 之前同时作用于预览, 这样能用预览放大检查锐化的白边.
+//!
+//! 按 frei0r 的 sharpness (有强度和半径两个参数), gaussianblur (负的 sigma) 的顺序
+//! 选用第一个可用的.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use super::bypass::{self, Bypass};
+use super::record::Resolution;
+
+/// tee 之前选择器名字的前缀.
+const PREFIX: &str = "sh";
+/// tee 之前和录制分支中锐化元素的名字.
+const FILTER: &str = "sharpen";
+pub(super) const RECORD_FILTER: &str = "sharpen_v";
+/// 半径的上限 (像素).
+pub(crate) const MAX_RADIUS: f32 = 3.0;
+/// 估算 CPU 占用时假定的帧率.
+const ASSUMED_FPS: f64 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) enum SharpenTarget {
+    /// 只锐化录像, 开关和目标在开始录制时决定.
+    #[default]
+    Recording,
+    /// 在 tee 之前锐化, 预览和录像一样.
+    Both,
+}
+
+impl SharpenTarget {
+    pub const ALL: [SharpenTarget; 2] = [SharpenTarget::Recording, SharpenTarget::Both];
+}
+
+/// 属于录制设置, 随预设切换. 强度和半径在录制中修改也立即生效.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SharpenSettings {
+    pub enabled: bool,
+    /// 0 到 1.
+    pub amount: f32,
+    /// 像素, 不超过 [MAX_RADIUS].
+    pub radius: f32,
+    pub target: SharpenTarget,
+}
+
+impl Default for SharpenSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            amount: 0.3,
+            radius: 1.0,
+            target: SharpenTarget::Recording,
+        }
+    }
+}
+
+impl SharpenSettings {
+    /// 在 tee 之前锐化.
+    pub fn before_tee(&self) -> bool {
+        self.enabled && self.target == SharpenTarget::Both
+    }
+
+    /// 只在新的录像的分支上锐化.
+    pub fn recording_only(&self) -> bool {
+        self.enabled && self.target == SharpenTarget::Recording
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SharpenFilter {
+    Frei0r,
+    /// 只有一个参数, 强度和半径合成负的 sigma.
+    GaussianBlur,
+}
+
+impl SharpenFilter {
+    /// 可用的锐化元素, 都没有时返回 None. 结果只查一次.
+    pub fn detect() -> Option<Self> {
+        static DETECTED: OnceLock<Option<SharpenFilter>> = OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            [SharpenFilter::Frei0r, SharpenFilter::GaussianBlur]
+                .into_iter()
+                .find(|f| gst::ElementFactory::find(f.element()).is_some())
+        })
+    }
+
+    pub fn element(self) -> &'static str {
+        match self {
+            SharpenFilter::Frei0r => "frei0r-filter-sharpness",
+            SharpenFilter::GaussianBlur => "gaussianblur",
+        }
+    }
+
+    /// 转换格式并锐化的一段描述, 不含首尾的 `!`.
+    pub(super) fn description(self, name: &str) -> String {
+        format!(
+            "videoconvert ! {} name={} ! videoconvert",
+            self.element(),
+            name
+        )
+    }
+
+    /// 大约占用一个 CPU 核心的百分之几, 包括前后的格式转换.
+    /// 按每百万像素每帧的耗时粗略估算, 半径越大越慢.
+    pub fn cpu_percent(self, settings: &SharpenSettings, res: Resolution) -> f64 {
+        let radius = settings.radius.clamp(0.0, MAX_RADIUS) as f64;
+        let ms_per_megapixel = match self {
+            SharpenFilter::Frei0r => 4.0 + 3.0 * radius,
+            SharpenFilter::GaussianBlur => 6.0 + 8.0 * radius,
+        };
+        let megapixels = res.width as f64 * res.height as f64 / 1e6;
+        ms_per_megapixel * megapixels * ASSUMED_FPS / 10.0
+    }
+
+    /// 按强度和半径设置属性, 运行中也可以修改.
+    pub(super) fn apply(self, el: &gst::Element, settings: &SharpenSettings) {
+        let amount = settings.amount.clamp(0.0, 1.0) as f64;
+        let radius = settings.radius.clamp(0.1, MAX_RADIUS) as f64;
+        match self {
+            SharpenFilter::Frei0r => {
+                el.set_property("amount", amount);
+                el.set_property("size", radius / MAX_RADIUS as f64);
+            }
+            SharpenFilter::GaussianBlur => {
+                el.set_property("sigma", -(radius * (0.5 + amount)));
+            }
+        }
+    }
+
+    /// 写入 sidecar 的说明, 例如 "frei0r-filter-sharpness amount=0.30 radius=1.0 Both".
+    pub(super) fn label(self, settings: &SharpenSettings) -> String {
+        format!(
+            "{} amount={:.2} radius={:.1} {:?}",
+            self.element(),
+            settings.amount,
+            settings.radius,
+            settings.target
+        )
+    }
+}
+
+/// 插在 tee 之前的一段描述, 以 `!` 结尾.
+pub(super) fn description(filter: SharpenFilter) -> String {
+    bypass::description(PREFIX, &filter.description(FILTER))
+}
+
+/// tee 之前的锐化, 只在目标是 [SharpenTarget::Both] 时打开.
+pub(crate) struct Sharpener {
+    filter: SharpenFilter,
+    bypass: Bypass,
+    element: gst::Element,
+}
+
+impl Sharpener {
+    pub fn attach(pipeline: &gst::Pipeline, filter: SharpenFilter) -> Result<Self, String> {
+        Ok(Self {
+            filter,
+            bypass: Bypass::attach(pipeline, PREFIX)?,
+            element: pipeline
+                .by_name(FILTER)
+                .ok_or_else(|| format!("pipeline has no element named '{}'", FILTER))?,
+        })
+    }
+
+    pub fn filter(&self) -> SharpenFilter {
+        self.filter
+    }
+
+    pub fn set(&self, settings: &SharpenSettings) {
+        if settings.before_tee() {
+            self.filter.apply(&self.element, settings);
+        }
+        self.bypass.select(settings.before_tee());
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// 快门速度 (曝光时间), 例如 "1/500" 或长曝光 "2s".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ShutterSpeed {
+    /// 1/n 秒.
+    Fraction(u32),
+    Seconds(f32),
+}
+
+use ShutterSpeed::{Fraction, Seconds};
+
+/// 标准档位, 从慢到快.
+pub(crate) const STOPS: &[ShutterSpeed] = &[
+    Seconds(2.0),
+    Seconds(1.0),
+    Fraction(2),
+    Fraction(4),
+    Fraction(8),
+    Fraction(15),
+    Fraction(25),
+    Fraction(30),
+    Fraction(50),
+    Fraction(60),
+    Fraction(100),
+    Fraction(125),
+    Fraction(250),
+    Fraction(500),
+    Fraction(1000),
+    Fraction(2000),
+    Fraction(4000),
+];
+
+impl ShutterSpeed {
+    pub fn micros(self) -> u64 {
+        match self {
+            Fraction(n) => (1_000_000.0 / n.max(1) as f64).round() as u64,
+            Seconds(s) => (s as f64 * 1_000_000.0).round() as u64,
+        }
+    }
+
+    /// 比当前更快的下一个标准档位, 已经最快时不变.
+    pub fn next(self) -> Self {
+        let micros = self.micros();
+        STOPS
+            .iter()
+            .find(|s| s.micros() < micros)
+            .copied()
+            .unwrap_or(self)
+    }
+
+    /// 比当前更慢的上一个标准档位, 已经最慢时不变.
+    pub fn prev(self) -> Self {
+        let micros = self.micros();
+        STOPS
+            .iter()
+            .rev()
+            .find(|s| s.micros() > micros)
+            .copied()
+            .unwrap_or(self)
+    }
+
+    /// 与曝光时间最接近的标准档位, 按比例比较.
+    pub fn nearest(micros: u64) -> Self {
+        let target = (micros.max(1) as f64).ln();
+        STOPS
+            .iter()
+            .copied()
+            .min_by(|a, b| {
+                let da = ((a.micros() as f64).ln() - target).abs();
+                let db = ((b.micros() as f64).ln() - target).abs();
+                da.total_cmp(&db)
+            })
+            .expect("STOPS is not empty")
+    }
+}
+
+impl Default for ShutterSpeed {
+    fn default() -> Self {
+        Fraction(500)
+    }
+}
+
+impl fmt::Display for ShutterSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fraction(n) => write!(f, "1/{}", n),
+            Seconds(s) => write!(f, "{}s", s),
+        }
+    }
+}
+
+impl FromStr for ShutterSpeed {
+    type Err = String;
+
+    /// 接受 "1/500", "2s" 和 "0.5s".
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if let Some(denominator) = s.strip_prefix("1/") {
+            return match denominator.trim().parse::<u32>() {
+                Ok(n) if n > 0 => Ok(Fraction(n)),
+                _ => Err(format!("'{}' is not a valid fraction, use e.g. 1/500", s)),
+            };
+        }
+        if let Some(seconds) = s.strip_suffix('s') {
+            return match seconds.trim().parse::<f32>() {
+                Ok(secs) if secs.is_finite() && secs > 0.0 && secs <= 60.0 => Ok(Seconds(secs)),
+                _ => Err(format!("'{}' is not a valid duration, use e.g. 2s", s)),
+            };
+        }
+        Err(format!("'{}' is not a shutter speed, use 1/500 or 2s", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fractions_and_seconds() {
+        assert_eq!("1/500".parse(), Ok(Fraction(500)));
+        assert_eq!(" 1/60 ".parse(), Ok(Fraction(60)));
+        assert_eq!("2s".parse(), Ok(Seconds(2.0)));
+        assert_eq!("0.5s".parse(), Ok(Seconds(0.5)));
+        for bad in [
+            "1/0", "hello", "", "1/", "500", "2/500", "-1s", "0s", "infs",
+        ] {
+            assert!(
+                bad.parse::<ShutterSpeed>().is_err(),
+                "{:?} should be rejected",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for stop in STOPS {
+            assert_eq!(stop.to_string().parse::<ShutterSpeed>(), Ok(*stop));
+        }
+        assert_eq!(Seconds(2.0).to_string(), "2s");
+        assert_eq!(Seconds(0.5).to_string(), "0.5s");
+        assert_eq!(Fraction(125).to_string(), "1/125");
+    }
+
+    #[test]
+    fn steps_through_the_stops() {
+        assert_eq!(Seconds(2.0).next(), Seconds(1.0));
+        assert_eq!(Seconds(1.0).next(), Fraction(2));
+        assert_eq!(Fraction(2).prev(), Seconds(1.0));
+        assert_eq!(Fraction(60).next(), Fraction(100));
+        // 到头时不变
+        assert_eq!(Fraction(4000).next(), Fraction(4000));
+        assert_eq!(Seconds(2.0).prev(), Seconds(2.0));
+        // 不在标准档位上时走到相邻的档位
+        assert_eq!(Fraction(90).next(), Fraction(100));
+        assert_eq!(Fraction(90).prev(), Fraction(60));
+
+        let mut speed = STOPS[0];
+        let mut seen = vec![speed];
+        while speed.next() != speed {
+            speed = speed.next();
+            seen.push(speed);
+        }
+        assert_eq!(seen, STOPS);
+    }
+
+    #[test]
+    fn converts_to_microseconds() {
+        assert_eq!(Fraction(500).micros(), 2000);
+        assert_eq!(Fraction(60).micros(), 16_667);
+        assert_eq!(Seconds(2.0).micros(), 2_000_000);
+        assert_eq!(ShutterSpeed::nearest(16_700), Fraction(60));
+        assert_eq!(ShutterSpeed::nearest(1_900_000), Seconds(2.0));
+    }
+}
@@ -0,0 +1,110 @@
+//! 录制中的无声检测: 话筒没电或者没插好, 录了一整段采访才发现没有声音.
+//! 只看电平表的 RMS. 电平元素只在有音频分支时发消息, 输入音量调成 0 算作有意静音, 不检测.
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// 属于音频设置.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SilenceSettings {
+    pub enabled: bool,
+    /// 电平 (dBFS) 不超过这个值算作无声.
+    pub threshold_db: f64,
+    /// 持续这么多秒才提示.
+    pub after_secs: u32,
+}
+
+impl Default for SilenceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_db: -50.0,
+            after_secs: 15,
+        }
+    }
+}
+
+/// 写入 sidecar 的一段无声.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Silence {
+    /// 开始无声的时刻.
+    pub at: DateTime<Local>,
+    /// 从录像开始算起的秒数.
+    pub start_secs: f64,
+    /// 声音恢复时的秒数, 停止录制时仍然无声为 None.
+    pub end_secs: Option<f64>,
+}
+
+/// [SilenceDetector::update] 报告的变化.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SilenceChange {
+    /// 已经无声了 [SilenceSettings::after_secs] 秒, 带上开始无声的时间.
+    Started {
+        since: Instant,
+    },
+    Ended,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SilenceDetector {
+    /// 连续无声的开始时间.
+    since: Option<Instant>,
+    active: bool,
+}
+
+impl SilenceDetector {
+    /// 每条电平消息调用一次. 电平是 NaN 时也算无声.
+    pub fn update(
+        &mut self,
+        db: f64,
+        settings: &SilenceSettings,
+        now: Instant,
+    ) -> Option<SilenceChange> {
+        if db > settings.threshold_db {
+            self.since = None;
+            return std::mem::take(&mut self.active).then_some(SilenceChange::Ended);
+        }
+        let since = *self.since.get_or_insert(now);
+        let after = Duration::from_secs(settings.after_secs as u64);
+        if !self.active && now.saturating_duration_since(since) >= after {
+            self.active = true;
+            return Some(SilenceChange::Started { since });
+        }
+        None
+    }
+
+    /// 没有录制或者关闭检测时调用, 返回之前是否在提示.
+    pub fn reset(&mut self) -> bool {
+        std::mem::take(self).active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warns_after_the_delay_and_resets_when_the_level_recovers() {
+        let settings = SilenceSettings::default();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut detector = SilenceDetector::default();
+        assert_eq!(detector.update(-70.0, &settings, at(0)), None);
+        assert_eq!(detector.update(f64::NEG_INFINITY, &settings, at(14)), None);
+        assert_eq!(
+            detector.update(-60.0, &settings, at(15)),
+            Some(SilenceChange::Started { since: at(0) })
+        );
+        assert_eq!(detector.update(-60.0, &settings, at(16)), None);
+        assert_eq!(
+            detector.update(-20.0, &settings, at(17)),
+            Some(SilenceChange::Ended)
+        );
+        // 恢复后重新计时
+        assert_eq!(detector.update(-60.0, &settings, at(18)), None);
+        assert_eq!(detector.update(-60.0, &settings, at(30)), None);
+        assert!(!detector.reset());
+    }
+}
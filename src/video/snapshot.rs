@@ -0,0 +1,344 @@
+use chrono::{DateTime, Local};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use image::ImageEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::pipeline::PREVIEW_SINK;
+
+/// 接在视频 tee 上的 fakesink, 只保留最后一帧, 用于保存全分辨率的静帧.
+pub(crate) const STILL_SINK: &str = "still";
+
+/// 转换和缩放一帧最多等待的时间.
+const ENCODE_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(5);
+/// 一次连拍最多的帧数, 每帧都要完整复制一份.
+pub(crate) const MAX_BURST: u32 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum StillFormat {
+    Png,
+    Jpeg,
+}
+
+impl StillFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            StillFormat::Png => "png",
+            StillFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum StillResolution {
+    /// 与录制相同.
+    Source,
+    /// 与屏幕上的预览相同, 文件小得多.
+    Preview,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SnapshotSettings {
+    pub format: StillFormat,
+    /// JPEG 的质量, 1 到 100.
+    pub jpeg_quality: u8,
+    pub resolution: StillResolution,
+    /// 连拍一次保存的帧数.
+    pub burst_frames: u32,
+}
+
+impl Default for SnapshotSettings {
+    fn default() -> Self {
+        Self {
+            format: StillFormat::Png,
+            jpeg_quality: 90,
+            resolution: StillResolution::Source,
+            burst_frames: 5,
+        }
+    }
+}
+
+/// 把最近的一帧保存为静帧. 没有预览叠加层.
+pub(crate) fn save(
+    pipeline: &gst::Pipeline,
+    path: &Path,
+    settings: &SnapshotSettings,
+) -> Result<(), String> {
+    let sink = pipeline
+        .by_name(STILL_SINK)
+        .ok_or("pipeline has no still sink")?;
+    let sample = sink
+        .property::<Option<gst::Sample>>("last-sample")
+        .ok_or("no frame has been captured yet")?;
+    let size = output_size(pipeline, settings);
+    write(&sample, size, path, settings, Local::now())?;
+    tracing::info!(path = %path.display(), "saved snapshot");
+    Ok(())
+}
+
+/// 连拍第 `index` 帧 (从 1 开始) 的文件名, 例如 `a.jpg` 变为 `a_burst_01.jpg`.
+pub(crate) fn burst_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{}_burst_{:02}", stem, index);
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// 尚未收齐的连拍.
+struct Burst<F> {
+    frames: Vec<(gst::Sample, DateTime<Local>)>,
+    first: Option<(DateTime<Local>, gst::ClockTime)>,
+    path: PathBuf,
+    settings: SnapshotSettings,
+    done: F,
+}
+
+/// 从下一帧开始连续保存 `burst_frames` 帧. 在静帧分支的队列之前临时加一个探针,
+/// 收齐后移除, 不会因为 leaky 队列而跳帧. 编码在单独的线程中进行, 完成后调用 `done`.
+pub(crate) fn burst(
+    pipeline: &gst::Pipeline,
+    path: PathBuf,
+    settings: SnapshotSettings,
+    done: impl FnOnce(Result<Vec<PathBuf>, String>) + Send + 'static,
+) -> Result<(), String> {
+    let sink = pipeline
+        .by_name(STILL_SINK)
+        .ok_or("pipeline has no still sink")?;
+    let pad = sink
+        .static_pad("sink")
+        .and_then(|pad| pad.peer())
+        .and_then(|pad| pad.parent_element())
+        .and_then(|queue| queue.static_pad("sink"))
+        .ok_or("still branch has no queue")?;
+    let size = output_size(pipeline, &settings);
+    let count = settings.burst_frames.clamp(1, MAX_BURST) as usize;
+    let pending = Mutex::new(Some(Burst {
+        frames: Vec::with_capacity(count),
+        first: None,
+        path,
+        settings,
+        done,
+    }));
+    pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+        let mut pending = pending.lock();
+        let Some(burst) = pending.as_mut() else {
+            return gst::PadProbeReturn::Remove;
+        };
+        let (Some(buffer), Some(caps)) = (info.buffer(), pad.current_caps()) else {
+            return gst::PadProbeReturn::Ok;
+        };
+        // 各帧的时间按时间戳推算, 不受编码和调度的延迟影响
+        let pts = buffer.pts().unwrap_or(gst::ClockTime::ZERO);
+        let (started, first_pts) = *burst.first.get_or_insert((Local::now(), pts));
+        let offset = pts.saturating_sub(first_pts);
+        let taken = started + chrono::Duration::microseconds(offset.useconds() as i64);
+        // 复制一份, 不长时间占用采集源的缓冲池
+        let buffer = buffer.copy_deep().unwrap_or_else(|_| buffer.to_owned());
+        let sample = gst::Sample::builder().buffer(&buffer).caps(&caps).build();
+        burst.frames.push((sample, taken));
+        if burst.frames.len() < count {
+            return gst::PadProbeReturn::Ok;
+        }
+        let Some(Burst {
+            frames,
+            path,
+            settings,
+            done,
+            ..
+        }) = pending.take()
+        else {
+            return gst::PadProbeReturn::Remove;
+        };
+        let spawned = std::thread::Builder::new()
+            .name("burst".into())
+            .spawn(move || {
+                let result = frames
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (sample, taken))| {
+                        let path = burst_path(&path, i + 1);
+                        write(sample, size, &path, &settings, *taken).map(|()| path)
+                    })
+                    .collect::<Result<Vec<_>, _>>();
+                if let Ok(paths) = &result {
+                    tracing::info!(count = paths.len(), path = %path.display(), "saved burst");
+                }
+                done(result);
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("could not start burst encoder: {}", e);
+        }
+        gst::PadProbeReturn::Remove
+    })
+    .ok_or("could not watch the still branch")?;
+    Ok(())
+}
+
+/// 按预览分辨率保存时返回预览的大小, 否则保持原样.
+fn output_size(pipeline: &gst::Pipeline, settings: &SnapshotSettings) -> Option<(u32, u32)> {
+    if settings.resolution != StillResolution::Preview {
+        return None;
+    }
+    let caps = pipeline
+        .by_name(PREVIEW_SINK)?
+        .static_pad("sink")?
+        .current_caps()?;
+    let info = gst_video::VideoInfo::from_caps(&caps).ok()?;
+    Some((info.width(), info.height()))
+}
+
+/// 转换为 RGB (需要时缩放) 后按设置的格式编码并写入.
+fn write(
+    sample: &gst::Sample,
+    size: Option<(u32, u32)>,
+    path: &Path,
+    settings: &SnapshotSettings,
+    taken: DateTime<Local>,
+) -> Result<(), String> {
+    let mut caps = gst::Caps::builder("video/x-raw").field("format", "RGB");
+    if let Some((width, height)) = size {
+        caps = caps
+            .field("width", width as i32)
+            .field("height", height as i32);
+    }
+    let rgb = gst_video::convert_sample(sample, &caps.build(), ENCODE_TIMEOUT)
+        .map_err(|e| format!("could not convert the frame: {}", e))?;
+    let info = rgb
+        .caps()
+        .and_then(|caps| gst_video::VideoInfo::from_caps(caps).ok())
+        .ok_or("converted frame has no size")?;
+    let buffer = rgb.buffer().ok_or("converted frame has no data")?;
+    let map = buffer
+        .map_readable()
+        .map_err(|_| "could not read the converted frame")?;
+
+    // 每行可能有对齐的填充, 编码器要求紧密排列
+    let (width, height) = (info.width(), info.height());
+    let stride = info.stride()[0] as usize;
+    let row = width as usize * 3;
+    let mut pixels = Vec::with_capacity(row * height as usize);
+    for y in 0..height as usize {
+        let line = map
+            .get(y * stride..y * stride + row)
+            .ok_or("converted frame is truncated")?;
+        pixels.extend_from_slice(line);
+    }
+
+    let mut encoded = Vec::new();
+    let result = match settings.format {
+        StillFormat::Png => PngEncoder::new(&mut encoded).write_image(
+            &pixels,
+            width,
+            height,
+            image::ExtendedColorType::Rgb8,
+        ),
+        StillFormat::Jpeg => {
+            let quality = settings.jpeg_quality.clamp(1, 100);
+            let mut encoder = JpegEncoder::new_with_quality(&mut encoded, quality);
+            // JPEG 编码器总是支持 EXIF
+            let _ = encoder.set_exif_metadata(exif(taken));
+            encoder.write_image(&pixels, width, height, image::ExtendedColorType::Rgb8)
+        }
+    };
+    result.map_err(|e| format!("could not encode the frame: {}", e))?;
+    std::fs::write(path, encoded).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// 最小的 EXIF (小端 TIFF): IFD0 的 DateTime 和 Exif IFD 的 DateTimeOriginal,
+/// OffsetTimeOriginal 与 SubSecTimeOriginal, 连拍的各帧能按毫秒区分.
+fn exif(taken: DateTime<Local>) -> Vec<u8> {
+    const ASCII: u16 = 2;
+    const LONG: u16 = 4;
+    let datetime = format!("{}\0", taken.format("%Y:%m:%d %H:%M:%S"));
+    let zone = format!("{}\0", taken.format("%:z"));
+    let subsec = format!("{:03}\0", taken.timestamp_subsec_millis());
+
+    // 头部 8 字节, IFD0 两项, Exif IFD 三项, 之后是放不进 4 字节的字符串
+    let exif_ifd = 8 + 2 + 2 * 12 + 4;
+    let data = exif_ifd + 2 + 3 * 12 + 4;
+    let mut out = b"II*\0".to_vec();
+    out.extend(8u32.to_le_bytes());
+    let mut strings = Vec::new();
+    let mut ascii = |out: &mut Vec<u8>, tag: u16, text: &str| {
+        out.extend(tag.to_le_bytes());
+        out.extend(ASCII.to_le_bytes());
+        out.extend((text.len() as u32).to_le_bytes());
+        if text.len() <= 4 {
+            let mut value = [0u8; 4];
+            value[..text.len()].copy_from_slice(text.as_bytes());
+            out.extend(value);
+        } else {
+            out.extend(((data + strings.len()) as u32).to_le_bytes());
+            strings.extend_from_slice(text.as_bytes());
+        }
+    };
+
+    out.extend(2u16.to_le_bytes());
+    ascii(&mut out, 0x0132, &datetime);
+    out.extend(0x8769u16.to_le_bytes());
+    out.extend(LONG.to_le_bytes());
+    out.extend(1u32.to_le_bytes());
+    out.extend((exif_ifd as u32).to_le_bytes());
+    out.extend(0u32.to_le_bytes());
+
+    out.extend(3u16.to_le_bytes());
+    ascii(&mut out, 0x9003, &datetime);
+    ascii(&mut out, 0x9011, &zone);
+    ascii(&mut out, 0x9291, &subsec);
+    out.extend(0u32.to_le_bytes());
+
+    out.extend(strings);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn burst_frames_are_numbered() {
+        let path = Path::new("/rec/A001_C002_T01.jpg");
+        assert_eq!(
+            burst_path(path, 1),
+            Path::new("/rec/A001_C002_T01_burst_01.jpg")
+        );
+        assert_eq!(
+            burst_path(path, 12),
+            Path::new("/rec/A001_C002_T01_burst_12.jpg")
+        );
+    }
+
+    #[test]
+    fn exif_points_at_timestamps() {
+        let taken = Local.with_ymd_and_hms(2024, 5, 1, 12, 30, 15).unwrap()
+            + chrono::Duration::milliseconds(250);
+        let exif = exif(taken);
+        let u16_at = |at: usize| u16::from_le_bytes([exif[at], exif[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes(exif[at..at + 4].try_into().unwrap()) as usize;
+        let text_at = |at: usize, len: usize| std::str::from_utf8(&exif[at..at + len]).unwrap();
+
+        assert_eq!(&exif[..4], b"II*\0");
+        let ifd0 = u32_at(4);
+        assert_eq!(u16_at(ifd0), 2);
+        assert_eq!(u16_at(ifd0 + 2), 0x0132);
+        assert_eq!(text_at(u32_at(ifd0 + 2 + 8), 19), "2024:05:01 12:30:15");
+
+        let exif_ifd = u32_at(ifd0 + 14 + 8);
+        assert_eq!(u16_at(exif_ifd), 3);
+        assert_eq!(u16_at(exif_ifd + 2), 0x9003);
+        assert_eq!(text_at(u32_at(exif_ifd + 2 + 8), 19), "2024:05:01 12:30:15");
+        // 毫秒放得进 4 字节, 直接写在条目里
+        assert_eq!(u16_at(exif_ifd + 26), 0x9291);
+        assert_eq!(text_at(exif_ifd + 26 + 8, 3), "250");
+    }
+}
@@ -0,0 +1,324 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::multiview;
+use super::pipeline::SOURCE;
+
+/// 视频选择器, 在视频 tee (和数字变焦) 之前.
+pub(crate) const VIDEO_SELECTOR: &str = "vsel";
+/// 音频跟随视频时的音频选择器.
+pub(crate) const AUDIO_SELECTOR: &str = "asel";
+
+/// 另外一路可以切换到的采集源.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct AlternateSource {
+    /// 源列表中显示的名称.
+    pub name: String,
+    /// gst-launch 语法的视频源, 例如 "v4l2src device=/dev/video2".
+    pub video_source: String,
+    /// 音频跟随视频时使用, 为空时这一路是静音.
+    pub audio_source: Option<String>,
+}
+
+/// 在运行中切换的多路采集源. 第一路是平常的采集源, 其余在 `sources` 中.
+/// 各路先统一成同样的原始视频格式再进选择器, 切换不需要等关键帧.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SwitchConfig {
+    pub sources: Vec<AlternateSource>,
+    pub width: u32,
+    pub height: u32,
+    pub framerate: u32,
+    /// 切换视频时同时切换音频, 否则一直使用第一路的音频.
+    pub audio_follows_video: bool,
+    /// 暂停没有选中的源, 节省 CPU. 切回时要等它重新出帧, 切换稍慢.
+    pub pause_inactive: bool,
+    /// 为每一路建立缩略图分支. 性能差的设备上关掉, 分支完全不建立.
+    pub multiview: bool,
+}
+
+impl Default for SwitchConfig {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            width: 1920,
+            height: 1080,
+            framerate: 30,
+            audio_follows_video: false,
+            pause_inactive: false,
+            multiview: true,
+        }
+    }
+}
+
+/// 第 `index` 路视频源的元素名, 第一路仍然是 `src`, 控件和设备名都从它读取.
+pub(crate) fn source_name(index: usize) -> String {
+    match index {
+        0 => SOURCE.to_string(),
+        _ => format!("{}{}", SOURCE, index),
+    }
+}
+
+impl SwitchConfig {
+    /// 选择器和接到它上面的各路视频, 以选择器的输出结束. `primary` 已经命名为 `src`.
+    pub fn video_input(&self, primary: &str) -> String {
+        let mut input = format!("input-selector name={}\n", VIDEO_SELECTOR);
+        input += &self.video_branch(0, primary);
+        for (i, alternate) in self.sources.iter().enumerate() {
+            let source = format!("{} name={}", alternate.video_source, source_name(i + 1));
+            input += &self.video_branch(i + 1, &source);
+        }
+        input + &format!("{}.", VIDEO_SELECTOR)
+    }
+
+    /// 一路视频: 统一格式后接到选择器, 需要时再分出缩略图.
+    fn video_branch(&self, index: usize, source: &str) -> String {
+        let format = format!(
+            "videoconvert ! videoscale ! videorate ! video/x-raw,format=I420,width={},height={},framerate={}/1",
+            self.width, self.height, self.framerate
+        );
+        let queue = "queue max-size-buffers=2 leaky=downstream";
+        let selector = format!("{}.sink_{}", VIDEO_SELECTOR, index);
+        if !self.multiview {
+            return format!("{} ! {} ! {} ! {}\n", source, format, queue, selector);
+        }
+        let tee = format!("{}_t", source_name(index));
+        format!(
+            "{} ! {} ! tee name={} ! {} ! {}\n{}\n",
+            source,
+            format,
+            tee,
+            queue,
+            selector,
+            multiview::tile_branch(&tee, index)
+        )
+    }
+
+    /// 音频跟随视频时的音频选择器, 否则原样返回第一路的音频源.
+    pub fn audio_input(&self, primary: &str) -> String {
+        if !self.audio_follows_video {
+            return primary.to_string();
+        }
+        let format = "audioconvert ! audioresample ! audio/x-raw,rate=48000,channels=2 ! queue";
+        let mut input = format!("input-selector name={}\n", AUDIO_SELECTOR);
+        input += &format!("{} ! {} ! {}.sink_0\n", primary, format, AUDIO_SELECTOR);
+        for (i, alternate) in self.sources.iter().enumerate() {
+            let audio = alternate
+                .audio_source
+                .as_deref()
+                .unwrap_or("audiotestsrc is-live=true wave=silence");
+            input += &format!(
+                "{} ! {} ! {}.sink_{}\n",
+                audio,
+                format,
+                AUDIO_SELECTOR,
+                i + 1
+            );
+        }
+        input + &format!("{}.", AUDIO_SELECTOR)
+    }
+}
+
+/// 切换命令, 在录制中也可以使用.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceCommand {
+    /// 切换到第几路, 从 0 开始.
+    Switch(usize),
+}
+
+#[derive(Debug, Default)]
+struct SwitchState {
+    active: usize,
+    /// 正在等待第一帧的源.
+    pending: Option<usize>,
+}
+
+/// 操作管线中的选择器. 暂停的源恢复后, 等它的第一帧到达选择器才切过去,
+/// 不会切到一段空白.
+pub(crate) struct SourceSwitcher {
+    video: gst::Element,
+    audio: Option<gst::Element>,
+    sources: Vec<gst::Element>,
+    pause_inactive: bool,
+    state: Arc<Mutex<SwitchState>>,
+    /// 切换完成后由 pad probe 填写, 之后在 gst 线程上暂停旧源.
+    switched: Arc<Mutex<Option<(usize, usize)>>>,
+}
+
+impl SourceSwitcher {
+    /// 可能的错误: 缺少选择器或某一路的源.
+    pub fn attach(pipeline: &gst::Pipeline, config: &SwitchConfig) -> Result<Self, String> {
+        let video = pipeline
+            .by_name(VIDEO_SELECTOR)
+            .ok_or("pipeline has no video selector")?;
+        let sources = (0..=config.sources.len())
+            .map(|i| {
+                pipeline
+                    .by_name(&source_name(i))
+                    .ok_or_else(|| format!("pipeline has no source '{}'", source_name(i)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let switcher = Self {
+            audio: pipeline.by_name(AUDIO_SELECTOR),
+            video,
+            sources,
+            pause_inactive: config.pause_inactive,
+            state: Arc::default(),
+            switched: Arc::default(),
+        };
+        set_active(&switcher.video, 0);
+        if let Some(audio) = &switcher.audio {
+            set_active(audio, 0);
+        }
+        Ok(switcher)
+    }
+
+    /// 管线开始播放后调用, 暂停第一路以外的源.
+    pub fn start(&self) {
+        if !self.pause_inactive {
+            return;
+        }
+        for src in &self.sources[1..] {
+            pause(src);
+        }
+    }
+
+    /// 源没有暂停时立即切换, 否则先恢复它.
+    pub fn switch(&self, index: usize) -> Result<(), String> {
+        if index >= self.sources.len() {
+            return Err(format!("there is no source {}", index + 1));
+        }
+        let mut state = self.state.lock();
+        if state.active == index || state.pending == Some(index) {
+            return Ok(());
+        }
+        let previous = state.active;
+        if !self.pause_inactive {
+            self.select(index);
+            state.active = index;
+            *self.switched.lock() = Some((previous, index));
+            return Ok(());
+        }
+        let pad = self
+            .video
+            .static_pad(&format!("sink_{}", index))
+            .and_then(|pad| pad.peer())
+            .ok_or("selector pad is not linked")?;
+        if let Err(e) = self.sources[index].set_state(gst::State::Playing) {
+            return Err(format!("could not resume source {}: {}", index + 1, e));
+        }
+        // 上一个还没等到第一帧的源不再需要
+        if let Some(stale) = state.pending.replace(index) {
+            pause(&self.sources[stale]);
+        }
+        drop(state);
+        let video = self.video.clone();
+        let audio = self.audio.clone();
+        let state = self.state.clone();
+        let switched = self.switched.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+            let mut s = state.lock();
+            // 等待期间又切到了别的源
+            if s.pending == Some(index) {
+                set_active(&video, index);
+                if let Some(audio) = &audio {
+                    set_active(audio, index);
+                }
+                *switched.lock() = Some((s.active, index));
+                s.active = index;
+                s.pending = None;
+            }
+            gst::PadProbeReturn::Remove
+        });
+        Ok(())
+    }
+
+    fn select(&self, index: usize) {
+        set_active(&self.video, index);
+        if let Some(audio) = &self.audio {
+            set_active(audio, index);
+        }
+    }
+
+    /// 在 gst 线程的循环中调用, 返回刚切换到的源. 状态切换不能在 pad probe 中做.
+    pub fn poll(&self) -> Option<usize> {
+        let (previous, index) = self.switched.lock().take()?;
+        if self.pause_inactive && previous != index {
+            pause(&self.sources[previous]);
+        }
+        tracing::info!(source = index + 1, "switched source");
+        Some(index)
+    }
+}
+
+fn set_active(selector: &gst::Element, index: usize) {
+    match selector.static_pad(&format!("sink_{}", index)) {
+        Some(pad) => selector.set_property("active-pad", &pad),
+        None => tracing::warn!(element = %selector.name(), index, "selector has no such pad"),
+    }
+}
+
+/// 实时源暂停后不再采集, 设备仍然保持打开.
+fn pause(src: &gst::Element) {
+    if let Err(e) = src.set_state(gst::State::Paused) {
+        tracing::warn!(element = %src.name(), "could not pause source: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_sources() -> SwitchConfig {
+        SwitchConfig {
+            sources: vec![AlternateSource {
+                name: "Wide".to_string(),
+                video_source: "v4l2src device=/dev/video2".to_string(),
+                audio_source: None,
+            }],
+            width: 1280,
+            height: 720,
+            multiview: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn names_every_source() {
+        let input = two_sources().video_input("v4l2src name=src device=/dev/video0");
+        assert!(input.starts_with("input-selector name=vsel\n"));
+        assert!(input.contains("v4l2src name=src device=/dev/video0 ! videoconvert"));
+        assert!(input.contains("v4l2src device=/dev/video2 name=src1 ! videoconvert"));
+        assert!(input.contains("width=1280,height=720,framerate=30/1"));
+        assert!(input.contains("vsel.sink_1\n"));
+        assert!(input.ends_with("vsel."));
+    }
+
+    #[test]
+    fn branches_tiles_off_each_source() {
+        let config = SwitchConfig {
+            multiview: true,
+            ..two_sources()
+        };
+        let input = config.video_input("v4l2src name=src device=/dev/video0");
+        assert!(input.contains("tee name=src_t ! queue"));
+        assert!(input.contains("tee name=src1_t ! queue"));
+        assert!(input.contains("src1_t. ! queue"));
+        assert!(input.contains("appsink name=tile_sink1"));
+    }
+
+    #[test]
+    fn audio_follows_video_only_when_asked() {
+        let mut config = two_sources();
+        assert_eq!(config.audio_input("alsasrc"), "alsasrc");
+        config.audio_follows_video = true;
+        let input = config.audio_input("alsasrc");
+        assert!(input.starts_with("input-selector name=asel\n"));
+        assert!(input.contains("audiotestsrc is-live=true wave=silence ! audioconvert"));
+        assert!(input.ends_with("asel."));
+    }
+}
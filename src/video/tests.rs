@@ -0,0 +1,1105 @@
+//! 端到端录制测试: 用测试源驱动真实管线, 录一段短片并用 Discoverer 检查结果.
+//! 需要安装 x264/x265/vpx/libav/fdkaac/opus/flac 等插件, 默认跳过, 用 `cargo test -- --ignored` 运行.
+
+use gstreamer as gst;
+use gstreamer_pbutils as gst_pbutils;
+use gstreamer_pbutils::prelude::*;
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use super::bus::FrameStats;
+use super::denoise::{DenoiseFilter, DenoiseSettings, DenoiseStrength};
+use super::exchange::FrameExchange;
+use super::overlay::{MeterOverlay, OverlayConfig, SharedOverlay};
+use super::pipeline::{CaptureSources, PipelineBuilder, SOURCE};
+use super::prores::ProResProfile;
+use super::record::{
+    BitDepth, ClipTags, Container, FrameRateMode, RecordCommand, RecordEvent, RecordSettings,
+    RecordingId, Resolution, VideoEncoder, resolve_ten_bit, target_rate,
+};
+use super::sharpen::{SharpenFilter, SharpenSettings};
+use super::silence::SilenceSettings;
+use super::timecode::{FrameRate, TimecodeSource};
+use super::{
+    LoopExit, PreviewFrame, SHUTDOWN_TIMEOUT, connect_preview, run_loop, spawn_gst_thread,
+    wait_for_reconnect,
+};
+use crate::file::naming::Slate;
+use crate::file::preflight::{BlockReason, Suggestion};
+use crate::file::sidecar::{Sidecar, sidecar_path};
+
+const RECORD_FOR: Duration = Duration::from_secs(2);
+/// 起停都是异步的, 实际时长会有些偏差.
+const DURATION_TOLERANCE: Duration = Duration::from_millis(700);
+const EVENT_TIMEOUT: Duration = Duration::from_secs(15);
+const RES: Resolution = Resolution {
+    width: 640,
+    height: 360,
+};
+
+fn test_sources() -> CaptureSources {
+    CaptureSources {
+        video: format!("videotestsrc name={} is-live=true", SOURCE),
+        audio: "audiotestsrc is-live=true".to_string(),
+        screen_portal: None,
+        switch: None,
+        pip: None,
+    }
+}
+
+fn wait_for(
+    rx: &mut mpsc::UnboundedReceiver<RecordEvent>,
+    mut matches: impl FnMut(&RecordEvent) -> bool,
+) -> RecordEvent {
+    let deadline = Instant::now() + EVENT_TIMEOUT;
+    while Instant::now() < deadline {
+        match rx.try_recv() {
+            Ok(RecordEvent::Error(e)) => panic!("recording failed: {}", e),
+            Ok(event) if matches(&event) => return event,
+            Ok(_) => {}
+            Err(_) => std::thread::sleep(Duration::from_millis(20)),
+        }
+    }
+    panic!("timed out waiting for recording event");
+}
+
+/// 每次调用都不同: 已经存在的文件会让开始录制被拦下, 并行的测试不能共用.
+fn output_path(enc: VideoEncoder, container: Container) -> PathBuf {
+    static NEXT: AtomicU32 = AtomicU32::new(0);
+    std::env::temp_dir().join(format!(
+        "cam-ui-test-{}-{:?}-{}-{}.{}",
+        enc.to_string().replace(' ', "-"),
+        container,
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed),
+        container.extension()
+    ))
+}
+
+fn record_and_validate(enc: VideoEncoder, container: Container) {
+    let (path, frames) = record_clip(enc, container, test_sources());
+    let result = validate(&path, enc, container).and_then(|()| check_frames(enc, frames));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(sidecar_path(&path));
+    result.unwrap();
+}
+
+/// 写进测试录像的元数据, MOV 和 MP4 读回来检查.
+const TEST_ARTIST: &str = "Test Operator";
+const TEST_DEVICE: &str = "Test Camera";
+
+fn settings(enc: VideoEncoder, container: Container, path: PathBuf) -> RecordSettings {
+    RecordSettings {
+        res: RES,
+        enc,
+        container,
+        bitrate_kbps: 1024,
+        av1_speed: 10,
+        ffv1_slices: 4,
+        mjpeg_quality: 85,
+        bit_depth: BitDepth::Auto,
+        timecode: TimecodeSource::Zero,
+        frame_rate: FrameRateMode::Source,
+        filepath: path,
+        tags: ClipTags {
+            artist: TEST_ARTIST.to_string(),
+            device_model: TEST_DEVICE.to_string(),
+            location: None,
+        },
+        slate: Slate::default(),
+        profile: None,
+    }
+}
+
+/// 在后台线程上用给定的采集源和叠加设置运行 [run_loop].
+fn spawn_loop(
+    sources: CaptureSources,
+    overlay: SharedOverlay,
+) -> (
+    mpsc::UnboundedSender<RecordCommand>,
+    mpsc::UnboundedReceiver<RecordEvent>,
+) {
+    gst::init().unwrap();
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+    let buffer = FrameExchange::default();
+    let level = Arc::new(Mutex::new(-60.0f32));
+    std::thread::spawn(move || {
+        let (pipeline, handles) = PipelineBuilder::new(sources).build().unwrap();
+        connect_preview(&handles, buffer, overlay.clone(), event_tx.clone());
+        run_loop(
+            &pipeline,
+            &handles,
+            level,
+            Default::default(),
+            overlay,
+            &mut cmd_rx,
+            event_tx,
+        );
+    });
+    (cmd_tx, event_rx)
+}
+
+/// 用给定的采集源录一段, 返回文件路径和帧计数.
+fn record_clip(
+    enc: VideoEncoder,
+    container: Container,
+    sources: CaptureSources,
+) -> (PathBuf, FrameStats) {
+    record_clip_with(enc, container, sources, SharedOverlay::default())
+}
+
+fn record_clip_with(
+    enc: VideoEncoder,
+    container: Container,
+    sources: CaptureSources,
+    overlay: SharedOverlay,
+) -> (PathBuf, FrameStats) {
+    let path = output_path(enc, container);
+    let frames = record_settings(settings(enc, container, path.clone()), sources, overlay);
+    (path, frames)
+}
+
+fn record_settings(
+    settings: RecordSettings,
+    sources: CaptureSources,
+    overlay: SharedOverlay,
+) -> FrameStats {
+    let (cmd_tx, mut event_rx) = spawn_loop(sources, overlay);
+    let id = RecordingId::MAIN;
+    cmd_tx.send(RecordCommand::Start { id, settings }).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Started { .. }));
+    std::thread::sleep(RECORD_FOR);
+    cmd_tx.send(RecordCommand::Stop { id }).unwrap();
+    let RecordEvent::Stopped { frames, .. } =
+        wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Stopped { .. }))
+    else {
+        unreachable!()
+    };
+    frames
+}
+
+/// 只有帧内编码时每一帧都对应一个输出, 进入队列的帧要么编码要么计为丢弃,
+/// 再加上 videorate 重复和跳过的帧.
+fn check_frames(enc: VideoEncoder, frames: FrameStats) -> Result<(), String> {
+    let intra = matches!(
+        enc,
+        VideoEncoder::Mjpeg | VideoEncoder::ProRes { .. } | VideoEncoder::Ffv1
+    );
+    if frames.entered == 0 {
+        return Err("no frames entered the recording queue".to_string());
+    }
+    if intra && frames.encoded + frames.dropped + frames.skipped != frames.entered + frames.repeated
+    {
+        return Err(format!("frame counts don't add up: {:?}", frames));
+    }
+    Ok(())
+}
+
+fn validate(path: &Path, enc: VideoEncoder, container: Container) -> Result<(), String> {
+    let uri = gst::glib::filename_to_uri(path, None).map_err(|e| e.to_string())?;
+    let discoverer = gst_pbutils::Discoverer::new(gst::ClockTime::from_seconds(10))
+        .map_err(|e| e.to_string())?;
+    let info = discoverer.discover_uri(&uri).map_err(|e| e.to_string())?;
+
+    let duration = Duration::from(info.duration().ok_or("no duration")?);
+    if duration.abs_diff(RECORD_FOR) > DURATION_TOLERANCE {
+        return Err(format!(
+            "duration {:?}, expected ~{:?}",
+            duration, RECORD_FOR
+        ));
+    }
+
+    let expected_container = match container {
+        Container::MP4 | Container::MOV => "video/quicktime",
+        Container::MKV => "video/x-matroska",
+        Container::WebM => "video/webm",
+        Container::AVI => "video/x-msvideo",
+    };
+    check_caps(
+        info.stream_info().and_then(|s| s.caps()),
+        expected_container,
+    )?;
+
+    let video = info.video_streams();
+    let video = video.first().ok_or("no video stream")?;
+    let expected_codec = match enc {
+        VideoEncoder::H264 => "video/x-h264",
+        VideoEncoder::H265 => "video/x-h265",
+        VideoEncoder::Av1 => "video/x-av1",
+        VideoEncoder::Vp9 => "video/x-vp9",
+        VideoEncoder::ProRes { .. } => "video/x-prores",
+        VideoEncoder::Ffv1 => "video/x-ffv",
+        VideoEncoder::Mjpeg => "image/jpeg",
+    };
+    check_caps(video.caps(), expected_codec)?;
+    // QuickTime 和手机只播放 avc1 / hvc1, 不认 hev1
+    let expected_format = match enc {
+        VideoEncoder::H264 => Some("avc"),
+        VideoEncoder::H265 => Some("hvc1"),
+        VideoEncoder::Av1 => Some("obu-stream"),
+        VideoEncoder::Vp9
+        | VideoEncoder::ProRes { .. }
+        | VideoEncoder::Ffv1
+        | VideoEncoder::Mjpeg => None,
+    };
+    let format = video
+        .caps()
+        .and_then(|c| {
+            c.structure(0)
+                .and_then(|s| s.get::<String>("stream-format").ok())
+        })
+        .unwrap_or_default();
+    if let Some(expected_format) = expected_format
+        && format != expected_format
+    {
+        return Err(format!(
+            "stream-format '{}', expected '{}'",
+            format, expected_format
+        ));
+    }
+    // 再直接查 sample entry 的 fourcc, 不依赖 demuxer 的转换.
+    // 只在 moov 中找, 压缩数据里碰巧出现的字节不算
+    let has_fourcc = |fourcc: &[u8]| -> Result<bool, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let moov = top_level_box(&bytes, b"moov").ok_or("no moov box")?;
+        Ok(moov.windows(4).any(|w| w == fourcc))
+    };
+    if enc == VideoEncoder::H265
+        && container != Container::MKV
+        && (!has_fourcc(b"hvc1")? || has_fourcc(b"hev1")?)
+    {
+        return Err("sample entry is not hvc1".to_string());
+    }
+    // qtmux 按 timecodestamper 的 meta 写入时间码轨道
+    if container == Container::MOV && !has_fourcc(b"tmcd")? {
+        return Err("no timecode track".to_string());
+    }
+    if let VideoEncoder::ProRes { profile } = enc
+        && !has_fourcc(prores_fourcc(profile))?
+    {
+        return Err(format!(
+            "sample entry is not {}",
+            String::from_utf8_lossy(prores_fourcc(profile))
+        ));
+    }
+    if (video.width(), video.height()) != (RES.width, RES.height) {
+        return Err(format!("resolution {}x{}", video.width(), video.height()));
+    }
+    if matches!(container, Container::MOV | Container::MP4) {
+        check_tags(&info)?;
+    }
+
+    let audio = info.audio_streams();
+    let audio = audio.first().ok_or("no audio stream")?;
+    let expected_audio = match (enc, container) {
+        (VideoEncoder::Ffv1, _) => "audio/x-flac",
+        (_, Container::WebM) => "audio/x-opus",
+        (_, Container::AVI) => "audio/x-raw",
+        _ => "audio/mpeg",
+    };
+    check_caps(audio.caps(), expected_audio)
+}
+
+/// 录制时写入的创建时间, 操作员, 设备和软件名经过 qtmux / mp4mux 后还在.
+fn check_tags(info: &gst_pbutils::DiscovererInfo) -> Result<(), String> {
+    let mut tags = info.tags().ok_or("no tags")?;
+    if let Some(container) = info.stream_info().and_then(|s| s.tags()) {
+        tags = tags.merge(&container, gst::TagMergeMode::Keep);
+    }
+    if tags.get::<gst::tags::DateTime>().is_none() {
+        return Err(format!("no creation time in {}", tags));
+    }
+    let artist = tags.get::<gst::tags::Artist>().map(|v| v.get().to_string());
+    if artist.as_deref() != Some(TEST_ARTIST) {
+        return Err(format!("artist {:?}", artist));
+    }
+    let model = tags
+        .get::<gst::tags::DeviceModel>()
+        .map(|v| v.get().to_string());
+    if model.as_deref() != Some(TEST_DEVICE) {
+        return Err(format!("device model {:?}", model));
+    }
+    // 编码器元素也会写 encoder 标签, 任何一个是本程序就算
+    let software = tags
+        .iter_tag::<gst::tags::Encoder>()
+        .chain(tags.iter_tag::<gst::tags::ApplicationName>())
+        .any(|v| v.get().starts_with("cam-ui "));
+    if !software {
+        return Err(format!("no software tag in {}", tags));
+    }
+    Ok(())
+}
+
+/// ISO BMFF 文件中指定类型的顶层 box 的内容.
+fn top_level_box<'a>(mut bytes: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    while bytes.len() >= 8 {
+        let size = u32::from_be_bytes(bytes[..4].try_into().ok()?) as u64;
+        let (header, size) = match size {
+            // 64 位的 largesize
+            1 => (16, u64::from_be_bytes(bytes.get(8..16)?.try_into().ok()?)),
+            // 一直到文件末尾
+            0 => (8, bytes.len() as u64),
+            size => (8, size),
+        };
+        let size = usize::try_from(size).ok().filter(|s| *s >= header)?;
+        let body = bytes.get(header..size)?;
+        if &bytes[4..8] == kind {
+            return Some(body);
+        }
+        bytes = &bytes[size..];
+    }
+    None
+}
+
+/// MOV 中 ProRes sample entry 的 fourcc.
+fn prores_fourcc(profile: ProResProfile) -> &'static [u8; 4] {
+    match profile {
+        ProResProfile::Proxy => b"apco",
+        ProResProfile::Lt => b"apcs",
+        ProResProfile::Standard => b"apcn",
+        ProResProfile::Hq => b"apch",
+    }
+}
+
+fn check_caps(caps: Option<gst::Caps>, expected: &str) -> Result<(), String> {
+    let name = caps
+        .as_ref()
+        .and_then(|c| c.structure(0))
+        .map(|s| s.name().to_string())
+        .unwrap_or_default();
+    if name == expected {
+        Ok(())
+    } else {
+        Err(format!("caps '{}', expected '{}'", name, expected))
+    }
+}
+
+#[test]
+#[ignore = "needs GStreamer base/good plugins"]
+fn loop_ignores_stray_stop_and_exits_when_commands_close() {
+    gst::init().unwrap();
+    let (pipeline, handles) = PipelineBuilder::new(test_sources()).build().unwrap();
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+
+    cmd_tx
+        .send(RecordCommand::Stop {
+            id: RecordingId::MAIN,
+        })
+        .unwrap();
+    drop(cmd_tx);
+    // 在当前线程运行, 通道关闭后应当返回
+    let level = Arc::new(Mutex::new(-60.0f32));
+    run_loop(
+        &pipeline,
+        &handles,
+        level,
+        Default::default(),
+        Default::default(),
+        &mut cmd_rx,
+        event_tx,
+    );
+    assert!(event_rx.try_recv().is_err());
+}
+
+/// 指令不再等下一次轮询, 空闲的管线也应当立即应答退出.
+#[test]
+#[ignore = "needs GStreamer base/good plugins"]
+fn loop_answers_shutdown_without_waiting_for_bus() {
+    gst::init().unwrap();
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    let buffer = FrameExchange::default();
+    let level = Arc::new(Mutex::new(-60.0f32));
+    let looping = std::thread::spawn(move || {
+        let (pipeline, handles) = PipelineBuilder::new(test_sources()).build().unwrap();
+        connect_preview(&handles, buffer, Default::default(), event_tx.clone());
+        run_loop(
+            &pipeline,
+            &handles,
+            level,
+            Default::default(),
+            Default::default(),
+            &mut cmd_rx,
+            event_tx,
+        )
+    });
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::PreviewStarted));
+
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    cmd_tx.send(RecordCommand::Shutdown(done_tx)).unwrap();
+    done_rx.recv_timeout(Duration::from_secs(2)).unwrap();
+    assert_eq!(looping.join().unwrap(), LoopExit::Closed);
+}
+
+#[test]
+#[ignore = "needs GStreamer base/good plugins"]
+fn gst_thread_exits_when_commands_close() {
+    gst::init().unwrap();
+    let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    let thread = spawn_gst_thread(
+        test_sources(),
+        FrameExchange::default(),
+        Default::default(),
+        Default::default(),
+        Arc::new(Mutex::new(-60.0f32)),
+        Default::default(),
+        cmd_rx,
+        event_tx,
+    );
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::PreviewStarted));
+    drop(cmd_tx);
+    assert!(thread.join(SHUTDOWN_TIMEOUT));
+}
+
+/// 界面频繁修改, 甚至一直持有叠加设置的锁时, 预览帧照常送达.
+#[test]
+#[ignore = "needs GStreamer base/good plugins"]
+fn overlay_updates_never_stall_frames() {
+    gst::init().unwrap();
+    let overlay = SharedOverlay::default();
+    let buffer = FrameExchange::<PreviewFrame>::default();
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    {
+        let overlay = overlay.clone();
+        let buffer = buffer.clone();
+        std::thread::spawn(move || {
+            let (pipeline, handles) = PipelineBuilder::new(test_sources()).build().unwrap();
+            connect_preview(&handles, buffer, overlay.clone(), event_tx.clone());
+            run_loop(
+                &pipeline,
+                &handles,
+                Arc::new(Mutex::new(-60.0f32)),
+                Default::default(),
+                overlay.clone(),
+                &mut cmd_rx,
+                event_tx,
+            );
+        });
+    }
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::PreviewStarted));
+    // 取走最新的帧, 记住最后看到的序号
+    let mut last = 0;
+    let mut sequence = || {
+        if let Some(frame) = buffer.take() {
+            last = frame.sequence;
+        }
+        last
+    };
+
+    let toggler = {
+        let overlay = overlay.clone();
+        std::thread::spawn(move || {
+            let until = Instant::now() + Duration::from_secs(1);
+            let mut thirds = false;
+            while Instant::now() < until {
+                overlay.set(OverlayConfig {
+                    thirds,
+                    meter: MeterOverlay {
+                        enabled: !thirds,
+                        ..Default::default()
+                    },
+                });
+                thirds = !thirds;
+            }
+        })
+    };
+    let before = sequence();
+    toggler.join().unwrap();
+    assert!(sequence() > before + 10, "frames stalled while toggling");
+
+    // 拿不到锁时沿用上一帧的设置
+    let held = overlay.lock();
+    let before = sequence();
+    std::thread::sleep(Duration::from_millis(500));
+    assert!(
+        sequence() > before + 5,
+        "frames stalled while the lock was held"
+    );
+    drop(held);
+    drop(cmd_tx);
+}
+
+/// 运行中开关降噪和修改强度, 预览不中断.
+#[test]
+#[ignore = "needs GStreamer base/good plugins"]
+fn denoise_toggles_without_stalling_frames() {
+    gst::init().unwrap();
+    let buffer = FrameExchange::<PreviewFrame>::default();
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    {
+        let buffer = buffer.clone();
+        std::thread::spawn(move || {
+            let (pipeline, handles) = PipelineBuilder::new(test_sources())
+                .denoise(Some(DenoiseFilter::Median))
+                .build()
+                .unwrap();
+            connect_preview(&handles, buffer, Default::default(), event_tx.clone());
+            run_loop(
+                &pipeline,
+                &handles,
+                Arc::new(Mutex::new(-60.0f32)),
+                Default::default(),
+                Default::default(),
+                &mut cmd_rx,
+                event_tx,
+            );
+        });
+    }
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::PreviewStarted));
+    // 取走最新的帧, 记住最后看到的序号
+    let mut last = 0;
+    let mut sequence = || {
+        if let Some(frame) = buffer.take() {
+            last = frame.sequence;
+        }
+        last
+    };
+
+    for (enabled, strength) in [
+        (true, DenoiseStrength::Light),
+        (true, DenoiseStrength::Strong),
+        (false, DenoiseStrength::Strong),
+    ] {
+        let settings = DenoiseSettings { enabled, strength };
+        cmd_tx.send(RecordCommand::Denoise(settings)).unwrap();
+        let before = sequence();
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(sequence() > before + 5, "frames stalled at {:?}", settings);
+    }
+    // 测试源的分辨率不超过上限, 不应当有警告
+    while let Ok(event) = event_rx.try_recv() {
+        assert!(!matches!(event, RecordEvent::Warning(_)), "{:?}", event);
+    }
+    drop(cmd_tx);
+}
+
+#[test]
+fn reconnect_wait_ends_on_shutdown() {
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    cmd_tx
+        .send(RecordCommand::Start {
+            id: RecordingId::MAIN,
+            settings: settings(VideoEncoder::H264, Container::MP4, "unused.mp4".into()),
+        })
+        .unwrap();
+    cmd_tx.send(RecordCommand::Shutdown(done_tx)).unwrap();
+
+    let started = Instant::now();
+    assert!(!wait_for_reconnect(
+        &mut cmd_rx,
+        &event_tx,
+        Duration::from_secs(60)
+    ));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(done_rx.try_recv().is_ok());
+    assert!(matches!(event_rx.try_recv(), Ok(RecordEvent::Error(_))));
+
+    // 没有指令时等满延迟后返回 true
+    let (_cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    assert!(wait_for_reconnect(
+        &mut cmd_rx,
+        &event_tx,
+        Duration::from_millis(20)
+    ));
+}
+
+/// 两个录像同时进行, 倒序停止, 各自的收尾互不影响.
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn concurrent_recordings_stop_independently() {
+    let clips = [
+        (RecordingId(1), VideoEncoder::H264, Container::MP4),
+        (RecordingId(2), VideoEncoder::Mjpeg, Container::MKV),
+    ];
+    let (cmd_tx, mut event_rx) = spawn_loop(test_sources(), SharedOverlay::default());
+    for (id, enc, container) in clips {
+        let path = output_path(enc, container);
+        cmd_tx
+            .send(RecordCommand::Start {
+                id,
+                settings: settings(enc, container, path),
+            })
+            .unwrap();
+        wait_for(
+            &mut event_rx,
+            |e| matches!(e, RecordEvent::Started { id: started, .. } if *started == id),
+        );
+    }
+    std::thread::sleep(RECORD_FOR);
+    for (id, _, _) in clips.iter().rev() {
+        cmd_tx.send(RecordCommand::Stop { id: *id }).unwrap();
+    }
+
+    let mut stopped = Vec::new();
+    while stopped.len() < clips.len() {
+        if let RecordEvent::Stopped { id, path, .. } =
+            wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Stopped { .. }))
+        {
+            stopped.push((id, path));
+        }
+    }
+    for (id, enc, container) in clips {
+        let (_, path) = stopped
+            .iter()
+            .find(|(stopped, _)| *stopped == id)
+            .expect("every recording reports its own Stopped");
+        let result = validate(path, enc, container);
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(sidecar_path(path));
+        result.unwrap();
+    }
+}
+
+/// 烧录电平表时录制分支多了 cairooverlay 和一次格式转换, 文件仍然正常.
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn burned_in_meter_keeps_the_file_valid() {
+    let overlay = SharedOverlay::default();
+    overlay.set(OverlayConfig {
+        meter: MeterOverlay {
+            enabled: true,
+            burn_in: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    let (path, frames) =
+        record_clip_with(VideoEncoder::H264, Container::MP4, test_sources(), overlay);
+    let result = validate(&path, VideoEncoder::H264, Container::MP4)
+        .and_then(|()| check_frames(VideoEncoder::H264, frames));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(sidecar_path(&path));
+    result.unwrap();
+}
+
+/// 彩条期间拒绝录制, 切回摄像头后预览照常.
+#[test]
+#[ignore = "needs GStreamer base/good plugins"]
+fn bars_block_recording_until_switched_back() {
+    let (cmd_tx, mut event_rx) = spawn_loop(test_sources(), SharedOverlay::default());
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::PreviewStarted));
+    cmd_tx
+        .send(RecordCommand::Bars(Some("CAM A".to_string())))
+        .unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Bars(true)));
+
+    let path = output_path(VideoEncoder::H264, Container::MP4);
+    let id = RecordingId::MAIN;
+    let start = RecordCommand::Start {
+        id,
+        settings: settings(VideoEncoder::H264, Container::MP4, path.clone()),
+    };
+    cmd_tx.send(start.clone()).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Error(_)));
+    assert!(!path.exists());
+
+    cmd_tx.send(RecordCommand::Bars(None)).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Bars(false)));
+    cmd_tx.send(start).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Started { .. }));
+    cmd_tx.send(RecordCommand::Stop { id }).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Stopped { .. }));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(sidecar_path(&path));
+}
+
+/// 同名的文件已经存在时不构建录制分支, 带回设置和不冲突的文件名, 改名后可以开始.
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn existing_file_blocks_start_until_renamed() {
+    let (cmd_tx, mut event_rx) = spawn_loop(test_sources(), SharedOverlay::default());
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::PreviewStarted));
+    let path = output_path(VideoEncoder::H264, Container::MP4);
+    std::fs::write(&path, b"keep me").unwrap();
+    let id = RecordingId::MAIN;
+    let settings = settings(VideoEncoder::H264, Container::MP4, path.clone());
+    cmd_tx.send(RecordCommand::Start { id, settings }).unwrap();
+    let mut blocked = None;
+    wait_for(&mut event_rx, |e| match e {
+        RecordEvent::StartBlocked {
+            settings,
+            reason,
+            suggestion,
+            ..
+        } => {
+            blocked = Some((settings.clone(), reason.clone(), suggestion.clone()));
+            true
+        }
+        _ => false,
+    });
+    let (mut settings, reason, suggestion) = blocked.unwrap();
+    assert_eq!(reason, BlockReason::Exists(path.clone()));
+    let Suggestion::Rename(renamed) = suggestion else {
+        panic!("expected a new name, got {:?}", suggestion);
+    };
+    assert_eq!(std::fs::read(&path).unwrap(), b"keep me");
+
+    settings.filepath = renamed.clone();
+    cmd_tx
+        .send(RecordCommand::Start {
+            id,
+            settings: *settings,
+        })
+        .unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Started { .. }));
+    cmd_tx.send(RecordCommand::Stop { id }).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Stopped { .. }));
+    for p in [&path, &renamed] {
+        let _ = std::fs::remove_file(p);
+        let _ = std::fs::remove_file(sidecar_path(p));
+    }
+}
+
+/// 只锐化录像时录制分支多了锐化元素, 文件仍然正常, 设置写进 sidecar.
+#[test]
+#[ignore = "needs GStreamer encoder plugins and frei0r or gaussianblur"]
+fn recording_only_sharpen_is_recorded_in_the_sidecar() {
+    gst::init().unwrap();
+    let filter = SharpenFilter::detect().expect("no sharpen filter");
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let (pipeline, handles) = PipelineBuilder::new(test_sources())
+            .sharpen(Some(filter))
+            .build()
+            .unwrap();
+        connect_preview(
+            &handles,
+            FrameExchange::default(),
+            Default::default(),
+            event_tx.clone(),
+        );
+        run_loop(
+            &pipeline,
+            &handles,
+            Arc::new(Mutex::new(-60.0f32)),
+            Default::default(),
+            Default::default(),
+            &mut cmd_rx,
+            event_tx,
+        );
+    });
+    let sharpen = SharpenSettings {
+        enabled: true,
+        ..Default::default()
+    };
+    cmd_tx.send(RecordCommand::Sharpen(sharpen)).unwrap();
+    let path = output_path(VideoEncoder::H264, Container::MP4);
+    let id = RecordingId::MAIN;
+    let settings = settings(VideoEncoder::H264, Container::MP4, path.clone());
+    cmd_tx.send(RecordCommand::Start { id, settings }).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Started { .. }));
+    std::thread::sleep(RECORD_FOR);
+    cmd_tx.send(RecordCommand::Stop { id }).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Stopped { .. }));
+
+    let result = validate(&path, VideoEncoder::H264, Container::MP4).and_then(|()| {
+        let sidecar = Sidecar::read(&path).map_err(|e| e.to_string())?;
+        match sidecar.settings.sharpen {
+            Some(label) if label.starts_with(filter.element()) => Ok(()),
+            other => Err(format!("sidecar sharpen {:?}", other)),
+        }
+    });
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(sidecar_path(&path));
+    result.unwrap();
+}
+
+/// 无声的音频源录制中会提示, 无声片段写进 sidecar.
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn silent_audio_is_reported_and_recorded_in_the_sidecar() {
+    gst::init().unwrap();
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel();
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let sources = CaptureSources {
+            audio: "audiotestsrc is-live=true wave=silence".to_string(),
+            ..test_sources()
+        };
+        let (pipeline, handles) = PipelineBuilder::new(sources).build().unwrap();
+        connect_preview(
+            &handles,
+            FrameExchange::default(),
+            Default::default(),
+            event_tx.clone(),
+        );
+        run_loop(
+            &pipeline,
+            &handles,
+            Arc::new(Mutex::new(-60.0f32)),
+            Default::default(),
+            Default::default(),
+            &mut cmd_rx,
+            event_tx,
+        );
+    });
+    let silence = SilenceSettings {
+        after_secs: 1,
+        ..Default::default()
+    };
+    cmd_tx.send(RecordCommand::Silence(silence)).unwrap();
+    let path = output_path(VideoEncoder::H264, Container::MP4);
+    let id = RecordingId::MAIN;
+    let settings = settings(VideoEncoder::H264, Container::MP4, path.clone());
+    cmd_tx.send(RecordCommand::Start { id, settings }).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Started { .. }));
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Silence(true)));
+    cmd_tx.send(RecordCommand::Stop { id }).unwrap();
+    wait_for(&mut event_rx, |e| matches!(e, RecordEvent::Stopped { .. }));
+
+    let sidecar = Sidecar::read(&path);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(sidecar_path(&path));
+    let silence = sidecar.unwrap().silence;
+    assert_eq!(silence.len(), 1, "{:?}", silence);
+    assert!(silence[0].start_secs < 1.0);
+    assert_eq!(silence[0].end_secs, None);
+}
+
+macro_rules! record_tests {
+    ($($name:ident: $enc:ident, $container:ident;)*) => {
+        $(
+            #[test]
+            #[ignore = "needs GStreamer encoder plugins"]
+            fn $name() {
+                record_and_validate(VideoEncoder::$enc, Container::$container);
+            }
+        )*
+    };
+}
+
+#[test]
+fn constant_rate_falls_back_when_the_source_is_variable() {
+    let pal = FrameRate { num: 25, den: 1 };
+    assert_eq!(target_rate(FrameRateMode::Source, Some(pal)), Some(pal));
+    assert_eq!(
+        target_rate(FrameRateMode::Source, None),
+        Some(FrameRate { num: 30, den: 1 })
+    );
+    assert_eq!(
+        target_rate(FrameRateMode::Fixed(50), Some(pal)),
+        Some(FrameRate { num: 50, den: 1 })
+    );
+    assert_eq!(target_rate(FrameRateMode::Variable, Some(pal)), None);
+}
+
+/// 采集源随机丢帧时, 恒定帧率的录像每一帧的时长仍然相同.
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn constant_rate_evens_out_frame_durations() {
+    let sources = CaptureSources {
+        video: format!(
+            "videotestsrc name={} is-live=true ! video/x-raw,framerate=30/1 ! \
+             identity drop-probability=0.3",
+            SOURCE
+        ),
+        ..test_sources()
+    };
+    let path = output_path(VideoEncoder::H264, Container::MP4);
+    let frames = record_settings(
+        RecordSettings {
+            frame_rate: FrameRateMode::Fixed(25),
+            ..settings(VideoEncoder::H264, Container::MP4, path.clone())
+        },
+        sources,
+        SharedOverlay::default(),
+    );
+    let pts = frame_timestamps(&format!(
+        "filesrc location={} ! qtdemux ! video/x-h264 ! appsink name=sink sync=false",
+        path.display()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(sidecar_path(&path));
+
+    assert!(frames.repeated > 0, "{:?}", frames);
+    assert!(pts.len() > 25, "only {} frames", pts.len());
+    let frame = Duration::from_millis(40);
+    for pair in pts.windows(2) {
+        let step = pair[1] - pair[0];
+        assert!(
+            step.abs_diff(frame) < Duration::from_millis(1),
+            "frame lasted {:?}",
+            step
+        );
+    }
+}
+
+/// 管线里 appsink 收到的各帧的时间戳, 按先后排序.
+fn frame_timestamps(description: &str) -> Vec<Duration> {
+    let pipeline = gst::parse::launch(description)
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+    let sink = pipeline
+        .by_name("sink")
+        .unwrap()
+        .downcast::<gstreamer_app::AppSink>()
+        .unwrap();
+    pipeline.set_state(gst::State::Playing).unwrap();
+    let mut pts = Vec::new();
+    while let Some(sample) = sink.try_pull_sample(gst::ClockTime::from_seconds(10)) {
+        if let Some(t) = sample.buffer().and_then(|b| b.pts()) {
+            pts.push(Duration::from(t));
+        }
+    }
+    pipeline.set_state(gst::State::Null).unwrap();
+    pts.sort();
+    pts
+}
+
+#[test]
+fn ten_bit_is_rejected_for_h264() {
+    assert!(resolve_ten_bit(BitDepth::Ten, true, VideoEncoder::H264).is_err());
+    assert_eq!(
+        resolve_ten_bit(BitDepth::Auto, true, VideoEncoder::H264),
+        Ok(false)
+    );
+    assert_eq!(
+        resolve_ten_bit(BitDepth::Auto, true, VideoEncoder::H265),
+        Ok(true)
+    );
+    assert_eq!(
+        resolve_ten_bit(BitDepth::Auto, false, VideoEncoder::H265),
+        Ok(false)
+    );
+}
+
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn h265_keeps_ten_bit_sources() {
+    let sources = CaptureSources {
+        video: format!(
+            "videotestsrc name={} is-live=true ! video/x-raw,format=I420_10LE",
+            SOURCE
+        ),
+        ..test_sources()
+    };
+    let (path, _) = record_clip(VideoEncoder::H265, Container::MKV, sources);
+    let result = validate(&path, VideoEncoder::H265, Container::MKV).and_then(|()| {
+        let profile = video_caps(&path)?
+            .structure(0)
+            .and_then(|s| s.get::<String>("profile").ok())
+            .unwrap_or_default();
+        if profile == "main-10" {
+            Ok(())
+        } else {
+            Err(format!("profile '{}', expected 'main-10'", profile))
+        }
+    });
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(sidecar_path(&path));
+    result.unwrap();
+}
+
+fn video_caps(path: &Path) -> Result<gst::Caps, String> {
+    let uri = gst::glib::filename_to_uri(path, None).map_err(|e| e.to_string())?;
+    let discoverer = gst_pbutils::Discoverer::new(gst::ClockTime::from_seconds(10))
+        .map_err(|e| e.to_string())?;
+    let info = discoverer.discover_uri(&uri).map_err(|e| e.to_string())?;
+    info.video_streams()
+        .first()
+        .and_then(|v| v.caps())
+        .ok_or_else(|| "no video stream".to_string())
+}
+
+/// 静止的彩条, 分辨率和格式与录制一致, 录制分支不会缩放或转换.
+const LOSSLESS_CAPS: &str = "video/x-raw,format=I420,width=640,height=360,framerate=30/1";
+
+/// 管线里 appsink 收到的第一帧的像素.
+fn first_frame(description: &str) -> Vec<u8> {
+    let pipeline = gst::parse::launch(description)
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+    let sink = pipeline
+        .by_name("sink")
+        .unwrap()
+        .downcast::<gstreamer_app::AppSink>()
+        .unwrap();
+    pipeline.set_state(gst::State::Paused).unwrap();
+    let sample = sink
+        .try_pull_preroll(gst::ClockTime::from_seconds(10))
+        .expect("no frame");
+    let pixels = sample.buffer().unwrap().map_readable().unwrap().to_vec();
+    pipeline.set_state(gst::State::Null).unwrap();
+    pixels
+}
+
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn ffv1_decodes_to_identical_frames() {
+    let sources = CaptureSources {
+        video: format!(
+            "videotestsrc name={} is-live=true pattern=smpte ! {}",
+            SOURCE, LOSSLESS_CAPS
+        ),
+        ..test_sources()
+    };
+    let (path, frames) = record_clip(VideoEncoder::Ffv1, Container::MKV, sources);
+    let result = validate(&path, VideoEncoder::Ffv1, Container::MKV)
+        .and_then(|()| check_frames(VideoEncoder::Ffv1, frames));
+    let decoded = first_frame(&format!(
+        "filesrc location={} ! matroskademux ! avdec_ffv1 ! {} ! appsink name=sink",
+        path.display(),
+        LOSSLESS_CAPS
+    ));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(sidecar_path(&path));
+    result.unwrap();
+
+    let reference = first_frame(&format!(
+        "videotestsrc pattern=smpte ! {} ! appsink name=sink",
+        LOSSLESS_CAPS
+    ));
+    assert_eq!(decoded.len(), reference.len());
+    assert!(
+        decoded == reference,
+        "decoded frame differs from the source"
+    );
+}
+
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn prores_proxy_mov() {
+    let enc = VideoEncoder::ProRes {
+        profile: ProResProfile::Proxy,
+    };
+    record_and_validate(enc, Container::MOV);
+}
+
+#[test]
+#[ignore = "needs GStreamer encoder plugins"]
+fn prores_hq_mov() {
+    let enc = VideoEncoder::ProRes {
+        profile: ProResProfile::Hq,
+    };
+    record_and_validate(enc, Container::MOV);
+}
+
+record_tests! {
+    h264_mp4: H264, MP4;
+    h264_mov: H264, MOV;
+    h264_mkv: H264, MKV;
+    h265_mp4: H265, MP4;
+    h265_mov: H265, MOV;
+    h265_mkv: H265, MKV;
+    av1_mp4: Av1, MP4;
+    av1_mkv: Av1, MKV;
+    vp9_mp4: Vp9, MP4;
+    vp9_mkv: Vp9, MKV;
+    vp9_webm: Vp9, WebM;
+    mjpeg_mkv: Mjpeg, MKV;
+    mjpeg_avi: Mjpeg, AVI;
+}
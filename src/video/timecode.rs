@@ -0,0 +1,252 @@
+//! SMPTE 时间码. 录制分支中的 timecodestamper 从这里算出的起始值开始计数,
+//! qtmux 据此写入 tmcd 轨道, 界面显示的也是同一个值.
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// 最近一帧写入文件的时间码, 由录制分支更新, 界面读取.
+pub(crate) type SharedTimecode = Arc<Mutex<Option<Timecode>>>;
+
+/// 录制分支中 timecodestamper 的名字.
+pub(super) const STAMPER: &str = "tc";
+
+/// 起始时间码的来源.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub(crate) enum TimecodeSource {
+    /// 从开始录制时的本地时间开始.
+    #[default]
+    TimeOfDay,
+    /// 每个镜次都从 00:00:00:00 开始.
+    Zero,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FrameRate {
+    pub num: u32,
+    pub den: u32,
+}
+
+impl FrameRate {
+    /// 时间码每秒的帧数, 29.97 按 30 计.
+    pub fn nominal(self) -> u32 {
+        self.num.div_ceil(self.den)
+    }
+
+    /// 29.97 和 59.94 使用丢帧时间码, 其他非整数帧率 (如 23.976) 不丢帧.
+    pub fn is_drop_frame(self) -> bool {
+        self.den == 1001 && (self.num == 30000 || self.num == 60000)
+    }
+
+    /// 丢帧时间码每分钟跳过的帧号, 除了每第十分钟.
+    fn dropped_per_minute(self) -> u32 {
+        if self.is_drop_frame() {
+            self.nominal() / 15
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Timecode {
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub frames: u32,
+    pub drop_frame: bool,
+}
+
+impl Timecode {
+    /// 第 `count` 帧的时间码, 超过 24 小时后回到零.
+    pub fn from_frame_count(count: u64, rate: FrameRate) -> Self {
+        let nominal = rate.nominal() as u64;
+        let drop = rate.dropped_per_minute() as u64;
+        let mut count = count % (nominal * 86_400 - drop * 9 * 144);
+        if drop > 0 {
+            let per_ten_minutes = nominal * 600 - drop * 9;
+            let per_minute = nominal * 60 - drop;
+            let tens = count / per_ten_minutes;
+            let rem = count % per_ten_minutes;
+            count += drop * 9 * tens;
+            if rem > drop {
+                count += drop * ((rem - drop) / per_minute);
+            }
+        }
+        Self {
+            hours: (count / (nominal * 3600)) as u32,
+            minutes: (count / (nominal * 60) % 60) as u32,
+            seconds: (count / nominal % 60) as u32,
+            frames: (count % nominal) as u32,
+            drop_frame: drop > 0,
+        }
+    }
+
+    /// 当天过了 `seconds` 秒时的时间码. 丢帧时间码与实际时间基本一致.
+    pub fn from_time_of_day(seconds: f64, rate: FrameRate) -> Self {
+        let frames = seconds * rate.num as f64 / rate.den as f64;
+        Self::from_frame_count(frames as u64, rate)
+    }
+}
+
+/// 录制分支中编码器之前的 timecodestamper. 只计数, 起始值由 [attach] 设置.
+pub(super) fn stamper_description(rate: FrameRate) -> String {
+    format!(
+        "timecodestamper name={} source=internal set=always drop-frame={} !",
+        STAMPER,
+        rate.is_drop_frame()
+    )
+}
+
+/// 设置起始时间码, 并在 timecodestamper 的输出端记下每一帧的时间码.
+pub(super) fn attach(
+    stamper: &gst::Element,
+    rate: FrameRate,
+    start: Timecode,
+) -> Result<SharedTimecode, gst::glib::BoolError> {
+    let flags = if start.drop_frame {
+        gst_video::VideoTimeCodeFlags::DROP_FRAME
+    } else {
+        gst_video::VideoTimeCodeFlags::empty()
+    };
+    let tc = gst_video::ValidVideoTimeCode::new(
+        gst::Fraction::new(rate.num as i32, rate.den as i32),
+        None,
+        flags,
+        start.hours,
+        start.minutes,
+        start.seconds,
+        start.frames,
+        0,
+    )?;
+    stamper.set_property("set-internal-timecode", &tc);
+
+    let shared = SharedTimecode::default();
+    let latest = shared.clone();
+    let pad = stamper
+        .static_pad("src")
+        .ok_or_else(|| gst::glib::bool_error!("timecodestamper has no src pad"))?;
+    pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+        if let Some(meta) = info
+            .buffer()
+            .and_then(|b| b.meta::<gst_video::VideoTimeCodeMeta>())
+        {
+            let tc = meta.tc();
+            *latest.lock() = Some(Timecode {
+                hours: tc.hours(),
+                minutes: tc.minutes(),
+                seconds: tc.seconds(),
+                frames: tc.frames(),
+                drop_frame: tc
+                    .flags()
+                    .contains(gst_video::VideoTimeCodeFlags::DROP_FRAME),
+            });
+        }
+        gst::PadProbeReturn::Ok
+    });
+    Ok(shared)
+}
+
+impl std::fmt::Display for Timecode {
+    /// 丢帧时间码按惯例用分号分隔帧数.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sep = if self.drop_frame { ';' } else { ':' };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, sep, self.frames
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NTSC: FrameRate = FrameRate {
+        num: 30000,
+        den: 1001,
+    };
+    const PAL: FrameRate = FrameRate { num: 25, den: 1 };
+
+    /// [Timecode::from_frame_count] 的逆运算.
+    fn frame_count(tc: Timecode, rate: FrameRate) -> u64 {
+        let nominal = rate.nominal() as u64;
+        let drop = rate.dropped_per_minute() as u64;
+        let minutes = tc.hours as u64 * 60 + tc.minutes as u64;
+        (minutes * 60 + tc.seconds as u64) * nominal + tc.frames as u64
+            - drop * (minutes - minutes / 10)
+    }
+
+    #[test]
+    fn drop_frame_skips_two_numbers_each_minute() {
+        assert!(NTSC.is_drop_frame());
+        assert_eq!(
+            Timecode::from_frame_count(1799, NTSC).to_string(),
+            "00:00:59;29"
+        );
+        assert_eq!(
+            Timecode::from_frame_count(1800, NTSC).to_string(),
+            "00:01:00;02"
+        );
+        // 每第十分钟不丢帧
+        assert_eq!(
+            Timecode::from_frame_count(17982, NTSC).to_string(),
+            "00:10:00;00"
+        );
+        assert_eq!(
+            Timecode::from_frame_count(17983, NTSC).to_string(),
+            "00:10:00;01"
+        );
+        for count in [0, 1799, 1800, 17982, 107_892, 2_589_407] {
+            let tc = Timecode::from_frame_count(count, NTSC);
+            assert_eq!(frame_count(tc, NTSC), count, "{}", tc);
+        }
+    }
+
+    #[test]
+    fn integer_and_pulldown_rates_do_not_drop() {
+        assert_eq!(
+            Timecode::from_frame_count(90_000, PAL).to_string(),
+            "01:00:00:00"
+        );
+        let film = FrameRate {
+            num: 24000,
+            den: 1001,
+        };
+        assert!(!film.is_drop_frame());
+        assert_eq!(film.nominal(), 24);
+        assert_eq!(
+            Timecode::from_frame_count(24 * 60, film).to_string(),
+            "00:01:00:00"
+        );
+        let fifty_nine = FrameRate {
+            num: 60000,
+            den: 1001,
+        };
+        assert_eq!(
+            Timecode::from_frame_count(3600, fifty_nine).to_string(),
+            "00:01:00;04"
+        );
+    }
+
+    #[test]
+    fn time_of_day_wraps_at_midnight() {
+        assert_eq!(
+            Timecode::from_time_of_day(13.0 * 3600.0 + 0.5, PAL).to_string(),
+            "13:00:00:12"
+        );
+        // 丢帧时间码一小时后仍与实际时间一致
+        assert_eq!(
+            Timecode::from_time_of_day(3600.0, NTSC).to_string(),
+            "01:00:00;00"
+        );
+        assert_eq!(
+            Timecode::from_time_of_day(86_400.0, PAL).to_string(),
+            "00:00:00:00"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use chrono::Local;
+use gstreamer as gst;
+use gstreamer_pbutils as gst_pbutils;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use xxhash_rust::xxh3::Xxh3;
+
+use super::record::RecordEvent;
+use crate::file::drop_page_cache;
+use crate::file::sidecar::{Sidecar, Verification};
+
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+const DISCOVER_TIMEOUT_SECS: u64 = 30;
+
+/// 录像封装完成后在后台计算校验和并检查文件结构.
+/// 校验失败只记录在 sidecar 中, 不会删除任何文件.
+pub(super) fn spawn(path: PathBuf, event_tx: mpsc::UnboundedSender<RecordEvent>) {
+    std::thread::spawn(move || {
+        // 必须在创建 Discoverer 之前设置, 它内部的线程会继承优先级
+        lower_priority();
+
+        let verification = verify(&path);
+        if let Err(e) =
+            Sidecar::update(&path, |meta| meta.verification = Some(verification.clone()))
+        {
+            tracing::error!("could not write sidecar: {}", e);
+        }
+        let result = match verification.error {
+            None => Ok(()),
+            Some(e) => Err(e),
+        };
+        let _ = event_tx.send(RecordEvent::Verified { path, result });
+    });
+}
+
+fn verify(path: &Path) -> Verification {
+    let mut verification = Verification::default();
+    match checksum(path) {
+        Ok(hash) => verification.xxh3 = format!("{:032x}", hash),
+        Err(e) => verification.error = Some(format!("checksum: {}", e)),
+    }
+    if verification.error.is_none()
+        && let Err(e) = check_structure(path)
+    {
+        verification.error = Some(e);
+    }
+    verification.verified_at = Local::now();
+    verification
+}
+
+/// 整个文件的 xxh3-128.
+fn checksum(path: &Path) -> std::io::Result<u128> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut hash = Xxh3::new();
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hash.update(&buf[..n]);
+    }
+    drop_page_cache(&file);
+    Ok(hash.digest128())
+}
+
+/// 用 Discoverer 打开文件, 确认时长大于 0 且音视频流都在.
+fn check_structure(path: &Path) -> Result<(), String> {
+    let uri = gst::glib::filename_to_uri(path, None).map_err(|e| e.to_string())?;
+    let discoverer =
+        gst_pbutils::Discoverer::new(gst::ClockTime::from_seconds(DISCOVER_TIMEOUT_SECS))
+            .map_err(|e| e.to_string())?;
+    let info = discoverer.discover_uri(&uri).map_err(|e| e.to_string())?;
+
+    if info.duration().is_none_or(|d| d.nseconds() == 0) {
+        return Err("duration is zero".to_string());
+    }
+    if info.video_streams().is_empty() {
+        return Err("no video stream".to_string());
+    }
+    if info.audio_streams().is_empty() {
+        return Err("no audio stream".to_string());
+    }
+    Ok(())
+}
+
+/// 把当前线程降为最低 CPU 优先级和 idle IO 调度, 避免影响接下来的录制.
+#[cfg(target_os = "linux")]
+pub(super) fn lower_priority() {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+    // SAFETY: who = 0 表示调用线程本身, 失败时只是保持原优先级
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, 19);
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn lower_priority() {}
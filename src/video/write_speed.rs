@@ -0,0 +1,236 @@
+//! 写入速度监测: 网络共享和老化的存储卡写不动时, filesink 阻塞, 写入队列堆积,
+//! 要等队列满了才开始丢帧, 这时已经晚了. 在写入队列两端用 probe 统计字节数,
+//! 比较封装器产出的速度和真正交给 filesink 的速度.
+//!
+//! 存储跟得上时两者相等, 队列几乎是空的. 只有队列里积压了数据, 写入速度又明显低于
+//! 产出速度, 才说明存储的速度不够.
+
+use chrono::{DateTime, Local};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 计算速度用的时间窗口, 封装器成块输出, 太短会来回跳.
+const WINDOW: Duration = Duration::from_secs(5);
+/// 写入速度低于产出速度的这个比例才算跟不上.
+const TOLERANCE: f64 = 0.95;
+/// 队列里至少积压了这么多数据才算跟不上.
+const MIN_BACKLOG_BYTES: u64 = 1024 * 1024;
+/// 持续跟不上这么久才提示.
+const UNDERRUN_AFTER: Duration = Duration::from_secs(5);
+/// 持续跟不上这么久, 建议降低码率或者换存储.
+const PERSISTENT_AFTER: Duration = Duration::from_secs(30);
+
+/// 写入队列两端的字节数, 由 pad probe 在流线程中更新.
+pub(super) struct WriteCounters {
+    /// 封装器输出, 进入写入队列.
+    produced: AtomicU64,
+    /// 离开写入队列, 交给 filesink.
+    written: AtomicU64,
+}
+
+impl WriteCounters {
+    pub fn attach(queue: &gst::Element) -> Result<Arc<Self>, gst::glib::BoolError> {
+        let counters = Arc::new(Self {
+            produced: AtomicU64::new(0),
+            written: AtomicU64::new(0),
+        });
+        counters.count(queue.static_pad("sink"), |c| &c.produced)?;
+        counters.count(queue.static_pad("src"), |c| &c.written)?;
+        Ok(counters)
+    }
+
+    fn count(
+        self: &Arc<Self>,
+        pad: Option<gst::Pad>,
+        counter: fn(&Self) -> &AtomicU64,
+    ) -> Result<(), gst::glib::BoolError> {
+        let pad = pad.ok_or_else(|| gst::glib::bool_error!("write queue is missing a pad"))?;
+        let counters = self.clone();
+        pad.add_probe(
+            gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+            move |_, info| {
+                let bytes = match &info.data {
+                    Some(gst::PadProbeData::Buffer(buffer)) => buffer.size(),
+                    Some(gst::PadProbeData::BufferList(list)) => list.calculate_size(),
+                    _ => 0,
+                };
+                counter(&counters).fetch_add(bytes as u64, Ordering::Relaxed);
+                gst::PadProbeReturn::Ok
+            },
+        );
+        Ok(())
+    }
+
+    /// 产出和写入的总字节数.
+    pub fn totals(&self) -> (u64, u64) {
+        (
+            self.produced.load(Ordering::Relaxed),
+            self.written.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// 最近 [WINDOW] 内的速度, 随录制进度发给界面.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WriteRate {
+    pub produced_per_sec: u64,
+    pub written_per_sec: u64,
+    /// 写入队列中还没写出去的字节数.
+    pub backlog_bytes: u64,
+}
+
+impl WriteRate {
+    /// 写入速度比产出速度快 (正) 或慢 (负) 的比例, 还没有数据时为 None.
+    pub fn margin(&self) -> Option<f64> {
+        (self.produced_per_sec > 0)
+            .then(|| self.written_per_sec as f64 / self.produced_per_sec as f64 - 1.0)
+    }
+
+    fn is_behind(&self) -> bool {
+        self.backlog_bytes >= MIN_BACKLOG_BYTES
+            && (self.written_per_sec as f64) < self.produced_per_sec as f64 * TOLERANCE
+    }
+}
+
+/// [WriteMonitor::update] 报告的变化.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteChange {
+    /// 已经跟不上 [UNDERRUN_AFTER], 带上开始跟不上的时间.
+    Underrun { since: Instant },
+    /// 跟不上超过 [PERSISTENT_AFTER], 只报告一次.
+    Persistent,
+    /// 又跟上了.
+    Recovered,
+}
+
+/// 写入 sidecar 的一段写入跟不上的时间.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WriteUnderrun {
+    /// 开始跟不上的时刻.
+    pub at: DateTime<Local>,
+    /// 从录像开始算起的秒数.
+    pub start_secs: f64,
+    /// 恢复时的秒数, 停止录制时仍然跟不上为 None.
+    pub end_secs: Option<f64>,
+    /// 这段时间里最低的写入速度和当时的产出速度.
+    pub written_bytes_per_sec: u64,
+    pub produced_bytes_per_sec: u64,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct WriteMonitor {
+    /// 窗口内每次采样的时间和两端的总字节数.
+    samples: VecDeque<(Instant, u64, u64)>,
+    /// 连续跟不上的开始时间.
+    since: Option<Instant>,
+    underrun: bool,
+    advised: bool,
+}
+
+impl WriteMonitor {
+    /// 每次报告进度时调用.
+    pub fn update(
+        &mut self,
+        now: Instant,
+        (produced, written): (u64, u64),
+        backlog_bytes: u64,
+    ) -> (WriteRate, Option<WriteChange>) {
+        self.samples.push_back((now, produced, written));
+        while self
+            .samples
+            .front()
+            .is_some_and(|&(at, ..)| now.saturating_duration_since(at) > WINDOW)
+        {
+            self.samples.pop_front();
+        }
+        let (at, first_produced, first_written) = self.samples[0];
+        let secs = now.saturating_duration_since(at).as_secs_f64();
+        let per_sec = |bytes: u64| {
+            if secs > 0.0 {
+                (bytes as f64 / secs) as u64
+            } else {
+                0
+            }
+        };
+        let rate = WriteRate {
+            produced_per_sec: per_sec(produced.saturating_sub(first_produced)),
+            written_per_sec: per_sec(written.saturating_sub(first_written)),
+            backlog_bytes,
+        };
+
+        if !rate.is_behind() {
+            self.since = None;
+            self.advised = false;
+            return (
+                rate,
+                std::mem::take(&mut self.underrun).then_some(WriteChange::Recovered),
+            );
+        }
+        let since = *self.since.get_or_insert(now);
+        let behind = now.saturating_duration_since(since);
+        let change = if !self.underrun && behind >= UNDERRUN_AFTER {
+            self.underrun = true;
+            Some(WriteChange::Underrun { since })
+        } else if self.underrun && !self.advised && behind >= PERSISTENT_AFTER {
+            self.advised = true;
+            Some(WriteChange::Persistent)
+        } else {
+            None
+        };
+        (rate, change)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1_000_000;
+
+    #[test]
+    fn fast_storage_with_bursty_muxer_is_fine() {
+        let start = Instant::now();
+        let mut monitor = WriteMonitor::default();
+        for s in 0..60u64 {
+            let at = start + Duration::from_secs(s);
+            // 整块输出, 队列里偶尔剩一点
+            let (rate, change) = monitor.update(at, (s * 3 * MB, s * 3 * MB), (s % 3) * MB / 4);
+            assert_eq!(change, None);
+            if s > 0 {
+                assert_eq!(rate.margin(), Some(0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn reports_underrun_advice_and_recovery() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut monitor = WriteMonitor::default();
+        let mut changes = Vec::new();
+        // 产出 3 MB/s, 只写得出 2 MB/s, 队列每秒多积压 1 MB
+        for s in 0..=40u64 {
+            let (rate, change) = monitor.update(at(s), (s * 3 * MB, s * 2 * MB), s * MB);
+            if s >= 5 {
+                assert!(rate.margin().unwrap() < -0.3);
+            }
+            changes.extend(change.map(|c| (s, c)));
+        }
+        // 第二秒积压才超过 1 MiB, 之后再等 5 秒
+        assert_eq!(
+            changes,
+            vec![
+                (7, WriteChange::Underrun { since: at(2) }),
+                (32, WriteChange::Persistent),
+            ]
+        );
+        // 存储恢复后写入速度超过产出, 开始消化积压
+        let (_, change) = monitor.update(at(41), (41 * 3 * MB, 95 * MB), 28 * MB);
+        assert_eq!(change, Some(WriteChange::Recovered));
+    }
+}
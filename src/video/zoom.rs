@@ -0,0 +1,190 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+pub(crate) const MIN_ZOOM: f32 = 1.0;
+pub(crate) const MAX_ZOOM: f32 = 4.0;
+
+/// videocrop 四边裁掉的像素.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Crop {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// 按变焦倍数居中裁剪. 可见区域取宽高比最简整数比的整数倍, 所以宽高比与原画面完全一致.
+pub(crate) fn crop_for_zoom(width: u32, height: u32, zoom: f32) -> Crop {
+    let units = gcd(width, height);
+    let (unit_w, unit_h) = (width / units.max(1), height / units.max(1));
+    let k = (units as f32 / zoom.clamp(MIN_ZOOM, MAX_ZOOM)).round() as u32;
+    if units < 2 || k >= units {
+        return Crop::default();
+    }
+    // 取偶数倍, 4:2:0 的色度平面也能整齐裁剪
+    let k = (k / 2 * 2).max(2);
+    let (dx, dy) = (width - unit_w * k, height - unit_h * k);
+    Crop {
+        left: dx / 2,
+        right: dx - dx / 2,
+        top: dy / 2,
+        bottom: dy - dy / 2,
+    }
+}
+
+struct State {
+    zoom: f32,
+    /// 采集的分辨率, 协商完成前为 None.
+    source: Option<(u32, u32)>,
+}
+
+/// tee 之前的 `videocrop ! videoscale ! capsfilter`, 预览和录制都看到裁剪后的画面.
+/// capsfilter 固定为采集的分辨率, 变焦时只有 videocrop 和 videoscale 之间重新协商,
+/// 编码器的输入不变.
+pub(crate) struct DigitalZoom {
+    crop: gst::Element,
+    state: Arc<Mutex<State>>,
+}
+
+impl DigitalZoom {
+    pub fn attach(crop: gst::Element, caps_filter: gst::Element) -> Result<Self, &'static str> {
+        let pad = crop.static_pad("sink").ok_or("zoom crop has no sink pad")?;
+        let state = Arc::new(Mutex::new(State {
+            zoom: MIN_ZOOM,
+            source: None,
+        }));
+        let shared = state.clone();
+        let element = crop.clone();
+        pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+            let Some(gst::PadProbeData::Event(event)) = &info.data else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let gst::EventView::Caps(caps) = event.view() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Ok(video) = gst_video::VideoInfo::from_caps(caps.caps()) else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let (width, height) = (video.width(), video.height());
+            let par = video.par();
+            let fixed = gst::Caps::builder("video/x-raw")
+                .field("width", width as i32)
+                .field("height", height as i32)
+                .field("pixel-aspect-ratio", par)
+                .build();
+            if caps_filter.property::<gst::Caps>("caps") != fixed {
+                tracing::debug!(width, height, "pinning zoom output resolution");
+                caps_filter.set_property("caps", fixed);
+            }
+            let zoom = {
+                let mut state = shared.lock();
+                state.source = Some((width, height));
+                state.zoom
+            };
+            apply(&element, crop_for_zoom(width, height, zoom));
+            gst::PadProbeReturn::Ok
+        });
+        Ok(Self { crop, state })
+    }
+
+    /// 协商完成前只记下倍数, 拿到分辨率后再裁剪.
+    pub fn set(&self, zoom: f32) {
+        let source = {
+            let mut state = self.state.lock();
+            state.zoom = zoom;
+            state.source
+        };
+        if let Some((width, height)) = source {
+            apply(&self.crop, crop_for_zoom(width, height, zoom));
+        }
+    }
+}
+
+fn apply(crop: &gst::Element, c: Crop) {
+    crop.set_property("left", c.left as i32);
+    crop.set_property("right", c.right as i32);
+    crop.set_property("top", c.top as i32);
+    crop.set_property("bottom", c.bottom as i32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visible(width: u32, height: u32, crop: Crop) -> (u32, u32) {
+        (
+            width - crop.left - crop.right,
+            height - crop.top - crop.bottom,
+        )
+    }
+
+    #[test]
+    fn no_crop_at_one_times() {
+        assert_eq!(crop_for_zoom(1920, 1080, 1.0), Crop::default());
+        assert_eq!(crop_for_zoom(1920, 1080, 0.5), Crop::default());
+    }
+
+    #[test]
+    fn crops_symmetrically() {
+        let crop = crop_for_zoom(1920, 1080, 2.0);
+        assert_eq!(
+            crop,
+            Crop {
+                left: 480,
+                right: 480,
+                top: 270,
+                bottom: 270,
+            }
+        );
+        assert_eq!(
+            visible(1920, 1080, crop_for_zoom(1920, 1080, 4.0)),
+            (480, 270)
+        );
+        // 超过上限时按上限
+        assert_eq!(
+            crop_for_zoom(1920, 1080, 8.0),
+            crop_for_zoom(1920, 1080, 4.0)
+        );
+    }
+
+    #[test]
+    fn keeps_the_aspect_ratio_exactly() {
+        for (width, height) in [
+            (1920, 1080),
+            (1280, 720),
+            (640, 480),
+            (3840, 2160),
+            (1280, 1024),
+        ] {
+            for step in 0..=30 {
+                let zoom = 1.0 + step as f32 * 0.1;
+                let (w, h) = visible(width, height, crop_for_zoom(width, height, zoom));
+                assert_eq!(
+                    w as u64 * height as u64,
+                    h as u64 * width as u64,
+                    "{}x{} at {}",
+                    width,
+                    height,
+                    zoom
+                );
+                // 实际倍数与请求的接近
+                let actual = width as f32 / w as f32;
+                assert!(
+                    (actual - zoom).abs() / zoom < 0.1,
+                    "{}x{} at {}: {}",
+                    width,
+                    height,
+                    zoom,
+                    actual
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use hmac::{Hmac, KeyInit, Mac};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::time::{Duration, SystemTime};
+use tracing::{error, warn};
+
+use crate::video::record::RecordEvent;
+
+/// 待发送队列的容量. 网络长时间不可用时丢弃新事件, 而不是阻塞调用方.
+const QUEUE_CAPACITY: usize = 32;
+const MAX_ATTEMPTS: u32 = 4;
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 签名头, 值为 `sha256=<hex>`, 对请求体做 HMAC-SHA256.
+const SIGNATURE_HEADER: &str = "X-CamUI-Signature";
+const EVENT_HEADER: &str = "X-CamUI-Event";
+
+#[derive(Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct WebhookSettings {
+    pub enabled: bool,
+    pub url: String,
+    /// 共享密钥, 为空时不签名.
+    pub secret: String,
+}
+
+/// 手动实现, 保证密钥不会出现在日志里.
+impl std::fmt::Debug for WebhookSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookSettings")
+            .field("enabled", &self.enabled)
+            .field("url", &self.url)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+/// 发送给集成方的 JSON 内容.
+#[derive(Debug, Clone, Serialize)]
+struct Payload {
+    event: &'static str,
+    /// RFC 3339 格式.
+    timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dropped_frames: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Payload {
+    fn new(event: &'static str) -> Self {
+        Self {
+            event,
+            timestamp: chrono::Local::now().to_rfc3339(),
+            path: None,
+            duration_secs: None,
+            size_bytes: None,
+            dropped_frames: None,
+            error: None,
+        }
+    }
+
+    /// 只有录制相关的事件才通知集成方.
+    fn from_event(event: &RecordEvent) -> Option<Self> {
+        let payload = match event {
+            RecordEvent::PreviewStarted
+            | RecordEvent::Progress { .. }
+            | RecordEvent::ScreenShared(_)
+            | RecordEvent::Network(_)
+            | RecordEvent::Snapshot(_)
+            | RecordEvent::Burst(_)
+            | RecordEvent::GraphSaved(_)
+            | RecordEvent::SourceSwitched(_)
+            | RecordEvent::Bars(_)
+            | RecordEvent::Silence(_)
+            | RecordEvent::WriteSpeed { .. }
+            | RecordEvent::Warning(_) => {
+                return None;
+            }
+            RecordEvent::Started { path, .. } => Self {
+                path: Some(path.display().to_string()),
+                ..Self::new("started")
+            },
+            RecordEvent::Stopped {
+                path,
+                duration,
+                size,
+                frames,
+                ..
+            } => Self {
+                path: Some(path.display().to_string()),
+                duration_secs: Some(duration.as_secs_f64()),
+                size_bytes: Some(*size),
+                dropped_frames: Some(frames.dropped),
+                ..Self::new("stopped")
+            },
+            RecordEvent::Verified { path, result } => Self {
+                path: Some(path.display().to_string()),
+                error: result.clone().err(),
+                ..Self::new("verified")
+            },
+            RecordEvent::Error(e) => Self {
+                error: Some(e.clone()),
+                ..Self::new("error")
+            },
+            RecordEvent::StartBlocked { reason, .. } => Self {
+                error: Some(reason.to_string()),
+                ..Self::new("error")
+            },
+        };
+        Some(payload)
+    }
+
+    /// 设置面板中 "Test" 按钮发送的示例.
+    fn sample() -> Self {
+        Self {
+            path: Some("rec_0000000000.mov".to_string()),
+            duration_secs: Some(12.5),
+            size_bytes: Some(1_048_576),
+            ..Self::new("test")
+        }
+    }
+}
+
+/// 最近一次投递的结果.
+#[derive(Debug, Clone)]
+pub(crate) struct DeliveryStatus {
+    pub event: &'static str,
+    pub at: SystemTime,
+    pub result: Result<u16, String>,
+}
+
+/// 在单独线程中投递 webhook, 不阻塞录制流程.
+pub(crate) struct Webhooks {
+    tx: SyncSender<Payload>,
+    settings: Arc<Mutex<WebhookSettings>>,
+    last_status: Arc<Mutex<Option<DeliveryStatus>>>,
+}
+
+impl Webhooks {
+    pub fn spawn(settings: WebhookSettings) -> Self {
+        let (tx, rx) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let settings = Arc::new(Mutex::new(settings));
+        let last_status = Arc::new(Mutex::new(None));
+
+        let settings_c = settings.clone();
+        let last_status_c = last_status.clone();
+        std::thread::spawn(move || worker(rx, settings_c, last_status_c));
+
+        Self {
+            tx,
+            settings,
+            last_status,
+        }
+    }
+
+    pub fn set_settings(&self, settings: WebhookSettings) {
+        *self.settings.lock() = settings;
+    }
+
+    pub fn notify(&self, event: &RecordEvent) {
+        if self.settings.lock().enabled
+            && let Some(payload) = Payload::from_event(event)
+        {
+            self.enqueue(payload);
+        }
+    }
+
+    /// 发送示例内容, 不论是否启用.
+    pub fn send_test(&self) {
+        self.enqueue(Payload::sample());
+    }
+
+    pub fn last_status(&self) -> Option<DeliveryStatus> {
+        self.last_status.lock().clone()
+    }
+
+    fn enqueue(&self, payload: Payload) {
+        match self.tx.try_send(payload) {
+            Ok(()) => {}
+            Err(TrySendError::Full(p)) => {
+                warn!("webhook queue full, dropping '{}' event", p.event);
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+fn worker(
+    rx: Receiver<Payload>,
+    settings: Arc<Mutex<WebhookSettings>>,
+    last_status: Arc<Mutex<Option<DeliveryStatus>>>,
+) {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(REQUEST_TIMEOUT))
+        .build()
+        .into();
+
+    while let Ok(payload) = rx.recv() {
+        let settings = settings.lock().clone();
+        if settings.url.is_empty() {
+            continue;
+        }
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("could not serialize webhook payload: {}", e);
+                continue;
+            }
+        };
+
+        let mut result = Err(String::new());
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt > 0 {
+                std::thread::sleep(BASE_BACKOFF * 2u32.pow(attempt - 1));
+            }
+            result = deliver(&agent, &settings, payload.event, &body);
+            match &result {
+                Ok(_) => break,
+                Err(e) => warn!(
+                    "webhook '{}' attempt {} failed: {}",
+                    payload.event,
+                    attempt + 1,
+                    e
+                ),
+            }
+        }
+        *last_status.lock() = Some(DeliveryStatus {
+            event: payload.event,
+            at: SystemTime::now(),
+            result,
+        });
+    }
+}
+
+fn deliver(
+    agent: &ureq::Agent,
+    settings: &WebhookSettings,
+    event: &str,
+    body: &str,
+) -> Result<u16, String> {
+    let mut request = agent
+        .post(&settings.url)
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, event);
+    if !settings.secret.is_empty() {
+        request = request.header(
+            SIGNATURE_HEADER,
+            format!("sha256={}", sign(&settings.secret, body)),
+        );
+    }
+    request
+        .send(body)
+        .map(|resp| resp.status().as_u16())
+        .map_err(|e| e.to_string())
+}
+
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
@@ -0,0 +1,89 @@
+//! 给正在录制的无界面进程发送 SIGTERM, 检查录像是否完整可播放.
+//! 需要 GStreamer 和 x264 等插件, 默认跳过, 用 `cargo test -- --ignored` 运行.
+#![cfg(unix)]
+
+use gstreamer as gst;
+use gstreamer_pbutils as gst_pbutils;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const RECORD_FOR: Duration = Duration::from_secs(2);
+
+fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cam-ui-sigterm-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_config(dir: &Path) -> PathBuf {
+    let path = dir.join("config.toml");
+    std::fs::write(
+        &path,
+        r#"
+[capture]
+video_source = "videotestsrc is-live=true"
+audio_source = "audiotestsrc is-live=true"
+
+[recording]
+resolution = { width = 640, height = 360 }
+"#,
+    )
+    .unwrap();
+    path
+}
+
+#[test]
+#[ignore = "needs GStreamer plugins"]
+fn sigterm_finalizes_the_active_recording() {
+    let dir = scratch_dir();
+    let config = write_config(&dir);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cam-ui"))
+        .arg("--headless")
+        .arg("--record")
+        .arg("--config")
+        .arg(&config)
+        .arg("--output-dir")
+        .arg(&dir)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // 等到录制真正开始
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let started = lines
+        .by_ref()
+        .map_while(Result::ok)
+        .find(|line| line.contains("event=started") || line.contains("event=error"))
+        .expect("process exited before recording started");
+    assert!(started.contains("event=started"), "{}", started);
+    std::thread::sleep(RECORD_FOR);
+
+    let pid = child.id() as libc::pid_t;
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGTERM) }, 0);
+    let rest: Vec<String> = lines.map_while(Result::ok).collect();
+    let status = child.wait().unwrap();
+    assert!(status.success(), "exit status {:?}", status);
+    assert!(
+        rest.iter().any(|line| line.contains("event=stopped")),
+        "no stopped event in {:?}",
+        rest
+    );
+
+    let clip = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "mov"))
+        .expect("no clip written");
+
+    gst::init().unwrap();
+    let discoverer = gst_pbutils::Discoverer::new(gst::ClockTime::from_seconds(10)).unwrap();
+    let uri = gst::glib::filename_to_uri(&clip, None).unwrap();
+    let info = discoverer.discover_uri(&uri).expect("clip is not playable");
+    let duration = info.duration().expect("clip has no duration");
+    assert!(duration >= gst::ClockTime::from_seconds(1), "{}", duration);
+    assert!(!info.video_streams().is_empty());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}